@@ -1,4 +1,5 @@
 use clap::{Parser, Subcommand, ValueEnum};
+use ferrumc_anvil::writer::CompressionType;
 use tracing::Level;
 
 #[derive(Parser)]
@@ -19,6 +20,18 @@ pub enum Command {
     Setup,
     /// Import the world data
     Import(ImportArgs),
+    /// Export the world data to vanilla region files
+    Export(ExportArgs),
+    /// Rewrite the stored chunks with a different compression
+    Recompress(RecompressArgs),
+    /// Upgrade all stored chunks to the latest chunk format
+    Migrate(MigrateArgs),
+    /// Roll the world back to a backup
+    Restore(RestoreArgs),
+    /// Generate chunks ahead of time
+    Pregen(PregenArgs),
+    /// Delete chunks outside an area or that were never changed, and shrink the database
+    Trim(TrimArgs),
     /// Start the server
     Run,
 }
@@ -38,6 +51,140 @@ pub struct ImportArgs {
     pub max_concurrent_tasks: usize,
 }
 
+#[derive(Debug, Clone, Parser)]
+pub struct ExportArgs {
+    /// Path to the folder to export the world to
+    ///
+    /// Region files are written to `region`, `DIM-1/region` and `DIM1/region` inside this folder,
+    /// the same layout as a singleplayer save. Copy a `level.dat` in next to them to open the world
+    /// in the game.
+    #[clap(long, required = true)]
+    pub export_path: String,
+    /// Compression used for the chunks in the region files
+    #[clap(long)]
+    #[arg(value_enum, default_value_t = ExportCompression::Zlib)]
+    pub compression: ExportCompression,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct RecompressArgs {
+    /// Compression algorithm to store the chunks with, defaults to the one in the config
+    ///
+    /// One of `gzip`, `zstd`, `brotli`, `deflate` or `zlib`.
+    #[clap(long)]
+    pub compression: Option<String>,
+    /// Compression level to use, defaults to the one in the config
+    #[clap(long)]
+    pub compression_level: Option<u32>,
+    /// Number of chunks to process at a time
+    #[clap(long, default_value_t = 1000)]
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct MigrateArgs {
+    /// Number of chunks to process at a time
+    #[clap(long, default_value_t = 1000)]
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct RestoreArgs {
+    /// Path to the backup to restore, defaults to the newest one in the backup folder
+    ///
+    /// The server must be stopped while restoring. The current world is kept next to the restored
+    /// one as `data.mdb.pre-restore` until the next restore.
+    #[clap(long)]
+    pub backup: Option<String>,
+    /// List the available backups instead of restoring one
+    #[clap(long)]
+    pub list: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct PregenArgs {
+    /// Dimension to generate chunks in
+    #[clap(long, default_value = "overworld")]
+    pub dimension: String,
+    /// Generate a square this many chunks out from the center chunk
+    #[clap(long, conflicts_with_all = ["min_x", "min_z", "max_x", "max_z"])]
+    pub radius: Option<u32>,
+    /// X coordinate of the center chunk when using `--radius`
+    #[clap(long, default_value_t = 0, allow_hyphen_values = true)]
+    pub center_x: i32,
+    /// Z coordinate of the center chunk when using `--radius`
+    #[clap(long, default_value_t = 0, allow_hyphen_values = true)]
+    pub center_z: i32,
+    /// Lowest chunk X coordinate of a rectangle to generate instead of a square
+    #[clap(long, requires_all = ["min_z", "max_x", "max_z"], allow_hyphen_values = true)]
+    pub min_x: Option<i32>,
+    /// Lowest chunk Z coordinate of the rectangle
+    #[clap(long, requires_all = ["min_x", "max_x", "max_z"], allow_hyphen_values = true)]
+    pub min_z: Option<i32>,
+    /// Highest chunk X coordinate of the rectangle
+    #[clap(long, requires_all = ["min_x", "min_z", "max_z"], allow_hyphen_values = true)]
+    pub max_x: Option<i32>,
+    /// Highest chunk Z coordinate of the rectangle
+    #[clap(long, requires_all = ["min_x", "min_z", "max_x"], allow_hyphen_values = true)]
+    pub max_z: Option<i32>,
+    /// Number of chunks to generate at a time
+    ///
+    /// Progress is saved after every batch. Stopping with Ctrl-C finishes the current batch, and
+    /// running the same command again carries on from there.
+    #[clap(long, default_value_t = 256)]
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct TrimArgs {
+    /// Only trim this dimension, defaults to every dimension
+    #[clap(long)]
+    pub dimension: Option<String>,
+    /// Keep the chunks within this many chunks of the center chunk
+    #[clap(long, conflicts_with = "polygon")]
+    pub radius: Option<u32>,
+    /// X coordinate of the center chunk when using `--radius`
+    #[clap(long, default_value_t = 0, allow_hyphen_values = true)]
+    pub center_x: i32,
+    /// Z coordinate of the center chunk when using `--radius`
+    #[clap(long, default_value_t = 0, allow_hyphen_values = true)]
+    pub center_z: i32,
+    /// Keep the chunks inside a polygon, given as chunk coordinates like `0,0;32,0;32,32`
+    #[clap(long, value_delimiter = ';', allow_hyphen_values = true)]
+    pub polygon: Vec<String>,
+    /// Only delete chunks nobody has changed since they were generated
+    ///
+    /// Combined with an area, only unchanged chunks outside of it are deleted.
+    #[clap(long)]
+    pub unmodified: bool,
+    /// Report what would be deleted without deleting anything
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Don't shrink the database file afterwards
+    #[clap(long)]
+    pub no_compact: bool,
+    /// Number of chunks to process at a time
+    #[clap(long, default_value_t = 1000)]
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportCompression {
+    Gzip,
+    Zlib,
+    None,
+}
+
+impl From<ExportCompression> for CompressionType {
+    fn from(compression: ExportCompression) -> Self {
+        match compression {
+            ExportCompression::Gzip => CompressionType::Gzip,
+            ExportCompression::Zlib => CompressionType::Zlib,
+            ExportCompression::None => CompressionType::None,
+        }
+    }
+}
+
 // Wrapper struct for the Level enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LogLevel(Level);
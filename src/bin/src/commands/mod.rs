@@ -13,6 +13,7 @@ use std::marker::PhantomData;
 use bevy_ecs::prelude::{Entity, Query, Resource};
 use brigadier_rs::{float_64, literal, BuildExecute, CommandArgument, Execute, Then};
 use ferrumc_core::{
+    chunks::chunk_receiver::ChunkReceiver,
     identity::player_identity::PlayerIdentity,
     inventory::{Inventory, ItemStack},
     transform::position::Position,
@@ -28,12 +29,18 @@ use ferrumc_net::{
 use ferrumc_net_codec::net_types::var_int::VarInt;
 use ferrumc_state::GlobalStateResource;
 use ferrumc_text::TextComponent;
-use ferrumc_world::{block_id::BlockId, vanilla_chunk_format::BlockData};
+use ferrumc_world::{
+    block_id::BlockId,
+    item_id::ItemId,
+    region_edit::{Region, RegionEdit},
+};
 use nom::IResult;
 use std::iter;
 use tracing::warn;
 
+use crate::pregen::{cancel_background, start_background, PregenArea};
 use crate::systems::chat_message;
+use crate::systems::region_edits::{start_region_job, RegionJob};
 
 /// Context provided to command handlers when they are executed.
 #[derive(Copy, Clone)]
@@ -50,6 +57,7 @@ pub struct CommandContext<'a> {
             &'a mut Position,
             &'a mut Inventory,
             &'a PlayerIdentity,
+            &'a ChunkReceiver,
         ),
     >,
     /// Global server state.
@@ -100,7 +108,7 @@ fn send_feedback(ctx: CommandContext, msg: String) {
     unsafe {
         let query = &mut *ctx.query;
         let state = &*ctx.state;
-        if let Ok((_, conn, _, _, _)) = query.get_mut(ctx.sender) {
+        if let Ok((_, conn, _, _, _, _)) = query.get_mut(ctx.sender) {
             let text = TextComponent::from(msg);
             chat_message::broadcast_text(text, iter::once((ctx.sender, conn)), state);
         }
@@ -137,7 +145,7 @@ pub fn say_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
                 let text = TextComponent::from(msg);
                 chat_message::broadcast_text(
                     text,
-                    query.iter_mut().map(|(e, conn, _, _, _)| (e, conn)),
+                    query.iter_mut().map(|(e, conn, _, _, _, _)| (e, conn)),
                     state,
                 );
             }
@@ -154,7 +162,7 @@ pub fn tp_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
                 unsafe {
                     let query = &mut *ctx.query;
                     let state = &*ctx.state;
-                    if let Ok((_, conn, mut pos, _, identity)) = query.get_mut(ctx.sender) {
+                    if let Ok((_, conn, mut pos, _, identity, _)) = query.get_mut(ctx.sender) {
                         if identity.permission_level < 2 {
                             let text = TextComponent::from("You do not have permission to use /tp");
                             chat_message::broadcast_text(
@@ -213,7 +221,7 @@ fn give_handler(ctx: CommandContext, item: String, count: u8) -> Result<(), Infa
     unsafe {
         let query = &mut *ctx.query;
         let state = &*ctx.state;
-        if let Ok((_, conn, _, mut inv, identity)) = query.get_mut(ctx.sender) {
+        if let Ok((_, conn, _, mut inv, identity, _)) = query.get_mut(ctx.sender) {
             if identity.permission_level < 2 {
                 let text = TextComponent::from("You do not have permission to use /give");
                 chat_message::broadcast_text(text, iter::once((ctx.sender, conn)), state);
@@ -224,15 +232,16 @@ fn give_handler(ctx: CommandContext, item: String, count: u8) -> Result<(), Infa
             } else {
                 format!("minecraft:{}", item)
             };
-            let block = BlockId::from_block_data(&BlockData {
-                name: name.clone(),
-                properties: None,
-            });
-            let stack = ItemStack::new(block, count, 64, None);
+            let Some(item_id) = ItemId::from_name(&name) else {
+                let text = TextComponent::from(format!("Unknown item {}", name));
+                chat_message::broadcast_text(text, iter::once((ctx.sender, conn)), state);
+                return Ok(());
+            };
+            let stack = ItemStack::of(item_id, count);
             inv.hotbar[0] = Some(stack.clone());
             let packet = ContainerSetSlotPacket::new(0, 0, 0, Some(&stack));
             let _ = conn.send_packet_ref(&packet);
-            let text = TextComponent::from(format!("Gave {} x{}", name, count));
+            let text = TextComponent::from(format!("Gave {} x{}", name, stack.count));
             chat_message::broadcast_text(text, iter::once((ctx.sender, conn)), state);
         } else {
             warn!("Sender entity {:?} not found for give", ctx.sender);
@@ -248,7 +257,7 @@ pub fn gamemode_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
             unsafe {
                 let query = &mut *ctx.query;
                 let state = &*ctx.state;
-                if let Ok((entity, conn, _pos, _inv, identity)) = query.get_mut(ctx.sender) {
+                if let Ok((entity, conn, _pos, _inv, identity, _)) = query.get_mut(ctx.sender) {
                     if identity.permission_level < 2 {
                         let text =
                             TextComponent::from("You do not have permission to use /gamemode");
@@ -273,7 +282,7 @@ pub fn gamemode_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
                         PlayerWithActions::update_game_mode(uuid, gm),
                     ]);
 
-                    for (e, conn, _, _, _) in query.iter_mut() {
+                    for (e, conn, _, _, _, _) in query.iter_mut() {
                         if !state.0.players.is_connected(e) {
                             continue;
                         }
@@ -289,12 +298,310 @@ pub fn gamemode_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
         .build_exec(|_ctx| Ok::<(), Infallible>(()))
 }
 
+/// `/gamerule <rule> [value]` command.
+pub fn gamerule_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
+    literal("gamerule")
+        .then(rest().build_exec(|ctx: CommandContext, args: String| gamerule_handler(ctx, args)))
+        .build_exec(|_ctx| Ok::<(), Infallible>(()))
+}
+
+fn gamerule_handler(ctx: CommandContext, args: String) -> Result<(), Infallible> {
+    let state = unsafe {
+        let query = &mut *ctx.query;
+        let state = &*ctx.state;
+        match query.get_mut(ctx.sender) {
+            Ok((_, conn, _, _, identity, _)) => {
+                if identity.permission_level < 2 {
+                    let text = TextComponent::from("You do not have permission to use /gamerule");
+                    chat_message::broadcast_text(text, iter::once((ctx.sender, conn)), state);
+                    return Ok(());
+                }
+            }
+            Err(_) => {
+                warn!("Sender entity {:?} not found for gamerule", ctx.sender);
+                return Ok(());
+            }
+        }
+        state.0.clone()
+    };
+
+    let msg = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [rule] => match state.world.game_rules().get(rule) {
+            Some(value) => format!("Gamerule {rule} is currently set to: {value}"),
+            None => format!("Unknown game rule {rule}"),
+        },
+        [rule, value] => match value.parse() {
+            Ok(value) => match state.world.set_game_rule(rule, value) {
+                Ok(()) => format!("Gamerule {rule} is now set to: {value}"),
+                Err(e) => format!("Could not set the game rule: {e}"),
+            },
+            Err(_) => format!("Invalid value {value}, expected a whole number"),
+        },
+        _ => "Usage: /gamerule <rule> [value]".to_string(),
+    };
+    send_feedback(ctx, msg);
+    Ok(())
+}
+
+/// How many chunks `/pregen` generates at a time.
+const PREGEN_BATCH_SIZE: usize = 256;
+
+/// `/pregen <dimension> <radius> [center_x center_z]` and `/pregen cancel` commands.
+///
+/// Generates the chunks in the background, progress is logged to the console.
+pub fn pregen_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
+    literal("pregen")
+        .then(rest().build_exec(|ctx: CommandContext, args: String| pregen_handler(ctx, args)))
+        .build_exec(|_ctx| Ok::<(), Infallible>(()))
+}
+
+fn pregen_handler(ctx: CommandContext, args: String) -> Result<(), Infallible> {
+    let state = unsafe {
+        let query = &mut *ctx.query;
+        let state = &*ctx.state;
+        match query.get_mut(ctx.sender) {
+            Ok((_, conn, _, _, identity, _)) => {
+                if identity.permission_level < 4 {
+                    let text = TextComponent::from("You do not have permission to use /pregen");
+                    chat_message::broadcast_text(text, iter::once((ctx.sender, conn)), state);
+                    return Ok(());
+                }
+            }
+            Err(_) => {
+                warn!("Sender entity {:?} not found for pregen", ctx.sender);
+                return Ok(());
+            }
+        }
+        state.0.clone()
+    };
+
+    let parts = args.split_whitespace().collect::<Vec<_>>();
+    if parts.as_slice() == ["cancel"] {
+        let msg = if cancel_background() {
+            "Stopping pre-generation after the current batch"
+        } else {
+            "No pre-generation is running"
+        };
+        send_feedback(ctx, msg.to_string());
+        return Ok(());
+    }
+
+    let area = match parts.as_slice() {
+        [dimension, radius] => radius
+            .parse()
+            .ok()
+            .and_then(|radius| PregenArea::around(dimension, 0, 0, radius).ok()),
+        [dimension, radius, x, z] => match (radius.parse(), x.parse(), z.parse()) {
+            (Ok(radius), Ok(x), Ok(z)) => PregenArea::around(dimension, x, z, radius).ok(),
+            _ => None,
+        },
+        _ => None,
+    };
+    let Some(area) = area else {
+        send_feedback(
+            ctx,
+            "Usage: /pregen <dimension> <radius> [center_x center_z] or /pregen cancel".to_string(),
+        );
+        return Ok(());
+    };
+    if state.world.dimensions().get(&area.dimension).is_none() {
+        send_feedback(ctx, format!("Unknown dimension {}", area.dimension));
+        return Ok(());
+    }
+
+    let count = match area.chunk_count() {
+        Ok(count) => count,
+        Err(e) => {
+            send_feedback(ctx, e.to_string());
+            return Ok(());
+        }
+    };
+    let msg = match start_background(state, area, PREGEN_BATCH_SIZE) {
+        Ok(true) => format!("Pre-generating {count} chunks, progress is logged to the console"),
+        Ok(false) => "A pre-generation is already running, /pregen cancel stops it".to_string(),
+        Err(e) => format!("Could not start pre-generation: {e}"),
+    };
+    send_feedback(ctx, msg);
+    Ok(())
+}
+
+/// The most blocks `/fill` and `/clone` change at once. Edits run off the tick, so this is well
+/// above vanilla's limit, it's only there to stop a typo from rewriting half the world.
+const MAX_REGION_VOLUME: u64 = 1 << 20;
+
+/// `/fill <x1> <y1> <z1> <x2> <y2> <z2> <block>` command.
+pub fn fill_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
+    literal("fill")
+        .then(rest().build_exec(|ctx: CommandContext, args: String| {
+            let parts = args.split_whitespace().collect::<Vec<_>>();
+            let parsed = match parts.as_slice() {
+                [x1, y1, z1, x2, y2, z2, block] => {
+                    let name = if block.contains(':') {
+                        block.to_string()
+                    } else {
+                        format!("minecraft:{block}")
+                    };
+                    parse_position(ctx, [x1, y1, z1])
+                        .zip(parse_position(ctx, [x2, y2, z2]))
+                        .zip(BlockId::from_name(&name))
+                }
+                _ => None,
+            };
+            let Some(((from, to), block)) = parsed else {
+                send_feedback(
+                    ctx,
+                    "Usage: /fill <x1> <y1> <z1> <x2> <y2> <z2> <block>".to_string(),
+                );
+                return Ok::<(), Infallible>(());
+            };
+            let region = Region::cuboid(from, to);
+            if !check_volume(ctx, &region) {
+                return Ok(());
+            }
+            let job = RegionJob::Edit {
+                edit: RegionEdit::fill(&region, block),
+                verb: "Filled",
+            };
+            region_edit_handler(ctx, "fill", job)
+        }))
+        .build_exec(|_ctx| Ok::<(), Infallible>(()))
+}
+
+/// `/clone <x1> <y1> <z1> <x2> <y2> <z2> <x> <y> <z>` command, copying the blocks between the
+/// corners so their lowest corner ends up at `x`, `y`, `z`.
+pub fn clone_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
+    literal("clone")
+        .then(rest().build_exec(|ctx: CommandContext, args: String| {
+            let parts = args.split_whitespace().collect::<Vec<_>>();
+            let parsed = match parts.as_slice() {
+                [x1, y1, z1, x2, y2, z2, x, y, z] => parse_position(ctx, [x1, y1, z1])
+                    .zip(parse_position(ctx, [x2, y2, z2]))
+                    .zip(parse_position(ctx, [x, y, z])),
+                _ => None,
+            };
+            let Some(((from, to), destination)) = parsed else {
+                send_feedback(
+                    ctx,
+                    "Usage: /clone <x1> <y1> <z1> <x2> <y2> <z2> <x> <y> <z>".to_string(),
+                );
+                return Ok::<(), Infallible>(());
+            };
+            let region = Region::cuboid(from, to);
+            if !check_volume(ctx, &region) {
+                return Ok(());
+            }
+            let min = (from.0.min(to.0), from.1.min(to.1), from.2.min(to.2));
+            let job = RegionJob::Clone {
+                region,
+                offset: (
+                    destination.0 - min.0,
+                    destination.1 - min.1,
+                    destination.2 - min.2,
+                ),
+            };
+            region_edit_handler(ctx, "clone", job)
+        }))
+        .build_exec(|_ctx| Ok::<(), Infallible>(()))
+}
+
+/// `//undo` command, undoing the last `/fill` or `/clone` the sender made.
+pub fn undo_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
+    // The chat handler strips every leading slash, so `//undo` arrives as `undo`
+    literal("undo")
+        .build_exec(|ctx: CommandContext| region_edit_handler(ctx, "undo", RegionJob::Undo))
+}
+
+/// `//redo` command, making the last edit the sender undid again.
+pub fn redo_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
+    literal("redo")
+        .build_exec(|ctx: CommandContext| region_edit_handler(ctx, "redo", RegionJob::Redo))
+}
+
+/// Parse a block position, where `~` is relative to the sender.
+fn parse_position(ctx: CommandContext, coords: [&&str; 3]) -> Option<(i32, i32, i32)> {
+    let sender = unsafe {
+        let query = &mut *ctx.query;
+        query
+            .get_mut(ctx.sender)
+            .ok()
+            .map(|(_, _, pos, _, _, _)| [pos.x, pos.y, pos.z])
+    };
+    let mut position = [0; 3];
+    for (i, coord) in coords.iter().enumerate() {
+        position[i] = match coord.strip_prefix('~') {
+            Some(offset) => {
+                let base = sender?[i].floor() as i32;
+                if offset.is_empty() {
+                    base
+                } else {
+                    base.checked_add(offset.parse().ok()?)?
+                }
+            }
+            None => coord.parse().ok()?,
+        };
+    }
+    Some((position[0], position[1], position[2]))
+}
+
+fn check_volume(ctx: CommandContext, region: &Region) -> bool {
+    match region.volume() {
+        Some(volume) if volume <= MAX_REGION_VOLUME => true,
+        Some(volume) => {
+            send_feedback(
+                ctx,
+                format!("Too many blocks, {volume} is more than the limit of {MAX_REGION_VOLUME}"),
+            );
+            false
+        }
+        None => {
+            send_feedback(
+                ctx,
+                format!("Too many blocks, the limit is {MAX_REGION_VOLUME}"),
+            );
+            false
+        }
+    }
+}
+
+/// Check the sender is allowed to edit regions and start the job in the background, in the
+/// dimension the sender is in.
+fn region_edit_handler(
+    ctx: CommandContext,
+    command: &str,
+    job: RegionJob,
+) -> Result<(), Infallible> {
+    let (state, actor, dimension) = unsafe {
+        let query = &mut *ctx.query;
+        let state = &*ctx.state;
+        match query.get_mut(ctx.sender) {
+            Ok((_, conn, _, _, identity, receiver)) => {
+                if identity.permission_level < 2 {
+                    let text = TextComponent::from(format!(
+                        "You do not have permission to use /{command}"
+                    ));
+                    chat_message::broadcast_text(text, iter::once((ctx.sender, conn)), state);
+                    return Ok(());
+                }
+                (state.0.clone(), identity.uuid, receiver.last_chunk.2.clone())
+            }
+            Err(_) => {
+                warn!("Sender entity {:?} not found for {}", ctx.sender, command);
+                return Ok(());
+            }
+        }
+    };
+    if let Err(e) = start_region_job(state, ctx.sender, actor, dimension, job) {
+        send_feedback(ctx, format!("Could not start the edit: {e}"));
+    }
+    Ok(())
+}
+
 /// `/help` command listing available commands.
 pub fn help_command() -> impl for<'a> Execute<CommandContext<'a>, ()> {
     literal("help").build_exec(|ctx: CommandContext| {
         send_feedback(
             ctx,
-            "Available commands: say, tp, give, gamemode, help".to_string(),
+            "Available commands: say, tp, give, gamemode, gamerule, pregen, fill, clone, //undo, //redo, help".to_string(),
         );
         Ok::<(), Infallible>(())
     })
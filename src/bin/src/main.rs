@@ -7,20 +7,30 @@ use ferrumc_config::whitelist::create_whitelist;
 use ferrumc_general_purpose::paths::get_root_path;
 use ferrumc_state::player_list::PlayerList;
 use ferrumc_state::{GlobalState, ServerState};
+use ferrumc_storage::compressors::{Compressor, CompressorType};
 use ferrumc_threadpool::ThreadPool;
+use ferrumc_utils::formatting::format_bytes;
+use ferrumc_world::backups::{list_backups, restore};
+use ferrumc_world::trimming::{TrimArea, TrimOptions};
 use ferrumc_world::World;
-use ferrumc_world_gen::WorldGenerator;
+use ferrumc_world_gen::Generators;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tracing::{error, info};
 
 pub(crate) mod errors;
-use crate::cli::{CLIArgs, Command, ImportArgs};
+use crate::cli::{
+    CLIArgs, Command, ExportArgs, ImportArgs, MigrateArgs, PregenArgs, RecompressArgs, RestoreArgs,
+    TrimArgs,
+};
+use crate::pregen::{pregen, PregenArea};
 mod chunk_sending;
 mod cli;
 mod commands;
 mod game_loop;
 mod packet_handlers;
+mod pregen;
 mod register_events;
 mod register_resources;
 mod systems;
@@ -62,6 +72,60 @@ fn main() {
                 info!("Import completed successfully.");
             }
         }
+        Some(Command::Export(export_args)) => {
+            info!("Starting export...");
+            if let Err(e) = handle_export(export_args) {
+                error!("Export failed with the following error: {}", e.to_string());
+            } else {
+                info!("Export completed successfully.");
+            }
+        }
+        Some(Command::Recompress(recompress_args)) => {
+            info!("Starting recompression...");
+            if let Err(e) = handle_recompress(recompress_args) {
+                error!(
+                    "Recompression failed with the following error: {}",
+                    e.to_string()
+                );
+            } else {
+                info!("Recompression completed successfully.");
+            }
+        }
+        Some(Command::Migrate(migrate_args)) => {
+            info!("Starting migration...");
+            if let Err(e) = handle_migrate(migrate_args) {
+                error!(
+                    "Migration failed with the following error: {}",
+                    e.to_string()
+                );
+            } else {
+                info!("Migration completed successfully.");
+            }
+        }
+        Some(Command::Pregen(pregen_args)) => {
+            info!("Starting pre-generation...");
+            if let Err(e) = handle_pregen(pregen_args) {
+                error!(
+                    "Pre-generation failed with the following error: {}",
+                    e.to_string()
+                );
+            } else {
+                info!("Pre-generation completed successfully.");
+            }
+        }
+        Some(Command::Trim(trim_args)) => {
+            info!("Starting trim...");
+            if let Err(e) = handle_trim(trim_args) {
+                error!("Trim failed with the following error: {}", e.to_string());
+            } else {
+                info!("Trim completed successfully.");
+            }
+        }
+        Some(Command::Restore(restore_args)) => {
+            if let Err(e) = handle_restore(restore_args) {
+                error!("Restore failed with the following error: {}", e.to_string());
+            }
+        }
         Some(Command::Run) | None => {
             info!("Starting server...");
             if let Err(e) = ferrumc_config::setup::setup() {
@@ -88,35 +152,17 @@ fn main() {
 
 fn generate_chunks(state: GlobalState) -> Result<(), BinaryError> {
     info!("No overworld spawn chunk found, generating spawn chunks...");
-    // Generate a 12x12 chunk area around the spawn point
-    let mut chunks = Vec::new();
+    // Generate the render distance around the spawn point
     let start = Instant::now();
-    let radius = get_global_config().chunk_render_distance as i32;
-    for x in -radius..=radius {
-        for z in -radius..=radius {
-            chunks.push((x, z));
-        }
-    }
-    let mut batch = state.thread_pool.batch();
-    for (x, z) in chunks {
-        let state_clone = state.clone();
-        batch.execute(move || {
-            let biome = state_clone.terrain_generator.biome_at(x, z);
-            let chunk = state_clone
-                .terrain_generator
-                .generate_chunk_for_biome(x, z, biome)
-                .map(Arc::new);
-            if let Err(e) = chunk {
-                error!("Error generating chunk ({}, {}): {:?}", x, z, e);
-            } else {
-                let chunk = chunk.unwrap();
-                if let Err(e) = state_clone.world.save_chunk(chunk) {
-                    error!("Error saving chunk ({}, {}): {:?}", x, z, e);
-                }
-            }
-        });
-    }
-    batch.wait();
+    let radius = get_global_config().chunk_render_distance;
+    let area = PregenArea::around("overworld", 0, 0, radius)?;
+    pregen(
+        &state.world,
+        &state.thread_pool,
+        &area,
+        usize::try_from(area.chunk_count()?).unwrap_or(usize::MAX),
+        &AtomicBool::new(false),
+    )?;
     info!("Finished generating spawn chunks in {:?}", start.elapsed());
     Ok(())
 }
@@ -133,20 +179,21 @@ fn entry(start_time: Instant) -> Result<(), BinaryError> {
         let global_state = global_state.clone();
         move || {
             info!("Shutting down server...");
+            // The game loop stops after its current tick, the world is saved once it has
             global_state
                 .shut_down
                 .store(true, std::sync::atomic::Ordering::Relaxed);
-            global_state
-                .world
-                .sync()
-                .expect("Failed to sync world before shutdown")
         }
     })
     .expect("Error setting Ctrl-C handler");
 
-    game_loop::start_game_loop(global_state.clone())?;
+    let result = game_loop::start_game_loop(global_state.clone());
 
-    Ok(())
+    // Nothing ticks the world any more, so the ticks waiting in it can be saved with their chunks
+    global_state.world.save_pending_ticks()?;
+    global_state.world.sync()?;
+
+    result
 }
 
 fn handle_import(import_args: ImportArgs) -> Result<(), BinaryError> {
@@ -154,7 +201,7 @@ fn handle_import(import_args: ImportArgs) -> Result<(), BinaryError> {
     info!("Importing world...");
 
     // let config = get_global_config();
-    let mut world = World::new(&get_global_config().database.db_path);
+    let mut world = World::new(&get_global_config().database.db_path)?;
 
     let root_path = get_root_path();
     let mut import_path = root_path.join(import_args.import_path);
@@ -162,22 +209,265 @@ fn handle_import(import_args: ImportArgs) -> Result<(), BinaryError> {
         import_path = root_path.join(import_path);
     }
 
-    if let Err(e) = world.import(
+    let summary = match world.import(
         import_path,
         import_args.batch_size,
         // import_args.max_concurrent_tasks,
     ) {
-        error!("Could not import world: {}", e.to_string());
-        return Err(BinaryError::Custom("Could not import world.".to_string()));
+        Ok(summary) => summary,
+        Err(e) => {
+            error!("Could not import world: {}", e.to_string());
+            return Err(BinaryError::Custom("Could not import world.".to_string()));
+        }
+    };
+    if summary.failed > 0 {
+        return Err(BinaryError::Custom(format!(
+            "{} chunks, entities or player files could not be imported",
+            summary.failed
+        )));
+    }
+
+    Ok(())
+}
+
+fn handle_export(export_args: ExportArgs) -> Result<(), BinaryError> {
+    //! Handles the export of the world to vanilla region files.
+    info!("Exporting world...");
+
+    let world = World::new(&get_global_config().database.db_path)?;
+
+    let root_path = get_root_path();
+    let export_path = root_path.join(export_args.export_path);
+
+    if let Err(e) = world.export(export_path, export_args.compression.into()) {
+        error!("Could not export world: {}", e.to_string());
+        return Err(BinaryError::Custom("Could not export world.".to_string()));
+    }
+
+    Ok(())
+}
+
+fn handle_recompress(recompress_args: RecompressArgs) -> Result<(), BinaryError> {
+    //! Handles rewriting the stored chunks with a different compressor.
+    let config = &get_global_config().database;
+    let algorithm_name = recompress_args
+        .compression
+        .unwrap_or_else(|| config.compression.clone());
+    let algorithm = algorithm_name
+        .parse::<CompressorType>()
+        .map_err(|e| BinaryError::Custom(e.to_string()))?;
+    let level = recompress_args
+        .compression_level
+        .unwrap_or(config.compression_level);
+    let compressor = Compressor::create(algorithm, level);
+
+    let world = World::new(&config.db_path)?;
+
+    if let Err(e) = world.recompress(compressor, recompress_args.batch_size) {
+        error!("Could not recompress world: {}", e.to_string());
+        return Err(BinaryError::Custom(
+            "Could not recompress world.".to_string(),
+        ));
+    }
+
+    let configured = config.compression.parse::<CompressorType>().ok();
+    if configured != Some(algorithm) || level != config.compression_level {
+        info!(
+            "Set compression = \"{}\" and compression_level = {} in the config to keep saving \
+            chunks this way.",
+            algorithm, level
+        );
+    }
+
+    Ok(())
+}
+
+fn handle_migrate(migrate_args: MigrateArgs) -> Result<(), BinaryError> {
+    //! Handles upgrading the stored chunks to the latest chunk format.
+    let world = World::new(&get_global_config().database.db_path)?;
+
+    if let Err(e) = world.migrate(migrate_args.batch_size) {
+        error!("Could not migrate world: {}", e.to_string());
+        return Err(BinaryError::Custom("Could not migrate world.".to_string()));
+    }
+
+    Ok(())
+}
+
+fn handle_pregen(pregen_args: PregenArgs) -> Result<(), BinaryError> {
+    //! Handles generating an area of chunks ahead of time.
+    let area = match (
+        pregen_args.min_x,
+        pregen_args.min_z,
+        pregen_args.max_x,
+        pregen_args.max_z,
+    ) {
+        (Some(min_x), Some(min_z), Some(max_x), Some(max_z)) => {
+            PregenArea::between(&pregen_args.dimension, (min_x, min_z), (max_x, max_z))
+        }
+        _ => PregenArea::around(
+            &pregen_args.dimension,
+            pregen_args.center_x,
+            pregen_args.center_z,
+            pregen_args
+                .radius
+                .unwrap_or(get_global_config().chunk_render_distance),
+        )?,
+    };
+    info!(
+        "Generating chunks {}, {} to {}, {} in {} ({} chunks)...",
+        area.min_x,
+        area.min_z,
+        area.max_x,
+        area.max_z,
+        area.dimension,
+        area.chunk_count()?
+    );
+
+    let world = World::new(&get_global_config().database.db_path)?;
+    let thread_pool = ThreadPool::new();
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    ctrlc::set_handler({
+        let cancel = cancel.clone();
+        move || {
+            info!("Stopping after the current batch...");
+            cancel.store(true, Ordering::Relaxed);
+        }
+    })
+    .map_err(|e| BinaryError::Custom(e.to_string()))?;
+
+    let summary = pregen(&world, &thread_pool, &area, pregen_args.batch_size, &cancel)?;
+    info!(
+        "Generated {} chunks and skipped {} that already existed.",
+        summary.generated, summary.skipped
+    );
+    if summary.failed > 0 {
+        return Err(BinaryError::Custom(format!(
+            "{} chunks could not be generated",
+            summary.failed
+        )));
+    }
+    if summary.cancelled {
+        return Err(BinaryError::Custom(
+            "Stopped before finishing, run the same command again to carry on.".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn handle_trim(trim_args: TrimArgs) -> Result<(), BinaryError> {
+    //! Handles deleting unneeded chunks and shrinking the database afterwards.
+    let keep = if let Some(radius) = trim_args.radius {
+        Some(TrimArea::Radius {
+            center_x: trim_args.center_x,
+            center_z: trim_args.center_z,
+            radius,
+        })
+    } else if !trim_args.polygon.is_empty() {
+        let corners = trim_args
+            .polygon
+            .iter()
+            .map(|corner| {
+                corner
+                    .split_once(',')
+                    .and_then(|(x, z)| Some((x.trim().parse().ok()?, z.trim().parse().ok()?)))
+                    .ok_or_else(|| BinaryError::Custom(format!("Invalid polygon corner {corner}")))
+            })
+            .collect::<Result<Vec<(i32, i32)>, BinaryError>>()?;
+        Some(TrimArea::Polygon(corners))
+    } else {
+        None
+    };
+    let options = TrimOptions {
+        dimension: trim_args.dimension,
+        keep,
+        unmodified_only: trim_args.unmodified,
+        dry_run: trim_args.dry_run,
+    };
+
+    let world = World::new(&get_global_config().database.db_path)?;
+    let report = world
+        .trim(&options, trim_args.batch_size)
+        .map_err(|e| BinaryError::Custom(e.to_string()))?;
+
+    let verb = if options.dry_run {
+        "Would delete"
+    } else {
+        "Deleted"
+    };
+    for (dimension, count) in &report.removed {
+        info!("{} {} chunks in {}", verb, count, dimension);
+    }
+    info!(
+        "{} {} of {} checked chunks, {} of chunk data.",
+        verb,
+        report.total_removed(),
+        report.checked,
+        format_bytes(report.removed_bytes)
+    );
+
+    if options.dry_run || trim_args.no_compact || report.total_removed() == 0 {
+        return Ok(());
+    }
+    info!("Compacting the database...");
+    let (before, after) = world
+        .compact()
+        .map_err(|e| BinaryError::Custom(format!("Could not compact the database: {e}")))?;
+    info!(
+        "Compacted the database from {} to {}.",
+        format_bytes(before),
+        format_bytes(after)
+    );
+
+    Ok(())
+}
+
+fn handle_restore(restore_args: RestoreArgs) -> Result<(), BinaryError> {
+    //! Handles listing backups and rolling the world back to one.
+    let root_path = get_root_path();
+    let backup_dir = root_path.join(&get_global_config().backups.path);
+    let backups = list_backups(&backup_dir).map_err(|e| BinaryError::Custom(e.to_string()))?;
+
+    if restore_args.list {
+        if backups.is_empty() {
+            info!("No backups found in {}", backup_dir.display());
+        }
+        for backup in backups {
+            info!("{} (taken at {})", backup.path.display(), backup.timestamp);
+        }
+        return Ok(());
+    }
+
+    let backup = match restore_args.backup {
+        Some(backup) => root_path.join(backup),
+        None => match backups.first() {
+            Some(backup) => backup.path.clone(),
+            None => {
+                return Err(BinaryError::Custom(format!(
+                    "No backups found in {}",
+                    backup_dir.display()
+                )));
+            }
+        },
+    };
+
+    info!("Restoring world from {}...", backup.display());
+    let db_path = root_path.join(&get_global_config().database.db_path);
+    if let Err(e) = restore(&backup, &db_path) {
+        error!("Could not restore world: {}", e.to_string());
+        return Err(BinaryError::Custom("Could not restore world.".to_string()));
     }
+    info!("Restore completed successfully.");
 
     Ok(())
 }
 
 fn create_state(start_time: Instant) -> Result<ServerState, BinaryError> {
     Ok(ServerState {
-        world: World::new(&get_global_config().database.db_path),
-        terrain_generator: WorldGenerator::new(0),
+        world: World::new(&get_global_config().database.db_path)?,
+        terrain_generator: Generators::new(0),
         shut_down: false.into(),
         players: PlayerList::default(),
         thread_pool: ThreadPool::new(),
@@ -7,7 +7,7 @@ use ferrumc_net::packets::outgoing::crafted_recipe::CraftedRecipePacket;
 use ferrumc_net::ContainerSlotStateChangedReceiver;
 use ferrumc_net_codec::net_types::prefixed_optional::PrefixedOptional;
 use ferrumc_state::GlobalStateResource;
-use ferrumc_world::block_id::BlockId;
+use ferrumc_world::item_id::ItemId;
 use tracing::debug;
 
 pub fn handle(
@@ -26,13 +26,13 @@ pub fn handle(
         let slot_index = event.slot as usize;
         let new_stack = match &event.item.item {
             PrefixedOptional::Some(data) => {
-                let block_id = BlockId::from_varint(data.item_id);
+                let item = ItemId::from_varint(data.item_id);
                 let nbt = if data.nbt.is_empty() {
                     None
                 } else {
                     Some(data.nbt.clone())
                 };
-                Some(ItemStack::new(block_id, data.count, 64, nbt))
+                Some(ItemStack::new(item, data.count, item.max_stack_size(), nbt))
             }
             PrefixedOptional::None => None,
         };
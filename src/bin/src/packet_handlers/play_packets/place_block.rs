@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use crate::errors::BinaryError;
 use bevy_ecs::prelude::{Entity, Query, Res};
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
 use ferrumc_core::collisions::bounds::CollisionBounds;
 use ferrumc_core::transform::position::Position;
 use ferrumc_net::connection::StreamWriter;
@@ -10,22 +11,22 @@ use ferrumc_net::packets::outgoing::block_update::BlockUpdate;
 use ferrumc_net::PlaceBlockReceiver;
 use ferrumc_net_codec::net_types::network_position::NetworkPosition;
 use ferrumc_net_codec::net_types::var_int::VarInt;
+use ferrumc_plugins::PluginManager;
 use ferrumc_state::GlobalStateResource;
-use ferrumc_world::block_id::BlockId;
-use ferrumc_world::vanilla_chunk_format::BlockData;
+use ferrumc_world::block_id::{Axis, BlockId, Direction};
+use ferrumc_world::errors::WorldError;
 use tracing::{debug, trace};
-use ferrumc_plugins::PluginManager;
 
 pub fn handle(
     events: Res<PlaceBlockReceiver>,
     state: Res<GlobalStateResource>,
-    conn_q: Query<(Entity, &StreamWriter)>,
+    conn_q: Query<(Entity, &StreamWriter, &ChunkReceiver)>,
     pos_q: Query<(&Position, &CollisionBounds)>,
     plugins: Res<PluginManager>,
 ) {
     'ev_loop: for (event, eid) in events.0.try_iter() {
         let res: Result<(), BinaryError> = try {
-            let Ok((entity, conn)) = conn_q.get(eid) else {
+            let Ok((entity, conn, receiver)) = conn_q.get(eid) else {
                 debug!("Could not get connection for entity {:?}", eid);
                 continue;
             };
@@ -33,6 +34,7 @@ pub fn handle(
                 trace!("Entity {:?} is not connected", entity);
                 continue;
             }
+            let dimension = receiver.last_chunk.2.as_str();
             let hand = event.hand.0 as usize;
             if hand > 1 {
                 debug!("Invalid hand");
@@ -47,44 +49,40 @@ pub fn handle(
                 continue;
             }
 
-            let mut block_data = block_id.to_block_data().unwrap_or(BlockData::default());
-            if let Some(props) = block_data.properties.as_mut() {
-                if props.contains_key("facing") {
-                    let facing = match event.face.0 {
-                        0 => "down",
-                        1 => "up",
-                        2 => "north",
-                        3 => "south",
-                        4 => "west",
-                        5 => "east",
-                        _ => "north",
-                    };
-                    props.insert("facing".into(), facing.into());
-                } else if props.contains_key("axis") {
-                    let axis = match event.face.0 {
-                        0 | 1 => "y",
-                        2 | 3 => "z",
-                        4 | 5 => "x",
-                        _ => "y",
-                    };
-                    props.insert("axis".into(), axis.into());
-                }
+            let facing = match event.face.0 {
+                0 => Direction::Down,
+                1 => Direction::Up,
+                2 => Direction::North,
+                3 => Direction::South,
+                4 => Direction::West,
+                5 => Direction::East,
+                _ => Direction::North,
+            };
+            let axis = match facing {
+                Direction::Down | Direction::Up => Axis::Y,
+                Direction::North | Direction::South => Axis::Z,
+                Direction::West | Direction::East => Axis::X,
+            };
+            // Blocks that can't face the clicked side, like stairs on a ceiling, are placed as
+            // they are
+            if let Some(facing_block) = block_id.with(facing) {
+                block_id = facing_block;
+            } else if let Some(axis_block) = block_id.with(axis) {
+                block_id = axis_block;
             }
-            block_id = BlockId::from_block_data(&block_data);
 
-            let mut chunk = match state.0.world.load_chunk_owned(
-                event.position.x >> 4,
-                event.position.z >> 4,
-                "overworld",
+            let block_clicked = match state.0.world.get_block_and_fetch(
+                event.position.x,
+                event.position.y as i32,
+                event.position.z,
+                dimension,
             ) {
-                Ok(chunk) => chunk,
+                Ok(block) => block,
                 Err(e) => {
                     debug!("Failed to load chunk: {:?}", e);
                     continue 'ev_loop;
                 }
             };
-            let block_clicked =
-                chunk.get_block(event.position.x, event.position.y as i32, event.position.z)?;
             trace!("Block clicked: {:?}", block_clicked);
             let (x_block_offset, y_block_offset, z_block_offset) = match event.face.0 {
                 0 => (0, -1, 0),
@@ -121,7 +119,28 @@ pub fn handle(
                 continue 'ev_loop;
             }
 
-            chunk.set_block(x & 0xF, y as i32, z & 0xF, block_id)?;
+            // The placed block can be in a chunk next to the clicked one that was never generated
+            if !state.0.world.chunk_exists(x >> 4, z >> 4, dimension)? {
+                trace!("Chunk not found, generating new chunk");
+                let generated_dimension = state
+                    .0
+                    .world
+                    .dimensions()
+                    .get(dimension)
+                    .ok_or_else(|| WorldError::UnknownDimension(dimension.to_string()))?;
+                let mut chunk = state.0.terrain_generator.generate_chunk(
+                    x >> 4,
+                    z >> 4,
+                    &generated_dimension,
+                )?;
+                state.0.world.light_new_chunk(&mut chunk);
+                state.0.world.save_chunk(Arc::new(chunk))?;
+            }
+            // Goes through the world so redstone, fluids and block entities next to it react
+            state
+                .0
+                .world
+                .set_block_and_fetch(x, y as i32, z, dimension, block_id)?;
             plugins.on_block_edit((x, y as i32, z), block_id.0);
 
             let chunk_packet = BlockUpdate {
@@ -133,8 +152,6 @@ pub fn handle(
                 sequence: event.sequence,
             };
             conn.send_packet_ref(&ack_packet)?;
-
-            state.0.world.save_chunk(Arc::new(chunk))?;
         };
         if let Err(e) = &res {
             debug!("Failed to handle place block: {:?}", e);
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use crate::errors::BinaryError;
 use bevy_ecs::prelude::{Entity, Query, Res};
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
 use ferrumc_net::connection::StreamWriter;
 use ferrumc_net::packets::outgoing::block_change_ack::BlockChangeAck;
 use ferrumc_net::packets::outgoing::block_update::BlockUpdate;
@@ -9,7 +10,7 @@ use ferrumc_net::PlayerActionReceiver;
 use ferrumc_net_codec::net_types::var_int::VarInt;
 use ferrumc_state::GlobalStateResource;
 use ferrumc_world::block_id::BlockId;
-use ferrumc_world::vanilla_chunk_format::BlockData;
+use ferrumc_world::errors::WorldError;
 use tracing::{debug, error, trace};
 
 fn send_ack(
@@ -33,6 +34,7 @@ pub fn handle(
     events: Res<PlayerActionReceiver>,
     state: Res<GlobalStateResource>,
     query: Query<(Entity, &StreamWriter)>,
+    receivers: Query<&ChunkReceiver>,
 ) {
     // https://minecraft.wiki/w/Minecraft_Wiki:Projects/wiki.vg_merge/Protocol?oldid=2773393#Player_Action
     for (event, trigger_eid) in events.0.try_iter() {
@@ -48,34 +50,37 @@ pub fn handle(
                 }
                 2 => {
                     // Finished digging
-                    let mut chunk = match state.0.clone().world.load_chunk_owned(
-                        event.location.x >> 4,
-                        event.location.z >> 4,
-                        "overworld",
-                    ) {
-                        Ok(chunk) => chunk,
-                        Err(e) => {
-                            trace!("Chunk not found, generating new chunk: {:?}", e);
-                    let gen = &state.0.terrain_generator;
-                            let bx = event.location.x >> 4;
-                            let bz = event.location.z >> 4;
-                            let biome = gen.biome_at(bx, bz);
-                            gen.generate_chunk_for_biome(bx, bz, biome)?
-                        }
+                    let (x, y, z) = (event.location.x, event.location.y as i32, event.location.z);
+                    let Ok(receiver) = receivers.get(trigger_eid) else {
+                        debug!("Could not get chunk receiver for entity {:?}", trigger_eid);
+                        continue;
                     };
-                    let (relative_x, relative_y, relative_z) = (
-                        event.location.x.abs() % 16,
-                        event.location.y as i32,
-                        event.location.z.abs() % 16,
-                    );
-                    chunk.set_block(relative_x, relative_y, relative_z, BlockData::default())?;
-                    state.0.world.save_chunk(Arc::new(chunk))?;
+                    let dimension = receiver.last_chunk.2.as_str();
+                    let world = &state.0.world;
+                    if !world.chunk_exists(x >> 4, z >> 4, dimension)? {
+                        trace!("Chunk not found, generating new chunk");
+                        let generated_dimension = world
+                            .dimensions()
+                            .get(dimension)
+                            .ok_or_else(|| WorldError::UnknownDimension(dimension.to_string()))?;
+                        let generator = &state.0.terrain_generator;
+                        let mut chunk =
+                            generator.generate_chunk(x >> 4, z >> 4, &generated_dimension)?;
+                        world.light_new_chunk(&mut chunk);
+                        world.save_chunk(Arc::new(chunk))?;
+                    }
+                    // Goes through the world so redstone, fluids and block entities next to it
+                    // react
+                    world.set_block_and_fetch(x, y, z, dimension, BlockId::default())?;
                     let block_update_packet = BlockUpdate {
                         location: event.location.clone(),
                         block_id: VarInt::from(BlockId::default()),
                     };
                     for (eid, conn) in query.iter() {
-                        if !state.0.players.is_connected(eid) {
+                        let same_dimension = receivers
+                            .get(eid)
+                            .is_ok_and(|receiver| receiver.last_chunk.2 == dimension);
+                        if !state.0.players.is_connected(eid) || !same_dimension {
                             continue;
                         }
                         conn.send_packet_ref(&block_update_packet)?;
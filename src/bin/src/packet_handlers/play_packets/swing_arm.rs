@@ -1,6 +1,7 @@
 use bevy_ecs::prelude::{Entity, Query, Res};
 use ferrumc_core::identity::player_identity::PlayerIdentity;
 use ferrumc_net::connection::StreamWriter;
+use ferrumc_net::outbound::Delivery;
 use ferrumc_net::packets::outgoing::entity_animation::EntityAnimationPacket;
 use ferrumc_net::SwingArmPacketReceiver;
 use ferrumc_net_codec::net_types::var_int::VarInt;
@@ -30,7 +31,7 @@ pub fn handle(
             if !state.0.players.is_connected(entity) {
                 continue; // Skip if the player is not connected
             }
-            if let Err(e) = conn.send_packet_ref(&packet) {
+            if let Err(e) = conn.send_packet_as(&packet, Delivery::Droppable) {
                 error!("Failed to send packet: {}", e);
             }
         }
@@ -4,6 +4,7 @@ use ferrumc_core::identity::entity_id::EntityId;
 use ferrumc_core::transform::position::Position;
 use ferrumc_core::transform::rotation::Rotation;
 use ferrumc_net::connection::StreamWriter;
+use ferrumc_net::outbound::Delivery;
 use ferrumc_net::packets::outgoing::entity_position_sync::TeleportEntityPacket;
 use tracing::warn;
 
@@ -13,8 +14,12 @@ pub fn handle(
 ) {
     for (id, pos, rot) in mobs.iter() {
         let packet = TeleportEntityPacket::new(id.short_uuid, pos, rot, true);
+        let delivery = Delivery::Latest {
+            kind: "entity_position_sync",
+            id: id.short_uuid,
+        };
         for conn in connections.iter() {
-            if let Err(e) = conn.send_packet_ref(&packet) {
+            if let Err(e) = conn.send_packet_as(&packet, delivery) {
                 warn!("Failed to send position packet: {:?}", e);
             }
         }
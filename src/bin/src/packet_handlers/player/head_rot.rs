@@ -3,6 +3,7 @@ use bevy_ecs::prelude::Query;
 use ferrumc_core::identity::player_identity::PlayerIdentity;
 use ferrumc_core::transform::rotation::Rotation;
 use ferrumc_net::connection::StreamWriter;
+use ferrumc_net::outbound::Delivery;
 use ferrumc_net::packets::outgoing::set_head_rotation::SetHeadRotationPacket;
 use ferrumc_net::packets::packet_events::TransformEvent;
 use ferrumc_net_codec::net_types::angle::NetAngle;
@@ -17,10 +18,14 @@ pub fn handle_player_move(
         let entity = event.entity;
 
         let (rot, identity) = query.get(entity).unwrap();
-        let head_rot_packet = SetHeadRotationPacket::new(
-            identity.uuid.as_u128() as i32,
-            NetAngle::from_degrees(rot.yaw as f64),
-        );
+        let entity_id = identity.uuid.as_u128() as i32;
+        let head_rot_packet =
+            SetHeadRotationPacket::new(entity_id, NetAngle::from_degrees(rot.yaw as f64));
+        // Only the newest rotation matters to players who are behind
+        let delivery = Delivery::Latest {
+            kind: "rotate_head",
+            id: entity_id,
+        };
 
         #[cfg(debug_assertions)]
         let start = std::time::Instant::now();
@@ -29,7 +34,7 @@ pub fn handle_player_move(
             if !writer.running.load(std::sync::atomic::Ordering::Relaxed) {
                 continue;
             }
-            if let Err(err) = writer.send_packet_ref(&head_rot_packet) {
+            if let Err(err) = writer.send_packet_as(&head_rot_packet, delivery) {
                 error!("Failed to send head rotation packet: {:?}", err);
             }
         }
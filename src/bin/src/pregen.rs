@@ -0,0 +1,281 @@
+//! Generating chunks ahead of time, so big maps can be built before players join.
+//!
+//! The area is worked through row by row in batches. Each batch is generated on the thread pool,
+//! written to the database in one go and then lit. After every batch the position is written to
+//! a progress file next to the server, so an interrupted run picks up where it left off when it's
+//! started again with the same area. The progress never goes past a chunk that failed to
+//! generate, so the next run tries it again. Chunks that already exist are never regenerated.
+
+use crate::errors::BinaryError;
+use ferrumc_general_purpose::paths::get_root_path;
+use ferrumc_state::GlobalState;
+use ferrumc_threadpool::ThreadPool;
+use ferrumc_world::chunk_format::Chunk;
+use ferrumc_world::World;
+use ferrumc_world_gen::Generators;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// How often to log progress.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Cancels the pre-generation started in-game, if there is one running.
+static RUNNING: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+
+/// A rectangle of chunks to generate, inclusive on both ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PregenArea {
+    pub dimension: String,
+    pub min_x: i32,
+    pub min_z: i32,
+    pub max_x: i32,
+    pub max_z: i32,
+}
+
+impl PregenArea {
+    /// A square of chunks `radius` chunks out from a center chunk. Fails if part of it would be
+    /// outside the range of chunk coordinates.
+    pub fn around(
+        dimension: &str,
+        center_x: i32,
+        center_z: i32,
+        radius: u32,
+    ) -> Result<Self, BinaryError> {
+        let out_of_range = || {
+            BinaryError::Custom(format!(
+                "A radius of {radius} around {center_x}, {center_z} is out of range"
+            ))
+        };
+        let radius = i32::try_from(radius).map_err(|_| out_of_range())?;
+        Ok(PregenArea {
+            dimension: dimension.to_string(),
+            min_x: center_x.checked_sub(radius).ok_or_else(out_of_range)?,
+            min_z: center_z.checked_sub(radius).ok_or_else(out_of_range)?,
+            max_x: center_x.checked_add(radius).ok_or_else(out_of_range)?,
+            max_z: center_z.checked_add(radius).ok_or_else(out_of_range)?,
+        })
+    }
+
+    /// The rectangle between two corner chunks, in any order.
+    pub fn between(dimension: &str, from: (i32, i32), to: (i32, i32)) -> Self {
+        PregenArea {
+            dimension: dimension.to_string(),
+            min_x: from.0.min(to.0),
+            min_z: from.1.min(to.1),
+            max_x: from.0.max(to.0),
+            max_z: from.1.max(to.1),
+        }
+    }
+
+    fn width(&self) -> u64 {
+        (self.max_x as i64 - self.min_x as i64 + 1) as u64
+    }
+
+    /// Fails if there are too many chunks to count, which only happens for areas covering the
+    /// whole world.
+    pub fn chunk_count(&self) -> Result<u64, BinaryError> {
+        self.width()
+            .checked_mul((self.max_z as i64 - self.min_z as i64 + 1) as u64)
+            .ok_or_else(|| BinaryError::Custom("Too many chunks to pre-generate".to_string()))
+    }
+
+    fn chunk_at(&self, index: u64) -> (i32, i32) {
+        let x = self.min_x as i64 + (index % self.width()) as i64;
+        let z = self.min_z as i64 + (index / self.width()) as i64;
+        (x as i32, z as i32)
+    }
+
+    /// Each area gets its own progress file, so several can be left half done at once.
+    fn progress_path(&self) -> PathBuf {
+        get_root_path().join(format!(
+            "pregen-{}-{}_{}_{}_{}.progress",
+            self.dimension.replace(':', "_"),
+            self.min_x,
+            self.min_z,
+            self.max_x,
+            self.max_z
+        ))
+    }
+
+    /// How many chunks of this area a previous run got through, if it was interrupted.
+    fn load_progress(&self, total: u64) -> u64 {
+        match std::fs::read_to_string(self.progress_path()) {
+            Ok(saved) => match saved.trim().parse::<u64>() {
+                Ok(done) => done.min(total),
+                Err(_) => {
+                    warn!("Ignoring unreadable pre-generation progress, starting over");
+                    0
+                }
+            },
+            Err(_) => 0,
+        }
+    }
+
+    fn save_progress(&self, done: u64) -> Result<(), BinaryError> {
+        std::fs::write(self.progress_path(), done.to_string())?;
+        Ok(())
+    }
+}
+
+/// What a run of [`pregen`] did.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PregenSummary {
+    pub generated: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    pub cancelled: bool,
+}
+
+/// Generate every missing chunk in an area.
+///
+/// Stops after the current batch once `cancel` is set, the next run with the same area carries on
+/// from there.
+pub(crate) fn pregen(
+    world: &World,
+    thread_pool: &ThreadPool,
+    area: &PregenArea,
+    batch_size: usize,
+    cancel: &AtomicBool,
+) -> Result<PregenSummary, BinaryError> {
+    let dimension = world
+        .dimensions()
+        .get(&area.dimension)
+        .ok_or_else(|| BinaryError::Custom(format!("Unknown dimension {}", area.dimension)))?;
+    let generators = Arc::new(Generators::new(0));
+    let total = area.chunk_count()?;
+    let batch_size = batch_size.max(1) as u64;
+
+    let mut done = area.load_progress(total);
+    if done > 0 {
+        info!(
+            "Resuming pre-generation of {} at {}/{} chunks",
+            dimension.name, done, total
+        );
+    }
+    let resumed_at = done;
+    let mut summary = PregenSummary::default();
+    let start = Instant::now();
+    let mut last_report = Instant::now();
+    // The first chunk that failed to generate, the progress isn't saved past it
+    let mut first_failed = None;
+
+    while done < total {
+        if cancel.load(Ordering::Relaxed) {
+            summary.cancelled = true;
+            break;
+        }
+
+        let end = (done + batch_size).min(total);
+        let mut batch = thread_pool.batch();
+        for index in done..end {
+            let (x, z) = area.chunk_at(index);
+            let world = world.clone();
+            let generators = generators.clone();
+            let dimension = dimension.clone();
+            batch.execute(move || -> Result<Option<Chunk>, BinaryError> {
+                if world.chunk_exists(x, z, &dimension.name)? {
+                    return Ok(None);
+                }
+                Ok(Some(generators.generate_chunk(x, z, &dimension)?))
+            });
+        }
+
+        let mut chunks = Vec::new();
+        // Results come back in the order the chunks were queued in
+        for (index, result) in (done..end).zip(batch.wait()) {
+            match result {
+                Ok(Some(chunk)) => chunks.push(chunk),
+                Ok(None) => summary.skipped += 1,
+                Err(e) => {
+                    let (x, z) = area.chunk_at(index);
+                    error!("Failed to generate chunk ({}, {}): {}", x, z, e);
+                    summary.failed += 1;
+                    first_failed.get_or_insert(index);
+                }
+            }
+        }
+        // Players may have loaded some of these while they were being generated, their versions
+        // win
+        let saved = world.save_new_chunks(&chunks)?;
+        summary.skipped += (chunks.len() - saved.len()) as u64;
+        // Light has to flow across chunk borders, so this is done one chunk at a time after the
+        // whole batch exists
+        for chunk in &saved {
+            if let Err(e) = world.relight_chunk(chunk.x, chunk.z, &dimension.name) {
+                error!("Failed to light chunk ({}, {}): {}", chunk.x, chunk.z, e);
+            }
+        }
+        summary.generated += saved.len() as u64;
+        done = end;
+        area.save_progress(first_failed.unwrap_or(done))?;
+
+        if last_report.elapsed() >= PROGRESS_INTERVAL || done == total {
+            last_report = Instant::now();
+            let per_second = (done - resumed_at) as f64 / start.elapsed().as_secs_f64().max(0.001);
+            let eta = Duration::from_secs(((total - done) as f64 / per_second.max(0.001)) as u64);
+            info!(
+                "Pre-generated {}/{} chunks of {} ({:.1}%), {:.0} chunks/s, ETA {:?}",
+                done,
+                total,
+                dimension.name,
+                done as f64 / total as f64 * 100.0,
+                per_second,
+                eta
+            );
+        }
+    }
+
+    world.sync()?;
+    if !summary.cancelled && first_failed.is_none() {
+        let _ = std::fs::remove_file(area.progress_path());
+    }
+    Ok(summary)
+}
+
+/// Start pre-generating in the background while the server keeps running.
+///
+/// Only one in-game pre-generation can run at a time. Returns false if one is already running.
+pub(crate) fn start_background(
+    state: GlobalState,
+    area: PregenArea,
+    batch_size: usize,
+) -> Result<bool, BinaryError> {
+    let mut running = RUNNING.lock().unwrap();
+    if running.is_some() {
+        return Ok(false);
+    }
+    let cancel = Arc::new(AtomicBool::new(false));
+    *running = Some(cancel.clone());
+
+    std::thread::Builder::new()
+        .name("pregen".to_string())
+        .spawn(move || {
+            match pregen(&state.world, &state.thread_pool, &area, batch_size, &cancel) {
+                Ok(summary) if summary.cancelled => info!(
+                    "Pre-generation of {} cancelled after generating {} chunks",
+                    area.dimension, summary.generated
+                ),
+                Ok(summary) => info!(
+                    "Pre-generation of {} finished, generated {} chunks and skipped {} existing ones",
+                    area.dimension, summary.generated, summary.skipped
+                ),
+                Err(e) => error!("Pre-generation of {} failed: {}", area.dimension, e),
+            }
+            *RUNNING.lock().unwrap() = None;
+        })?;
+    Ok(true)
+}
+
+/// Stop the in-game pre-generation after its current batch. Returns false if none is running.
+pub(crate) fn cancel_background() -> bool {
+    match RUNNING.lock().unwrap().as_ref() {
+        Some(cancel) => {
+            cancel.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
@@ -1,9 +1,11 @@
 use crate::commands::{
-    gamemode_command, give_command, say_command, tp_command, CommandDispatcher,
+    clone_command, fill_command, gamemode_command, gamerule_command, give_command, pregen_command,
+    redo_command, say_command, tp_command, undo_command, CommandDispatcher,
 };
 use crate::systems::new_connections::NewConnectionRecv;
 use bevy_ecs::prelude::World;
 use crossbeam_channel::Receiver;
+use ferrumc_core::chunks::world_backup_tracker::WorldBackupTracker;
 use ferrumc_core::chunks::world_sync_tracker::WorldSyncTracker;
 use ferrumc_core::conn::player_count_update_cooldown::PlayerCountUpdateCooldown;
 use ferrumc_net::connection::NewConnection;
@@ -24,6 +26,10 @@ pub fn register_resources(
     world.insert_resource(WorldSyncTracker {
         last_synced: std::time::Instant::now(),
     });
+    world.insert_resource(WorldBackupTracker {
+        last_backup: std::time::Instant::now(),
+        in_progress: Default::default(),
+    });
 
     let mut plugins = PluginManager::default();
     if let Err(e) = plugins.load_from_dir("plugins") {
@@ -36,5 +42,11 @@ pub fn register_resources(
     dispatcher.register(tp_command());
     dispatcher.register(give_command());
     dispatcher.register(gamemode_command());
+    dispatcher.register(gamerule_command());
+    dispatcher.register(pregen_command());
+    dispatcher.register(fill_command());
+    dispatcher.register(clone_command());
+    dispatcher.register(undo_command());
+    dispatcher.register(redo_command());
     world.insert_resource(dispatcher);
 }
@@ -0,0 +1,32 @@
+use bevy_ecs::prelude::{Entity, Query, Res};
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
+use ferrumc_net::connection::StreamWriter;
+use ferrumc_net::packets::outgoing::block_entity_data::BlockEntityData;
+use ferrumc_net_codec::net_types::network_position::NetworkPosition;
+use ferrumc_state::GlobalStateResource;
+use tracing::debug;
+
+/// Send the block entities that were created or changed since the last run to the players who
+/// were sent their chunk. The others get them with the chunk.
+pub fn run_block_entity_updates(
+    query: Query<(Entity, &StreamWriter, &ChunkReceiver)>,
+    state: Res<GlobalStateResource>,
+) {
+    for update in state.0.world.take_block_entity_updates() {
+        let (x, y, z) = update.position;
+        let packet = BlockEntityData {
+            location: NetworkPosition::new(x, y as i16, z),
+            entity_type: update.entity_type,
+            nbt: update.nbt,
+        };
+        let chunk = (x >> 4, z >> 4, update.dimension);
+        for (entity, conn, receiver) in query.iter() {
+            if !state.0.players.is_connected(entity) || !receiver.seen.contains(&chunk) {
+                continue;
+            }
+            if let Err(e) = conn.send_packet_ref(&packet) {
+                debug!("Failed to send a block entity to {:?}: {}", entity, e);
+            }
+        }
+    }
+}
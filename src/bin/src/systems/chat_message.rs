@@ -39,6 +39,7 @@ pub fn broadcast_chat_messages(
         &mut ferrumc_core::transform::position::Position,
         &mut ferrumc_core::inventory::Inventory,
         &ferrumc_core::identity::player_identity::PlayerIdentity,
+        &ferrumc_core::chunks::chunk_receiver::ChunkReceiver,
     )>,
     state: Res<GlobalStateResource>,
     dispatcher: Res<CommandDispatcher>,
@@ -61,7 +62,7 @@ pub fn broadcast_chat_messages(
             let message = TextComponent::from(line);
             broadcast_text(
                 message,
-                query.iter_mut().map(|(e, conn, _, _, _)| (e, conn)),
+                query.iter_mut().map(|(e, conn, _, _, _, _)| (e, conn)),
                 state.as_ref(),
             );
         }
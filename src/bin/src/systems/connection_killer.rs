@@ -1,20 +1,30 @@
 use bevy_ecs::prelude::{Commands, Entity, Query, Res};
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
 use ferrumc_core::identity::player_identity::PlayerIdentity;
 use ferrumc_core::inventory::Inventory;
 use ferrumc_core::transform::position::Position;
 use ferrumc_net::connection::StreamWriter;
 use ferrumc_state::GlobalStateResource;
-use ferrumc_storage::player_data::{save_player_data, InventoryData, PlayerData, PlayerStatsData, PositionData};
+use ferrumc_storage::player_data::{
+    save_player_data, InventoryData, PlayerData, PlayerStatsData, PositionData, ITEM_FORMAT_VERSION,
+};
 use ferrumc_text::TextComponent;
 use tracing::{info, trace, warn};
 
 pub fn connection_killer(
-    query: Query<(Entity, &StreamWriter, &PlayerIdentity, &Position, &Inventory)>,
+    query: Query<(
+        Entity,
+        &StreamWriter,
+        &PlayerIdentity,
+        &Position,
+        &Inventory,
+        &ChunkReceiver,
+    )>,
     mut cmd: Commands,
     state: Res<GlobalStateResource>,
 ) {
     while let Some((disconnecting_entity, reason)) = state.0.players.disconnection_queue.pop() {
-        for (entity, conn, player_identity, position, inventory) in query.iter() {
+        for (entity, conn, player_identity, position, inventory, receiver) in query.iter() {
             if disconnecting_entity == entity {
                 info!(
                     "Player {} ({}) disconnected: {}",
@@ -55,6 +65,8 @@ pub fn connection_killer(
                     },
                     stats: PlayerStatsData::default(),
                     advancements: Vec::new(),
+                    item_format: Some(ITEM_FORMAT_VERSION),
+                    dimension: Some(receiver.last_chunk.2.clone()),
                 };
                 let _ = save_player_data(
                     state.0.world.backend(),
@@ -1,14 +1,16 @@
 use crate::systems::send_chunks::send_chunks;
 use bevy_ecs::prelude::{EventReader, Query, Res};
 use ferrumc_config::server_config::get_global_config;
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
 use ferrumc_core::chunks::cross_chunk_boundary_event::CrossChunkBoundaryEvent;
 use ferrumc_net::connection::StreamWriter;
 use ferrumc_state::GlobalStateResource;
 use std::collections::HashSet;
+use tracing::error;
 
 pub fn cross_chunk_boundary(
     mut events: EventReader<CrossChunkBoundaryEvent>,
-    mut query: Query<&mut StreamWriter>,
+    mut query: Query<(&mut StreamWriter, &mut ChunkReceiver)>,
     state: Res<GlobalStateResource>,
 ) {
     if events.is_empty() {
@@ -32,17 +34,28 @@ pub fn cross_chunk_boundary(
                 new_chunk_seen.insert((x, z));
             }
         }
+        let center_chunk = (event.new_chunk.0, event.new_chunk.1);
+        let (mut conn, mut recv) = query.get_mut(event.player).expect("Player does not exist");
+        let dimension = recv.last_chunk.2.clone();
+        // The client drops the chunks that are out of view now
+        recv.seen
+            .retain(|(x, z, _)| new_chunk_seen.contains(&(*x, *z)));
         let needed_chunks: Vec<_> = new_chunk_seen
             .iter()
             .filter(|chunk| !old_chunk_seen.contains(chunk))
             .map(|chunk| {
                 let (x, z) = *chunk;
-                (x, z, "overworld".to_string())
+                (x, z, dimension.clone())
             })
             .collect();
-        let center_chunk = (event.new_chunk.0, event.new_chunk.1);
-        let mut conn = query.get_mut(event.player).expect("Player does not exist");
-        send_chunks(state.0.clone(), needed_chunks, &mut conn, center_chunk)
-            .expect("Failed to send chunks")
+        if let Err(err) = send_chunks(
+            state.0.clone(),
+            needed_chunks,
+            &mut conn,
+            &mut recv,
+            center_chunk,
+        ) {
+            error!("Failed to send chunks to {:?}: {:?}", event.player, err);
+        }
     }
 }
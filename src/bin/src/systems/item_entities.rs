@@ -0,0 +1,119 @@
+use bevy_ecs::prelude::{Commands, Entity, Query, Res};
+use ferrumc_core::ai::EntityKind;
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
+use ferrumc_core::entities::item::ItemEntity;
+use ferrumc_core::entities::Item;
+use ferrumc_core::identity::entity_id::EntityId;
+use ferrumc_core::inventory::ItemStack;
+use ferrumc_core::transform::dimension::Dimension;
+use ferrumc_core::transform::position::Position;
+use ferrumc_core::transform::rotation::Rotation;
+use ferrumc_net::connection::StreamWriter;
+use ferrumc_net::packets::outgoing::entity_metadata::{EntityMetadata, EntityMetadataPacket};
+use ferrumc_net::packets::outgoing::remove_entities::RemoveEntitiesPacket;
+use ferrumc_net::packets::outgoing::spawn_entity::SpawnEntityPacket;
+use ferrumc_net_codec::encode::NetEncode;
+use ferrumc_net_codec::net_types::var_int::VarInt;
+use ferrumc_state::GlobalStateResource;
+use ferrumc_world::entities::StoredItem;
+use ferrumc_world::hoppers::LiveItem;
+use ferrumc_world::item_id::ItemId;
+use tracing::debug;
+
+type Players<'w, 's> = Query<'w, 's, (Entity, &'static StreamWriter, &'static ChunkReceiver)>;
+
+/// Spawn the items dispensers and droppers threw out since the last run, for the players in
+/// their dimension to see.
+pub fn spawn_dropped_items(
+    mut commands: Commands,
+    players: Players,
+    state: Res<GlobalStateResource>,
+) {
+    for dropped in state.0.world.take_dropped_items() {
+        let Some(item) = ItemId::from_name(&dropped.item.id) else {
+            debug!("Not spawning unknown item {}", dropped.item.id);
+            continue;
+        };
+        let id = EntityId::new(rand::random::<u128>());
+        let (x, y, z) = dropped.position;
+        let position = Position::new(x, y, z);
+        let stack = ItemStack::of(item, dropped.item.count);
+        let spawn = SpawnEntityPacket::mob(&id, EntityKind::Item, &position, &Rotation::default())
+            .with_velocity(dropped.velocity);
+        send_to_dimension(&players, &state, &dropped.dimension, &spawn);
+        send_to_dimension(&players, &state, &dropped.dimension, &shown(&id, &stack));
+        commands.spawn((
+            id,
+            Item,
+            ItemEntity(stack),
+            position,
+            Rotation::default(),
+            Dimension(dropped.dimension),
+        ));
+    }
+}
+
+/// Offer the item entities lying around to the hoppers under them, and take away what the
+/// hoppers picked up since the last run.
+pub fn exchange_hopper_items(
+    mut commands: Commands,
+    mut items: Query<(Entity, &EntityId, &mut ItemEntity, &Position, &Dimension)>,
+    players: Players,
+    state: Res<GlobalStateResource>,
+) {
+    let lying = items
+        .iter()
+        .filter_map(|(_, id, item, position, dimension)| {
+            Some(LiveItem {
+                uuid: id.uuid.as_u128(),
+                dimension: dimension.0.clone(),
+                position: (position.x, position.y, position.z),
+                item: StoredItem {
+                    id: item.0.item.name()?.to_string(),
+                    count: item.0.count,
+                },
+            })
+        })
+        .collect();
+    let taken = state.0.world.exchange_items(lying);
+    if taken.is_empty() {
+        return;
+    }
+    for (entity, id, mut item, _, dimension) in items.iter_mut() {
+        let Some(&picked) = taken.get(&id.uuid.as_u128()) else {
+            continue;
+        };
+        item.0.count = item.0.count.saturating_sub(picked);
+        if item.0.count > 0 {
+            send_to_dimension(&players, &state, &dimension.0, &shown(id, &item.0));
+            continue;
+        }
+        let removed = RemoveEntitiesPacket::from_entity_ids([id]);
+        send_to_dimension(&players, &state, &dimension.0, &removed);
+        commands.entity(entity).despawn();
+    }
+}
+
+/// The metadata that makes an item entity show its stack.
+fn shown(id: &EntityId, stack: &ItemStack) -> EntityMetadataPacket {
+    EntityMetadataPacket::new(
+        VarInt::new(id.short_uuid),
+        [EntityMetadata::item_stack(stack)],
+    )
+}
+
+fn send_to_dimension(
+    players: &Players,
+    state: &GlobalStateResource,
+    dimension: &str,
+    packet: &(impl NetEncode + Send),
+) {
+    for (entity, conn, receiver) in players.iter() {
+        if !state.0.players.is_connected(entity) || receiver.last_chunk.2 != dimension {
+            continue;
+        }
+        if let Err(e) = conn.send_packet_ref(packet) {
+            debug!("Failed to send an item entity to {:?}: {}", entity, e);
+        }
+    }
+}
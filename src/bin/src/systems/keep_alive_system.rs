@@ -1,7 +1,11 @@
 use bevy_ecs::prelude::{Entity, Query, Res};
 use ferrumc_core::conn::keepalive::KeepAliveTracker;
 use ferrumc_net::connection::StreamWriter;
+use ferrumc_net::outbound::SLOW_CLIENT_REASON;
 use ferrumc_state::GlobalStateResource;
+use ferrumc_utils::metrics::{
+    OUTBOUND_QUEUE_HISTOGRAM, OUTBOUND_QUEUE_MAX_BYTES, OUTBOUND_SLOW_CLIENT_KICKS,
+};
 use std::time::{Duration, SystemTime};
 use tracing::warn;
 
@@ -12,6 +16,7 @@ pub fn keep_alive_system(
     // Get the times before the queries, since it's possible a query takes more than a millisecond with a lot of entities.
 
     let current_time = SystemTime::now();
+    let mut max_queued = 0;
 
     for (entity, mut keep_alive_tracker, stream_writer) in query {
         if !stream_writer
@@ -20,6 +25,22 @@ pub fn keep_alive_system(
         {
             continue;
         }
+        let queued = stream_writer.queued_bytes();
+        OUTBOUND_QUEUE_HISTOGRAM.observe(queued as f64);
+        max_queued = max_queued.max(queued);
+        // Kick players whose connection can't keep up with what they're being sent
+        if stream_writer.fell_behind() && state.0.players.is_connected(entity) {
+            warn!(
+                "Killing connection for {}, it fell too far behind on the data sent to it",
+                entity
+            );
+            OUTBOUND_SLOW_CLIENT_KICKS.inc();
+            state
+                .0
+                .players
+                .disconnect(entity, Some(SLOW_CLIENT_REASON.to_string()));
+            continue;
+        }
         // If it's been more than 15 seconds since the last keep alive packet was received, kill the connection
         let time_diff = current_time
             .duration_since(keep_alive_tracker.last_received_keep_alive)
@@ -48,4 +69,5 @@ pub fn keep_alive_system(
             keep_alive_tracker.has_received_keep_alive = false;
         }
     }
+    OUTBOUND_QUEUE_MAX_BYTES.set(max_queued as i64);
 }
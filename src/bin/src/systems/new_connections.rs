@@ -31,10 +31,18 @@ pub fn accept_new_connections(
             z: pdata.position.z,
         };
         let inventory = Inventory::from(&pdata.inventory);
+        let mut chunk_receiver = ChunkReceiver::default();
+        if let Some(dimension) = pdata.dimension {
+            chunk_receiver.last_chunk.2 = dimension;
+        }
+        chunk_receiver.seen.extend(new_connection.sent_chunks);
+        chunk_receiver
+            .needs_reload
+            .extend(new_connection.pending_chunks);
         let entity = cmd.spawn((
             new_connection.stream,
             position,
-            ChunkReceiver::default(),
+            chunk_receiver,
             Rotation::default(),
             OnGround::default(),
             new_connection.player_identity,
@@ -0,0 +1,163 @@
+//! Showing pistons moving to players, and pushing entities out of the way.
+
+use bevy_ecs::prelude::{Commands, Entity, Query, Res, With, Without};
+use ferrumc_core::ai::Mob;
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
+use ferrumc_core::collisions::bounds::CollisionBounds;
+use ferrumc_core::collisions::push::{push_entity, PLAYER_BOUNDS};
+use ferrumc_core::transform::dimension::Dimension;
+use ferrumc_core::transform::pending_teleport::PendingTeleport;
+use ferrumc_core::transform::position::Position;
+use ferrumc_net::connection::StreamWriter;
+use ferrumc_net::packets::outgoing::block_event::BlockEvent;
+use ferrumc_net::packets::outgoing::synchronize_player_position::SynchronizePlayerPositionPacket;
+use ferrumc_net_codec::net_types::network_position::NetworkPosition;
+use ferrumc_net_codec::net_types::var_int::VarInt;
+use ferrumc_state::GlobalStateResource;
+use ferrumc_world::block_id::Direction;
+use ferrumc_world::pistons::PistonEvent;
+use tracing::debug;
+
+use crate::systems::region_edits;
+
+/// Teleport flags making the position, rotation and velocity in a position sync relative, so a
+/// pushed player keeps looking and moving the way they were.
+const RELATIVE: i32 = 0xFF;
+
+type Players<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut Position, &'static ChunkReceiver), Without<Mob>>;
+type Mobs<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Position,
+        &'static CollisionBounds,
+        Option<&'static Dimension>,
+    ),
+    With<Mob>,
+>;
+
+/// Send a block event for every piston that moved during the world tick to the players in its
+/// dimension, followed by the blocks it changed, and push the entities in the way of the moving
+/// blocks.
+pub fn run_piston_events(
+    query: Query<(Entity, &StreamWriter, &ChunkReceiver)>,
+    mut players: Players,
+    mut mobs: Mobs,
+    mut commands: Commands,
+    state: Res<GlobalStateResource>,
+) {
+    for event in state.0.world.take_piston_events() {
+        match event {
+            PistonEvent::Moved {
+                pos: (x, y, z),
+                facing,
+                extending,
+                sticky,
+                direction,
+                moving_to,
+                applied,
+            } => {
+                let packet = BlockEvent::piston(
+                    NetworkPosition::new(x, y as i16, z),
+                    extending,
+                    protocol_direction(facing),
+                    sticky,
+                );
+                for (entity, conn, receiver) in query.iter() {
+                    if !state.0.players.is_connected(entity)
+                        || receiver.last_chunk.2 != applied.dimension
+                    {
+                        continue;
+                    }
+                    if let Err(e) = conn.send_packet_ref(&packet) {
+                        debug!("Failed to send a piston moving to {:?}: {}", entity, e);
+                    }
+                }
+                region_edits::send_changes(&applied, &query, &state);
+                let push = Push {
+                    dimension: &applied.dimension,
+                    moving_to: &moving_to,
+                    offset: direction.offset(),
+                };
+                push.players(&mut players, &query, &mut commands, &state);
+                push.mobs(&mut mobs);
+            }
+            PistonEvent::Landed(applied) => {
+                region_edits::send_changes(&applied, &query, &state);
+            }
+        }
+    }
+}
+
+/// Blocks a piston is moving, which shove the entities in their way a block further.
+struct Push<'a> {
+    dimension: &'a str,
+    moving_to: &'a [(i32, i32, i32)],
+    offset: (i32, i32, i32),
+}
+
+impl Push<'_> {
+    /// Players move themselves, so they're told where they were pushed to and confirm it like
+    /// any other teleport.
+    fn players(
+        &self,
+        players: &mut Players,
+        connections: &Query<(Entity, &StreamWriter, &ChunkReceiver)>,
+        commands: &mut Commands,
+        state: &GlobalStateResource,
+    ) {
+        for (entity, mut position, receiver) in players.iter_mut() {
+            if !state.0.players.is_connected(entity)
+                || receiver.last_chunk.2 != self.dimension
+                || !push_entity(&mut position, &PLAYER_BOUNDS, self.moving_to, self.offset)
+            {
+                continue;
+            }
+            let Ok((_, conn, _)) = connections.get(entity) else {
+                continue;
+            };
+            let teleport_id = VarInt::new((rand::random::<u32>() & 0x3FFF_FFFF) as i32);
+            let (dx, dy, dz) = self.offset;
+            let packet = SynchronizePlayerPositionPacket::new(
+                (dx as f64, dy as f64, dz as f64),
+                (0.0, 0.0, 0.0),
+                0.0,
+                0.0,
+                RELATIVE,
+                teleport_id,
+            );
+            if let Err(e) = conn.send_packet_ref(&packet) {
+                debug!("Failed to push {:?} out of a piston's way: {}", entity, e);
+                continue;
+            }
+            let pushed_to = Position::new(position.x, position.y, position.z);
+            commands
+                .entity(entity)
+                .insert(PendingTeleport::new(teleport_id.0, pushed_to));
+        }
+    }
+
+    /// Mobs without a dimension of their own live in the overworld. Players see them move with
+    /// the next position sync every mob gets.
+    fn mobs(&self, mobs: &mut Mobs) {
+        for (mut position, bounds, dimension) in mobs.iter_mut() {
+            let dimension = dimension.map_or("overworld", |dimension| dimension.0.as_str());
+            if dimension == self.dimension {
+                push_entity(&mut position, bounds, self.moving_to, self.offset);
+            }
+        }
+    }
+}
+
+/// The id block events use for a direction.
+fn protocol_direction(direction: Direction) -> u8 {
+    match direction {
+        Direction::Down => 0,
+        Direction::Up => 1,
+        Direction::North => 2,
+        Direction::South => 3,
+        Direction::West => 4,
+        Direction::East => 5,
+    }
+}
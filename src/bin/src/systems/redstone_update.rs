@@ -1,26 +1,16 @@
-use bevy_ecs::prelude::{ResMut, Resource};
-use std::collections::VecDeque;
+use bevy_ecs::prelude::{Entity, Query, Res};
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
+use ferrumc_net::connection::StreamWriter;
+use ferrumc_state::GlobalStateResource;
 
-#[derive(Default, Resource)]
-pub struct RedstoneScheduler {
-    pub queue: VecDeque<RedstoneUpdate>,
-}
-
-#[derive(Clone)]
-pub struct RedstoneUpdate {
-    pub position: (i32, i32, i32),
-    pub delay: u8,
-}
+use crate::systems::region_edits;
 
-pub fn run_redstone_updates(mut scheduler: ResMut<RedstoneScheduler>) {
-    let scheduler = &mut *scheduler;
-    let mut i = 0;
-    while i < scheduler.queue.len() {
-        if scheduler.queue[i].delay == 0 {
-            scheduler.queue.remove(i);
-        } else {
-            scheduler.queue[i].delay -= 1;
-            i += 1;
-        }
+/// Send the blocks redstone changed during the world tick to every player in the same dimension.
+pub fn run_redstone_updates(
+    query: Query<(Entity, &StreamWriter, &ChunkReceiver)>,
+    state: Res<GlobalStateResource>,
+) {
+    for applied in state.0.world.take_redstone_updates() {
+        region_edits::send_changes(&applied, &query, &state);
     }
 }
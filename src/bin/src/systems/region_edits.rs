@@ -0,0 +1,176 @@
+//! Running `/fill`, `/clone`, `//undo` and `//redo` off the tick.
+//!
+//! Each edit gets a thread of its own that splits the work up on the thread pool. Once it's done,
+//! the changed blocks are picked up by [`broadcast_region_edits`] on the next tick and sent to
+//! every player in the same dimension a section at a time.
+
+use bevy_ecs::prelude::{Entity, Query, Res};
+use crossbeam_channel::{Receiver, Sender};
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
+use ferrumc_net::connection::StreamWriter;
+use ferrumc_net::packets::outgoing::section_blocks_update::SectionBlocksUpdate;
+use ferrumc_state::{GlobalState, GlobalStateResource};
+use ferrumc_text::TextComponent;
+use ferrumc_world::errors::WorldError;
+use ferrumc_world::region_edit::{AppliedEdit, Region, RegionEdit};
+use std::collections::BTreeMap;
+use std::iter;
+use std::sync::LazyLock;
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::systems::chat_message;
+
+/// Edits that have finished, waiting to be sent to the players.
+static FINISHED: LazyLock<(Sender<FinishedEdit>, Receiver<FinishedEdit>)> =
+    LazyLock::new(crossbeam_channel::unbounded);
+
+pub(crate) enum RegionJob {
+    /// `verb` goes in the message the player gets afterwards, e.g. "Filled".
+    Edit {
+        edit: RegionEdit,
+        verb: &'static str,
+    },
+    /// Copying happens in the background too, the blocks are read right before they're placed.
+    Clone {
+        region: Region,
+        offset: (i32, i32, i32),
+    },
+    Undo,
+    Redo,
+}
+
+struct FinishedEdit {
+    /// The player who asked for the edit.
+    sender: Entity,
+    applied: Option<AppliedEdit>,
+    message: String,
+}
+
+/// Start a region edit for a player in the background. Edits and clones happen in `dimension`,
+/// undoing and redoing happen wherever the journaled edit was made.
+pub(crate) fn start_region_job(
+    state: GlobalState,
+    sender: Entity,
+    actor: Uuid,
+    dimension: String,
+    job: RegionJob,
+) -> Result<(), std::io::Error> {
+    std::thread::Builder::new()
+        .name("region_edit".to_string())
+        .spawn(move || {
+            let result = match job {
+                RegionJob::Edit { edit, verb } => state
+                    .world
+                    .edit_region(edit, &dimension, Some(actor), &state.thread_pool)
+                    .map(|applied| {
+                        let message = format!("{verb} {} blocks", applied.changes.len());
+                        (Some(applied), message)
+                    }),
+                RegionJob::Clone { region, offset } => state
+                    .world
+                    .copy_region(&region, offset, &dimension)
+                    .and_then(|edit| {
+                        state
+                            .world
+                            .edit_region(edit, &dimension, Some(actor), &state.thread_pool)
+                    })
+                    .map(|applied| {
+                        let message = format!("Cloned {} blocks", applied.changes.len());
+                        (Some(applied), message)
+                    }),
+                RegionJob::Undo => state
+                    .world
+                    .undo_region_edit(actor, &state.thread_pool)
+                    .map(|applied| finished_message(applied, "Undid", "Nothing to undo")),
+                RegionJob::Redo => state
+                    .world
+                    .redo_region_edit(actor, &state.thread_pool)
+                    .map(|applied| finished_message(applied, "Redid", "Nothing to redo")),
+            };
+            let (applied, message) = result.unwrap_or_else(|e| {
+                error!("Region edit failed: {}", e);
+                (None, failure_message(&e))
+            });
+            let _ = FINISHED.0.send(FinishedEdit {
+                sender,
+                applied,
+                message,
+            });
+        })?;
+    Ok(())
+}
+
+fn finished_message(
+    applied: Option<AppliedEdit>,
+    verb: &str,
+    nothing: &str,
+) -> (Option<AppliedEdit>, String) {
+    match applied {
+        Some(applied) => {
+            let message = format!("{verb} a change of {} blocks", applied.changes.len());
+            (Some(applied), message)
+        }
+        None => (None, nothing.to_string()),
+    }
+}
+
+fn failure_message(error: &WorldError) -> String {
+    match error {
+        WorldError::ChunkNotFound => {
+            "That area isn't fully generated yet, nothing was changed".to_string()
+        }
+        e => format!("The edit failed, nothing was changed: {e}"),
+    }
+}
+
+/// Send the blocks finished edits changed to every player in the same dimension.
+pub fn broadcast_region_edits(
+    query: Query<(Entity, &StreamWriter, &ChunkReceiver)>,
+    state: Res<GlobalStateResource>,
+) {
+    for finished in FINISHED.1.try_iter() {
+        if let Some(applied) = &finished.applied {
+            send_changes(applied, &query, &state);
+        }
+
+        if let Ok((entity, conn, _)) = query.get(finished.sender) {
+            chat_message::broadcast_text(
+                TextComponent::from(finished.message),
+                iter::once((entity, conn)),
+                &state,
+            );
+        }
+    }
+}
+
+/// Group the changed blocks by section and send them to every player in the edit's dimension.
+pub(crate) fn send_changes(
+    applied: &AppliedEdit,
+    query: &Query<(Entity, &StreamWriter, &ChunkReceiver)>,
+    state: &GlobalStateResource,
+) {
+    let mut sections: BTreeMap<(i32, i32, i32), Vec<((u8, u8, u8), u32)>> = BTreeMap::new();
+    for change in &applied.changes {
+        let (x, y, z) = change.position;
+        sections.entry((x >> 4, y >> 4, z >> 4)).or_default().push((
+            ((x & 0xf) as u8, (y & 0xf) as u8, (z & 0xf) as u8),
+            change.new.0,
+        ));
+    }
+    let packets = sections
+        .into_iter()
+        .map(|((x, y, z), blocks)| SectionBlocksUpdate::new(x, y, z, blocks))
+        .collect::<Vec<_>>();
+    for (entity, conn, receiver) in query.iter() {
+        if !state.0.players.is_connected(entity) || receiver.last_chunk.2 != applied.dimension {
+            continue;
+        }
+        for packet in &packets {
+            if let Err(e) = conn.send_packet_ref(packet) {
+                debug!("Failed to send block changes to {:?}: {}", entity, e);
+                break;
+            }
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use crate::errors::BinaryError;
-use bevy_ecs::prelude::Mut;
+use bevy_ecs::prelude::{Entity, Mut, Query, Res};
+use ferrumc_config::server_config::get_global_config;
+use ferrumc_core::chunks::chunk_receiver::ChunkReceiver;
+use ferrumc_core::transform::position::Position;
 use ferrumc_macros::profile;
 use ferrumc_net::compression::compress_packet;
 use ferrumc_net::connection::StreamWriter;
 use ferrumc_net::errors::NetError;
+use ferrumc_net::outbound::Delivery;
 use ferrumc_net::packets::outgoing::chunk_and_light_data::ChunkAndLightData;
 use ferrumc_net::packets::outgoing::chunk_batch_finish::ChunkBatchFinish;
 use ferrumc_net::packets::outgoing::chunk_batch_start::ChunkBatchStart;
@@ -11,17 +15,22 @@ use ferrumc_net::packets::outgoing::set_center_chunk::SetCenterChunk;
 use ferrumc_net_codec::encode::NetEncode;
 use ferrumc_net_codec::encode::NetEncodeOpts::WithLength;
 use ferrumc_net_codec::net_types::var_int::VarInt;
-use ferrumc_state::GlobalState;
+use ferrumc_state::{GlobalState, GlobalStateResource};
 use ferrumc_utils::metrics::CHUNK_STREAM_HISTOGRAM;
+use ferrumc_world::errors::WorldError;
 use std::sync::atomic::Ordering;
 use tracing::{error, trace};
 
+/// Sends chunks to a player, closest to `center_chunk` first.
+///
+/// Chunks the connection can't take yet because the player is falling behind are left in
+/// `recv.needs_reload`, and [`send_pending_chunks`] sends them once it catches up.
 #[profile("chunk_streaming")]
 pub fn send_chunks(
     state: GlobalState,
     mut chunk_coords: Vec<(i32, i32, String)>,
     conn: &mut Mut<StreamWriter>,
-    // recv: &mut Mut<ChunkReceiver>,
+    recv: &mut Mut<ChunkReceiver>,
     center_chunk: (i32, i32),
 ) -> Result<(), BinaryError> {
     let _timer = CHUNK_STREAM_HISTOGRAM.start_timer();
@@ -37,6 +46,11 @@ pub fn send_chunks(
     let center_chunk_packet = SetCenterChunk::new(center_x, center_z);
     conn.send_packet_ref(&center_chunk_packet)?;
 
+    if conn.is_backed_up() {
+        recv.needs_reload.extend(chunk_coords);
+        return Ok(());
+    }
+
     let batch_start_packet = ChunkBatchStart {};
     conn.send_packet_ref(&batch_start_packet)?;
 
@@ -62,11 +76,16 @@ pub fn send_chunks(
             } else {
                 trace!("Generating chunk {}x{} in dimension {}", x, z, dim);
                 // Don't bother saving the chunk if it hasn't been edited yet
-                let biome = state_clone.terrain_generator.biome_at(x, z);
-                let chunk = state_clone
+                let dimension = state_clone
+                    .world
+                    .dimensions()
+                    .get(&dim)
+                    .ok_or_else(|| WorldError::UnknownDimension(dim.clone()))?;
+                let mut chunk = state_clone
                     .terrain_generator
-                    .generate_chunk_for_biome(x, z, biome)
+                    .generate_chunk(x, z, &dimension)
                     .map_err(|err| NetError::Misc(err.to_string()))?;
+                state_clone.world.light_new_chunk(&mut chunk);
                 Ok((ChunkAndLightData::from_chunk(&chunk), x, z))
             }?;
             match packet {
@@ -74,13 +93,13 @@ pub fn send_chunks(
                     if is_compressed {
                         // Compress the packet if compression is enabled
                         let compressed_packet = compress_packet(&packet, true, &WithLength)?;
-                        Ok((compressed_packet, x, z))
+                        Ok((compressed_packet, x, z, dim))
                     } else {
                         let mut buffer = Vec::new();
                         packet
                             .encode(&mut buffer, &WithLength)
                             .map_err(|e| NetError::Misc(e.to_string()))?;
-                        Ok((buffer, x, z))
+                        Ok((buffer, x, z, dim))
                     }
                 }
                 Err(e) => {
@@ -95,10 +114,19 @@ pub fn send_chunks(
 
     for packet in packets {
         match packet {
-            Ok((packet, x, z)) => {
+            Ok((packet, x, z, dim)) => {
                 trace!("Sending chunk data for chunk at coordinates ({}, {})", x, z);
-                conn.send_raw_packet(packet)?;
-                chunks_sent += 1;
+                match conn.send_raw_packet_as(packet, Delivery::Deferrable) {
+                    Ok(()) => {
+                        recv.seen.insert((x, z, dim));
+                        chunks_sent += 1;
+                    }
+                    // Try again once the player has caught up
+                    Err(NetError::Backpressure) => {
+                        recv.needs_reload.insert((x, z, dim));
+                    }
+                    Err(err) => return Err(err.into()),
+                }
             }
             Err(e) => {
                 error!("Unexpected error while processing chunk: {:?}", e);
@@ -114,3 +142,31 @@ pub fn send_chunks(
 
     Ok(())
 }
+
+/// Sends the chunks held back while players were falling behind, once they've caught up.
+pub fn send_pending_chunks(
+    mut query: Query<(Entity, &mut StreamWriter, &mut ChunkReceiver, &Position)>,
+    state: Res<GlobalStateResource>,
+) {
+    let radius = get_global_config().chunk_render_distance as i32;
+    for (entity, mut conn, mut recv, position) in query.iter_mut() {
+        if recv.needs_reload.is_empty() || conn.is_backed_up() {
+            continue;
+        }
+        if !state.0.players.is_connected(entity) {
+            continue;
+        }
+        let center = (
+            (position.x.floor() as i32) >> 4,
+            (position.z.floor() as i32) >> 4,
+        );
+        // Leave out the ones the player has moved away from in the meantime
+        let pending = std::mem::take(&mut recv.needs_reload)
+            .into_iter()
+            .filter(|(x, z, _)| (x - center.0).abs() <= radius && (z - center.1).abs() <= radius)
+            .collect();
+        if let Err(err) = send_chunks(state.0.clone(), pending, &mut conn, &mut recv, center) {
+            error!("Failed to send pending chunks to {:?}: {:?}", entity, err);
+        }
+    }
+}
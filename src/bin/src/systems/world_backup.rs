@@ -0,0 +1,52 @@
+use bevy_ecs::prelude::{Res, ResMut};
+use ferrumc_config::server_config::get_global_config;
+use ferrumc_core::chunks::world_backup_tracker::WorldBackupTracker;
+use ferrumc_general_purpose::paths::get_root_path;
+use ferrumc_state::GlobalStateResource;
+use ferrumc_world::backups::prune_backups;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tracing::{error, info};
+
+pub fn backup_world(state: Res<GlobalStateResource>, mut tracker: ResMut<WorldBackupTracker>) {
+    let config = &get_global_config().backups;
+    if !config.enabled || state.0.shut_down.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if tracker.last_backup.elapsed().as_secs() < config.interval * 60 {
+        return;
+    }
+    // Skip this one if the last backup still hasn't finished
+    if tracker.in_progress.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    tracker.last_backup = std::time::Instant::now();
+
+    // Backups can take a while on big worlds, so do them in the thread pool. The copy runs in its
+    // own read transaction, so the world can keep being saved while it happens.
+    let _handle = state.0.thread_pool.oneshot({
+        let state = state.0.clone();
+        let in_progress = tracker.in_progress.clone();
+        move || {
+            let backup_dir = get_root_path().join(&config.path);
+            info!("Backing up world...");
+            match state.world.backup(&backup_dir, config.compact) {
+                Ok(path) => info!("World backed up to {}", path.display()),
+                Err(e) => error!("Failed to back up world: {}", e),
+            }
+            match prune_backups(
+                &backup_dir,
+                config.max_backups,
+                Duration::from_secs(config.max_age * 60 * 60),
+            ) {
+                Ok(pruned) if !pruned.is_empty() => {
+                    info!("Deleted {} old backups", pruned.len())
+                }
+                Ok(_) => {}
+                Err(e) => error!("Failed to delete old backups: {}", e),
+            }
+            in_progress.store(false, Ordering::Release);
+        }
+    });
+}
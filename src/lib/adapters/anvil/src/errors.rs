@@ -19,6 +19,10 @@ pub enum AnvilError {
     MissingChecksum,
     #[error("Cannot decompress data (probably invalid)")]
     DecompressionError,
+    #[error("Unable to write file {0}: {1}")]
+    UnableToWriteFile(PathBuf, std::io::Error),
+    #[error("Cannot compress data: {0}")]
+    CompressionError(String),
 }
 
 impl From<lzzzz::Error> for AnvilError {
@@ -1,4 +1,5 @@
 pub mod errors;
+pub mod writer;
 
 use crate::errors::AnvilError;
 use memmap2::Mmap;
@@ -155,6 +156,10 @@ impl LoadedAnvilFile {
     /// fails, the compression type is unknown, the checksum is missing, the checksum is invalid,
     /// or the decompression fails.
     pub fn get_chunk_from_location(&self, location: u32) -> Result<Option<Vec<u8>>, AnvilError> {
+        // An empty location means the chunk hasn't been generated
+        if location == 0 {
+            return Ok(None);
+        }
         let offset = (location >> 8) & 0xFFFFFF;
         if u64::from(offset) * 4096 >= u64::from(u32::MAX) {
             error!("Invalid offset: {}", offset);
@@ -163,7 +168,14 @@ impl LoadedAnvilFile {
         let offset = offset * 4096;
         let size = (location & 0xFF) * 4096;
         let chunk_data = self.get_data_from_file(offset, size)?;
-        let chunk_compressed_data = &chunk_data[5..];
+        if chunk_data.len() < 5 {
+            return Err(AnvilError::InvalidOffsetOrSize);
+        }
+        // The length covers the compression type and the data, anything after it is padding
+        let length =
+            u32::from_be_bytes([chunk_data[0], chunk_data[1], chunk_data[2], chunk_data[3]]);
+        let end = (4 + length as usize).min(chunk_data.len());
+        let chunk_compressed_data = &chunk_data[5..end.max(5)];
         let compression_type = chunk_data[4];
 
         match compression_type {
@@ -209,8 +221,7 @@ impl LoadedAnvilFile {
     /// This function will return the decompressed chunk data, or an error if the data reading
     /// fails for any reason.
     pub fn get_chunk(&self, x: u32, z: u32) -> Result<Option<Vec<u8>>, AnvilError> {
-        let index = u64::from(4 * ((x & 31) + (z & 31) * 32));
-        let base_index = index as usize * 4;
+        let base_index = ((x & 31) + (z & 31) * 32) as usize * 4;
         let chunk_data = [
             u32::from(self.table[base_index]),
             u32::from(self.table[base_index + 1]),
@@ -0,0 +1,266 @@
+use crate::errors::AnvilError;
+use flate2::write::GzEncoder;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use yazi::CompressionLevel;
+
+/// Size of a single sector in a region file. Both header tables take up one sector each, and
+/// every chunk is padded out to a whole number of sectors.
+pub const SECTOR_SIZE: usize = 4096;
+
+/// The largest number of sectors a chunk can take up in the region file itself, since the size
+/// is stored in a single byte of the location. Anything bigger goes into a `.mcc` file next to
+/// the region.
+const MAX_INLINE_SECTORS: usize = 255;
+
+/// Flag set on the compression type when the chunk data is stored in an external `.mcc` file.
+const EXTERNAL_FLAG: u8 = 0x80;
+
+/// The compression types that can be written to a region file.
+///
+/// LZ4 can be read but not written, since 1.20.1 doesn't know how to load it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionType {
+    Gzip,
+    #[default]
+    Zlib,
+    None,
+}
+
+impl CompressionType {
+    /// The id stored in front of the chunk data, see
+    /// [`LoadedAnvilFile::get_chunk_from_location`](crate::LoadedAnvilFile::get_chunk_from_location)
+    pub fn id(&self) -> u8 {
+        match self {
+            CompressionType::Gzip => 1,
+            CompressionType::Zlib => 2,
+            CompressionType::None => 3,
+        }
+    }
+
+    /// Compress the raw chunk NBT with this compression type
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, AnvilError> {
+        match self {
+            CompressionType::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder
+                    .write_all(data)
+                    .map_err(|e| AnvilError::CompressionError(e.to_string()))?;
+                encoder
+                    .finish()
+                    .map_err(|e| AnvilError::CompressionError(e.to_string()))
+            }
+            CompressionType::Zlib => {
+                yazi::compress(data, yazi::Format::Zlib, CompressionLevel::Default)
+                    .map_err(|e| AnvilError::CompressionError(format!("{e:?}")))
+            }
+            CompressionType::None => Ok(data.to_vec()),
+        }
+    }
+}
+
+struct PendingChunk {
+    x: i32,
+    z: i32,
+    data: Vec<u8>,
+    timestamp: u32,
+}
+
+/// Builds a single region file in memory and writes it out in one go.
+///
+/// Chunks are compressed as they are inserted, so the writer only ever holds the compressed data.
+/// The sectors are laid out in the order of the location table, the same as a freshly optimised
+/// region file from the vanilla server.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ferrumc_anvil::writer::{CompressionType, RegionWriter};
+/// use std::path::PathBuf;
+///
+/// let mut writer = RegionWriter::new(CompressionType::Zlib);
+/// writer.insert(0, 0, &[10, 0, 0, 0], 0).unwrap();
+/// writer.write(PathBuf::from("r.0.0.mca")).unwrap();
+/// ```
+pub struct RegionWriter {
+    compression: CompressionType,
+    chunks: Vec<Option<PendingChunk>>,
+}
+
+impl RegionWriter {
+    pub fn new(compression: CompressionType) -> Self {
+        RegionWriter {
+            compression,
+            chunks: (0..1024).map(|_| None).collect(),
+        }
+    }
+
+    /// Compress and add a chunk to the region, replacing any chunk already at that position
+    ///
+    /// Arguments:
+    ///
+    /// * `x` - The chunk x coordinate, only the lowest 5 bits are used to place it in the region
+    /// * `z` - The chunk z coordinate, only the lowest 5 bits are used to place it in the region
+    /// * `data` - The uncompressed chunk NBT
+    /// * `timestamp` - The last modification time, in seconds since the epoch
+    pub fn insert(
+        &mut self,
+        x: i32,
+        z: i32,
+        data: &[u8],
+        timestamp: u32,
+    ) -> Result<(), AnvilError> {
+        let index = ((x & 31) + (z & 31) * 32) as usize;
+        self.chunks[index] = Some(PendingChunk {
+            x,
+            z,
+            data: self.compression.compress(data)?,
+            timestamp,
+        });
+        Ok(())
+    }
+
+    /// The number of chunks in the region
+    pub fn len(&self) -> usize {
+        self.chunks.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Serialize the region into the bytes of a `.mca` file
+    ///
+    /// Chunks too big to fit in 255 sectors are returned separately along with their coordinates,
+    /// they need to be written to `c.<x>.<z>.mcc` files next to the region file.
+    fn build(&self) -> Result<(Vec<u8>, Vec<(i32, i32, &[u8])>), AnvilError> {
+        let mut buffer = vec![0u8; SECTOR_SIZE * 2];
+        let mut external = Vec::new();
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let Some(chunk) = chunk else {
+                continue;
+            };
+
+            let offset = buffer.len() / SECTOR_SIZE;
+            // 4 bytes of length, then the compression type, then the data
+            let sectors = (chunk.data.len() + 5).div_ceil(SECTOR_SIZE);
+            if sectors > MAX_INLINE_SECTORS {
+                buffer.extend_from_slice(&1u32.to_be_bytes());
+                buffer.push(self.compression.id() | EXTERNAL_FLAG);
+                external.push((chunk.x, chunk.z, chunk.data.as_slice()));
+            } else {
+                buffer.extend_from_slice(&(chunk.data.len() as u32 + 1).to_be_bytes());
+                buffer.push(self.compression.id());
+                buffer.extend_from_slice(&chunk.data);
+            }
+            buffer.resize(buffer.len().next_multiple_of(SECTOR_SIZE), 0);
+
+            let sectors = buffer.len() / SECTOR_SIZE - offset;
+            if offset > 0xFFFFFF {
+                return Err(AnvilError::InvalidOffsetOrSize);
+            }
+            let location = ((offset as u32) << 8) | sectors as u32;
+            buffer[index * 4..index * 4 + 4].copy_from_slice(&location.to_be_bytes());
+            buffer[SECTOR_SIZE + index * 4..SECTOR_SIZE + index * 4 + 4]
+                .copy_from_slice(&chunk.timestamp.to_be_bytes());
+        }
+
+        Ok((buffer, external))
+    }
+
+    /// Write the region to the given path, along with any `.mcc` files for oversized chunks
+    ///
+    /// Any existing file at the path will be overwritten.
+    pub fn write(&self, file_path: PathBuf) -> Result<(), AnvilError> {
+        let (buffer, external) = self.build()?;
+        let dir = file_path.parent().unwrap_or(Path::new("."));
+        for (x, z, data) in external {
+            let external_path = dir.join(format!("c.{x}.{z}.mcc"));
+            std::fs::write(&external_path, data)
+                .map_err(|e| AnvilError::UnableToWriteFile(external_path, e))?;
+        }
+        std::fs::write(&file_path, buffer).map_err(|e| AnvilError::UnableToWriteFile(file_path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_anvil_file;
+    use fastanvil::Region;
+    use ferrumc_utils::root;
+    use std::fs::File;
+
+    fn temp_region(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("ferrumc_anvil_tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = load_anvil_file(PathBuf::from(root!(".etc/r.0.0.mca"))).unwrap();
+        let path = temp_region("r.round_trip.mca");
+
+        for compression in [
+            CompressionType::Gzip,
+            CompressionType::Zlib,
+            CompressionType::None,
+        ] {
+            let mut writer = RegionWriter::new(compression);
+            for (x, z) in [(0, 0), (5, 3), (31, 31)] {
+                let chunk = source.get_chunk(0, 0).unwrap().unwrap();
+                writer.insert(x, z, &chunk, 1234).unwrap();
+            }
+            writer.write(path.clone()).unwrap();
+
+            let written = load_anvil_file(path.clone()).unwrap();
+            assert_eq!(written.get_locations().len(), 3);
+            let expected = source.get_chunk(0, 0).unwrap();
+            assert_eq!(written.get_chunk(5, 3).unwrap(), expected);
+            assert_eq!(written.get_chunk(31, 31).unwrap(), expected);
+
+            if compression != CompressionType::None {
+                let mut fast_file = Region::from_stream(File::open(&path).unwrap()).unwrap();
+                assert_eq!(fast_file.read_chunk(5, 3).unwrap(), expected);
+                assert!(fast_file.read_chunk(1, 1).unwrap().is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sector_layout() {
+        let mut writer = RegionWriter::new(CompressionType::None);
+        writer.insert(1, 0, &[0; 5000], 42).unwrap();
+        writer.insert(0, 0, &[0; 10], 7).unwrap();
+        let (buffer, external) = writer.build().unwrap();
+
+        assert!(external.is_empty());
+        assert_eq!(buffer.len() % SECTOR_SIZE, 0);
+        // First chunk in the table goes straight after the header
+        assert_eq!(&buffer[0..4], &[0, 0, 2, 1]);
+        assert_eq!(&buffer[4..8], &[0, 0, 3, 2]);
+        assert_eq!(&buffer[SECTOR_SIZE..SECTOR_SIZE + 4], &7u32.to_be_bytes());
+        assert_eq!(
+            &buffer[SECTOR_SIZE + 4..SECTOR_SIZE + 8],
+            &42u32.to_be_bytes()
+        );
+        assert_eq!(buffer.len(), SECTOR_SIZE * 5);
+    }
+
+    #[test]
+    fn test_oversized_chunk() {
+        let mut writer = RegionWriter::new(CompressionType::None);
+        writer.insert(3, 4, &vec![1; SECTOR_SIZE * 256], 0).unwrap();
+        let (buffer, external) = writer.build().unwrap();
+
+        assert_eq!(external.len(), 1);
+        assert_eq!((external[0].0, external[0].1), (3, 4));
+        assert_eq!(buffer.len(), SECTOR_SIZE * 3);
+        assert_eq!(
+            buffer[SECTOR_SIZE * 2 + 4],
+            CompressionType::None.id() | EXTERNAL_FLAG
+        );
+    }
+}
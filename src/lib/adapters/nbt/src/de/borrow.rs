@@ -45,7 +45,7 @@ impl From<u8> for NbtTag {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum NbtTapeElement<'a> {
     End,
     Byte(i8),
@@ -197,6 +197,27 @@ impl<'a> NbtTape<'a> {
         res.flatten()
     }
 
+    /// Serializes an element of this tape back into NBT, e.g. to keep a compound around as-is
+    /// without a struct to read it into.
+    pub fn serialize_element(
+        &self,
+        element: &NbtTapeElement<'a>,
+        opts: &NBTSerializeOptions,
+    ) -> Vec<u8> {
+        // Lists are read back from the data, so they need a tape of their own to move around in
+        let mut tape = NbtTape {
+            data: self.data,
+            pos: 0,
+            depth: 0,
+            root: None,
+        };
+        let mut buf = Vec::new();
+        element
+            .serialize_as_network(&mut tape, &mut buf, opts)
+            .expect("Writing to a Vec can't fail");
+        buf
+    }
+
     pub fn unpack_list<T: FromNbt<'a>>(&self, element: &NbtTapeElement<'a>) -> Option<Vec<T>> {
         match element {
             NbtTapeElement::List {
@@ -217,7 +238,7 @@ impl<'a> NbtTape<'a> {
                         NbtDeserializableOptions::TagType(el_type.clone()),
                     );
 
-                    let element = T::from_nbt(&tape, &nbt_element).unwrap();
+                    let element = T::from_nbt(&tape, &nbt_element).ok()?;
 
                     elements.push(element);
                 }
@@ -708,7 +729,10 @@ impl NbtTapeElement<'_> {
                         tape,
                         NbtDeserializableOptions::TagType(el_type.clone()),
                     );
+                    // Lists inside the element move the tape to their own elements
+                    let next = tape.pos;
                     element.serialize_as_network(tape, writer, &NBTSerializeOptions::None)?;
+                    tape.pos = next;
                 }
 
                 Ok(())
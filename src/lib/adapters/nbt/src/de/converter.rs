@@ -90,6 +90,12 @@ mod primitives {
         }
     }
 
+    impl<'a> FromNbt<'a> for NbtTapeElement<'a> {
+        fn from_nbt(_tapes: &NbtTape<'a>, element: &NbtTapeElement<'a>) -> Result<Self> {
+            Ok(element.clone())
+        }
+    }
+
     // optional
     impl<'a, T: FromNbt<'a>> FromNbt<'a> for Option<T> {
         fn from_nbt(tapes: &NbtTape<'a>, element: &NbtTapeElement<'a>) -> Result<Self> {
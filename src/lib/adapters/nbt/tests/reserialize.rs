@@ -0,0 +1,57 @@
+use ferrumc_macros::NBTSerialize;
+use ferrumc_nbt::{FromNbt, NBTSerializeOptions, NbtTape, NbtTapeElement};
+
+#[test]
+fn reserialize_nested_compound() {
+    #[derive(NBTSerialize)]
+    struct Inner {
+        name: String,
+        values: Vec<i16>,
+        longs: Vec<i64>,
+    }
+
+    #[derive(NBTSerialize)]
+    struct Outer {
+        id: i32,
+        inner: Inner,
+        list: Vec<Inner>,
+    }
+
+    let outer = Outer {
+        id: 7,
+        inner: Inner {
+            name: "a".to_string(),
+            values: vec![1, 2, 3],
+            longs: vec![4, 5],
+        },
+        // The list inside the first element has to be stepped over to get to the second one
+        list: vec![
+            Inner {
+                name: "b".to_string(),
+                values: vec![8, 9],
+                longs: vec![6],
+            },
+            Inner {
+                name: "c".to_string(),
+                values: vec![],
+                longs: vec![],
+            },
+        ],
+    };
+    let buf = outer.serialize_with_header();
+
+    let mut tape = NbtTape::new(&buf);
+    tape.parse();
+    let root = tape.root.as_ref().map(|(_, root)| root).unwrap();
+
+    // The whole root written back out is byte for byte the same
+    let rewritten = tape.serialize_element(root, &NBTSerializeOptions::WithHeader("Outer"));
+    assert_eq!(rewritten, buf);
+
+    let list = root.get("list").unwrap();
+    let elements: Vec<NbtTapeElement> = FromNbt::from_nbt(&tape, list).unwrap();
+    let name: String = FromNbt::from_nbt(&tape, elements[0].get("name").unwrap()).unwrap();
+    assert_eq!(name, "b");
+    let name: String = FromNbt::from_nbt(&tape, elements[1].get("name").unwrap()).unwrap();
+    assert_eq!(name, "c");
+}
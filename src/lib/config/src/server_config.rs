@@ -32,12 +32,15 @@ pub fn set_global_config(config: ServerConfig) {
 /// - `max_players`: The maximum number of players that can be connected to the server.
 /// - `tps`: The ticks per second that the server will run at.
 /// - `database` - [DatabaseConfig]: The configuration for the database.
+/// - `backups` - [BackupConfig]: The configuration for automatic world backups.
 /// - `world`: The name of the world that the server will load.
 /// - `network_compression_threshold`: The threshold at which the server will compress network packets.
 /// - `whitelist`: Whether the server whitelist is enabled or not.
 /// - `online_mode`: Whether the server should authenticate players or run in offline mode.
 /// - `chunk_render_distance`: The render distance of the chunks. This is the number of chunks that will be
 ///   loaded around the player.
+/// - `dimensions` - [DimensionConfig]: Extra dimensions to host alongside the overworld, nether and end.
+/// - `outbound_queue` - [OutboundQueueConfig]: Limits on the data waiting to be sent to each client.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ServerConfig {
     pub host: String,
@@ -46,6 +49,8 @@ pub struct ServerConfig {
     pub max_players: u32,
     pub tps: u32,
     pub database: DatabaseConfig,
+    #[serde(default)]
+    pub backups: BackupConfig,
     pub world: String,
     pub network_compression_threshold: i32, // Can be negative
     pub verify_decompressed_packets: bool,
@@ -53,6 +58,10 @@ pub struct ServerConfig {
     #[serde(default = "default_online_mode")]
     pub online_mode: bool,
     pub chunk_render_distance: u32,
+    #[serde(default)]
+    pub dimensions: Vec<DimensionConfig>,
+    #[serde(default)]
+    pub outbound_queue: OutboundQueueConfig,
 }
 
 const fn default_online_mode() -> bool {
@@ -68,12 +77,15 @@ impl Default for ServerConfig {
             max_players: Default::default(),
             tps: Default::default(),
             database: Default::default(),
+            backups: Default::default(),
             world: Default::default(),
             network_compression_threshold: Default::default(),
             verify_decompressed_packets: Default::default(),
             whitelist: Default::default(),
             online_mode: default_online_mode(),
             chunk_render_distance: Default::default(),
+            dimensions: Default::default(),
+            outbound_queue: Default::default(),
         }
     }
 }
@@ -81,6 +93,8 @@ impl Default for ServerConfig {
 /// The database configuration section from [ServerConfig].
 ///
 /// Fields:
+/// - `backend`: Which database to store the world in. Options are `lmdb`, `redb` and `memory`.
+///   `memory` doesn't save anything and is only meant for testing.
 /// - `db_path`: The path to the database. This is relative to the server root path.
 /// - `verify_chunk_data`: Whether to verify chunk data when loading it from the database.
 /// - `map_size`: The max size of the database's memory map. Basically you need this to be big enough
@@ -89,13 +103,168 @@ impl Default for ServerConfig {
 ///   but it won't actually use that much memory, it'll just show up as virtual memory use.
 /// - `cache_ttl`: The time to live for cache entries in seconds.
 /// - `cache_capacity`: How big the cache can be in kb.
-#[derive(Debug, Deserialize, Serialize, Default)]
+/// - `flush_interval`: The longest a changed chunk waits before it's written to the database, in
+///   milliseconds. Changes are batched up in memory until then. 0 writes every change straight
+///   away.
+/// - `flush_batch_size`: How many changed chunks to let pile up before writing them early.
+/// - `compression`: Which compression algorithm to use for chunks. Options are `brotli`, `deflate`,
+///   `gzip`, `zlib` and `zstd`.
+/// - `compression_level`: The compression level to use. How high this can go depends on the
+///   algorithm, zstd goes up to 22, brotli up to 11 and the rest up to 9.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct DatabaseConfig {
+    #[serde(default = "default_backend")]
+    pub backend: String,
     pub db_path: String,
     pub verify_chunk_data: bool,
     pub map_size: u64,
     pub cache_ttl: u64,
     pub cache_capacity: u64,
+    #[serde(default = "default_flush_interval")]
+    pub flush_interval: u64,
+    #[serde(default = "default_flush_batch_size")]
+    pub flush_batch_size: usize,
+    #[serde(default = "default_compression")]
+    pub compression: String,
+    #[serde(default = "default_compression_level")]
+    pub compression_level: u32,
+}
+
+fn default_backend() -> String {
+    "lmdb".to_string()
+}
+
+const fn default_flush_interval() -> u64 {
+    1000
+}
+
+const fn default_flush_batch_size() -> usize {
+    256
+}
+
+fn default_compression() -> String {
+    "zlib".to_string()
+}
+
+const fn default_compression_level() -> u32 {
+    1
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            backend: default_backend(),
+            db_path: Default::default(),
+            verify_chunk_data: Default::default(),
+            map_size: Default::default(),
+            cache_ttl: Default::default(),
+            cache_capacity: Default::default(),
+            flush_interval: default_flush_interval(),
+            flush_batch_size: default_flush_batch_size(),
+            compression: default_compression(),
+            compression_level: default_compression_level(),
+        }
+    }
+}
+
+/// The backup configuration section from [ServerConfig].
+///
+/// Fields:
+/// - `enabled`: Whether to take backups of the world database while the server is running.
+/// - `path`: The folder to keep backups in. This is relative to the server root path.
+/// - `interval`: How often to take a backup, in minutes.
+/// - `max_backups`: How many backups to keep before the oldest are deleted. 0 keeps them all.
+/// - `max_age`: How old a backup can get before it's deleted, in hours. 0 keeps them forever.
+/// - `compact`: Whether to leave free pages out of the backups. This makes them smaller but
+///   slower to take.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub enabled: bool,
+    pub path: String,
+    pub interval: u64,
+    pub max_backups: usize,
+    pub max_age: u64,
+    pub compact: bool,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "backups".to_string(),
+            interval: 60,
+            max_backups: 24,
+            max_age: 0,
+            compact: true,
+        }
+    }
+}
+
+/// The outbound queue configuration section from [ServerConfig].
+///
+/// Fields:
+/// - `soft_limit`: How many bytes can wait to be sent to a client before it counts as falling
+///   behind. Past this, packets that don't matter much like animations are dropped, and chunk
+///   streaming waits for the queue to drain.
+/// - `hard_limit`: How many bytes can wait to be sent to a client at most.
+/// - `overflow_policy`: What to do with a client whose queue is full. `kick` disconnects it, `drop`
+///   throws away packets that don't fit, which can leave the client out of sync.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OutboundQueueConfig {
+    pub soft_limit: usize,
+    pub hard_limit: usize,
+    pub overflow_policy: String,
+}
+
+impl Default for OutboundQueueConfig {
+    fn default() -> Self {
+        Self {
+            soft_limit: 4 * 1024 * 1024,
+            hard_limit: 32 * 1024 * 1024,
+            overflow_policy: "kick".to_string(),
+        }
+    }
+}
+
+/// A custom dimension from [ServerConfig].
+///
+/// Fields:
+/// - `name`: The name the dimension is referred to by, e.g. `lobby`. Clients see it as
+///   `ferrumc:<name>` unless it has a namespace of its own.
+/// - `height`: How many blocks tall the dimension is. Must be a multiple of 16.
+/// - `min_y`: The lowest y level in the dimension. Must be a multiple of 16.
+/// - `has_skylight`: Whether the sky lights up the dimension.
+/// - `generator`: Which terrain generator to use for new chunks. Options are `overworld`,
+///   `nether` and `end`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DimensionConfig {
+    pub name: String,
+    #[serde(default = "default_dimension_height")]
+    pub height: u32,
+    #[serde(default = "default_dimension_min_y")]
+    pub min_y: i32,
+    #[serde(default = "default_has_skylight")]
+    pub has_skylight: bool,
+    #[serde(default = "default_generator")]
+    pub generator: String,
+}
+
+const fn default_dimension_height() -> u32 {
+    384
+}
+
+const fn default_dimension_min_y() -> i32 {
+    -64
+}
+
+const fn default_has_skylight() -> bool {
+    true
+}
+
+fn default_generator() -> String {
+    "overworld".to_string()
 }
 
 fn create_config() -> ServerConfig {
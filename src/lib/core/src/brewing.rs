@@ -9,11 +9,11 @@ pub struct BrewingStand {
 }
 
 fn blaze_fuel_value(stack: &ItemStack) -> i16 {
-    stack
-        .item
-        .to_block_data()
-        .map(|data| if data.name.contains("blaze_powder") { 20 } else { 0 })
-        .unwrap_or(0)
+    if stack.item.name() == Some("minecraft:blaze_powder") {
+        20
+    } else {
+        0
+    }
 }
 
 fn brew_result(potion: &ItemStack, _ingredient: &ItemStack) -> ItemStack {
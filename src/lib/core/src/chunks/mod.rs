@@ -1,4 +1,5 @@
 pub mod block_break_progress;
 pub mod chunk_receiver;
 pub mod cross_chunk_boundary_event;
+pub mod world_backup_tracker;
 pub mod world_sync_tracker;
@@ -0,0 +1,12 @@
+use bevy_ecs::prelude::Resource;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Resource)]
+pub struct WorldBackupTracker {
+    pub last_backup: Instant,
+    /// Set while a backup is being taken, so a slow backup doesn't get a second one started on
+    /// top of it.
+    pub in_progress: Arc<AtomicBool>,
+}
@@ -11,9 +11,9 @@ pub fn block_bounds(chunk: &Chunk, x: i32, y: i32, z: i32) -> Option<CollisionBo
     if block == BlockId::default() {
         return None;
     }
-    let data = block.to_block_data().unwrap_or_default();
+    let name = block.name().unwrap_or_default();
     // Treat water and other fluids as non-solid for collision purposes.
-    if data.name.contains("water") || data.name.contains("lava") {
+    if name.contains("water") || name.contains("lava") {
         return None;
     }
     Some(CollisionBounds {
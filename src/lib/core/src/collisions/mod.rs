@@ -1,3 +1,4 @@
 pub mod block;
 pub mod bounds;
+pub mod push;
 pub mod world;
@@ -0,0 +1,84 @@
+use crate::collisions::bounds::CollisionBounds;
+use crate::transform::position::Position;
+
+/// The space a block takes up.
+pub const BLOCK_BOUNDS: CollisionBounds = CollisionBounds {
+    x_offset_start: 0.0,
+    x_offset_end: 1.0,
+    y_offset_start: 0.0,
+    y_offset_end: 1.0,
+    z_offset_start: 0.0,
+    z_offset_end: 1.0,
+};
+
+/// The space a player takes up. Players don't get [`CollisionBounds`] of their own.
+pub const PLAYER_BOUNDS: CollisionBounds = CollisionBounds {
+    x_offset_start: -0.3,
+    x_offset_end: 0.3,
+    y_offset_start: 0.0,
+    y_offset_end: 1.8,
+    z_offset_start: -0.3,
+    z_offset_end: 0.3,
+};
+
+/// Move an entity a block along `offset` if it's in the way of any of the blocks moving to
+/// `moving_to`, like a piston shoves it. Returns whether it moved.
+pub fn push_entity(
+    position: &mut Position,
+    bounds: &CollisionBounds,
+    moving_to: &[(i32, i32, i32)],
+    offset: (i32, i32, i32),
+) -> bool {
+    let in_the_way = moving_to.iter().any(|&(x, y, z)| {
+        bounds.collides(
+            (position.x, position.y, position.z),
+            &BLOCK_BOUNDS,
+            (x as f64, y as f64, z as f64),
+        )
+    });
+    if in_the_way {
+        position.x += offset.0 as f64;
+        position.y += offset.1 as f64;
+        position.z += offset.2 as f64;
+    }
+    in_the_way
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_in_the_way_are_pushed_along() {
+        let mut position = Position::new(2.5, 64.0, 0.5);
+        assert!(push_entity(
+            &mut position,
+            &PLAYER_BOUNDS,
+            &[(1, 64, 0), (2, 64, 0)],
+            (1, 0, 0)
+        ));
+        assert_eq!((position.x, position.y, position.z), (3.5, 64.0, 0.5));
+
+        // Standing next to the blocks rather than in front of them
+        let mut beside = Position::new(2.5, 64.0, 1.5);
+        assert!(!push_entity(
+            &mut beside,
+            &PLAYER_BOUNDS,
+            &[(1, 64, 0), (2, 64, 0)],
+            (1, 0, 0)
+        ));
+        assert_eq!((beside.x, beside.y, beside.z), (2.5, 64.0, 1.5));
+    }
+
+    #[test]
+    fn entities_on_top_are_lifted() {
+        let mut position = Position::new(0.5, 65.0, 0.5);
+        assert!(push_entity(
+            &mut position,
+            &PLAYER_BOUNDS,
+            &[(0, 65, 0)],
+            (0, 1, 0)
+        ));
+        assert_eq!(position.y, 66.0);
+    }
+}
@@ -0,0 +1,7 @@
+use crate::inventory::ItemStack;
+use bevy_ecs::prelude::Component;
+use typename::TypeName;
+
+/// The stack an item entity lying around in the world is made of.
+#[derive(TypeName, Component, Debug, Clone)]
+pub struct ItemEntity(pub ItemStack);
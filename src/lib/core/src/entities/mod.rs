@@ -160,4 +160,5 @@ entities!(
     TntMinecart,
 );
 
+pub mod item;
 pub mod spawn_rules;
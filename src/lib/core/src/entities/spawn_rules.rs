@@ -21,6 +21,14 @@ pub struct SpawnRule {
     pub dimension: Dimension,
 }
 
+impl SpawnRule {
+    /// Returns whether this rule allows spawning at the given light level.
+    pub fn allows_light(&self, light: u8) -> bool {
+        self.min_light.is_none_or(|min| light >= min)
+            && self.max_light.is_none_or(|max| light <= max)
+    }
+}
+
 /// Returns spawn rules for the given biome id.
 pub fn rules_for_biome(biome: u8) -> &'static [SpawnRule] {
     match biome {
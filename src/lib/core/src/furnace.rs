@@ -1,6 +1,6 @@
 use crate::inventory::{Inventory, ItemStack};
 use bevy_ecs::prelude::{Component, Query};
-use ferrumc_world::block_id::{BlockData, BlockId};
+use ferrumc_world::item_id::ItemId;
 
 #[derive(Component, Debug, Clone, Default)]
 pub struct Furnace {
@@ -15,9 +15,8 @@ pub struct Furnace {
 fn fuel_value(stack: &ItemStack) -> i16 {
     stack
         .item
-        .to_block_data()
-        .map(|data| {
-            let name = data.name.as_str();
+        .name()
+        .map(|name| {
             if name.contains("coal") {
                 1600
             } else if name.contains("log") || name.contains("planks") {
@@ -31,8 +30,7 @@ fn fuel_value(stack: &ItemStack) -> i16 {
 
 /// Determines the smelting output and experience for a given input item.
 fn smelting_result(input: &ItemStack) -> Option<(ItemStack, f32)> {
-    let data = input.item.to_block_data()?;
-    let name = data.name.as_str();
+    let name = input.item.name()?;
     let (out_name, xp) = if name.contains("iron_ore") {
         ("minecraft:iron_ingot", 0.7)
     } else if name.contains("gold_ore") {
@@ -42,12 +40,8 @@ fn smelting_result(input: &ItemStack) -> Option<(ItemStack, f32)> {
     } else {
         return None;
     };
-    let out_block = BlockData {
-        name: out_name.to_string(),
-        properties: None,
-    };
-    let out_id = BlockId::from_block_data(&out_block);
-    Some((ItemStack::new(out_id, 1, 64, None), xp))
+    let out_id = ItemId::from_name(out_name)?;
+    Some((ItemStack::of(out_id, 1), xp))
 }
 
 /// Furnace smelting system implementing fuel consumption, output conversion and XP gain.
@@ -1,17 +1,17 @@
 use bevy_ecs::prelude::Component;
-use ferrumc_world::{block_id::BlockId, recipes::RECIPES};
+use ferrumc_world::{item_id::ItemId, recipes::RECIPES};
 use ferrumc_storage::player_data::{InventoryData, ItemStackData};
 
 #[derive(Debug, Clone)]
 pub struct ItemStack {
-    pub item: BlockId,
+    pub item: ItemId,
     pub count: u8,
     pub max_stack_size: u8,
     pub nbt: Option<Vec<u8>>,
 }
 
 impl ItemStack {
-    pub fn new(item: BlockId, count: u8, max_stack_size: u8, nbt: Option<Vec<u8>>) -> Self {
+    pub fn new(item: ItemId, count: u8, max_stack_size: u8, nbt: Option<Vec<u8>>) -> Self {
         let count = count.min(max_stack_size);
         Self {
             item,
@@ -20,6 +20,11 @@ impl ItemStack {
             nbt,
         }
     }
+
+    /// A stack of an item that holds as many as the item normally stacks to.
+    pub fn of(item: ItemId, count: u8) -> Self {
+        Self::new(item, count, item.max_stack_size(), None)
+    }
 }
 
 pub type Slot = Option<ItemStack>;
@@ -128,8 +133,7 @@ impl Inventory {
     pub fn right_click_slot(&mut self, index: usize) -> ItemUseResult {
         if let Some(slot) = self.get_slot_mut(index) {
             if let Some(stack) = slot {
-                if let Some(data) = stack.item.to_block_data() {
-                    let name = data.name.as_str();
+                if let Some(name) = stack.item.name() {
                     if name.contains("bow") {
                         return ItemUseResult::ShotBow;
                     }
@@ -317,7 +321,7 @@ impl CraftingGrid {
                     }
                 }
                 let (id, count) = recipe.output;
-                return Some(ItemStack::new(id, count, 64, None));
+                return Some(ItemStack::of(id, count));
             }
         }
         None
@@ -338,7 +342,7 @@ impl From<&ItemStack> for ItemStackData {
 impl From<&ItemStackData> for ItemStack {
     fn from(data: &ItemStackData) -> Self {
         ItemStack {
-            item: BlockId(data.item),
+            item: ItemId(data.item),
             count: data.count,
             max_stack_size: data.max_stack_size,
             nbt: data.nbt.clone(),
@@ -1,5 +1,6 @@
 use bevy_ecs::prelude::Component;
 use ferrumc_world::chunk_format::Chunk;
+use ferrumc_world::fluids::{self, Fluid};
 use typename::TypeName;
 
 use crate::{
@@ -69,16 +70,27 @@ impl Movement {
         }
     }
 
-    /// Apply forces from water flow. Currently only adds upward flow.
-    fn apply_water_flow(&mut self, in_fluid: bool) {
+    /// Apply forces from water flow: `flow` is the push of the fluid the entity is in, see
+    /// [`fluid_flow`].
+    fn apply_water_flow(&mut self, in_fluid: bool, flow: (f64, f64, f64)) {
         if in_fluid {
             // Simple upward current to emulate swimming buoyancy.
             self.vy += 0.01;
+            self.vx += flow.0;
+            self.vy += flow.1;
+            self.vz += flow.2;
         }
     }
 
-    /// Tick movement, applying gravity, drag, friction and flow forces.
-    pub fn tick(&mut self, position: &mut Position, bounds: &CollisionBounds, chunk: &Chunk) {
+    /// Tick movement, applying gravity, drag, friction and flow forces. `neighbours` are the
+    /// loaded chunks around `chunk`, fluid flowing in from them pushes entities at its edges.
+    pub fn tick(
+        &mut self,
+        position: &mut Position,
+        bounds: &CollisionBounds,
+        chunk: &Chunk,
+        neighbours: &[&Chunk],
+    ) {
         let in_fluid = is_in_fluid(chunk, position, bounds);
         let climbing = is_touching_climbable(chunk, position, bounds);
         if !climbing {
@@ -86,7 +98,7 @@ impl Movement {
         } else {
             self.vy = self.vy.clamp(-0.15, 0.2);
         }
-        self.apply_water_flow(in_fluid);
+        self.apply_water_flow(in_fluid, fluid_flow(chunk, neighbours, position, bounds));
         self.apply_drag(in_fluid);
         if self.sneaking {
             self.vx *= 0.3;
@@ -109,8 +121,8 @@ fn is_in_fluid(chunk: &Chunk, position: &Position, bounds: &CollisionBounds) ->
             return;
         }
         if let Ok(block) = chunk.get_block(x, y, z) {
-            if let Some(data) = block.to_block_data() {
-                if data.name.contains("water") || data.name.contains("lava") {
+            if let Some(name) = block.name() {
+                if name.contains("water") || name.contains("lava") {
                     found = true;
                 }
             }
@@ -120,6 +132,38 @@ fn is_in_fluid(chunk: &Chunk, position: &Position, bounds: &CollisionBounds) ->
     found
 }
 
+/// The average push of the fluid blocks the entity is in. Lava flows a lot slower than water.
+fn fluid_flow(
+    chunk: &Chunk,
+    neighbours: &[&Chunk],
+    position: &Position,
+    bounds: &CollisionBounds,
+) -> (f64, f64, f64) {
+    const WATER_FLOW: f64 = 0.014;
+    const LAVA_FLOW: f64 = 0.0023;
+    let mut total = (0.0, 0.0, 0.0);
+    let mut count = 0;
+    for_each_block_in_bounds(chunk, position, bounds, |x, y, z| {
+        let Some((fx, fy, fz)) = fluids::flow_in_chunk(chunk, neighbours, x, y, z) else {
+            return;
+        };
+        let block = chunk.get_block(x, y, z).unwrap_or_default();
+        let speed = match Fluid::of(block) {
+            Some(Fluid::Lava) => LAVA_FLOW,
+            _ => WATER_FLOW,
+        };
+        total.0 += fx * speed;
+        total.1 += fy * speed;
+        total.2 += fz * speed;
+        count += 1;
+    });
+    if count == 0 {
+        return total;
+    }
+    let count = count as f64;
+    (total.0 / count, total.1 / count, total.2 / count)
+}
+
 /// Check if the entity is touching a climbable block such as a ladder or vine.
 fn is_touching_climbable(chunk: &Chunk, position: &Position, bounds: &CollisionBounds) -> bool {
     let mut found = false;
@@ -128,8 +172,8 @@ fn is_touching_climbable(chunk: &Chunk, position: &Position, bounds: &CollisionB
             return;
         }
         if let Ok(block) = chunk.get_block(x, y, z) {
-            if let Some(data) = block.to_block_data() {
-                if data.name.contains("ladder") || data.name.contains("vine") {
+            if let Some(name) = block.name() {
+                if name.contains("ladder") || name.contains("vine") {
                     found = true;
                 }
             }
@@ -160,7 +204,7 @@ mod tests {
         let chunk = Chunk::new(0, 0, "overworld".to_string());
         let mut position = Position::new(0.0, 2.0, 0.0);
         let mut movement = Movement::default();
-        movement.tick(&mut position, &entity_bounds(), &chunk);
+        movement.tick(&mut position, &entity_bounds(), &chunk, &[]);
         assert!(position.y < 2.0);
     }
 
@@ -170,7 +214,7 @@ mod tests {
         let mut position = Position::default();
         let mut movement = Movement::new(0.0, 0.42, 0.0);
         for _ in 0..10 {
-            movement.tick(&mut position, &entity_bounds(), &chunk);
+            movement.tick(&mut position, &entity_bounds(), &chunk, &[]);
         }
         assert!(movement.vy < 0.0);
     }
@@ -188,11 +232,48 @@ mod tests {
         chunk.set_block(0, 0, 0, water).unwrap();
         let mut position = Position::new(0.5, 0.5, 0.5);
         let mut movement = Movement::new(0.2, -0.1, 0.0);
-        movement.tick(&mut position, &entity_bounds(), &chunk);
+        movement.tick(&mut position, &entity_bounds(), &chunk, &[]);
         assert!(movement.vy > -0.1);
         assert!(movement.vx.abs() < 0.2);
     }
 
+    #[test]
+    fn flowing_water_pushes_entities() {
+        let mut chunk = Chunk::new(0, 0, "overworld".to_string());
+        let stone = BlockData {
+            name: "minecraft:stone".to_string(),
+            properties: None,
+        }
+        .to_block_id();
+        for x in 0..4 {
+            for z in 0..3 {
+                chunk.set_block(x, 0, z, stone).unwrap();
+            }
+        }
+        // Water thinning out towards +x
+        for (x, amount) in [(0, 8), (1, 7), (2, 6), (3, 5)] {
+            let water = if amount == 8 {
+                fluids::FluidState::source(Fluid::Water)
+            } else {
+                fluids::FluidState::flowing(Fluid::Water, amount)
+            };
+            chunk.set_block(x, 1, 1, water.block()).unwrap();
+        }
+        let mut position = Position::new(1.5, 1.0, 1.5);
+        let bounds = CollisionBounds {
+            x_offset_start: -0.2,
+            x_offset_end: 0.2,
+            y_offset_start: 0.0,
+            y_offset_end: 0.4,
+            z_offset_start: -0.2,
+            z_offset_end: 0.2,
+        };
+        let mut movement = Movement::default();
+        movement.tick(&mut position, &bounds, &chunk, &[]);
+        assert!(movement.vx > 0.0);
+        assert!(position.x > 1.5);
+    }
+
     #[test]
     fn collisions_stop_on_solid_blocks() {
         let mut chunk = Chunk::new(0, 0, "overworld".to_string());
@@ -204,7 +285,7 @@ mod tests {
         chunk.set_block(0, 0, 0, stone).unwrap();
         let mut position = Position::new(0.5, 1.0, 0.5);
         let mut movement = Movement::new(0.0, -1.0, 0.0);
-        movement.tick(&mut position, &entity_bounds(), &chunk);
+        movement.tick(&mut position, &entity_bounds(), &chunk, &[]);
         assert!(position.y >= 1.0);
         assert_eq!(movement.vy, 0.0);
     }
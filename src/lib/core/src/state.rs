@@ -1,7 +1,7 @@
 use bevy_ecs::prelude::{Commands, Query, Res};
 use ferrumc_state::GlobalStateResource;
 use ferrumc_storage::errors::StorageError;
-use ferrumc_storage::lmdb::LmdbBackend;
+use ferrumc_storage::backend::StorageBackend;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use tracing::error;
@@ -25,7 +25,7 @@ pub struct PlayerStats {
 }
 
 pub fn load_player_stats(
-    db: &LmdbBackend,
+    db: &dyn StorageBackend,
     uuid: u128,
 ) -> Result<PlayerStats, StorageError> {
     if let Some(bytes) = db.get("player_stats".to_string(), uuid)? {
@@ -37,7 +37,7 @@ pub fn load_player_stats(
 }
 
 pub fn save_player_stats(
-    db: &LmdbBackend,
+    db: &dyn StorageBackend,
     uuid: u128,
     stats: &PlayerStats,
 ) -> Result<(), StorageError> {
@@ -65,15 +65,31 @@ pub fn spawn_entities_from_biomes(
     }
 
     let biome = state.0.terrain_generator.biome_at(0, 0);
-    let rules = spawn_rules::rules_for_biome(biome);
-    if let Some(kind) = select_weighted(rules) {
+    let position = Position::default();
+    // Unloaded chunks have no light to check against, so treat them as dark
+    let light = state
+        .0
+        .world
+        .get_light(
+            position.x as i32,
+            position.y as i32,
+            position.z as i32,
+            "overworld",
+        )
+        .unwrap_or(0);
+    let rules: Vec<SpawnRule> = spawn_rules::rules_for_biome(biome)
+        .iter()
+        .filter(|rule| rule.allows_light(light))
+        .copied()
+        .collect();
+    if let Some(kind) = select_weighted(&rules) {
         let id = EntityId::new(rand::random::<u128>());
         let attrs = attributes_for(kind);
         cmd.spawn((
             id,
             Mob { kind },
             attrs.health,
-            Position::default(),
+            position,
             Rotation::default(),
             Movement::default(),
             attrs.speed,
@@ -0,0 +1,13 @@
+use bevy_ecs::prelude::Component;
+use typename::TypeName;
+
+/// The dimension an entity is in. Players are in the dimension of the chunks they're sent, see
+/// [`crate::chunks::chunk_receiver::ChunkReceiver::last_chunk`].
+#[derive(TypeName, Debug, Clone, PartialEq, Eq, Component)]
+pub struct Dimension(pub String);
+
+impl Default for Dimension {
+    fn default() -> Self {
+        Self("overworld".to_string())
+    }
+}
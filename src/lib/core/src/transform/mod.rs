@@ -1,3 +1,4 @@
+pub mod dimension;
 pub mod grounded;
 pub mod pending_teleport;
 pub mod position;
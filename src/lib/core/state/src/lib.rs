@@ -4,14 +4,14 @@ use crate::player_list::PlayerList;
 use bevy_ecs::prelude::Resource;
 use ferrumc_threadpool::ThreadPool;
 use ferrumc_world::World;
-use ferrumc_world_gen::WorldGenerator;
+use ferrumc_world_gen::Generators;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Instant;
 
 pub struct ServerState {
     pub world: World,
-    pub terrain_generator: WorldGenerator,
+    pub terrain_generator: Generators,
     pub shut_down: AtomicBool,
     pub players: PlayerList, // (UUID, Username)
     pub thread_pool: ThreadPool,
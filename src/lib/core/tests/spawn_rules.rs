@@ -43,3 +43,14 @@ fn nether_wastes_rules() {
         ]
     );
 }
+
+#[test]
+fn light_limits() {
+    let rules = rules_for_biome(32);
+    let cow = rules.iter().find(|r| r.kind == EntityKind::Cow).unwrap();
+    let zombie = rules.iter().find(|r| r.kind == EntityKind::Zombie).unwrap();
+    assert!(cow.allows_light(15));
+    assert!(!cow.allows_light(7));
+    assert!(zombie.allows_light(0));
+    assert!(!zombie.allows_light(8));
+}
@@ -9,6 +9,7 @@ use crate::packets::incoming::packet_skeleton::PacketSkeleton;
 use crate::packets::outgoing::container_set_content::ContainerSetContentPacket;
 use crate::packets::outgoing::known_packs::{ClientboundKnownPacks, KnownPack as ClientKnownPack};
 use crate::packets::outgoing::login_disconnect::LoginDisconnectPacket;
+use crate::packets::outgoing::registry_data::RegistryDataPacket;
 use crate::ConnState::*;
 use ferrumc_config::server_config::get_global_config;
 use ferrumc_core::identity::player_identity::PlayerIdentity;
@@ -21,11 +22,19 @@ use ferrumc_net_codec::net_types::byte_array::ByteArray;
 use ferrumc_net_codec::net_types::length_prefixed_vec::LengthPrefixedVec;
 use ferrumc_net_encryption::{decrypt_shared_secret, generate_rsa_keypair, generate_verify_token};
 use ferrumc_state::GlobalState;
+use ferrumc_world::errors::WorldError;
+use ferrumc_world::item_id::upgrade_player_data;
 use rsa::pkcs1::EncodeRsaPublicKey;
+use std::sync::Arc;
 use tokio::io::AsyncRead;
 use tracing::error;
 use uuid::Uuid;
 
+/// How many chunks out from spawn are sent before login finishes, so the player has ground to
+/// stand on. The rest of the render distance is streamed afterwards, and can wait for a slow
+/// connection to catch up.
+const SPAWN_CHUNK_RADIUS: i32 = 1;
+
 /// Handles the **login sequence** for a newly connecting client.
 ///
 /// This function follows the Minecraft 1.20.1 login handshake:
@@ -132,6 +141,8 @@ pub(super) async fn login<R: AsyncRead + Unpin>(
                         player_identity: None,
                         compression: false,
                         player_data: None,
+                        sent_chunks: Vec::new(),
+                        pending_chunks: Vec::new(),
                     },
                 ));
             }
@@ -196,10 +207,16 @@ pub(super) async fn login<R: AsyncRead + Unpin>(
                 player_identity: None,
                 compression: compressed,
                 player_data: None,
+                sent_chunks: Vec::new(),
+                pending_chunks: Vec::new(),
             },
         ));
     }
 
+    // Tell the client about the type of every dimension the server hosts, custom ones included
+    let dimensions = state.world.dimensions().network_order();
+    conn_write.send_packet(RegistryDataPacket::dimension_types(&dimensions))?;
+
     // =============================================================================================
     // 5 Send Login Success (UUID and username acknowledgement)
     let login_success = crate::packets::outgoing::login_success::LoginSuccessPacket {
@@ -219,12 +236,40 @@ pub(super) async fn login<R: AsyncRead + Unpin>(
     };
 
     // =============================================================================================
-    // 6 Send login_play packet to switch to Play state
+    // 6 Send login_play packet to switch to Play state, listing every dimension the server hosts
+    let mut player_data = load_player_data(state.world.backend(), player_uuid)
+        .map_err(|e| NetError::Misc(e.to_string()))?;
+    upgrade_player_data(&mut player_data);
+    // Players in a dimension that's gone since they left start over in the overworld
+    if player_data
+        .dimension
+        .as_deref()
+        .is_some_and(|dimension| state.world.dimensions().get(dimension).is_none())
+    {
+        player_data.dimension = None;
+        player_data.position = Default::default();
+    }
+    let spawn_dimension = player_data
+        .dimension
+        .clone()
+        .unwrap_or_else(|| "overworld".to_string());
+    let registry_names: Vec<String> = dimensions
+        .iter()
+        .map(|dimension| dimension.registry_name())
+        .collect();
+    let dimension_names: Vec<&str> = registry_names.iter().map(String::as_str).collect();
+    let spawn_name = state
+        .world
+        .dimensions()
+        .get(&spawn_dimension)
+        .map(|dimension| dimension.registry_name())
+        .unwrap_or_default();
     let login_play = crate::packets::outgoing::login_play::LoginPlayPacket::new(
         player_identity.short_uuid,
-        0,
+        &dimension_names,
+        &spawn_name,
         None,
-    );
+    )?;
     conn_write.send_packet(login_play)?;
 
     // =============================================================================================
@@ -287,8 +332,6 @@ pub(super) async fn login<R: AsyncRead + Unpin>(
 
     // =============================================================================================
     // 10b Send initial inventory contents
-    let player_data = load_player_data(state.world.backend(), player_uuid)
-        .map_err(|e| NetError::Misc(e.to_string()))?;
     let inventory = Inventory::from(&player_data.inventory);
     let inv_packet = ContainerSetContentPacket::from_inventory(&inventory);
     conn_write.send_packet(inv_packet)?;
@@ -299,17 +342,42 @@ pub(super) async fn login<R: AsyncRead + Unpin>(
     conn_write.send_packet(center_chunk)?;
 
     // =============================================================================================
-    // 12 Load and send surrounding chunks within render distance
+    // 12 Load and send the chunks right around spawn, and leave the rest of the render distance
+    // for chunk streaming
     let radius = get_global_config().chunk_render_distance as i32;
+    let spawn_radius = SPAWN_CHUNK_RADIUS.min(radius);
+    let mut sent_chunks = Vec::new();
+    let mut pending_chunks = Vec::new();
 
     let mut batch = state.thread_pool.batch();
 
     for x in -radius..=radius {
         for z in -radius..=radius {
+            if x.abs() > spawn_radius || z.abs() > spawn_radius {
+                pending_chunks.push((x, z, spawn_dimension.clone()));
+                continue;
+            }
+            sent_chunks.push((x, z, spawn_dimension.clone()));
             batch.execute({
                 let state = state.clone();
+                let spawn_dimension = spawn_dimension.clone();
                 move || -> Result<Vec<u8>, NetError> {
-                    let chunk = state.world.load_chunk(x, z, "overworld")?;
+                    let chunk = if state.world.chunk_exists(x, z, &spawn_dimension)? {
+                        state.world.load_chunk(x, z, &spawn_dimension)?
+                    } else {
+                        // Spawn chunks nobody has edited yet aren't saved, same as streamed ones
+                        let dimension = state
+                            .world
+                            .dimensions()
+                            .get(&spawn_dimension)
+                            .ok_or_else(|| WorldError::UnknownDimension(spawn_dimension.clone()))?;
+                        let mut chunk = state
+                            .terrain_generator
+                            .generate_chunk(x, z, &dimension)
+                            .map_err(|err| NetError::Misc(err.to_string()))?;
+                        state.world.light_new_chunk(&mut chunk);
+                        Arc::new(chunk)
+                    };
                     let chunk_data =
                         crate::packets::outgoing::chunk_and_light_data::ChunkAndLightData::from_chunk(
                             &chunk,
@@ -346,6 +414,8 @@ pub(super) async fn login<R: AsyncRead + Unpin>(
             player_identity: Some(player_identity),
             compression: compressed,
             player_data: Some(player_data),
+            sent_chunks,
+            pending_chunks,
         },
     ))
 }
@@ -24,10 +24,15 @@ use tracing::{error, trace};
 ///
 /// - `player_identity`: Populated when login is successful and a player is identified.
 /// - `compression`: Indicates whether network compression should be enabled for this connection.
+/// - `sent_chunks`: Chunks around spawn that were sent while logging in.
+/// - `pending_chunks`: Chunks around spawn left for chunk streaming to send once the player is in
+///   the game.
 pub(crate) struct LoginResult {
     pub player_identity: Option<PlayerIdentity>,
     pub compression: bool,
     pub player_data: Option<PlayerData>,
+    pub sent_chunks: Vec<(i32, i32, String)>,
+    pub pending_chunks: Vec<(i32, i32, String)>,
 }
 
 /// Minecraft version targeted by this server implementation.
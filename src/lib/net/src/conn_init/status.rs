@@ -98,6 +98,8 @@ pub(super) async fn status<R: AsyncRead + Unpin>(
             player_identity: None,
             compression: false,
             player_data: None,
+            sent_chunks: Vec::new(),
+            pending_chunks: Vec::new(),
         },
     ))
 }
@@ -27,6 +27,8 @@ pub(super) async fn transfer<R: AsyncRead + Unpin>(
             player_identity: None,
             compression: false,
             player_data: None,
+            sent_chunks: Vec::new(),
+            pending_chunks: Vec::new(),
         },
     ))
 }
@@ -4,6 +4,7 @@ use crate::errors::CompressionError::GenericCompressionError;
 use crate::errors::NetError;
 use crate::errors::NetError::HandshakeTimeout;
 use crate::errors::PacketError::InvalidPacket;
+use crate::outbound::{Delivery, OutboundQueue, QueueLimits};
 use crate::packets::incoming::packet_skeleton::PacketSkeleton;
 use crate::ConnState::Play;
 use crate::{handle_packet, PacketSender};
@@ -26,7 +27,6 @@ use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWriteExt, ReadBuf};
 use tokio::net::tcp::OwnedWriteHalf;
 use tokio::net::TcpStream;
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tokio::sync::oneshot;
 use tokio::time::timeout;
 use tracing::{debug_span, error, trace, warn, Instrument};
@@ -95,13 +95,13 @@ impl<R: AsyncRead + Unpin> AsyncRead for EncryptedReader<R> {
 /// StreamWriter manages asynchronous writes to a client's TCP connection.
 ///
 /// It:
-/// - Buffers outgoing packets via a Tokio `mpsc` channel.
+/// - Buffers outgoing packets in a bounded [`OutboundQueue`].
 /// - Runs a background task that writes packets to the underlying socket.
 /// - Supports toggling compression dynamically.
 /// - Gracefully handles disconnection when dropped.
 #[derive(TypeName, Component)]
 pub struct StreamWriter {
-    sender: UnboundedSender<Vec<u8>>,
+    queue: Arc<OutboundQueue>,
     pub running: Arc<AtomicBool>,
     pub compress: Arc<AtomicBool>,
     encryptor: Arc<Mutex<Option<Aes128Cfb8Encryptor>>>,
@@ -111,26 +111,28 @@ impl Drop for StreamWriter {
     /// When the writer is dropped, mark the connection as no longer active.
     fn drop(&mut self) {
         self.running.store(false, Ordering::Relaxed);
+        self.queue.wake();
     }
 }
 
 impl StreamWriter {
     /// Creates a new StreamWriter for the connection's write half.
     ///
-    /// Spawns a background task that continuously reads from the channel
+    /// Spawns a background task that continuously takes packets from the queue
     /// and writes bytes to the network socket.
     pub async fn new(mut writer: OwnedWriteHalf, running: Arc<AtomicBool>) -> Self {
         let compress = Arc::new(AtomicBool::new(false)); // Default: no compression
         let encryptor = Arc::new(Mutex::new(None));
-        let (sender, mut receiver): (UnboundedSender<Vec<u8>>, UnboundedReceiver<Vec<u8>>) =
-            tokio::sync::mpsc::unbounded_channel();
+        let queue = Arc::new(OutboundQueue::new(QueueLimits::from_config()));
         let running_clone = running.clone();
+        let queue_clone = queue.clone();
 
-        // Task: forward packets from channel to socket
+        // Task: forward packets from the queue to the socket
         tokio::spawn(async move {
             while running_clone.load(Ordering::Relaxed) {
-                let Some(bytes) = receiver.recv().await else {
-                    break;
+                let Some(bytes) = queue_clone.pop() else {
+                    queue_clone.wait().await;
+                    continue;
                 };
 
                 if let Err(e) = writer.write_all(&bytes).await {
@@ -139,10 +141,11 @@ impl StreamWriter {
                     break;
                 }
             }
+            queue_clone.clear();
         });
 
         Self {
-            sender,
+            queue,
             running,
             compress,
             encryptor,
@@ -159,6 +162,16 @@ impl StreamWriter {
         self.send_packet_with_opts(packet, &NetEncodeOpts::WithLength)
     }
 
+    /// Sends a packet that can be dropped, replaced or held back while the client falls behind.
+    /// See [`Delivery`].
+    pub fn send_packet_as(
+        &self,
+        packet: &(impl NetEncode + Send),
+        delivery: Delivery,
+    ) -> Result<(), NetError> {
+        self.queue_packet(packet, &NetEncodeOpts::WithLength, delivery)
+    }
+
     /// Sends a packet with custom encoding options (e.g., with or without length prefix).
     ///
     /// Handles optional compression based on `self.compress` flag.
@@ -166,6 +179,15 @@ impl StreamWriter {
         &self,
         packet: &(impl NetEncode + Send),
         net_encode_opts: &NetEncodeOpts,
+    ) -> Result<(), NetError> {
+        self.queue_packet(packet, net_encode_opts, Delivery::Reliable)
+    }
+
+    fn queue_packet(
+        &self,
+        packet: &(impl NetEncode + Send),
+        net_encode_opts: &NetEncodeOpts,
+        delivery: Delivery,
     ) -> Result<(), NetError> {
         if !self.running.load(Ordering::Relaxed) {
             #[cfg(debug_assertions)]
@@ -192,12 +214,21 @@ impl StreamWriter {
             raw_bytes
         };
 
-        self.sender.send(raw_bytes).map_err(std::io::Error::other)?;
-        Ok(())
+        self.queue.push(raw_bytes, delivery)
     }
 
     /// Sends pre-encoded raw bytes to the client without additional processing.
     pub fn send_raw_packet(&self, raw_bytes: Vec<u8>) -> Result<(), NetError> {
+        self.send_raw_packet_as(raw_bytes, Delivery::Reliable)
+    }
+
+    /// Sends pre-encoded raw bytes that can be dropped, replaced or held back while the client
+    /// falls behind. See [`Delivery`].
+    pub fn send_raw_packet_as(
+        &self,
+        raw_bytes: Vec<u8>,
+        delivery: Delivery,
+    ) -> Result<(), NetError> {
         if !self.running.load(Ordering::Relaxed) {
             #[cfg(debug_assertions)]
             warn!("Attempted to send raw bytes on closed connection");
@@ -210,8 +241,23 @@ impl StreamWriter {
             raw_bytes
         };
 
-        self.sender.send(raw_bytes).map_err(std::io::Error::other)?;
-        Ok(())
+        self.queue.push(raw_bytes, delivery)
+    }
+
+    /// How many bytes are waiting to be written to the client.
+    pub fn queued_bytes(&self) -> usize {
+        self.queue.queued_bytes()
+    }
+
+    /// Whether the client is falling behind, so anything that can wait should.
+    pub fn is_backed_up(&self) -> bool {
+        self.queue.is_backed_up()
+    }
+
+    /// Whether the client fell so far behind it should be kicked, with
+    /// [`crate::outbound::SLOW_CLIENT_REASON`].
+    pub fn fell_behind(&self) -> bool {
+        self.queue.overflowed()
     }
 
     pub fn enable_encryption(&self, shared_secret: &[u8; 16]) -> Result<(), NetError> {
@@ -227,6 +273,10 @@ pub struct NewConnection {
     pub stream: StreamWriter,
     pub player_identity: PlayerIdentity,
     pub player_data: PlayerData,
+    /// Chunks the player was sent while logging in.
+    pub sent_chunks: Vec<(i32, i32, String)>,
+    /// Chunks the player still needs, for chunk streaming to send.
+    pub pending_chunks: Vec<(i32, i32, String)>,
     pub entity_return: oneshot::Sender<Entity>,
 }
 
@@ -322,6 +372,8 @@ pub async fn handle_connection(
             stream,
             player_identity: login_result.player_identity.unwrap_or_default(),
             player_data: login_result.player_data.unwrap_or_default(),
+            sent_chunks: login_result.sent_chunks,
+            pending_chunks: login_result.pending_chunks,
             entity_return,
         })
         .map_err(|_| NetError::Misc("Failed to register new connection".to_string()))?;
@@ -22,6 +22,12 @@ pub enum NetError {
     #[error("Connection Dropped")]
     ConnectionDropped,
 
+    #[error("Outbound queue is backed up, try again later")]
+    Backpressure,
+
+    #[error("Client fell too far behind on the data sent to it")]
+    SlowClient,
+
     #[error("Addr parse error: {0}")]
     AddrParseError(#[from] std::net::AddrParseError),
 
@@ -9,6 +9,7 @@ pub mod compression;
 mod conn_init;
 pub mod connection;
 pub mod errors;
+pub mod outbound;
 pub mod packets;
 pub mod server;
 
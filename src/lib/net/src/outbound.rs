@@ -0,0 +1,331 @@
+//! The queue of packets waiting to be written to a client.
+//!
+//! Every connection gets one, limited by how many bytes it holds rather than how many packets, so
+//! a client that stops reading can't make the server buffer chunk data until it runs out of memory.
+//! Past the soft limit the client counts as falling behind: packets that can be lost are dropped,
+//! ones that only carry the latest state of something replace what's already queued, and chunk
+//! streaming is told to wait. Past the hard limit the client is kicked, or what doesn't fit is
+//! dropped, depending on the config.
+
+use crate::errors::NetError;
+use ferrumc_config::server_config::get_global_config;
+use ferrumc_utils::metrics::{OUTBOUND_DROPPED_PACKETS, OUTBOUND_QUEUE_BYTES};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use tracing::warn;
+
+/// The reason given to clients kicked for falling too far behind.
+pub const SLOW_CLIENT_REASON: &str = "Your connection is too slow to keep up with the server";
+
+/// How a packet should be treated when the client it's for falls behind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delivery {
+    /// Always queued. Most packets are this.
+    Reliable,
+    /// Dropped while the client is behind, e.g. animations and sounds.
+    Droppable,
+    /// Only the newest state of something, e.g. an entity's rotation. Replaces a queued packet
+    /// with the same `kind` and `id`, and is dropped like [`Delivery::Droppable`] otherwise. The
+    /// replacement goes to the back of the queue, so it's still written after everything that was
+    /// sent before it.
+    Latest { kind: &'static str, id: i32 },
+    /// Refused with [`NetError::Backpressure`] while the client is behind, for callers that can
+    /// send it later, like chunk streaming.
+    Deferrable,
+}
+
+/// What happens when a client's queue is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Disconnect the client, see [`OutboundQueue::overflowed`].
+    Kick,
+    /// Throw away packets that don't fit.
+    Drop,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueLimits {
+    /// Bytes queued before the client counts as falling behind.
+    pub soft: usize,
+    /// The most bytes that can be queued.
+    pub hard: usize,
+    pub policy: OverflowPolicy,
+}
+
+impl QueueLimits {
+    pub fn from_config() -> Self {
+        let config = &get_global_config().outbound_queue;
+        let policy = match config.overflow_policy.as_str() {
+            "kick" => OverflowPolicy::Kick,
+            "drop" => OverflowPolicy::Drop,
+            other => {
+                warn!("Unknown outbound queue overflow policy {other:?}, kicking slow clients");
+                OverflowPolicy::Kick
+            }
+        };
+        Self {
+            soft: config.soft_limit,
+            hard: config.hard_limit.max(config.soft_limit),
+            policy,
+        }
+    }
+}
+
+struct Queued {
+    bytes: Vec<u8>,
+    latest: Option<(&'static str, i32)>,
+}
+
+#[derive(Default)]
+struct Packets {
+    queued: VecDeque<Queued>,
+    /// How many packets have ever been taken off the front of `queued`, so a packet's sequence
+    /// number minus this is its index.
+    popped: u64,
+    /// The sequence number of the queued packet for each [`Delivery::Latest`] key.
+    latest: HashMap<(&'static str, i32), u64>,
+    bytes: usize,
+    overflowed: bool,
+}
+
+impl Packets {
+    /// Where the queued packet with the given [`Delivery::Latest`] key is, if it's still queued.
+    fn latest_index(&self, key: (&'static str, i32)) -> Option<usize> {
+        let index = self.latest.get(&key)?.checked_sub(self.popped)? as usize;
+        (index < self.queued.len()).then_some(index)
+    }
+
+    /// Add a packet to the back of the queue, its bytes already counted.
+    fn queue(&mut self, latest: Option<(&'static str, i32)>, bytes: Vec<u8>) {
+        if let Some(key) = latest {
+            let sequence = self.popped + self.queued.len() as u64;
+            self.latest.insert(key, sequence);
+        }
+        self.queued.push_back(Queued { bytes, latest });
+    }
+
+    fn clear(&mut self) {
+        self.popped += self.queued.len() as u64;
+        self.queued.clear();
+        self.latest.clear();
+        self.bytes = 0;
+    }
+}
+
+/// A connection's outbound packets, written to the socket by its writer task.
+pub struct OutboundQueue {
+    packets: Mutex<Packets>,
+    notify: Notify,
+    limits: QueueLimits,
+}
+
+impl OutboundQueue {
+    pub fn new(limits: QueueLimits) -> Self {
+        Self {
+            packets: Mutex::new(Packets::default()),
+            notify: Notify::new(),
+            limits,
+        }
+    }
+
+    /// Queue encoded packet bytes for writing.
+    ///
+    /// Fails with [`NetError::Backpressure`] if a [`Delivery::Deferrable`] packet has to wait, and
+    /// with [`NetError::SlowClient`] if the queue is full and the client should be kicked. In that
+    /// case everything queued is thrown away, so the disconnect packet can still go out.
+    pub fn push(&self, bytes: Vec<u8>, delivery: Delivery) -> Result<(), NetError> {
+        let mut guard = self.packets.lock().unwrap();
+        let packets = &mut *guard;
+        let len = bytes.len();
+        if let Delivery::Latest { kind, id } = delivery {
+            if let Some(index) = packets.latest_index((kind, id)) {
+                // Replacing it in place would put it ahead of packets queued after the old one
+                let replaced = packets.queued.remove(index).map_or(0, |q| q.bytes.len());
+                let replaced_sequence = packets.popped + index as u64;
+                for sequence in packets.latest.values_mut() {
+                    if *sequence > replaced_sequence {
+                        *sequence -= 1;
+                    }
+                }
+                packets.bytes = packets.bytes - replaced + len;
+                OUTBOUND_QUEUE_BYTES.add(len as i64 - replaced as i64);
+                packets.queue(Some((kind, id)), bytes);
+                return Ok(());
+            }
+        }
+        if packets.bytes >= self.limits.soft {
+            match delivery {
+                Delivery::Reliable => {}
+                Delivery::Droppable | Delivery::Latest { .. } => {
+                    OUTBOUND_DROPPED_PACKETS.inc();
+                    return Ok(());
+                }
+                Delivery::Deferrable => return Err(NetError::Backpressure),
+            }
+        }
+        if packets.bytes + len > self.limits.hard {
+            OUTBOUND_DROPPED_PACKETS.inc();
+            return match self.limits.policy {
+                OverflowPolicy::Drop => Ok(()),
+                OverflowPolicy::Kick => {
+                    OUTBOUND_DROPPED_PACKETS.inc_by(packets.queued.len() as u64);
+                    OUTBOUND_QUEUE_BYTES.sub(packets.bytes as i64);
+                    packets.clear();
+                    packets.overflowed = true;
+                    Err(NetError::SlowClient)
+                }
+            };
+        }
+        packets.bytes += len;
+        OUTBOUND_QUEUE_BYTES.add(len as i64);
+        let latest = match delivery {
+            Delivery::Latest { kind, id } => Some((kind, id)),
+            _ => None,
+        };
+        packets.queue(latest, bytes);
+        drop(guard);
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    /// Take the next packet to write, if there is one.
+    pub fn pop(&self) -> Option<Vec<u8>> {
+        let mut packets = self.packets.lock().unwrap();
+        let queued = packets.queued.pop_front()?;
+        // Older packets with the same key were taken out when this one replaced them
+        if let Some(key) = queued.latest {
+            packets.latest.remove(&key);
+        }
+        packets.popped += 1;
+        packets.bytes -= queued.bytes.len();
+        OUTBOUND_QUEUE_BYTES.sub(queued.bytes.len() as i64);
+        Some(queued.bytes)
+    }
+
+    /// Wait until a packet is queued or [`OutboundQueue::wake`] is called.
+    pub async fn wait(&self) {
+        self.notify.notified().await;
+    }
+
+    /// Wake the writer task, e.g. so it notices the connection closed.
+    pub fn wake(&self) {
+        self.notify.notify_one();
+    }
+
+    /// How many bytes are waiting to be written.
+    pub fn queued_bytes(&self) -> usize {
+        self.packets.lock().unwrap().bytes
+    }
+
+    /// Whether the client is behind, and deferrable packets should wait.
+    pub fn is_backed_up(&self) -> bool {
+        self.queued_bytes() >= self.limits.soft
+    }
+
+    /// Whether the queue filled up and the client should be kicked.
+    pub fn overflowed(&self) -> bool {
+        self.packets.lock().unwrap().overflowed
+    }
+
+    /// Throw away everything still queued, once the connection is closed.
+    pub fn clear(&self) {
+        let mut packets = self.packets.lock().unwrap();
+        OUTBOUND_QUEUE_BYTES.sub(packets.bytes as i64);
+        packets.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(policy: OverflowPolicy) -> OutboundQueue {
+        OutboundQueue::new(QueueLimits {
+            soft: 10,
+            hard: 20,
+            policy,
+        })
+    }
+
+    #[test]
+    fn test_droppable_packets_are_dropped_once_behind() {
+        let queue = queue(OverflowPolicy::Kick);
+        queue.push(vec![0; 10], Delivery::Reliable).unwrap();
+        assert!(queue.is_backed_up());
+        queue.push(vec![1; 4], Delivery::Droppable).unwrap();
+        assert!(matches!(
+            queue.push(vec![2; 4], Delivery::Deferrable),
+            Err(NetError::Backpressure)
+        ));
+        queue.push(vec![3; 4], Delivery::Reliable).unwrap();
+        assert_eq!(queue.queued_bytes(), 14);
+        assert_eq!(queue.pop(), Some(vec![0; 10]));
+        assert_eq!(queue.pop(), Some(vec![3; 4]));
+        assert_eq!(queue.pop(), None);
+        assert_eq!(queue.queued_bytes(), 0);
+    }
+
+    #[test]
+    fn test_latest_packets_replace_queued_ones() {
+        let queue = queue(OverflowPolicy::Kick);
+        let latest = Delivery::Latest {
+            kind: "rotate_head",
+            id: 7,
+        };
+        queue.push(vec![1; 2], latest).unwrap();
+        queue.push(vec![9; 8], Delivery::Reliable).unwrap();
+        queue.push(vec![2; 3], latest).unwrap();
+        assert_eq!(queue.queued_bytes(), 11);
+        // The replacement keeps its place after the packets sent before it
+        assert_eq!(queue.pop(), Some(vec![9; 8]));
+        assert_eq!(queue.pop(), Some(vec![2; 3]));
+        assert_eq!(queue.pop(), None);
+
+        // Once written, the next one is queued again rather than replacing anything
+        queue.push(vec![3; 1], latest).unwrap();
+        queue.push(vec![4; 1], Delivery::Reliable).unwrap();
+        assert_eq!(queue.pop(), Some(vec![3; 1]));
+        queue.push(vec![5; 1], latest).unwrap();
+        queue.push(vec![6; 1], Delivery::Reliable).unwrap();
+        queue.push(vec![7; 1], latest).unwrap();
+        queue.push(vec![8; 1], latest).unwrap();
+        assert_eq!(queue.queued_bytes(), 3);
+        assert_eq!(queue.pop(), Some(vec![4; 1]));
+        assert_eq!(queue.pop(), Some(vec![6; 1]));
+        assert_eq!(queue.pop(), Some(vec![8; 1]));
+        assert_eq!(queue.pop(), None);
+        assert_eq!(queue.queued_bytes(), 0);
+
+        // Packets with other keys are still found after one ahead of them moves to the back
+        let other = Delivery::Latest {
+            kind: "rotate_head",
+            id: 8,
+        };
+        queue.push(vec![1; 1], latest).unwrap();
+        queue.push(vec![2; 1], other).unwrap();
+        queue.push(vec![3; 1], latest).unwrap();
+        queue.push(vec![4; 1], other).unwrap();
+        assert_eq!(queue.pop(), Some(vec![3; 1]));
+        assert_eq!(queue.pop(), Some(vec![4; 1]));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn test_full_queues_kick_or_drop() {
+        let kicking = queue(OverflowPolicy::Kick);
+        kicking.push(vec![0; 15], Delivery::Reliable).unwrap();
+        assert!(matches!(
+            kicking.push(vec![0; 6], Delivery::Reliable),
+            Err(NetError::SlowClient)
+        ));
+        assert!(kicking.overflowed());
+        assert_eq!(kicking.queued_bytes(), 0);
+
+        let dropping = queue(OverflowPolicy::Drop);
+        dropping.push(vec![0; 15], Delivery::Reliable).unwrap();
+        dropping.push(vec![0; 6], Delivery::Reliable).unwrap();
+        assert!(!dropping.overflowed());
+        assert_eq!(dropping.queued_bytes(), 15);
+    }
+}
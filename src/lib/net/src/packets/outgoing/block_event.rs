@@ -0,0 +1,34 @@
+use ferrumc_macros::{get_registry_entry, packet, NetEncode};
+use ferrumc_net_codec::net_types::network_position::NetworkPosition;
+use ferrumc_net_codec::net_types::var_int::VarInt;
+use std::io::Write;
+
+/// Plays a block's action on the client, like a piston moving or a chest lid opening. What
+/// `action_id` and `action_parameter` mean depends on the block.
+#[derive(NetEncode)]
+#[packet(packet_id = "block_event", state = "play")]
+pub struct BlockEvent {
+    pub location: NetworkPosition,
+    pub action_id: u8,
+    pub action_parameter: u8,
+    /// The block's id in the `minecraft:block` registry, not a block state.
+    pub block_type: VarInt,
+}
+
+const PISTON_ID: u64 = get_registry_entry!("minecraft:block.entries.minecraft:piston");
+const STICKY_PISTON_ID: u64 =
+    get_registry_entry!("minecraft:block.entries.minecraft:sticky_piston");
+
+impl BlockEvent {
+    /// A piston extending or retracting. `facing` is the direction's protocol id: down, up,
+    /// north, south, west, east.
+    pub fn piston(location: NetworkPosition, extending: bool, facing: u8, sticky: bool) -> Self {
+        let block_type = if sticky { STICKY_PISTON_ID } else { PISTON_ID };
+        Self {
+            location,
+            action_id: if extending { 0 } else { 1 },
+            action_parameter: facing,
+            block_type: VarInt::new(block_type as i32),
+        }
+    }
+}
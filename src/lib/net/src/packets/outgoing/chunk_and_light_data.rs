@@ -5,13 +5,13 @@ use ferrumc_net_codec::net_types::bitset::BitSet;
 use ferrumc_net_codec::net_types::byte_array::ByteArray;
 use ferrumc_net_codec::net_types::length_prefixed_vec::LengthPrefixedVec;
 use ferrumc_net_codec::net_types::var_int::VarInt;
-use ferrumc_world::chunk_format::{BlockEntity as WorldBlockEntity, Chunk, PaletteType};
+use ferrumc_world::chunk_format::{BlockEntity as WorldBlockEntity, Chunk, PaletteType, Section};
 use std::io::{Cursor, Write};
-use std::ops::Not;
 use tracing::warn;
 
 const SECTIONS: usize = 24; // Number of sections, adjust for your Y range (-64 to 319)
-                            // Heightmap identifiers as defined by the protocol
+const MIN_SECTION_Y: i32 = -4;
+// Heightmap identifiers as defined by the protocol
 const HEIGHTMAP_MOTION_BLOCKING: i32 = 4;
 const HEIGHTMAP_WORLD_SURFACE: i32 = 1;
 
@@ -49,57 +49,29 @@ pub struct ChunkAndLightData {
 
 impl ChunkAndLightData {
     pub fn empty(chunk_x: i32, chunk_z: i32) -> Self {
-        let sky_light_arrays = (0..SECTIONS)
-            .map(|_| ByteArray::new(vec![0; 2048]))
-            .collect();
-        let block_light_arrays = (0..SECTIONS)
-            .map(|_| ByteArray::new(vec![0; 2048]))
-            .collect();
+        // Every section, including the ones above and below the world, is marked as unlit
         let mut empty_sky_light_mask = BitSet::new(SECTIONS + 2);
-        empty_sky_light_mask.set_all(false);
+        empty_sky_light_mask.set_all(true);
         let mut empty_block_light_mask = BitSet::new(SECTIONS + 2);
-        empty_block_light_mask.set_all(false);
+        empty_block_light_mask.set_all(true);
         ChunkAndLightData {
             chunk_x,
             chunk_z,
             heightmaps: LengthPrefixedVec::default(),
             data: ByteArray::new(vec![0; SECTIONS * 10]),
             block_entities: LengthPrefixedVec::new(Vec::new()),
-            sky_light_mask: BitSet::new(SECTIONS),
-            block_light_mask: BitSet::new(SECTIONS),
+            sky_light_mask: BitSet::new(SECTIONS + 2),
+            block_light_mask: BitSet::new(SECTIONS + 2),
             empty_sky_light_mask,
             empty_block_light_mask,
-            sky_light_arrays: LengthPrefixedVec::new(sky_light_arrays),
-            block_light_arrays: LengthPrefixedVec::new(block_light_arrays),
+            sky_light_arrays: LengthPrefixedVec::new(Vec::new()),
+            block_light_arrays: LengthPrefixedVec::new(Vec::new()),
         }
     }
 
     pub fn from_chunk(chunk: &Chunk) -> Result<Self, NetError> {
         let mut raw_data = Cursor::new(Vec::new());
-        let mut sky_light_data = Vec::new();
-        let mut block_light_data = Vec::new();
         for section in &chunk.sections {
-            let section_sky_light_data = if section.sky_light.len() != 2048 {
-                warn!(
-                    "Sky light data for section at {}, {} is not 2048 bytes long",
-                    chunk.x, chunk.z
-                );
-                vec![255; 2048]
-            } else {
-                section.sky_light.clone()
-            };
-            sky_light_data.push(section_sky_light_data);
-            let section_block_light_data = if section.block_light.len() != 2048 {
-                warn!(
-                    "Block light data for section at {}, {} is not 2048 bytes long",
-                    chunk.x, chunk.z
-                );
-                vec![255; 2048]
-            } else {
-                section.block_light.clone()
-            };
-            block_light_data.push(section_block_light_data);
-
             raw_data.write_u16::<BigEndian>(section.block_states.non_air_blocks)?;
 
             match &section.block_states.block_data {
@@ -153,37 +125,37 @@ impl ChunkAndLightData {
                 }
             }
         }
-        let mut sky_light_mask = BitSet::new(SECTIONS + 2);
-        let mut block_light_mask = BitSet::new(SECTIONS + 2);
+        let mut sky_light = LightData::default();
+        let mut block_light = LightData::default();
 
-        // Populate masks based on light data
-        for (i, section) in chunk.sections.iter().enumerate() {
-            if !section.sky_light.is_empty() && section.sky_light.len() == 2048 {
-                sky_light_mask.set(i, true);
+        // Bit 0 of the light masks is the section below the world, so every section is shifted up
+        // by one. The arrays have to be sent in the same order as the bits.
+        let mut sections: Vec<&Section> = chunk.sections.iter().collect();
+        sections.sort_by_key(|section| section.y);
+        for section in sections {
+            let bit = section.y as i32 - MIN_SECTION_Y + 1;
+            if bit < 1 || bit > SECTIONS as i32 {
+                warn!(
+                    "Section {} of chunk {}, {} is outside of the world",
+                    section.y, chunk.x, chunk.z
+                );
+                continue;
             }
-            if !section.block_light.is_empty() && section.block_light.len() == 2048 {
-                block_light_mask.set(i, true);
+            if section.sky_light.len() != 2048 || section.block_light.len() != 2048 {
+                warn!(
+                    "Light data for section {} at {}, {} is not 2048 bytes long",
+                    section.y, chunk.x, chunk.z
+                );
             }
+            sky_light.add_section(bit as usize, &section.sky_light);
+            block_light.add_section(bit as usize, &section.block_light);
+        }
+        // Nothing is lit above or below the world
+        for bit in [0, SECTIONS + 1] {
+            sky_light.empty_mask.set(bit, true);
+            block_light.empty_mask.set(bit, true);
         }
 
-        // Invert masks to create empty masks
-        let empty_sky_light_mask = sky_light_mask.clone().not();
-        let empty_block_light_mask = block_light_mask.clone().not();
-
-        // Align light arrays with masks
-        let sky_light_arrays = chunk
-            .sections
-            .iter()
-            .filter(|section| !section.sky_light.is_empty())
-            .map(|section| ByteArray::new(section.sky_light.clone()))
-            .collect();
-
-        let block_light_arrays = chunk
-            .sections
-            .iter()
-            .filter(|section| !section.block_light.is_empty())
-            .map(|section| ByteArray::new(section.block_light.clone()))
-            .collect();
         // Heightmaps must be sent using the named protocol IDs
         // in the order expected by 1.20.1 clients
         let heightmaps = vec![
@@ -214,12 +186,42 @@ impl ChunkAndLightData {
             heightmaps: LengthPrefixedVec::new(heightmaps),
             data: ByteArray::new(raw_data.into_inner()),
             block_entities: LengthPrefixedVec::new(block_entities),
-            sky_light_mask,
-            block_light_mask,
-            empty_sky_light_mask,
-            empty_block_light_mask,
-            sky_light_arrays: LengthPrefixedVec::new(sky_light_arrays),
-            block_light_arrays: LengthPrefixedVec::new(block_light_arrays),
+            sky_light_mask: sky_light.mask,
+            block_light_mask: block_light.mask,
+            empty_sky_light_mask: sky_light.empty_mask,
+            empty_block_light_mask: block_light.empty_mask,
+            sky_light_arrays: LengthPrefixedVec::new(sky_light.arrays),
+            block_light_arrays: LengthPrefixedVec::new(block_light.arrays),
         })
     }
 }
+
+/// Masks and arrays for one type of light, built up section by section.
+struct LightData {
+    mask: BitSet,
+    empty_mask: BitSet,
+    arrays: Vec<ByteArray>,
+}
+
+impl Default for LightData {
+    fn default() -> Self {
+        Self {
+            mask: BitSet::new(SECTIONS + 2),
+            empty_mask: BitSet::new(SECTIONS + 2),
+            arrays: Vec::new(),
+        }
+    }
+}
+
+impl LightData {
+    /// Only sections with some light in them are sent, the rest are marked as empty so the
+    /// client knows they're dark instead of missing.
+    fn add_section(&mut self, bit: usize, light: &[u8]) {
+        if light.len() == 2048 && light.iter().any(|b| *b != 0) {
+            self.mask.set(bit, true);
+            self.arrays.push(ByteArray::new(light.to_vec()));
+        } else {
+            self.empty_mask.set(bit, true);
+        }
+    }
+}
@@ -56,6 +56,8 @@ pub struct EntityMetadata {
 pub mod constructors {
     use super::*;
     use crate::packets::outgoing::entity_metadata::extra_data_types::EntityPose;
+    use crate::packets::slot::Slot;
+    use ferrumc_core::inventory::ItemStack;
 
     impl EntityMetadata {
         fn new(index_type: EntityMetadataIndexType, value: EntityMetadataValue) -> Self {
@@ -89,6 +91,14 @@ pub mod constructors {
                 EntityMetadataValue::Entity6(EntityPose::Standing),
             )
         }
+
+        /// The stack an item entity shows
+        pub fn item_stack(stack: &ItemStack) -> Self {
+            Self::new(
+                EntityMetadataIndexType::Slot,
+                EntityMetadataValue::Item8(Slot::from_stack(Some(stack))),
+            )
+        }
     }
 }
 
@@ -101,6 +111,7 @@ mod index_type {
     #[derive(Debug, Clone, Copy)]
     pub enum EntityMetadataIndexType {
         Byte, // (0) Used for bit masks and small numbers
+        Slot, // (7) Used for item stacks
         Pose, // (21) Used for entity pose
     }
 
@@ -109,6 +120,7 @@ mod index_type {
             use EntityMetadataIndexType::*;
             let val = match self {
                 Byte => 0,
+                Slot => 7,
                 Pose => 21,
             };
 
@@ -138,6 +150,7 @@ mod index_type {
 mod value {
     use super::*;
     use crate::packets::outgoing::entity_metadata::extra_data_types::EntityPose;
+    use crate::packets::slot::Slot;
     /// Possible metadata values that can be sent
     ///
     /// Couldn't be arsed coming up with the names.
@@ -150,6 +163,7 @@ mod value {
     pub enum EntityMetadataValue {
         Entity0(EntityStateMask),
         Entity6(EntityPose),
+        Item8(Slot),
     }
 
     impl EntityMetadataValue {
@@ -158,6 +172,7 @@ mod value {
             match self {
                 Entity0(_) => 0,
                 Entity6(_) => 6,
+                Item8(_) => 8,
             }
         }
     }
@@ -1,7 +1,9 @@
+use crate::errors::NetError;
 use ferrumc_config::server_config::get_global_config;
 use ferrumc_macros::{NetEncode, packet};
 use ferrumc_net_codec::net_types::var_int::VarInt;
 use ferrumc_net_codec::net_types::prefixed_optional::PrefixedOptional;
+use ferrumc_world::errors::WorldError;
 use std::io::Write;
 
 pub use ferrumc_net_codec::net_types::global_pos::GlobalPos;
@@ -33,24 +35,57 @@ pub struct LoginPlayPacket<'a> {
 }
 
 impl<'a> LoginPlayPacket<'a> {
-    pub fn new(conn_id: i32, dimension_id: i32, death: Option<GlobalPos<'a>>) -> Self {
-        let dimension_name = match dimension_id {
-            -1 => "minecraft:the_nether",
-            1 => "minecraft:the_end",
-            _ => "minecraft:overworld",
-        };
-        Self {
+    /// Log into the dimension with the registry name `dimension_name`, e.g.
+    /// `minecraft:overworld`.
+    ///
+    /// `dimension_names` are the dimensions sent to the client, in the order their types were sent
+    /// in the `minecraft:dimension_type` registry, so the index of `dimension_name` among them is
+    /// its dimension type. Fails if `dimension_name` isn't one of them.
+    pub fn new(
+        conn_id: i32,
+        dimension_names: &'a [&'a str],
+        dimension_name: &str,
+        death: Option<GlobalPos<'a>>,
+    ) -> Result<Self, NetError> {
+        let index = dimension_names
+            .iter()
+            .position(|name| *name == dimension_name)
+            .ok_or_else(|| WorldError::UnknownDimension(dimension_name.to_string()))?;
+        Self::in_dimension(conn_id, dimension_names, index, death)
+    }
+
+    /// Log into any dimension the server hosts.
+    ///
+    /// `dimension_names` are the registry names of every dimension, in the order of
+    /// `DimensionRegistry::network_order`, and `dimension_index` is where the player's dimension
+    /// is in it. Fails if the index is out of range.
+    pub fn in_dimension(
+        conn_id: i32,
+        dimension_names: &'a [&'a str],
+        dimension_index: usize,
+        death: Option<GlobalPos<'a>>,
+    ) -> Result<Self, NetError> {
+        let dimension_name = dimension_names
+            .get(dimension_index)
+            .copied()
+            .ok_or_else(|| {
+                NetError::Misc(format!(
+                    "No dimension at index {dimension_index}, only {} were sent",
+                    dimension_names.len()
+                ))
+            })?;
+        Ok(Self {
             entity_id: conn_id,
             is_hardcore: false,
-            dimension_length: VarInt::from(3),
-            dimension_names: &["minecraft:overworld", "minecraft:the_nether", "minecraft:the_end"],
+            dimension_length: VarInt::from(dimension_names.len() as i32),
+            dimension_names,
             max_players: VarInt::from(get_global_config().max_players as i32),
             view_distance: VarInt::from(get_global_config().chunk_render_distance as i32),
             simulation_distance: VarInt::from(get_global_config().chunk_render_distance as i32),
             reduced_debug_info: false,
             enable_respawn_screen: true,
             do_limited_crafting: false,
-            dimension_type: VarInt::new(dimension_id),
+            dimension_type: VarInt::new(dimension_index as i32),
             dimension_name,
             seed_hash: 0,
             gamemode: 1,
@@ -61,6 +96,6 @@ impl<'a> LoginPlayPacket<'a> {
             portal_cooldown: VarInt::from(0),
             sea_level: VarInt::from(63),
             enforces_secure_chat: false,
-        }
+        })
     }
 }
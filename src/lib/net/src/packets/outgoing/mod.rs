@@ -17,6 +17,7 @@ pub mod login_disconnect;
 pub mod login_encryption_request;
 pub mod login_play;
 pub mod login_success;
+pub mod registry_data;
 pub mod custom_query;
 pub mod ping_response;
 pub mod set_center_chunk;
@@ -46,5 +47,7 @@ pub mod update_entity_rotation;
 pub mod block_change_ack;
 
 pub mod block_entity_data;
+pub mod block_event;
 pub mod block_update;
+pub mod section_blocks_update;
 pub(crate) mod set_compression;
@@ -0,0 +1,122 @@
+use ferrumc_macros::{packet, NBTSerialize, NetEncode};
+use ferrumc_nbt::{NBTSerializable, NBTSerializeOptions};
+use ferrumc_net_codec::net_types::length_prefixed_vec::LengthPrefixedVec;
+use ferrumc_net_codec::net_types::prefixed_optional::PrefixedOptional;
+use ferrumc_world::dimensions::{Dimension, DimensionType};
+use std::io::Write;
+
+#[derive(NetEncode)]
+#[packet(packet_id = "registry_data", state = "login")]
+pub struct RegistryDataPacket<'a> {
+    pub registry_id: &'a str,
+    pub entries: LengthPrefixedVec<RegistryEntry>,
+}
+
+#[derive(NetEncode)]
+pub struct RegistryEntry {
+    pub id: String,
+    /// The entry as network NBT. Left out, the client uses the entry it ships with.
+    pub data: PrefixedOptional<Vec<u8>>,
+}
+
+impl RegistryDataPacket<'_> {
+    /// The `minecraft:dimension_type` registry, with one type per dimension named after it.
+    ///
+    /// Entries are sent in the order of `dimensions`, so the index of a dimension in it is the id
+    /// the client knows its type by. Pass `DimensionRegistry::network_order` to line those up
+    /// with `DimensionRegistry::network_id`.
+    pub fn dimension_types(dimensions: &[Dimension]) -> Self {
+        let entries = dimensions
+            .iter()
+            .map(|dimension| {
+                let mut data = Vec::new();
+                DimensionTypeData::new(&dimension.dimension_type)
+                    .serialize(&mut data, &NBTSerializeOptions::Network);
+                RegistryEntry {
+                    id: dimension.registry_name(),
+                    data: PrefixedOptional::Some(data),
+                }
+            })
+            .collect();
+        Self {
+            registry_id: "minecraft:dimension_type",
+            entries: LengthPrefixedVec::new(entries),
+        }
+    }
+}
+
+/// A dimension type the way clients expect it. Everything that isn't configurable is taken from
+/// the vanilla dimension the type is generated like.
+#[derive(NBTSerialize)]
+struct DimensionTypeData {
+    fixed_time: Option<i64>,
+    has_skylight: bool,
+    has_ceiling: bool,
+    ultrawarm: bool,
+    natural: bool,
+    coordinate_scale: f64,
+    bed_works: bool,
+    respawn_anchor_works: bool,
+    min_y: i32,
+    height: i32,
+    logical_height: i32,
+    infiniburn: &'static str,
+    effects: &'static str,
+    ambient_light: f32,
+    piglin_safe: bool,
+    has_raids: bool,
+    monster_spawn_light_level: i32,
+    monster_spawn_block_light_limit: i32,
+}
+
+impl DimensionTypeData {
+    fn new(dimension_type: &DimensionType) -> Self {
+        let overworld = DimensionTypeData {
+            fixed_time: None,
+            has_skylight: dimension_type.has_skylight,
+            has_ceiling: false,
+            ultrawarm: dimension_type.ultrawarm(),
+            natural: true,
+            coordinate_scale: 1.0,
+            bed_works: true,
+            respawn_anchor_works: false,
+            min_y: dimension_type.min_y,
+            height: dimension_type.height as i32,
+            logical_height: dimension_type.height as i32,
+            infiniburn: "#minecraft:infiniburn_overworld",
+            effects: "minecraft:overworld",
+            ambient_light: 0.0,
+            piglin_safe: false,
+            has_raids: true,
+            monster_spawn_light_level: 0,
+            monster_spawn_block_light_limit: 0,
+        };
+        match dimension_type.generator.as_str() {
+            "nether" => DimensionTypeData {
+                fixed_time: Some(18000),
+                has_ceiling: true,
+                natural: false,
+                coordinate_scale: 8.0,
+                bed_works: false,
+                respawn_anchor_works: true,
+                infiniburn: "#minecraft:infiniburn_nether",
+                effects: "minecraft:the_nether",
+                ambient_light: 0.1,
+                piglin_safe: true,
+                has_raids: false,
+                monster_spawn_light_level: 7,
+                monster_spawn_block_light_limit: 15,
+                ..overworld
+            },
+            "end" => DimensionTypeData {
+                fixed_time: Some(6000),
+                natural: false,
+                bed_works: false,
+                infiniburn: "#minecraft:infiniburn_end",
+                effects: "minecraft:the_end",
+                ..overworld
+            },
+            _ => overworld,
+        }
+    }
+}
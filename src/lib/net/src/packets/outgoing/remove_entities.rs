@@ -1,3 +1,4 @@
+use ferrumc_core::identity::entity_id::EntityId;
 use ferrumc_core::identity::player_identity::PlayerIdentity;
 use ferrumc_macros::{NetEncode, packet};
 use ferrumc_net_codec::net_types::length_prefixed_vec::LengthPrefixedVec;
@@ -23,4 +24,17 @@ impl RemoveEntitiesPacket {
             entity_ids: LengthPrefixedVec::new(entity_ids),
         }
     }
+
+    pub fn from_entity_ids<'a, T>(entity_ids: T) -> Self
+    where
+        T: IntoIterator<Item = &'a EntityId>,
+    {
+        let entity_ids: Vec<VarInt> = entity_ids
+            .into_iter()
+            .map(|entity| VarInt::new(entity.short_uuid))
+            .collect();
+        Self {
+            entity_ids: LengthPrefixedVec::new(entity_ids),
+        }
+    }
 }
@@ -0,0 +1,81 @@
+use ferrumc_macros::{packet, NetEncode};
+use ferrumc_net_codec::encode::errors::NetEncodeError;
+use ferrumc_net_codec::encode::{NetEncode, NetEncodeOpts};
+use ferrumc_net_codec::net_types::length_prefixed_vec::LengthPrefixedVec;
+use std::io::Write;
+use tokio::io::AsyncWriteExt;
+
+/// Changes any number of blocks in one chunk section at once, instead of a [`BlockUpdate`] per
+/// block.
+///
+/// [`BlockUpdate`]: super::block_update::BlockUpdate
+#[derive(NetEncode)]
+#[packet(packet_id = "section_blocks_update", state = "play")]
+pub struct SectionBlocksUpdate {
+    /// The section's x, y and z packed into 22, 20 and 22 bits, see [`SectionBlocksUpdate::new`].
+    pub section: i64,
+    pub blocks: LengthPrefixedVec<SectionBlock>,
+}
+
+impl SectionBlocksUpdate {
+    /// `blocks` are the section-relative coordinates of each block and its new block state id.
+    pub fn new(
+        section_x: i32,
+        section_y: i32,
+        section_z: i32,
+        blocks: impl IntoIterator<Item = ((u8, u8, u8), u32)>,
+    ) -> Self {
+        let section = ((section_x as i64 & 0x3f_ffff) << 42)
+            | ((section_z as i64 & 0x3f_ffff) << 20)
+            | (section_y as i64 & 0xf_ffff);
+        let blocks = blocks
+            .into_iter()
+            .map(|((x, y, z), block)| {
+                SectionBlock(
+                    ((block as i64) << 12)
+                        | ((x as i64 & 0xf) << 8)
+                        | ((z as i64 & 0xf) << 4)
+                        | (y as i64 & 0xf),
+                )
+            })
+            .collect();
+        Self {
+            section,
+            blocks: LengthPrefixedVec::new(blocks),
+        }
+    }
+}
+
+/// A block state id and position packed into one long, sent as a VarLong.
+pub struct SectionBlock(pub i64);
+
+impl SectionBlock {
+    fn to_var_long(&self) -> Vec<u8> {
+        let mut value = self.0 as u64;
+        let mut bytes = Vec::with_capacity(10);
+        loop {
+            if value < 0x80 {
+                bytes.push(value as u8);
+                return bytes;
+            }
+            bytes.push((value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+    }
+}
+
+impl NetEncode for SectionBlock {
+    fn encode<W: Write>(&self, writer: &mut W, _: &NetEncodeOpts) -> Result<(), NetEncodeError> {
+        writer.write_all(&self.to_var_long())?;
+        Ok(())
+    }
+
+    async fn encode_async<W: tokio::io::AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        _: &NetEncodeOpts,
+    ) -> Result<(), NetEncodeError> {
+        writer.write_all(&self.to_var_long()).await?;
+        Ok(())
+    }
+}
@@ -80,4 +80,14 @@ const PLAYER_ID: u64 = get_registry_entry!("minecraft:entity_type.entries.minecr
                 velocity_z: 0,
             }
         }
+
+        /// Start the entity off moving, in blocks per game tick.
+        pub fn with_velocity(mut self, (x, y, z): (f64, f64, f64)) -> Self {
+            // Sent in 1/8000ths of a block per tick
+            let encode = |v: f64| (v * 8000.0).clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+            self.velocity_x = encode(x);
+            self.velocity_y = encode(y);
+            self.velocity_z = encode(z);
+            self
+        }
     }
@@ -14,14 +14,14 @@ pub struct ItemData {
 impl From<&ItemStack> for ItemData {
     fn from(stack: &ItemStack) -> Self {
         Self {
-            item_id: VarInt::new(stack.item.0 as i32),
+            item_id: stack.item.to_varint(),
             count: stack.count,
             nbt: stack.nbt.clone().unwrap_or_default(),
         }
     }
 }
 
-#[derive(NetEncode, NetDecode)]
+#[derive(NetEncode, NetDecode, Clone)]
 pub struct Slot {
     pub item: PrefixedOptional<ItemData>,
 }
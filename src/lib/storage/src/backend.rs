@@ -0,0 +1,81 @@
+use crate::errors::StorageError;
+use std::fmt::Debug;
+use std::path::Path;
+
+/// A key/value store made of named tables, with `u128` keys and byte values.
+///
+/// This is everything the world and player data need from a database, so the engine behind them
+/// can be swapped out. [`LmdbBackend`](crate::lmdb::LmdbBackend) is the default, with
+/// [`RedbBackend`](crate::redb_backend::RedbBackend) as an alternative and
+/// [`MemoryBackend`](crate::memory::MemoryBackend) for tests.
+///
+/// Every backend behaves the same way as LMDB does:
+/// - Reading, updating or deleting from a table that doesn't exist returns
+///   [`StorageError::TableError`].
+/// - [`insert`](Self::insert), [`batch_insert`](Self::batch_insert) and
+///   [`batch_upsert`](Self::batch_upsert) create the table if needed,
+///   [`upsert`](Self::upsert) doesn't.
+/// - Batch operations either fully happen or not at all.
+pub trait StorageBackend: Debug + Send + Sync {
+    /// Insert a new key, failing with [`StorageError::KeyExists`] if it's already there.
+    fn insert(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError>;
+
+    fn get(&self, table: String, key: u128) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Delete a key, failing with [`StorageError::KeyNotFound`] if it isn't there.
+    fn delete(&self, table: String, key: u128) -> Result<(), StorageError>;
+
+    /// Replace the value of a key, failing with [`StorageError::KeyNotFound`] if it isn't there.
+    fn update(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError>;
+
+    /// Insert or replace the value of a key.
+    fn upsert(&self, table: String, key: u128, value: Vec<u8>) -> Result<bool, StorageError>;
+
+    fn batch_upsert(&self, table: String, data: Vec<(u128, Vec<u8>)>) -> Result<(), StorageError>;
+
+    fn batch_insert(&self, table: String, data: Vec<(u128, Vec<u8>)>) -> Result<(), StorageError>;
+
+    /// Get the values of several keys, in the same order as the keys.
+    fn batch_get(
+        &self,
+        table: String,
+        keys: Vec<u128>,
+    ) -> Result<Vec<Option<Vec<u8>>>, StorageError>;
+
+    fn exists(&self, table: String, key: u128) -> Result<bool, StorageError>;
+
+    fn table_exists(&self, table: String) -> Result<bool, StorageError>;
+
+    /// Get every key in a table, in ascending order.
+    fn get_keys(&self, table: String) -> Result<Vec<u128>, StorageError>;
+
+    fn create_table(&self, table: String) -> Result<(), StorageError>;
+
+    /// Make sure everything written so far is on disk.
+    fn flush(&self) -> Result<(), StorageError>;
+
+    fn close(&self) -> Result<(), StorageError> {
+        self.flush()
+    }
+
+    /// A short description of the backend and its state, for logging.
+    fn details(&self) -> String;
+
+    /// Copy a consistent snapshot of the database to a single file at `path`, without stopping
+    /// reads or writes.
+    ///
+    /// Not every backend can do this, the default returns [`StorageError::Unsupported`].
+    fn backup(&self, path: &Path, compact: bool) -> Result<(), StorageError> {
+        let _ = (path, compact);
+        Err(StorageError::Unsupported(format!(
+            "Backups are not supported by {}",
+            self.details()
+        )))
+    }
+
+    /// The name of the file a [`backup`](Self::backup) takes the place of in a database folder,
+    /// or `None` for backends that can't be backed up.
+    fn data_file(&self) -> Option<&'static str> {
+        None
+    }
+}
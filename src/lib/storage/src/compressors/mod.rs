@@ -4,6 +4,8 @@ use crate::compressors::gzip::{compress_gzip, decompress_gzip};
 use crate::compressors::zlib::{compress_zlib, decompress_zlib};
 use crate::compressors::zstd::{compress_zstd, decompress_zstd};
 use crate::errors::StorageError;
+use std::fmt::Display;
+use std::str::FromStr;
 
 pub mod brotli;
 pub mod deflate;
@@ -11,7 +13,7 @@ pub mod gzip;
 pub mod zlib;
 pub mod zstd;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CompressorType {
     Gzip,
     Zstd,
@@ -20,7 +22,70 @@ pub enum CompressorType {
     Zlib,
 }
 
-#[derive(Clone, Copy)]
+impl CompressorType {
+    /// A stable id for the algorithm, so data can record what it was compressed with.
+    ///
+    /// Don't change these, anything already written with them would become unreadable.
+    pub fn id(&self) -> u8 {
+        match self {
+            CompressorType::Gzip => 1,
+            CompressorType::Zstd => 2,
+            CompressorType::Brotli => 3,
+            CompressorType::Deflate => 4,
+            CompressorType::Zlib => 5,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(CompressorType::Gzip),
+            2 => Some(CompressorType::Zstd),
+            3 => Some(CompressorType::Brotli),
+            4 => Some(CompressorType::Deflate),
+            5 => Some(CompressorType::Zlib),
+            _ => None,
+        }
+    }
+
+    /// The highest compression level the algorithm supports.
+    pub fn max_level(&self) -> u32 {
+        match self {
+            CompressorType::Zstd => 22,
+            CompressorType::Brotli => 11,
+            CompressorType::Gzip | CompressorType::Deflate | CompressorType::Zlib => 9,
+        }
+    }
+}
+
+impl FromStr for CompressorType {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gzip" => Ok(CompressorType::Gzip),
+            "zstd" => Ok(CompressorType::Zstd),
+            "brotli" => Ok(CompressorType::Brotli),
+            "deflate" => Ok(CompressorType::Deflate),
+            "zlib" => Ok(CompressorType::Zlib),
+            _ => Err(StorageError::UnknownCompressor(s.to_string())),
+        }
+    }
+}
+
+impl Display for CompressorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CompressorType::Gzip => "gzip",
+            CompressorType::Zstd => "zstd",
+            CompressorType::Brotli => "brotli",
+            CompressorType::Deflate => "deflate",
+            CompressorType::Zlib => "zlib",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Compressor {
     pub algorithm: CompressorType,
     pub level: u32,
@@ -51,3 +116,27 @@ impl Compressor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_round_trip() {
+        for algorithm in [
+            CompressorType::Gzip,
+            CompressorType::Zstd,
+            CompressorType::Brotli,
+            CompressorType::Deflate,
+            CompressorType::Zlib,
+        ] {
+            assert_eq!(CompressorType::from_id(algorithm.id()), Some(algorithm));
+            assert_eq!(
+                algorithm.to_string().parse::<CompressorType>().unwrap(),
+                algorithm
+            );
+        }
+        assert!(CompressorType::from_id(0).is_none());
+        assert!("lzma".parse::<CompressorType>().is_err());
+    }
+}
@@ -9,6 +9,8 @@ pub enum StorageError {
     CompressionError(String),
     #[error("Decompression error: {0}")]
     DecompressionError(String),
+    #[error("Unknown compressor: {0}")]
+    UnknownCompressor(String),
     #[error("Invalid path")]
     InvalidPath,
     #[error("Failed to write to database: {0}")]
@@ -35,6 +37,8 @@ pub enum StorageError {
     GenericIoError(io::Error),
     #[error("Database error: {0}")]
     DatabaseError(String),
+    #[error("Unsupported operation: {0}")]
+    Unsupported(String),
 }
 
 impl From<io::Error> for StorageError {
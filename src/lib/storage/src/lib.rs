@@ -1,6 +1,10 @@
+pub mod backend;
+pub mod compressors;
 pub mod errors;
 pub mod lmdb;
+pub mod memory;
 pub mod player_data;
+pub mod redb_backend;
 
 #[cfg(test)]
 mod tests;
@@ -1,11 +1,12 @@
+use crate::backend::StorageBackend;
 use crate::errors::StorageError;
 use heed;
 use heed::byteorder::BigEndian;
 use heed::types::{Bytes, U128};
-use heed::{Database, Env, EnvOpenOptions, WithoutTls};
+use heed::{CompactionOption, Database, Env, EnvOpenOptions, WithoutTls};
 use parking_lot::Mutex;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
@@ -25,6 +26,7 @@ impl From<heed::Error> for StorageError {
 }
 
 impl LmdbBackend {
+    /// Open the environment at `store_path`, with the map size from the global config.
     pub fn initialize(store_path: Option<PathBuf>) -> Result<Self, StorageError>
     where
         Self: Sized,
@@ -32,16 +34,23 @@ impl LmdbBackend {
         let Some(checked_path) = store_path else {
             return Err(StorageError::InvalidPath);
         };
-        if !checked_path.exists() {
-            std::fs::create_dir_all(&checked_path)?;
-        }
-        // Convert the map size from GB to bytes and round it to the nearest page size.
+        // Convert the map size from GB to bytes
         let map_size = ferrumc_config::server_config::get_global_config()
             .database
             .map_size as usize
             * 1024
             * 1024
             * 1024;
+        Self::open(checked_path, map_size)
+    }
+
+    /// Open the environment at `store_path` with the given map size in bytes, without touching
+    /// the global config.
+    pub fn open(checked_path: PathBuf, map_size: usize) -> Result<Self, StorageError> {
+        if !checked_path.exists() {
+            std::fs::create_dir_all(&checked_path)?;
+        }
+        // Round the map size to the nearest page size.
         let rounded_map_size = ((map_size as f64 / page_size::get() as f64).round()
             * page_size::get() as f64) as usize;
         unsafe {
@@ -50,7 +59,7 @@ impl LmdbBackend {
                     EnvOpenOptions::new()
                         .read_txn_without_tls()
                         // Change this as more tables are needed.
-                        .max_dbs(2)
+                        .max_dbs(8)
                         .map_size(rounded_map_size)
                         .open(checked_path)
                         .map_err(|e| StorageError::DatabaseInitError(e.to_string()))?,
@@ -59,8 +68,10 @@ impl LmdbBackend {
             Ok(backend)
         }
     }
+}
 
-    pub fn insert(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError> {
+impl StorageBackend for LmdbBackend {
+    fn insert(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError> {
         let env = self.env.lock();
         let mut rw_txn = env.write_txn()?;
         let db: Database<U128<BigEndian>, Bytes> =
@@ -73,7 +84,7 @@ impl LmdbBackend {
         Ok(())
     }
 
-    pub fn get(&self, table: String, key: u128) -> Result<Option<Vec<u8>>, StorageError> {
+    fn get(&self, table: String, key: u128) -> Result<Option<Vec<u8>>, StorageError> {
         let env = self.env.lock();
         let ro_txn = env.read_txn()?;
         let db: Database<U128<BigEndian>, Bytes> = env
@@ -87,7 +98,7 @@ impl LmdbBackend {
         }
     }
 
-    pub fn delete(&self, table: String, key: u128) -> Result<(), StorageError> {
+    fn delete(&self, table: String, key: u128) -> Result<(), StorageError> {
         let env = self.env.lock();
         let mut rw_txn = env.write_txn()?;
         let db: Database<U128<BigEndian>, Bytes> = env
@@ -101,7 +112,7 @@ impl LmdbBackend {
         Ok(())
     }
 
-    pub fn update(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError> {
+    fn update(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError> {
         let env = self.env.lock();
         let mut rw_txn = env.write_txn()?;
         let db: Database<U128<BigEndian>, Bytes> = env
@@ -115,7 +126,7 @@ impl LmdbBackend {
         Ok(())
     }
 
-    pub fn upsert(&self, table: String, key: u128, value: Vec<u8>) -> Result<bool, StorageError> {
+    fn upsert(&self, table: String, key: u128, value: Vec<u8>) -> Result<bool, StorageError> {
         let env = self.env.lock();
         let mut rw_txn = env.write_txn()?;
         let db: Database<U128<BigEndian>, Bytes> = env
@@ -126,11 +137,7 @@ impl LmdbBackend {
         Ok(true)
     }
 
-    pub fn batch_upsert(
-        &self,
-        table: String,
-        data: Vec<(u128, Vec<u8>)>,
-    ) -> Result<(), StorageError> {
+    fn batch_upsert(&self, table: String, data: Vec<(u128, Vec<u8>)>) -> Result<(), StorageError> {
         let env = self.env.lock();
         let mut rw_txn = env.write_txn()?;
 
@@ -161,7 +168,7 @@ impl LmdbBackend {
         Ok(())
     }
 
-    pub fn exists(&self, table: String, key: u128) -> Result<bool, StorageError> {
+    fn exists(&self, table: String, key: u128) -> Result<bool, StorageError> {
         let env = self.env.lock();
         let ro_txn = env.read_txn()?;
         let db: Database<U128<BigEndian>, Bytes> = env
@@ -170,22 +177,18 @@ impl LmdbBackend {
         Ok(db.get(&ro_txn, &key)?.is_some())
     }
 
-    pub fn table_exists(&self, table: String) -> Result<bool, StorageError> {
+    fn table_exists(&self, table: String) -> Result<bool, StorageError> {
         let env = self.env.lock();
         let ro_txn = env.read_txn()?;
         let db = env.open_database::<U128<BigEndian>, Bytes>(&ro_txn, Some(&table))?;
         Ok(db.is_some())
     }
 
-    pub fn details(&self) -> String {
+    fn details(&self) -> String {
         format!("LMDB (heed 0.20.5): {:?}", self.env.lock().info())
     }
 
-    pub fn batch_insert(
-        &self,
-        table: String,
-        data: Vec<(u128, Vec<u8>)>,
-    ) -> Result<(), StorageError> {
+    fn batch_insert(&self, table: String, data: Vec<(u128, Vec<u8>)>) -> Result<(), StorageError> {
         let env = self.env.lock();
         let mut rw_txn = env.write_txn()?;
         let db = env.create_database::<U128<BigEndian>, Bytes>(&mut rw_txn, Some(&table))?;
@@ -204,7 +207,7 @@ impl LmdbBackend {
         Ok(())
     }
 
-    pub fn batch_get(
+    fn batch_get(
         &self,
         table: String,
         keys: Vec<u128>,
@@ -226,14 +229,29 @@ impl LmdbBackend {
         Ok(values)
     }
 
-    pub fn flush(&self) -> Result<(), StorageError> {
+    /// Get every key in a table, in ascending order.
+    fn get_keys(&self, table: String) -> Result<Vec<u128>, StorageError> {
+        let env = self.env.lock();
+        let ro_txn = env.read_txn()?;
+        let db: Database<U128<BigEndian>, Bytes> = env
+            .open_database(&ro_txn, Some(&table))?
+            .ok_or(StorageError::TableError("Table not found".to_string()))?;
+        let mut keys = Vec::new();
+        for entry in db.iter(&ro_txn)? {
+            let (key, _) = entry?;
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    fn flush(&self) -> Result<(), StorageError> {
         let env = self.env.lock();
         env.clear_stale_readers()?;
         env.force_sync()?;
         Ok(())
     }
 
-    pub fn create_table(&self, table: String) -> Result<(), StorageError> {
+    fn create_table(&self, table: String) -> Result<(), StorageError> {
         let env = self.env.lock();
         let mut rw_txn = env.write_txn()?;
         env.create_database::<U128<BigEndian>, Bytes>(&mut rw_txn, Some(&table))?;
@@ -241,7 +259,29 @@ impl LmdbBackend {
         Ok(())
     }
 
-    pub fn close(&self) -> Result<(), StorageError> {
+    /// Copy a consistent snapshot of the whole environment to a single file at `path`.
+    ///
+    /// The copy runs in its own read transaction, so other reads and writes carry on while it
+    /// happens and none of them end up half in the copy. With `compact` set, free pages are left
+    /// out and the copy is renumbered, which takes longer but gives a smaller file. The file at
+    /// `path` can be used as the `data.mdb` of a new environment.
+    fn backup(&self, path: &Path, compact: bool) -> Result<(), StorageError> {
+        // Clone the environment so the lock isn't held for the whole copy
+        let env = self.env.lock().clone();
+        let option = if compact {
+            CompactionOption::Enabled
+        } else {
+            CompactionOption::Disabled
+        };
+        env.copy_to_path(path, option)?;
+        Ok(())
+    }
+
+    fn data_file(&self) -> Option<&'static str> {
+        Some("data.mdb")
+    }
+
+    fn close(&self) -> Result<(), StorageError> {
         self.flush()?;
         Ok(())
     }
@@ -299,6 +339,49 @@ mod tests {
         remove_dir_all(path).unwrap();
     }
 
+    #[test]
+    fn test_get_keys() {
+        let path = tempdir().unwrap().keep();
+        {
+            let backend = LmdbBackend::initialize(Some(path.clone())).unwrap();
+            backend.create_table("test_table".to_string()).unwrap();
+            let data = vec![(3u128, vec![1]), (1u128, vec![2]), (2u128, vec![3])];
+            backend
+                .batch_insert("test_table".to_string(), data)
+                .unwrap();
+            let keys = backend.get_keys("test_table".to_string()).unwrap();
+            assert_eq!(keys, vec![1, 2, 3]);
+        }
+        remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_backup() {
+        let path = tempdir().unwrap().keep();
+        let backup_path = tempdir().unwrap().keep();
+        {
+            let backend = LmdbBackend::initialize(Some(path.clone())).unwrap();
+            backend.create_table("test_table".to_string()).unwrap();
+            backend
+                .insert("test_table".to_string(), 1, vec![1, 2, 3])
+                .unwrap();
+            backend.backup(&backup_path.join("data.mdb"), true).unwrap();
+            // Changes after the backup shouldn't be in it
+            backend
+                .insert("test_table".to_string(), 2, vec![4, 5, 6])
+                .unwrap();
+
+            let restored = LmdbBackend::initialize(Some(backup_path.clone())).unwrap();
+            assert_eq!(
+                restored.get("test_table".to_string(), 1).unwrap(),
+                Some(vec![1, 2, 3])
+            );
+            assert_eq!(restored.get("test_table".to_string(), 2).unwrap(), None);
+        }
+        remove_dir_all(path).unwrap();
+        remove_dir_all(backup_path).unwrap();
+    }
+
     #[test]
     fn test_concurrent_write() {
         let path = tempdir().unwrap().keep();
@@ -0,0 +1,177 @@
+use crate::backend::StorageBackend;
+use crate::errors::StorageError;
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+type Table = BTreeMap<u128, Vec<u8>>;
+
+/// A backend that keeps everything in memory and loses it when dropped.
+///
+/// Doesn't need a folder or the global config, which makes it handy for tests. Clones share the
+/// same data.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackend {
+    tables: Arc<RwLock<HashMap<String, Table>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn table_not_found() -> StorageError {
+    StorageError::TableError("Table not found".to_string())
+}
+
+impl StorageBackend for MemoryBackend {
+    fn insert(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError> {
+        let mut tables = self.tables.write();
+        let table = tables.entry(table).or_default();
+        if table.contains_key(&key) {
+            return Err(StorageError::KeyExists(key as u64));
+        }
+        table.insert(key, value);
+        Ok(())
+    }
+
+    fn get(&self, table: String, key: u128) -> Result<Option<Vec<u8>>, StorageError> {
+        let tables = self.tables.read();
+        let table = tables.get(&table).ok_or_else(table_not_found)?;
+        Ok(table.get(&key).cloned())
+    }
+
+    fn delete(&self, table: String, key: u128) -> Result<(), StorageError> {
+        let mut tables = self.tables.write();
+        let table = tables.get_mut(&table).ok_or_else(table_not_found)?;
+        table
+            .remove(&key)
+            .map(|_| ())
+            .ok_or(StorageError::KeyNotFound(key as u64))
+    }
+
+    fn update(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError> {
+        let mut tables = self.tables.write();
+        let table = tables.get_mut(&table).ok_or_else(table_not_found)?;
+        let Some(existing) = table.get_mut(&key) else {
+            return Err(StorageError::KeyNotFound(key as u64));
+        };
+        *existing = value;
+        Ok(())
+    }
+
+    fn upsert(&self, table: String, key: u128, value: Vec<u8>) -> Result<bool, StorageError> {
+        let mut tables = self.tables.write();
+        let table = tables.get_mut(&table).ok_or_else(table_not_found)?;
+        table.insert(key, value);
+        Ok(true)
+    }
+
+    fn batch_upsert(&self, table: String, data: Vec<(u128, Vec<u8>)>) -> Result<(), StorageError> {
+        let mut tables = self.tables.write();
+        tables.entry(table).or_default().extend(data);
+        Ok(())
+    }
+
+    fn batch_insert(&self, table: String, data: Vec<(u128, Vec<u8>)>) -> Result<(), StorageError> {
+        let mut tables = self.tables.write();
+        let table = tables.entry(table).or_default();
+        // Check everything first so a failed batch doesn't leave half of it behind
+        if let Some((key, _)) = data.iter().find(|(key, _)| table.contains_key(key)) {
+            return Err(StorageError::KeyExists(*key as u64));
+        }
+        table.extend(data);
+        Ok(())
+    }
+
+    fn batch_get(
+        &self,
+        table: String,
+        keys: Vec<u128>,
+    ) -> Result<Vec<Option<Vec<u8>>>, StorageError> {
+        let tables = self.tables.read();
+        let table = tables.get(&table).ok_or_else(table_not_found)?;
+        Ok(keys.iter().map(|key| table.get(key).cloned()).collect())
+    }
+
+    fn exists(&self, table: String, key: u128) -> Result<bool, StorageError> {
+        let tables = self.tables.read();
+        let table = tables.get(&table).ok_or_else(table_not_found)?;
+        Ok(table.contains_key(&key))
+    }
+
+    fn table_exists(&self, table: String) -> Result<bool, StorageError> {
+        Ok(self.tables.read().contains_key(&table))
+    }
+
+    fn get_keys(&self, table: String) -> Result<Vec<u128>, StorageError> {
+        let tables = self.tables.read();
+        let table = tables.get(&table).ok_or_else(table_not_found)?;
+        Ok(table.keys().copied().collect())
+    }
+
+    fn create_table(&self, table: String) -> Result<(), StorageError> {
+        self.tables.write().entry(table).or_default();
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn details(&self) -> String {
+        format!("In-memory ({} tables)", self.tables.read().len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_table() {
+        let backend = MemoryBackend::new();
+        assert!(matches!(
+            backend.get("test_table".to_string(), 1),
+            Err(StorageError::TableError(_))
+        ));
+        assert!(backend
+            .upsert("test_table".to_string(), 1, vec![1])
+            .is_err());
+        assert!(!backend.table_exists("test_table".to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_batch_insert_is_all_or_nothing() {
+        let backend = MemoryBackend::new();
+        backend
+            .insert("test_table".to_string(), 2, vec![2])
+            .unwrap();
+        assert!(backend
+            .batch_insert("test_table".to_string(), vec![(1, vec![1]), (2, vec![3])])
+            .is_err());
+        assert_eq!(backend.get_keys("test_table".to_string()).unwrap(), vec![2]);
+        assert_eq!(
+            backend.get("test_table".to_string(), 2).unwrap(),
+            Some(vec![2])
+        );
+    }
+
+    #[test]
+    fn test_clones_share_data() {
+        let backend = MemoryBackend::new();
+        let clone = backend.clone();
+        backend
+            .batch_upsert("test_table".to_string(), vec![(1, vec![1]), (2, vec![2])])
+            .unwrap();
+        assert_eq!(
+            clone
+                .batch_get("test_table".to_string(), vec![2, 3, 1])
+                .unwrap(),
+            vec![Some(vec![2]), None, Some(vec![1])]
+        );
+        clone.delete("test_table".to_string(), 1).unwrap();
+        assert!(!backend.exists("test_table".to_string(), 1).unwrap());
+    }
+}
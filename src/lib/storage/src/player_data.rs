@@ -3,10 +3,15 @@ use ferrumc_nbt::{FromNbt, NBTSerializable, NBTSerializeOptions, NbtTape};
 use serde::{Deserialize, Serialize};
 
 use crate::errors::StorageError;
-use crate::lmdb::LmdbBackend;
+use crate::backend::StorageBackend;
+
+/// The layout of the items in [`PlayerData`]. Version 1 stores item ids, player data saved
+/// before that has block state ids and no version at all.
+pub const ITEM_FORMAT_VERSION: u32 = 1;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, NBTSerialize, NBTDeserialize)]
 pub struct ItemStackData {
+    /// The item's protocol id.
     pub item: u32,
     pub count: u8,
     pub max_stack_size: u8,
@@ -34,16 +39,34 @@ pub struct PlayerStatsData {
     pub mobs_killed: u32,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, NBTSerialize, NBTDeserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, NBTSerialize, NBTDeserialize)]
 pub struct PlayerData {
     pub inventory: InventoryData,
     pub position: PositionData,
     pub stats: PlayerStatsData,
     pub advancements: Vec<String>,
+    /// See [`ITEM_FORMAT_VERSION`]. None for player data saved before items had their own ids.
+    pub item_format: Option<u32>,
+    /// The dimension [`Self::position`] is in. None means the overworld, which is also where
+    /// players saved before dimensions were kept are.
+    pub dimension: Option<String>,
+}
+
+impl Default for PlayerData {
+    fn default() -> Self {
+        Self {
+            inventory: InventoryData::default(),
+            position: PositionData::default(),
+            stats: PlayerStatsData::default(),
+            advancements: Vec::new(),
+            item_format: Some(ITEM_FORMAT_VERSION),
+            dimension: None,
+        }
+    }
 }
 
 pub fn save_player_data(
-    db: &LmdbBackend,
+    db: &dyn StorageBackend,
     uuid: u128,
     data: &PlayerData,
 ) -> Result<(), StorageError> {
@@ -56,7 +79,7 @@ pub fn save_player_data(
     Ok(())
 }
 
-pub fn load_player_data(db: &LmdbBackend, uuid: u128) -> Result<PlayerData, StorageError> {
+pub fn load_player_data(db: &dyn StorageBackend, uuid: u128) -> Result<PlayerData, StorageError> {
     match db.get("player_data".to_string(), uuid) {
         Ok(Some(bytes)) => {
             let mut tape = NbtTape::new(&bytes);
@@ -0,0 +1,367 @@
+use crate::backend::StorageBackend;
+use crate::errors::StorageError;
+use redb::{
+    CommitError, Database, DatabaseError, ReadableTable, TableDefinition, TableError, TableHandle,
+    TransactionError, WriteTransaction,
+};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The file the database is kept in, inside the database folder.
+const DATA_FILE: &str = "data.redb";
+
+/// A backend using [redb](https://www.redb.org/), a pure Rust embedded database.
+///
+/// Everything is kept in a single `data.redb` file, and unlike LMDB it doesn't need a map size.
+#[derive(Clone)]
+pub struct RedbBackend {
+    db: Arc<Database>,
+    path: PathBuf,
+}
+
+impl Debug for RedbBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedbBackend")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl From<DatabaseError> for StorageError {
+    fn from(err: DatabaseError) -> Self {
+        StorageError::DatabaseInitError(err.to_string())
+    }
+}
+
+impl From<TransactionError> for StorageError {
+    fn from(err: TransactionError) -> Self {
+        StorageError::DatabaseError(err.to_string())
+    }
+}
+
+impl From<TableError> for StorageError {
+    fn from(err: TableError) -> Self {
+        match err {
+            TableError::TableDoesNotExist(_) => {
+                StorageError::TableError("Table not found".to_string())
+            }
+            _ => StorageError::TableError(err.to_string()),
+        }
+    }
+}
+
+impl From<redb::StorageError> for StorageError {
+    fn from(err: redb::StorageError) -> Self {
+        match err {
+            redb::StorageError::Io(e) => StorageError::GenericIoError(e),
+            _ => StorageError::DatabaseError(err.to_string()),
+        }
+    }
+}
+
+impl From<CommitError> for StorageError {
+    fn from(err: CommitError) -> Self {
+        StorageError::CommitError(err.to_string())
+    }
+}
+
+fn definition(table: &str) -> TableDefinition<'_, u128, &'static [u8]> {
+    TableDefinition::new(table)
+}
+
+fn has_table(txn: &WriteTransaction, table: &str) -> Result<bool, StorageError> {
+    Ok(txn.list_tables()?.any(|handle| handle.name() == table))
+}
+
+impl RedbBackend {
+    /// Open or create the database in the folder at `store_path`.
+    pub fn initialize(store_path: Option<PathBuf>) -> Result<Self, StorageError> {
+        let Some(checked_path) = store_path else {
+            return Err(StorageError::InvalidPath);
+        };
+        if !checked_path.exists() {
+            std::fs::create_dir_all(&checked_path)?;
+        }
+        let path = checked_path.join(DATA_FILE);
+        let db = Database::create(&path)?;
+        Ok(RedbBackend {
+            db: Arc::new(db),
+            path,
+        })
+    }
+}
+
+impl StorageBackend for RedbBackend {
+    fn insert(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError> {
+        let txn = self.db.begin_write()?;
+        {
+            let mut db = txn.open_table(definition(&table))?;
+            if db.get(key)?.is_some() {
+                return Err(StorageError::KeyExists(key as u64));
+            }
+            db.insert(key, value.as_slice())?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn get(&self, table: String, key: u128) -> Result<Option<Vec<u8>>, StorageError> {
+        let txn = self.db.begin_read()?;
+        let db = txn.open_table(definition(&table))?;
+        Ok(db.get(key)?.map(|value| value.value().to_vec()))
+    }
+
+    fn delete(&self, table: String, key: u128) -> Result<(), StorageError> {
+        let txn = self.db.begin_write()?;
+        if !has_table(&txn, &table)? {
+            return Err(StorageError::TableError("Table not found".to_string()));
+        }
+        {
+            let mut db = txn.open_table(definition(&table))?;
+            if db.remove(key)?.is_none() {
+                return Err(StorageError::KeyNotFound(key as u64));
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn update(&self, table: String, key: u128, value: Vec<u8>) -> Result<(), StorageError> {
+        let txn = self.db.begin_write()?;
+        if !has_table(&txn, &table)? {
+            return Err(StorageError::TableError("Table not found".to_string()));
+        }
+        {
+            let mut db = txn.open_table(definition(&table))?;
+            if db.get(key)?.is_none() {
+                return Err(StorageError::KeyNotFound(key as u64));
+            }
+            db.insert(key, value.as_slice())?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn upsert(&self, table: String, key: u128, value: Vec<u8>) -> Result<bool, StorageError> {
+        let txn = self.db.begin_write()?;
+        if !has_table(&txn, &table)? {
+            return Err(StorageError::TableError("Table not found".to_string()));
+        }
+        {
+            let mut db = txn.open_table(definition(&table))?;
+            db.insert(key, value.as_slice())?;
+        }
+        txn.commit()?;
+        Ok(true)
+    }
+
+    fn batch_upsert(&self, table: String, data: Vec<(u128, Vec<u8>)>) -> Result<(), StorageError> {
+        // Sorted inserts are a lot faster for B-trees
+        let sorted: BTreeMap<u128, Vec<u8>> = data.into_iter().collect();
+        let txn = self.db.begin_write()?;
+        {
+            let mut db = txn.open_table(definition(&table))?;
+            for (key, value) in &sorted {
+                db.insert(key, value.as_slice())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn batch_insert(&self, table: String, data: Vec<(u128, Vec<u8>)>) -> Result<(), StorageError> {
+        let sorted: BTreeMap<u128, Vec<u8>> = data.into_iter().collect();
+        let txn = self.db.begin_write()?;
+        {
+            let mut db = txn.open_table(definition(&table))?;
+            for (key, value) in &sorted {
+                if db.get(key)?.is_some() {
+                    // Dropping the transaction without committing throws the batch away
+                    return Err(StorageError::KeyExists(*key as u64));
+                }
+                db.insert(key, value.as_slice())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn batch_get(
+        &self,
+        table: String,
+        keys: Vec<u128>,
+    ) -> Result<Vec<Option<Vec<u8>>>, StorageError> {
+        let txn = self.db.begin_read()?;
+        let db = txn.open_table(definition(&table))?;
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            values.push(db.get(key)?.map(|value| value.value().to_vec()));
+        }
+        Ok(values)
+    }
+
+    fn exists(&self, table: String, key: u128) -> Result<bool, StorageError> {
+        let txn = self.db.begin_read()?;
+        let db = txn.open_table(definition(&table))?;
+        Ok(db.get(key)?.is_some())
+    }
+
+    fn table_exists(&self, table: String) -> Result<bool, StorageError> {
+        let txn = self.db.begin_read()?;
+        match txn.open_table(definition(&table)) {
+            Ok(_) => Ok(true),
+            Err(TableError::TableDoesNotExist(_)) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_keys(&self, table: String) -> Result<Vec<u128>, StorageError> {
+        let txn = self.db.begin_read()?;
+        let db = txn.open_table(definition(&table))?;
+        let mut keys = Vec::new();
+        for entry in db.iter()? {
+            let (key, _) = entry?;
+            keys.push(key.value());
+        }
+        Ok(keys)
+    }
+
+    fn create_table(&self, table: String) -> Result<(), StorageError> {
+        let txn = self.db.begin_write()?;
+        txn.open_table(definition(&table))?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), StorageError> {
+        // Every commit is already durable
+        Ok(())
+    }
+
+    fn details(&self) -> String {
+        format!("redb: {}", self.path.display())
+    }
+
+    /// Copy a consistent snapshot of every table into a new database at `path`.
+    ///
+    /// redb can't copy a database file itself, so the tables are read in a single read
+    /// transaction, which other reads and writes don't affect, and written into the copy. The
+    /// copy has no free pages to begin with, so `compact` makes no difference. The file at `path`
+    /// can be used as the `data.redb` of a new database.
+    fn backup(&self, path: &Path, compact: bool) -> Result<(), StorageError> {
+        let _ = compact;
+        let snapshot = self.db.begin_read()?;
+        let copy = Database::create(path)?;
+        let txn = copy.begin_write()?;
+        for handle in snapshot.list_tables()? {
+            let source = snapshot.open_table(definition(handle.name()))?;
+            let mut destination = txn.open_table(definition(handle.name()))?;
+            for entry in source.iter()? {
+                let (key, value) = entry?;
+                destination.insert(key.value(), value.value())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn data_file(&self) -> Option<&'static str> {
+        Some(DATA_FILE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::remove_dir_all;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_and_reopen() {
+        let path = tempdir().unwrap().keep();
+        {
+            let backend = RedbBackend::initialize(Some(path.clone())).unwrap();
+            assert!(!backend.table_exists("test_table".to_string()).unwrap());
+            backend
+                .batch_insert("test_table".to_string(), vec![(3, vec![3]), (1, vec![1])])
+                .unwrap();
+            backend
+                .upsert("test_table".to_string(), 2, vec![2])
+                .unwrap();
+        }
+        {
+            let backend = RedbBackend::initialize(Some(path.clone())).unwrap();
+            assert_eq!(
+                backend.get_keys("test_table".to_string()).unwrap(),
+                vec![1, 2, 3]
+            );
+            assert_eq!(
+                backend.get("test_table".to_string(), 3).unwrap(),
+                Some(vec![3])
+            );
+        }
+        remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_backup() {
+        let path = tempdir().unwrap().keep();
+        let backup_path = tempdir().unwrap().keep();
+        {
+            let backend = RedbBackend::initialize(Some(path.clone())).unwrap();
+            backend
+                .insert("test_table".to_string(), 1, vec![1, 2, 3])
+                .unwrap();
+            backend.create_table("empty_table".to_string()).unwrap();
+            backend.backup(&backup_path.join(DATA_FILE), true).unwrap();
+            // Changes after the backup shouldn't be in it
+            backend
+                .insert("test_table".to_string(), 2, vec![4, 5, 6])
+                .unwrap();
+
+            let restored = RedbBackend::initialize(Some(backup_path.clone())).unwrap();
+            assert_eq!(
+                restored.get("test_table".to_string(), 1).unwrap(),
+                Some(vec![1, 2, 3])
+            );
+            assert_eq!(restored.get("test_table".to_string(), 2).unwrap(), None);
+            assert!(restored.table_exists("empty_table".to_string()).unwrap());
+        }
+        remove_dir_all(path).unwrap();
+        remove_dir_all(backup_path).unwrap();
+    }
+
+    #[test]
+    fn test_matches_lmdb_errors() {
+        let path = tempdir().unwrap().keep();
+        {
+            let backend = RedbBackend::initialize(Some(path.clone())).unwrap();
+            assert!(matches!(
+                backend.get("test_table".to_string(), 1),
+                Err(StorageError::TableError(_))
+            ));
+            assert!(backend
+                .upsert("test_table".to_string(), 1, vec![1])
+                .is_err());
+            backend
+                .insert("test_table".to_string(), 1, vec![1])
+                .unwrap();
+            assert!(matches!(
+                backend.insert("test_table".to_string(), 1, vec![1]),
+                Err(StorageError::KeyExists(1))
+            ));
+            assert!(matches!(
+                backend.delete("test_table".to_string(), 2),
+                Err(StorageError::KeyNotFound(2))
+            ));
+            // A failed batch shouldn't leave any of it behind
+            assert!(backend
+                .batch_insert("test_table".to_string(), vec![(0, vec![0]), (1, vec![2])])
+                .is_err());
+            assert!(!backend.exists("test_table".to_string(), 0).unwrap());
+        }
+        remove_dir_all(path).unwrap();
+    }
+}
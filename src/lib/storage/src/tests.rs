@@ -1,5 +1,9 @@
-use crate::player_data::{load_player_data, save_player_data, ItemStackData, PlayerStatsData, PositionData};
+use crate::player_data::{
+    load_player_data, save_player_data, ItemStackData, PlayerStatsData, PositionData,
+    ITEM_FORMAT_VERSION,
+};
 use crate::lmdb::LmdbBackend;
+use crate::memory::MemoryBackend;
 use tempfile::tempdir;
 
 #[test]
@@ -26,3 +30,25 @@ fn inventory_persists_across_sessions() {
     let loaded = load_player_data(&db, uuid).unwrap();
     assert_eq!(loaded.inventory.hotbar[0].as_ref().unwrap().count, 5);
 }
+
+#[test]
+fn player_data_round_trips_in_memory() {
+    let db = MemoryBackend::new();
+    let uuid = 42u128;
+
+    let mut pdata = load_player_data(&db, uuid).unwrap();
+    pdata.inventory.hotbar.resize(9, None);
+    pdata.inventory.hotbar[4] = Some(ItemStackData {
+        item: 7,
+        count: 12,
+        max_stack_size: 64,
+        nbt: None,
+    });
+    pdata.advancements.push("minecraft:story/root".to_string());
+    save_player_data(&db, uuid, &pdata).unwrap();
+
+    let loaded = load_player_data(&db, uuid).unwrap();
+    assert_eq!(loaded.inventory.hotbar[4].as_ref().unwrap().count, 12);
+    assert_eq!(loaded.advancements, vec!["minecraft:story/root".to_string()]);
+    assert_eq!(loaded.item_format, Some(ITEM_FORMAT_VERSION));
+}
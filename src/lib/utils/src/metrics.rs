@@ -1,5 +1,8 @@
 use lazy_static::lazy_static;
-use prometheus::{Encoder, Histogram, HistogramOpts, Registry, TextEncoder};
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Registry,
+    TextEncoder,
+};
 
 lazy_static! {
     /// Global metrics registry.
@@ -7,15 +10,48 @@ lazy_static! {
     /// Histogram tracking chunk streaming durations.
     pub static ref CHUNK_STREAM_HISTOGRAM: Histogram = Histogram::with_opts(HistogramOpts::new(
         "chunk_stream_duration_seconds",
-        "Time spent streaming chunks"
+        "Time spent streaming chunks",
     ))
     .expect("failed to create chunk histogram");
     /// Histogram tracking packet processing durations.
     pub static ref PACKET_PROCESS_HISTOGRAM: Histogram = Histogram::with_opts(HistogramOpts::new(
         "packet_process_duration_seconds",
-        "Time spent processing packets"
+        "Time spent processing packets",
     ))
     .expect("failed to create packet histogram");
+    /// Bytes waiting to be sent to clients, across all connections.
+    pub static ref OUTBOUND_QUEUE_BYTES: IntGauge = IntGauge::new(
+        "outbound_queue_bytes",
+        "Bytes waiting to be sent to clients",
+    )
+    .expect("failed to create outbound queue gauge");
+    /// Packets dropped because the client they were for had fallen behind.
+    pub static ref OUTBOUND_DROPPED_PACKETS: IntCounter = IntCounter::new(
+        "outbound_dropped_packets_total",
+        "Packets dropped for clients that fell behind",
+    )
+    .expect("failed to create dropped packet counter");
+    /// Bytes waiting to be sent to the connection with the most queued, as of the last sample.
+    pub static ref OUTBOUND_QUEUE_MAX_BYTES: IntGauge = IntGauge::new(
+        "outbound_queue_max_bytes",
+        "Bytes waiting to be sent to the most backed up client",
+    )
+    .expect("failed to create outbound queue max gauge");
+    /// Bytes waiting to be sent to each connection, sampled every tick.
+    pub static ref OUTBOUND_QUEUE_HISTOGRAM: Histogram = Histogram::with_opts(
+        HistogramOpts::new(
+            "outbound_queue_depth_bytes",
+            "Bytes waiting to be sent to each client",
+        )
+        .buckets(exponential_buckets(1024.0, 4.0, 10).expect("invalid outbound queue buckets")),
+    )
+    .expect("failed to create outbound queue histogram");
+    /// Clients kicked because their outbound queue filled up.
+    pub static ref OUTBOUND_SLOW_CLIENT_KICKS: IntCounter = IntCounter::new(
+        "outbound_slow_client_kicks_total",
+        "Clients kicked for falling too far behind",
+    )
+    .expect("failed to create slow client kick counter");
 }
 
 /// Registers all default metrics with the global registry.
@@ -23,6 +59,11 @@ pub fn init_metrics() {
     // Registration errors are ignored because metrics may already be registered
     let _ = REGISTRY.register(Box::new(CHUNK_STREAM_HISTOGRAM.clone()));
     let _ = REGISTRY.register(Box::new(PACKET_PROCESS_HISTOGRAM.clone()));
+    let _ = REGISTRY.register(Box::new(OUTBOUND_QUEUE_BYTES.clone()));
+    let _ = REGISTRY.register(Box::new(OUTBOUND_DROPPED_PACKETS.clone()));
+    let _ = REGISTRY.register(Box::new(OUTBOUND_QUEUE_MAX_BYTES.clone()));
+    let _ = REGISTRY.register(Box::new(OUTBOUND_QUEUE_HISTOGRAM.clone()));
+    let _ = REGISTRY.register(Box::new(OUTBOUND_SLOW_CLIENT_KICKS.clone()));
 }
 
 /// Exports collected metrics in the Prometheus text format.
@@ -0,0 +1,382 @@
+//! Generates the state layout of every block and a type for each block from the block mappings.
+//! What the generated code is for is described in `src/block_id/states.rs`, which includes it.
+
+use std::fmt::Write;
+
+/// One state from the block mappings.
+pub struct State {
+    pub name: String,
+    /// In no particular order.
+    pub properties: Vec<(String, String)>,
+}
+
+struct Property {
+    name: String,
+    /// In the order vanilla numbers them.
+    values: Vec<String>,
+    stride: usize,
+}
+
+impl Property {
+    fn kind(&self) -> Kind {
+        if self.values == ["true", "false"] || self.values == ["false", "true"] {
+            Kind::Bool
+        } else if self.values.iter().all(|value| value.parse::<u8>().is_ok()) {
+            Kind::Number
+        } else {
+            Kind::Enum
+        }
+    }
+
+    fn field(&self) -> String {
+        match self.name.as_str() {
+            "type" => "r#type".to_string(),
+            name => name.to_string(),
+        }
+    }
+
+    /// How the index of the property's value is read from the offset of a state.
+    fn index(&self, count: usize) -> String {
+        let offset = match self.stride {
+            1 => "offset".to_string(),
+            stride => format!("offset / {stride}"),
+        };
+        if self.stride * self.values.len() == count {
+            offset
+        } else {
+            format!("{offset} % {}", self.values.len())
+        }
+    }
+}
+
+enum Kind {
+    Bool,
+    Number,
+    Enum,
+}
+
+struct Block<'a> {
+    name: &'a str,
+    first: usize,
+    count: usize,
+    /// With the one that changes slowest first.
+    properties: Vec<Property>,
+}
+
+impl Block<'_> {
+    /// The part after `minecraft:`, which is what the block's module is called.
+    fn module(&self) -> &str {
+        self.name
+            .split_once(':')
+            .map_or(self.name, |(_, name)| name)
+    }
+
+    fn type_name(&self) -> String {
+        pascal_case(self.module())
+    }
+
+    fn value<'b>(state: &'b State, name: &str) -> Option<&'b String> {
+        state
+            .properties
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect()
+}
+
+/// Split the states into blocks and work out how each one numbers its states. Vanilla counts
+/// through the values of a block's properties like the digits of a number, with the last property
+/// changing fastest.
+fn blocks(states: &[State]) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut first = 0;
+    while first < states.len() {
+        let name = &states[first].name;
+        let count = states[first..]
+            .iter()
+            .take_while(|state| state.name == *name)
+            .count();
+        let block_states = &states[first..first + count];
+        let value = |state: usize, name: &str| Block::value(&block_states[state], name);
+
+        let mut properties = Vec::new();
+        for (name, _) in &block_states[0].properties {
+            let stride = (1..count)
+                .find(|state| value(*state, name) != value(0, name))
+                .unwrap_or(count);
+            let mut values: Vec<String> = Vec::new();
+            for state in (0..count).step_by(stride) {
+                match value(state, name) {
+                    Some(value) if values.first() != Some(value) => values.push(value.clone()),
+                    _ => break,
+                }
+            }
+            properties.push(Property {
+                name: name.clone(),
+                values,
+                stride,
+            });
+        }
+        properties.sort_by_key(|property| std::cmp::Reverse(property.stride));
+
+        // Every state has to be where the arithmetic says it is
+        let consistent = properties.iter().map(|p| p.values.len()).product::<usize>() == count
+            && (0..count).all(|state| {
+                properties.iter().all(|property| {
+                    let index = state / property.stride % property.values.len();
+                    value(state, &property.name) == Some(&property.values[index])
+                })
+            });
+        if !consistent {
+            panic!("block {name} doesn't number its states the usual way");
+        }
+
+        blocks.push(Block {
+            name,
+            first,
+            count,
+            properties,
+        });
+        first += count;
+    }
+    blocks
+}
+
+/// The Rust code for the blocks in `states`, which are all the states in the game, in order.
+pub fn generate(states: &[State]) -> String {
+    let blocks = blocks(states);
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by build/block_states.rs from the block mappings.\n"
+    )
+    .unwrap();
+
+    writeln!(out, "static BLOCKS: [BlockLayout; {}] = [", blocks.len()).unwrap();
+    for block in &blocks {
+        writeln!(out, "    BlockLayout {{").unwrap();
+        writeln!(out, "        name: {:?},", block.name).unwrap();
+        writeln!(out, "        first: {},", block.first).unwrap();
+        writeln!(out, "        properties: &[").unwrap();
+        for property in &block.properties {
+            writeln!(
+                out,
+                "            PropertyLayout {{ name: {:?}, values: &{:?}, stride: {} }},",
+                property.name, property.values, property.stride
+            )
+            .unwrap();
+        }
+        writeln!(out, "        ],").unwrap();
+        writeln!(out, "    }},").unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+
+    writeln!(out, "static STATE_BLOCKS: [u16; {}] = [", states.len()).unwrap();
+    let indices = blocks
+        .iter()
+        .enumerate()
+        .flat_map(|(index, block)| std::iter::repeat_n(index, block.count))
+        .collect::<Vec<_>>();
+    for line in indices.chunks(32) {
+        let line = line
+            .iter()
+            .map(|index| index.to_string())
+            .collect::<Vec<_>>();
+        writeln!(out, "    {},", line.join(", ")).unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+
+    writeln!(
+        out,
+        "/// A type for every block, with a field for each of its properties. See [`BlockState`]."
+    )
+    .unwrap();
+    writeln!(out, "pub mod blocks {{").unwrap();
+    writeln!(out, "    use super::BlockState;").unwrap();
+    for block in &blocks {
+        write_block(&mut out, block);
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn write_block(out: &mut String, block: &Block) {
+    let type_name = block.type_name();
+    let module = block.module();
+    let enum_type = |property: &Property| format!("{module}::{}", pascal_case(&property.name));
+
+    writeln!(out).unwrap();
+    writeln!(out, "    /// `{}`", block.name).unwrap();
+    if block.properties.is_empty() {
+        writeln!(
+            out,
+            "    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]"
+        )
+        .unwrap();
+        writeln!(out, "    pub struct {type_name};").unwrap();
+    } else {
+        writeln!(
+            out,
+            "    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]"
+        )
+        .unwrap();
+        writeln!(out, "    pub struct {type_name} {{").unwrap();
+        for property in &block.properties {
+            let rust_type = match property.kind() {
+                Kind::Bool => "bool".to_string(),
+                Kind::Number => "u8".to_string(),
+                Kind::Enum => enum_type(property),
+            };
+            writeln!(out, "        pub {}: {rust_type},", property.field()).unwrap();
+        }
+        writeln!(out, "    }}").unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "    impl BlockState for {type_name} {{").unwrap();
+    writeln!(out, "        const NAME: &'static str = {:?};", block.name).unwrap();
+    writeln!(out, "        const FIRST: u32 = {};", block.first).unwrap();
+    writeln!(out, "        const COUNT: u32 = {};", block.count).unwrap();
+    writeln!(out).unwrap();
+    if block.properties.is_empty() {
+        writeln!(out, "        fn from_offset(_offset: u32) -> Self {{").unwrap();
+        writeln!(out, "            {type_name}").unwrap();
+        writeln!(out, "        }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "        fn to_offset(self) -> Option<u32> {{").unwrap();
+        writeln!(out, "            Some(0)").unwrap();
+        writeln!(out, "        }}").unwrap();
+    } else {
+        writeln!(out, "        fn from_offset(offset: u32) -> Self {{").unwrap();
+        writeln!(out, "            {type_name} {{").unwrap();
+        for property in &block.properties {
+            let index = property.index(block.count);
+            let cast = if index.contains(' ') {
+                format!("({index}) as usize")
+            } else {
+                format!("{index} as usize")
+            };
+            let value = match property.kind() {
+                Kind::Bool | Kind::Number => format!("[{}][{cast}]", property.values.join(", ")),
+                Kind::Enum => format!("{}::ALL[{cast}]", enum_type(property)),
+            };
+            writeln!(out, "                {}: {value},", property.field()).unwrap();
+        }
+        writeln!(out, "            }}").unwrap();
+        writeln!(out, "        }}").unwrap();
+        writeln!(out).unwrap();
+
+        writeln!(out, "        fn to_offset(self) -> Option<u32> {{").unwrap();
+        let terms = block
+            .properties
+            .iter()
+            .map(|property| {
+                let field = property.field();
+                let index = match property.kind() {
+                    Kind::Bool if true_index(property) == 0 => format!("u32::from(!self.{field})"),
+                    Kind::Bool => format!("u32::from(self.{field})"),
+                    Kind::Number => format!(
+                        "[{}].iter().position(|value| *value == self.{field})? as u32",
+                        property.values.join(", ")
+                    ),
+                    Kind::Enum => format!("self.{field} as u32"),
+                };
+                match property.stride {
+                    1 => index,
+                    stride => format!("{index} * {stride}"),
+                }
+            })
+            .collect::<Vec<_>>();
+        writeln!(out, "            Some({})", terms.join(" + ")).unwrap();
+        writeln!(out, "        }}").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+
+    let enums = block
+        .properties
+        .iter()
+        .filter(|property| matches!(property.kind(), Kind::Enum))
+        .collect::<Vec<_>>();
+    if enums.is_empty() {
+        return;
+    }
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "    /// The properties of `{}` that have names for values.",
+        block.name
+    )
+    .unwrap();
+    writeln!(out, "    pub mod {module} {{").unwrap();
+    for (i, property) in enums.into_iter().enumerate() {
+        let enum_name = pascal_case(&property.name);
+        let variants = property
+            .values
+            .iter()
+            .map(|value| format!("{enum_name}::{}", pascal_case(value)))
+            .collect::<Vec<_>>();
+        if i > 0 {
+            writeln!(out).unwrap();
+        }
+        writeln!(out, "        /// `{}`", property.name).unwrap();
+        writeln!(
+            out,
+            "        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]"
+        )
+        .unwrap();
+        writeln!(out, "        pub enum {enum_name} {{").unwrap();
+        for value in &property.values {
+            writeln!(out, "            {},", pascal_case(value)).unwrap();
+        }
+        writeln!(out, "        }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "        impl {enum_name} {{").unwrap();
+        writeln!(
+            out,
+            "            /// Every value, in the order vanilla numbers them."
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "            pub const ALL: [{enum_name}; {}] = [{}];",
+            variants.len(),
+            variants.join(", ")
+        )
+        .unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "            /// The value as it's written in block states."
+        )
+        .unwrap();
+        writeln!(out, "            pub fn as_str(self) -> &'static str {{").unwrap();
+        writeln!(out, "                match self {{").unwrap();
+        for (variant, value) in variants.iter().zip(&property.values) {
+            writeln!(out, "                    {variant} => {value:?},").unwrap();
+        }
+        writeln!(out, "                }}").unwrap();
+        writeln!(out, "            }}").unwrap();
+        writeln!(out, "        }}").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+}
+
+/// Which index `true` is at, for a property that's either `true` or `false`.
+fn true_index(property: &Property) -> usize {
+    property
+        .values
+        .iter()
+        .position(|value| value == "true")
+        .expect("only called for booleans")
+}
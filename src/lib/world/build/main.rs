@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use block_states::State;
+
+mod block_states;
+
+fn main() {
+    const B64: &str = include_str!("../blockmappings.b64");
+    println!("cargo:rerun-if-changed=blockmappings.b64");
+    println!("cargo:rerun-if-changed=build");
+    let data: String = B64.lines().collect();
+    let bytes = STANDARD.decode(data).expect("invalid base64 block mapping");
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR not set"));
+    fs::write(out_dir.join("blockmappings.bz2"), &bytes)
+        .expect("failed to write blockmappings.bz2");
+
+    let mut json = String::new();
+    bzip2::read::BzDecoder::new(bytes.as_slice())
+        .read_to_string(&mut json)
+        .expect("invalid bzip2 block mapping");
+    let mappings: HashMap<u32, serde_json::Value> =
+        serde_json::from_str(&json).expect("invalid block mapping json");
+    let states = (0..mappings.len() as u32)
+        .map(|id| {
+            let state = mappings
+                .get(&id)
+                .unwrap_or_else(|| panic!("block state {id} is missing from the mappings"));
+            State {
+                name: state["name"]
+                    .as_str()
+                    .expect("block state without a name")
+                    .to_string(),
+                properties: state["properties"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(key, value)| {
+                        let value = value.as_str().expect("property value isn't a string");
+                        (key.clone(), value.to_string())
+                    })
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    fs::write(
+        out_dir.join("block_states.rs"),
+        block_states::generate(&states),
+    )
+    .expect("failed to write block_states.rs");
+}
@@ -0,0 +1,496 @@
+//! Snapshots of the world database that can be rolled back to.
+//!
+//! Backups are taken with [`World::backup`] while the server keeps running, and each one is a
+//! gzipped copy of the database file named after the time it was taken and the backend it's
+//! from, e.g. `backup-1700000000.mdb.gz` for LMDB or `backup-1700000000.redb.gz` for redb.
+//! [`restore`] swaps one back in, but only while the server is stopped, which it checks with the
+//! lock every open world holds on [`SERVER_LOCK_FILE`].
+
+use crate::errors::WorldError;
+use crate::World;
+use ferrumc_storage::backend::StorageBackend;
+use ferrumc_storage::lmdb::LmdbBackend;
+use ferrumc_storage::redb_backend::RedbBackend;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+const BACKUP_PREFIX: &str = "backup-";
+/// The lock file LMDB keeps next to its data file.
+pub(crate) const LOCK_FILE: &str = "lock.mdb";
+/// Locked by [`World::new`] for as long as the world is open, and holds the id of the process
+/// that opened it.
+pub const SERVER_LOCK_FILE: &str = "server.lock";
+
+/// The backends backups can be taken of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupFormat {
+    Lmdb,
+    Redb,
+}
+
+impl BackupFormat {
+    const ALL: [BackupFormat; 2] = [BackupFormat::Lmdb, BackupFormat::Redb];
+
+    /// The file the backend keeps everything in, inside the database folder.
+    pub fn data_file(self) -> &'static str {
+        match self {
+            BackupFormat::Lmdb => "data.mdb",
+            BackupFormat::Redb => "data.redb",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            BackupFormat::Lmdb => ".mdb.gz",
+            BackupFormat::Redb => ".redb.gz",
+        }
+    }
+
+    fn from_data_file(data_file: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.data_file() == data_file)
+    }
+
+    /// Make sure a data file can be opened by the backend, by opening the folder it's in.
+    fn check(self, db_path: &Path) -> Result<bool, WorldError> {
+        let backend: Box<dyn StorageBackend> = match self {
+            BackupFormat::Lmdb => Box::new(LmdbBackend::initialize(Some(db_path.to_path_buf()))?),
+            BackupFormat::Redb => Box::new(RedbBackend::initialize(Some(db_path.to_path_buf()))?),
+        };
+        Ok(backend.table_exists("chunks".to_string())?)
+    }
+}
+
+/// A backup found in a backup folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// When the backup was taken, in seconds since the epoch.
+    pub timestamp: u64,
+    /// How many other backups were taken in the same second before this one.
+    pub index: u32,
+    pub format: BackupFormat,
+}
+
+impl World {
+    /// Take a backup of the world database and put it in `backup_dir`.
+    ///
+    /// The cache is synced first so the backup includes recent changes. The copy itself is taken
+    /// in a single read transaction, so it's consistent even while chunks are being saved. Set
+    /// `compact` to leave free pages out of the copy.
+    ///
+    /// Returns the path of the new backup.
+    pub fn backup(&self, backup_dir: &Path, compact: bool) -> Result<PathBuf, WorldError> {
+        let Some(format) = self
+            .storage_backend
+            .data_file()
+            .and_then(BackupFormat::from_data_file)
+        else {
+            return Err(WorldError::BackupError(format!(
+                "Backups are not supported by {}",
+                self.storage_backend.details()
+            )));
+        };
+        std::fs::create_dir_all(backup_dir)?;
+        self.sync()?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        // Backups taken in the same second get a number after the timestamp
+        let mut index = 0;
+        let name = loop {
+            let name = backup_name(timestamp, index);
+            let taken = BackupFormat::ALL
+                .iter()
+                .map(|format| format.extension())
+                .chain([".db.partial", ".gz.partial"])
+                .any(|extension| backup_dir.join(format!("{name}{extension}")).exists());
+            if !taken {
+                break name;
+            }
+            index += 1;
+        };
+        let backup_path = backup_dir.join(format!("{name}{}", format.extension()));
+
+        // The backends can only copy to a file they create themselves, so copy first and
+        // compress after
+        let raw_path = backup_dir.join(format!("{name}.db.partial"));
+        let partial_path = backup_dir.join(format!("{name}.gz.partial"));
+        let result = self
+            .storage_backend
+            .backup(&raw_path, compact)
+            .map_err(WorldError::from)
+            .and_then(|_| gzip_file(&raw_path, &partial_path));
+        let _ = std::fs::remove_file(&raw_path);
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&partial_path);
+            return Err(e);
+        }
+        // Only give the backup its real name once it's complete, so a crash halfway through
+        // never leaves something that looks like a valid backup
+        std::fs::rename(&partial_path, &backup_path)?;
+
+        Ok(backup_path)
+    }
+}
+
+/// Take the lock on the database folder at `db_path`, creating the folder if needed.
+///
+/// Fails with [`WorldError::DatabaseLocked`] if another world, in this process or another one,
+/// already has it. The lock is released when the returned file is closed.
+pub(crate) fn lock_database(db_path: &Path) -> Result<File, WorldError> {
+    std::fs::create_dir_all(db_path)?;
+    let path = db_path.join(SERVER_LOCK_FILE);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let holder = std::fs::read_to_string(&path).unwrap_or_default();
+            return Err(WorldError::DatabaseLocked(format!(
+                "{} (process {})",
+                db_path.display(),
+                holder.trim()
+            )));
+        }
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+    // Only for people looking at the file, the lock is what counts
+    file.set_len(0)?;
+    write!(file, "{}", std::process::id())?;
+    Ok(file)
+}
+
+fn gzip_file(source: &Path, destination: &Path) -> Result<(), WorldError> {
+    let mut reader = BufReader::new(File::open(source)?);
+    let mut encoder = GzEncoder::new(
+        BufWriter::new(File::create(destination)?),
+        Compression::fast(),
+    );
+    std::io::copy(&mut reader, &mut encoder)?;
+    encoder
+        .finish()?
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    Ok(())
+}
+
+/// The file name of a backup without its extension. The first backup of a second is named after
+/// just the timestamp.
+fn backup_name(timestamp: u64, index: u32) -> String {
+    if index == 0 {
+        format!("{BACKUP_PREFIX}{timestamp}")
+    } else {
+        format!("{BACKUP_PREFIX}{timestamp}-{index}")
+    }
+}
+
+/// List the backups in a folder, newest first.
+///
+/// Files that don't look like backups are ignored, and a missing folder has no backups.
+pub fn list_backups(backup_dir: &Path) -> Result<Vec<Backup>, WorldError> {
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(backup_dir)? {
+        let path = entry?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(BACKUP_PREFIX))
+        else {
+            continue;
+        };
+        let Some((timestamp, index, format)) = BackupFormat::ALL.into_iter().find_map(|format| {
+            let stem = name.strip_suffix(format.extension())?;
+            let (timestamp, index) = match stem.split_once('-') {
+                Some((timestamp, index)) => (timestamp.parse().ok()?, index.parse().ok()?),
+                None => (stem.parse().ok()?, 0),
+            };
+            Some((timestamp, index, format))
+        }) else {
+            continue;
+        };
+        backups.push(Backup {
+            path,
+            timestamp,
+            index,
+            format,
+        });
+    }
+    backups.sort_by(|a, b| (b.timestamp, b.index).cmp(&(a.timestamp, a.index)));
+    Ok(backups)
+}
+
+/// Delete old backups so at most `max_backups` are left and none are older than `max_age`.
+///
+/// A `max_backups` of 0 or a zero `max_age` turns that limit off. The newest backup is never
+/// deleted for its age, so there's always something to restore. Returns the deleted backups.
+pub fn prune_backups(
+    backup_dir: &Path,
+    max_backups: usize,
+    max_age: Duration,
+) -> Result<Vec<Backup>, WorldError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut pruned = Vec::new();
+    for (index, backup) in list_backups(backup_dir)?.into_iter().enumerate() {
+        let too_many = max_backups != 0 && index >= max_backups;
+        let too_old = !max_age.is_zero()
+            && index > 0
+            && now.saturating_sub(backup.timestamp) > max_age.as_secs();
+        if too_many || too_old {
+            std::fs::remove_file(&backup.path)?;
+            pruned.push(backup);
+        }
+    }
+    Ok(pruned)
+}
+
+/// Replace the world database at `db_path` with a backup.
+///
+/// The backup is unpacked and opened next to the database first, so a broken backup is caught
+/// before anything is touched. The current data file is kept with `.pre-restore` added to its
+/// name in case the restore needs undoing.
+///
+/// Fails with [`WorldError::DatabaseLocked`] if the server, or anything else, has the world open.
+/// The lock is held until the restore is done, so the server can't start halfway through either.
+/// Backups of one backend can't be restored into a world that uses the other.
+pub fn restore(backup: &Path, db_path: &Path) -> Result<(), WorldError> {
+    if !backup.is_file() {
+        return Err(WorldError::BackupError(format!(
+            "Backup {} does not exist",
+            backup.display()
+        )));
+    }
+    let file_name = backup
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let Some(format) = BackupFormat::ALL
+        .into_iter()
+        .find(|format| file_name.ends_with(format.extension()))
+    else {
+        return Err(WorldError::BackupError(format!(
+            "{} is not a backup, backups end in .mdb.gz or .redb.gz",
+            backup.display()
+        )));
+    };
+    if let Some(other) = BackupFormat::ALL
+        .into_iter()
+        .find(|other| *other != format && db_path.join(other.data_file()).exists())
+    {
+        return Err(WorldError::BackupError(format!(
+            "{} is a {:?} backup, but the world at {} uses {:?}",
+            backup.display(),
+            format,
+            db_path.display(),
+            other
+        )));
+    }
+    let _lock = lock_database(db_path)?;
+
+    let staging_dir = db_path.join(".restore");
+    if staging_dir.exists() {
+        std::fs::remove_dir_all(&staging_dir)?;
+    }
+    std::fs::create_dir_all(&staging_dir)?;
+    let staged_data = staging_dir.join(format.data_file());
+
+    info!("Unpacking {}...", backup.display());
+    let mut decoder = GzDecoder::new(BufReader::new(File::open(backup)?));
+    let mut writer = BufWriter::new(File::create(&staged_data)?);
+    std::io::copy(&mut decoder, &mut writer)?;
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+
+    // Make sure the backend can actually open it before swapping it in
+    let has_chunks = format.check(&staging_dir).map_err(|e| {
+        WorldError::BackupError(format!("Backup {} is not valid: {}", backup.display(), e))
+    })?;
+    if !has_chunks {
+        warn!("Backup {} has no chunks in it", backup.display());
+    }
+
+    let data_path = db_path.join(format.data_file());
+    if data_path.exists() {
+        let previous = db_path.join(format!("{}.pre-restore", format.data_file()));
+        std::fs::rename(&data_path, &previous)?;
+        info!("Moved the old world database to {}", previous.display());
+    }
+    std::fs::rename(&staged_data, &data_path)?;
+    // The lock file describes the old data file's readers, LMDB recreates it on open
+    let lock_path = db_path.join(LOCK_FILE);
+    if format == BackupFormat::Lmdb && lock_path.exists() {
+        std::fs::remove_file(lock_path)?;
+    }
+    std::fs::remove_dir_all(&staging_dir)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ferrumc_backup_tests_{}_{}",
+            name,
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn fake_backup(dir: &Path, timestamp: u64) {
+        fake_backup_of(dir, timestamp, BackupFormat::Lmdb);
+    }
+
+    fn fake_backup_of(dir: &Path, timestamp: u64, format: BackupFormat) {
+        std::fs::write(
+            dir.join(format!("{BACKUP_PREFIX}{timestamp}{}", format.extension())),
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_lists_newest_first() {
+        let dir = temp_dir("list");
+        fake_backup(&dir, 10);
+        fake_backup(&dir, 30);
+        fake_backup_of(&dir, 20, BackupFormat::Redb);
+        std::fs::write(dir.join("notes.txt"), []).unwrap();
+        std::fs::write(dir.join("backup-40.gz.partial"), []).unwrap();
+
+        let backups = list_backups(&dir)
+            .unwrap()
+            .iter()
+            .map(|backup| (backup.timestamp, backup.format))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            backups,
+            vec![
+                (30, BackupFormat::Lmdb),
+                (20, BackupFormat::Redb),
+                (10, BackupFormat::Lmdb)
+            ]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_prunes_by_count_and_age() {
+        let dir = temp_dir("prune");
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        for age in [0, 60, 120, 7200] {
+            fake_backup(&dir, now - age);
+        }
+
+        let pruned = prune_backups(&dir, 3, Duration::ZERO).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].timestamp, now - 7200);
+
+        let pruned = prune_backups(&dir, 0, Duration::from_secs(90)).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].timestamp, now - 120);
+        assert_eq!(list_backups(&dir).unwrap().len(), 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_backups_in_the_same_second_get_their_own_names() {
+        let dir = temp_dir("same_second");
+        let backend = RedbBackend::initialize(Some(dir.join("world"))).unwrap();
+        let world = World::with_backend(Arc::new(backend)).unwrap();
+        let first = world.backup(&dir.join("backups"), false).unwrap();
+        let second = world.backup(&dir.join("backups"), false).unwrap();
+        assert_ne!(first, second);
+
+        let backups = list_backups(&dir.join("backups")).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].path, second);
+        assert_eq!(backups[1].path, first);
+        drop(world);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_never_prunes_newest_for_age() {
+        let dir = temp_dir("prune_newest");
+        fake_backup(&dir, 1);
+        fake_backup(&dir, 2);
+        let pruned = prune_backups(&dir, 0, Duration::from_secs(1)).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(list_backups(&dir).unwrap()[0].timestamp, 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_missing_backup() {
+        let dir = temp_dir("restore_missing");
+        assert!(restore(&dir.join("backup-1.mdb.gz"), &dir.join("world")).is_err());
+        assert!(!dir.join("world").join("data.mdb").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_refuses_backup_of_other_backend() {
+        let dir = temp_dir("restore_other_backend");
+        fake_backup_of(&dir, 1, BackupFormat::Redb);
+        let world_dir = dir.join("world");
+        std::fs::create_dir_all(&world_dir).unwrap();
+        std::fs::write(world_dir.join("data.mdb"), []).unwrap();
+        assert!(matches!(
+            restore(&dir.join("backup-1.redb.gz"), &world_dir),
+            Err(WorldError::BackupError(_))
+        ));
+        assert!(!world_dir.join("data.redb").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_refuses_to_restore_open_world() {
+        let dir = temp_dir("restore_locked");
+        fake_backup(&dir, 1);
+        let world_dir = dir.join("world");
+        let lock = lock_database(&world_dir).unwrap();
+        assert!(matches!(
+            lock_database(&world_dir),
+            Err(WorldError::DatabaseLocked(_))
+        ));
+        assert!(matches!(
+            restore(&dir.join("backup-1.mdb.gz"), &world_dir),
+            Err(WorldError::DatabaseLocked(_))
+        ));
+        drop(lock);
+        assert!(lock_database(&world_dir).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -10,21 +10,21 @@ pub(crate) fn bench_cache(c: &mut Criterion) {
     let mut group = c.benchmark_group("world_load");
     group.bench_function("Load chunk 1,1 uncached", |b| {
         b.iter_batched(
-            || World::new(&backend_path),
+            || World::new(&backend_path).unwrap(),
             |world| world.load_chunk(black_box(1), black_box(1), black_box("overworld")),
             criterion::BatchSize::PerIteration,
         );
     });
     group.bench_function("Load chunk 1,1 uncached, owned", |b| {
         b.iter_batched(
-            || World::new(&backend_path),
+            || World::new(&backend_path).unwrap(),
             |world| world.load_chunk_owned(black_box(1), black_box(1), black_box("overworld")),
             criterion::BatchSize::PerIteration,
         );
     });
     group.bench_function("Load block 1,1 uncached", |b| {
         b.iter_batched(
-            || World::new(&backend_path),
+            || World::new(&backend_path).unwrap(),
             |world| {
                 world.get_block_and_fetch(
                     black_box(1),
@@ -36,7 +36,7 @@ pub(crate) fn bench_cache(c: &mut Criterion) {
             criterion::BatchSize::PerIteration,
         );
     });
-    let world = World::new(backend_path);
+    let world = World::new(backend_path).unwrap();
     let load_chunk = || {
         world.load_chunk(1, 1, "overworld").expect(
             "Failed to load chunk. If it's a bitcode error, chances are the chunk format \
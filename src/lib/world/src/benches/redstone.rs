@@ -0,0 +1,139 @@
+use crate::redstone_legacy::{PowerLevelCache, Scheduler};
+use criterion::{Criterion, Throughput};
+use ferrumc_storage::memory::MemoryBackend;
+use ferrumc_world::block_id::{BlockId, Delay, Direction, Lit, Powered};
+use ferrumc_world::chunk_format::Chunk;
+use ferrumc_world::edit_batch::EditBatch;
+use ferrumc_world::redstone::RedstoneNetworks;
+use ferrumc_world::tick::BlockPos;
+use ferrumc_world::World;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::Arc;
+
+type Pos = (i32, i32, i32);
+
+fn block(name: &str) -> BlockId {
+    BlockId::from_name(&format!("minecraft:{name}")).unwrap()
+}
+
+/// `lines` observer clocks, each driving `segments` lengths of 15 wire with a repeater after each.
+fn contraption(lines: i32, segments: i32) -> HashMap<Pos, BlockId> {
+    let observer = block("observer").with(Powered(false)).unwrap();
+    let repeater = block("repeater")
+        .with(Delay(1))
+        .and_then(|repeater| repeater.with(Powered(false)))
+        .unwrap();
+    let mut blocks = HashMap::new();
+    for line in 0..lines {
+        let z = line * 2;
+        blocks.insert((0, 1, z), observer.with(Direction::East).unwrap());
+        blocks.insert((1, 1, z), observer.with(Direction::West).unwrap());
+        let mut x = 2;
+        for _ in 0..segments {
+            for _ in 0..15 {
+                blocks.insert((x, 1, z), block("redstone_wire"));
+                x += 1;
+            }
+            blocks.insert((x, 1, z), repeater.with(Direction::West).unwrap());
+            x += 1;
+        }
+        blocks.insert((x, 1, z), block("redstone_lamp").with(Lit(false)).unwrap());
+    }
+    blocks
+}
+
+/// Compile every line and set its clock off.
+fn start_networks(lines: i32, blocks: &HashMap<Pos, BlockId>) -> RedstoneNetworks {
+    let mut networks = RedstoneNetworks::default();
+    for line in 0..lines {
+        networks.block_changed("overworld", (1, 1, line * 2));
+    }
+    networks.tick(|_, pos| blocks.get(&pos).copied());
+    networks
+}
+
+/// A world holding the contraption on a floor of stone. An observer in every line is placed again
+/// at the end, which compiles the networks the way placing blocks does in game.
+fn contraption_world(lines: i32, blocks: &HashMap<Pos, BlockId>) -> World {
+    let world = World::with_backend(Arc::new(MemoryBackend::new())).unwrap();
+    let stone = block("stone");
+    let max_x = blocks.keys().map(|(x, _, _)| *x).max().unwrap_or(0);
+    let max_z = blocks.keys().map(|(_, _, z)| *z).max().unwrap_or(0);
+    for chunk_x in 0..=max_x >> 4 {
+        for chunk_z in 0..=max_z >> 4 {
+            let mut chunk = Chunk::new(chunk_x, chunk_z, "overworld".to_string());
+            let mut batch = EditBatch::new(&mut chunk);
+            for x in 0..16 {
+                for z in 0..16 {
+                    batch.set_block(x, 0, z, stone);
+                }
+            }
+            for (&(x, y, z), &block) in blocks {
+                if (x >> 4, z >> 4) == (chunk_x, chunk_z) {
+                    batch.set_block(x & 0xf, y, z & 0xf, block);
+                }
+            }
+            batch.apply().unwrap();
+            world.save_chunk(Arc::new(chunk)).unwrap();
+        }
+    }
+    for line in 0..lines {
+        let pos = (1, 1, line * 2);
+        world
+            .set_block_and_fetch(pos.0, pos.1, pos.2, "overworld", blocks[&pos])
+            .unwrap();
+    }
+    world
+}
+
+pub(crate) fn bench_redstone(c: &mut Criterion) {
+    let (lines, segments) = (32, 16);
+    let blocks = contraption(lines, segments);
+
+    let mut group = c.benchmark_group("redstone");
+    group.throughput(Throughput::Elements(blocks.len() as u64));
+
+    group.bench_function("Compile 32 lines", |b| {
+        b.iter(|| black_box(start_networks(lines, &blocks)));
+    });
+
+    // Both tick a real world, so the compiled networks pay for writing the blocks they change
+    // back to the chunks, like they do in game
+    group.bench_function("Tick 32 lines compiled", |b| {
+        let world = contraption_world(lines, &blocks);
+        // Let the pulses reach the end of the lines first
+        for _ in 0..segments * 4 {
+            world.tick().unwrap();
+            world.take_redstone_updates();
+        }
+        b.iter(|| {
+            world.tick().unwrap();
+            black_box(world.take_redstone_updates())
+        });
+    });
+
+    group.bench_function("Tick 32 lines previous implementation", |b| {
+        let world = contraption_world(lines, &blocks);
+        let mut scheduler = Scheduler::default();
+        let mut cache = PowerLevelCache::default();
+        for line in 0..lines {
+            for x in 0..2 {
+                let pos = BlockPos {
+                    x,
+                    y: 1,
+                    z: line * 2,
+                    dimension: "overworld".to_string(),
+                };
+                let observer = blocks[&(x, 1, line * 2)];
+                scheduler.schedule(pos, observer, 1);
+            }
+        }
+        for _ in 0..segments * 4 {
+            scheduler.tick(&world, &mut cache).unwrap();
+        }
+        b.iter(|| black_box(scheduler.tick(&world, &mut cache).unwrap()));
+    });
+
+    group.finish();
+}
@@ -0,0 +1,271 @@
+//! Redstone as it worked before it was compiled into networks, for [`crate::redstone`] to compare
+//! against. This is the old `ferrumc_world::redstone` with the power cache passed in rather than
+//! kept on the world, and with a [`Scheduler`] standing in for the old tick manager, which went
+//! with the rest of the old tick code. Blocks are read from a real [`World`] like they were.
+
+use ferrumc_world::block_id::{BlockId, Delay, Direction};
+use ferrumc_world::errors::WorldError;
+use ferrumc_world::tick::BlockPos;
+use ferrumc_world::World;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+#[derive(Default)]
+pub struct PowerLevelCache {
+    pub levels: HashMap<(i32, i32, i32, String), u8>,
+}
+
+/// The redstone ticks waiting to run, by the game tick they're due on.
+///
+/// Like the old tick manager, a tick with a delay of `d` runs `d + 1` game ticks later. Unlike
+/// it, a position only gets one tick per game tick, otherwise every pair of observers facing each
+/// other doubles its ticks on every game tick.
+#[derive(Default)]
+pub struct Scheduler {
+    time: u64,
+    due: BTreeMap<u64, Vec<(BlockPos, BlockId)>>,
+    waiting: HashSet<(u64, BlockPos)>,
+}
+
+impl Scheduler {
+    pub fn schedule(&mut self, pos: BlockPos, block: BlockId, delay: u32) {
+        let due = self.time + delay as u64 + 1;
+        if self.waiting.insert((due, pos.clone())) {
+            self.due.entry(due).or_default().push((pos, block));
+        }
+    }
+
+    /// Advance a game tick and run the redstone ticks that are due.
+    pub fn tick(&mut self, world: &World, cache: &mut PowerLevelCache) -> Result<(), WorldError> {
+        self.time += 1;
+        let Some(ticks) = self.due.remove(&self.time) else {
+            return Ok(());
+        };
+        for (pos, block) in ticks {
+            self.waiting.remove(&(self.time, pos.clone()));
+            tick_block(world, self, cache, &pos, block)?;
+        }
+        Ok(())
+    }
+}
+
+/// The redstone part of the old `tick_block`.
+fn tick_block(
+    world: &World,
+    tm: &mut Scheduler,
+    cache: &mut PowerLevelCache,
+    pos: &BlockPos,
+    block: BlockId,
+) -> Result<(), WorldError> {
+    let Some(name) = block.name() else {
+        return Ok(());
+    };
+    let facing = || block.get::<Direction>().unwrap_or(Direction::North);
+    match name {
+        "minecraft:redstone_torch" | "minecraft:redstone_wall_torch" => {
+            tick_torch(world, tm, cache, pos, block)
+        }
+        "minecraft:repeater" => {
+            let delay = block.get::<Delay>().map_or(1, |delay| delay.0);
+            tick_repeater(world, tm, cache, pos, delay, facing(), block)
+        }
+        "minecraft:observer" => tick_observer(world, tm, cache, pos, facing(), block),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RedstoneComponent {
+    Wire,
+    Torch,
+    Repeater { delay: u8 },
+    Comparator,
+    Observer,
+    Piston,
+}
+
+fn identify_component(block: &BlockId) -> Option<RedstoneComponent> {
+    match block.name()? {
+        "minecraft:redstone_wire" => Some(RedstoneComponent::Wire),
+        "minecraft:redstone_torch" | "minecraft:redstone_wall_torch" => {
+            Some(RedstoneComponent::Torch)
+        }
+        "minecraft:repeater" => Some(RedstoneComponent::Repeater {
+            delay: block.get::<Delay>().map_or(1, |delay| delay.0),
+        }),
+        "minecraft:comparator" => Some(RedstoneComponent::Comparator),
+        "minecraft:observer" => Some(RedstoneComponent::Observer),
+        "minecraft:piston" | "minecraft:sticky_piston" => Some(RedstoneComponent::Piston),
+        _ => None,
+    }
+}
+
+fn propagate_from(world: &World, cache: &mut PowerLevelCache, start: BlockPos, power: u8) {
+    let mut queue = VecDeque::new();
+    queue.push_back((start, power));
+    while let Some((pos, level)) = queue.pop_front() {
+        let key = (pos.x, pos.y, pos.z, pos.dimension.clone());
+        let current = cache.levels.get(&key).copied().unwrap_or(0);
+        if level <= current {
+            continue;
+        }
+        cache.levels.insert(key, level);
+        if level == 0 {
+            continue;
+        }
+        if level > 1 {
+            let dirs = [(1, 0, 0), (-1, 0, 0), (0, 0, 1), (0, 0, -1)];
+            for (dx, dy, dz) in dirs.into_iter() {
+                let nx = pos.x + dx;
+                let ny = pos.y + dy;
+                let nz = pos.z + dz;
+                if let Ok(block) = world.get_block_and_fetch(nx, ny, nz, &pos.dimension) {
+                    if identify_component(&block) == Some(RedstoneComponent::Wire) {
+                        let next_pos = BlockPos {
+                            x: nx,
+                            y: ny,
+                            z: nz,
+                            dimension: pos.dimension.clone(),
+                        };
+                        queue.push_back((next_pos, level - 1));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn component_delay(comp: &RedstoneComponent) -> u32 {
+    match comp {
+        RedstoneComponent::Wire => 0,
+        RedstoneComponent::Torch => 1,
+        RedstoneComponent::Repeater { delay } => *delay as u32,
+        RedstoneComponent::Comparator => 1,
+        RedstoneComponent::Observer => 1,
+        RedstoneComponent::Piston => 1,
+    }
+}
+
+fn schedule_component_update(
+    world: &World,
+    tm: &mut Scheduler,
+    cache: &mut PowerLevelCache,
+    pos: BlockPos,
+) {
+    if let Ok(block) = world.get_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension) {
+        if let Some(comp) = identify_component(&block) {
+            let delay = component_delay(&comp);
+            if delay == 0 {
+                propagate_from(world, cache, pos, 15);
+            } else {
+                tm.schedule(pos, block, delay);
+            }
+        }
+    }
+}
+
+fn propagate_block_update(
+    world: &World,
+    tm: &mut Scheduler,
+    cache: &mut PowerLevelCache,
+    pos: &BlockPos,
+) {
+    let neighbors = [
+        (1, 0, 0),
+        (-1, 0, 0),
+        (0, 0, 1),
+        (0, 0, -1),
+        (0, 1, 0),
+        (0, -1, 0),
+    ];
+    for (dx, dy, dz) in neighbors {
+        let npos = BlockPos {
+            x: pos.x + dx,
+            y: pos.y + dy,
+            z: pos.z + dz,
+            dimension: pos.dimension.clone(),
+        };
+        schedule_component_update(world, tm, cache, npos);
+    }
+}
+
+fn offset(pos: &BlockPos, (dx, dy, dz): (i32, i32, i32)) -> BlockPos {
+    BlockPos {
+        x: pos.x + dx,
+        y: pos.y + dy,
+        z: pos.z + dz,
+        dimension: pos.dimension.clone(),
+    }
+}
+
+fn tick_torch(
+    world: &World,
+    tm: &mut Scheduler,
+    cache: &mut PowerLevelCache,
+    pos: &BlockPos,
+    block: BlockId,
+) -> Result<(), WorldError> {
+    propagate_from(world, cache, pos.clone(), 15);
+    propagate_block_update(world, tm, cache, pos);
+    tm.schedule(pos.clone(), block, 1);
+    Ok(())
+}
+
+fn tick_repeater(
+    world: &World,
+    tm: &mut Scheduler,
+    cache: &mut PowerLevelCache,
+    pos: &BlockPos,
+    delay: u8,
+    facing: Direction,
+    block: BlockId,
+) -> Result<(), WorldError> {
+    let input = offset(pos, facing.opposite().offset());
+    let input_key = (input.x, input.y, input.z, input.dimension);
+    let input_power = *cache.levels.get(&input_key).unwrap_or(&0);
+
+    let output_pos = offset(pos, facing.offset());
+    if input_power > 0 {
+        propagate_from(world, cache, output_pos.clone(), 15);
+    } else {
+        cache.levels.insert(
+            (
+                output_pos.x,
+                output_pos.y,
+                output_pos.z,
+                output_pos.dimension.clone(),
+            ),
+            0,
+        );
+    }
+    schedule_component_update(world, tm, cache, output_pos);
+    propagate_block_update(world, tm, cache, pos);
+    tm.schedule(pos.clone(), block, delay as u32);
+    Ok(())
+}
+
+fn tick_observer(
+    world: &World,
+    tm: &mut Scheduler,
+    cache: &mut PowerLevelCache,
+    pos: &BlockPos,
+    facing: Direction,
+    block: BlockId,
+) -> Result<(), WorldError> {
+    let output_pos = offset(pos, facing.offset());
+    let output_key = (
+        output_pos.x,
+        output_pos.y,
+        output_pos.z,
+        output_pos.dimension.clone(),
+    );
+    let current_power = *cache.levels.get(&output_key).unwrap_or(&0);
+    if current_power == 0 {
+        propagate_from(world, cache, output_pos.clone(), 15);
+    } else {
+        cache.levels.insert(output_key, 0);
+    }
+    schedule_component_update(world, tm, cache, output_pos);
+    propagate_block_update(world, tm, cache, pos);
+    tm.schedule(pos.clone(), block, 1);
+    Ok(())
+}
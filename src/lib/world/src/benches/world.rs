@@ -1,12 +1,15 @@
 mod cache;
 mod chunk_loading;
 mod edit_bench;
+mod redstone;
+mod redstone_legacy;
 
 use criterion::{criterion_group, criterion_main};
 fn world_benches(c: &mut criterion::Criterion) {
     edit_bench::bench_edits(c);
     cache::bench_cache(c);
     chunk_loading::bench_chunk_loading(c);
+    redstone::bench_redstone(c);
 }
 criterion_group!(world_bench, world_benches);
 criterion_main!(world_bench);
@@ -19,6 +19,10 @@ lazy_static! {
             .map(|e| (format!("minecraft:{}", e.name), e.id))
             .collect()
     };
+    pub static ref BIOME_ID_TO_NAME: HashMap<i32, String> = BIOME_NAME_TO_ID
+        .iter()
+        .map(|(name, id)| (*id, name.clone()))
+        .collect();
 }
 
 /// Retrieve the biome id for a given biome name.
@@ -30,3 +34,13 @@ pub fn get_biome_id(name: &str) -> Option<i32> {
         None
     }
 }
+
+/// Retrieve the namespaced biome name for a given biome id.
+pub fn get_biome_name(id: i32) -> Option<&'static str> {
+    if let Some(name) = BIOME_ID_TO_NAME.get(&id) {
+        Some(name.as_str())
+    } else {
+        error!("Could not find biome name for id: {}", id);
+        None
+    }
+}
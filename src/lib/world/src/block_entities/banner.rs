@@ -0,0 +1,21 @@
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct BannerBlockEntity {
+    #[nbt(rename = "CustomName")]
+    pub custom_name: Option<String>,
+    #[nbt(rename = "Patterns")]
+    pub patterns: Option<Vec<BannerPattern>>,
+}
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq)]
+pub struct BannerPattern {
+    /// The short code of the pattern, e.g. `bs` for a base.
+    #[nbt(rename = "Pattern")]
+    pub pattern: String,
+    /// The dye colour id.
+    #[nbt(rename = "Color")]
+    pub color: i32,
+}
@@ -0,0 +1,10 @@
+use crate::block_entities::chest::ChestItem;
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct BarrelBlockEntity {
+    #[nbt(rename = "Items")]
+    pub items: Vec<ChestItem>,
+}
@@ -0,0 +1,14 @@
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct BeaconBlockEntity {
+    /// How many layers of the pyramid under the beacon are complete.
+    #[nbt(rename = "Levels")]
+    pub levels: i32,
+    #[nbt(rename = "primary_effect")]
+    pub primary_effect: Option<String>,
+    #[nbt(rename = "secondary_effect")]
+    pub secondary_effect: Option<String>,
+}
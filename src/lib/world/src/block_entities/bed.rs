@@ -0,0 +1,7 @@
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+/// Beds keep no data of their own, the colour comes from the block.
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct BedBlockEntity {}
@@ -0,0 +1,16 @@
+use crate::block_entities::chest::ChestItem;
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+/// Slots 0 to 2 hold the potions, 3 the ingredient and 4 the blaze powder.
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct BrewingStandBlockEntity {
+    #[nbt(rename = "Items")]
+    pub items: Vec<ChestItem>,
+    #[nbt(rename = "BrewTime")]
+    pub brew_time: i16,
+    /// Brews left from the blaze powder put in last.
+    #[nbt(rename = "Fuel")]
+    pub fuel: i8,
+}
@@ -0,0 +1,19 @@
+use crate::block_entities::chest::ChestItem;
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct DispenserBlockEntity {
+    #[nbt(rename = "Items")]
+    pub items: Vec<ChestItem>,
+}
+
+/// Droppers keep the same data as dispensers, under their own type.
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct DropperBlockEntity {
+    #[nbt(rename = "Items")]
+    pub items: Vec<ChestItem>,
+}
@@ -1,5 +1,8 @@
+use crate::block_entities::chest::ChestItem;
 use ferrumc_macros::{NBTDeserialize, NBTSerialize};
 
+/// Furnaces, smokers and blast furnaces. Slot 0 holds what's being smelted, 1 the fuel and 2 the
+/// result.
 #[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
 #[nbt(is_root)]
 #[nbt(rename = "")]
@@ -14,12 +17,5 @@ pub struct FurnaceBlockEntity {
     pub cook_time_total: i16,
 }
 
-#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq)]
-pub struct FurnaceItem {
-    #[nbt(rename = "Slot")]
-    pub slot: i8,
-    #[nbt(rename = "id")]
-    pub id: String,
-    #[nbt(rename = "Count")]
-    pub count: u8,
-}
+/// Furnace slots are stored the same way as chest ones.
+pub type FurnaceItem = ChestItem;
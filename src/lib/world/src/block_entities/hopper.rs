@@ -0,0 +1,13 @@
+use crate::block_entities::chest::ChestItem;
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct HopperBlockEntity {
+    #[nbt(rename = "Items")]
+    pub items: Vec<ChestItem>,
+    /// Game ticks until the hopper moves another item.
+    #[nbt(rename = "TransferCooldown")]
+    pub transfer_cooldown: i32,
+}
@@ -0,0 +1,20 @@
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct LecternBlockEntity {
+    #[nbt(rename = "Book")]
+    pub book: Option<LecternBook>,
+    /// The page the book is open at.
+    #[nbt(rename = "Page")]
+    pub page: i32,
+}
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq)]
+pub struct LecternBook {
+    #[nbt(rename = "id")]
+    pub id: String,
+    #[nbt(rename = "Count")]
+    pub count: u8,
+}
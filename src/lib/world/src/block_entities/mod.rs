@@ -1,2 +1,537 @@
+//! Block entities, the extra data some blocks keep next to their state, like the items in a chest
+//! or the text on a sign.
+//!
+//! Every block entity type has a type id from [`BLOCK_ENTITY_TYPES`], and the common ones have a
+//! typed NBT struct implementing [`BlockEntityKind`]. Setting a block in a [`Chunk`] creates the
+//! block entity its block needs with default data and removes the one of the block it replaced.
+//! Block entities changed through the [`World`] are queued for sending to players, see
+//! [`World::take_block_entity_updates`].
+
+pub mod banner;
+pub mod barrel;
+pub mod beacon;
+pub mod bed;
+pub mod brewing_stand;
 pub mod chest;
+pub mod dispenser;
 pub mod furnace;
+pub mod hopper;
+pub mod lectern;
+pub mod shulker_box;
+pub mod sign;
+pub mod skull;
+pub mod spawner;
+
+use crate::block_entities::chest::ChestItem;
+use crate::block_id::{BlockId, ID2BLOCK};
+use crate::chunk_format::Chunk;
+use crate::errors::WorldError;
+use crate::item_id::ItemId;
+use crate::World;
+use ferrumc_nbt::{FromNbt, NBTSerializable, NBTSerializeOptions, NbtTape};
+use ferrumc_net_codec::net_types::var_int::VarInt;
+use lazy_static::lazy_static;
+
+/// The block entity types in the order of the `minecraft:block_entity_type` registry for 1.20.1.
+/// The index is the type id sent to the client and stored in
+/// [`BlockEntity::entity_type`](crate::chunk_format::BlockEntity::entity_type).
+pub const BLOCK_ENTITY_TYPES: [&str; 41] = [
+    "minecraft:furnace",
+    "minecraft:chest",
+    "minecraft:trapped_chest",
+    "minecraft:ender_chest",
+    "minecraft:jukebox",
+    "minecraft:dispenser",
+    "minecraft:dropper",
+    "minecraft:sign",
+    "minecraft:hanging_sign",
+    "minecraft:mob_spawner",
+    "minecraft:piston",
+    "minecraft:brewing_stand",
+    "minecraft:enchanting_table",
+    "minecraft:end_portal",
+    "minecraft:beacon",
+    "minecraft:skull",
+    "minecraft:daylight_detector",
+    "minecraft:hopper",
+    "minecraft:comparator",
+    "minecraft:banner",
+    "minecraft:structure_block",
+    "minecraft:end_gateway",
+    "minecraft:command_block",
+    "minecraft:shulker_box",
+    "minecraft:bed",
+    "minecraft:conduit",
+    "minecraft:barrel",
+    "minecraft:smoker",
+    "minecraft:blast_furnace",
+    "minecraft:lectern",
+    "minecraft:bell",
+    "minecraft:jigsaw",
+    "minecraft:campfire",
+    "minecraft:beehive",
+    "minecraft:sculk_sensor",
+    "minecraft:calibrated_sculk_sensor",
+    "minecraft:sculk_catalyst",
+    "minecraft:sculk_shrieker",
+    "minecraft:chiseled_bookshelf",
+    "minecraft:brushable_block",
+    "minecraft:decorated_pot",
+];
+
+/// Get the namespaced name of a block entity type id.
+pub fn block_entity_type_name(entity_type: i32) -> Option<&'static str> {
+    usize::try_from(entity_type)
+        .ok()
+        .and_then(|index| BLOCK_ENTITY_TYPES.get(index))
+        .copied()
+}
+
+/// Get the type id of a namespaced block entity type, e.g. `minecraft:sign`.
+pub fn block_entity_type_id(name: &str) -> Option<VarInt> {
+    BLOCK_ENTITY_TYPES
+        .iter()
+        .position(|entity_type| *entity_type == name)
+        .map(|index| VarInt::new(index as i32))
+}
+
+/// A block entity type with a typed NBT layout.
+pub trait BlockEntityKind: NBTSerializable + for<'a> FromNbt<'a> + Default {
+    /// The namespaced block entity type.
+    const TYPE: &'static str;
+
+    /// The type id sent to the client and stored with the block entity.
+    fn type_id() -> VarInt {
+        block_entity_type_id(Self::TYPE).expect("Block entity type is not registered")
+    }
+}
+
+macro_rules! block_entity_kinds {
+    ($($kind:ty => $name:literal),* $(,)?) => {
+        $(
+            impl BlockEntityKind for $kind {
+                const TYPE: &'static str = $name;
+            }
+        )*
+
+        /// The NBT a new block entity of a type starts with. Types without a typed layout get an
+        /// empty compound.
+        pub fn default_nbt(entity_type: VarInt) -> Vec<u8> {
+            match block_entity_type_name(entity_type.0) {
+                $(Some($name) => encode(&<$kind>::default()),)*
+                _ => vec![10, 0, 0, 0],
+            }
+        }
+
+        /// Whether block entity data can be read into the typed layout of its type. Types
+        /// without a typed layout take anything.
+        pub(crate) fn fits_layout(entity_type: &str, nbt: &[u8]) -> bool {
+            match entity_type {
+                $($name => fits::<$kind>(nbt),)*
+                _ => true,
+            }
+        }
+    };
+}
+
+block_entity_kinds! {
+    banner::BannerBlockEntity => "minecraft:banner",
+    barrel::BarrelBlockEntity => "minecraft:barrel",
+    beacon::BeaconBlockEntity => "minecraft:beacon",
+    bed::BedBlockEntity => "minecraft:bed",
+    brewing_stand::BrewingStandBlockEntity => "minecraft:brewing_stand",
+    chest::ChestBlockEntity => "minecraft:chest",
+    dispenser::DispenserBlockEntity => "minecraft:dispenser",
+    dispenser::DropperBlockEntity => "minecraft:dropper",
+    furnace::FurnaceBlockEntity => "minecraft:furnace",
+    hopper::HopperBlockEntity => "minecraft:hopper",
+    lectern::LecternBlockEntity => "minecraft:lectern",
+    shulker_box::ShulkerBoxBlockEntity => "minecraft:shulker_box",
+    sign::SignBlockEntity => "minecraft:sign",
+    skull::SkullBlockEntity => "minecraft:skull",
+    spawner::SpawnerBlockEntity => "minecraft:mob_spawner",
+}
+
+fn fits<T: for<'a> FromNbt<'a>>(nbt: &[u8]) -> bool {
+    let mut tape = NbtTape::new(nbt);
+    tape.parse();
+    tape.root
+        .as_ref()
+        .is_some_and(|(_, root)| T::from_nbt(&tape, root).is_ok())
+}
+
+/// Serialize block entity data the way it's stored in chunks.
+pub fn encode<T: NBTSerializable>(data: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    data.serialize(&mut buf, &NBTSerializeOptions::WithHeader(""));
+    buf
+}
+
+/// Blocks whose block entity type isn't their own name.
+const BLOCK_ENTITY_BLOCKS: &[(&str, &str)] = &[
+    ("spawner", "mob_spawner"),
+    ("soul_campfire", "campfire"),
+    ("bee_nest", "beehive"),
+    ("chain_command_block", "command_block"),
+    ("repeating_command_block", "command_block"),
+    ("suspicious_sand", "brushable_block"),
+    ("suspicious_gravel", "brushable_block"),
+];
+
+/// Block name suffixes that share a block entity type, checked in order.
+const BLOCK_ENTITY_SUFFIXES: &[(&str, &str)] = &[
+    ("_hanging_sign", "hanging_sign"),
+    ("_sign", "sign"),
+    ("_banner", "banner"),
+    ("_bed", "bed"),
+    ("_skull", "skull"),
+    ("_head", "skull"),
+    ("shulker_box", "shulker_box"),
+];
+
+/// Block entity types that aren't created by placing their block.
+const NOT_PLACED: &[&str] = &["minecraft:piston"];
+
+lazy_static! {
+    /// The block entity type id every block state needs, indexed by block ID.
+    static ref BLOCK_ENTITY_TYPE: Vec<Option<VarInt>> = ID2BLOCK
+        .iter()
+        .map(|block| type_for_block(&block.name))
+        .collect();
+}
+
+fn type_for_block(name: &str) -> Option<VarInt> {
+    let name = name.trim_start_matches("minecraft:");
+    if name == "piston_head" {
+        return None;
+    }
+    let entity_type = BLOCK_ENTITY_BLOCKS
+        .iter()
+        .chain(BLOCK_ENTITY_SUFFIXES)
+        .find(|(block, _)| name == *block || name.ends_with(*block))
+        .map_or(name, |(_, entity_type)| *entity_type);
+    let entity_type = format!("minecraft:{entity_type}");
+    if NOT_PLACED.contains(&entity_type.as_str()) {
+        return None;
+    }
+    block_entity_type_id(&entity_type)
+}
+
+/// Get the type id of the block entity a block needs, if any.
+pub fn block_entity_type(block: BlockId) -> Option<VarInt> {
+    BLOCK_ENTITY_TYPE.get(block.0 as usize).copied().flatten()
+}
+
+/// A block entity holding items in numbered slots.
+pub trait Container {
+    /// How many slots it has.
+    fn size(&self) -> u8;
+
+    fn items(&self) -> &[ChestItem];
+
+    fn items_mut(&mut self) -> &mut Vec<ChestItem>;
+
+    /// The data to store for it, see [`encode`].
+    fn to_nbt(&self) -> Vec<u8>;
+
+    /// The signal a comparator reading it gives out, from how full its slots are.
+    fn signal(&self) -> u8 {
+        let fullness: f32 = self
+            .items()
+            .iter()
+            .filter(|item| item.count > 0)
+            .map(|item| {
+                let max = ItemId::from_name(&item.id).map_or(64, |id| id.max_stack_size());
+                item.count as f32 / max.max(1) as f32
+            })
+            .sum();
+        if fullness == 0.0 {
+            return 0;
+        }
+        1 + (fullness / self.size() as f32 * 14.0) as u8
+    }
+}
+
+macro_rules! containers {
+    ($($kind:ty => $size:literal),* $(,)?) => {
+        $(
+            impl Container for $kind {
+                fn size(&self) -> u8 {
+                    $size
+                }
+
+                fn items(&self) -> &[ChestItem] {
+                    &self.items
+                }
+
+                fn items_mut(&mut self) -> &mut Vec<ChestItem> {
+                    &mut self.items
+                }
+
+                fn to_nbt(&self) -> Vec<u8> {
+                    encode(self)
+                }
+            }
+        )*
+    };
+}
+
+containers! {
+    barrel::BarrelBlockEntity => 27,
+    brewing_stand::BrewingStandBlockEntity => 5,
+    chest::ChestBlockEntity => 27,
+    dispenser::DispenserBlockEntity => 9,
+    dispenser::DropperBlockEntity => 9,
+    furnace::FurnaceBlockEntity => 3,
+    hopper::HopperBlockEntity => 5,
+    shulker_box::ShulkerBoxBlockEntity => 27,
+}
+
+/// Block entity types that hold items.
+const CONTAINERS: &[&str] = &[
+    "minecraft:barrel",
+    "minecraft:blast_furnace",
+    "minecraft:brewing_stand",
+    "minecraft:chest",
+    "minecraft:dispenser",
+    "minecraft:dropper",
+    "minecraft:furnace",
+    "minecraft:hopper",
+    "minecraft:shulker_box",
+    "minecraft:smoker",
+    "minecraft:trapped_chest",
+];
+
+/// Whether a block keeps items in its block entity.
+pub fn is_container(block: BlockId) -> bool {
+    block_entity_type(block)
+        .and_then(|entity_type| block_entity_type_name(entity_type.0))
+        .is_some_and(|name| CONTAINERS.contains(&name))
+}
+
+/// Read the container at chunk-local coordinates, `None` if there isn't one or its data is
+/// broken.
+pub fn load_container(chunk: &Chunk, x: u8, y: u16, z: u8) -> Option<Box<dyn Container>> {
+    fn load<T: Container + for<'a> FromNbt<'a> + 'static>(
+        chunk: &Chunk,
+        x: u8,
+        y: u16,
+        z: u8,
+    ) -> Option<Box<dyn Container>> {
+        let container = chunk.get_block_entity_data::<T>(x, y, z)?;
+        Some(Box::new(container))
+    }
+    let entity_type = chunk.get_block_entity(x, y, z)?.entity_type;
+    match block_entity_type_name(entity_type.0)? {
+        "minecraft:barrel" => load::<barrel::BarrelBlockEntity>(chunk, x, y, z),
+        "minecraft:brewing_stand" => load::<brewing_stand::BrewingStandBlockEntity>(chunk, x, y, z),
+        "minecraft:chest" | "minecraft:trapped_chest" => {
+            load::<chest::ChestBlockEntity>(chunk, x, y, z)
+        }
+        "minecraft:dispenser" => load::<dispenser::DispenserBlockEntity>(chunk, x, y, z),
+        "minecraft:dropper" => load::<dispenser::DropperBlockEntity>(chunk, x, y, z),
+        "minecraft:furnace" | "minecraft:smoker" | "minecraft:blast_furnace" => {
+            load::<furnace::FurnaceBlockEntity>(chunk, x, y, z)
+        }
+        "minecraft:hopper" => load::<hopper::HopperBlockEntity>(chunk, x, y, z),
+        "minecraft:shulker_box" => load::<shulker_box::ShulkerBoxBlockEntity>(chunk, x, y, z),
+        _ => None,
+    }
+}
+
+/// A block entity that was created or changed, for sending to players.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockEntityUpdate {
+    pub dimension: String,
+    pub position: (i32, i32, i32),
+    pub entity_type: VarInt,
+    pub nbt: Vec<u8>,
+}
+
+impl Chunk {
+    /// Give a block that was just set the block entity it needs, keeping one of the same type
+    /// that's already there, and remove any other.
+    pub(crate) fn replace_block_entity(&mut self, x: i32, y: i32, z: i32, block: BlockId) {
+        let (x, y, z) = ((x & 15) as u8, y as u16, (z & 15) as u8);
+        let entity_type = block_entity_type(block);
+        if self.get_block_entity(x, y, z).map(|be| be.entity_type) == entity_type {
+            return;
+        }
+        self.remove_block_entity(x, y, z);
+        if let Some(entity_type) = entity_type {
+            self.set_block_entity(x, y, z, entity_type, default_nbt(entity_type));
+        }
+    }
+}
+
+impl World {
+    /// Save a block entity with a typed layout at world coordinates.
+    pub fn save_typed_block_entity<T: BlockEntityKind>(
+        &self,
+        x: i32,
+        y: i32,
+        z: i32,
+        dimension: &str,
+        data: &T,
+    ) -> Result<(), WorldError> {
+        self.save_block_entity(x, y, z, dimension, T::type_id(), data)
+    }
+
+    /// Queue a block entity for sending to players.
+    pub(crate) fn block_entity_changed(
+        &self,
+        dimension: &str,
+        position: (i32, i32, i32),
+        entity_type: VarInt,
+        nbt: Vec<u8>,
+    ) {
+        self.block_entity_updates
+            .lock()
+            .unwrap()
+            .push(BlockEntityUpdate {
+                dimension: dimension.to_string(),
+                position,
+                entity_type,
+                nbt,
+            });
+    }
+
+    /// Take the block entities created or changed since this was last called, for sending to
+    /// players.
+    pub fn take_block_entity_updates(&self) -> Vec<BlockEntityUpdate> {
+        std::mem::take(&mut self.block_entity_updates.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_entities::chest::{ChestBlockEntity, ChestItem};
+    use crate::block_entities::sign::{SignBlockEntity, SignText};
+    use crate::block_entities::spawner::SpawnerBlockEntity;
+    use crate::block_id::Direction;
+    use crate::testing::memory_world;
+
+    fn block(name: &str) -> BlockId {
+        BlockId::from_name(&format!("minecraft:{name}")).unwrap()
+    }
+
+    fn type_name(name: &str) -> Option<&'static str> {
+        block_entity_type(block(name)).and_then(|entity_type| block_entity_type_name(entity_type.0))
+    }
+
+    #[test]
+    fn test_blocks_get_their_block_entity_type() {
+        assert_eq!(type_name("chest"), Some("minecraft:chest"));
+        assert_eq!(type_name("oak_wall_sign"), Some("minecraft:sign"));
+        assert_eq!(
+            type_name("oak_hanging_sign"),
+            Some("minecraft:hanging_sign")
+        );
+        assert_eq!(type_name("red_wall_banner"), Some("minecraft:banner"));
+        assert_eq!(type_name("red_bed"), Some("minecraft:bed"));
+        assert_eq!(type_name("creeper_head"), Some("minecraft:skull"));
+        assert_eq!(type_name("shulker_box"), Some("minecraft:shulker_box"));
+        assert_eq!(type_name("lime_shulker_box"), Some("minecraft:shulker_box"));
+        assert_eq!(type_name("spawner"), Some("minecraft:mob_spawner"));
+        assert_eq!(type_name("lectern"), Some("minecraft:lectern"));
+        assert_eq!(type_name("piston"), None);
+        assert_eq!(type_name("piston_head"), None);
+        assert_eq!(type_name("stone"), None);
+    }
+
+    #[test]
+    fn test_new_block_entities_start_with_defaults() {
+        let mut chunk = Chunk::new(0, 0, "overworld".to_string());
+        chunk.set_block(1, 64, 1, block("oak_sign")).unwrap();
+        chunk.set_block(2, 64, 1, block("spawner")).unwrap();
+        assert_eq!(
+            chunk.get_block_entity_data::<SignBlockEntity>(1, 64, 1),
+            Some(SignBlockEntity::default())
+        );
+        assert_eq!(
+            chunk.get_block_entity_data::<SpawnerBlockEntity>(2, 64, 1),
+            Some(SpawnerBlockEntity::default())
+        );
+    }
+
+    #[test]
+    fn test_block_entities_follow_their_block() {
+        let mut chunk = Chunk::new(0, 0, "overworld".to_string());
+        let chest = ChestBlockEntity {
+            items: vec![ChestItem {
+                slot: 3,
+                id: "minecraft:stone".to_string(),
+                count: 5,
+            }],
+        };
+        chunk.set_block(1, 64, 1, block("chest")).unwrap();
+        chunk.set_block_entity_data(1, 64, 1, ChestBlockEntity::type_id(), &chest);
+
+        // Turning the chest keeps its items
+        let turned = block("chest").with(Direction::East).unwrap();
+        chunk.set_block(1, 64, 1, turned).unwrap();
+        assert_eq!(chunk.get_block_entity_data(1, 64, 1), Some(chest));
+
+        chunk.set_block(1, 64, 1, block("barrel")).unwrap();
+        assert_eq!(
+            chunk.get_block_entity(1, 64, 1).map(|be| be.entity_type),
+            block_entity_type_id("minecraft:barrel")
+        );
+        chunk.set_block(1, 64, 1, block("stone")).unwrap();
+        assert!(chunk.block_entities.is_empty());
+    }
+
+    #[test]
+    fn test_block_entities_are_queued_for_players() {
+        let (world, _) = memory_world([(0, 0)]);
+        world
+            .set_block_and_fetch(3, 64, 4, "overworld", block("oak_sign"))
+            .unwrap();
+        let updates = world.take_block_entity_updates();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].position, (3, 64, 4));
+        assert_eq!(updates[0].entity_type, SignBlockEntity::type_id());
+        assert_eq!(
+            world
+                .load_block_entity::<SignBlockEntity>(3, 64, 4, "overworld")
+                .unwrap(),
+            SignBlockEntity::default()
+        );
+
+        let written = SignBlockEntity {
+            front_text: SignText {
+                messages: ["\"Hello\"", "\"\"", "\"\"", "\"\""]
+                    .map(str::to_string)
+                    .to_vec(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        world
+            .save_typed_block_entity(3, 64, 4, "overworld", &written)
+            .unwrap();
+        assert!(matches!(
+            world.take_block_entity_updates().as_slice(),
+            [BlockEntityUpdate {
+                position: (3, 64, 4),
+                ..
+            }]
+        ));
+        assert_eq!(
+            world
+                .load_block_entity::<SignBlockEntity>(3, 64, 4, "overworld")
+                .unwrap(),
+            written
+        );
+
+        // Breaking the sign takes its text with it
+        world
+            .set_block_and_fetch(3, 64, 4, "overworld", BlockId::default())
+            .unwrap();
+        assert!(world.take_block_entity_updates().is_empty());
+        assert!(world
+            .load_block_entity::<SignBlockEntity>(3, 64, 4, "overworld")
+            .is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+/// Signs and hanging signs, which share the same layout.
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct SignBlockEntity {
+    #[nbt(rename = "front_text")]
+    pub front_text: SignText,
+    #[nbt(rename = "back_text")]
+    pub back_text: SignText,
+    #[nbt(rename = "is_waxed")]
+    pub is_waxed: bool,
+}
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq)]
+pub struct SignText {
+    /// The four lines as JSON text components.
+    #[nbt(rename = "messages")]
+    pub messages: Vec<String>,
+    #[nbt(rename = "color")]
+    pub color: String,
+    #[nbt(rename = "has_glowing_text")]
+    pub has_glowing_text: bool,
+}
+
+impl Default for SignText {
+    fn default() -> Self {
+        SignText {
+            messages: vec!["\"\"".to_string(); 4],
+            color: "black".to_string(),
+            has_glowing_text: false,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+/// Mob and player heads.
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq, Default)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct SkullBlockEntity {
+    #[nbt(rename = "SkullOwner")]
+    pub skull_owner: Option<SkullOwner>,
+    /// The sound a note block plays with the head on top of it.
+    #[nbt(rename = "note_block_sound")]
+    pub note_block_sound: Option<String>,
+}
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq)]
+pub struct SkullOwner {
+    #[nbt(rename = "Name")]
+    pub name: String,
+}
@@ -0,0 +1,53 @@
+use ferrumc_macros::{NBTDeserialize, NBTSerialize};
+
+/// Monster spawners. Delays are in game ticks and ranges in blocks.
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq)]
+#[nbt(is_root)]
+#[nbt(rename = "")]
+pub struct SpawnerBlockEntity {
+    #[nbt(rename = "SpawnData")]
+    pub spawn_data: Option<SpawnData>,
+    #[nbt(rename = "Delay")]
+    pub delay: i16,
+    #[nbt(rename = "MinSpawnDelay")]
+    pub min_spawn_delay: i16,
+    #[nbt(rename = "MaxSpawnDelay")]
+    pub max_spawn_delay: i16,
+    #[nbt(rename = "SpawnCount")]
+    pub spawn_count: i16,
+    #[nbt(rename = "MaxNearbyEntities")]
+    pub max_nearby_entities: i16,
+    #[nbt(rename = "RequiredPlayerRange")]
+    pub required_player_range: i16,
+    #[nbt(rename = "SpawnRange")]
+    pub spawn_range: i16,
+}
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq)]
+pub struct SpawnData {
+    #[nbt(rename = "entity")]
+    pub entity: SpawnEntity,
+}
+
+#[derive(NBTDeserialize, NBTSerialize, Debug, Clone, PartialEq)]
+pub struct SpawnEntity {
+    /// The namespaced entity type, e.g. `minecraft:zombie`.
+    #[nbt(rename = "id")]
+    pub id: String,
+}
+
+impl Default for SpawnerBlockEntity {
+    /// The values vanilla gives a new spawner.
+    fn default() -> Self {
+        SpawnerBlockEntity {
+            spawn_data: None,
+            delay: 20,
+            min_spawn_delay: 200,
+            max_spawn_delay: 800,
+            spawn_count: 4,
+            max_nearby_entities: 6,
+            required_player_range: 16,
+            spawn_range: 4,
+        }
+    }
+}
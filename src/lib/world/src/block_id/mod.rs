@@ -9,6 +9,10 @@ use std::io::Read;
 use std::process::exit;
 use tracing::error;
 
+mod states;
+
+pub use states::*;
+
 // The number of block entries in the mappings file
 // Go to the .etc/blockstates.json file, see what the last ID is, and add 1 to it.
 const BLOCK_ENTRIES: usize = 24276;
@@ -59,6 +63,17 @@ impl BlockId {
         BlockId(*id as u32)
     }
 
+    /// Get the first state of a block by its name, e.g. `minecraft:oak_log`. Returns None if
+    /// there's no block with that name.
+    ///
+    /// This searches every block state, so don't use it in hot loops either.
+    pub fn from_name(name: &str) -> Option<Self> {
+        ID2BLOCK
+            .iter()
+            .position(|block| block.name == name)
+            .map(|id| BlockId(id as u32))
+    }
+
     /// Given a block ID, return a BlockData. Will clone, so don't use in hot loops.
     /// If the ID is not found, returns None.
     pub fn to_block_data(&self) -> Option<BlockData> {
@@ -0,0 +1,422 @@
+//! Typed access to block state properties, without going through [`BlockData`].
+//!
+//! Vanilla numbers the states of each block one after the other, counting through the values of
+//! its properties like the digits of a number, with the last property changing fastest. The
+//! layout of every block is worked out from the block mappings at build time, so reading or
+//! changing a property is a bit of arithmetic on the id instead of building a [`BlockData`] and
+//! hashing it.
+//!
+//! Every block also gets a type of its own in [`blocks`], with a field per property and an enum
+//! for each property that has names for values, e.g. [`blocks::Repeater`]. Those are for code that
+//! deals with one kind of block. Properties that many blocks share, like `facing` or `powered`,
+//! can be read from any block with the [`StateProperty`] types further down.
+//!
+//! [`BlockData`]: crate::vanilla_chunk_format::BlockData
+
+use super::BlockId;
+use crate::errors::WorldError;
+
+struct PropertyLayout {
+    name: &'static str,
+    /// In the order vanilla numbers them.
+    values: &'static [&'static str],
+    /// How far apart the ids of two states are when only this property differs by one value.
+    stride: u32,
+}
+
+struct BlockLayout {
+    name: &'static str,
+    first: u32,
+    properties: &'static [PropertyLayout],
+}
+
+impl BlockLayout {
+    fn property(&self, name: &str) -> Option<&PropertyLayout> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+}
+
+// `BLOCKS`, `STATE_BLOCKS` (the index into `BLOCKS` of every state id) and the `blocks` module
+include!(concat!(env!("OUT_DIR"), "/block_states.rs"));
+
+/// A block's states as a type, with a field for each property. Implemented by every type in
+/// [`blocks`].
+pub trait BlockState: Copy {
+    /// The namespaced name of the block, e.g. `minecraft:repeater`.
+    const NAME: &'static str;
+    /// The id of the block's first state.
+    const FIRST: u32;
+    /// How many states the block has.
+    const COUNT: u32;
+
+    /// The state that's `offset` ids after the first one. `offset` is less than [`Self::COUNT`].
+    fn from_offset(offset: u32) -> Self;
+
+    /// How many ids after the first one this state is, or None if one of its numbers is out of
+    /// range, e.g. a repeater with a delay of 5.
+    fn to_offset(self) -> Option<u32>;
+}
+
+impl BlockId {
+    fn layout(&self) -> Option<&'static BlockLayout> {
+        let index = *STATE_BLOCKS.get(self.0 as usize)?;
+        BLOCKS.get(index as usize)
+    }
+
+    /// The name of the block, e.g. `minecraft:oak_log`. Returns None if the id is invalid.
+    pub fn name(&self) -> Option<&'static str> {
+        self.layout().map(|layout| layout.name)
+    }
+
+    /// The first state of the block, which is what [`BlockId::from_name`] finds.
+    pub fn first_state(&self) -> Option<BlockId> {
+        self.layout().map(|layout| BlockId(layout.first))
+    }
+
+    /// The value of one of the block's properties, e.g. `north` for `facing`. Returns None if the
+    /// block doesn't have that property.
+    pub fn get_property(&self, name: &str) -> Option<&'static str> {
+        let layout = self.layout()?;
+        let property = layout.property(name)?;
+        let index = (self.0 - layout.first) / property.stride % property.values.len() as u32;
+        Some(property.values[index as usize])
+    }
+
+    /// The same block with one of its properties changed, the rest stay as they are.
+    ///
+    /// Fails if the block doesn't have the property or the value isn't one it can take.
+    pub fn with_property(&self, name: &str, value: &str) -> Result<BlockId, WorldError> {
+        let invalid = || {
+            WorldError::InvalidBlockProperty(
+                self.name().unwrap_or("unknown block").to_string(),
+                format!("{name}={value}"),
+            )
+        };
+        let layout = self.layout().ok_or(WorldError::InvalidBlockId(self.0))?;
+        let property = layout.property(name).ok_or_else(invalid)?;
+        let new = property
+            .values
+            .iter()
+            .position(|v| *v == value)
+            .ok_or_else(invalid)?;
+        Ok(self.with_value_index(layout, property, new))
+    }
+
+    fn with_value_index(
+        &self,
+        layout: &BlockLayout,
+        property: &PropertyLayout,
+        new: usize,
+    ) -> BlockId {
+        let current = (self.0 - layout.first) / property.stride % property.values.len() as u32;
+        BlockId(self.0 - current * property.stride + new as u32 * property.stride)
+    }
+
+    /// Read a property as its typed value. Returns None if the block doesn't have it.
+    pub fn get<P: StateProperty>(&self) -> Option<P> {
+        self.get_property(P::NAME).and_then(P::from_value)
+    }
+
+    /// The same block with a property set to a typed value. Returns None if the block doesn't
+    /// have the property or can't take that value, e.g. a stair facing up.
+    pub fn with<P: StateProperty>(&self, value: P) -> Option<BlockId> {
+        let layout = self.layout()?;
+        let property = layout.property(P::NAME)?;
+        let new = property.values.iter().position(|v| value.matches(v))?;
+        Some(self.with_value_index(layout, property, new))
+    }
+
+    /// The state as its block's type, e.g. [`blocks::Repeater`]. Returns None if it's a state of
+    /// some other block.
+    pub fn state<B: BlockState>(&self) -> Option<B> {
+        let offset = self.0.checked_sub(B::FIRST)?;
+        (offset < B::COUNT).then(|| B::from_offset(offset))
+    }
+
+    /// The id of a block's state. Returns None if one of its numbers is out of range.
+    pub fn from_state<B: BlockState>(state: B) -> Option<BlockId> {
+        Some(BlockId(B::FIRST + state.to_offset()?))
+    }
+}
+
+/// A block state property with a typed value.
+pub trait StateProperty: Copy {
+    /// The name of the property in the block states, e.g. `facing`.
+    const NAME: &'static str;
+
+    fn from_value(value: &str) -> Option<Self>;
+
+    /// Whether the value in the block states is this one.
+    fn matches(self, value: &str) -> bool;
+}
+
+/// Defines a property whose values are a fixed set of names.
+macro_rules! enum_property {
+    ($(#[$meta:meta])* $name:ident, $key:literal { $($variant:ident => $value:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $value),+
+                }
+            }
+        }
+
+        impl StateProperty for $name {
+            const NAME: &'static str = $key;
+
+            fn from_value(value: &str) -> Option<Self> {
+                match value {
+                    $($value => Some($name::$variant),)+
+                    _ => None,
+                }
+            }
+
+            fn matches(self, value: &str) -> bool {
+                self.as_str() == value
+            }
+        }
+    };
+}
+
+/// Defines a property that's either `true` or `false`.
+macro_rules! bool_property {
+    ($(#[$meta:meta])* $name:ident, $key:literal) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub struct $name(pub bool);
+
+        impl StateProperty for $name {
+            const NAME: &'static str = $key;
+
+            fn from_value(value: &str) -> Option<Self> {
+                value.parse().ok().map($name)
+            }
+
+            fn matches(self, value: &str) -> bool {
+                value.parse::<bool>().ok() == Some(self.0)
+            }
+        }
+    };
+}
+
+/// Defines a property that's a small number.
+macro_rules! int_property {
+    ($(#[$meta:meta])* $name:ident, $key:literal) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub struct $name(pub u8);
+
+        impl StateProperty for $name {
+            const NAME: &'static str = $key;
+
+            fn from_value(value: &str) -> Option<Self> {
+                value.parse().ok().map($name)
+            }
+
+            fn matches(self, value: &str) -> bool {
+                value.parse::<u8>().ok() == Some(self.0)
+            }
+        }
+    };
+}
+
+enum_property!(
+    /// `facing`. Most blocks only face the four horizontal directions.
+    Direction, "facing" {
+        North => "north",
+        South => "south",
+        East => "east",
+        West => "west",
+        Up => "up",
+        Down => "down",
+    }
+);
+enum_property!(Axis, "axis" { X => "x", Y => "y", Z => "z" });
+enum_property!(
+    /// Stairs and trapdoors are `Top` or `Bottom`, doors and tall plants `Upper` or `Lower`.
+    Half, "half" { Top => "top", Bottom => "bottom", Upper => "upper", Lower => "lower" }
+);
+enum_property!(ComparatorMode, "mode" { Compare => "compare", Subtract => "subtract" });
+bool_property!(Powered, "powered");
+bool_property!(Lit, "lit");
+bool_property!(Open, "open");
+bool_property!(Waterlogged, "waterlogged");
+bool_property!(
+    /// Whether a piston's head is out.
+    Extended, "extended"
+);
+bool_property!(
+    /// Whether leaves were placed by a player, which stops them from decaying.
+    Persistent, "persistent"
+);
+bool_property!(
+    /// Whether a hopper moves items, which it stops doing while powered.
+    Enabled, "enabled"
+);
+bool_property!(
+    /// Whether a dispenser or dropper is powered.
+    Triggered, "triggered"
+);
+int_property!(
+    /// Redstone signal strength, 0 to 15.
+    Power, "power"
+);
+int_property!(
+    /// A repeater's delay in redstone ticks, 1 to 4.
+    Delay, "delay"
+);
+int_property!(Age, "age");
+int_property!(
+    /// How far leaves are from a log, counting through other leaves, 1 to 7.
+    Distance, "distance"
+);
+int_property!(Stage, "stage");
+int_property!(Moisture, "moisture");
+int_property!(
+    /// How far a fluid is from its source, 0 for the source itself.
+    Level, "level"
+);
+int_property!(
+    /// Which way a sign, banner or head points, in sixteenths of a turn from south.
+    Rotation, "rotation"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_id::ID2BLOCK;
+    use crate::vanilla_chunk_format::BlockData;
+    use std::collections::BTreeMap;
+
+    fn state(name: &str, properties: &[(&str, &str)]) -> BlockId {
+        let name = format!("minecraft:{name}");
+        let wanted: BTreeMap<String, String> = properties
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        ID2BLOCK
+            .iter()
+            .position(|state: &BlockData| {
+                state.name == name
+                    && wanted.iter().all(|(key, value)| {
+                        state.properties.as_ref().and_then(|p| p.get(key)) == Some(value)
+                    })
+            })
+            .map(|id| BlockId(id as u32))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_every_state_round_trips() {
+        for (id, data) in ID2BLOCK.iter().enumerate() {
+            let block = BlockId(id as u32);
+            assert_eq!(block.name(), Some(data.name.as_str()));
+            for (key, value) in data.properties.iter().flatten() {
+                assert_eq!(block.get_property(key), Some(value.as_str()));
+                assert_eq!(block.with_property(key, value).unwrap(), block);
+            }
+        }
+    }
+
+    #[test]
+    fn test_changing_properties() {
+        let repeater = state("repeater", &[("delay", "1"), ("facing", "north")]);
+        assert_eq!(repeater.get::<Delay>(), Some(Delay(1)));
+        let slower = repeater.with(Delay(3)).unwrap();
+        assert_eq!(slower.get::<Delay>(), Some(Delay(3)));
+        assert_eq!(slower.get::<Direction>(), Some(Direction::North));
+        assert_eq!(
+            slower,
+            state("repeater", &[("delay", "3"), ("facing", "north")])
+        );
+        assert_eq!(
+            slower.to_block_data(),
+            repeater
+                .with_property("delay", "3")
+                .unwrap()
+                .to_block_data()
+        );
+
+        // Repeaters only face sideways and don't have an axis
+        assert_eq!(repeater.with(Direction::Up), None);
+        assert_eq!(repeater.get::<Axis>(), None);
+        assert!(matches!(
+            repeater.with_property("delay", "5"),
+            Err(WorldError::InvalidBlockProperty(..))
+        ));
+        assert_eq!(BlockId::default().get::<Powered>(), None);
+        assert_eq!(BlockId::default().name(), Some("minecraft:air"));
+    }
+
+    #[test]
+    fn test_block_types() {
+        use blocks::repeater::Facing;
+
+        let id = state(
+            "repeater",
+            &[
+                ("delay", "2"),
+                ("facing", "west"),
+                ("locked", "false"),
+                ("powered", "true"),
+            ],
+        );
+        let repeater = id.state::<blocks::Repeater>().unwrap();
+        assert_eq!(
+            repeater,
+            blocks::Repeater {
+                delay: 2,
+                facing: Facing::West,
+                locked: false,
+                powered: true,
+            }
+        );
+        assert_eq!(BlockId::from_state(repeater), Some(id));
+        assert_eq!(
+            BlockId::from_state(blocks::Repeater {
+                facing: Facing::East,
+                ..repeater
+            }),
+            id.with(Direction::East)
+        );
+        assert_eq!(Facing::West.as_str(), "west");
+        assert_eq!(
+            BlockId::from_state(blocks::Repeater {
+                delay: 5,
+                ..repeater
+            }),
+            None
+        );
+
+        // Every state of a block is one of its type's values, and no other block's state is
+        let first = BlockId(blocks::Repeater::FIRST);
+        for offset in 0..blocks::Repeater::COUNT {
+            let id = BlockId(first.0 + offset);
+            assert_eq!(
+                BlockId::from_state(id.state::<blocks::Repeater>().unwrap()),
+                Some(id)
+            );
+        }
+        assert_eq!(BlockId(first.0 - 1).state::<blocks::Repeater>(), None);
+        assert_eq!(
+            BlockId(first.0 + blocks::Repeater::COUNT).state::<blocks::Repeater>(),
+            None
+        );
+        assert_eq!(BlockId::default().state::<blocks::Air>(), Some(blocks::Air));
+        assert_eq!(BlockId::from_state(blocks::Air), Some(BlockId::default()));
+        let slab = BlockId::from_name("minecraft:oak_slab").unwrap();
+        assert_eq!(
+            slab.state::<blocks::OakSlab>()
+                .map(|slab| slab.r#type.as_str()),
+            slab.get_property("type")
+        );
+    }
+}
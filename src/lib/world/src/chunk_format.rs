@@ -1,8 +1,9 @@
 use crate::biome_id::get_biome_id;
+use crate::block_entities::block_entity_type_id;
 use crate::block_id::{BlockId, BLOCK2ID};
+use crate::errors::WorldError;
 use crate::vanilla_chunk_format;
 use crate::vanilla_chunk_format::VanillaChunk;
-use crate::{errors::WorldError, vanilla_chunk_format::VanillaHeightmaps};
 use bitcode_derive::{Decode, Encode};
 use deepsize::DeepSizeOf;
 use ferrumc_general_purpose::data_packing::i32::read_nbit_i32;
@@ -11,6 +12,7 @@ use ferrumc_nbt::{FromNbt, NBTSerializable, NBTSerializeOptions, NbtTape};
 use ferrumc_net_codec::net_types::var_int::VarInt;
 use std::cmp::max;
 use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::error;
 use vanilla_chunk_format::BlockData;
 // #[cfg(test)]
@@ -32,6 +34,15 @@ pub struct Chunk {
     pub sections: Vec<Section>,
     pub heightmaps: Heightmaps,
     pub block_entities: Vec<BlockEntity>,
+    /// When a player last changed the chunk, in seconds since the epoch. 0 means it's untouched
+    /// since it was generated, which is what `trim` uses to find chunks that are safe to drop.
+    pub last_modified: u64,
+    /// Block and redstone ticks that were still waiting when the chunk was unloaded. They're
+    /// handed back to the scheduler when it's loaded again, so this is empty for loaded chunks.
+    pub pending_ticks: Vec<PendingTick>,
+    /// Set by [`Chunk::set_block`] when the light no longer matches the blocks, and cleared once
+    /// the chunk is relit. Dirty chunks are relit when they're saved.
+    pub light_dirty: bool,
 }
 
 #[derive(Encode, Decode, NBTDeserialize, NBTSerialize, Clone, DeepSizeOf, Debug)]
@@ -42,6 +53,10 @@ pub struct Heightmaps {
     pub motion_blocking: Vec<i64>,
     #[nbt(rename = "WORLD_SURFACE")]
     pub world_surface: Vec<i64>,
+    #[nbt(rename = "OCEAN_FLOOR")]
+    pub ocean_floor: Vec<i64>,
+    #[nbt(rename = "MOTION_BLOCKING_NO_LEAVES")]
+    pub motion_blocking_no_leaves: Vec<i64>,
 }
 #[derive(Encode, Decode, Clone, DeepSizeOf, Eq, PartialEq, Debug)]
 pub struct Section {
@@ -107,6 +122,47 @@ impl BlockEntity {
     }
 }
 
+/// A scheduled tick saved with its chunk.
+#[derive(Encode, Decode, Clone, DeepSizeOf, Eq, PartialEq, Debug)]
+pub struct PendingTick {
+    pub xz: u8,
+    pub y: i16,
+    pub block: BlockId,
+    /// Game ticks left until it was due when it was saved.
+    pub delay: u32,
+    pub priority: i8,
+    /// Whether it belongs to a redstone component rather than the block tick scheduler.
+    pub redstone: bool,
+}
+
+impl PendingTick {
+    pub fn new(
+        (x, y, z): (i32, i32, i32),
+        block: BlockId,
+        delay: u32,
+        priority: i8,
+        redstone: bool,
+    ) -> Self {
+        PendingTick {
+            xz: (((x & 0x0F) << 4) | (z & 0x0F)) as u8,
+            y: y as i16,
+            block,
+            delay,
+            priority,
+            redstone,
+        }
+    }
+
+    /// The tick's position in the world, given the chunk it was saved in.
+    pub fn pos(&self, chunk_x: i32, chunk_z: i32) -> (i32, i32, i32) {
+        (
+            (chunk_x << 4) | (self.xz >> 4) as i32,
+            self.y as i32,
+            (chunk_z << 4) | (self.xz & 0x0F) as i32,
+        )
+    }
+}
+
 fn convert_to_net_palette(vanilla_palettes: Vec<BlockData>) -> Result<Vec<VarInt>, WorldError> {
     let mut new_palette = Vec::new();
     for palette in vanilla_palettes {
@@ -133,11 +189,21 @@ fn convert_biome_palette(vanilla_palettes: Vec<String>) -> Result<Vec<VarInt>, W
     Ok(new_palette)
 }
 
+/// The current time in seconds since the epoch, as stored in [`Chunk::last_modified`].
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 impl Heightmaps {
     pub fn new() -> Self {
         Heightmaps {
             motion_blocking: vec![],
             world_surface: vec![],
+            ocean_floor: vec![],
+            motion_blocking_no_leaves: vec![],
         }
     }
 }
@@ -148,16 +214,22 @@ impl Default for Heightmaps {
     }
 }
 
-impl From<VanillaHeightmaps> for Heightmaps {
-    fn from(value: VanillaHeightmaps) -> Self {
-        Self {
-            motion_blocking: value.motion_blocking.unwrap_or_default(),
-            world_surface: value.world_surface.unwrap_or_default(),
-        }
+impl VanillaChunk {
+    /// Whether the chunk was saved with valid light data. Chunks saved before the game finished
+    /// lighting them (or by tools that strip light) need to be relit.
+    pub(crate) fn has_light(&self) -> bool {
+        self.is_light_on == Some(1)
+            && self.sections.as_ref().is_some_and(|sections| {
+                sections.iter().all(|section| {
+                    section
+                        .block_light
+                        .as_ref()
+                        .is_some_and(|l| l.len() == 2048)
+                        && section.sky_light.as_ref().is_some_and(|l| l.len() == 2048)
+                })
+            })
     }
-}
 
-impl VanillaChunk {
     pub fn to_custom_format(&self) -> Result<Chunk, WorldError> {
         let mut sections = Vec::new();
         for section in self.sections.as_ref().unwrap() {
@@ -196,8 +268,13 @@ impl VanillaChunk {
                 }
             }
             let block_data = if raw_block_data.is_empty() {
-                block_counts.insert(BlockId::default(), 4096);
-                PaletteType::Single(VarInt::from(0))
+                // No data means the whole section is the first (and only) palette entry
+                let block = palette
+                    .first()
+                    .map(|block| block.to_block_id())
+                    .unwrap_or_default();
+                block_counts.insert(block, 4096);
+                PaletteType::Single(block.to_varint())
             } else {
                 PaletteType::Indirect {
                     bits_per_block,
@@ -286,21 +363,53 @@ impl VanillaChunk {
 
         let dimension = self.dimension.clone().unwrap_or("overworld".to_string());
 
-        let heightmaps: Heightmaps = self.heightmaps.clone().map(Into::into).unwrap_or_default();
+        // Block entities of unknown types are left out by `upgrade`, this only catches chunks
+        // that weren't upgraded first
+        let block_entities = self
+            .block_entities
+            .iter()
+            .flatten()
+            .filter_map(|be| {
+                Some(BlockEntity::new(
+                    (be.x & 15) as u8,
+                    be.y as u16,
+                    (be.z & 15) as u8,
+                    block_entity_type_id(&be.id)?,
+                    vanilla_chunk_format::root_compound(&be.data),
+                ))
+            })
+            .collect();
 
-        Ok(Chunk {
+        let mut chunk = Chunk {
             x: self.x_pos,
             z: self.z_pos,
             dimension,
             sections,
-            heightmaps,
-            block_entities: Vec::new(),
-        })
+            heightmaps: Heightmaps::new(),
+            block_entities,
+            // Vanilla counts how long players have spent near a chunk, anything above 0 means
+            // someone has been there and may have changed it
+            last_modified: if self.inhabited_time.unwrap_or(0) > 0 {
+                now()
+            } else {
+                0
+            },
+            pending_ticks: Vec::new(),
+            light_dirty: false,
+        };
+        // The vanilla heightmaps are ignored, so they always agree with what edits keep up to date
+        chunk.recalculate_heightmaps();
+        // Whether the dimension has a sky is up to the world it ends up in, so it's lit there
+        chunk.light_dirty = !self.has_light();
+        Ok(chunk)
     }
 }
 
 impl Chunk {
     pub fn new(x: i32, z: i32, dimension: String) -> Self {
+        // An empty chunk is fully lit by the sky. Chunks in dimensions without one have to be
+        // relit before they're sent.
+        let sky_light = 255;
         let mut sections: Vec<Section> = (-4..20)
             .map(|y| Section {
                 y: y as i8,
@@ -314,21 +423,33 @@ impl Chunk {
                     data: vec![],
                     palette: vec![VarInt::from(0)],
                 },
-                block_light: vec![255; 2048],
-                sky_light: vec![255; 2048],
+                block_light: vec![0; 2048],
+                sky_light: vec![sky_light; 2048],
             })
             .collect();
         for section in &mut sections {
             section.optimise().expect("Failed to optimise section");
         }
-        Chunk {
+        let mut chunk = Chunk {
             x,
             z,
             dimension,
             sections,
             heightmaps: Heightmaps::new(),
             block_entities: Vec::new(),
-        }
+            last_modified: 0,
+            pending_ticks: Vec::new(),
+            light_dirty: false,
+        };
+        chunk.recalculate_heightmaps();
+        chunk
+    }
+
+    /// Record that a player changed the chunk, so it isn't trimmed as an untouched one.
+    ///
+    /// Only call this for changes made by players, not for generation or relighting.
+    pub fn mark_modified(&mut self) {
+        self.last_modified = now();
     }
 
     pub fn set_block_entity(&mut self, x: u8, y: u16, z: u8, entity_type: VarInt, nbt: Vec<u8>) {
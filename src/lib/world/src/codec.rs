@@ -0,0 +1,234 @@
+//! The on-disk format of chunks in the `chunks` table.
+//!
+//! Every record starts with a small header saying which layout the chunk was saved with and how
+//! the rest of it was compressed, so neither the compression in the config nor the [`Chunk`]
+//! struct can change in a way that breaks chunks that were already saved:
+//!
+//! | Bytes | Contents                                                     |
+//! |-------|--------------------------------------------------------------|
+//! | 1     | [`RECORD_MAGIC`]                                             |
+//! | 2     | Chunk format version, big endian, see [`crate::migrations`]  |
+//! | 1     | Compression algorithm, see [`CompressorType::id`]            |
+//! | 1     | Compression level                                            |
+//! | 4     | Adler-32 checksum of the uncompressed data, big endian       |
+//! | ..    | The compressed, bitcode encoded [`Chunk`]                    |
+//!
+//! Records from before there was a header at all are still read, as format version 0. They're
+//! plain zlib streams, which always start with a byte ending in `0x8`, so they can't be mistaken
+//! for [`RECORD_MAGIC`].
+
+use crate::chunk_format::{Chunk, Heightmaps};
+use crate::errors::WorldError;
+use crate::migrations::{migrate, CHUNK_FORMAT_VERSION};
+use crate::warn;
+use ferrumc_config::server_config::DatabaseConfig;
+use ferrumc_storage::compressors::{Compressor, CompressorType};
+use yazi::Adler32;
+
+/// First byte of every record with a versioned header.
+const RECORD_MAGIC: u8 = 0xFD;
+const HEADER_SIZE: usize = 9;
+
+/// Build the compressor set in the config, making sure the level is in range for it.
+pub(crate) fn compressor_from_config(config: &DatabaseConfig) -> Result<Compressor, WorldError> {
+    let algorithm = config
+        .compression
+        .parse::<CompressorType>()
+        .map_err(|_| WorldError::InvalidCompressor(config.compression.clone()))?;
+    validate_compressor(Compressor::create(algorithm, config.compression_level))
+}
+
+/// Make sure the compression level fits both the algorithm and the record header.
+pub(crate) fn validate_compressor(compressor: Compressor) -> Result<Compressor, WorldError> {
+    if compressor.level > compressor.algorithm.max_level() {
+        return Err(WorldError::InvalidCompressor(format!(
+            "{} only supports levels up to {}, got {}",
+            compressor.algorithm,
+            compressor.algorithm.max_level(),
+            compressor.level
+        )));
+    }
+    Ok(compressor)
+}
+
+/// Get the compressor a record was written with, or `None` if it's from before records had a
+/// header.
+pub(crate) fn record_compressor(record: &[u8]) -> Option<Compressor> {
+    if record.len() < HEADER_SIZE || record[0] != RECORD_MAGIC {
+        return None;
+    }
+    let algorithm = CompressorType::from_id(record[3])?;
+    Some(Compressor::create(algorithm, record[4] as u32))
+}
+
+/// Get the chunk format version a record was written with, or `None` if it's from before records
+/// carried a version.
+pub(crate) fn record_version(record: &[u8]) -> Option<u16> {
+    if record.len() < HEADER_SIZE || record[0] != RECORD_MAGIC {
+        return None;
+    }
+    Some(u16::from_be_bytes([record[1], record[2]]))
+}
+
+/// Encode and compress a chunk into a record for the `chunks` table.
+pub(crate) fn encode_chunk(compressor: &Compressor, chunk: &Chunk) -> Result<Vec<u8>, WorldError> {
+    let encoded = bitcode::encode(chunk);
+    let checksum = Adler32::from_buf(&encoded).finish();
+    let compressed = compressor
+        .compress(&encoded)
+        .map_err(|e| WorldError::CompressionError(e.to_string()))?;
+
+    let mut record = Vec::with_capacity(HEADER_SIZE + compressed.len());
+    record.push(RECORD_MAGIC);
+    record.extend_from_slice(&CHUNK_FORMAT_VERSION.to_be_bytes());
+    record.push(compressor.algorithm.id());
+    record.push(compressor.level as u8);
+    record.extend_from_slice(&checksum.to_be_bytes());
+    record.extend_from_slice(&compressed);
+    Ok(record)
+}
+
+/// Decompress and decode a record from the `chunks` table, whatever it was compressed with.
+///
+/// Chunks saved with an older format version are migrated to the current one. With `verify`
+/// set, the checksum in the header is checked against the data.
+pub(crate) fn decode_chunk(record: &[u8], verify: bool) -> Result<Chunk, WorldError> {
+    let (version, data, checksum) = match record.first() {
+        Some(&RECORD_MAGIC) => {
+            let header = split_header(record, HEADER_SIZE)?;
+            let version = u16::from_be_bytes([header[1], header[2]]);
+            let (data, checksum) = decompress_body(&header[3..], &record[HEADER_SIZE..])?;
+            (version, data, Some(checksum))
+        }
+        _ => {
+            let (data, checksum) = yazi::decompress(record, yazi::Format::Zlib)?;
+            (0, data, checksum)
+        }
+    };
+
+    if verify {
+        if let Some(expected_checksum) = checksum {
+            let real_checksum = Adler32::from_buf(data.as_slice()).finish();
+            if real_checksum != expected_checksum {
+                return Err(WorldError::CorruptedChunkData(
+                    real_checksum,
+                    expected_checksum,
+                ));
+            }
+        } else {
+            warn!("Chunk data does not have a checksum, skipping verification.");
+        }
+    }
+
+    let data = migrate(data, version)?;
+    let mut chunk: Chunk =
+        bitcode::decode(&data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
+    // Migrations can't calculate heightmaps, so ones that need new heightmaps leave them empty
+    if chunk.heightmaps == Heightmaps::new() {
+        chunk.recalculate_heightmaps();
+    }
+    Ok(chunk)
+}
+
+fn split_header(record: &[u8], size: usize) -> Result<&[u8], WorldError> {
+    if record.len() < size {
+        return Err(WorldError::DecompressionError(
+            "Chunk record is too short to have a header".to_string(),
+        ));
+    }
+    Ok(&record[..size])
+}
+
+/// Decompress the body of a record, given the compressor and checksum part of its header.
+fn decompress_body(header: &[u8], body: &[u8]) -> Result<(Vec<u8>, u32), WorldError> {
+    let algorithm = CompressorType::from_id(header[0]).ok_or_else(|| {
+        WorldError::DecompressionError(format!("Unknown compression id {}", header[0]))
+    })?;
+    let checksum = u32::from_be_bytes([header[2], header[3], header[4], header[5]]);
+    let data = Compressor::create(algorithm, header[1] as u32)
+        .decompress(body)
+        .map_err(|e| WorldError::DecompressionError(e.to_string()))?;
+    Ok((data, checksum))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::v0;
+    use yazi::CompressionLevel;
+
+    #[test]
+    fn test_round_trip_all_compressors() {
+        let chunk = Chunk::new(1, 2, "overworld".to_string());
+        for algorithm in [
+            CompressorType::Gzip,
+            CompressorType::Zstd,
+            CompressorType::Brotli,
+            CompressorType::Deflate,
+            CompressorType::Zlib,
+        ] {
+            let compressor = Compressor::create(algorithm, 3);
+            let record = encode_chunk(&compressor, &chunk).unwrap();
+            assert_eq!(record_compressor(&record), Some(compressor));
+            assert_eq!(record_version(&record), Some(CHUNK_FORMAT_VERSION));
+            assert_eq!(decode_chunk(&record, true).unwrap(), chunk);
+        }
+    }
+
+    /// Encode a chunk the way it was laid out before records carried a version.
+    fn encode_v0(chunk: &Chunk) -> Vec<u8> {
+        bitcode::encode(&v0::Chunk {
+            x: chunk.x,
+            z: chunk.z,
+            dimension: chunk.dimension.clone(),
+            sections: bitcode::decode(&bitcode::encode(&chunk.sections)).unwrap(),
+            // Recalculated when the chunk is loaded
+            heightmaps: v0::Heightmaps {
+                motion_blocking: vec![],
+                world_surface: vec![],
+            },
+            block_entities: bitcode::decode(&bitcode::encode(&chunk.block_entities)).unwrap(),
+        })
+    }
+
+    /// Compare a decoded old chunk against the original, ignoring the stamp the migration added.
+    fn assert_migrated(decoded: Chunk, mut chunk: Chunk) {
+        assert_ne!(decoded.last_modified, 0);
+        chunk.last_modified = decoded.last_modified;
+        assert_eq!(decoded, chunk);
+    }
+
+    #[test]
+    fn test_reads_legacy_records() {
+        let chunk = Chunk::new(1, 2, "overworld".to_string());
+        let record = yazi::compress(
+            &encode_v0(&chunk),
+            yazi::Format::Zlib,
+            CompressionLevel::BestSpeed,
+        )
+        .unwrap();
+        assert!(record_compressor(&record).is_none());
+        assert!(record_version(&record).is_none());
+        assert_migrated(decode_chunk(&record, true).unwrap(), chunk);
+    }
+
+    #[test]
+    fn test_rejects_newer_versions() {
+        let chunk = Chunk::new(1, 2, "overworld".to_string());
+        let compressor = Compressor::create(CompressorType::Zlib, 1);
+        let mut record = encode_chunk(&compressor, &chunk).unwrap();
+        record[1..3].copy_from_slice(&(CHUNK_FORMAT_VERSION + 1).to_be_bytes());
+        assert!(matches!(
+            decode_chunk(&record, true),
+            Err(WorldError::UnsupportedChunkVersion(..))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_bad_levels() {
+        let compressor = Compressor::create(CompressorType::Brotli, 12);
+        assert!(validate_compressor(compressor).is_err());
+        let compressor = Compressor::create(CompressorType::Zstd, 22);
+        assert!(validate_compressor(compressor).is_ok());
+    }
+}
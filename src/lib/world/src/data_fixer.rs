@@ -0,0 +1,667 @@
+//! Upgrading chunks from older versions of the game before they're imported.
+//!
+//! Block states and biomes are converted through mappings for 1.20.1 (see
+//! [`DATA_VERSION`](crate::exporting::DATA_VERSION)), so anything saved by an older version has to
+//! be brought up to date first, otherwise renamed blocks and blocks that gained a property since
+//! would be lost. Chunks saved before 1.18 also use a different layout, which is converted here
+//! too. Chunks from before 1.16 pack their block states differently and aren't supported.
+//!
+//! Block entities keep their NBT. Signs and spawners saved before their layout changed are
+//! rewritten to the layout the typed block entities in [`block_entities`](crate::block_entities)
+//! read, and the rest is carried over as it is. Ones of a type that doesn't exist anymore are left
+//! out and counted in the report.
+
+use crate::biome_id::BIOME_NAME_TO_ID;
+use crate::block_entities::sign::{SignBlockEntity, SignText};
+use crate::block_entities::{block_entity_type_id, encode, fits_layout};
+use crate::block_id::{BLOCK2ID, ID2BLOCK};
+use crate::errors::WorldError;
+use crate::exporting::{compound_body, pack};
+use crate::vanilla_chunk_format::{
+    compound_without, root_compound, Biomes, BlockData, BlockStates, Section, VanillaBlockEntity,
+    VanillaChunk,
+};
+use ferrumc_macros::NBTDeserialize;
+use ferrumc_nbt::{FromNbt, NBTSerializable, NBTSerializeOptions, NbtTape};
+use lazy_static::lazy_static;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use tracing::debug;
+
+/// 1.16, the oldest version that can be imported.
+const V1_16: i32 = 2566;
+/// 1.17, which renamed grass paths and split water out of cauldrons.
+const V1_17: i32 = 2724;
+/// 1.18, which merged and renamed a lot of biomes and wrapped what spawners spawn in an `entity`
+/// compound.
+const V1_18: i32 = 2860;
+/// 1.20, which gave signs a back side.
+const V1_20: i32 = 3463;
+
+/// Biomes that were renamed or merged into another one in 1.18.
+const BIOME_RENAMES_1_18: &[(&str, &str)] = &[
+    ("badlands_plateau", "badlands"),
+    ("bamboo_jungle_hills", "bamboo_jungle"),
+    ("birch_forest_hills", "birch_forest"),
+    ("dark_forest_hills", "dark_forest"),
+    ("desert_hills", "desert"),
+    ("desert_lakes", "desert"),
+    ("giant_spruce_taiga", "old_growth_spruce_taiga"),
+    ("giant_spruce_taiga_hills", "old_growth_spruce_taiga"),
+    ("giant_tree_taiga", "old_growth_pine_taiga"),
+    ("giant_tree_taiga_hills", "old_growth_pine_taiga"),
+    ("gravelly_mountains", "windswept_gravelly_hills"),
+    ("jungle_edge", "sparse_jungle"),
+    ("jungle_hills", "jungle"),
+    ("modified_badlands_plateau", "badlands"),
+    ("modified_gravelly_mountains", "windswept_gravelly_hills"),
+    ("modified_jungle", "jungle"),
+    ("modified_jungle_edge", "sparse_jungle"),
+    ("modified_wooded_badlands_plateau", "wooded_badlands"),
+    ("mountain_edge", "windswept_hills"),
+    ("mountains", "windswept_hills"),
+    ("mushroom_field_shore", "mushroom_fields"),
+    ("shattered_savanna", "windswept_savanna"),
+    ("shattered_savanna_plateau", "windswept_savanna"),
+    ("snowy_mountains", "snowy_plains"),
+    ("snowy_taiga_hills", "snowy_taiga"),
+    ("snowy_taiga_mountains", "snowy_taiga"),
+    ("snowy_tundra", "snowy_plains"),
+    ("stone_shore", "stony_shore"),
+    ("swamp_hills", "swamp"),
+    ("taiga_hills", "taiga"),
+    ("taiga_mountains", "taiga"),
+    ("tall_birch_forest", "old_growth_birch_forest"),
+    ("tall_birch_hills", "old_growth_birch_forest"),
+    ("wooded_badlands_plateau", "wooded_badlands"),
+    ("wooded_hills", "forest"),
+    ("wooded_mountains", "windswept_forest"),
+    ("deep_warm_ocean", "warm_ocean"),
+];
+
+/// The numeric biome ids chunks were saved with before 1.18, with their names at the time.
+const LEGACY_BIOME_IDS: &[(i32, &str)] = &[
+    (0, "ocean"),
+    (1, "plains"),
+    (2, "desert"),
+    (3, "mountains"),
+    (4, "forest"),
+    (5, "taiga"),
+    (6, "swamp"),
+    (7, "river"),
+    (8, "nether_wastes"),
+    (9, "the_end"),
+    (10, "frozen_ocean"),
+    (11, "frozen_river"),
+    (12, "snowy_tundra"),
+    (13, "snowy_mountains"),
+    (14, "mushroom_fields"),
+    (15, "mushroom_field_shore"),
+    (16, "beach"),
+    (17, "desert_hills"),
+    (18, "wooded_hills"),
+    (19, "taiga_hills"),
+    (20, "mountain_edge"),
+    (21, "jungle"),
+    (22, "jungle_hills"),
+    (23, "jungle_edge"),
+    (24, "deep_ocean"),
+    (25, "stone_shore"),
+    (26, "snowy_beach"),
+    (27, "birch_forest"),
+    (28, "birch_forest_hills"),
+    (29, "dark_forest"),
+    (30, "snowy_taiga"),
+    (31, "snowy_taiga_hills"),
+    (32, "giant_tree_taiga"),
+    (33, "giant_tree_taiga_hills"),
+    (34, "wooded_mountains"),
+    (35, "savanna"),
+    (36, "savanna_plateau"),
+    (37, "badlands"),
+    (38, "wooded_badlands_plateau"),
+    (39, "badlands_plateau"),
+    (40, "small_end_islands"),
+    (41, "end_midlands"),
+    (42, "end_highlands"),
+    (43, "end_barrens"),
+    (44, "warm_ocean"),
+    (45, "lukewarm_ocean"),
+    (46, "cold_ocean"),
+    (47, "deep_warm_ocean"),
+    (48, "deep_lukewarm_ocean"),
+    (49, "deep_cold_ocean"),
+    (50, "deep_frozen_ocean"),
+    (127, "the_void"),
+    (129, "sunflower_plains"),
+    (130, "desert_lakes"),
+    (131, "gravelly_mountains"),
+    (132, "flower_forest"),
+    (133, "taiga_mountains"),
+    (134, "swamp_hills"),
+    (140, "ice_spikes"),
+    (149, "modified_jungle"),
+    (151, "modified_jungle_edge"),
+    (155, "tall_birch_forest"),
+    (156, "tall_birch_hills"),
+    (157, "dark_forest_hills"),
+    (158, "snowy_taiga_mountains"),
+    (160, "giant_spruce_taiga"),
+    (161, "giant_spruce_taiga_hills"),
+    (162, "modified_gravelly_mountains"),
+    (163, "shattered_savanna"),
+    (164, "shattered_savanna_plateau"),
+    (165, "eroded_badlands"),
+    (166, "modified_wooded_badlands_plateau"),
+    (167, "modified_badlands_plateau"),
+    (168, "bamboo_jungle"),
+    (169, "bamboo_jungle_hills"),
+    (170, "soul_sand_valley"),
+    (171, "crimson_forest"),
+    (172, "warped_forest"),
+    (173, "basalt_deltas"),
+    (174, "dripstone_caves"),
+    (175, "lush_caves"),
+];
+
+lazy_static! {
+    /// Every state of every block, by block name.
+    static ref STATES_BY_NAME: HashMap<&'static str, Vec<&'static BlockData>> = {
+        let mut states: HashMap<&str, Vec<&BlockData>> = HashMap::new();
+        for block in ID2BLOCK.iter() {
+            states.entry(block.name.as_str()).or_default().push(block);
+        }
+        states
+    };
+}
+
+/// Palette entries that couldn't be mapped to anything, even after upgrading them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct UpgradeReport {
+    /// Block states that were replaced with air.
+    pub unmapped_blocks: u64,
+    /// Biomes that were replaced with the first biome.
+    pub unmapped_biomes: u64,
+    /// Block entities that were left out, since their type doesn't exist anymore.
+    pub skipped_block_entities: u64,
+}
+
+/// A chunk in the layout used before 1.18, where everything is inside a `Level` compound.
+#[derive(NBTDeserialize, Debug, Clone)]
+struct LegacyChunk {
+    #[nbt(rename = "DataVersion")]
+    data_version: Option<i32>,
+    #[nbt(rename = "Level")]
+    level: LegacyLevel,
+}
+
+#[derive(NBTDeserialize, Debug, Clone)]
+struct LegacyLevel {
+    #[nbt(rename = "xPos")]
+    x_pos: i32,
+    #[nbt(rename = "zPos")]
+    z_pos: i32,
+    #[nbt(rename = "Status")]
+    status: Option<String>,
+    #[nbt(rename = "InhabitedTime")]
+    inhabited_time: Option<i64>,
+    #[nbt(rename = "LastUpdate")]
+    last_update: Option<i64>,
+    #[nbt(rename = "Sections")]
+    sections: Option<Vec<LegacySection>>,
+    /// The numeric biome ids of every 4x4x4 cell, from the bottom up.
+    #[nbt(rename = "Biomes")]
+    biomes: Option<Vec<i32>>,
+    #[nbt(rename = "TileEntities")]
+    tile_entities: Option<Vec<VanillaBlockEntity>>,
+}
+
+#[derive(NBTDeserialize, Debug, Clone)]
+struct LegacySection {
+    #[nbt(rename = "Y")]
+    y: Option<i8>,
+    #[nbt(rename = "Palette")]
+    palette: Option<Vec<BlockData>>,
+    #[nbt(rename = "BlockStates")]
+    block_states: Option<Vec<i64>>,
+    #[nbt(rename = "BlockLight")]
+    block_light: Option<Vec<i8>>,
+    #[nbt(rename = "SkyLight")]
+    sky_light: Option<Vec<i8>>,
+}
+
+/// Read a chunk from a region file, in either the current layout or the one from before 1.18.
+pub(crate) fn read_chunk(data: &[u8]) -> Result<VanillaChunk, WorldError> {
+    let error = match VanillaChunk::from_bytes(data) {
+        Ok(chunk) => return Ok(chunk),
+        Err(e) => e,
+    };
+    match LegacyChunk::from_bytes(data) {
+        Ok(legacy) => legacy.into_current_layout(),
+        // Not a legacy chunk either, so the first error is the one that matters
+        Err(_) => Err(WorldError::NbtDecodeError(error.to_string())),
+    }
+}
+
+impl LegacyChunk {
+    fn into_current_layout(self) -> Result<VanillaChunk, WorldError> {
+        let data_version = self.data_version.unwrap_or(0);
+        if data_version < V1_16 {
+            return Err(WorldError::UnsupportedDataVersion(data_version, V1_16));
+        }
+        let level = self.level;
+        let legacy_biomes = level.biomes.unwrap_or_default();
+
+        // The world used to go from 0 to 256, the sections above and below that are left empty
+        let mut legacy_sections: HashMap<i8, LegacySection> = level
+            .sections
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|section| section.palette.is_some())
+            .filter_map(|section| Some((section.y?, section)))
+            .collect();
+        let sections = (-4..20)
+            .map(|y| {
+                let (palette, data, block_light, sky_light) = match legacy_sections.remove(&y) {
+                    Some(section) => (
+                        section.palette,
+                        section.block_states,
+                        section.block_light,
+                        section.sky_light,
+                    ),
+                    None => (Some(vec![BlockData::default()]), None, None, None),
+                };
+                Section {
+                    block_states: Some(BlockStates { data, palette }),
+                    biomes: Some(legacy_section_biomes(&legacy_biomes, y)),
+                    y,
+                    block_light,
+                    sky_light,
+                }
+            })
+            .collect();
+
+        Ok(VanillaChunk {
+            dimension: None,
+            status: level.status.unwrap_or("full".to_string()),
+            data_version,
+            // These are recalculated when the chunk is converted anyway
+            heightmaps: None,
+            // The new sections don't have any light, so the chunk needs relighting
+            is_light_on: None,
+            inhabited_time: level.inhabited_time,
+            y_pos: -4,
+            x_pos: level.x_pos,
+            z_pos: level.z_pos,
+            structures: None,
+            last_update: level.last_update,
+            sections: Some(sections),
+            block_entities: level.tile_entities,
+        })
+    }
+}
+
+/// Build the biome palette of a section from the biomes of the whole chunk, as saved before 1.18.
+fn legacy_section_biomes(legacy_biomes: &[i32], section_y: i8) -> Biomes {
+    let mut palette: Vec<String> = Vec::new();
+    let indices: Vec<u64> = (0..64)
+        .map(|index| {
+            let (y, z, x) = (index >> 4, (index >> 2) & 3, index & 3);
+            // Sections that didn't exist before take the biomes of the nearest layer that did
+            let legacy_y = (section_y as i32 * 4 + y).clamp(0, 63);
+            let legacy_index = ((legacy_y << 4) | (z << 2) | x) as usize;
+            let name = legacy_biomes
+                .get(legacy_index)
+                .and_then(|id| {
+                    LEGACY_BIOME_IDS
+                        .iter()
+                        .find(|(legacy_id, _)| legacy_id == id)
+                })
+                .map(|(_, name)| format!("minecraft:{name}"))
+                .unwrap_or("minecraft:plains".to_string());
+            match palette.iter().position(|entry| *entry == name) {
+                Some(position) => position as u64,
+                None => {
+                    palette.push(name);
+                    palette.len() as u64 - 1
+                }
+            }
+        })
+        .collect();
+
+    let data = if palette.len() <= 1 {
+        None
+    } else {
+        let bits = (palette.len() as f32).log2().ceil() as u8;
+        Some(pack(bits, &indices))
+    };
+    Biomes { data, palette }
+}
+
+impl VanillaChunk {
+    /// Upgrade the block states and biomes of the chunk to the version the block mappings are for.
+    ///
+    /// Palette entries that still can't be mapped afterwards are replaced with air (or the first
+    /// biome), and counted in the report.
+    pub(crate) fn upgrade(&mut self) -> UpgradeReport {
+        let data_version = self.data_version;
+        let mut report = UpgradeReport::default();
+        for section in self.sections.iter_mut().flatten() {
+            let palette = section
+                .block_states
+                .as_mut()
+                .and_then(|states| states.palette.as_mut());
+            for block in palette.into_iter().flatten() {
+                upgrade_block(block, data_version);
+                match resolve_block(block) {
+                    Some(resolved) => *block = resolved,
+                    None => {
+                        report.unmapped_blocks += 1;
+                        *block = BlockData::default();
+                    }
+                }
+            }
+
+            if let Some(biomes) = &mut section.biomes {
+                for biome in &mut biomes.palette {
+                    upgrade_biome(biome, data_version);
+                    if !BIOME_NAME_TO_ID.contains_key(biome.as_str()) {
+                        report.unmapped_biomes += 1;
+                    }
+                }
+            }
+        }
+        if let Some(block_entities) = &mut self.block_entities {
+            // The ones without an id were broken to begin with, so they don't count
+            block_entities.retain(|block_entity| !block_entity.id.is_empty());
+            let before = block_entities.len();
+            block_entities.retain(|block_entity| block_entity_type_id(&block_entity.id).is_some());
+            report.skipped_block_entities = (before - block_entities.len()) as u64;
+            for block_entity in block_entities.iter_mut() {
+                upgrade_block_entity(block_entity, data_version);
+                if !fits_layout(&block_entity.id, &root_compound(&block_entity.data)) {
+                    debug!(
+                        "Block entity {} at {}, {}, {} doesn't have the data it should, keeping \
+                        it as it is",
+                        block_entity.id, block_entity.x, block_entity.y, block_entity.z
+                    );
+                }
+            }
+        }
+        report
+    }
+}
+
+/// Bring the NBT of a block entity to the layout it has now.
+pub(crate) fn upgrade_block_entity(block_entity: &mut VanillaBlockEntity, data_version: i32) {
+    let upgraded = match block_entity.id.as_str() {
+        "minecraft:sign" if data_version < V1_20 => upgrade_sign(&block_entity.data),
+        "minecraft:mob_spawner" if data_version < V1_18 => upgrade_spawner(&block_entity.data),
+        _ => return,
+    };
+    block_entity.data = upgraded;
+}
+
+/// Signs used to have one side, with each line in its own tag.
+fn upgrade_sign(data: &[u8]) -> Vec<u8> {
+    let nbt = root_compound(data);
+    let mut tape = NbtTape::new(&nbt);
+    tape.parse();
+    let Some((_, root)) = tape.root.as_ref() else {
+        return data.to_vec();
+    };
+    let get_string = |name: &str| root.get(name).and_then(|e| String::from_nbt(&tape, e).ok());
+    let front_text = SignText {
+        messages: (1..=4)
+            .map(|line| get_string(&format!("Text{line}")).unwrap_or_else(|| "\"\"".to_string()))
+            .collect(),
+        color: get_string("Color").unwrap_or_else(|| "black".to_string()),
+        has_glowing_text: root
+            .get("GlowingText")
+            .and_then(|e| bool::from_nbt(&tape, e).ok())
+            .unwrap_or(false),
+    };
+    let sign = SignBlockEntity {
+        front_text,
+        ..Default::default()
+    };
+    let mut upgraded = compound_body(&encode(&sign)).unwrap_or_default().to_vec();
+    upgraded.extend(compound_without(
+        &tape,
+        root,
+        &["Text1", "Text2", "Text3", "Text4", "Color", "GlowingText"],
+    ));
+    upgraded
+}
+
+/// Spawners used to keep the entity they spawn straight in `SpawnData`. The potential spawns
+/// changed layout too, so they're dropped and the spawner sticks to `SpawnData`.
+fn upgrade_spawner(data: &[u8]) -> Vec<u8> {
+    let nbt = root_compound(data);
+    let mut tape = NbtTape::new(&nbt);
+    tape.parse();
+    let Some((_, root)) = tape.root.as_ref() else {
+        return data.to_vec();
+    };
+    let mut upgraded = compound_without(&tape, root, &["SpawnData", "SpawnPotentials"]);
+    if let Some(entity) = root.get("SpawnData") {
+        upgraded.push(10);
+        "SpawnData".serialize(&mut upgraded, &NBTSerializeOptions::None);
+        let entity = tape.serialize_element(entity, &NBTSerializeOptions::WithHeader("entity"));
+        upgraded.extend(entity);
+        upgraded.push(0);
+    }
+    upgraded
+}
+
+/// Rename a block or change its properties to what they became in later versions.
+pub(crate) fn upgrade_block(block: &mut BlockData, data_version: i32) {
+    if data_version < V1_17 {
+        match block.name.as_str() {
+            "minecraft:grass_path" => block.name = "minecraft:dirt_path".to_string(),
+            "minecraft:cauldron" => {
+                let level = block
+                    .properties
+                    .as_ref()
+                    .and_then(|properties| properties.get("level"))
+                    .filter(|level| *level != "0");
+                if level.is_some() {
+                    block.name = "minecraft:water_cauldron".to_string();
+                } else {
+                    block.properties = None;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Find the block state a block maps to. If a property was added or removed since it was saved,
+/// this is the state with the most properties in common with it.
+pub(crate) fn resolve_block(block: &BlockData) -> Option<BlockData> {
+    if BLOCK2ID.contains_key(block) {
+        return Some(block.clone());
+    }
+    let states = STATES_BY_NAME.get(block.name.as_str())?;
+    let empty = Default::default();
+    let old_properties = block.properties.as_ref().unwrap_or(&empty);
+    states
+        .iter()
+        .min_by_key(|state| {
+            let properties = state.properties.as_ref().unwrap_or(&empty);
+            let matching = properties
+                .iter()
+                .filter(|(key, value)| old_properties.get(*key) == Some(*value))
+                .count();
+            // New properties are almost always off by default, like `waterlogged`
+            let new_and_off = properties
+                .iter()
+                .filter(|(key, value)| !old_properties.contains_key(*key) && *value == "false")
+                .count();
+            Reverse((matching, new_and_off))
+        })
+        .map(|state| (*state).clone())
+}
+
+fn upgrade_biome(biome: &mut String, data_version: i32) {
+    if data_version < V1_18 {
+        let name = biome.strip_prefix("minecraft:").unwrap_or(biome);
+        if let Some((_, new_name)) = BIOME_RENAMES_1_18.iter().find(|(old, _)| *old == name) {
+            *biome = format!("minecraft:{new_name}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn block(name: &str, properties: &[(&str, &str)]) -> BlockData {
+        BlockData {
+            name: name.to_string(),
+            properties: Some(
+                properties
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect::<BTreeMap<_, _>>(),
+            )
+            .filter(|properties| !properties.is_empty()),
+        }
+    }
+
+    #[test]
+    fn test_renamed_blocks() {
+        let mut path = block("minecraft:grass_path", &[]);
+        upgrade_block(&mut path, 2586);
+        assert_eq!(path.name, "minecraft:dirt_path");
+
+        let mut full = block("minecraft:cauldron", &[("level", "2")]);
+        upgrade_block(&mut full, 2586);
+        assert_eq!(full, block("minecraft:water_cauldron", &[("level", "2")]));
+        assert!(resolve_block(&full).is_some());
+
+        let mut empty = block("minecraft:cauldron", &[("level", "0")]);
+        upgrade_block(&mut empty, 2586);
+        assert_eq!(
+            resolve_block(&empty),
+            Some(block("minecraft:cauldron", &[]))
+        );
+
+        // Newer chunks are left alone
+        let mut path = block("minecraft:grass_path", &[]);
+        upgrade_block(&mut path, 3465);
+        assert_eq!(path.name, "minecraft:grass_path");
+    }
+
+    #[test]
+    fn test_missing_properties_are_filled_in() {
+        // Leaves only became waterloggable in 1.19
+        let leaves = block(
+            "minecraft:oak_leaves",
+            &[("distance", "3"), ("persistent", "true")],
+        );
+        let resolved = resolve_block(&leaves).unwrap();
+        let properties = resolved.properties.unwrap();
+        assert_eq!(properties["distance"], "3");
+        assert_eq!(properties["persistent"], "true");
+        assert_eq!(properties["waterlogged"], "false");
+
+        assert!(resolve_block(&block("minecraft:not_a_block", &[])).is_none());
+    }
+
+    #[test]
+    fn test_biomes() {
+        let mut biome = "minecraft:mountains".to_string();
+        upgrade_biome(&mut biome, 2730);
+        assert_eq!(biome, "minecraft:windswept_hills");
+
+        // Every legacy biome ends up as one that exists now
+        for (_, name) in LEGACY_BIOME_IDS {
+            let mut biome = format!("minecraft:{name}");
+            upgrade_biome(&mut biome, 2730);
+            assert!(BIOME_NAME_TO_ID.contains_key(&biome), "{biome}");
+        }
+    }
+
+    #[test]
+    fn test_legacy_section_biomes() {
+        // Desert everywhere except the top layer of cells in the first section
+        let mut legacy = vec![2; 1024];
+        legacy[3 * 16..4 * 16].fill(1);
+        let biomes = legacy_section_biomes(&legacy, 0);
+        assert_eq!(biomes.palette, vec!["minecraft:desert", "minecraft:plains"]);
+        assert_eq!(biomes.data.unwrap().len(), 1);
+
+        let below = legacy_section_biomes(&legacy, -2);
+        assert_eq!(below.palette, vec!["minecraft:desert"]);
+        assert!(below.data.is_none());
+    }
+
+    #[test]
+    fn test_block_entities_are_upgraded() {
+        let string_tag = |name: &str, value: &str| {
+            let mut tag = vec![8];
+            name.serialize(&mut tag, &NBTSerializeOptions::None);
+            value.serialize(&mut tag, &NBTSerializeOptions::None);
+            tag
+        };
+        let block_entity = |id: &str, data: Vec<u8>| VanillaBlockEntity {
+            id: id.to_string(),
+            x: 1,
+            y: 64,
+            z: 2,
+            data,
+        };
+        let mut sign = string_tag("Text1", "\"Hello\"");
+        sign.extend(string_tag("Color", "red"));
+        sign.extend(string_tag("CustomName", "\"Sign\""));
+        let mut spawner = vec![10];
+        "SpawnData".serialize(&mut spawner, &NBTSerializeOptions::None);
+        spawner.extend(string_tag("id", "minecraft:zombie"));
+        spawner.push(0);
+        let mut chunk = VanillaChunk {
+            dimension: None,
+            status: "full".to_string(),
+            data_version: 2586,
+            heightmaps: None,
+            is_light_on: None,
+            inhabited_time: None,
+            y_pos: -4,
+            x_pos: 0,
+            z_pos: 0,
+            structures: None,
+            last_update: None,
+            sections: None,
+            block_entities: Some(vec![
+                block_entity("minecraft:sign", sign),
+                block_entity("minecraft:mob_spawner", spawner),
+                block_entity("minecraft:removed_thing", Vec::new()),
+                block_entity("", Vec::new()),
+            ]),
+        };
+        let report = chunk.upgrade();
+        assert_eq!(report.skipped_block_entities, 1);
+        let block_entities = chunk.block_entities.unwrap();
+        assert_eq!(block_entities.len(), 2);
+
+        let sign_nbt = root_compound(&block_entities[0].data);
+        let mut tape = NbtTape::new(&sign_nbt);
+        tape.parse();
+        let root = &tape.root.as_ref().unwrap().1;
+        let sign = SignBlockEntity::from_nbt(&tape, root).unwrap();
+        assert_eq!(sign.front_text.messages[0], "\"Hello\"");
+        assert_eq!(sign.front_text.messages[1], "\"\"");
+        assert_eq!(sign.front_text.color, "red");
+        assert_eq!(sign.back_text, SignText::default());
+        let name = root.get("CustomName").unwrap();
+        assert_eq!(String::from_nbt(&tape, name).unwrap(), "\"Sign\"");
+
+        let spawner_nbt = root_compound(&block_entities[1].data);
+        let mut tape = NbtTape::new(&spawner_nbt);
+        tape.parse();
+        let entity = tape.get("SpawnData").and_then(|data| data.get("entity"));
+        let id = entity.and_then(|entity| entity.get("id")).unwrap();
+        assert_eq!(String::from_nbt(&tape, id).unwrap(), "minecraft:zombie");
+    }
+}
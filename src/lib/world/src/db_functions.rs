@@ -1,28 +1,130 @@
+use crate::block_entities::{encode, load_container};
 use crate::chunk_format::Chunk;
+use crate::codec::{
+    decode_chunk, encode_chunk, record_compressor, record_version, validate_compressor,
+};
 use crate::errors::WorldError;
-use crate::errors::WorldError::CorruptedChunkData;
+use crate::light::relight_dirty_chunks;
+use crate::migrations::CHUNK_FORMAT_VERSION;
 // db_functions.rs
-use crate::warn;
-use crate::{dimension_id, World};
-use ferrumc_config::server_config::get_global_config;
+use crate::dimensions::DimensionRegistry;
+use crate::World;
 use ferrumc_nbt::{FromNbt, NBTSerializable};
 use ferrumc_net_codec::net_types::var_int::VarInt;
-use std::sync::Arc;
-use tracing::trace;
-use yazi::CompressionLevel;
+use ferrumc_storage::backend::StorageBackend;
+use ferrumc_storage::compressors::Compressor;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::borrow::{Borrow, Cow};
+use std::sync::{Arc, MutexGuard};
+use tracing::{error, info, trace, warn};
 
 impl World {
     /// Save a chunk to the storage backend
     ///
     /// This function will save a chunk to the storage backend and update the cache with the new
     /// chunk data. If the chunk already exists in the cache, it will be updated with the new data.
+    ///
+    /// Unless `flush_interval` is set to 0 in the config, the chunk is only marked dirty here and
+    /// written out in the background shortly after, along with any other changed chunks.
+    ///
+    /// Chunks edited with [`Chunk::set_block`] since they were last lit are relit first, along
+    /// with the loaded chunks around them, which are saved too if the light crossing over into
+    /// them changed.
     pub fn save_chunk(&self, chunk: Arc<Chunk>) -> Result<(), WorldError> {
-        let ret = save_chunk_internal(self, &chunk);
+        // Catch chunks in dimensions that don't exist now rather than when they're written
+        self.dimensions.id(&chunk.dimension)?;
+        if chunk.light_dirty {
+            return self.save_relit_chunk(Arc::unwrap_or_clone(chunk));
+        }
+        let ret = if self.write_behind.is_some() {
+            self.dirty.mark(chunk.clone());
+            // The chunk is kept either way, but whoever saved it should know it isn't on disk
+            self.dirty.check_failed()
+        } else {
+            // Keeps World::save_new_chunks from writing over it between its check and its write
+            let _flushing = self.dirty.flush_lock.lock().unwrap();
+            save_chunk_internal(self, &chunk)
+        };
         self.cache
-            .insert((chunk.x, chunk.z, chunk.dimension.clone()), chunk);
+            .insert((chunk.x, chunk.z, chunk.dimension.clone()), chunk.clone());
+        self.track_random_sections(&chunk);
         ret
     }
 
+    /// Save a batch of chunks straight to the storage backend in a single transaction.
+    ///
+    /// Unlike [`World::save_chunk`] this doesn't go through the background writer or the cache,
+    /// so it's meant for writing lots of chunks nobody is looking at yet, e.g. when
+    /// pre-generating. Chunks that need their light updated are relit, with light crossing over
+    /// between the chunks in the batch.
+    pub fn save_chunk_batch(&self, chunks: &[Chunk]) -> Result<(), WorldError> {
+        let lit = self.lit_batch(&chunks.iter().collect::<Vec<_>>());
+        // Stop older unsaved versions of these chunks from being written over them afterwards
+        let _flushing = self.dirty.flush_lock.lock().unwrap();
+        save_chunk_internal_batch(
+            self.storage_backend.as_ref(),
+            &self.dimensions,
+            &self.compressor,
+            &lit,
+        )?;
+        for chunk in chunks {
+            self.dirty.remove(chunk.x, chunk.z, &chunk.dimension);
+            self.cache
+                .invalidate(&(chunk.x, chunk.z, chunk.dimension.clone()));
+        }
+        Ok(())
+    }
+
+    /// Write chunks straight to the storage backend in a single transaction and put them in the
+    /// cache, for edits that have to be saved all or nothing. Their light has to be up to date
+    /// already, and the caller holds the flush lock while it checks the chunks and writes them.
+    pub(crate) fn write_chunks_locked(
+        &self,
+        _flushing: &MutexGuard<'_, ()>,
+        chunks: Vec<Chunk>,
+    ) -> Result<(), WorldError> {
+        save_chunk_internal_batch(
+            self.storage_backend.as_ref(),
+            &self.dimensions,
+            &self.compressor,
+            &chunks,
+        )?;
+        for chunk in chunks {
+            let chunk = Arc::new(chunk);
+            self.dirty.remove(chunk.x, chunk.z, &chunk.dimension);
+            self.cache
+                .insert((chunk.x, chunk.z, chunk.dimension.clone()), chunk.clone());
+            self.track_random_sections(&chunk);
+        }
+        Ok(())
+    }
+
+    /// Like [`World::save_chunk_batch`], but only saves the chunks that don't exist yet, and
+    /// returns those. Meant for generating chunks in the background, where a player can load or
+    /// generate one of them between the generator checking for it and saving it.
+    ///
+    /// The check and the write happen under the same lock that saves and background flushes
+    /// take, so a chunk saved by anyone else is either skipped here or written over this one
+    /// afterwards, never the other way around.
+    pub fn save_new_chunks<'a>(&self, chunks: &'a [Chunk]) -> Result<Vec<&'a Chunk>, WorldError> {
+        let _flushing = self.dirty.flush_lock.lock().unwrap();
+        let mut new = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            if !self.chunk_exists(chunk.x, chunk.z, &chunk.dimension)? {
+                new.push(chunk);
+            }
+        }
+        let lit = self.lit_batch(&new);
+        save_chunk_internal_batch(
+            self.storage_backend.as_ref(),
+            &self.dimensions,
+            &self.compressor,
+            &lit,
+        )?;
+        Ok(new)
+    }
+
     /// Load a chunk from the storage backend. If the chunk is in the cache, it will be returned
     /// from the cache instead of the storage backend. If the chunk is not in the cache, it will be
     /// loaded from the storage backend and inserted into the cache.
@@ -30,19 +132,35 @@ impl World {
         if let Some(chunk) = self.cache.get(&(x, z, dimension.to_string())) {
             return Ok(chunk);
         }
-        let chunk = load_chunk_internal(self, x, z, dimension);
-        if let Ok(ref chunk) = chunk {
-            self.cache
-                .insert((x, z, dimension.to_string()), Arc::from(chunk.clone()));
+        let chunk = load_chunk_internal(self, x, z, dimension)?;
+        self.cache_loaded(chunk)
+    }
+
+    /// Put a chunk that was just loaded from storage in the cache. If it has ticks saved in it,
+    /// they're handed to the scheduler and the cached copy goes without them.
+    fn cache_loaded(&self, mut chunk: Chunk) -> Result<Arc<Chunk>, WorldError> {
+        if chunk.light_dirty {
+            // Stored before it was ever lit, e.g. imported without light, so light it before
+            // it's sent to anyone
+            chunk.relight(self.has_skylight(&chunk.dimension));
         }
-        chunk.map(Arc::new)
+        let ticks = std::mem::take(&mut chunk.pending_ticks);
+        if !ticks.is_empty() {
+            self.restore_chunk_ticks(&chunk, ticks);
+        }
+        let chunk = Arc::new(chunk);
+        self.cache
+            .insert((chunk.x, chunk.z, chunk.dimension.clone()), chunk.clone());
+        self.track_random_sections(&chunk);
+        Ok(chunk)
     }
 
     pub fn load_chunk_owned(&self, x: i32, z: i32, dimension: &str) -> Result<Chunk, WorldError> {
         self.load_chunk(x, z, dimension).map(|c| c.as_ref().clone())
     }
 
-    /// Save a block entity at world coordinates.
+    /// Save a block entity at world coordinates, and queue it for sending to players. Comparators
+    /// reading it get its new signal if it's a container.
     pub fn save_block_entity<T: NBTSerializable>(
         &self,
         x: i32,
@@ -53,8 +171,26 @@ impl World {
         data: &T,
     ) -> Result<(), WorldError> {
         let mut chunk = self.load_chunk_owned(x >> 4, z >> 4, dimension)?;
-        chunk.set_block_entity_data((x & 15) as u8, y as u16, (z & 15) as u8, entity_type, data);
-        self.save_chunk(Arc::new(chunk))
+        let nbt = encode(data);
+        chunk.set_block_entity(
+            (x & 15) as u8,
+            y as u16,
+            (z & 15) as u8,
+            entity_type,
+            nbt.clone(),
+        );
+        chunk.mark_modified();
+        let container = load_container(&chunk, (x & 15) as u8, y as u16, (z & 15) as u8);
+        self.save_chunk(Arc::new(chunk))?;
+        self.block_entity_changed(dimension, (x, y, z), entity_type, nbt);
+        if let Some(container) = container {
+            self.redstone.lock().unwrap().container_changed(
+                dimension,
+                (x, y, z),
+                container.signal(),
+            );
+        }
+        Ok(())
     }
 
     /// Load a block entity at world coordinates.
@@ -85,22 +221,30 @@ impl World {
 
     /// Delete a chunk from the storage backend.
     ///
-    /// This function will remove the chunk from the cache and delete it from the storage backend.
+    /// This function will remove the chunk from the cache and delete it from the storage backend,
+    /// along with any entities stored in it.
     pub fn delete_chunk(&self, x: i32, z: i32, dimension: &str) -> Result<(), WorldError> {
+        // Stop a flush that's already running from writing the chunk back
+        let _flushing = self.dirty.flush_lock.lock().unwrap();
         self.cache.remove(&(x, z, dimension.to_string()));
+        self.dirty.remove(x, z, dimension);
+        self.save_entities(x, z, dimension, &[])?;
         delete_chunk_internal(self, x, z, dimension)
     }
 
     /// Sync the storage backend.
     ///
-    /// This function will save all chunks in the cache to the storage backend and then sync the
-    /// storage backend. This should be run after inserting or updating a large number of chunks
-    /// to ensure that the data is properly saved to disk.
+    /// This function will save all dirty chunks to the storage backend right away instead of
+    /// waiting for the background writer, and then sync the storage backend. Chunks that haven't
+    /// changed since they were last saved are skipped. This should be run after inserting or
+    /// updating a large number of chunks to ensure that the data is properly saved to disk.
     pub fn sync(&self) -> Result<(), WorldError> {
-        for (k, v) in self.cache.iter() {
-            trace!("Syncing chunk: {:?}", (k.0, k.1));
-            save_chunk_internal(self, &v)?;
-        }
+        let saved = self.dirty.flush(
+            self.storage_backend.as_ref(),
+            &self.dimensions,
+            &self.compressor,
+        )?;
+        trace!("Synced {} dirty chunks", saved);
         sync_internal(self)
     }
 
@@ -124,14 +268,60 @@ impl World {
         }
         let fetched = load_chunk_batch_internal(self, &missing_chunks)?;
         for chunk in fetched {
-            let chunk = Arc::new(chunk);
-            self.cache
-                .insert((chunk.x, chunk.z, chunk.dimension.clone()), chunk.clone());
-            found_chunks.push(chunk);
+            found_chunks.push(self.cache_loaded(chunk)?);
         }
         Ok(found_chunks)
     }
 
+    /// Rewrite every chunk in the storage backend with the given compressor.
+    ///
+    /// Chunks already stored with the same algorithm and level are left alone. This is meant to be
+    /// run while the server is offline, and only changes what's already on disk; chunks saved
+    /// afterward still use the compressor from the config, so update that too.
+    pub fn recompress(&self, compressor: Compressor, batch_size: usize) -> Result<(), WorldError> {
+        let compressor = validate_compressor(compressor)?;
+        info!(
+            "Recompressing chunks with {} level {}...",
+            compressor.algorithm, compressor.level
+        );
+        let start = std::time::Instant::now();
+        let (rewritten, total) = rewrite_chunks_internal(self, compressor, batch_size, |record| {
+            record_compressor(record) == Some(compressor)
+                && record_version(record) == Some(CHUNK_FORMAT_VERSION)
+        })?;
+        info!(
+            "Recompressed {} chunks ({} already up to date) in {:?}",
+            rewritten,
+            total - rewritten,
+            start.elapsed()
+        );
+        Ok(())
+    }
+
+    /// Rewrite every chunk saved with an older chunk format version in the current one.
+    ///
+    /// Old chunks are migrated whenever they're loaded anyway, this just does them all at once so
+    /// the migrations don't have to run on the live server. The compressor from the config is used
+    /// for the rewritten chunks. Meant to be run while the server is offline.
+    pub fn migrate(&self, batch_size: usize) -> Result<(), WorldError> {
+        info!(
+            "Migrating chunks to chunk format version {}...",
+            CHUNK_FORMAT_VERSION
+        );
+        let start = std::time::Instant::now();
+        let (rewritten, total) =
+            rewrite_chunks_internal(self, self.compressor, batch_size, |record| {
+                record_version(record) == Some(CHUNK_FORMAT_VERSION)
+            })?;
+        info!(
+            "Migrated {} chunks ({} already up to date) in {:?}",
+            rewritten,
+            total - rewritten,
+            start.elapsed()
+        );
+        Ok(())
+    }
+
     /// Pre-cache a chunk in the cache
     ///
     /// This function will load a chunk from the storage backend and insert it into the cache
@@ -140,50 +330,63 @@ impl World {
     pub fn pre_cache(&self, x: i32, z: i32, dimension: &str) -> Result<(), WorldError> {
         if self.cache.get(&(x, z, dimension.to_string())).is_none() {
             let chunk = load_chunk_internal(self, x, z, dimension)?;
-            self.cache
-                .insert((x, z, dimension.to_string()), Arc::new(chunk));
+            self.cache_loaded(chunk)?;
         }
         Ok(())
     }
 }
 
+impl World {
+    /// The chunks with their light up to date, see [`Chunk::light_dirty`]. Light crosses over
+    /// between the chunks in the batch, but not into chunks outside of it.
+    fn lit_batch<'a>(&self, chunks: &[&'a Chunk]) -> Vec<Cow<'a, Chunk>> {
+        if !chunks.iter().any(|chunk| chunk.light_dirty) {
+            return chunks.iter().map(|chunk| Cow::Borrowed(*chunk)).collect();
+        }
+        let mut owned = chunks
+            .iter()
+            .map(|chunk| (*chunk).clone())
+            .collect::<Vec<_>>();
+        relight_dirty_chunks(&mut owned, |dimension| self.has_skylight(dimension));
+        owned.into_iter().map(Cow::Owned).collect()
+    }
+}
+
 pub(crate) fn save_chunk_internal(world: &World, chunk: &Chunk) -> Result<(), WorldError> {
     if !world.storage_backend.table_exists("chunks".to_string())? {
         world.storage_backend.create_table("chunks".to_string())?;
     }
-    let as_bytes = yazi::compress(
-        &bitcode::encode(chunk),
-        yazi::Format::Zlib,
-        CompressionLevel::BestSpeed,
-    )?;
-    let digest = create_key(chunk.dimension.as_str(), chunk.x, chunk.z);
+    let as_bytes = encode_chunk(&world.compressor, chunk)?;
+    let digest = create_key(world.dimensions.id(&chunk.dimension)?, chunk.x, chunk.z);
     world
         .storage_backend
         .upsert("chunks".to_string(), digest, as_bytes)?;
     Ok(())
 }
 
-pub(crate) fn save_chunk_internal_batch(world: &World, chunks: &[Chunk]) -> Result<(), WorldError> {
-    // Prepare the batch data for the upsert
-    let mut batch_data = Vec::new();
-
-    for chunk in chunks.iter() {
-        // Compress the chunk and encode it
-        let as_bytes = yazi::compress(
-            &bitcode::encode(chunk),
-            yazi::Format::Zlib,
-            CompressionLevel::BestSpeed,
-        )?;
-        // Create the key for the chunk
-        let digest = create_key(chunk.dimension.as_str(), chunk.x, chunk.z);
-        // Collect the key-value pair into the batch data
-        batch_data.push((digest, as_bytes));
-    }
+/// Save a batch of chunks in a single transaction.
+///
+/// This takes the backend and compressor rather than the world so the background writer can use
+/// it too.
+pub(crate) fn save_chunk_internal_batch<C: Borrow<Chunk> + Sync>(
+    storage_backend: &dyn StorageBackend,
+    dimensions: &DimensionRegistry,
+    compressor: &Compressor,
+    chunks: &[C],
+) -> Result<(), WorldError> {
+    // Compress and encode the chunks, collecting them into the batch data for the upsert
+    let batch_data = chunks
+        .par_iter()
+        .map(|chunk| {
+            let chunk = chunk.borrow();
+            let as_bytes = encode_chunk(compressor, chunk)?;
+            let digest = create_key(dimensions.id(&chunk.dimension)?, chunk.x, chunk.z);
+            Ok((digest, as_bytes))
+        })
+        .collect::<Result<Vec<_>, WorldError>>()?;
 
     // Perform the batch upsert
-    world
-        .storage_backend
-        .batch_upsert("chunks".to_string(), batch_data)?;
+    storage_backend.batch_upsert("chunks".to_string(), batch_data)?;
 
     Ok(())
 }
@@ -194,24 +397,13 @@ pub(crate) fn load_chunk_internal(
     z: i32,
     dimension: &str,
 ) -> Result<Chunk, WorldError> {
-    let digest = create_key(dimension, x, z);
+    // Chunks that haven't been written yet might not be in the cache any more
+    if let Some(chunk) = world.dirty.get(x, z, dimension) {
+        return Ok(chunk.as_ref().clone());
+    }
+    let digest = create_key(world.dimensions.id(dimension)?, x, z);
     match world.storage_backend.get("chunks".to_string(), digest)? {
-        Some(compressed) => {
-            let (data, checksum) = yazi::decompress(compressed.as_slice(), yazi::Format::Zlib)?;
-            if get_global_config().database.verify_chunk_data {
-                if let Some(expected_checksum) = checksum {
-                    let real_checksum = yazi::Adler32::from_buf(data.as_slice()).finish();
-                    if real_checksum != expected_checksum {
-                        return Err(CorruptedChunkData(real_checksum, expected_checksum));
-                    }
-                } else {
-                    warn!("Chunk data does not have a checksum, skipping verification.");
-                }
-            }
-            let chunk: Chunk = bitcode::decode(&data)
-                .map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
-            Ok(chunk)
-        }
+        Some(record) => decode_chunk(&record, world.verify_chunk_data),
         None => Err(WorldError::ChunkNotFound),
     }
 }
@@ -222,30 +414,21 @@ pub(crate) fn load_chunk_batch_internal(
 ) -> Result<Vec<Chunk>, WorldError> {
     let digests = coords
         .iter()
-        .map(|&(x, z, dim)| create_key(dim, x, z))
-        .collect();
+        .map(|&(x, z, dim)| Ok(create_key(world.dimensions.id(dim)?, x, z)))
+        .collect::<Result<Vec<_>, WorldError>>()?;
     world
         .storage_backend
         .batch_get("chunks".to_string(), digests)?
         .iter()
-        .map(|chunk| match chunk {
-            Some(compressed) => {
-                let (data, checksum) = yazi::decompress(compressed, yazi::Format::Zlib)?;
-                if get_global_config().database.verify_chunk_data {
-                    if let Some(expected_checksum) = checksum {
-                        let real_checksum = yazi::Adler32::from_buf(data.as_slice()).finish();
-                        if real_checksum != expected_checksum {
-                            return Err(CorruptedChunkData(real_checksum, expected_checksum));
-                        }
-                    } else {
-                        warn!("Chunk data does not have a checksum, skipping verification.");
-                    }
-                }
-                let chunk: Chunk = bitcode::decode(&data)
-                    .map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
-                Ok(chunk)
+        .zip(coords)
+        .map(|(chunk, &(x, z, dim))| {
+            if let Some(dirty) = world.dirty.get(x, z, dim) {
+                return Ok(dirty.as_ref().clone());
+            }
+            match chunk {
+                Some(record) => decode_chunk(record, world.verify_chunk_data),
+                None => Err(WorldError::ChunkNotFound),
             }
-            None => Err(WorldError::ChunkNotFound),
         })
         .collect()
 }
@@ -256,10 +439,13 @@ pub(crate) fn chunk_exists_internal(
     z: i32,
     dimension: &str,
 ) -> Result<bool, WorldError> {
+    if world.dirty.contains(&(x, z, dimension.to_string())) {
+        return Ok(true);
+    }
     if !world.storage_backend.table_exists("chunks".to_string())? {
         return Ok(false);
     }
-    let digest = create_key(dimension, x, z);
+    let digest = create_key(world.dimensions.id(dimension)?, x, z);
     Ok(world.storage_backend.exists("chunks".to_string(), digest)?)
 }
 
@@ -269,21 +455,148 @@ pub(crate) fn delete_chunk_internal(
     z: i32,
     dimension: &str,
 ) -> Result<(), WorldError> {
-    let digest = create_key(dimension, x, z);
+    let digest = create_key(world.dimensions.id(dimension)?, x, z);
     world.storage_backend.delete("chunks".to_string(), digest)?;
     Ok(())
 }
 
+/// Get the coordinates and dimension of every chunk in the storage backend.
+pub(crate) fn chunk_coords_internal(world: &World) -> Result<Vec<(i32, i32, String)>, WorldError> {
+    if !world.storage_backend.table_exists("chunks".to_string())? {
+        return Ok(Vec::new());
+    }
+    Ok(world
+        .storage_backend
+        .get_keys("chunks".to_string())?
+        .into_iter()
+        .filter_map(|key| {
+            let (dimension, x, z) = parse_key(key);
+            match world.dimensions.by_id(dimension) {
+                Some(dimension) => Some((x, z, dimension.name)),
+                None => {
+                    warn!(
+                        "Skipping chunk {}, {} in unknown dimension {}",
+                        x, z, dimension
+                    );
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
 pub(crate) fn sync_internal(world: &World) -> Result<(), WorldError> {
     world.storage_backend.flush()?;
     Ok(())
 }
 
-fn create_key(dimension: &str, x: i32, z: i32) -> u128 {
+pub(crate) fn create_key(dimension_id: i32, x: i32, z: i32) -> u128 {
     let mut key = 0u128;
-    let dim_id = dimension_id(dimension) as i64 as u128;
+    let dim_id = dimension_id as i64 as u128;
     key |= (dim_id & 0xFFFF_FFFF) << 96;
     key |= ((x as u128) & 0x0000_0000_FFFF_FFFF) << 48;
     key |= (z as u128) & 0x0000_0000_FFFF_FFFF;
     key
 }
+
+/// The reverse of [`create_key`], returns the dimension id and chunk coordinates.
+pub(crate) fn parse_key(key: u128) -> (i32, i32, i32) {
+    let dim_id = (key >> 96) as u32 as i32;
+    let x = (key >> 48) as u32 as i32;
+    let z = key as u32 as i32;
+    (dim_id, x, z)
+}
+
+/// Decode and re-encode every chunk with the given compressor, skipping records `is_current`
+/// returns true for.
+///
+/// Chunks that fail to decode are logged and left as they are. Returns the number of chunks that
+/// were rewritten and the total number of chunks.
+fn rewrite_chunks_internal(
+    world: &World,
+    compressor: Compressor,
+    batch_size: usize,
+    is_current: impl Fn(&[u8]) -> bool + Sync,
+) -> Result<(usize, usize), WorldError> {
+    // Get any unsaved changes into the table first so they're rewritten too
+    world.dirty.flush(
+        world.storage_backend.as_ref(),
+        &world.dimensions,
+        &world.compressor,
+    )?;
+    if !world.storage_backend.table_exists("chunks".to_string())? {
+        info!("No chunks to rewrite.");
+        return Ok((0, 0));
+    }
+
+    let keys = world.storage_backend.get_keys("chunks".to_string())?;
+    let progress_style = ProgressStyle::default_bar()
+        .template("[{elapsed_precise}/{eta_precise} eta] {bar:40.cyan/blue} {percent}%, {pos:>7}/{len:7}, {per_sec}, {msg}")
+        .unwrap();
+    let progress = ProgressBar::new(keys.len() as u64);
+    progress.set_style(progress_style);
+    let mut rewritten = 0;
+
+    for batch in keys.chunks(batch_size.max(1)) {
+        let records = world
+            .storage_backend
+            .batch_get("chunks".to_string(), batch.to_vec())?;
+        let updated: Vec<(u128, Vec<u8>)> = batch
+            .par_iter()
+            .zip(records.par_iter())
+            .filter_map(|(key, record)| {
+                let record = record.as_ref()?;
+                if is_current(record) {
+                    return None;
+                }
+                let chunk = decode_chunk(record, world.verify_chunk_data);
+                match chunk.and_then(|chunk| encode_chunk(&compressor, &chunk)) {
+                    Ok(record) => Some((*key, record)),
+                    Err(e) => {
+                        error!("Failed to rewrite chunk with key {:X}: {}", key, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        rewritten += updated.len();
+        if !updated.is_empty() {
+            world
+                .storage_backend
+                .batch_upsert("chunks".to_string(), updated)?;
+        }
+        progress.inc(batch.len() as u64);
+    }
+
+    sync_internal(world)?;
+    progress.finish();
+
+    Ok((rewritten, keys.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk_format::Chunk;
+    use crate::testing::memory_world;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_saving_new_chunks_skips_existing_ones() {
+        let (world, _) = memory_world([]);
+        let mut existing = Chunk::new(1, 0, "overworld".to_string());
+        existing.mark_modified();
+        world.save_chunk(Arc::new(existing.clone())).unwrap();
+
+        let generated = [
+            Chunk::new(0, 0, "overworld".to_string()),
+            Chunk::new(1, 0, "overworld".to_string()),
+        ];
+        let saved = world.save_new_chunks(&generated).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!((saved[0].x, saved[0].z), (0, 0));
+        world.sync().unwrap();
+        assert_eq!(*world.load_chunk(1, 0, "overworld").unwrap(), existing);
+        assert!(world.chunk_exists(0, 0, "overworld").unwrap());
+    }
+}
@@ -0,0 +1,372 @@
+//! The dimensions a world is made of.
+//!
+//! Every dimension has a name and a numeric id, and the id is what chunks are stored under, so it
+//! has to stay the same for as long as the world exists. The vanilla dimensions always have their
+//! old ids (0, -1 and 1), and any other dimension gets the next free id the first time it's
+//! registered. The registry is kept in the `dimensions` table so those ids survive restarts.
+
+use crate::errors::WorldError;
+use bitcode_derive::{Decode, Encode};
+use ferrumc_config::server_config::DimensionConfig;
+use ferrumc_storage::backend::StorageBackend;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+pub const OVERWORLD_ID: i32 = 0;
+pub const NETHER_ID: i32 = -1;
+pub const END_ID: i32 = 1;
+
+const TABLE: &str = "dimensions";
+
+/// What a dimension is like, sent to clients so they know how to render it.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct DimensionType {
+    /// How many blocks tall the dimension is. Must be a multiple of 16.
+    pub height: u32,
+    /// The lowest y level blocks can be placed at. Must be a multiple of 16.
+    pub min_y: i32,
+    pub has_skylight: bool,
+    /// Which terrain generator new chunks come from, e.g. `overworld`, `nether` or `end`.
+    pub generator: String,
+}
+
+impl DimensionType {
+    pub fn overworld() -> Self {
+        DimensionType {
+            height: 384,
+            min_y: -64,
+            has_skylight: true,
+            generator: "overworld".to_string(),
+        }
+    }
+
+    pub fn nether() -> Self {
+        DimensionType {
+            height: 256,
+            min_y: 0,
+            has_skylight: false,
+            generator: "nether".to_string(),
+        }
+    }
+
+    pub fn end() -> Self {
+        DimensionType {
+            height: 256,
+            min_y: 0,
+            has_skylight: false,
+            generator: "end".to_string(),
+        }
+    }
+
+    /// Whether the dimension is hot enough for water to evaporate and lava to flow quickly, which
+    /// is the case for dimensions generated like the nether.
+    pub fn ultrawarm(&self) -> bool {
+        self.generator == "nether"
+    }
+
+    fn validate(&self, name: &str) -> Result<(), WorldError> {
+        if self.height == 0 || self.height % 16 != 0 || self.min_y % 16 != 0 {
+            return Err(WorldError::InvalidDimension(format!(
+                "{name}: height and min_y must be multiples of 16, got {} and {}",
+                self.height, self.min_y
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl From<&DimensionConfig> for DimensionType {
+    fn from(config: &DimensionConfig) -> Self {
+        DimensionType {
+            height: config.height,
+            min_y: config.min_y,
+            has_skylight: config.has_skylight,
+            generator: config.generator.clone(),
+        }
+    }
+}
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct Dimension {
+    pub id: i32,
+    /// The name chunks refer to the dimension by, see [`canonical_name`].
+    pub name: String,
+    pub dimension_type: DimensionType,
+}
+
+impl Dimension {
+    /// The namespaced name clients know the dimension by, e.g. `minecraft:the_nether`.
+    pub fn registry_name(&self) -> String {
+        match self.id {
+            OVERWORLD_ID => "minecraft:overworld".to_string(),
+            NETHER_ID => "minecraft:the_nether".to_string(),
+            END_ID => "minecraft:the_end".to_string(),
+            _ if self.name.contains(':') => self.name.clone(),
+            _ => format!("ferrumc:{}", self.name),
+        }
+    }
+}
+
+/// Turns the different ways of naming a dimension into the one it's registered under.
+///
+/// The vanilla dimensions can be called by their short name (`nether`), their path
+/// (`the_nether`) or their full registry name (`minecraft:the_nether`). Everything else is
+/// used as is.
+pub fn canonical_name(name: &str) -> &str {
+    match name {
+        "overworld" | "minecraft:overworld" => "overworld",
+        "nether" | "the_nether" | "minecraft:the_nether" => "nether",
+        "end" | "the_end" | "minecraft:the_end" => "end",
+        _ => name.strip_prefix("ferrumc:").unwrap_or(name),
+    }
+}
+
+fn builtin_dimensions() -> [Dimension; 3] {
+    [
+        Dimension {
+            id: OVERWORLD_ID,
+            name: "overworld".to_string(),
+            dimension_type: DimensionType::overworld(),
+        },
+        Dimension {
+            id: NETHER_ID,
+            name: "nether".to_string(),
+            dimension_type: DimensionType::nether(),
+        },
+        Dimension {
+            id: END_ID,
+            name: "end".to_string(),
+            dimension_type: DimensionType::end(),
+        },
+    ]
+}
+
+fn storage_key(id: i32) -> u128 {
+    id as u32 as u128
+}
+
+/// Maps dimension names to their ids and types.
+#[derive(Debug)]
+pub struct DimensionRegistry {
+    dimensions: RwLock<BTreeMap<i32, Dimension>>,
+}
+
+impl DimensionRegistry {
+    /// Load the registry from the storage backend, on top of the vanilla dimensions.
+    pub(crate) fn load(storage_backend: &dyn StorageBackend) -> Result<Self, WorldError> {
+        let mut dimensions: BTreeMap<i32, Dimension> = builtin_dimensions()
+            .into_iter()
+            .map(|dimension| (dimension.id, dimension))
+            .collect();
+        if storage_backend.table_exists(TABLE.to_string())? {
+            let keys = storage_backend.get_keys(TABLE.to_string())?;
+            for value in storage_backend
+                .batch_get(TABLE.to_string(), keys)?
+                .into_iter()
+                .flatten()
+            {
+                let dimension: Dimension = bitcode::decode(&value)
+                    .map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
+                dimensions.insert(dimension.id, dimension);
+            }
+        }
+        Ok(DimensionRegistry {
+            dimensions: RwLock::new(dimensions),
+        })
+    }
+
+    /// Add a dimension, or change the type of one that's already registered.
+    ///
+    /// Dimensions keep the id they were first given, new ones get the id after the highest one in
+    /// use. The change is saved to the storage backend straight away.
+    pub(crate) fn register(
+        &self,
+        storage_backend: &dyn StorageBackend,
+        name: &str,
+        dimension_type: DimensionType,
+    ) -> Result<Dimension, WorldError> {
+        let name = canonical_name(name);
+        if name.is_empty() {
+            return Err(WorldError::InvalidDimension(
+                "Dimension names can't be empty".to_string(),
+            ));
+        }
+        dimension_type.validate(name)?;
+
+        // Hold the lock while saving so two new dimensions can't be given the same id
+        let mut dimensions = self.dimensions.write().unwrap();
+        let id = match dimensions.values().find(|dimension| dimension.name == name) {
+            Some(existing) if existing.dimension_type == dimension_type => {
+                return Ok(existing.clone())
+            }
+            Some(existing) => existing.id,
+            None => dimensions.keys().last().copied().unwrap_or(END_ID) + 1,
+        };
+        let dimension = Dimension {
+            id,
+            name: name.to_string(),
+            dimension_type,
+        };
+        storage_backend.batch_upsert(
+            TABLE.to_string(),
+            vec![(storage_key(id), bitcode::encode(&dimension))],
+        )?;
+        dimensions.insert(id, dimension.clone());
+        Ok(dimension)
+    }
+
+    /// Get the id chunks in a dimension are stored under.
+    pub fn id(&self, name: &str) -> Result<i32, WorldError> {
+        let name = canonical_name(name);
+        self.dimensions
+            .read()
+            .unwrap()
+            .values()
+            .find(|dimension| dimension.name == name)
+            .map(|dimension| dimension.id)
+            .ok_or_else(|| WorldError::UnknownDimension(name.to_string()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Dimension> {
+        let name = canonical_name(name);
+        self.dimensions
+            .read()
+            .unwrap()
+            .values()
+            .find(|dimension| dimension.name == name)
+            .cloned()
+    }
+
+    pub fn by_id(&self, id: i32) -> Option<Dimension> {
+        self.dimensions.read().unwrap().get(&id).cloned()
+    }
+
+    /// Every registered dimension, ordered by id.
+    pub fn all(&self) -> Vec<Dimension> {
+        self.dimensions.read().unwrap().values().cloned().collect()
+    }
+
+    /// Every registered dimension, in the order their types are sent to clients in the
+    /// `minecraft:dimension_type` registry.
+    ///
+    /// The vanilla dimensions come first in the order vanilla sends them, so the overworld is
+    /// always 0, followed by every other dimension ordered by id.
+    pub fn network_order(&self) -> Vec<Dimension> {
+        let mut dimensions = self.all();
+        dimensions.sort_by_key(|dimension| match dimension.id {
+            OVERWORLD_ID => (0, 0),
+            END_ID => (1, 0),
+            NETHER_ID => (2, 0),
+            id => (3, id),
+        });
+        dimensions
+    }
+
+    /// The index of a dimension in [`Self::network_order`], which is the id of its type in the
+    /// `minecraft:dimension_type` registry sent to clients.
+    pub fn network_id(&self, name: &str) -> Option<usize> {
+        let name = canonical_name(name);
+        self.network_order()
+            .iter()
+            .position(|dimension| dimension.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_format::Chunk;
+    use crate::testing::memory_world;
+    use crate::World;
+    use ferrumc_storage::memory::MemoryBackend;
+    use std::sync::Arc;
+
+    fn lobby() -> DimensionType {
+        DimensionType {
+            height: 128,
+            min_y: 0,
+            has_skylight: true,
+            generator: "overworld".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_vanilla_aliases() {
+        let registry = DimensionRegistry::load(&MemoryBackend::new()).unwrap();
+        assert_eq!(registry.id("minecraft:overworld").unwrap(), OVERWORLD_ID);
+        assert_eq!(registry.id("the_nether").unwrap(), NETHER_ID);
+        assert_eq!(registry.id("end").unwrap(), END_ID);
+        assert!(matches!(
+            registry.id("lobby"),
+            Err(WorldError::UnknownDimension(_))
+        ));
+    }
+
+    #[test]
+    fn test_ids_survive_reload() {
+        let backend = MemoryBackend::new();
+        let registry = DimensionRegistry::load(&backend).unwrap();
+        let lobby_id = registry.register(&backend, "lobby", lobby()).unwrap().id;
+        let creative_id = registry
+            .register(&backend, "creative", DimensionType::overworld())
+            .unwrap()
+            .id;
+        assert_eq!(lobby_id, 2);
+        assert_eq!(creative_id, 3);
+
+        // Changing the type of an existing dimension keeps its id
+        let mut taller = lobby();
+        taller.height = 256;
+        assert_eq!(registry.register(&backend, "lobby", taller).unwrap().id, 2);
+
+        let reloaded = DimensionRegistry::load(&backend).unwrap();
+        assert_eq!(reloaded.id("ferrumc:lobby").unwrap(), lobby_id);
+        assert_eq!(reloaded.id("creative").unwrap(), creative_id);
+        assert_eq!(reloaded.get("lobby").unwrap().dimension_type.height, 256);
+        assert_eq!(
+            reloaded.get("lobby").unwrap().registry_name(),
+            "ferrumc:lobby"
+        );
+        assert_eq!(reloaded.network_id("overworld"), Some(0));
+        assert_eq!(reloaded.network_id("nether"), Some(2));
+        assert_eq!(reloaded.network_id("lobby"), Some(3));
+        assert_eq!(reloaded.network_id("creative"), Some(4));
+    }
+
+    #[test]
+    fn test_rejects_bad_types() {
+        let backend = MemoryBackend::new();
+        let registry = DimensionRegistry::load(&backend).unwrap();
+        let mut bad = lobby();
+        bad.min_y = 5;
+        assert!(registry.register(&backend, "lobby", bad).is_err());
+        assert!(registry.get("lobby").is_none());
+    }
+
+    #[test]
+    fn test_custom_dimensions_get_their_own_chunks() {
+        let (world, backend) = memory_world([]);
+        assert!(world
+            .save_chunk(Arc::new(Chunk::new(0, 0, "lobby".to_string())))
+            .is_err());
+
+        let lobby = world
+            .register_dimension("lobby", DimensionType::overworld())
+            .unwrap();
+        let overworld = Chunk::new(0, 0, "overworld".to_string());
+        world.save_chunk(Arc::new(overworld.clone())).unwrap();
+        world
+            .save_chunk(Arc::new(Chunk::new(0, 0, "lobby".to_string())))
+            .unwrap();
+        world.sync().unwrap();
+        assert_eq!(backend.get_keys("chunks".to_string()).unwrap().len(), 2);
+
+        let reopened = World::with_backend(backend.clone()).unwrap();
+        assert_eq!(reopened.dimensions().id("lobby").unwrap(), lobby.id);
+        assert_eq!(*reopened.load_chunk(0, 0, "overworld").unwrap(), overworld);
+        assert_eq!(
+            reopened.load_chunk(0, 0, "lobby").unwrap().dimension,
+            "lobby"
+        );
+    }
+}
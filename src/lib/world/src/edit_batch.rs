@@ -86,9 +86,22 @@ impl<'a> EditBatch<'a> {
 
     /// Applies all edits in the batch to the chunk.
     ///
-    /// This will modify the chunk in place and clear the batch.
+    /// This will modify the chunk in place, recompute its heightmaps, give the changed blocks
+    /// the block entities they need and clear the batch. The light is marked as needing an
+    /// update, like with [`Chunk::set_block`].
     /// Will return an error if the batch has already been used or if there are no edits.
     pub fn apply(&mut self) -> Result<(), WorldError> {
+        self.apply_unlit()?;
+        // Blocks changed all over the place, so it's cheaper to light the whole chunk in one go
+        // than to update the light around every single edit. That's done when it's saved, when
+        // the neighbours the light spills into are at hand.
+        self.chunk.light_dirty = true;
+        Ok(())
+    }
+
+    /// Like [`EditBatch::apply`], but leaves the light as it was, for when it's updated around
+    /// the changed blocks afterwards.
+    pub(crate) fn apply_unlit(&mut self) -> Result<(), WorldError> {
         if self.used {
             return Err(WorldError::InvalidBatchingOperation(
                 "EditBatch has already been used".to_string(),
@@ -102,6 +115,7 @@ impl<'a> EditBatch<'a> {
 
         let mut section_edits: AHashMap<i8, Vec<Option<&Edit>>> = AHashMap::new();
         let mut all_blocks = AHashSet::new();
+        let mut changed = Vec::new();
 
         // Convert edits into per-section sparse arrays (Vec<Option<&Edit>>),
         // using block index (0..4095) as the key instead of hashing 3D coords
@@ -272,6 +286,7 @@ impl<'a> EditBatch<'a> {
                     .map_err(|e| {
                         WorldError::InvalidBlockStateData(format!("Packing error: {e}"))
                     })?;
+                changed.push(edit);
             }
 
             // Update block counts
@@ -305,6 +320,12 @@ impl<'a> EditBatch<'a> {
             }
         }
 
+        self.chunk.recalculate_heightmaps();
+        for edit in changed {
+            self.chunk
+                .replace_block_entity(edit.x, edit.y, edit.z, edit.block);
+        }
+
         // Clear edits after applying
         self.edits.clear();
         self.used = true;
@@ -367,4 +388,23 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_block_entities_follow_blocks() {
+        let mut chunk = Chunk::new(0, 0, "overworld".to_string());
+        let chest = BlockId::from_name("minecraft:chest").unwrap();
+
+        let mut batch = EditBatch::new(&mut chunk);
+        batch.set_block(1, 64, 1, chest);
+        batch.set_block(2, 64, 1, chest);
+        batch.apply().unwrap();
+        assert!(chunk.get_block_entity(1, 64, 1).is_some());
+        assert!(chunk.get_block_entity(2, 64, 1).is_some());
+
+        let mut batch = EditBatch::new(&mut chunk);
+        batch.set_block(1, 64, 1, make_test_block("minecraft:stone"));
+        batch.apply().unwrap();
+        assert!(chunk.get_block_entity(1, 64, 1).is_none());
+        assert!(chunk.get_block_entity(2, 64, 1).is_some());
+    }
 }
@@ -1,15 +1,15 @@
+use crate::block_entities::{block_entity_type, default_nbt};
 use crate::block_id::{BlockId, BLOCK2ID, ID2BLOCK};
 use crate::chunk_format::{BiomeStates, BlockStates, Chunk, PaletteType, Section};
 use crate::errors::WorldError;
+use crate::fluids;
+use crate::tick;
 use crate::vanilla_chunk_format::BlockData;
 use crate::World;
-use crate::redstone::{self, RedstoneComponent};
-use crate::tick;
 use ferrumc_general_purpose::data_packing::i32::read_nbit_i32;
 use ferrumc_net_codec::net_types::var_int::VarInt;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::sync::Arc;
 use tracing::{debug, error, warn};
 
 impl World {
@@ -48,8 +48,8 @@ impl World {
     }
 
     /// Sets the block data at the specified coordinates in the given dimension.
-    /// Under the hood, this function just fetches the chunk containing the block and then calls
-    /// [`Chunk::set_block`] on it.
+    /// Under the hood, this function fetches the chunk containing the block, sets the block and
+    /// then updates the light around it, including any light spilling into neighbouring chunks.
     ///
     /// # Arguments
     ///
@@ -79,52 +79,53 @@ impl World {
         let chunk_x = x >> 4;
         let chunk_z = z >> 4;
         let mut chunk = self.load_chunk_owned(chunk_x, chunk_z, dimension)?;
+        chunk.mark_modified();
 
         debug!("Chunk: {}, {}", chunk_x, chunk_z);
-
-        chunk.set_block(x, y, z, block)?;
-        for section in &mut chunk.sections {
-            section.optimise()?;
+        let old_block = chunk.get_block(x, y, z)?;
+
+        // Sets the block, updates the light around it and saves the affected chunks
+        self.set_block_and_relight(chunk, x, y, z, block)?;
+        self.block_updated(x, y, z, dimension, block);
+
+        // Players need the data of a block entity the new block came with
+        let entity_type = block_entity_type(block);
+        if entity_type != block_entity_type(old_block) {
+            if let Some(entity_type) = entity_type {
+                let nbt = default_nbt(entity_type);
+                self.block_entity_changed(dimension, (x, y, z), entity_type, nbt);
+            }
         }
 
-        // Save chunk
-        self.save_chunk(Arc::new(chunk))?;
+        Ok(())
+    }
 
-        // handle redstone components
-        if let Some(comp) = redstone::identify_component(&block) {
-            match comp {
-                RedstoneComponent::Torch => {
-                    self.schedule_tick(x, y, z, dimension, 1);
-                }
-                RedstoneComponent::Repeater { delay, .. } => {
-                    self.schedule_tick(x, y, z, dimension, delay as u32);
-                }
-                RedstoneComponent::Wire => {}
-            }
+    /// Let the redstone, fluids and leaves around a block that was just set react to it.
+    pub(crate) fn block_updated(&self, x: i32, y: i32, z: i32, dimension: &str, block: BlockId) {
+        // Redstone networks next to the block get recompiled on the next tick
+        self.redstone
+            .lock()
+            .unwrap()
+            .block_changed(dimension, (x, y, z));
+        let delay = fluids::tick_delay(self, block, dimension)
+            .or_else(|| tick::leaves_update_delay(block));
+        if let Some(delay) = delay {
+            self.schedule_tick(x, y, z, dimension, delay);
         }
 
         // notify neighbors
         let neighbors = [(1, 0, 0), (-1, 0, 0), (0, 0, 1), (0, 0, -1), (0, 1, 0), (0, -1, 0)];
         for (dx, dy, dz) in neighbors {
             if let Ok(nb) = self.get_block_and_fetch(x + dx, y + dy, z + dz, dimension) {
-                if let Some(comp) = redstone::identify_component(&nb) {
-                    let delay = match comp {
-                        RedstoneComponent::Torch => 1,
-                        RedstoneComponent::Repeater { delay, .. } => delay as u32,
-                        RedstoneComponent::Wire => 0,
-                    };
-                    if delay == 0 {
-                        let pos = tick::BlockPos { x: x + dx, y: y + dy, z: z + dz, dimension: dimension.to_string() };
-                        let mut cache = self.redstone_cache.lock().unwrap();
-                        redstone::propagate_from(self, &mut cache, pos, 15);
-                    } else {
-                        self.schedule_tick(x + dx, y + dy, z + dz, dimension, delay);
-                    }
+                // Fluids flow into the space or stop flowing into it, leaves find their way to a
+                // log again
+                let delay = fluids::tick_delay(self, nb, dimension)
+                    .or_else(|| tick::leaves_update_delay(nb));
+                if let Some(delay) = delay {
+                    self.schedule_tick(x + dx, y + dy, z + dz, dimension, delay);
                 }
             }
         }
-
-        Ok(())
     }
 }
 
@@ -325,12 +326,37 @@ impl Chunk {
     /// The positions are modulo'd by 16 to get the block index in the section anyway, so converting
     /// the coordinates to section coordinates isn't really necessary, but you should probably do it
     /// anyway for readability's sake.
+    ///
+    /// The heightmaps are updated as well. The light isn't, the chunk is only marked as needing
+    /// it, and is relit along with the loaded chunks around it when it's saved, or on its own
+    /// when [`Chunk::relight`] is called. Use [`World::set_block_and_fetch`] to update the light
+    /// right away, including any light spilling into neighbouring chunks.
     pub fn set_block(
         &mut self,
         x: i32,
         y: i32,
         z: i32,
         block: impl Into<BlockId>,
+    ) -> Result<(), WorldError> {
+        let block = block.into();
+        if self.get_block(x, y, z)? == block {
+            return Ok(());
+        }
+        self.set_block_raw(x, y, z, block)?;
+        self.light_dirty = true;
+        Ok(())
+    }
+
+    /// Same as [`Chunk::set_block`], but doesn't mark the light as needing an update. The
+    /// heightmaps are still updated.
+    ///
+    /// Meant for edits whose light is updated around the changed blocks straight afterwards.
+    pub(crate) fn set_block_raw(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: impl Into<BlockId>,
     ) -> Result<(), WorldError> {
         let block = block.into();
         // Get old block
@@ -428,7 +454,7 @@ impl Chunk {
                 // Set block
                 let blocks_per_i64 = (64f64 / *bits_per_block as f64).floor() as usize;
                 let index =
-                    ((y.abs() & 0xf) * 256 + (z.abs() & 0xf) * 16 + (x.abs() & 0xf)) as usize;
+                    ((y & 0xf) * 256 + (z & 0xf) * 16 + (x & 0xf)) as usize;
                 let i64_index = index / blocks_per_i64;
                 let packed_u64 =
                     data.get_mut(i64_index)
@@ -488,7 +514,7 @@ impl Chunk {
                 // Write block ID directly into packed data
                 let blocks_per_i64 = (64f64 / *bits_per_block as f64).floor() as usize;
                 let index =
-                    ((y.abs() & 0xf) * 256 + (z.abs() & 0xf) * 16 + (x.abs() & 0xf)) as usize;
+                    ((y & 0xf) * 256 + (z & 0xf) * 16 + (x & 0xf)) as usize;
                 let i64_index = index / blocks_per_i64;
                 let packed_u64 =
                     data.get_mut(i64_index)
@@ -523,6 +549,8 @@ impl Chunk {
         self.sections
             .iter_mut()
             .for_each(|section| section.optimise().unwrap());
+        self.replace_block_entity(x, y, z, block);
+        self.update_heightmaps_at(x, y, z)?;
         Ok(())
     }
 
@@ -558,7 +586,7 @@ impl Chunk {
         let section = self
             .sections
             .iter()
-            .find(|section| section.y == (y / 16) as i8)
+            .find(|section| section.y == (y >> 4) as i8)
             .ok_or(WorldError::SectionOutOfBounds(y >> 4))?;
         match &section.block_states.block_data {
             PaletteType::Single(val) => Ok(BlockId::from_varint(*val)),
@@ -613,6 +641,9 @@ impl Chunk {
     /// Sets the section at the specified index to the specified block data.
     /// If the section is out of bounds, an error is returned.
     ///
+    /// The heightmaps are recalculated, but the light isn't, call [`Chunk::relight`] once you're
+    /// done editing.
+    ///
     /// # Arguments
     ///
     /// * `section` - The index of the section to set.
@@ -628,7 +659,9 @@ impl Chunk {
             .iter_mut()
             .find(|section| section.y == section_y)
         {
-            section.fill(block.clone())
+            section.fill(block.clone())?;
+            self.recalculate_heightmaps();
+            Ok(())
         } else {
             Err(WorldError::SectionOutOfBounds(section_y as i32))
         }
@@ -636,6 +669,9 @@ impl Chunk {
 
     /// Fills the chunk with the specified block.
     ///
+    /// The heightmaps are recalculated, but the light isn't, call [`Chunk::relight`] once you're
+    /// done editing.
+    ///
     /// # Arguments
     ///
     /// * `block` - The block data to fill the chunk with.
@@ -648,6 +684,7 @@ impl Chunk {
         for section in &mut self.sections {
             section.fill(block.clone())?;
         }
+        self.recalculate_heightmaps();
         Ok(())
     }
 }
@@ -0,0 +1,78 @@
+//! Entities that live in the world rather than being connected players, like mobs and dropped
+//! items.
+//!
+//! They're kept per chunk in the `entities` table, under the same keys as the chunks themselves,
+//! so loading the entities of a chunk is a single lookup.
+
+use crate::db_functions::create_key;
+use crate::errors::WorldError;
+use crate::World;
+use bitcode_derive::{Decode, Encode};
+
+const TABLE: &str = "entities";
+
+/// An item stack carried by an entity, e.g. a dropped item.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct StoredItem {
+    /// The namespaced item name, e.g. `minecraft:diamond`.
+    pub id: String,
+    pub count: u8,
+}
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct StoredEntity {
+    /// The namespaced entity type, e.g. `minecraft:zombie`.
+    pub entity_type: String,
+    pub uuid: u128,
+    pub position: (f64, f64, f64),
+    pub velocity: (f64, f64, f64),
+    pub yaw: f32,
+    pub pitch: f32,
+    pub item: Option<StoredItem>,
+}
+
+impl World {
+    /// Replace the entities stored in a chunk. An empty list removes them.
+    pub fn save_entities(
+        &self,
+        x: i32,
+        z: i32,
+        dimension: &str,
+        entities: &[StoredEntity],
+    ) -> Result<(), WorldError> {
+        let key = create_key(self.dimensions.id(dimension)?, x, z);
+        if entities.is_empty() {
+            if self.storage_backend.table_exists(TABLE.to_string())?
+                && self.storage_backend.exists(TABLE.to_string(), key)?
+            {
+                self.storage_backend.delete(TABLE.to_string(), key)?;
+            }
+            return Ok(());
+        }
+        if !self.storage_backend.table_exists(TABLE.to_string())? {
+            self.storage_backend.create_table(TABLE.to_string())?;
+        }
+        self.storage_backend
+            .upsert(TABLE.to_string(), key, bitcode::encode(entities))?;
+        Ok(())
+    }
+
+    /// Get the entities stored in a chunk, if there are any.
+    pub fn load_entities(
+        &self,
+        x: i32,
+        z: i32,
+        dimension: &str,
+    ) -> Result<Vec<StoredEntity>, WorldError> {
+        if !self.storage_backend.table_exists(TABLE.to_string())? {
+            return Ok(Vec::new());
+        }
+        let key = create_key(self.dimensions.id(dimension)?, x, z);
+        match self.storage_backend.get(TABLE.to_string(), key)? {
+            Some(data) => {
+                bitcode::decode(&data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+}
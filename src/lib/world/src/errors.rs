@@ -20,6 +20,8 @@ pub enum WorldError {
     InvalidCacheSize(String),
     #[error("Invalid Import Path: {0}")]
     InvalidImportPath(String),
+    #[error("Invalid Export Path: {0}")]
+    InvalidExportPath(String),
     #[error("No region files")]
     NoRegionFiles,
     #[error("Unable to obtain permission to access file/folder: {0}")]
@@ -38,6 +40,8 @@ pub enum WorldError {
     BlockEntityNotFound,
     #[error("Anvil Decode Error: {0}")]
     AnvilDecodeError(AnvilError),
+    #[error("Anvil Encode Error: {0}")]
+    AnvilEncodeError(AnvilError),
     #[error("Missing block mapping: {0}")]
     MissingBlockMapping(BlockData),
     #[error("Invalid memory map size: {0}")]
@@ -54,6 +58,8 @@ pub enum WorldError {
     InvalidBatchingOperation(String),
     #[error("Invalid block ID: {0}")]
     InvalidBlockId(u32),
+    #[error("Invalid property {1} for block {0}")]
+    InvalidBlockProperty(String, String),
     #[error("World generation error: {0}")]
     WorldGenerationError(String),
     #[error("Compression error: {0}")]
@@ -62,6 +68,32 @@ pub enum WorldError {
     DecompressionError(String),
     #[error("Corrupted chunk data: got checksum {0}, expected checksum {1}")]
     CorruptedChunkData(u32, u32),
+    #[error("Chunk format version {0} is newer than the latest supported version {1}")]
+    UnsupportedChunkVersion(u16, u16),
+    #[error("Failed to migrate chunk from format version {0}: {1}")]
+    ChunkMigrationError(u16, String),
+    #[error("Backup error: {0}")]
+    BackupError(String),
+    #[error("The world database at {0} is in use by another process")]
+    DatabaseLocked(String),
+    #[error("Saving chunks in the background keeps failing: {0}")]
+    FlushError(String),
+    #[error("Unknown dimension: {0}")]
+    UnknownDimension(String),
+    #[error("Invalid dimension: {0}")]
+    InvalidDimension(String),
+    #[error("Trim error: {0}")]
+    TrimError(String),
+    #[error("NBT decode error: {0}")]
+    NbtDecodeError(String),
+    #[error("Chunks from data version {0} can't be imported, the oldest supported is {1}")]
+    UnsupportedDataVersion(i32, i32),
+    #[error("Schematic error: {0}")]
+    SchematicError(String),
+    #[error("Unknown game rule: {0}")]
+    UnknownGameRule(String),
+    #[error("Chunk {0}, {1} kept changing while it was being edited")]
+    ChunkContended(i32, i32),
 }
 
 impl From<std::io::Error> for WorldError {
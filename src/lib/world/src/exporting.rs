@@ -0,0 +1,441 @@
+use crate::biome_id::get_biome_name;
+use crate::block_entities::block_entity_type_name;
+use crate::block_id::BlockId;
+use crate::chunk_format::{Chunk, PaletteType, Section};
+use crate::db_functions::{chunk_coords_internal, load_chunk_internal};
+use crate::dimensions::canonical_name;
+use crate::errors::WorldError;
+use crate::vanilla_chunk_format::{
+    self, BlockData, Structures, VanillaBlockEntity, VanillaChunk, VanillaHeightmaps,
+};
+use crate::World;
+use ferrumc_anvil::writer::{CompressionType, RegionWriter};
+use ferrumc_nbt::{NBTSerializable, NBTSerializeOptions};
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+
+/// The data version of 1.20.1, which is the version the chunks are written as.
+pub(crate) const DATA_VERSION: i32 = 3465;
+
+impl Chunk {
+    /// Converts the chunk back into the format used by vanilla region files.
+    pub(crate) fn to_vanilla_format(&self) -> Result<VanillaChunk, WorldError> {
+        let mut sections = self.sections.iter().collect::<Vec<_>>();
+        sections.sort_by_key(|section| section.y);
+        let sections = sections
+            .into_iter()
+            .map(section_to_vanilla)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let heightmaps = VanillaHeightmaps {
+            motion_blocking_no_leaves: Some(self.heightmaps.motion_blocking_no_leaves.clone())
+                .filter(|data| !data.is_empty()),
+            motion_blocking: Some(self.heightmaps.motion_blocking.clone())
+                .filter(|data| !data.is_empty()),
+            ocean_floor: Some(self.heightmaps.ocean_floor.clone()).filter(|data| !data.is_empty()),
+            world_surface: Some(self.heightmaps.world_surface.clone())
+                .filter(|data| !data.is_empty()),
+        };
+
+        let block_entities = self
+            .block_entities
+            .iter()
+            .filter_map(|block_entity| {
+                let Some(id) = block_entity_type_name(block_entity.entity_type.0) else {
+                    warn!(
+                        "Skipping block entity with unknown type {} in chunk {}, {}",
+                        block_entity.entity_type.0, self.x, self.z
+                    );
+                    return None;
+                };
+                let data = compound_body(&block_entity.nbt).unwrap_or_else(|| {
+                    warn!(
+                        "Block entity at {}, {}, {} has invalid data, only its position will be exported",
+                        block_entity.x(),
+                        block_entity.y as i16,
+                        block_entity.z()
+                    );
+                    &[]
+                });
+                Some(VanillaBlockEntity {
+                    id: id.to_string(),
+                    x: self.x * 16 + block_entity.x() as i32,
+                    // Stored as a u16, but it's really the (possibly negative) world y
+                    y: block_entity.y as i16 as i32,
+                    z: self.z * 16 + block_entity.z() as i32,
+                    data: data.to_vec(),
+                })
+            })
+            .collect();
+
+        Ok(VanillaChunk {
+            dimension: None,
+            status: "minecraft:full".to_string(),
+            data_version: DATA_VERSION,
+            heightmaps: Some(heightmaps),
+            is_light_on: Some(1),
+            // Anything above 0 makes the chunk count as modified again when it's imported
+            inhabited_time: Some(if self.last_modified != 0 { 1 } else { 0 }),
+            y_pos: self.sections.iter().map(|s| s.y as i32).min().unwrap_or(-4),
+            x_pos: self.x,
+            z_pos: self.z,
+            structures: Some(Structures {
+                starts: vanilla_chunk_format::Starts {},
+                references: vanilla_chunk_format::References {},
+            }),
+            last_update: Some(0),
+            sections: Some(sections),
+            block_entities: Some(block_entities),
+        })
+    }
+}
+
+fn section_to_vanilla(section: &Section) -> Result<vanilla_chunk_format::Section, WorldError> {
+    // Vanilla doesn't have a direct palette on disk, and expects the bits per block to match the
+    // palette size exactly, so the indices get repacked no matter what they were before.
+    let (palette, indices) = match &section.block_states.block_data {
+        PaletteType::Single(val) => (vec![BlockId::from_varint(*val)], vec![]),
+        PaletteType::Indirect {
+            bits_per_block,
+            data,
+            palette,
+        } => {
+            let palette: Vec<BlockId> = palette.iter().map(|p| BlockId::from_varint(*p)).collect();
+            if palette.len() <= 1 || *bits_per_block == 0 {
+                (palette, vec![])
+            } else {
+                let max_index = palette.len() as u64 - 1;
+                let indices = unpack(*bits_per_block, data, 4096)
+                    .into_iter()
+                    .map(|index| index.min(max_index))
+                    .collect();
+                (palette, indices)
+            }
+        }
+        PaletteType::Direct {
+            bits_per_block,
+            data,
+        } => {
+            let mut palette = Vec::new();
+            let mut lookup = HashMap::new();
+            let indices = unpack(*bits_per_block, data, 4096)
+                .into_iter()
+                .map(|id| {
+                    *lookup.entry(id).or_insert_with(|| {
+                        palette.push(BlockId(id as u32));
+                        palette.len() as u64 - 1
+                    })
+                })
+                .collect();
+            (palette, indices)
+        }
+    };
+
+    let palette = palette
+        .into_iter()
+        .map(|id| {
+            id.to_block_data().unwrap_or_else(|| {
+                error!("Could not find block data for block id: {}", id.0);
+                BlockData::default()
+            })
+        })
+        .collect::<Vec<_>>();
+    let data = if palette.len() <= 1 {
+        None
+    } else {
+        let bits = ((palette.len() as f32).log2().ceil() as u8).max(4);
+        Some(pack(bits, &indices))
+    };
+
+    let biome_palette = section
+        .biome_states
+        .palette
+        .iter()
+        .map(|id| {
+            get_biome_name(id.0)
+                .unwrap_or("minecraft:plains")
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    let biomes = if biome_palette.is_empty() {
+        vanilla_chunk_format::Biomes {
+            data: None,
+            palette: vec!["minecraft:plains".to_string()],
+        }
+    } else {
+        vanilla_chunk_format::Biomes {
+            data: Some(section.biome_states.data.clone())
+                .filter(|data| biome_palette.len() > 1 && !data.is_empty()),
+            palette: biome_palette,
+        }
+    };
+
+    let light = |light: &Vec<u8>| {
+        Some(light.iter().map(|&x| x as i8).collect::<Vec<_>>()).filter(|l| l.len() == 2048)
+    };
+
+    Ok(vanilla_chunk_format::Section {
+        block_states: Some(vanilla_chunk_format::BlockStates {
+            data,
+            palette: Some(palette),
+        }),
+        biomes: Some(biomes),
+        y: section.y,
+        block_light: light(&section.block_light),
+        sky_light: light(&section.sky_light),
+    })
+}
+
+fn unpack(bits_per_entry: u8, data: &[i64], count: usize) -> Vec<u64> {
+    let bits = bits_per_entry as usize;
+    let per_long = 64 / bits;
+    let mask = (1u64 << bits) - 1;
+    (0..count)
+        .map(|index| {
+            data.get(index / per_long)
+                .map(|long| ((*long as u64) >> ((index % per_long) * bits)) & mask)
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+pub(crate) fn pack(bits_per_entry: u8, values: &[u64]) -> Vec<i64> {
+    let bits = bits_per_entry as usize;
+    let per_long = 64 / bits;
+    let mut data = vec![0i64; values.len().div_ceil(per_long)];
+    for (index, value) in values.iter().enumerate() {
+        let offset = (index % per_long) * bits;
+        data[index / per_long] |= (*value << offset) as i64;
+    }
+    data
+}
+
+/// Strips the root header and end tag off a serialized compound, leaving just the tags inside
+/// it.
+pub(crate) fn compound_body(nbt: &[u8]) -> Option<&[u8]> {
+    if nbt.len() < 4 || nbt[0] != 10 || nbt[nbt.len() - 1] != 0 {
+        return None;
+    }
+    let name_length = u16::from_be_bytes([nbt[1], nbt[2]]) as usize;
+    nbt.get(3 + name_length..nbt.len() - 1)
+}
+
+fn region_dir(export_dir: &Path, dimension: &str) -> PathBuf {
+    match canonical_name(dimension) {
+        "overworld" => export_dir.join("region"),
+        "nether" => export_dir.join("DIM-1").join("region"),
+        "end" => export_dir.join("DIM1").join("region"),
+        // Anything else goes where vanilla keeps dimensions added by datapacks
+        custom => {
+            let (namespace, path) = custom.split_once(':').unwrap_or(("ferrumc", custom));
+            export_dir
+                .join("dimensions")
+                .join(namespace)
+                .join(path)
+                .join("region")
+        }
+    }
+}
+
+impl World {
+    /// Export every chunk in the world into vanilla region files.
+    ///
+    /// The overworld is written to `region`, the nether to `DIM-1/region` and the end to
+    /// `DIM1/region` inside `export_dir`, the same layout as a singleplayer save. Other dimensions
+    /// go in `dimensions/<namespace>/<name>/region`, like ones added by a datapack. Refuses to
+    /// write into a directory that already has region files in it.
+    pub fn export(
+        &self,
+        export_dir: PathBuf,
+        compression: CompressionType,
+    ) -> Result<(), WorldError> {
+        check_export_path(&export_dir)?;
+
+        // Make sure chunks that only exist in the cache are written out too
+        self.sync()?;
+
+        let coords = chunk_coords_internal(self)?;
+        let mut regions: BTreeMap<(String, i32, i32), Vec<(i32, i32)>> = BTreeMap::new();
+        for (x, z, dimension) in coords {
+            regions
+                .entry((dimension, x >> 5, z >> 5))
+                .or_default()
+                .push((x, z));
+        }
+
+        let total_chunks = regions.values().map(|chunks| chunks.len() as u64).sum();
+        let progress_style = ProgressStyle::default_bar()
+            .template("[{elapsed_precise}/{eta_precise} eta] {bar:40.cyan/blue} {percent}%, {pos:>7}/{len:7}, {per_sec}, {msg}")
+            .unwrap();
+        let progress = ProgressBar::new(total_chunks);
+        progress.set_style(progress_style);
+
+        info!(
+            "Exporting {} chunks into {} region files...",
+            total_chunks,
+            regions.len()
+        );
+        let start = std::time::Instant::now();
+
+        // Chunks nobody changed since they were generated don't have a time of their own
+        let export_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or_default();
+
+        regions.into_par_iter().try_for_each(
+            |((dimension, region_x, region_z), chunks)| -> Result<(), WorldError> {
+                let mut writer = RegionWriter::new(compression);
+                for (x, z) in chunks {
+                    let result = load_chunk_internal(self, x, z, &dimension).and_then(|chunk| {
+                        let timestamp = match chunk.last_modified {
+                            0 => export_time,
+                            last_modified => last_modified as u32,
+                        };
+                        Ok((chunk.to_vanilla_format()?, timestamp))
+                    });
+                    match result {
+                        Ok((vanilla_chunk, timestamp)) => {
+                            let mut buf = Vec::new();
+                            vanilla_chunk.serialize(&mut buf, &NBTSerializeOptions::WithHeader(""));
+                            writer
+                                .insert(x, z, &buf, timestamp)
+                                .map_err(WorldError::AnvilEncodeError)?;
+                        }
+                        Err(e) => {
+                            error!("Failed to export chunk {}, {}: {}", x, z, e);
+                        }
+                    }
+                    progress.inc(1);
+                }
+
+                if writer.is_empty() {
+                    return Ok(());
+                }
+                let dir = region_dir(&export_dir, &dimension);
+                std::fs::create_dir_all(&dir)?;
+                writer
+                    .write(dir.join(format!("r.{region_x}.{region_z}.mca")))
+                    .map_err(WorldError::AnvilEncodeError)
+            },
+        )?;
+
+        progress.finish();
+
+        info!(
+            "Exported {} chunks in {:?}",
+            progress.position(),
+            start.elapsed()
+        );
+
+        Ok(())
+    }
+}
+
+fn check_export_path(export_dir: &Path) -> Result<(), WorldError> {
+    if export_dir.is_file() {
+        return Err(WorldError::InvalidExportPath(
+            export_dir.display().to_string(),
+        ));
+    }
+
+    for dimension in ["overworld", "nether", "end"] {
+        let dir = region_dir(export_dir, dimension);
+        if let Ok(mut entries) = dir.read_dir() {
+            if entries.next().is_some() {
+                error!(
+                    "{} already contains region files, refusing to overwrite them",
+                    dir.display()
+                );
+                return Err(WorldError::InvalidExportPath(
+                    export_dir.display().to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_format::Chunk;
+    use ferrumc_net_codec::net_types::var_int::VarInt;
+
+    fn block(name: &str) -> BlockId {
+        BlockData {
+            name: name.to_string(),
+            properties: None,
+        }
+        .to_block_id()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut chunk = Chunk::new(3, -2, "overworld".to_string());
+        let stone = block("minecraft:stone");
+        let torch = block("minecraft:torch");
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, -64, z, stone).unwrap();
+            }
+        }
+        chunk.set_block(4, 10, 7, torch).unwrap();
+        chunk
+            .set_section(2, BlockData::from_block_id(stone))
+            .unwrap();
+        chunk.relight(true);
+        let mut custom_name = vec![8];
+        "CustomName".serialize(&mut custom_name, &NBTSerializeOptions::None);
+        "\"Loot\"".serialize(&mut custom_name, &NBTSerializeOptions::None);
+        let nbt = vanilla_chunk_format::root_compound(&custom_name);
+        chunk.set_block_entity(1, (-3i16) as u16, 2, VarInt::from(1), nbt);
+
+        let vanilla = chunk.to_vanilla_format().unwrap();
+        let mut buf = Vec::new();
+        vanilla.serialize(&mut buf, &NBTSerializeOptions::WithHeader(""));
+
+        let parsed = VanillaChunk::from_bytes(&buf).unwrap();
+        assert_eq!((parsed.x_pos, parsed.z_pos), (3, -2));
+        let block_entities = parsed.block_entities.as_ref().unwrap();
+        assert_eq!(block_entities.len(), 1);
+        assert_eq!(block_entities[0].id, "minecraft:chest");
+        assert_eq!(
+            (
+                block_entities[0].x,
+                block_entities[0].y,
+                block_entities[0].z
+            ),
+            (49, -3, -30)
+        );
+
+        let imported = parsed.to_custom_format().unwrap();
+        for x in 0..16 {
+            for z in 0..16 {
+                assert_eq!(imported.get_block(x, -64, z).unwrap(), stone);
+            }
+        }
+        assert_eq!(imported.get_block(4, 10, 7).unwrap(), torch);
+        assert_eq!(imported.get_block(4, 11, 7).unwrap(), BlockId::default());
+        assert_eq!(imported.get_block(0, 40, 0).unwrap(), stone);
+        assert_eq!(imported.block_entities, chunk.block_entities);
+        for section in &chunk.sections {
+            let other = imported.sections.iter().find(|s| s.y == section.y).unwrap();
+            assert_eq!(section.block_light, other.block_light);
+            assert_eq!(section.sky_light, other.sky_light);
+        }
+    }
+
+    #[test]
+    fn test_repacks_palette() {
+        let values: Vec<u64> = (0..4096).map(|i| i % 20).collect();
+        let packed = pack(5, &values);
+        assert_eq!(packed.len(), 4096usize.div_ceil(12));
+        assert_eq!(unpack(5, &packed, 4096), values);
+    }
+}
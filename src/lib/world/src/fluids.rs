@@ -0,0 +1,835 @@
+//! Water and lava flowing.
+//!
+//! Fluids keep how much of them is in a block in the `level` property: 0 is a source, 1 to 7 is
+//! flowing fluid that gets thinner the higher it goes, and 8 and above is fluid falling down.
+//! Whenever a fluid block changes or something next to it does, it gets a scheduled tick, and
+//! each tick works out how much fluid the block should hold from its neighbours and then spreads
+//! it further. Neighbours are always reached through the world, so fluid flows into the next
+//! chunk the same way it flows inside one.
+//!
+//! The blocks fluids change are collected in [`FluidEdits`] while the ticks run and written a
+//! chunk at a time afterwards, so a tick that changes lots of fluid doesn't relight and save the
+//! chunk for every block of it. Lava hardening because of a change is a tick of its own too.
+
+use crate::block_id::{BlockId, Level};
+use crate::chunk_format::Chunk;
+use crate::dimensions::DimensionType;
+use crate::errors::WorldError;
+use crate::tick::{priority, BlockPos, ScheduledTick, TickManager};
+use crate::World;
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap};
+
+lazy_static! {
+    static ref WATER: BlockId = BlockId::from_name("minecraft:water").unwrap_or_default();
+    static ref LAVA: BlockId = BlockId::from_name("minecraft:lava").unwrap_or_default();
+    static ref STONE: BlockId = BlockId::from_name("minecraft:stone").unwrap_or_default();
+    static ref COBBLESTONE: BlockId =
+        BlockId::from_name("minecraft:cobblestone").unwrap_or_default();
+    static ref OBSIDIAN: BlockId = BlockId::from_name("minecraft:obsidian").unwrap_or_default();
+}
+
+const HORIZONTAL: [(i32, i32); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+/// The distance to a drop when there isn't one in reach.
+const NO_DROP: u32 = 1000;
+
+/// Blocks fluid washes away when it flows into them, besides air.
+const REPLACEABLE: &[&str] = &[
+    "short_grass",
+    "grass",
+    "fern",
+    "tall_grass",
+    "large_fern",
+    "dead_bush",
+    "vine",
+    "dandelion",
+    "poppy",
+    "blue_orchid",
+    "allium",
+    "azure_bluet",
+    "red_tulip",
+    "orange_tulip",
+    "white_tulip",
+    "pink_tulip",
+    "oxeye_daisy",
+    "cornflower",
+    "lily_of_the_valley",
+    "sunflower",
+    "lilac",
+    "rose_bush",
+    "peony",
+    "brown_mushroom",
+    "red_mushroom",
+    "torch",
+    "wall_torch",
+    "redstone_wire",
+    "fire",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fluid {
+    Water,
+    Lava,
+}
+
+impl Fluid {
+    /// The fluid a block is made of, if any.
+    pub fn of(block: BlockId) -> Option<Fluid> {
+        let first = block.first_state()?;
+        if first == *WATER {
+            Some(Fluid::Water)
+        } else if first == *LAVA {
+            Some(Fluid::Lava)
+        } else {
+            None
+        }
+    }
+
+    /// How many game ticks it takes the fluid to flow one block further.
+    ///
+    /// Lava is quicker in dimensions that are hot enough, see [`DimensionType::ultrawarm`].
+    pub fn tick_delay(self, dimension: &DimensionType) -> u32 {
+        match self {
+            Fluid::Water => 5,
+            Fluid::Lava if dimension.ultrawarm() => 10,
+            Fluid::Lava => 30,
+        }
+    }
+
+    /// How much thinner the fluid gets with every block it flows sideways.
+    fn drop_off(self, dimension: &DimensionType) -> u8 {
+        match self {
+            Fluid::Lava if !dimension.ultrawarm() => 2,
+            _ => 1,
+        }
+    }
+
+    /// How many blocks away the fluid can see a drop it'll flow towards.
+    fn slope_distance(self, dimension: &DimensionType) -> u32 {
+        match self {
+            Fluid::Lava if !dimension.ultrawarm() => 2,
+            _ => 4,
+        }
+    }
+
+    /// Only water turns into a source between two others.
+    fn forms_sources(self) -> bool {
+        self == Fluid::Water
+    }
+}
+
+/// A fluid and how much of it is in a block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FluidState {
+    pub fluid: Fluid,
+    /// The block's `level` property.
+    pub level: u8,
+}
+
+impl FluidState {
+    pub fn of(block: BlockId) -> Option<Self> {
+        Some(FluidState {
+            fluid: Fluid::of(block)?,
+            level: block.get::<Level>().map_or(0, |level| level.0),
+        })
+    }
+
+    pub fn source(fluid: Fluid) -> Self {
+        FluidState { fluid, level: 0 }
+    }
+
+    pub fn falling(fluid: Fluid) -> Self {
+        FluidState { fluid, level: 8 }
+    }
+
+    /// Flowing fluid with `amount` from 1 to 7.
+    pub fn flowing(fluid: Fluid, amount: u8) -> Self {
+        FluidState {
+            fluid,
+            level: 8 - amount.clamp(1, 7),
+        }
+    }
+
+    pub fn is_source(self) -> bool {
+        self.level == 0
+    }
+
+    pub fn is_falling(self) -> bool {
+        self.level >= 8
+    }
+
+    /// How much fluid there is, from 1 to 8. Sources and falling fluid are full.
+    pub fn amount(self) -> u8 {
+        if self.is_source() || self.is_falling() {
+            8
+        } else {
+            8 - self.level
+        }
+    }
+
+    /// How high the fluid stands in its block, as a fraction of the block.
+    pub fn height(self) -> f64 {
+        self.amount() as f64 / 9.0
+    }
+
+    pub fn block(self) -> BlockId {
+        let block = match self.fluid {
+            Fluid::Water => *WATER,
+            Fluid::Lava => *LAVA,
+        };
+        block.with(Level(self.level)).unwrap_or(block)
+    }
+}
+
+/// The delay a scheduled tick for a block should have if it's a fluid.
+pub(crate) fn tick_delay(world: &World, block: BlockId, dimension: &str) -> Option<u32> {
+    let fluid = Fluid::of(block)?;
+    let dimension = world.dimensions().get(dimension)?;
+    Some(fluid.tick_delay(&dimension.dimension_type))
+}
+
+/// Blocks fluids changed that haven't been written to their chunks yet, by chunk.
+#[derive(Default)]
+pub(crate) struct FluidEdits {
+    chunks: BTreeMap<(i32, i32, String), HashMap<(i32, i32, i32), BlockId>>,
+}
+
+impl FluidEdits {
+    fn get(&self, pos: &BlockPos) -> Option<BlockId> {
+        let key = (pos.x >> 4, pos.z >> 4, pos.dimension.clone());
+        self.chunks.get(&key)?.get(&(pos.x, pos.y, pos.z)).copied()
+    }
+
+    fn set(&mut self, pos: &BlockPos, block: BlockId) {
+        let key = (pos.x >> 4, pos.z >> 4, pos.dimension.clone());
+        self.chunks
+            .entry(key)
+            .or_default()
+            .insert((pos.x, pos.y, pos.z), block);
+    }
+
+    /// Write the changed blocks with one [`crate::edit_batch::EditBatch`] per chunk, updating
+    /// the light around them, and let redstone next to them know. Fluids scheduled the ticks
+    /// the changes cause themselves, which [`World::block_updated`] would try to do again while
+    /// the scheduler is locked.
+    pub(crate) fn apply(&mut self, world: &World) -> Result<(), WorldError> {
+        for ((chunk_x, chunk_z, dimension), blocks) in std::mem::take(&mut self.chunks) {
+            let mut chunk = match world.load_chunk_owned(chunk_x, chunk_z, &dimension) {
+                Ok(chunk) => chunk,
+                // Deleted since the fluid flowed into it
+                Err(WorldError::ChunkNotFound) => continue,
+                Err(e) => return Err(e),
+            };
+            chunk.mark_modified();
+            let blocks = blocks.into_iter().collect::<Vec<_>>();
+            world.set_blocks_and_relight(chunk, &blocks)?;
+            // Water washing dust or a torch away breaks the circuit it was in
+            let mut redstone = world.redstone.lock().unwrap();
+            for ((x, y, z), _) in &blocks {
+                redstone.block_changed(&dimension, (*x, *y, *z));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether fluid can flow into a block without anything but plants and the like being in its
+/// way, which it washes away.
+fn is_open(block: BlockId) -> bool {
+    match block.name() {
+        Some("minecraft:air" | "minecraft:cave_air" | "minecraft:void_air") => true,
+        Some(name) => name
+            .strip_prefix("minecraft:")
+            .is_some_and(|name| name.ends_with("_sapling") || REPLACEABLE.contains(&name)),
+        None => false,
+    }
+}
+
+/// The block at a position, or None if it's outside the world or its chunk doesn't exist yet.
+/// Fluid treats both like a wall. Blocks fluid changed but hasn't written yet are seen too.
+fn block_at(
+    world: &World,
+    edits: &FluidEdits,
+    pos: &BlockPos,
+) -> Result<Option<BlockId>, WorldError> {
+    if let Some(block) = edits.get(pos) {
+        return Ok(Some(block));
+    }
+    match world.get_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension) {
+        Ok(block) => Ok(Some(block)),
+        Err(WorldError::ChunkNotFound | WorldError::SectionOutOfBounds(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn fluid_at(
+    world: &World,
+    edits: &FluidEdits,
+    pos: &BlockPos,
+) -> Result<Option<FluidState>, WorldError> {
+    Ok(block_at(world, edits, pos)?.and_then(FluidState::of))
+}
+
+fn offset(pos: &BlockPos, dx: i32, dy: i32, dz: i32) -> BlockPos {
+    BlockPos {
+        x: pos.x + dx,
+        y: pos.y + dy,
+        z: pos.z + dz,
+        dimension: pos.dimension.clone(),
+    }
+}
+
+/// Change a block, then schedule the fluids around it so they can react. The change is written
+/// to its chunk by [`FluidEdits::apply`].
+fn set_block(
+    world: &World,
+    tm: &mut TickManager,
+    edits: &mut FluidEdits,
+    pos: &BlockPos,
+    block: BlockId,
+    dimension: &DimensionType,
+) -> Result<(), WorldError> {
+    edits.set(pos, block);
+
+    let around = [
+        (0, 0, 0),
+        (0, -1, 0),
+        (0, 1, 0),
+        (0, 0, -1),
+        (0, 0, 1),
+        (-1, 0, 0),
+        (1, 0, 0),
+    ];
+    for (dx, dy, dz) in around {
+        let neighbour = offset(pos, dx, dy, dz);
+        let Some(state) = fluid_at(world, edits, &neighbour)? else {
+            continue;
+        };
+        // Lava the change made touch water hardens on the next game tick, rather than changing
+        // more blocks from in here
+        let hardens = state.fluid == Fluid::Lava && touches_water(world, edits, &neighbour)?;
+        if hardens || !tm.is_scheduled(&neighbour) {
+            tm.schedule(ScheduledTick {
+                pos: neighbour,
+                block: state.block(),
+                delay: if hardens {
+                    1
+                } else {
+                    state.fluid.tick_delay(dimension)
+                },
+                priority: priority::NORMAL,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Whether lava at `pos` touches water. Water below lava doesn't count, the lava flows into it
+/// and makes stone instead.
+fn touches_water(world: &World, edits: &FluidEdits, pos: &BlockPos) -> Result<bool, WorldError> {
+    for (dx, dy, dz) in [(0, 1, 0), (0, 0, -1), (0, 0, 1), (-1, 0, 0), (1, 0, 0)] {
+        let neighbour = fluid_at(world, edits, &offset(pos, dx, dy, dz))?;
+        if neighbour.is_some_and(|n| n.fluid == Fluid::Water) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Turn lava touching water into obsidian if it's a source, cobblestone otherwise.
+fn harden_lava(
+    world: &World,
+    tm: &mut TickManager,
+    edits: &mut FluidEdits,
+    pos: &BlockPos,
+    state: FluidState,
+    dimension: &DimensionType,
+) -> Result<bool, WorldError> {
+    if !touches_water(world, edits, pos)? {
+        return Ok(false);
+    }
+    let block = if state.is_source() {
+        *OBSIDIAN
+    } else {
+        *COBBLESTONE
+    };
+    set_block(world, tm, edits, pos, block, dimension)?;
+    Ok(true)
+}
+
+/// Run a scheduled tick for the fluid at `pos`. The blocks it changes go in `edits`.
+pub(crate) fn tick_fluid(
+    world: &World,
+    tm: &mut TickManager,
+    edits: &mut FluidEdits,
+    pos: &BlockPos,
+) -> Result<(), WorldError> {
+    // The block may have changed since the tick was scheduled
+    let Some(mut state) = fluid_at(world, edits, pos)? else {
+        return Ok(());
+    };
+    let Some(dimension) = world.dimensions().get(&pos.dimension) else {
+        return Ok(());
+    };
+    let dimension = &dimension.dimension_type;
+
+    if state.fluid == Fluid::Lava && harden_lava(world, tm, edits, pos, state, dimension)? {
+        return Ok(());
+    }
+
+    if !state.is_source() {
+        match new_state(world, edits, pos, state.fluid, dimension)? {
+            None => {
+                let air = BlockId::default();
+                return set_block(world, tm, edits, pos, air, dimension);
+            }
+            Some(new) if new != state => {
+                set_block(world, tm, edits, pos, new.block(), dimension)?;
+                state = new;
+            }
+            Some(_) => {}
+        }
+    }
+    spread(world, tm, edits, pos, state, dimension)
+}
+
+/// How much fluid a block that isn't a source should hold, going by the blocks around it. None
+/// if it should dry up.
+fn new_state(
+    world: &World,
+    edits: &FluidEdits,
+    pos: &BlockPos,
+    fluid: Fluid,
+    dimension: &DimensionType,
+) -> Result<Option<FluidState>, WorldError> {
+    let mut most = 0;
+    let mut sources = 0;
+    for (dx, dz) in HORIZONTAL {
+        let Some(neighbour) = fluid_at(world, edits, &offset(pos, dx, 0, dz))? else {
+            continue;
+        };
+        if neighbour.fluid != fluid {
+            continue;
+        }
+        if neighbour.is_source() {
+            sources += 1;
+        }
+        most = most.max(neighbour.amount());
+    }
+
+    if sources >= 2 && fluid.forms_sources() {
+        // A new source needs something to rest on
+        let below = block_at(world, edits, &offset(pos, 0, -1, 0))?;
+        let supported = below.is_some_and(|below| match FluidState::of(below) {
+            Some(state) => state.fluid == fluid && state.is_source(),
+            None => !is_open(below),
+        });
+        if supported {
+            return Ok(Some(FluidState::source(fluid)));
+        }
+    }
+
+    let above = fluid_at(world, edits, &offset(pos, 0, 1, 0))?;
+    if above.is_some_and(|above| above.fluid == fluid) {
+        return Ok(Some(FluidState::falling(fluid)));
+    }
+
+    let amount = most.saturating_sub(fluid.drop_off(dimension));
+    Ok((amount > 0).then(|| FluidState::flowing(fluid, amount)))
+}
+
+/// Whether fluid can go down from a block into the one below it, which is the case if it's open
+/// or already holds the same fluid.
+fn is_drop(
+    world: &World,
+    edits: &FluidEdits,
+    below: &BlockPos,
+    fluid: Fluid,
+) -> Result<bool, WorldError> {
+    Ok(match block_at(world, edits, below)? {
+        Some(block) => {
+            is_open(block) || FluidState::of(block).is_some_and(|state| state.fluid == fluid)
+        }
+        None => false,
+    })
+}
+
+/// Let the fluid fall if it can, otherwise flow sideways.
+fn spread(
+    world: &World,
+    tm: &mut TickManager,
+    edits: &mut FluidEdits,
+    pos: &BlockPos,
+    state: FluidState,
+    dimension: &DimensionType,
+) -> Result<(), WorldError> {
+    let below = offset(pos, 0, -1, 0);
+    let below_block = block_at(world, edits, &below)?;
+    if below_block.is_some_and(is_open) {
+        let falling = FluidState::falling(state.fluid).block();
+        set_block(world, tm, edits, &below, falling, dimension)?;
+        if count_sources(world, edits, pos, state.fluid)? >= 3 {
+            spread_sideways(world, tm, edits, pos, state, dimension)?;
+        }
+        Ok(())
+    } else if state.fluid == Fluid::Lava && below_block.and_then(Fluid::of) == Some(Fluid::Water) {
+        set_block(world, tm, edits, &below, *STONE, dimension)
+    } else if state.is_source() || !is_drop(world, edits, &below, state.fluid)? {
+        spread_sideways(world, tm, edits, pos, state, dimension)
+    } else {
+        Ok(())
+    }
+}
+
+fn count_sources(
+    world: &World,
+    edits: &FluidEdits,
+    pos: &BlockPos,
+    fluid: Fluid,
+) -> Result<usize, WorldError> {
+    let mut sources = 0;
+    for (dx, dz) in HORIZONTAL {
+        if fluid_at(world, edits, &offset(pos, dx, 0, dz))?
+            .is_some_and(|state| state.fluid == fluid && state.is_source())
+        {
+            sources += 1;
+        }
+    }
+    Ok(sources)
+}
+
+fn spread_sideways(
+    world: &World,
+    tm: &mut TickManager,
+    edits: &mut FluidEdits,
+    pos: &BlockPos,
+    state: FluidState,
+    dimension: &DimensionType,
+) -> Result<(), WorldError> {
+    let amount = if state.is_falling() {
+        7
+    } else {
+        state
+            .amount()
+            .saturating_sub(state.fluid.drop_off(dimension))
+    };
+    if amount == 0 {
+        return Ok(());
+    }
+    let flowing = FluidState::flowing(state.fluid, amount).block();
+    for (dx, dz) in flow_directions(world, edits, pos, state.fluid, dimension)? {
+        let target = offset(pos, dx, 0, dz);
+        if block_at(world, edits, &target)?.is_some_and(is_open) {
+            set_block(world, tm, edits, &target, flowing, dimension)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether fluid could flow through a block while looking for a drop.
+fn can_pass(
+    world: &World,
+    edits: &FluidEdits,
+    pos: &BlockPos,
+    fluid: Fluid,
+) -> Result<bool, WorldError> {
+    Ok(match block_at(world, edits, pos)? {
+        Some(block) => {
+            is_open(block)
+                || FluidState::of(block).is_some_and(|s| s.fluid == fluid && !s.is_source())
+        }
+        None => false,
+    })
+}
+
+/// The sideways directions fluid flows in, which are the ones with the nearest drop. If there's
+/// no drop close enough it flows every way it can.
+fn flow_directions(
+    world: &World,
+    edits: &FluidEdits,
+    pos: &BlockPos,
+    fluid: Fluid,
+    dimension: &DimensionType,
+) -> Result<Vec<(i32, i32)>, WorldError> {
+    let limit = fluid.slope_distance(dimension);
+    let mut best = NO_DROP;
+    let mut directions = Vec::new();
+    for (dx, dz) in HORIZONTAL {
+        let next = offset(pos, dx, 0, dz);
+        if !can_pass(world, edits, &next, fluid)? {
+            continue;
+        }
+        let distance = if is_drop(world, edits, &offset(&next, 0, -1, 0), fluid)? {
+            0
+        } else {
+            distance_to_drop(world, edits, &next, (-dx, -dz), 1, limit, fluid)?
+        };
+        if distance < best {
+            best = distance;
+            directions.clear();
+        }
+        if distance == best {
+            directions.push((dx, dz));
+        }
+    }
+    Ok(directions)
+}
+
+/// How many blocks it is from `pos` to the nearest drop, not going back the way it came.
+fn distance_to_drop(
+    world: &World,
+    edits: &FluidEdits,
+    pos: &BlockPos,
+    came_from: (i32, i32),
+    depth: u32,
+    limit: u32,
+    fluid: Fluid,
+) -> Result<u32, WorldError> {
+    let mut best = NO_DROP;
+    for (dx, dz) in HORIZONTAL {
+        if (dx, dz) == came_from {
+            continue;
+        }
+        let next = offset(pos, dx, 0, dz);
+        if !can_pass(world, edits, &next, fluid)? {
+            continue;
+        }
+        if is_drop(world, edits, &offset(&next, 0, -1, 0), fluid)? {
+            return Ok(depth);
+        }
+        if depth < limit {
+            best = best.min(distance_to_drop(
+                world,
+                edits,
+                &next,
+                (-dx, -dz),
+                depth + 1,
+                limit,
+                fluid,
+            )?);
+        }
+    }
+    Ok(best)
+}
+
+/// Which way the fluid at a block pushes things in it, as a vector no longer than 1. None if
+/// there's no fluid there.
+///
+/// Neighbours are read through the world, so the blocks in the next chunk count at a chunk's
+/// edges. Ones that aren't loaded or generated are left out.
+pub fn flow(world: &World, x: i32, y: i32, z: i32, dimension: &str) -> Option<(f64, f64, f64)> {
+    flow_with(x, y, z, |x, y, z| {
+        world.get_block_and_fetch(x, y, z, dimension).ok()
+    })
+}
+
+/// Like [`flow`], when only `chunk` and the chunks around it are at hand. At its edges the blocks
+/// in whichever of `neighbours` is next to it count, blocks in a chunk that isn't there are left
+/// out.
+pub fn flow_in_chunk(
+    chunk: &Chunk,
+    neighbours: &[&Chunk],
+    x: i32,
+    y: i32,
+    z: i32,
+) -> Option<(f64, f64, f64)> {
+    flow_with(x, y, z, |x, y, z| {
+        std::iter::once(chunk)
+            .chain(neighbours.iter().copied())
+            .find(|chunk| x >> 4 == chunk.x && z >> 4 == chunk.z)?
+            .get_block(x, y, z)
+            .ok()
+    })
+}
+
+fn flow_with(
+    x: i32,
+    y: i32,
+    z: i32,
+    block_at: impl Fn(i32, i32, i32) -> Option<BlockId>,
+) -> Option<(f64, f64, f64)> {
+    let state = FluidState::of(block_at(x, y, z)?)?;
+    // How high a block's fluid stands, if it's the same fluid or none at all
+    let height = |block: BlockId| match FluidState::of(block) {
+        Some(other) if other.fluid == state.fluid => Some(other.height()),
+        Some(_) => None,
+        None => Some(0.0),
+    };
+
+    let (mut fx, mut fz) = (0.0, 0.0);
+    for (dx, dz) in HORIZONTAL {
+        let Some(block) = block_at(x + dx, y, z + dz) else {
+            continue;
+        };
+        let Some(neighbour) = height(block) else {
+            continue;
+        };
+        let difference = if neighbour > 0.0 {
+            state.height() - neighbour
+        } else if is_open(block) {
+            // Fluid running off an edge pulls towards the fluid below it
+            match block_at(x + dx, y - 1, z + dz).and_then(height) {
+                Some(below) if below > 0.0 => state.height() - (below - 8.0 / 9.0),
+                _ => 0.0,
+            }
+        } else {
+            0.0
+        };
+        fx += dx as f64 * difference;
+        fz += dz as f64 * difference;
+    }
+
+    let mut fy = 0.0;
+    if state.is_falling() {
+        let against_wall = HORIZONTAL.iter().any(|(dx, dz)| {
+            [y, y + 1].iter().any(|y| {
+                block_at(x + dx, *y, z + dz)
+                    .is_some_and(|block| !is_open(block) && Fluid::of(block).is_none())
+            })
+        });
+        if against_wall {
+            (fx, fz) = normalize(fx, 0.0, fz).map_or((0.0, 0.0), |(x, _, z)| (x, z));
+            fy = -6.0;
+        }
+    }
+    Some(normalize(fx, fy, fz).unwrap_or((0.0, 0.0, 0.0)))
+}
+
+fn normalize(x: f64, y: f64, z: f64) -> Option<(f64, f64, f64)> {
+    let length = (x * x + y * y + z * z).sqrt();
+    (length > 1e-4).then_some((x / length, y / length, z / length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{memory_world, tick};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_levels() {
+        let source = FluidState::source(Fluid::Water);
+        assert_eq!(FluidState::of(source.block()), Some(source));
+        assert_eq!(source.amount(), 8);
+
+        let flowing = FluidState::flowing(Fluid::Lava, 3);
+        assert_eq!(flowing.level, 5);
+        assert_eq!(FluidState::of(flowing.block()), Some(flowing));
+        assert_eq!(flowing.amount(), 3);
+        assert!(!flowing.is_source() && !flowing.is_falling());
+
+        let falling = FluidState::falling(Fluid::Water);
+        assert!(falling.is_falling());
+        assert_eq!(falling.amount(), 8);
+        assert_eq!(Fluid::of(BlockId::default()), None);
+    }
+
+    #[test]
+    fn test_lava_is_faster_where_its_hot() {
+        let overworld = DimensionType::overworld();
+        let nether = DimensionType::nether();
+        assert_eq!(Fluid::Water.tick_delay(&overworld), 5);
+        assert_eq!(Fluid::Lava.tick_delay(&overworld), 30);
+        assert_eq!(Fluid::Lava.tick_delay(&nether), 10);
+        assert_eq!(Fluid::Lava.drop_off(&overworld), 2);
+        assert_eq!(Fluid::Lava.drop_off(&nether), 1);
+    }
+
+    #[test]
+    fn test_plants_are_washed_away() {
+        for name in ["air", "short_grass", "oak_sapling", "poppy", "torch"] {
+            let block = BlockId::from_name(&format!("minecraft:{name}")).unwrap();
+            assert!(is_open(block), "{name}");
+        }
+        for name in ["stone", "oak_planks", "water"] {
+            let block = BlockId::from_name(&format!("minecraft:{name}")).unwrap();
+            assert!(!is_open(block), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_flow_goes_downhill() {
+        let mut chunk = Chunk::new(0, 0, "overworld".to_string());
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        for x in 0..4 {
+            chunk.set_block(x, 0, 0, stone).unwrap();
+        }
+        chunk
+            .set_block(1, 1, 0, FluidState::source(Fluid::Water).block())
+            .unwrap();
+        chunk
+            .set_block(2, 1, 0, FluidState::flowing(Fluid::Water, 7).block())
+            .unwrap();
+        chunk
+            .set_block(3, 1, 0, FluidState::flowing(Fluid::Water, 6).block())
+            .unwrap();
+
+        let (x, y, z) = flow_in_chunk(&chunk, &[], 2, 1, 0).unwrap();
+        assert!(x > 0.0);
+        assert_eq!(y, 0.0);
+        assert_eq!(z, 0.0);
+        assert_eq!(flow_in_chunk(&chunk, &[], 0, 1, 0), None);
+
+        // Water coming from the next chunk over pushes away from it
+        let mut next = Chunk::new(-1, 0, "overworld".to_string());
+        next.set_block(-1, 0, 0, stone).unwrap();
+        next.set_block(-1, 1, 0, FluidState::source(Fluid::Water).block())
+            .unwrap();
+        chunk
+            .set_block(0, 1, 0, FluidState::flowing(Fluid::Water, 7).block())
+            .unwrap();
+        chunk
+            .set_block(1, 1, 0, FluidState::flowing(Fluid::Water, 7).block())
+            .unwrap();
+        assert_eq!(flow_in_chunk(&chunk, &[], 0, 1, 0), Some((0.0, 0.0, 0.0)));
+        let (x, _, _) = flow_in_chunk(&chunk, &[&next], 0, 1, 0).unwrap();
+        assert!(x > 0.0);
+    }
+
+    #[test]
+    fn test_fluids_flow_across_chunks_and_harden() {
+        let (world, _) = memory_world([(-1, 0), (0, 0)]);
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        for chunk_x in -1..1 {
+            let mut chunk = (*world.load_chunk(chunk_x, 0, "overworld").unwrap()).clone();
+            for x in 0..16 {
+                for z in 0..16 {
+                    chunk.set_block(x, 63, z, stone).unwrap();
+                }
+            }
+            world.save_chunk(Arc::new(chunk)).unwrap();
+        }
+
+        let source = FluidState::source(Fluid::Water);
+        world
+            .set_block_and_fetch(0, 64, 0, "overworld", source.block())
+            .unwrap();
+        tick(&world, 100);
+        let water_at =
+            |x: i32| FluidState::of(world.get_block_and_fetch(x, 64, 0, "overworld").unwrap());
+        // Over the chunk border and thinning out with every block
+        assert_eq!(water_at(-1), Some(FluidState::flowing(Fluid::Water, 7)));
+        assert_eq!(water_at(-3), Some(FluidState::flowing(Fluid::Water, 5)));
+        assert_eq!(water_at(7), Some(FluidState::flowing(Fluid::Water, 1)));
+        assert_eq!(water_at(8), None);
+
+        // Lava next to water turns to obsidian, and flowing onto it turns the water to stone
+        let lava = FluidState::source(Fluid::Lava).block();
+        world
+            .set_block_and_fetch(5, 64, 0, "overworld", lava)
+            .unwrap();
+        world
+            .set_block_and_fetch(-3, 65, 0, "overworld", lava)
+            .unwrap();
+        tick(&world, 100);
+        assert_eq!(
+            world.get_block_and_fetch(5, 64, 0, "overworld").unwrap(),
+            BlockId::from_name("minecraft:obsidian").unwrap()
+        );
+        assert_eq!(
+            world.get_block_and_fetch(-3, 64, 0, "overworld").unwrap(),
+            stone
+        );
+    }
+}
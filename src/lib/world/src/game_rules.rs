@@ -0,0 +1,101 @@
+//! Game rules, the world-wide gameplay settings changed with `/gamerule`.
+//!
+//! Only rules that were changed from their defaults are saved, in the `game_rules` table, by their
+//! vanilla name. That way rules can be added later without breaking saved worlds.
+
+use crate::errors::WorldError;
+use crate::World;
+use bitcode_derive::{Decode, Encode};
+use ferrumc_storage::backend::StorageBackend;
+use std::collections::BTreeMap;
+
+const TABLE: &str = "game_rules";
+const KEY: u128 = 0;
+
+/// How many blocks in each section get a random tick every game tick. 0 turns random ticks off.
+pub const RANDOM_TICK_SPEED: &str = "randomTickSpeed";
+
+/// Every rule the server knows about, with its default. They're all whole numbers for now.
+pub const RULES: &[(&str, i32)] = &[(RANDOM_TICK_SPEED, 3)];
+
+#[derive(Encode, Decode, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameRules {
+    /// The rules that were changed from their defaults.
+    values: BTreeMap<String, i32>,
+}
+
+impl GameRules {
+    /// A rule's value, or `None` if there's no such rule.
+    pub fn get(&self, rule: &str) -> Option<i32> {
+        let (_, default) = RULES.iter().find(|(name, _)| *name == rule)?;
+        Some(self.values.get(rule).copied().unwrap_or(*default))
+    }
+
+    pub fn set(&mut self, rule: &str, value: i32) -> Result<(), WorldError> {
+        if !RULES.iter().any(|(name, _)| *name == rule) {
+            return Err(WorldError::UnknownGameRule(rule.to_string()));
+        }
+        self.values.insert(rule.to_string(), value);
+        Ok(())
+    }
+
+    /// See [`RANDOM_TICK_SPEED`]. Negative speeds count as 0.
+    pub fn random_tick_speed(&self) -> u32 {
+        self.get(RANDOM_TICK_SPEED).unwrap_or_default().max(0) as u32
+    }
+
+    /// Read the rules saved in a storage backend, or the defaults if none have been saved yet.
+    pub(crate) fn load(storage_backend: &dyn StorageBackend) -> Result<Self, WorldError> {
+        if !storage_backend.table_exists(TABLE.to_string())? {
+            return Ok(GameRules::default());
+        }
+        match storage_backend.get(TABLE.to_string(), KEY)? {
+            Some(data) => {
+                bitcode::decode(&data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))
+            }
+            None => Ok(GameRules::default()),
+        }
+    }
+}
+
+impl World {
+    pub fn game_rules(&self) -> GameRules {
+        self.game_rules.lock().unwrap().clone()
+    }
+
+    /// Change a game rule and save it.
+    pub fn set_game_rule(&self, rule: &str, value: i32) -> Result<(), WorldError> {
+        let mut game_rules = self.game_rules.lock().unwrap();
+        game_rules.set(rule, value)?;
+        if !self.storage_backend.table_exists(TABLE.to_string())? {
+            self.storage_backend.create_table(TABLE.to_string())?;
+        }
+        self.storage_backend
+            .upsert(TABLE.to_string(), KEY, bitcode::encode(&*game_rules))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unchanged_rules_use_their_default() {
+        let mut rules = GameRules::default();
+        assert_eq!(rules.get(RANDOM_TICK_SPEED), Some(3));
+        rules.set(RANDOM_TICK_SPEED, -5).unwrap();
+        assert_eq!(rules.get(RANDOM_TICK_SPEED), Some(-5));
+        assert_eq!(rules.random_tick_speed(), 0);
+    }
+
+    #[test]
+    fn test_unknown_rules_are_rejected() {
+        let mut rules = GameRules::default();
+        assert_eq!(rules.get("doFireTick"), None);
+        assert!(matches!(
+            rules.set("doFireTick", 0),
+            Err(WorldError::UnknownGameRule(rule)) if rule == "doFireTick"
+        ));
+    }
+}
@@ -0,0 +1,458 @@
+//! Heightmaps, the highest block in every column of a chunk that matches some condition.
+//!
+//! Every heightmap stores 256 heights, one per column in `z << 4 | x` order, packed the same way
+//! as vanilla: as many bits as it takes to store the height of the world plus one, with entries
+//! never spanning two longs. A height is the number of blocks between the bottom of the world and
+//! the top of the highest matching block, so 0 means the column has no matching block at all.
+//!
+//! [`Chunk::set_block`] and the other ways of editing a chunk keep them up to date, so they're
+//! never more than a lookup away. [`Chunk::recalculate_heightmaps`] rebuilds them from scratch.
+
+use crate::block_id::{BlockId, ID2BLOCK};
+use crate::chunk_format::{Chunk, Heightmaps, PaletteType, Section};
+use crate::errors::WorldError;
+use crate::exporting::pack;
+use crate::light::unpack;
+use crate::vanilla_chunk_format::BlockData;
+use lazy_static::lazy_static;
+
+/// Anything other than air.
+const NOT_AIR: u8 = 1;
+/// Blocks that stop entities moving through them.
+const SOLID: u8 = 2;
+/// Fluids, and blocks that hold one.
+const FLUID: u8 = 4;
+const LEAVES: u8 = 8;
+
+/// Blocks that entities can move through, other than air.
+const NON_SOLID_BLOCKS: &[&str] = &[
+    "water",
+    "lava",
+    "bubble_column",
+    "light",
+    "structure_void",
+    "fire",
+    "soul_fire",
+    "cobweb",
+    "snow",
+    "nether_portal",
+    "end_portal",
+    "end_gateway",
+    "grass",
+    "fern",
+    "tall_grass",
+    "large_fern",
+    "dead_bush",
+    "seagrass",
+    "tall_seagrass",
+    "kelp",
+    "kelp_plant",
+    "sugar_cane",
+    "vine",
+    "glow_lichen",
+    "sculk_vein",
+    "hanging_roots",
+    "spore_blossom",
+    "small_dripleaf",
+    "big_dripleaf_stem",
+    "cave_vines",
+    "cave_vines_plant",
+    "twisting_vines",
+    "twisting_vines_plant",
+    "weeping_vines",
+    "weeping_vines_plant",
+    "nether_sprouts",
+    "crimson_roots",
+    "warped_roots",
+    "crimson_fungus",
+    "warped_fungus",
+    "brown_mushroom",
+    "red_mushroom",
+    "lily_pad",
+    "redstone_wire",
+    "tripwire",
+    "tripwire_hook",
+    "lever",
+    "ladder",
+    "rail",
+    "wheat",
+    "carrots",
+    "potatoes",
+    "beetroots",
+    "melon_stem",
+    "pumpkin_stem",
+    "attached_melon_stem",
+    "attached_pumpkin_stem",
+    "nether_wart",
+    "sweet_berry_bush",
+    "cocoa",
+    "bamboo_sapling",
+    "frogspawn",
+    "pink_petals",
+    "torchflower",
+    "torchflower_crop",
+    "pitcher_crop",
+    "pitcher_plant",
+    "dandelion",
+    "poppy",
+    "blue_orchid",
+    "allium",
+    "azure_bluet",
+    "oxeye_daisy",
+    "cornflower",
+    "lily_of_the_valley",
+    "wither_rose",
+    "sunflower",
+    "lilac",
+    "rose_bush",
+    "peony",
+    "flower_pot",
+];
+
+/// Suffixes of blocks that entities can move through, like `_sapling` or `_wall_torch`.
+const NON_SOLID_SUFFIXES: &[&str] = &[
+    "_sapling",
+    "_tulip",
+    "_button",
+    "_pressure_plate",
+    "_sign",
+    "_banner",
+    "_torch",
+    "_rail",
+    "_coral",
+    "_coral_fan",
+];
+
+/// Blocks that are always full of water, without needing a `waterlogged` property.
+const FLUID_BLOCKS: &[&str] = &[
+    "water",
+    "lava",
+    "bubble_column",
+    "seagrass",
+    "tall_seagrass",
+    "kelp",
+    "kelp_plant",
+];
+
+lazy_static! {
+    /// Which heightmaps every block state counts for, indexed by block ID.
+    static ref HEIGHTMAP_FLAGS: Vec<u8> = ID2BLOCK.iter().map(compute_flags).collect();
+}
+
+fn compute_flags(block: &BlockData) -> u8 {
+    let name = block.name.trim_start_matches("minecraft:");
+    if matches!(name, "air" | "cave_air" | "void_air") {
+        return 0;
+    }
+    let mut flags = NOT_AIR;
+    let waterlogged = block
+        .properties
+        .as_ref()
+        .and_then(|properties| properties.get("waterlogged"))
+        .is_some_and(|waterlogged| waterlogged == "true");
+    if waterlogged || FLUID_BLOCKS.contains(&name) {
+        flags |= FLUID;
+    }
+    let non_solid = NON_SOLID_BLOCKS.contains(&name)
+        || name.starts_with("potted_")
+        || NON_SOLID_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix));
+    if !non_solid {
+        flags |= SOLID;
+    }
+    if name.ends_with("_leaves") {
+        flags |= LEAVES;
+    }
+    flags
+}
+
+fn heightmap_flags(block: BlockId) -> u8 {
+    // Unknown IDs are treated as solid blocks, same as for light.
+    HEIGHTMAP_FLAGS
+        .get(block.0 as usize)
+        .copied()
+        .unwrap_or(NOT_AIR | SOLID)
+}
+
+/// The kinds of heightmap kept for every chunk, named after their vanilla counterparts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum HeightmapType {
+    /// The highest block that isn't air. Used for the world surface, e.g. where the sky is.
+    WorldSurface,
+    /// The highest block that stops movement, ignoring fluids. Used for structures.
+    OceanFloor,
+    /// The highest block that stops movement or holds a fluid. Used for rain and snow.
+    MotionBlocking,
+    /// Same as [`HeightmapType::MotionBlocking`], but ignoring leaves. Used for mob spawning.
+    MotionBlockingNoLeaves,
+}
+
+impl HeightmapType {
+    pub const ALL: [HeightmapType; 4] = [
+        HeightmapType::WorldSurface,
+        HeightmapType::OceanFloor,
+        HeightmapType::MotionBlocking,
+        HeightmapType::MotionBlockingNoLeaves,
+    ];
+
+    /// Whether the given block state counts for this heightmap.
+    pub fn matches(self, block: BlockId) -> bool {
+        self.matches_flags(heightmap_flags(block))
+    }
+
+    fn matches_flags(self, flags: u8) -> bool {
+        match self {
+            HeightmapType::WorldSurface => flags & NOT_AIR != 0,
+            HeightmapType::OceanFloor => flags & SOLID != 0,
+            HeightmapType::MotionBlocking => flags & (SOLID | FLUID) != 0,
+            HeightmapType::MotionBlockingNoLeaves => {
+                flags & (SOLID | FLUID) != 0 && flags & LEAVES == 0
+            }
+        }
+    }
+}
+
+impl Heightmaps {
+    pub fn get(&self, kind: HeightmapType) -> &Vec<i64> {
+        match kind {
+            HeightmapType::WorldSurface => &self.world_surface,
+            HeightmapType::OceanFloor => &self.ocean_floor,
+            HeightmapType::MotionBlocking => &self.motion_blocking,
+            HeightmapType::MotionBlockingNoLeaves => &self.motion_blocking_no_leaves,
+        }
+    }
+
+    fn get_mut(&mut self, kind: HeightmapType) -> &mut Vec<i64> {
+        match kind {
+            HeightmapType::WorldSurface => &mut self.world_surface,
+            HeightmapType::OceanFloor => &mut self.ocean_floor,
+            HeightmapType::MotionBlocking => &mut self.motion_blocking,
+            HeightmapType::MotionBlockingNoLeaves => &mut self.motion_blocking_no_leaves,
+        }
+    }
+}
+
+/// How many bits each height takes up in a world this many blocks tall.
+fn bits_per_height(world_height: i32) -> usize {
+    (u32::BITS - (world_height as u32).leading_zeros()).max(1) as usize
+}
+
+fn read_height(data: &[i64], bits: usize, column: usize) -> u64 {
+    let per_long = 64 / bits;
+    data.get(column / per_long)
+        .map(|long| ((*long as u64) >> ((column % per_long) * bits)) & ((1 << bits) - 1))
+        .unwrap_or(0)
+}
+
+fn write_height(data: &mut [i64], bits: usize, column: usize, height: u64) {
+    let per_long = 64 / bits;
+    let offset = (column % per_long) * bits;
+    let mask = ((1u64 << bits) - 1) << offset;
+    let long = &mut data[column / per_long];
+    *long = ((*long as u64 & !mask) | (height << offset)) as i64;
+}
+
+/// Decodes which heightmaps every block in a section counts for into `out`.
+fn section_flags(section: &Section, out: &mut [u8]) {
+    match &section.block_states.block_data {
+        PaletteType::Single(val) => out.fill(heightmap_flags(BlockId::from_varint(*val))),
+        PaletteType::Indirect {
+            bits_per_block,
+            data,
+            palette,
+        } => {
+            let palette: Vec<u8> = palette
+                .iter()
+                .map(|id| heightmap_flags(BlockId::from_varint(*id)))
+                .collect();
+            if palette.len() == 1 || *bits_per_block == 0 {
+                out.fill(palette.first().copied().unwrap_or(0));
+                return;
+            }
+            unpack(*bits_per_block, data, out, |value| {
+                palette.get(value as usize).copied().unwrap_or(0)
+            });
+        }
+        PaletteType::Direct {
+            bits_per_block,
+            data,
+        } => unpack(*bits_per_block, data, out, |value| {
+            heightmap_flags(BlockId(value as u32))
+        }),
+    }
+}
+
+impl Chunk {
+    fn vertical_bounds(&self) -> (i32, i32) {
+        let min_section = self.sections.iter().map(|s| s.y as i32).min().unwrap_or(0);
+        let max_section = self.sections.iter().map(|s| s.y as i32).max().unwrap_or(0);
+        (min_section * 16, (max_section + 1) * 16)
+    }
+
+    /// Gets the y of the first block above the highest one in the column that counts for the
+    /// heightmap, or the bottom of the world if there isn't one.
+    pub fn get_height(&self, kind: HeightmapType, x: i32, z: i32) -> i32 {
+        let (min_y, max_y) = self.vertical_bounds();
+        let bits = bits_per_height(max_y - min_y);
+        let column = ((z & 0xf) * 16 + (x & 0xf)) as usize;
+        min_y + read_height(self.heightmaps.get(kind), bits, column) as i32
+    }
+
+    /// Rebuilds every heightmap from the blocks in the chunk.
+    pub fn recalculate_heightmaps(&mut self) {
+        let (min_y, max_y) = self.vertical_bounds();
+        let bits = bits_per_height(max_y - min_y);
+        let mut heights = [[0u64; 256]; 4];
+        let mut remaining = 256 * HeightmapType::ALL.len();
+
+        let mut sections: Vec<&Section> = self.sections.iter().collect();
+        sections.sort_by_key(|section| std::cmp::Reverse(section.y));
+        let mut flags = vec![0u8; 4096];
+        for section in sections {
+            if remaining == 0 {
+                break;
+            }
+            section_flags(section, &mut flags);
+            for y in (0..16).rev() {
+                let height = (section.y as i32 * 16 + y + 1 - min_y) as u64;
+                for column in 0..256 {
+                    let block = flags[y as usize * 256 + column];
+                    for (kind, heights) in HeightmapType::ALL.iter().zip(heights.iter_mut()) {
+                        if heights[column] == 0 && kind.matches_flags(block) {
+                            heights[column] = height;
+                            remaining -= 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (kind, heights) in HeightmapType::ALL.iter().zip(heights.iter()) {
+            *self.heightmaps.get_mut(*kind) = pack(bits as u8, heights);
+        }
+    }
+
+    /// Updates the heightmaps after the block at the given position changed.
+    pub(crate) fn update_heightmaps_at(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+    ) -> Result<(), WorldError> {
+        let (min_y, max_y) = self.vertical_bounds();
+        let bits = bits_per_height(max_y - min_y);
+        let expected_len = 256usize.div_ceil(64 / bits);
+        if HeightmapType::ALL
+            .iter()
+            .any(|kind| self.heightmaps.get(*kind).len() != expected_len)
+        {
+            // Never calculated, or the world got taller since
+            self.recalculate_heightmaps();
+            return Ok(());
+        }
+
+        let column = ((z & 0xf) * 16 + (x & 0xf)) as usize;
+        let flags = heightmap_flags(self.get_block(x, y, z)?);
+        let height = (y + 1 - min_y) as u64;
+        for kind in HeightmapType::ALL {
+            let current = read_height(self.heightmaps.get(kind), bits, column);
+            let new_height = if kind.matches_flags(flags) {
+                current.max(height)
+            } else if current == height {
+                // The top of the column went away, so look for the next block down
+                let mut below = 0;
+                for below_y in (min_y..y).rev() {
+                    if kind.matches(self.get_block(x, below_y, z)?) {
+                        below = (below_y + 1 - min_y) as u64;
+                        break;
+                    }
+                }
+                below
+            } else {
+                current
+            };
+            if new_height != current {
+                write_height(self.heightmaps.get_mut(kind), bits, column, new_height);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit_batch::EditBatch;
+
+    fn block(name: &str) -> BlockId {
+        BlockId::from_name(name).unwrap()
+    }
+
+    #[test]
+    fn test_empty_chunk() {
+        let chunk = Chunk::new(0, 0, "overworld".to_string());
+        for kind in HeightmapType::ALL {
+            assert_eq!(chunk.heightmaps.get(kind).len(), 37);
+            assert_eq!(chunk.get_height(kind, 3, 7), -64);
+        }
+    }
+
+    #[test]
+    fn test_set_block_updates_heightmaps() {
+        let mut chunk = Chunk::new(0, 0, "overworld".to_string());
+        chunk.set_block(1, 10, 2, block("minecraft:stone")).unwrap();
+        chunk
+            .set_block(1, 20, 2, block("minecraft:oak_leaves"))
+            .unwrap();
+        chunk.set_block(1, 30, 2, block("minecraft:torch")).unwrap();
+        assert_eq!(chunk.get_height(HeightmapType::WorldSurface, 1, 2), 31);
+        assert_eq!(chunk.get_height(HeightmapType::MotionBlocking, 1, 2), 21);
+        assert_eq!(
+            chunk.get_height(HeightmapType::MotionBlockingNoLeaves, 1, 2),
+            11
+        );
+        assert_eq!(chunk.get_height(HeightmapType::OceanFloor, 1, 2), 21);
+        // Other columns aren't touched
+        assert_eq!(chunk.get_height(HeightmapType::WorldSurface, 2, 1), -64);
+
+        chunk.set_block(1, 30, 2, block("minecraft:air")).unwrap();
+        chunk.set_block(1, 20, 2, block("minecraft:air")).unwrap();
+        assert_eq!(chunk.get_height(HeightmapType::WorldSurface, 1, 2), 11);
+        assert_eq!(chunk.get_height(HeightmapType::MotionBlocking, 1, 2), 11);
+
+        let incremental = chunk.heightmaps.clone();
+        chunk.recalculate_heightmaps();
+        assert_eq!(chunk.heightmaps, incremental);
+    }
+
+    #[test]
+    fn test_water_only_counts_for_motion_blocking() {
+        let mut chunk = Chunk::new(0, 0, "overworld".to_string());
+        chunk.set_block(0, 0, 0, block("minecraft:sand")).unwrap();
+        chunk.set_block(0, 1, 0, block("minecraft:water")).unwrap();
+        assert_eq!(chunk.get_height(HeightmapType::MotionBlocking, 0, 0), 2);
+        assert_eq!(chunk.get_height(HeightmapType::OceanFloor, 0, 0), 1);
+    }
+
+    #[test]
+    fn test_bulk_edits_update_heightmaps() {
+        let mut chunk = Chunk::new(0, 0, "overworld".to_string());
+        chunk
+            .set_section(
+                0,
+                BlockData {
+                    name: "minecraft:stone".to_string(),
+                    properties: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(chunk.get_height(HeightmapType::OceanFloor, 5, 5), 16);
+
+        let mut batch = EditBatch::new(&mut chunk);
+        batch.set_block(5, 40, 5, block("minecraft:dirt"));
+        batch.apply().unwrap();
+        assert_eq!(chunk.get_height(HeightmapType::OceanFloor, 5, 5), 41);
+        assert_eq!(chunk.get_height(HeightmapType::OceanFloor, 6, 5), 16);
+    }
+}
@@ -0,0 +1,668 @@
+//! Hoppers, droppers and dispensers moving items between containers.
+//!
+//! Everything works on the item lists of the containers' block entities, which are the same
+//! slots `ferrumc_core::inventory::Inventory` is filled from when a player opens one. Hoppers
+//! are ticked from the loaded chunks they're in, so they stop along with their chunk, and only
+//! move items into or out of other loaded chunks. Dispensers and droppers fire on a block tick
+//! scheduled when redstone powers them.
+//!
+//! Every change to a container also hands its new fill level to the redstone networks, for the
+//! comparators reading it.
+//!
+//! Item entities live in the game rather than the world, so the game offers the ones lying around
+//! to the hoppers with [`World::exchange_items`] and gets the items dispensers threw out from
+//! [`World::take_dropped_items`].
+
+use crate::block_entities::chest::ChestItem;
+use crate::block_entities::hopper::HopperBlockEntity;
+use crate::block_entities::{load_container, BlockEntityKind, Container};
+use crate::block_id::{BlockId, Enabled};
+use crate::chunk_format::Chunk;
+use crate::entities::StoredItem;
+use crate::errors::WorldError;
+use crate::item_id::ItemId;
+use crate::redstone::{Direction, Pos};
+use crate::tick::BlockPos;
+use crate::World;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Game ticks a hopper waits after moving an item, like vanilla.
+pub const TRANSFER_COOLDOWN: i32 = 8;
+
+/// Game ticks between a dispenser or dropper getting powered and it firing.
+pub const DISPENSE_DELAY: u32 = 4;
+
+/// An item entity lying around in the game, offered to the hoppers under it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiveItem {
+    pub uuid: u128,
+    pub dimension: String,
+    pub position: (f64, f64, f64),
+    pub item: StoredItem,
+}
+
+/// An item a dispenser or dropper threw out, for the game to spawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DroppedItem {
+    pub dimension: String,
+    pub position: (f64, f64, f64),
+    /// In blocks per game tick.
+    pub velocity: (f64, f64, f64),
+    pub item: StoredItem,
+}
+
+/// What hoppers keep between ticks, and the items passed between them and the game.
+#[derive(Default)]
+pub struct Hoppers {
+    /// Game ticks until each hopper in a loaded chunk moves another item. Hoppers start from the
+    /// cooldown saved with them, which is only written when they move items.
+    cooldowns: HashMap<(String, Pos), i32>,
+    /// The item entities the game offered, by the block they're in.
+    offered: HashMap<(String, Pos), Vec<LiveItem>>,
+    /// How many of each offered item hoppers picked up, by UUID.
+    taken: HashMap<u128, u8>,
+    dropped: Vec<DroppedItem>,
+}
+
+impl Hoppers {
+    /// Forget the hoppers and items in a dimension.
+    pub fn unload_dimension(&mut self, dimension: &str) {
+        self.cooldowns.retain(|(dim, _), _| dim != dimension);
+        self.offered.retain(|(dim, _), _| dim != dimension);
+    }
+}
+
+/// The slots of a container items can be put in through a face, in the order they're tried.
+/// `face` is the side of the container the item comes in from.
+pub fn insert_slots(block: BlockId, face: Direction, item: &str, size: u8) -> Vec<u8> {
+    match block.name() {
+        Some("minecraft:furnace" | "minecraft:smoker" | "minecraft:blast_furnace") => match face {
+            Direction::Up => vec![0],
+            Direction::Down => Vec::new(),
+            _ => vec![1],
+        },
+        Some("minecraft:brewing_stand") => match face {
+            Direction::Up => vec![3],
+            _ if item == "minecraft:blaze_powder" => vec![4],
+            _ => vec![0, 1, 2],
+        },
+        _ => (0..size).collect(),
+    }
+}
+
+/// The slots of a container items can be taken out of through a face.
+pub fn extract_slots(block: BlockId, face: Direction, size: u8) -> Vec<u8> {
+    match block.name() {
+        Some("minecraft:furnace" | "minecraft:smoker" | "minecraft:blast_furnace") => match face {
+            Direction::Down => vec![2],
+            _ => Vec::new(),
+        },
+        Some("minecraft:brewing_stand") => match face {
+            Direction::Down => vec![0, 1, 2],
+            _ => Vec::new(),
+        },
+        _ => (0..size).collect(),
+    }
+}
+
+fn max_stack_size(item: &str) -> u8 {
+    ItemId::from_name(item).map_or(64, |id| id.max_stack_size())
+}
+
+/// Put one of `item` in the first of `slots` that's empty or holds a stack it fits on.
+pub fn insert_one(container: &mut dyn Container, slots: &[u8], item: &str) -> bool {
+    let max = max_stack_size(item);
+    for &slot in slots {
+        let items = container.items_mut();
+        match items.iter_mut().find(|stack| stack.slot == slot as i8) {
+            Some(stack) if stack.count == 0 => {
+                stack.id = item.to_string();
+                stack.count = 1;
+                return true;
+            }
+            Some(stack) if stack.id == item && stack.count < max => {
+                stack.count += 1;
+                return true;
+            }
+            Some(_) => {}
+            None => {
+                items.push(ChestItem {
+                    slot: slot as i8,
+                    id: item.to_string(),
+                    count: 1,
+                });
+                items.sort_by_key(|stack| stack.slot);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Take one item out of `slot`, removing the stack once it's empty.
+pub fn take_one(container: &mut dyn Container, slot: u8) -> Option<String> {
+    let items = container.items_mut();
+    let index = items
+        .iter()
+        .position(|stack| stack.slot == slot as i8 && stack.count > 0)?;
+    let id = items[index].id.clone();
+    items[index].count -= 1;
+    if items[index].count == 0 {
+        items.remove(index);
+    }
+    Some(id)
+}
+
+/// The items in a container's slots, in slot order.
+fn stacks(container: &dyn Container, slots: &[u8]) -> Vec<(u8, String)> {
+    slots
+        .iter()
+        .filter_map(|&slot| {
+            container
+                .items()
+                .iter()
+                .find(|stack| stack.slot == slot as i8 && stack.count > 0)
+                .map(|stack| (slot, stack.id.clone()))
+        })
+        .collect()
+}
+
+/// Move one item from `from` to `to`, trying the stacks in `from` in slot order.
+fn move_one(
+    from: &mut dyn Container,
+    from_slots: &[u8],
+    to: &mut dyn Container,
+    to_slots: &[u8],
+) -> bool {
+    for (slot, item) in stacks(from, from_slots) {
+        if insert_one(to, to_slots, &item) {
+            take_one(from, slot);
+            return true;
+        }
+    }
+    false
+}
+
+impl World {
+    fn is_loaded(&self, dimension: &str, (x, _, z): Pos) -> bool {
+        self.cache
+            .contains_key(&(x >> 4, z >> 4, dimension.to_string()))
+    }
+
+    /// Read the container at a position, `None` if there isn't one or its chunk isn't loaded.
+    pub(crate) fn load_container_at(
+        &self,
+        dimension: &str,
+        pos: Pos,
+    ) -> Result<Option<(BlockId, Box<dyn Container>)>, WorldError> {
+        if !self.is_loaded(dimension, pos) {
+            return Ok(None);
+        }
+        let (x, y, z) = pos;
+        let chunk = self.load_chunk(x >> 4, z >> 4, dimension)?;
+        let block = chunk.get_block(x, y, z)?;
+        let container = load_container(&chunk, (x & 15) as u8, y as u16, (z & 15) as u8);
+        Ok(container.map(|container| (block, container)))
+    }
+
+    /// Store the items of a container, queue it for players and tell the comparators reading it.
+    fn save_container(
+        &self,
+        dimension: &str,
+        pos: Pos,
+        container: &dyn Container,
+    ) -> Result<(), WorldError> {
+        let (x, y, z) = pos;
+        let mut chunk = self.load_chunk_owned(x >> 4, z >> 4, dimension)?;
+        let (local_x, local_z) = ((x & 15) as u8, (z & 15) as u8);
+        let Some(entity_type) = chunk
+            .get_block_entity(local_x, y as u16, local_z)
+            .map(|block_entity| block_entity.entity_type)
+        else {
+            return Ok(());
+        };
+        let nbt = container.to_nbt();
+        chunk.set_block_entity(local_x, y as u16, local_z, entity_type, nbt.clone());
+        self.save_chunk(Arc::new(chunk))?;
+        self.block_entity_changed(dimension, pos, entity_type, nbt);
+        self.redstone
+            .lock()
+            .unwrap()
+            .container_changed(dimension, pos, container.signal());
+        Ok(())
+    }
+
+    /// Move items with every hopper in a loaded chunk.
+    pub(crate) fn tick_hoppers(&self) -> Result<(), WorldError> {
+        let hopper = HopperBlockEntity::type_id();
+        let chunks: Vec<Arc<Chunk>> = self.cache.iter().map(|(_, chunk)| chunk).collect();
+        let mut hoppers = Vec::new();
+        for chunk in chunks {
+            for block_entity in &chunk.block_entities {
+                if block_entity.entity_type == hopper {
+                    let x = (chunk.x << 4) | block_entity.x() as i32;
+                    let z = (chunk.z << 4) | block_entity.z() as i32;
+                    let y = block_entity.y as i16 as i32;
+                    hoppers.push((chunk.dimension.clone(), (x, y, z)));
+                }
+            }
+        }
+        hoppers.sort();
+        // Ones that were unloaded or broken since
+        self.hoppers
+            .lock()
+            .unwrap()
+            .cooldowns
+            .retain(|hopper, _| hoppers.binary_search(hopper).is_ok());
+        for (dimension, pos) in hoppers {
+            self.tick_hopper(&dimension, pos)?;
+        }
+        Ok(())
+    }
+
+    fn tick_hopper(&self, dimension: &str, pos: Pos) -> Result<(), WorldError> {
+        let (x, y, z) = pos;
+        // Hoppers earlier in the tick might have moved items into this one
+        let chunk = self.load_chunk(x >> 4, z >> 4, dimension)?;
+        let block = chunk.get_block(x, y, z)?;
+        let Some(mut hopper) = chunk.get_block_entity_data::<HopperBlockEntity>(
+            (x & 15) as u8,
+            y as u16,
+            (z & 15) as u8,
+        ) else {
+            return Ok(());
+        };
+        {
+            let mut hoppers = self.hoppers.lock().unwrap();
+            let cooldown = hoppers
+                .cooldowns
+                .entry((dimension.to_string(), pos))
+                .or_insert(hopper.transfer_cooldown);
+            *cooldown = (*cooldown - 1).max(0);
+            if *cooldown > 0 || block.get::<Enabled>() == Some(Enabled(false)) {
+                return Ok(());
+            }
+        }
+        let all: Vec<u8> = (0..hopper.size()).collect();
+        let mut moved = false;
+
+        let facing = block.get::<Direction>().unwrap_or(Direction::Down);
+        let front = facing.step(pos);
+        if let Some((target, mut container)) = self.load_container_at(dimension, front)? {
+            let size = container.size();
+            let mut pushed = false;
+            for (slot, item) in stacks(&hopper, &all) {
+                let slots = insert_slots(target, facing.opposite(), &item, size);
+                if insert_one(container.as_mut(), &slots, &item) {
+                    take_one(&mut hopper, slot);
+                    pushed = true;
+                    break;
+                }
+            }
+            if pushed {
+                self.save_container(dimension, front, container.as_ref())?;
+                moved = true;
+            }
+        }
+
+        let above = Direction::Up.step(pos);
+        if let Some((source, mut container)) = self.load_container_at(dimension, above)? {
+            let slots = extract_slots(source, Direction::Down, container.size());
+            if move_one(container.as_mut(), &slots, &mut hopper, &all) {
+                self.save_container(dimension, above, container.as_ref())?;
+                moved = true;
+            }
+        } else if self.pick_up_items(dimension, pos, &mut hopper) {
+            moved = true;
+        }
+
+        if moved {
+            hopper.transfer_cooldown = TRANSFER_COOLDOWN;
+            self.hoppers
+                .lock()
+                .unwrap()
+                .cooldowns
+                .insert((dimension.to_string(), pos), TRANSFER_COOLDOWN);
+            self.save_container(dimension, pos, &hopper)?;
+        }
+        Ok(())
+    }
+
+    /// Put as much as fits of the item entities resting on top of a hopper into it.
+    fn pick_up_items(
+        &self,
+        dimension: &str,
+        (x, y, z): Pos,
+        hopper: &mut HopperBlockEntity,
+    ) -> bool {
+        let all: Vec<u8> = (0..hopper.size()).collect();
+        let mut hoppers = self.hoppers.lock().unwrap();
+        let Hoppers { offered, taken, .. } = &mut *hoppers;
+        let mut picked = false;
+        for block in [(x, y, z), (x, y + 1, z)] {
+            let Some(lying) = offered.get_mut(&(dimension.to_string(), block)) else {
+                continue;
+            };
+            // Items in the hopper's own block only count from halfway up
+            for live in lying
+                .iter_mut()
+                .filter(|live| live.position.1 >= y as f64 + 0.5)
+            {
+                while live.item.count > 0 && insert_one(hopper, &all, &live.item.id) {
+                    live.item.count -= 1;
+                    *taken.entry(live.uuid).or_default() += 1;
+                    picked = true;
+                }
+            }
+        }
+        picked
+    }
+
+    /// Throw an item out of the front of a dispenser or dropper.
+    fn drop_item(&self, dimension: &str, front: Pos, facing: Direction, item: String) {
+        let (x, y, z) = front;
+        let (dx, dy, dz) = facing.offset();
+        self.hoppers.lock().unwrap().dropped.push(DroppedItem {
+            dimension: dimension.to_string(),
+            position: (x as f64 + 0.5, y as f64 + 0.5, z as f64 + 0.5),
+            velocity: (dx as f64 * 0.2, dy as f64 * 0.2 + 0.1, dz as f64 * 0.2),
+            item: StoredItem { id: item, count: 1 },
+        });
+    }
+
+    /// Offer the item entities lying around to the hoppers for the next ticks, in place of the
+    /// ones offered before, and return how many of each the hoppers picked up since the last
+    /// call. Those are already left out of what's offered now.
+    pub fn exchange_items(&self, items: Vec<LiveItem>) -> HashMap<u128, u8> {
+        let mut hoppers = self.hoppers.lock().unwrap();
+        let taken = std::mem::take(&mut hoppers.taken);
+        hoppers.offered.clear();
+        for mut live in items {
+            let picked = taken.get(&live.uuid).copied().unwrap_or(0);
+            live.item.count = live.item.count.saturating_sub(picked);
+            if live.item.count == 0 {
+                continue;
+            }
+            let (x, y, z) = live.position;
+            let block = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+            hoppers
+                .offered
+                .entry((live.dimension.clone(), block))
+                .or_default()
+                .push(live);
+        }
+        taken
+    }
+
+    /// Take the items dispensers and droppers threw out since this was last called, for spawning.
+    pub fn take_dropped_items(&self) -> Vec<DroppedItem> {
+        std::mem::take(&mut self.hoppers.lock().unwrap().dropped)
+    }
+}
+
+/// Fire a dispenser or dropper, on the block tick scheduled when it got powered.
+///
+/// Droppers put one item from a random slot into the container they face, or drop it if there
+/// isn't one. Dispensers always drop it, they don't use items yet.
+pub(crate) fn dispense(world: &World, pos: &BlockPos, block: BlockId) -> Result<(), WorldError> {
+    let position = (pos.x, pos.y, pos.z);
+    let Some((_, mut dispenser)) = world.load_container_at(&pos.dimension, position)? else {
+        return Ok(());
+    };
+    let filled = stacks(
+        dispenser.as_ref(),
+        &(0..dispenser.size()).collect::<Vec<_>>(),
+    );
+    if filled.is_empty() {
+        return Ok(());
+    }
+    let (slot, item) = filled[rand::rng().random_range(0..filled.len())].clone();
+    let facing = block.get::<Direction>().unwrap_or(Direction::North);
+    let front = facing.step(position);
+    if block.name() == Some("minecraft:dropper") {
+        if let Some((target, mut container)) = world.load_container_at(&pos.dimension, front)? {
+            let slots = insert_slots(target, facing.opposite(), &item, container.size());
+            if insert_one(container.as_mut(), &slots, &item) {
+                take_one(dispenser.as_mut(), slot);
+                world.save_container(&pos.dimension, front, container.as_ref())?;
+                world.save_container(&pos.dimension, position, dispenser.as_ref())?;
+            }
+            return Ok(());
+        }
+    }
+    take_one(dispenser.as_mut(), slot);
+    world.save_container(&pos.dimension, position, dispenser.as_ref())?;
+    world.drop_item(&pos.dimension, front, facing, item);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_entities::chest::ChestBlockEntity;
+    use crate::block_entities::dispenser::DispenserBlockEntity;
+    use crate::block_entities::furnace::FurnaceBlockEntity;
+    use crate::testing::{memory_world, tick};
+
+    fn block(name: &str) -> BlockId {
+        BlockId::from_name(&format!("minecraft:{name}")).unwrap()
+    }
+
+    fn stack(slot: i8, id: &str, count: u8) -> ChestItem {
+        ChestItem {
+            slot,
+            id: id.to_string(),
+            count,
+        }
+    }
+
+    #[test]
+    fn test_items_go_into_the_first_slot_they_fit() {
+        let mut chest = ChestBlockEntity {
+            items: vec![
+                stack(0, "minecraft:stone", 64),
+                stack(1, "minecraft:dirt", 3),
+            ],
+        };
+        assert!(insert_one(&mut chest, &[0, 1, 2], "minecraft:stone"));
+        assert_eq!(chest.items[2], stack(2, "minecraft:stone", 1));
+        assert!(insert_one(&mut chest, &[0, 1, 2], "minecraft:dirt"));
+        assert_eq!(chest.items[1], stack(1, "minecraft:dirt", 4));
+        assert!(!insert_one(&mut chest, &[0, 1], "minecraft:sand"));
+    }
+
+    #[test]
+    fn test_furnaces_are_filled_by_face() {
+        let furnace = block("furnace");
+        assert_eq!(
+            insert_slots(furnace, Direction::Up, "minecraft:iron_ore", 3),
+            [0]
+        );
+        assert_eq!(
+            insert_slots(furnace, Direction::North, "minecraft:coal", 3),
+            [1]
+        );
+        assert_eq!(extract_slots(furnace, Direction::Down, 3), [2]);
+
+        let stand = block("brewing_stand");
+        assert_eq!(
+            insert_slots(stand, Direction::Up, "minecraft:nether_wart", 5),
+            [3]
+        );
+        assert_eq!(
+            insert_slots(stand, Direction::East, "minecraft:blaze_powder", 5),
+            [4]
+        );
+        assert_eq!(
+            insert_slots(stand, Direction::East, "minecraft:potion", 5),
+            [0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_comparator_signal_follows_fill_level() {
+        let mut furnace = FurnaceBlockEntity::default();
+        assert_eq!(furnace.signal(), 0);
+        furnace.items.push(stack(0, "minecraft:iron_ore", 1));
+        assert_eq!(furnace.signal(), 1);
+        furnace.items = (0..3)
+            .map(|slot| stack(slot, "minecraft:iron_ore", 64))
+            .collect();
+        assert_eq!(furnace.signal(), 15);
+        let chest = ChestBlockEntity {
+            items: vec![stack(0, "minecraft:stone", 64)],
+        };
+        assert_eq!(chest.signal(), 1);
+    }
+
+    #[test]
+    fn test_hoppers_move_items_between_containers() {
+        let (world, _) = memory_world([(0, 0)]);
+        let ore = |slot: i8, count: u8| stack(slot, "minecraft:iron_ore", count);
+        let smelting = || {
+            world
+                .load_block_entity::<FurnaceBlockEntity>(1, 63, 1, "overworld")
+                .unwrap()
+                .items
+        };
+
+        // A chest feeding a furnace through a hopper, with a comparator reading the chest
+        let hopper = block("hopper")
+            .with(Direction::Down)
+            .and_then(|b| b.with(Enabled(true)))
+            .unwrap();
+        let comparator = block("comparator").with(Direction::North).unwrap();
+        for (y, placed) in [(63, block("furnace")), (64, hopper), (65, block("chest"))] {
+            world
+                .set_block_and_fetch(1, y, 1, "overworld", placed)
+                .unwrap();
+        }
+        world
+            .set_block_and_fetch(1, 65, 2, "overworld", comparator)
+            .unwrap();
+        let chest = ChestBlockEntity {
+            items: vec![ore(4, 3)],
+        };
+        world
+            .save_typed_block_entity(1, 65, 1, "overworld", &chest)
+            .unwrap();
+
+        tick(&world, 1);
+        let hopper_items = world
+            .load_block_entity::<HopperBlockEntity>(1, 64, 1, "overworld")
+            .unwrap();
+        assert_eq!(hopper_items.items, [ore(0, 1)]);
+        assert_eq!(hopper_items.transfer_cooldown, 8);
+        // Cooling down doesn't touch the chunk
+        let cooling = world.load_chunk(0, 0, "overworld").unwrap();
+        tick(&world, 6);
+        assert!(Arc::ptr_eq(
+            &cooling,
+            &world.load_chunk(0, 0, "overworld").unwrap()
+        ));
+        tick(&world, 1);
+        assert!(smelting().is_empty());
+        assert_eq!(world.get_power_level(1, 65, 2, "overworld"), 1);
+        // Pushed down into the furnace's input slot on the eighth tick after the last move
+        tick(&world, 1);
+        assert_eq!(smelting(), [ore(0, 1)]);
+        tick(&world, 16);
+        assert_eq!(smelting(), [ore(0, 3)]);
+        assert!(world
+            .load_block_entity::<ChestBlockEntity>(1, 65, 1, "overworld")
+            .unwrap()
+            .items
+            .is_empty());
+        tick(&world, 3);
+        assert_eq!(world.get_power_level(1, 65, 2, "overworld"), 0);
+
+        // Powered hoppers stop moving items
+        world
+            .save_typed_block_entity(1, 65, 1, "overworld", &chest)
+            .unwrap();
+        world
+            .set_block_and_fetch(2, 64, 1, "overworld", block("redstone_block"))
+            .unwrap();
+        tick(&world, 20);
+        assert_eq!(
+            world
+                .get_block_and_fetch(1, 64, 1, "overworld")
+                .unwrap()
+                .get::<Enabled>(),
+            Some(Enabled(false))
+        );
+        assert_eq!(
+            world
+                .load_block_entity::<ChestBlockEntity>(1, 65, 1, "overworld")
+                .unwrap(),
+            chest
+        );
+
+        // Dispensers drop an item in front of them when powered
+        let dispenser = block("dispenser").with(Direction::East).unwrap();
+        world
+            .set_block_and_fetch(8, 64, 8, "overworld", dispenser)
+            .unwrap();
+        let loaded = DispenserBlockEntity {
+            items: vec![ore(0, 2)],
+        };
+        world
+            .save_typed_block_entity(8, 64, 8, "overworld", &loaded)
+            .unwrap();
+        world
+            .set_block_and_fetch(8, 64, 9, "overworld", block("redstone_block"))
+            .unwrap();
+        tick(&world, 6);
+        let dropped = world.take_dropped_items();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].item.id, "minecraft:iron_ore");
+        assert_eq!(dropped[0].position, (9.5, 64.5, 8.5));
+        assert_eq!(
+            world
+                .load_block_entity::<DispenserBlockEntity>(8, 64, 8, "overworld")
+                .unwrap()
+                .items,
+            [ore(0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_hoppers_pick_up_items_the_game_offers() {
+        let (world, _) = memory_world([(0, 0)]);
+        let hopper = block("hopper")
+            .with(Direction::Down)
+            .and_then(|b| b.with(Enabled(true)))
+            .unwrap();
+        world
+            .set_block_and_fetch(4, 64, 4, "overworld", hopper)
+            .unwrap();
+        let lying = |uuid: u128, x: f64, y: f64, count: u8| LiveItem {
+            uuid,
+            dimension: "overworld".to_string(),
+            position: (x, y, 4.5),
+            item: StoredItem {
+                id: "minecraft:iron_ore".to_string(),
+                count,
+            },
+        };
+        let offered = vec![lying(1, 4.5, 65.2, 2), lying(2, 6.5, 65.2, 1)];
+
+        assert!(world.exchange_items(offered.clone()).is_empty());
+        tick(&world, 1);
+        // The game hasn't taken them away yet, but they aren't offered twice
+        assert_eq!(world.exchange_items(offered), HashMap::from([(1, 2)]));
+        tick(&world, 1);
+        assert!(world
+            .exchange_items(vec![lying(2, 6.5, 65.2, 1)])
+            .is_empty());
+        assert_eq!(
+            world
+                .load_block_entity::<HopperBlockEntity>(4, 64, 4, "overworld")
+                .unwrap()
+                .items,
+            [stack(0, "minecraft:iron_ore", 2)]
+        );
+    }
+}
@@ -1,70 +1,206 @@
+//! Importing vanilla saves.
+//!
+//! The terrain of all three dimensions is imported from `region`, `DIM-1/region` and
+//! `DIM1/region`, and the mobs and dropped items in them from the matching `entities` folders.
+//! `level.dat` gives the spawn point, seed, time and game rules, and every file in `playerdata`
+//! gives a player's inventory, position and dimension.
+//!
+//! Chunks saved by older versions of the game are upgraded on the way in, see
+//! [`data_fixer`](crate::data_fixer).
+
+use crate::data_fixer::read_chunk;
 use crate::db_functions::save_chunk_internal_batch;
+use crate::dimensions::DimensionRegistry;
+use crate::entities::{StoredEntity, StoredItem};
 use crate::errors::WorldError;
+use crate::game_rules::RANDOM_TICK_SPEED;
+use crate::item_id::ItemId;
+use crate::level::LevelInfo;
 use crate::vanilla_chunk_format::VanillaChunk;
+use crate::vanilla_save_format::{
+    VanillaEntity, VanillaEntityChunk, VanillaItem, VanillaLevel, VanillaPlayer,
+};
 use crate::Chunk;
 use crate::World;
 use ferrumc_anvil::load_anvil_file;
+use ferrumc_storage::player_data::{
+    load_player_data, save_player_data, InventoryData, ItemStackData, PlayerData, PositionData,
+    ITEM_FORMAT_VERSION,
+};
+use flate2::read::GzDecoder;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::thread;
-use tracing::{error, info};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+/// The folder each vanilla dimension is kept in, relative to the save.
+const DIMENSION_FOLDERS: [(&str, &str); 3] =
+    [("overworld", ""), ("nether", "DIM-1"), ("end", "DIM1")];
+
+/// What [`World::import`] brought over.
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    /// How many chunks were imported in each dimension.
+    pub chunks: BTreeMap<String, u64>,
+    pub entities: u64,
+    pub players: u64,
+    /// Whether the spawn point, seed, time and game rules were imported from `level.dat`.
+    pub level: bool,
+    /// Inventory items that don't have a block to stand in for them, so they were left out.
+    pub skipped_items: u64,
+    /// Block states in chunk palettes that couldn't be upgraded to one that exists now, so they
+    /// were replaced with air.
+    pub unmapped_blocks: u64,
+    /// Biomes in chunk palettes that couldn't be upgraded to one that exists now.
+    pub unmapped_biomes: u64,
+    /// Block entities that were left out, since their type doesn't exist anymore.
+    pub skipped_block_entities: u64,
+    /// Chunks, entities and player files that couldn't be read, converted or saved.
+    pub failed: u64,
+}
+
+/// Counts kept by the threads importing the chunks of a dimension.
+#[derive(Default)]
+struct ChunkCounters {
+    imported: AtomicU64,
+    /// Chunks that couldn't be converted or saved.
+    failed: AtomicU64,
+    unmapped_blocks: AtomicU64,
+    unmapped_biomes: AtomicU64,
+    skipped_block_entities: AtomicU64,
+}
 
 impl World {
     fn process_chunk_batch(
         &self,
-        chunks: &[VanillaChunk],
-        progress: ProgressBar,
-    ) -> Result<(), WorldError> {
-        let chunk_objects: Vec<Chunk> = chunks
-            .iter()
-            .flat_map(|chunk| chunk.to_custom_format())
-            .collect();
-
-        let mut success_count = 0;
-        if let Ok(()) = save_chunk_internal_batch(self, &chunk_objects) {
-            success_count = chunk_objects.len();
+        chunks: &mut [VanillaChunk],
+        progress: &ProgressBar,
+        counters: &ChunkCounters,
+        relit: &Mutex<Vec<(i32, i32, String)>>,
+    ) {
+        for chunk in chunks.iter_mut() {
+            let report = chunk.upgrade();
+            counters
+                .unmapped_blocks
+                .fetch_add(report.unmapped_blocks, Ordering::Relaxed);
+            counters
+                .unmapped_biomes
+                .fetch_add(report.unmapped_biomes, Ordering::Relaxed);
+            counters
+                .skipped_block_entities
+                .fetch_add(report.skipped_block_entities, Ordering::Relaxed);
+        }
+        let mut chunk_objects: Vec<Chunk> = Vec::with_capacity(chunks.len());
+        for chunk in chunks.iter() {
+            match chunk.to_custom_format() {
+                Ok(converted) => chunk_objects.push(converted),
+                Err(e) => {
+                    error!(
+                        "Failed to convert chunk {}, {} in {}: {}",
+                        chunk.x_pos,
+                        chunk.z_pos,
+                        chunk.dimension.as_deref().unwrap_or("overworld"),
+                        e
+                    );
+                    counters.failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
         }
 
-        progress.inc(success_count.try_into().unwrap());
+        // Chunks without light data are stored unlit, and lit once their neighbours are
+        // imported too so the light along their borders is right.
+        relit
+            .lock()
+            .unwrap()
+            .extend(
+                chunks
+                    .iter()
+                    .filter(|chunk| !chunk.has_light())
+                    .map(|chunk| {
+                        let dimension = chunk.dimension.clone().unwrap_or("overworld".to_string());
+                        (chunk.x_pos, chunk.z_pos, dimension)
+                    }),
+            );
+
+        if let Err(e) = save_chunk_internal_batch(
+            self.storage_backend.as_ref(),
+            &self.dimensions,
+            &self.compressor,
+            &chunk_objects,
+        ) {
+            let coords = chunk_objects
+                .iter()
+                .map(|chunk| format!("({}, {})", chunk.x, chunk.z))
+                .collect::<Vec<_>>();
+            error!(
+                "Failed to save {} chunks in {}: {}. Chunks: {}",
+                chunk_objects.len(),
+                chunk_objects
+                    .first()
+                    .map_or("overworld", |chunk| chunk.dimension.as_str()),
+                e,
+                coords.join(", ")
+            );
+            counters
+                .failed
+                .fetch_add(chunk_objects.len() as u64, Ordering::Relaxed);
+            return;
+        }
 
-        Ok(())
+        counters
+            .imported
+            .fetch_add(chunk_objects.len() as u64, Ordering::Relaxed);
+        progress.inc(chunk_objects.len() as u64);
     }
 
-    fn get_chunk_count(&self, import_dir: &Path) -> Result<u64, WorldError> {
+    fn get_chunk_count(&self, region_dirs: &[(&str, PathBuf)]) -> Result<u64, WorldError> {
         info!("Counting chunks in import directory...");
-        let regions_dir = import_dir.join("region").read_dir()?;
         let chunk_count = AtomicU64::new(0);
 
-        regions_dir
-            .par_bridge()
-            .try_for_each(|region_file| -> Result<(), WorldError> {
-                let entry = region_file?;
-                if entry.path().is_dir() {
-                    return Ok(());
-                }
+        for (_, region_dir) in region_dirs {
+            region_dir.read_dir()?.par_bridge().try_for_each(
+                |region_file| -> Result<(), WorldError> {
+                    let entry = region_file?;
+                    if entry.path().is_dir() {
+                        return Ok(());
+                    }
 
-                if let Ok(anvil_file) = load_anvil_file(entry.path()) {
-                    chunk_count
-                        .fetch_add(anvil_file.get_locations().len() as u64, Ordering::Relaxed);
-                }
-                Ok(())
-            })?;
+                    if let Ok(anvil_file) = load_anvil_file(entry.path()) {
+                        chunk_count
+                            .fetch_add(anvil_file.get_locations().len() as u64, Ordering::Relaxed);
+                    }
+                    Ok(())
+                },
+            )?;
+        }
 
         Ok(chunk_count.load(Ordering::Relaxed))
     }
 
+    /// Import a vanilla save, overwriting anything in the world that's also in the save.
+    ///
+    /// Only the overworld has to be there, the other dimensions, entities, `level.dat` and player
+    /// data are all imported if they exist.
     pub fn import(
         &mut self,
         import_dir: PathBuf,
         batch_size: usize,
         // max_concurrent_tasks: usize,
-    ) -> Result<(), WorldError> {
+    ) -> Result<ImportSummary, WorldError> {
         check_paths_validity(&import_dir)?;
+        let mut summary = ImportSummary::default();
 
-        let total_chunks = self.get_chunk_count(&import_dir)?;
+        let region_dirs: Vec<(&str, PathBuf)> = DIMENSION_FOLDERS
+            .iter()
+            .map(|(dimension, folder)| (*dimension, import_dir.join(folder).join("region")))
+            .filter(|(_, region_dir)| region_dir.is_dir())
+            .collect();
+        let total_chunks = self.get_chunk_count(&region_dirs)?;
         let progress_style = ProgressStyle::default_bar()
             .template("[{elapsed_precise}/{eta_precise} eta] {bar:40.cyan/blue} {percent}%, {pos:>7}/{len:7}, {per_sec}, {msg}")
             .unwrap();
@@ -77,85 +213,119 @@ impl World {
         info!("Starting chunk import...");
         let start = std::time::Instant::now();
 
-        let regions_dir = import_dir.join("region").read_dir()?;
-        let mut current_batch = Vec::with_capacity(batch_size);
-
-        let remaining_tasks = Arc::new(AtomicU32::new(0));
-
-        for region_result in regions_dir {
-            let region_entry = region_result?;
-            if region_entry.path().is_dir() {
-                continue;
-            }
-
-            let anvil_file = match load_anvil_file(region_entry.path()) {
-                Ok(file) => file,
-                Err(e) => {
-                    error!(
-                        "Failed to load region file {}: {}",
-                        region_entry.path().display(),
-                        e
-                    );
-                    continue;
-                }
-            };
+        let relit = &Mutex::new(Vec::new());
+        let progress = &progress;
+        let world: &World = self;
+        for (dimension, region_dir) in &region_dirs {
+            let counters = &ChunkCounters::default();
+            // Batches are converted and saved on the thread pool while the next ones are read
+            rayon::scope(|scope| -> Result<(), WorldError> {
+                let mut tasks = 0;
+                let mut current_batch = Vec::with_capacity(batch_size);
+                let queue = move |mut batch: Vec<VanillaChunk>| {
+                    scope.spawn(move |_| {
+                        world.process_chunk_batch(&mut batch, progress, counters, relit);
+                    });
+                };
+
+                for region_result in region_dir.read_dir()? {
+                    let region_entry = region_result?;
+                    if region_entry.path().is_dir() {
+                        continue;
+                    }
 
-            for location in anvil_file.get_locations() {
-                let remaining_tasks_clone = remaining_tasks.clone();
-                if let Ok(Some(chunk_data)) = anvil_file.get_chunk_from_location(location) {
-                    if let Ok(vanilla_chunk) = VanillaChunk::from_bytes(&chunk_data) {
+                    let anvil_file = match load_anvil_file(region_entry.path()) {
+                        Ok(file) => file,
+                        Err(e) => {
+                            error!(
+                                "Failed to load region file {}: {}",
+                                region_entry.path().display(),
+                                e
+                            );
+                            continue;
+                        }
+                    };
+
+                    for location in anvil_file.get_locations() {
+                        let chunk_data = match anvil_file.get_chunk_from_location(location) {
+                            Ok(Some(chunk_data)) => chunk_data,
+                            Ok(None) => {
+                                error!(
+                                    "Chunk at sector {} in {} has no data",
+                                    location >> 8,
+                                    region_entry.path().display()
+                                );
+                                counters.failed.fetch_add(1, Ordering::Relaxed);
+                                continue;
+                            }
+                            Err(e) => {
+                                error!(
+                                    "Failed to read chunk at sector {} in {}: {}",
+                                    location >> 8,
+                                    region_entry.path().display(),
+                                    e
+                                );
+                                counters.failed.fetch_add(1, Ordering::Relaxed);
+                                continue;
+                            }
+                        };
+                        let mut vanilla_chunk = match read_chunk(&chunk_data) {
+                            Ok(chunk) => chunk,
+                            Err(e) => {
+                                error!(
+                                    "Failed to read chunk in {}: {}",
+                                    region_entry.path().display(),
+                                    e
+                                );
+                                counters.failed.fetch_add(1, Ordering::Relaxed);
+                                continue;
+                            }
+                        };
+                        // Vanilla chunks don't say which dimension they're in, only the folder
+                        // they're in does
+                        vanilla_chunk.dimension = Some(dimension.to_string());
                         current_batch.push(vanilla_chunk);
 
                         if current_batch.len() >= batch_size {
-                            let self_clone = self.clone();
-                            let progress_clone = progress.clone();
-
                             let batch = core::mem::take(&mut current_batch);
                             current_batch.reserve(batch_size);
-                            let remaining_tasks_clone = remaining_tasks_clone.clone();
-
-                            thread::spawn(move || {
-                                remaining_tasks_clone.fetch_add(1, Ordering::Relaxed);
-                                if let Err(e) =
-                                    self_clone.process_chunk_batch(&batch, progress_clone)
-                                {
-                                    error!("Batch processing error: {}", e);
-                                }
-                            });
-
-                            progress.set_message(format!(
-                                "tasks: {}",
-                                remaining_tasks.clone().load(Ordering::Relaxed)
-                            ));
+                            queue(batch);
+                            tasks += 1;
+                            progress.set_message(format!("{dimension}, tasks: {tasks}"));
                         }
                     }
                 }
-            }
-        }
-
-        if !current_batch.is_empty() {
-            let progress = progress.clone();
-            let self_clone = self.clone();
-            let remaining_tasks_clone = remaining_tasks.clone();
 
-            thread::spawn(move || {
-                remaining_tasks_clone.fetch_add(1, Ordering::Relaxed);
-                if let Err(e) = self_clone.process_chunk_batch(&current_batch, progress) {
-                    error!("Final batch processing error: {}", e);
+                if !current_batch.is_empty() {
+                    queue(current_batch);
                 }
-            });
-        }
+                Ok(())
+            })?;
 
-        while remaining_tasks.load(Ordering::Relaxed) > 0 {
-            progress.set_message(format!(
-                "tasks: {}",
-                remaining_tasks.clone().load(Ordering::Relaxed)
-            ));
-            thread::sleep(std::time::Duration::from_secs(1));
+            summary.chunks.insert(
+                dimension.to_string(),
+                counters.imported.load(Ordering::Relaxed),
+            );
+            summary.failed += counters.failed.load(Ordering::Relaxed);
+            summary.unmapped_blocks += counters.unmapped_blocks.load(Ordering::Relaxed);
+            summary.unmapped_biomes += counters.unmapped_biomes.load(Ordering::Relaxed);
+            summary.skipped_block_entities +=
+                counters.skipped_block_entities.load(Ordering::Relaxed);
         }
 
         self.sync()?;
 
+        let relit = std::mem::take(&mut *relit.lock().unwrap());
+        if !relit.is_empty() {
+            info!("Fixing up light for {} unlit chunks...", relit.len());
+            for (x, z, dimension) in relit {
+                if let Err(e) = self.relight_chunk(x, z, &dimension) {
+                    error!("Failed to relight chunk {}, {}: {}", x, z, e);
+                }
+            }
+            self.sync()?;
+        }
+
         progress.finish();
 
         info!(
@@ -164,10 +334,357 @@ impl World {
             start.elapsed()
         );
 
+        self.import_entities(&import_dir, &mut summary)?;
+        let level_info = self.import_level(&import_dir, &mut summary)?;
+        self.import_players(&import_dir, &level_info, &mut summary)?;
+        self.sync()?;
+
+        info!("Import summary:");
+        for (dimension, count) in &summary.chunks {
+            info!("  Chunks in {}: {}", dimension, count);
+        }
+        info!("  Entities: {}", summary.entities);
+        info!(
+            "  Level data: {}",
+            if summary.level {
+                "imported"
+            } else {
+                "not found"
+            }
+        );
+        info!("  Players: {}", summary.players);
+        if summary.skipped_items > 0 {
+            warn!(
+                "  Inventory items left out: {} (items this version doesn't have)",
+                summary.skipped_items
+            );
+        }
+        if summary.unmapped_blocks > 0 || summary.unmapped_biomes > 0 {
+            warn!(
+                "  Unknown palette entries: {} blocks (replaced with air), {} biomes",
+                summary.unmapped_blocks, summary.unmapped_biomes
+            );
+        }
+        if summary.skipped_block_entities > 0 {
+            warn!(
+                "  Block entities of unknown types left out: {}",
+                summary.skipped_block_entities
+            );
+        }
+        if summary.failed > 0 {
+            warn!(
+                "  Failed to import: {} (see the errors above for which ones)",
+                summary.failed
+            );
+        }
+
+        Ok(summary)
+    }
+
+    /// Import the mobs and dropped items from the `entities` folder of every dimension.
+    fn import_entities(
+        &self,
+        import_dir: &Path,
+        summary: &mut ImportSummary,
+    ) -> Result<(), WorldError> {
+        for (dimension, folder) in DIMENSION_FOLDERS {
+            let entities_dir = import_dir.join(folder).join("entities");
+            if !entities_dir.is_dir() {
+                continue;
+            }
+            info!("Importing entities in {}...", dimension);
+            for region_result in entities_dir.read_dir()? {
+                let region_path = region_result?.path();
+                if region_path.is_dir() {
+                    continue;
+                }
+                let anvil_file = match load_anvil_file(region_path.clone()) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        error!(
+                            "Failed to load entity region file {}: {}",
+                            region_path.display(),
+                            e
+                        );
+                        summary.failed += 1;
+                        continue;
+                    }
+                };
+
+                for location in anvil_file.get_locations() {
+                    let chunk = match anvil_file.get_chunk_from_location(location) {
+                        Ok(Some(data)) => VanillaEntityChunk::from_bytes(&data)
+                            .map_err(|e| WorldError::NbtDecodeError(e.to_string())),
+                        Ok(None) => continue,
+                        Err(e) => Err(e.into()),
+                    };
+                    let chunk = match chunk {
+                        Ok(chunk) => chunk,
+                        Err(e) => {
+                            error!("Failed to read entity chunk: {}", e);
+                            summary.failed += 1;
+                            continue;
+                        }
+                    };
+                    let Some(&[x, z]) = chunk.position.as_deref() else {
+                        summary.failed += 1;
+                        continue;
+                    };
+
+                    let mut entities = Vec::new();
+                    for entity in chunk.entities.unwrap_or_default() {
+                        match stored_entity(&entity) {
+                            Some(entity) => entities.push(entity),
+                            None => summary.failed += 1,
+                        }
+                    }
+                    if !entities.is_empty() {
+                        self.save_entities(x, z, dimension, &entities)?;
+                        summary.entities += entities.len() as u64;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Import the spawn point, seed, time and game rules from `level.dat`. Returns the level info
+    /// the world ends up with.
+    fn import_level(
+        &self,
+        import_dir: &Path,
+        summary: &mut ImportSummary,
+    ) -> Result<LevelInfo, WorldError> {
+        let mut level_info = self.level_info()?;
+        let level_path = import_dir.join("level.dat");
+        if !level_path.is_file() {
+            return Ok(level_info);
+        }
+
+        let data = read_gzipped(&level_path)?;
+        let level = VanillaLevel::from_bytes(&data)
+            .map_err(|e| WorldError::NbtDecodeError(e.to_string()))?;
+        let Some(data) = level.data else {
+            warn!("level.dat has no Data tag, skipping it");
+            return Ok(level_info);
+        };
+        level_info.spawn_x = data.spawn_x.unwrap_or(level_info.spawn_x);
+        level_info.spawn_y = data.spawn_y.unwrap_or(level_info.spawn_y);
+        level_info.spawn_z = data.spawn_z.unwrap_or(level_info.spawn_z);
+        level_info.time = data.time.unwrap_or(level_info.time);
+        level_info.day_time = data.day_time.unwrap_or(level_info.day_time);
+        level_info.seed = data
+            .world_gen_settings
+            .and_then(|settings| settings.seed)
+            .or(data.random_seed)
+            .unwrap_or(level_info.seed);
+        self.save_level_info(&level_info)?;
+        // Vanilla saves every rule as a string
+        let random_tick_speed = data
+            .game_rules
+            .and_then(|rules| rules.random_tick_speed)
+            .and_then(|speed| speed.parse().ok());
+        if let Some(speed) = random_tick_speed {
+            self.set_game_rule(RANDOM_TICK_SPEED, speed)?;
+        }
+        summary.level = true;
+
+        Ok(level_info)
+    }
+
+    /// Import the inventory and position of every player in the `playerdata` folder.
+    fn import_players(
+        &self,
+        import_dir: &Path,
+        level_info: &LevelInfo,
+        summary: &mut ImportSummary,
+    ) -> Result<(), WorldError> {
+        let player_dir = import_dir.join("playerdata");
+        if !player_dir.is_dir() {
+            return Ok(());
+        }
+        info!("Importing player data...");
+
+        for entry in player_dir.read_dir()? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "dat") {
+                continue;
+            }
+            let Some(uuid) = path
+                .file_stem()
+                .and_then(|stem| Uuid::parse_str(&stem.to_string_lossy()).ok())
+            else {
+                debug!("Skipping {}, it's not named after a UUID", path.display());
+                continue;
+            };
+
+            let player = read_gzipped(&path).and_then(|data| {
+                VanillaPlayer::from_bytes(&data)
+                    .map_err(|e| WorldError::NbtDecodeError(e.to_string()))
+            });
+            let player = match player {
+                Ok(player) => player,
+                Err(e) => {
+                    error!("Failed to read player data {}: {}", path.display(), e);
+                    summary.failed += 1;
+                    continue;
+                }
+            };
+
+            // Anything else the player already has, like their stats, is kept
+            let existing = load_player_data(self.backend(), uuid.as_u128())?;
+            let player_data = player_data_from_vanilla(
+                &player,
+                existing,
+                level_info,
+                &self.dimensions,
+                &mut summary.skipped_items,
+            );
+            save_player_data(self.backend(), uuid.as_u128(), &player_data)?;
+            summary.players += 1;
+        }
         Ok(())
     }
 }
 
+fn read_gzipped(path: &Path) -> Result<Vec<u8>, WorldError> {
+    let mut data = Vec::new();
+    GzDecoder::new(std::fs::File::open(path)?).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Convert a vanilla entity, or None if it's missing its type or position.
+pub(crate) fn stored_entity(entity: &VanillaEntity) -> Option<StoredEntity> {
+    let entity_type = entity.id.clone()?;
+    let &[x, y, z] = entity.pos.as_deref()? else {
+        return None;
+    };
+    let velocity = match entity.motion.as_deref() {
+        Some(&[x, y, z]) => (x, y, z),
+        _ => (0.0, 0.0, 0.0),
+    };
+    let (yaw, pitch) = match entity.rotation.as_deref() {
+        Some(&[yaw, pitch]) => (yaw, pitch),
+        _ => (0.0, 0.0),
+    };
+    let uuid = match entity.uuid.as_deref() {
+        Some(&[a, b, c, d]) => {
+            ((a as u32 as u128) << 96)
+                | ((b as u32 as u128) << 64)
+                | ((c as u32 as u128) << 32)
+                | (d as u32 as u128)
+        }
+        // Versions before 1.16 kept the UUID in two longs instead, a new one does just as well
+        _ => Uuid::new_v4().as_u128(),
+    };
+    let item = entity.item.as_ref().and_then(|item| {
+        Some(StoredItem {
+            id: item.id.clone()?,
+            count: item.count(),
+        })
+    });
+
+    Some(StoredEntity {
+        entity_type,
+        uuid,
+        position: (x, y, z),
+        velocity,
+        yaw,
+        pitch,
+        item,
+    })
+}
+
+/// Convert an inventory item, or None if it's empty or isn't in the item registry.
+fn item_stack(item: &VanillaItem, skipped_items: &mut u64) -> Option<ItemStackData> {
+    let id = item.id.as_deref()?;
+    if id == "minecraft:air" || item.count() == 0 {
+        return None;
+    }
+    let Some(item_id) = ItemId::from_name(id) else {
+        *skipped_items += 1;
+        return None;
+    };
+    let max_stack_size = item_id.max_stack_size();
+    Some(ItemStackData {
+        item: item_id.0,
+        count: item.count().min(max_stack_size),
+        max_stack_size,
+        nbt: None,
+    })
+}
+
+/// Put a vanilla player's inventory, position and dimension into their player data.
+///
+/// Players in a dimension the world doesn't have are moved to the spawn point, since their
+/// position would mean something else anywhere else.
+fn player_data_from_vanilla(
+    player: &VanillaPlayer,
+    mut player_data: PlayerData,
+    level_info: &LevelInfo,
+    dimensions: &DimensionRegistry,
+    skipped_items: &mut u64,
+) -> PlayerData {
+    // Equipment is head, chest, legs, feet
+    let mut inventory = InventoryData {
+        hotbar: vec![None; 9],
+        main: vec![None; 27],
+        equipment: vec![None; 4],
+        offhand: None,
+    };
+    for item in player.inventory.iter().flatten() {
+        let Some(slot) = item.slot else {
+            continue;
+        };
+        let stack = item_stack(item, skipped_items);
+        match slot {
+            0..=8 => inventory.hotbar[slot as usize] = stack,
+            9..=35 => inventory.main[slot as usize - 9] = stack,
+            // Before 1.21.5 armor was in slots 100 (feet) to 103 (head)
+            100..=103 => inventory.equipment[(103 - slot) as usize] = stack,
+            -106 => inventory.offhand = stack,
+            _ => {}
+        }
+    }
+    if let Some(equipment) = &player.equipment {
+        for (index, item) in [
+            &equipment.head,
+            &equipment.chest,
+            &equipment.legs,
+            &equipment.feet,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if let Some(item) = item {
+                inventory.equipment[index] = item_stack(item, skipped_items);
+            }
+        }
+        if let Some(item) = &equipment.offhand {
+            inventory.offhand = item_stack(item, skipped_items);
+        }
+    }
+    player_data.inventory = inventory;
+    player_data.item_format = Some(ITEM_FORMAT_VERSION);
+
+    let dimension = dimensions.get(player.dimension.as_deref().unwrap_or("overworld"));
+    match (player.pos.as_deref(), dimension) {
+        (Some(&[x, y, z]), Some(dimension)) => {
+            player_data.position = PositionData { x, y, z };
+            player_data.dimension = Some(dimension.name);
+        }
+        _ => {
+            player_data.position = PositionData {
+                x: level_info.spawn_x as f64 + 0.5,
+                y: level_info.spawn_y as f64,
+                z: level_info.spawn_z as f64 + 0.5,
+            };
+            player_data.dimension = None;
+        }
+    }
+    player_data
+}
+
 fn check_paths_validity(import_dir: &Path) -> Result<(), WorldError> {
     if !import_dir.exists() {
         return Err(WorldError::InvalidImportPath(
@@ -202,3 +719,141 @@ fn check_paths_validity(import_dir: &Path) -> Result<(), WorldError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vanilla_save_format::VanillaEquipment;
+    use ferrumc_storage::memory::MemoryBackend;
+
+    fn dimensions() -> DimensionRegistry {
+        DimensionRegistry::load(&MemoryBackend::new()).unwrap()
+    }
+
+    fn item(id: &str, slot: Option<i8>, count: i32) -> VanillaItem {
+        VanillaItem {
+            id: Some(id.to_string()),
+            slot,
+            legacy_count: None,
+            count: Some(count),
+        }
+    }
+
+    #[test]
+    fn test_player_inventory_slots() {
+        let player = VanillaPlayer {
+            pos: Some(vec![10.5, 70.0, -3.5]),
+            dimension: Some("minecraft:overworld".to_string()),
+            inventory: Some(vec![
+                item("minecraft:stone", Some(0), 32),
+                item("minecraft:oak_log", Some(35), 5),
+                item("minecraft:diamond_sword", Some(1), 1),
+                item("minecraft:not_an_item", Some(2), 1),
+                item("minecraft:carved_pumpkin", Some(103), 1),
+            ]),
+            equipment: Some(VanillaEquipment {
+                offhand: Some(item("minecraft:torch", None, 16)),
+                ..Default::default()
+            }),
+        };
+        let mut skipped = 0;
+        let data = player_data_from_vanilla(
+            &player,
+            PlayerData::default(),
+            &LevelInfo::default(),
+            &dimensions(),
+            &mut skipped,
+        );
+
+        let stone = data.inventory.hotbar[0].as_ref().unwrap();
+        assert_eq!(stone.item, ItemId::from_name("minecraft:stone").unwrap().0);
+        assert_eq!(stone.count, 32);
+        assert_eq!(data.inventory.main[26].as_ref().unwrap().count, 5);
+        let sword = data.inventory.hotbar[1].as_ref().unwrap();
+        assert_eq!(
+            sword.item,
+            ItemId::from_name("minecraft:diamond_sword").unwrap().0
+        );
+        assert_eq!(sword.max_stack_size, 1);
+        assert!(data.inventory.hotbar[2].is_none());
+        assert!(data.inventory.equipment[0].is_some());
+        assert!(data.inventory.offhand.is_some());
+        assert_eq!(skipped, 1);
+        assert_eq!(data.position.x, 10.5);
+        assert_eq!(data.position.z, -3.5);
+        assert_eq!(data.dimension.as_deref(), Some("overworld"));
+    }
+
+    #[test]
+    fn test_players_keep_their_dimension() {
+        let player = VanillaPlayer {
+            pos: Some(vec![100.0, 40.0, 100.0]),
+            dimension: Some("minecraft:the_nether".to_string()),
+            ..Default::default()
+        };
+        let data = player_data_from_vanilla(
+            &player,
+            PlayerData::default(),
+            &LevelInfo::default(),
+            &dimensions(),
+            &mut 0,
+        );
+        assert_eq!(data.position.x, 100.0);
+        assert_eq!(data.position.y, 40.0);
+        assert_eq!(data.dimension.as_deref(), Some("nether"));
+    }
+
+    #[test]
+    fn test_players_in_unknown_dimensions_go_to_spawn() {
+        let player = VanillaPlayer {
+            pos: Some(vec![100.0, 40.0, 100.0]),
+            dimension: Some("mymod:moon".to_string()),
+            ..Default::default()
+        };
+        let level_info = LevelInfo {
+            spawn_x: 8,
+            spawn_y: 64,
+            spawn_z: -8,
+            ..Default::default()
+        };
+        let data = player_data_from_vanilla(
+            &player,
+            PlayerData::default(),
+            &level_info,
+            &dimensions(),
+            &mut 0,
+        );
+        assert_eq!(data.position.x, 8.5);
+        assert_eq!(data.position.y, 64.0);
+        assert_eq!(data.position.z, -7.5);
+        assert!(data.dimension.is_none());
+    }
+
+    #[test]
+    fn test_entity_uuid_and_item() {
+        let entity = VanillaEntity {
+            id: Some("minecraft:item".to_string()),
+            uuid: Some(vec![1, 2, 3, -1]),
+            pos: Some(vec![1.0, 2.0, 3.0]),
+            motion: None,
+            rotation: Some(vec![90.0, 0.0]),
+            item: Some(VanillaItem {
+                id: Some("minecraft:diamond".to_string()),
+                slot: None,
+                legacy_count: Some(3),
+                count: None,
+            }),
+        };
+        let stored = stored_entity(&entity).unwrap();
+        assert_eq!(stored.uuid, 0x00000001_00000002_00000003_FFFFFFFF);
+        assert_eq!(stored.position, (1.0, 2.0, 3.0));
+        assert_eq!(stored.yaw, 90.0);
+        assert_eq!(stored.item.unwrap().count, 3);
+
+        let no_position = VanillaEntity {
+            id: Some("minecraft:zombie".to_string()),
+            ..Default::default()
+        };
+        assert!(stored_entity(&no_position).is_none());
+    }
+}
@@ -0,0 +1,377 @@
+//! Item ids, which are numbered separately from blocks.
+//!
+//! Every item in the `minecraft:item` registry gets the protocol id vanilla sends it with. Items
+//! that place a block know which one, and blocks that have an item know it back, so inventories
+//! can hold swords and buckets as well as stone without going through block states.
+//!
+//! Max stack sizes aren't in `registries.json`, vanilla keeps them in each item's components,
+//! which aren't in the assets. Until they are, they're guessed from item names, see
+//! [`ItemId::max_stack_size`]. That's right for vanilla items as of this version, but new items
+//! that don't follow the naming of the existing ones get 64 until they're added to the lists.
+
+use crate::block_id::{BlockId, ID2BLOCK};
+use ahash::RandomState;
+use ferrumc_net_codec::net_types::var_int::VarInt;
+use ferrumc_storage::player_data::{ItemStackData, PlayerData, ITEM_FORMAT_VERSION};
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::warn;
+
+const REGISTRIES: &str = include_str!("../../../../assets/data/registries.json");
+
+/// Items whose block has a different name, e.g. seeds are planted as the crop.
+const BLOCK_NAMES: &[(&str, &str)] = &[
+    ("minecraft:redstone", "minecraft:redstone_wire"),
+    ("minecraft:string", "minecraft:tripwire"),
+    ("minecraft:wheat_seeds", "minecraft:wheat"),
+    ("minecraft:beetroot_seeds", "minecraft:beetroots"),
+    ("minecraft:carrot", "minecraft:carrots"),
+    ("minecraft:potato", "minecraft:potatoes"),
+    ("minecraft:melon_seeds", "minecraft:melon_stem"),
+    ("minecraft:pumpkin_seeds", "minecraft:pumpkin_stem"),
+    ("minecraft:cocoa_beans", "minecraft:cocoa"),
+    ("minecraft:sweet_berries", "minecraft:sweet_berry_bush"),
+    ("minecraft:glow_berries", "minecraft:cave_vines"),
+    ("minecraft:torchflower_seeds", "minecraft:torchflower_crop"),
+    ("minecraft:pitcher_pod", "minecraft:pitcher_crop"),
+];
+
+struct ItemInfo {
+    name: String,
+    max_stack_size: u8,
+    block: Option<BlockId>,
+}
+
+struct Items {
+    /// Indexed by protocol id.
+    items: Vec<ItemInfo>,
+    by_name: HashMap<String, ItemId, RandomState>,
+    /// The item of every block that has one, keyed by the block's first state.
+    by_block: HashMap<u32, ItemId, RandomState>,
+}
+
+lazy_static! {
+    static ref ITEMS: Items = load_items();
+}
+
+fn load_items() -> Items {
+    let registries: Value = serde_json::from_str(REGISTRIES).expect("registries.json is invalid");
+    let entries = registries["minecraft:item"]["entries"]
+        .as_object()
+        .expect("registries.json has no items");
+
+    let mut first_states: HashMap<&str, BlockId, RandomState> = HashMap::default();
+    for (id, block) in ID2BLOCK.iter().enumerate() {
+        first_states
+            .entry(block.name.as_str())
+            .or_insert(BlockId(id as u32));
+    }
+    let block_names: HashMap<&str, &str> = BLOCK_NAMES.iter().copied().collect();
+
+    let mut items: Vec<Option<ItemInfo>> = Vec::new();
+    items.resize_with(entries.len(), || None);
+    let mut by_name = HashMap::default();
+    let mut by_block = HashMap::default();
+    for (name, entry) in entries {
+        let Some(id) = entry["protocol_id"].as_u64() else {
+            warn!("Item {} has no protocol id", name);
+            continue;
+        };
+        if id as usize >= items.len() {
+            items.resize_with(id as usize + 1, || None);
+        }
+        let block_name = block_names.get(name.as_str()).copied().unwrap_or(name);
+        let block = first_states.get(block_name).copied();
+        if let Some(block) = block {
+            by_block.insert(block.0, ItemId(id as u32));
+        }
+        by_name.insert(name.clone(), ItemId(id as u32));
+        items[id as usize] = Some(ItemInfo {
+            name: name.clone(),
+            max_stack_size: max_stack_size(name),
+            block,
+        });
+    }
+
+    // Blocks that only exist on a wall, like wall torches and signs, drop the item they were
+    // placed with
+    for (name, block) in &first_states {
+        if by_block.contains_key(&block.0) || !name.contains("wall_") {
+            continue;
+        }
+        if let Some(item) = by_name.get(&name.replacen("wall_", "", 1)) {
+            by_block.insert(block.0, *item);
+        }
+    }
+
+    let items = items
+        .into_iter()
+        .enumerate()
+        .map(|(id, item)| {
+            item.unwrap_or_else(|| {
+                warn!("There's no item with protocol id {}", id);
+                ItemInfo {
+                    name: "minecraft:air".to_string(),
+                    max_stack_size: 64,
+                    block: None,
+                }
+            })
+        })
+        .collect();
+    Items {
+        items,
+        by_name,
+        by_block,
+    }
+}
+
+/// How many of an item fit in one slot. The registry doesn't say, so this goes by the name, with
+/// the items that don't stack or stack to 16 listed by name or by what their names end in.
+fn max_stack_size(name: &str) -> u8 {
+    let name = name.trim_start_matches("minecraft:");
+    const SINGLE: &[&str] = &[
+        "bow",
+        "crossbow",
+        "trident",
+        "mace",
+        "shield",
+        "elytra",
+        "fishing_rod",
+        "flint_and_steel",
+        "shears",
+        "brush",
+        "spyglass",
+        "carrot_on_a_stick",
+        "warped_fungus_on_a_stick",
+        "potion",
+        "splash_potion",
+        "lingering_potion",
+        "saddle",
+        "totem_of_undying",
+        "enchanted_book",
+        "writable_book",
+        "knowledge_book",
+        "debug_stick",
+        "bundle",
+        "goat_horn",
+        "cake",
+        "wolf_armor",
+        "minecart",
+        "shulker_box",
+    ];
+    const SINGLE_SUFFIXES: &[&str] = &[
+        "_sword",
+        "_pickaxe",
+        "_axe",
+        "_shovel",
+        "_hoe",
+        "_helmet",
+        "_chestplate",
+        "_leggings",
+        "_boots",
+        "_horse_armor",
+        "_bucket",
+        "_banner_pattern",
+        "_boat",
+        "_raft",
+        "_minecart",
+        "_shulker_box",
+        "_bed",
+        "_stew",
+        "_soup",
+    ];
+    const SIXTEEN: &[&str] = &[
+        "bucket",
+        "ender_pearl",
+        "snowball",
+        "egg",
+        "honey_bottle",
+        "armor_stand",
+        "written_book",
+    ];
+    const SIXTEEN_SUFFIXES: &[&str] = &["_sign", "_banner"];
+
+    if SINGLE.contains(&name)
+        || name.starts_with("music_disc_")
+        || SINGLE_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+    {
+        1
+    } else if SIXTEEN.contains(&name) || SIXTEEN_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+        16
+    } else {
+        64
+    }
+}
+
+/// An item, by the protocol id vanilla uses for it. Not the same numbers as [`BlockId`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ItemId(pub u32);
+
+impl ItemId {
+    /// Look an item up by its name, e.g. `minecraft:diamond_sword`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        ITEMS.by_name.get(name).copied()
+    }
+
+    /// The item a block is placed with, in any of its states. Returns None for blocks that can't
+    /// be held, like fire.
+    pub fn from_block(block: BlockId) -> Option<Self> {
+        let first = block.first_state()?;
+        ITEMS.by_block.get(&first.0).copied()
+    }
+
+    /// The name of the item. Returns None if the id is invalid.
+    pub fn name(&self) -> Option<&'static str> {
+        ITEMS
+            .items
+            .get(self.0 as usize)
+            .map(|item| item.name.as_str())
+    }
+
+    /// How many of the item fit in one slot, 64 for invalid ids.
+    ///
+    /// This is worked out from the item's name rather than read from vanilla's data, see the
+    /// [module docs](crate::item_id) for why.
+    pub fn max_stack_size(&self) -> u8 {
+        ITEMS
+            .items
+            .get(self.0 as usize)
+            .map_or(64, |item| item.max_stack_size)
+    }
+
+    /// The block this item places, in its default state. Returns None for items that aren't
+    /// blocks.
+    pub fn block(&self) -> Option<BlockId> {
+        ITEMS.items.get(self.0 as usize).and_then(|item| item.block)
+    }
+
+    pub fn from_varint(var_int: VarInt) -> Self {
+        ItemId(var_int.0 as u32)
+    }
+
+    pub fn to_varint(&self) -> VarInt {
+        VarInt(self.0 as i32)
+    }
+}
+
+impl Default for ItemId {
+    /// Returns air, which is what empty slots hold.
+    fn default() -> Self {
+        Self(0)
+    }
+}
+
+/// Upgrade the inventory of player data saved before items had their own ids.
+///
+/// Those inventories hold block state ids, which are swapped for the item of the block. Blocks
+/// without an item can't have been held legitimately, so they're dropped. Does nothing if the
+/// data is already up to date.
+pub fn upgrade_player_data(data: &mut PlayerData) {
+    if data.item_format.is_some_and(|v| v >= ITEM_FORMAT_VERSION) {
+        return;
+    }
+    let inventory = &mut data.inventory;
+    let slots = inventory
+        .hotbar
+        .iter_mut()
+        .chain(inventory.main.iter_mut())
+        .chain(inventory.equipment.iter_mut())
+        .chain(std::iter::once(&mut inventory.offhand));
+    for slot in slots {
+        *slot = slot.take().and_then(upgrade_stack);
+    }
+    data.item_format = Some(ITEM_FORMAT_VERSION);
+}
+
+fn upgrade_stack(stack: ItemStackData) -> Option<ItemStackData> {
+    let Some(item) = ItemId::from_block(BlockId(stack.item)) else {
+        warn!("Dropping stored block {} that has no item", stack.item);
+        return None;
+    };
+    let max_stack_size = item.max_stack_size();
+    Some(ItemStackData {
+        item: item.0,
+        count: stack.count.min(max_stack_size),
+        max_stack_size,
+        ..stack
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrumc_storage::player_data::InventoryData;
+
+    fn item(name: &str) -> ItemId {
+        ItemId::from_name(name).unwrap()
+    }
+
+    #[test]
+    fn test_items_and_blocks() {
+        assert_eq!(ItemId::default().name(), Some("minecraft:air"));
+        assert_eq!(item("minecraft:stone").name(), Some("minecraft:stone"));
+
+        let sword = item("minecraft:diamond_sword");
+        assert_eq!(sword.block(), None);
+        assert_eq!(sword.max_stack_size(), 1);
+        assert_eq!(item("minecraft:ender_pearl").max_stack_size(), 16);
+        assert_eq!(item("minecraft:bucket").max_stack_size(), 16);
+        assert_eq!(item("minecraft:water_bucket").max_stack_size(), 1);
+        assert_eq!(item("minecraft:cobblestone").max_stack_size(), 64);
+
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        assert_eq!(item("minecraft:stone").block(), Some(stone));
+        assert_eq!(ItemId::from_block(stone), Some(item("minecraft:stone")));
+        // Any state of the block gives the same item
+        let log = BlockId::from_name("minecraft:oak_log").unwrap();
+        let log_x = log.with_property("axis", "x").unwrap();
+        assert_eq!(ItemId::from_block(log_x), Some(item("minecraft:oak_log")));
+
+        let wire = BlockId::from_name("minecraft:redstone_wire").unwrap();
+        assert_eq!(item("minecraft:redstone").block(), Some(wire));
+        assert_eq!(ItemId::from_block(wire), Some(item("minecraft:redstone")));
+        let wall_torch = BlockId::from_name("minecraft:wall_torch").unwrap();
+        assert_eq!(
+            ItemId::from_block(wall_torch),
+            Some(item("minecraft:torch"))
+        );
+        let fire = BlockId::from_name("minecraft:fire").unwrap();
+        assert_eq!(ItemId::from_block(fire), None);
+    }
+
+    #[test]
+    fn test_upgrading_player_data() {
+        let stored = |item: u32, count: u8| ItemStackData {
+            item,
+            count,
+            max_stack_size: 64,
+            nbt: None,
+        };
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        let fire = BlockId::from_name("minecraft:fire").unwrap();
+        let mut data = PlayerData {
+            inventory: InventoryData {
+                hotbar: vec![Some(stored(stone.0, 10)), Some(stored(fire.0, 1)), None],
+                main: vec![],
+                equipment: vec![],
+                offhand: None,
+            },
+            item_format: None,
+            ..Default::default()
+        };
+
+        upgrade_player_data(&mut data);
+        let hotbar = &data.inventory.hotbar;
+        assert_eq!(hotbar[0].as_ref().unwrap().item, item("minecraft:stone").0);
+        assert_eq!(hotbar[0].as_ref().unwrap().count, 10);
+        assert!(hotbar[1].is_none());
+        assert!(hotbar[2].is_none());
+        assert_eq!(data.item_format, Some(ITEM_FORMAT_VERSION));
+
+        // Up to date data is left alone
+        let before = data.inventory.hotbar[0].as_ref().unwrap().item;
+        upgrade_player_data(&mut data);
+        assert_eq!(data.inventory.hotbar[0].as_ref().unwrap().item, before);
+    }
+}
@@ -0,0 +1,59 @@
+//! Settings that belong to the world as a whole rather than a chunk, like the spawn point.
+//!
+//! Kept as a single record in the `level` table, the equivalent of a vanilla `level.dat`.
+
+use crate::errors::WorldError;
+use crate::World;
+use bitcode_derive::{Decode, Encode};
+
+const TABLE: &str = "level";
+const KEY: u128 = 0;
+
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct LevelInfo {
+    pub spawn_x: i32,
+    pub spawn_y: i32,
+    pub spawn_z: i32,
+    pub seed: i64,
+    /// Ticks since the world was created.
+    pub time: i64,
+    /// The time of day, in ticks.
+    pub day_time: i64,
+}
+
+impl Default for LevelInfo {
+    fn default() -> Self {
+        LevelInfo {
+            spawn_x: 0,
+            spawn_y: 100,
+            spawn_z: 0,
+            seed: 0,
+            time: 0,
+            day_time: 0,
+        }
+    }
+}
+
+impl World {
+    /// Get the world's level info, or the defaults if none has been saved yet.
+    pub fn level_info(&self) -> Result<LevelInfo, WorldError> {
+        if !self.storage_backend.table_exists(TABLE.to_string())? {
+            return Ok(LevelInfo::default());
+        }
+        match self.storage_backend.get(TABLE.to_string(), KEY)? {
+            Some(data) => {
+                bitcode::decode(&data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))
+            }
+            None => Ok(LevelInfo::default()),
+        }
+    }
+
+    pub fn save_level_info(&self, level_info: &LevelInfo) -> Result<(), WorldError> {
+        if !self.storage_backend.table_exists(TABLE.to_string())? {
+            self.storage_backend.create_table(TABLE.to_string())?;
+        }
+        self.storage_backend
+            .upsert(TABLE.to_string(), KEY, bitcode::encode(level_info))?;
+        Ok(())
+    }
+}
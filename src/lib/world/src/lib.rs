@@ -1,66 +1,147 @@
+pub mod backups;
 pub mod biome_id;
 pub mod block_entities;
 pub mod block_id;
 pub mod chunk_format;
+mod codec;
+mod data_fixer;
 mod db_functions;
+pub mod dimensions;
 pub mod edit_batch;
 pub mod edits;
+pub mod entities;
 pub mod errors;
+mod exporting;
+pub mod fluids;
+pub mod game_rules;
+pub mod heightmaps;
+pub mod hoppers;
 mod importing;
+pub mod item_id;
+pub mod level;
+pub mod light;
+mod migrations;
+pub mod pistons;
 pub mod recipes;
 pub mod redstone;
+pub mod region_edit;
+pub mod schematic;
+#[cfg(test)]
+mod testing;
 pub mod tick;
+pub mod trimming;
 pub mod vanilla_chunk_format;
+mod vanilla_save_format;
+mod write_behind;
 
 use crate::chunk_format::Chunk;
+use crate::dimensions::{Dimension, DimensionRegistry, DimensionType};
 use crate::errors::WorldError;
+use crate::write_behind::{DirtyChunks, WriteBehindWorker};
 use deepsize::DeepSizeOf;
-use ferrumc_config::server_config::get_global_config;
+use ferrumc_config::server_config::{get_global_config, ServerConfig};
 use ferrumc_general_purpose::paths::get_root_path;
+use ferrumc_storage::backend::StorageBackend;
+use ferrumc_storage::compressors::{Compressor, CompressorType};
 use ferrumc_storage::lmdb::LmdbBackend;
+use ferrumc_storage::memory::MemoryBackend;
+use ferrumc_storage::redb_backend::RedbBackend;
 use moka::{notification::RemovalCause, sync::Cache};
-use std::fs::create_dir_all;
+use std::collections::HashMap;
+use std::fs::{create_dir_all, File};
 use std::path::{Path, PathBuf};
-use std::process::exit;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tracing::{error, trace, warn};
 
-pub const OVERWORLD_ID: i32 = 0;
-pub const NETHER_ID: i32 = -1;
-pub const END_ID: i32 = 1;
+pub use dimensions::{END_ID, NETHER_ID, OVERWORLD_ID};
 
-pub fn dimension_id(name: &str) -> i32 {
-    match name {
-        "overworld" | "minecraft:overworld" => OVERWORLD_ID,
-        "nether" | "minecraft:the_nether" => NETHER_ID,
-        "end" | "minecraft:the_end" => END_ID,
-        _ => OVERWORLD_ID,
-    }
+#[derive(Clone)]
+pub struct World {
+    storage_backend: Arc<dyn StorageBackend>,
+    compressor: Compressor,
+    dimensions: Arc<DimensionRegistry>,
+    dirty: Arc<DirtyChunks>,
+    /// `None` when chunks are written straight away instead.
+    write_behind: Option<Arc<WriteBehindWorker>>,
+    cache: Cache<(i32, i32, String), Arc<Chunk>>,
+    pub(crate) tick_manager: Arc<Mutex<tick::TickManager>>,
+    /// Ticks to save with chunks that were unloaded or restore from ones that were loaded.
+    pub(crate) tick_transfers: Arc<Mutex<Vec<tick::TickTransfer>>>,
+    /// The sections of loaded chunks with blocks in them that react to random ticks.
+    pub(crate) random_sections: Arc<Mutex<HashMap<(i32, i32, String), Vec<i8>>>>,
+    pub(crate) redstone: Arc<Mutex<redstone::RedstoneNetworks>>,
+    pub(crate) pistons: Arc<Mutex<pistons::Pistons>>,
+    pub(crate) hoppers: Arc<Mutex<hoppers::Hoppers>>,
+    game_rules: Arc<Mutex<game_rules::GameRules>>,
+    /// Block entities created or changed since players were last sent them.
+    block_entity_updates: Arc<Mutex<Vec<block_entities::BlockEntityUpdate>>>,
+    region_edits: Arc<Mutex<region_edit::EditHistory>>,
+    /// Held for as long as the world is open, so the database can't be restored under it. `None`
+    /// for worlds made with [`World::with_backend`].
+    db_lock: Option<Arc<File>>,
+    /// Where the database is, with the root path in front if it was configured relative to it.
+    /// `None` for worlds made with [`World::with_backend`].
+    db_path: Option<PathBuf>,
+    verify_chunk_data: bool,
 }
 
-pub fn dimension_name(id: i32) -> &'static str {
-    match id {
-        NETHER_ID => "nether",
-        END_ID => "end",
-        _ => "overworld",
-    }
+/// What a world is opened with, besides its storage backend.
+///
+/// [`World::new`] takes these from the config. The defaults are the same as the default config
+/// without any extra dimensions, so worlds made with [`World::with_backend`] don't depend on the
+/// config at all.
+#[derive(Clone, Debug)]
+pub struct WorldOptions {
+    /// What chunks are compressed with when they're saved.
+    pub compressor: Compressor,
+    /// Whether to check the checksum of chunks when they're loaded.
+    pub verify_chunk_data: bool,
+    /// How long chunks stay cached after they're loaded, in seconds.
+    pub cache_ttl: u64,
+    /// How big the chunk cache can get, in kilobytes. Either both this and `cache_ttl` are 0 or
+    /// neither is.
+    pub cache_capacity: u64,
+    /// The longest a changed chunk waits before it's written, in milliseconds. 0 writes every
+    /// change straight away.
+    pub flush_interval: u64,
+    /// How many changed chunks can pile up before they're written early.
+    pub flush_batch_size: usize,
+    /// Dimensions to host on top of the overworld, nether and end.
+    pub dimensions: Vec<(String, DimensionType)>,
 }
 
-pub fn dimension_registry_name(id: i32) -> &'static str {
-    match id {
-        NETHER_ID => "minecraft:the_nether",
-        END_ID => "minecraft:the_end",
-        _ => "minecraft:overworld",
+impl WorldOptions {
+    /// Take the options from the database and dimension sections of the config.
+    pub fn from_config(config: &ServerConfig) -> Result<Self, WorldError> {
+        Ok(WorldOptions {
+            compressor: codec::compressor_from_config(&config.database)?,
+            verify_chunk_data: config.database.verify_chunk_data,
+            cache_ttl: config.database.cache_ttl,
+            cache_capacity: config.database.cache_capacity,
+            flush_interval: config.database.flush_interval,
+            flush_batch_size: config.database.flush_batch_size,
+            dimensions: config
+                .dimensions
+                .iter()
+                .map(|dimension| (dimension.name.clone(), dimension.into()))
+                .collect(),
+        })
     }
 }
 
-#[derive(Clone)]
-pub struct World {
-    storage_backend: LmdbBackend,
-    cache: Cache<(i32, i32, String), Arc<Chunk>>,
-    pub(crate) tick_manager: Arc<Mutex<tick::TickManager>>,
-    pub(crate) redstone_cache: Arc<Mutex<redstone::PowerLevelCache>>,
+impl Default for WorldOptions {
+    fn default() -> Self {
+        WorldOptions {
+            compressor: Compressor::create(CompressorType::Zlib, 1),
+            verify_chunk_data: true,
+            cache_ttl: 60,
+            cache_capacity: 20_000,
+            flush_interval: 1000,
+            flush_batch_size: 256,
+            dimensions: Vec::new(),
+        }
+    }
 }
 
 fn check_config_validity() -> Result<(), WorldError> {
@@ -118,6 +199,20 @@ fn check_config_validity() -> Result<(), WorldError> {
         );
         return Err(WorldError::InvalidMapSize(config.database.map_size));
     }
+    if !["lmdb", "redb", "memory"].contains(&config.database.backend.as_str()) {
+        error!(
+            "Unknown database backend \"{}\". Valid backends are lmdb, redb and memory.",
+            config.database.backend
+        );
+        return Err(WorldError::InvalidBackend(config.database.backend.clone()));
+    }
+    if let Err(e) = codec::compressor_from_config(&config.database) {
+        error!(
+            "Invalid chunk compression settings. Check the compression and compression_level \
+        options in the configuration file."
+        );
+        return Err(e);
+    }
     Ok(())
 }
 
@@ -126,55 +221,156 @@ impl World {
     ///
     /// You'd probably want to call this at the start of your program. And then use the returned
     /// in a state struct or something.
-    pub fn new(backend_path: impl Into<PathBuf>) -> Self {
-        recipes::init();
+    ///
+    /// The database is opened at `backend_path` with the backend set in the config, LMDB unless
+    /// changed. Fails if the config is invalid, the database can't be opened, or another world
+    /// already has it open.
+    pub fn new(backend_path: impl Into<PathBuf>) -> Result<Self, WorldError> {
         if let Err(e) = check_config_validity() {
             error!("Fatal error in database config: {}", e);
-            exit(1);
+            return Err(e);
         }
         let mut backend_path = backend_path.into();
         // Clones are kinda ok here since this is only run once at startup.
         if backend_path.is_relative() {
             backend_path = get_root_path().join(backend_path);
         }
-        let storage_backend =
-            LmdbBackend::initialize(Some(backend_path)).expect("Failed to initialize database");
+        let db_lock = backups::lock_database(&backend_path)?;
+        let storage_backend: Arc<dyn StorageBackend> =
+            match get_global_config().database.backend.as_str() {
+                "redb" => Arc::new(RedbBackend::initialize(Some(backend_path.clone()))?),
+                "memory" => {
+                    warn!("Using the in-memory database backend, nothing will be saved!");
+                    Arc::new(MemoryBackend::new())
+                }
+                _ => Arc::new(LmdbBackend::initialize(Some(backend_path.clone()))?),
+            };
+        let options = WorldOptions::from_config(get_global_config())?;
+        Ok(World {
+            db_lock: Some(Arc::new(db_lock)),
+            db_path: Some(backend_path),
+            ..Self::with_options(storage_backend, options)?
+        })
+    }
+
+    /// Creates a new world instance on top of an already opened storage backend, with the
+    /// default [`WorldOptions`].
+    ///
+    /// Unlike [`World::new`] this doesn't check or create the database folder or read the config,
+    /// so it works with any backend, e.g. a [`MemoryBackend`] for tests that shouldn't touch the
+    /// disk.
+    pub fn with_backend(storage_backend: Arc<dyn StorageBackend>) -> Result<Self, WorldError> {
+        Self::with_options(storage_backend, WorldOptions::default())
+    }
 
-        if get_global_config().database.cache_ttl != 0
-            && get_global_config().database.cache_capacity == 0
-        {
+    /// Creates a new world instance on top of an already opened storage backend.
+    ///
+    /// Fails if the options are invalid, or the dimensions or game rules saved in the database
+    /// can't be loaded.
+    pub fn with_options(
+        storage_backend: Arc<dyn StorageBackend>,
+        options: WorldOptions,
+    ) -> Result<Self, WorldError> {
+        recipes::init();
+        let compressor = codec::validate_compressor(options.compressor)?;
+        if (options.cache_ttl == 0) != (options.cache_capacity == 0) {
             error!("Cache TTL and capacity must both be set to 0 or both be set to a value greater than 0.");
-            exit(1);
+            return Err(WorldError::InvalidCacheSize(format!(
+                "TTL {}, capacity {}",
+                options.cache_ttl, options.cache_capacity
+            )));
+        }
+
+        let dimensions = Arc::new(DimensionRegistry::load(storage_backend.as_ref())?);
+        for (name, dimension_type) in options.dimensions {
+            if let Err(e) = dimensions.register(storage_backend.as_ref(), &name, dimension_type) {
+                error!("Failed to register dimension \"{}\": {}", name, e);
+                return Err(e);
+            }
         }
+        let game_rules = Arc::new(Mutex::new(game_rules::GameRules::load(
+            storage_backend.as_ref(),
+        )?));
+
+        let dirty = Arc::new(DirtyChunks::new(options.flush_batch_size));
+        let write_behind = (options.flush_interval != 0).then(|| {
+            Arc::new(WriteBehindWorker::spawn(
+                dirty.clone(),
+                storage_backend.clone(),
+                dimensions.clone(),
+                compressor,
+                Duration::from_millis(options.flush_interval),
+            ))
+        });
 
         let tick_manager = Arc::new(Mutex::new(tick::TickManager::default()));
-        let redstone_cache = Arc::new(Mutex::new(redstone::PowerLevelCache::default()));
+        let tick_transfers = Arc::new(Mutex::new(Vec::new()));
         let tm_clone = Arc::clone(&tick_manager);
+        let transfers_clone = Arc::clone(&tick_transfers);
+        let dirty_clone = Arc::clone(&dirty);
         let eviction_listener =
-            move |key: Arc<(i32, i32, String)>, _: Arc<Chunk>, cause: RemovalCause| {
+            move |key: Arc<(i32, i32, String)>, chunk: Arc<Chunk>, cause: RemovalCause| {
                 trace!("Evicting key: {:?}, cause: {:?}", key, cause);
-                let (cx, cz, dim) = &*key;
-                tm_clone.lock().unwrap().cleanup_chunk(*cx, *cz, dim);
+                if cause.was_evicted() {
+                    // Unsaved chunks are safe in the dirty set, but get them on disk soon rather
+                    // than holding on to chunks nobody is using
+                    if dirty_clone.contains(&key) {
+                        dirty_clone.wake();
+                    }
+                    // The ticks waiting in it are saved with it on the next world tick
+                    transfers_clone
+                        .lock()
+                        .unwrap()
+                        .push(tick::TickTransfer::Unloaded(chunk));
+                } else if matches!(cause, RemovalCause::Explicit) {
+                    let (cx, cz, dim) = &*key;
+                    tm_clone.lock().unwrap().cleanup_chunk(*cx, *cz, dim);
+                }
             };
 
         let cache = Cache::builder()
             .eviction_listener(eviction_listener)
             .weigher(|_k, v: &Arc<Chunk>| v.deep_size_of() as u32)
-            .time_to_live(Duration::from_secs(get_global_config().database.cache_ttl))
-            .max_capacity(get_global_config().database.cache_capacity * 1024)
+            .time_to_live(Duration::from_secs(options.cache_ttl))
+            .max_capacity(options.cache_capacity * 1024)
             .build();
 
-        World {
+        Ok(World {
             storage_backend,
+            compressor,
+            dimensions,
+            dirty,
+            write_behind,
             cache,
             tick_manager,
-            redstone_cache,
-        }
+            tick_transfers,
+            random_sections: Default::default(),
+            redstone: Default::default(),
+            pistons: Default::default(),
+            hoppers: Default::default(),
+            game_rules,
+            block_entity_updates: Default::default(),
+            region_edits: Default::default(),
+            db_lock: None,
+            db_path: None,
+            verify_chunk_data: options.verify_chunk_data,
+        })
     }
 
-    /// Ticks the world, processing scheduled and random block updates.
+    /// Ticks the world, processing scheduled and random block updates, then redstone and the
+    /// pistons it moves, then hoppers. The ticks of chunks that were loaded or unloaded since the
+    /// last tick are restored or saved first.
     pub fn tick(&self) -> Result<(), WorldError> {
-        self.tick_manager.lock().unwrap().tick_world(self)
+        self.transfer_ticks()?;
+        // Blocks schedule more ticks while they're being ticked, and deleting chunks clears
+        // theirs, so the manager can't stay locked the whole time
+        let mut tick_manager = self.tick_manager.lock().unwrap().take();
+        let result = tick_manager.tick_world(self);
+        self.tick_manager.lock().unwrap().merge(tick_manager);
+        result?;
+        self.tick_redstone()?;
+        self.tick_pistons()?;
+        self.tick_hoppers()
     }
 
     /// Schedule a future tick for the block at the given position.
@@ -187,25 +383,49 @@ impl World {
         tick::schedule_random_tick(self, x, y, z, dimension, chance);
     }
 
-    /// Get cached redstone power level at position.
+    /// Get the power of the wire at a position, or the signal the component there gives out.
     pub fn get_power_level(&self, x: i32, y: i32, z: i32, dimension: &str) -> u8 {
-        let cache = self.redstone_cache.lock().unwrap();
-        *cache
-            .levels
-            .get(&(x, y, z, dimension.to_string()))
-            .unwrap_or(&0)
+        self.redstone.lock().unwrap().power_at(dimension, (x, y, z))
     }
 
-    /// Remove all pending ticks associated with a dimension.
+    /// Remove all pending ticks, redstone networks, moving blocks and hopper state associated with
+    /// a dimension.
     pub fn unload_dimension(&self, dimension: &str) {
         self.tick_manager
             .lock()
             .unwrap()
             .cleanup_dimension(dimension);
+        self.redstone.lock().unwrap().unload_dimension(dimension);
+        self.pistons.lock().unwrap().unload_dimension(dimension);
+        self.hoppers.lock().unwrap().unload_dimension(dimension);
+        self.tick_transfers
+            .lock()
+            .unwrap()
+            .retain(|transfer| match transfer {
+                tick::TickTransfer::Loaded((_, _, dim), _) => dim != dimension,
+                tick::TickTransfer::Unloaded(chunk) => chunk.dimension != dimension,
+            });
+    }
+
+    pub fn backend(&self) -> &dyn StorageBackend {
+        self.storage_backend.as_ref()
     }
 
-    pub fn backend(&self) -> &LmdbBackend {
-        &self.storage_backend
+    pub fn dimensions(&self) -> &DimensionRegistry {
+        &self.dimensions
+    }
+
+    /// Add a new dimension to the world, or change the type of an existing one.
+    ///
+    /// Dimensions from the config are registered when the world is opened, this is for ones
+    /// created while the server is running.
+    pub fn register_dimension(
+        &self,
+        name: &str,
+        dimension_type: DimensionType,
+    ) -> Result<Dimension, WorldError> {
+        self.dimensions
+            .register(self.storage_backend.as_ref(), name, dimension_type)
     }
 }
 
@@ -220,10 +440,11 @@ mod tests {
             std::env::current_dir()
                 .unwrap()
                 .join("../../../target/debug/world"),
-        );
+        )
+        .unwrap();
         let chunk = world.load_chunk(1, 1, "overworld").expect(
             "Failed to load chunk. If it's a bitcode error, chances are the chunk format \
-             has changed since last generating a world so you'll need to regenerate",
+             has changed without a migration being added, see migrations.rs",
         );
         let encoded = bitcode::encode(&chunk);
         std::fs::write("../../../.etc/raw_chunk.dat", encoded).unwrap();
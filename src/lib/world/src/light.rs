@@ -0,0 +1,1263 @@
+//! Sky light and block light engine.
+//!
+//! Light is stored per section as two nibble arrays (`Section::sky_light` and
+//! `Section::block_light`), 2048 bytes each, using the same `y << 8 | z << 4 | x` ordering as the
+//! vanilla format. Every block state has an emission level and an opacity, looked up from
+//! [`light_emission`] and [`light_opacity`].
+//!
+//! The engine works on a [`LightRegion`]: a chunk plus whichever of its 8 neighbours are loaded.
+//! Light can travel at most 15 blocks, so a change inside one chunk can never reach further than
+//! its direct neighbours, which means a 3x3 region is always enough to update light exactly.
+//!
+//! Updates use the usual two pass flood fill:
+//! 1. Removal: the old light of every changed block is removed, spreading outwards to every block
+//!    that could have been lit by it. Brighter blocks found on the way are queued for re-lighting.
+//! 2. Increase: sources (emitting blocks, the open sky and the queued blocks) are flooded out,
+//!    losing `max(1, opacity)` levels per step. Full sky light travels straight down without loss.
+
+use crate::block_id::{BlockId, ID2BLOCK};
+use crate::chunk_format::{Chunk, PaletteType, Section};
+use crate::edit_batch::EditBatch;
+use crate::errors::WorldError;
+use crate::vanilla_chunk_format::BlockData;
+use crate::World;
+use lazy_static::lazy_static;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use tracing::{trace, warn};
+
+/// The brightest a block can be lit.
+pub const MAX_LIGHT: u8 = 15;
+
+/// The two kinds of light tracked for every block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum LightType {
+    /// Light coming from the sky. Only present in dimensions that have a sky.
+    Sky,
+    /// Light emitted by blocks such as torches, lava or glowstone.
+    Block,
+}
+
+const DIRECTIONS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+// Blocks that let light through completely.
+const TRANSPARENT_BLOCKS: &[&str] = &[
+    "air",
+    "cave_air",
+    "void_air",
+    "barrier",
+    "light",
+    "structure_void",
+    "torch",
+    "wall_torch",
+    "soul_torch",
+    "soul_wall_torch",
+    "redstone_torch",
+    "redstone_wall_torch",
+    "ladder",
+    "lever",
+    "vine",
+    "redstone_wire",
+    "repeater",
+    "comparator",
+    "tripwire",
+    "tripwire_hook",
+    "cobweb",
+    "fire",
+    "soul_fire",
+    "nether_portal",
+    "end_portal",
+    "end_gateway",
+    "end_portal_frame",
+    "flower_pot",
+    "grass",
+    "fern",
+    "sugar_cane",
+    "cactus",
+    "bamboo",
+    "bamboo_sapling",
+    "scaffolding",
+    "snow",
+    "cake",
+    "chest",
+    "trapped_chest",
+    "ender_chest",
+    "enchanting_table",
+    "brewing_stand",
+    "hopper",
+    "lectern",
+    "grindstone",
+    "stonecutter",
+    "bell",
+    "beacon",
+    "conduit",
+    "lantern",
+    "soul_lantern",
+    "chain",
+    "iron_bars",
+    "campfire",
+    "soul_campfire",
+    "anvil",
+    "chipped_anvil",
+    "damaged_anvil",
+    "cauldron",
+    "water_cauldron",
+    "lava_cauldron",
+    "powder_snow_cauldron",
+    "daylight_detector",
+    "piston_head",
+    "moving_piston",
+    "dragon_egg",
+    "wheat",
+    "carrots",
+    "potatoes",
+    "beetroots",
+    "melon_stem",
+    "pumpkin_stem",
+    "attached_melon_stem",
+    "attached_pumpkin_stem",
+    "nether_wart",
+    "cocoa",
+    "lily_pad",
+    "dandelion",
+    "poppy",
+    "blue_orchid",
+    "allium",
+    "azure_bluet",
+    "oxeye_daisy",
+    "cornflower",
+    "lily_of_the_valley",
+    "wither_rose",
+    "sunflower",
+    "lilac",
+    "peony",
+    "torchflower",
+    "pink_petals",
+    "spore_blossom",
+    "hanging_roots",
+    "glow_lichen",
+    "sculk_vein",
+    "big_dripleaf_stem",
+    "sweet_berry_bush",
+];
+
+// Suffixes of block families that are not full cubes and therefore don't block light.
+const TRANSPARENT_SUFFIXES: &[&str] = &[
+    "glass",
+    "_pane",
+    "_slab",
+    "_stairs",
+    "_fence",
+    "_fence_gate",
+    "_wall",
+    "_door",
+    "_trapdoor",
+    "_sign",
+    "_button",
+    "_pressure_plate",
+    "_carpet",
+    "_bed",
+    "_banner",
+    "candle",
+    "_head",
+    "_skull",
+    "_sapling",
+    "rail",
+    "_tulip",
+    "_mushroom",
+    "_fungus",
+    "_roots",
+    "_sprouts",
+    "_bush",
+    "_grass",
+    "_fern",
+    "_rod",
+    "_cluster",
+    "_bud",
+    "_dripleaf",
+    "_vines",
+    "_vines_plant",
+    "_coral",
+    "_coral_fan",
+];
+
+// Blocks that let light through, but dim it by one level per block.
+const FILTERING_BLOCKS: &[&str] = &[
+    "water",
+    "lava",
+    "bubble_column",
+    "ice",
+    "frosted_ice",
+    "slime_block",
+    "honey_block",
+    "seagrass",
+    "tall_seagrass",
+    "kelp",
+    "kelp_plant",
+    "sea_pickle",
+];
+
+// Blocks that always emit the same light level, regardless of their state.
+const EMITTING_BLOCKS: &[(&str, u8)] = &[
+    ("glowstone", 15),
+    ("sea_lantern", 15),
+    ("lantern", 15),
+    ("jack_o_lantern", 15),
+    ("beacon", 15),
+    ("conduit", 15),
+    ("shroomlight", 15),
+    ("lava", 15),
+    ("fire", 15),
+    ("end_gateway", 15),
+    ("end_portal", 15),
+    ("ochre_froglight", 15),
+    ("verdant_froglight", 15),
+    ("pearlescent_froglight", 15),
+    ("torch", 14),
+    ("wall_torch", 14),
+    ("end_rod", 14),
+    ("nether_portal", 11),
+    ("soul_torch", 10),
+    ("soul_wall_torch", 10),
+    ("soul_lantern", 10),
+    ("soul_fire", 10),
+    ("crying_obsidian", 10),
+    ("enchanting_table", 7),
+    ("ender_chest", 7),
+    ("glow_lichen", 7),
+    ("amethyst_cluster", 5),
+    ("large_amethyst_bud", 4),
+    ("magma_block", 3),
+    ("medium_amethyst_bud", 2),
+    ("brewing_stand", 1),
+    ("brown_mushroom", 1),
+    ("dragon_egg", 1),
+    ("end_portal_frame", 1),
+    ("sculk_sensor", 1),
+    ("small_amethyst_bud", 1),
+];
+
+// Blocks that only emit light while their `lit` property is true.
+const LIT_BLOCKS: &[(&str, u8)] = &[
+    ("campfire", 15),
+    ("redstone_lamp", 15),
+    ("furnace", 13),
+    ("blast_furnace", 13),
+    ("smoker", 13),
+    ("soul_campfire", 10),
+    ("redstone_ore", 9),
+    ("deepslate_redstone_ore", 9),
+    ("redstone_torch", 7),
+    ("redstone_wall_torch", 7),
+];
+
+lazy_static! {
+    /// Light properties of every block state, indexed by block ID. The low nibble holds the
+    /// emitted light level and the high nibble holds the opacity.
+    static ref LIGHT_PROPERTIES: Vec<u8> = ID2BLOCK
+        .iter()
+        .map(|block| (compute_opacity(block) << 4) | compute_emission(block))
+        .collect();
+}
+
+fn property<'a>(block: &'a BlockData, name: &str) -> Option<&'a str> {
+    block
+        .properties
+        .as_ref()
+        .and_then(|props| props.get(name))
+        .map(String::as_str)
+}
+
+fn compute_emission(block: &BlockData) -> u8 {
+    let name = block.name.trim_start_matches("minecraft:");
+    if let Some((_, level)) = EMITTING_BLOCKS.iter().find(|(n, _)| *n == name) {
+        return *level;
+    }
+    let lit = property(block, "lit") == Some("true");
+    if let Some((_, level)) = LIT_BLOCKS.iter().find(|(n, _)| *n == name) {
+        return if lit { *level } else { 0 };
+    }
+    if name.ends_with("candle") && lit {
+        let candles = property(block, "candles")
+            .and_then(|c| c.parse::<u8>().ok())
+            .unwrap_or(1);
+        return (candles * 3).min(MAX_LIGHT);
+    }
+    if name == "sea_pickle" && property(block, "waterlogged") == Some("true") {
+        let pickles = property(block, "pickles")
+            .and_then(|c| c.parse::<u8>().ok())
+            .unwrap_or(1);
+        return (pickles * 3 + 3).min(MAX_LIGHT);
+    }
+    if name == "light" {
+        return property(block, "level")
+            .and_then(|l| l.parse::<u8>().ok())
+            .unwrap_or(MAX_LIGHT)
+            .min(MAX_LIGHT);
+    }
+    0
+}
+
+fn compute_opacity(block: &BlockData) -> u8 {
+    let name = block.name.trim_start_matches("minecraft:");
+    let opacity = if FILTERING_BLOCKS.contains(&name) || name.ends_with("_leaves") {
+        1
+    } else if TRANSPARENT_BLOCKS.contains(&name)
+        || name.starts_with("potted_")
+        || TRANSPARENT_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+    {
+        0
+    } else {
+        MAX_LIGHT
+    };
+    if opacity == 0 && property(block, "waterlogged") == Some("true") {
+        1
+    } else {
+        opacity
+    }
+}
+
+fn light_properties(block: BlockId) -> u8 {
+    // Unknown IDs are treated as solid, non-emitting blocks.
+    LIGHT_PROPERTIES
+        .get(block.0 as usize)
+        .copied()
+        .unwrap_or(MAX_LIGHT << 4)
+}
+
+/// Returns the light level emitted by the given block state.
+pub fn light_emission(block: BlockId) -> u8 {
+    light_properties(block) & 0xF
+}
+
+/// Returns how many light levels the given block state absorbs. `0` means light passes through
+/// untouched (other than the usual 1 level per block), `15` means the block is fully opaque.
+pub fn light_opacity(block: BlockId) -> u8 {
+    light_properties(block) >> 4
+}
+
+fn get_nibble(array: &[u8], index: usize) -> u8 {
+    let byte = array[index >> 1];
+    if index & 1 == 0 {
+        byte & 0xF
+    } else {
+        byte >> 4
+    }
+}
+
+fn set_nibble(array: &mut [u8], index: usize, value: u8) {
+    let byte = &mut array[index >> 1];
+    if index & 1 == 0 {
+        *byte = (*byte & 0xF0) | (value & 0xF);
+    } else {
+        *byte = (*byte & 0x0F) | ((value & 0xF) << 4);
+    }
+}
+
+/// Decodes the light properties of all 4096 blocks in a section into `out`.
+fn section_properties(section: &Section, out: &mut [u8]) {
+    match &section.block_states.block_data {
+        PaletteType::Single(val) => {
+            out.fill(light_properties(BlockId::from_varint(*val)));
+        }
+        PaletteType::Indirect {
+            bits_per_block,
+            data,
+            palette,
+        } => {
+            let palette: Vec<u8> = palette
+                .iter()
+                .map(|id| light_properties(BlockId::from_varint(*id)))
+                .collect();
+            if palette.len() == 1 || *bits_per_block == 0 {
+                out.fill(palette.first().copied().unwrap_or(0));
+                return;
+            }
+            unpack(*bits_per_block, data, out, |value| {
+                palette.get(value as usize).copied().unwrap_or(0)
+            });
+        }
+        PaletteType::Direct {
+            bits_per_block,
+            data,
+        } => {
+            unpack(*bits_per_block, data, out, |value| {
+                light_properties(BlockId(value as u32))
+            });
+        }
+    }
+}
+
+pub(crate) fn unpack(bits_per_block: u8, data: &[i64], out: &mut [u8], map: impl Fn(u64) -> u8) {
+    let bits = bits_per_block as usize;
+    let per_long = 64 / bits;
+    let mask = (1u64 << bits) - 1;
+    for (index, slot) in out.iter_mut().enumerate() {
+        let Some(long) = data.get(index / per_long) else {
+            // Missing data is treated as palette entry 0, same as the client does.
+            *slot = map(0);
+            continue;
+        };
+        let offset = (index % per_long) * bits;
+        *slot = map(((*long as u64) >> offset) & mask);
+    }
+}
+
+/// A chunk taking part in a light update, along with the decoded light properties of its blocks.
+struct LightColumn<'a> {
+    chunk: &'a mut Chunk,
+    /// Index into `chunk.sections` for every section, starting at `min_section`.
+    section_lookup: Vec<Option<usize>>,
+    min_section: i32,
+    /// Packed opacity and emission of every block in the column, see [`LIGHT_PROPERTIES`].
+    properties: Vec<u8>,
+    sky: bool,
+    changed: bool,
+}
+
+impl<'a> LightColumn<'a> {
+    fn new(chunk: &'a mut Chunk, sky: bool) -> Self {
+        let min_section = chunk.sections.iter().map(|s| s.y as i32).min().unwrap_or(0);
+        let max_section = chunk
+            .sections
+            .iter()
+            .map(|s| s.y as i32)
+            .max()
+            .unwrap_or(-1);
+        let section_count = (max_section - min_section + 1).max(0) as usize;
+        let mut section_lookup = vec![None; section_count];
+        let mut properties = vec![MAX_LIGHT << 4; section_count * 4096];
+        let mut changed = false;
+        for (i, section) in chunk.sections.iter_mut().enumerate() {
+            let slot = (section.y as i32 - min_section) as usize;
+            section_lookup[slot] = Some(i);
+            section_properties(section, &mut properties[slot * 4096..(slot + 1) * 4096]);
+            for array in [&mut section.sky_light, &mut section.block_light] {
+                if array.len() != 2048 {
+                    array.resize(2048, 0);
+                    changed = true;
+                }
+            }
+        }
+        Self {
+            chunk,
+            section_lookup,
+            min_section,
+            properties,
+            sky,
+            changed,
+        }
+    }
+
+    fn min_y(&self) -> i32 {
+        self.min_section * 16
+    }
+
+    fn max_y(&self) -> i32 {
+        (self.min_section + self.section_lookup.len() as i32) * 16
+    }
+
+    fn index(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        if y < self.min_y() || y >= self.max_y() {
+            return None;
+        }
+        let index = (((y - self.min_y()) as usize) << 8) | (((z & 0xF) as usize) << 4);
+        let index = index | (x & 0xF) as usize;
+        self.section_lookup[index >> 12].map(|_| index)
+    }
+
+    fn array(&self, kind: LightType, index: usize) -> &[u8] {
+        let section = &self.chunk.sections[self.section_lookup[index >> 12].unwrap()];
+        match kind {
+            LightType::Sky => &section.sky_light,
+            LightType::Block => &section.block_light,
+        }
+    }
+
+    fn light(&self, kind: LightType, index: usize) -> u8 {
+        if kind == LightType::Sky && !self.sky {
+            return 0;
+        }
+        get_nibble(self.array(kind, index), index & 0xFFF)
+    }
+
+    fn set_light(&mut self, kind: LightType, index: usize, value: u8) {
+        let section = &mut self.chunk.sections[self.section_lookup[index >> 12].unwrap()];
+        let array = match kind {
+            LightType::Sky => &mut section.sky_light,
+            LightType::Block => &mut section.block_light,
+        };
+        if get_nibble(array, index & 0xFFF) != value {
+            set_nibble(array, index & 0xFFF, value);
+            self.changed = true;
+        }
+    }
+}
+
+/// A chunk and (some of) its 8 neighbours, used to run light updates across chunk borders.
+pub struct LightRegion<'a> {
+    center_x: i32,
+    center_z: i32,
+    columns: [Option<LightColumn<'a>>; 9],
+}
+
+impl<'a> LightRegion<'a> {
+    /// Creates a region centered on `center`. Any chunks in `neighbours` that aren't directly
+    /// next to the center are ignored.
+    ///
+    /// `has_skylight` comes from the type of the dimension the chunks are in, see
+    /// [`World::has_skylight`].
+    pub fn new(
+        center: &'a mut Chunk,
+        neighbours: impl IntoIterator<Item = &'a mut Chunk>,
+        has_skylight: bool,
+    ) -> Self {
+        let (center_x, center_z) = (center.x, center.z);
+        let mut region = Self {
+            center_x,
+            center_z,
+            columns: Default::default(),
+        };
+        region.columns[4] = Some(LightColumn::new(center, has_skylight));
+        for chunk in neighbours {
+            if chunk.dimension != region.columns[4].as_ref().unwrap().chunk.dimension {
+                continue;
+            }
+            if let Some(slot) = region.slot(chunk.x, chunk.z) {
+                if slot != 4 {
+                    region.columns[slot] = Some(LightColumn::new(chunk, has_skylight));
+                }
+            }
+        }
+        region
+    }
+
+    /// Returns the coordinates of all chunks whose light was changed by this region.
+    pub fn changed_chunks(&self) -> Vec<(i32, i32)> {
+        self.columns
+            .iter()
+            .flatten()
+            .filter(|column| column.changed)
+            .map(|column| (column.chunk.x, column.chunk.z))
+            .collect()
+    }
+
+    fn slot(&self, chunk_x: i32, chunk_z: i32) -> Option<usize> {
+        let dx = chunk_x - self.center_x + 1;
+        let dz = chunk_z - self.center_z + 1;
+        if (0..3).contains(&dx) && (0..3).contains(&dz) {
+            Some((dz * 3 + dx) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn locate(&self, x: i32, y: i32, z: i32) -> Option<(usize, usize)> {
+        let slot = self.slot(x >> 4, z >> 4)?;
+        let column = self.columns[slot].as_ref()?;
+        Some((slot, column.index(x, y, z)?))
+    }
+
+    fn column(&self, slot: usize) -> &LightColumn<'a> {
+        self.columns[slot].as_ref().unwrap()
+    }
+
+    fn column_mut(&mut self, slot: usize) -> &mut LightColumn<'a> {
+        self.columns[slot].as_mut().unwrap()
+    }
+
+    /// Light that a block produces by itself, without taking neighbours into account.
+    fn intrinsic_light(&self, kind: LightType, slot: usize, index: usize, y: i32) -> u8 {
+        let column = self.column(slot);
+        let properties = column.properties[index];
+        match kind {
+            LightType::Block => properties & 0xF,
+            LightType::Sky => {
+                // The topmost block of the world is lit directly by the sky.
+                if column.sky && y == column.max_y() - 1 {
+                    propagated(kind, MAX_LIGHT, -1, properties >> 4)
+                } else {
+                    0
+                }
+            }
+        }
+    }
+
+    /// Recomputes both light types for the blocks at the given positions, after their block
+    /// states have changed.
+    ///
+    /// Positions are in world coordinates. Positions outside the region are ignored.
+    pub fn update_blocks(&mut self, positions: &[(i32, i32, i32)]) {
+        self.refresh_properties(positions);
+        for kind in [LightType::Sky, LightType::Block] {
+            let mut removal = VecDeque::new();
+            let mut increase = VecDeque::new();
+            let mut reseed = Vec::new();
+            for &(x, y, z) in positions {
+                let Some((slot, index)) = self.locate(x, y, z) else {
+                    continue;
+                };
+                let old = self.column(slot).light(kind, index);
+                if old > 0 {
+                    self.column_mut(slot).set_light(kind, index, 0);
+                    removal.push_back((x, y, z, old));
+                }
+                reseed.push((x, y, z));
+                // Neighbours might be able to light this block now, so give them another go.
+                for (dx, dy, dz) in DIRECTIONS {
+                    increase.push_back((x + dx, y + dy, z + dz));
+                }
+            }
+            self.propagate_removal(kind, &mut removal, &mut increase, &mut reseed);
+            self.seed(kind, &reseed, &mut increase);
+            self.propagate_increase(kind, &mut increase);
+        }
+    }
+
+    /// Throws away the light of the center chunk and computes it from scratch, including light
+    /// coming in from, or going out to, the neighbouring chunks.
+    pub fn relight_center(&mut self) {
+        let (min_y, max_y, sky) = {
+            let center = self.column(4);
+            (center.min_y(), center.max_y(), center.sky)
+        };
+        let base_x = self.center_x * 16;
+        let base_z = self.center_z * 16;
+        let kinds: &[LightType] = if sky {
+            &[LightType::Sky, LightType::Block]
+        } else {
+            &[LightType::Block]
+        };
+        if !sky {
+            // Make sure no stale sky light is sent for dimensions without a sky.
+            let center = self.column_mut(4);
+            for section in center.chunk.sections.iter_mut() {
+                if section.sky_light.iter().any(|b| *b != 0) {
+                    section.sky_light.fill(0);
+                    center.changed = true;
+                }
+            }
+        }
+        for &kind in kinds {
+            let mut removal = VecDeque::new();
+            let mut increase = VecDeque::new();
+            let mut reseed = Vec::new();
+            // Remember the old light along the edges so it can be removed from the neighbours.
+            for y in min_y..max_y {
+                for i in 0..16 {
+                    for (lx, lz) in [(i, 0), (i, 15), (0, i), (15, i)] {
+                        let Some(index) = self.column(4).index(lx, y, lz) else {
+                            continue;
+                        };
+                        let old = self.column(4).light(kind, index);
+                        if old > 0 {
+                            removal.push_back((base_x + lx, y, base_z + lz, old));
+                        }
+                    }
+                }
+            }
+            for section in self.column_mut(4).chunk.sections.iter_mut() {
+                match kind {
+                    LightType::Sky => section.sky_light.fill(0),
+                    LightType::Block => section.block_light.fill(0),
+                }
+            }
+            self.propagate_removal(kind, &mut removal, &mut increase, &mut reseed);
+            self.seed(kind, &reseed, &mut increase);
+
+            // Light coming in from the neighbours.
+            for y in min_y..max_y {
+                for i in 0..16 {
+                    for (nx, nz) in [(i, -1), (i, 16), (-1, i), (16, i)] {
+                        increase.push_back((base_x + nx, y, base_z + nz));
+                    }
+                }
+            }
+
+            match kind {
+                LightType::Block => self.seed_emitters(&mut increase),
+                LightType::Sky => self.seed_sky(&mut increase),
+            }
+            self.propagate_increase(kind, &mut increase);
+        }
+        let center = self.column_mut(4);
+        // The arrays were cleared in place, so there's nothing to compare against anymore.
+        center.changed = true;
+        center.chunk.light_dirty = false;
+    }
+
+    fn refresh_properties(&mut self, positions: &[(i32, i32, i32)]) {
+        for &(x, y, z) in positions {
+            let Some((slot, index)) = self.locate(x, y, z) else {
+                continue;
+            };
+            let column = self.column_mut(slot);
+            column.properties[index] = column
+                .chunk
+                .get_block(x, y, z)
+                .map(light_properties)
+                .unwrap_or(MAX_LIGHT << 4);
+        }
+    }
+
+    fn seed(
+        &mut self,
+        kind: LightType,
+        positions: &[(i32, i32, i32)],
+        increase: &mut VecDeque<(i32, i32, i32)>,
+    ) {
+        for &(x, y, z) in positions {
+            let Some((slot, index)) = self.locate(x, y, z) else {
+                continue;
+            };
+            let intrinsic = self.intrinsic_light(kind, slot, index, y);
+            if intrinsic > self.column(slot).light(kind, index) {
+                self.column_mut(slot).set_light(kind, index, intrinsic);
+                increase.push_back((x, y, z));
+            }
+        }
+    }
+
+    fn seed_emitters(&mut self, increase: &mut VecDeque<(i32, i32, i32)>) {
+        let base_x = self.center_x * 16;
+        let base_z = self.center_z * 16;
+        let column = self.column_mut(4);
+        let min_y = column.min_y();
+        for index in 0..column.properties.len() {
+            if column.section_lookup[index >> 12].is_none() {
+                continue;
+            }
+            let emission = column.properties[index] & 0xF;
+            if emission > 0 {
+                column.set_light(LightType::Block, index, emission);
+                let x = base_x + (index & 0xF) as i32;
+                let z = base_z + ((index >> 4) & 0xF) as i32;
+                let y = min_y + (index >> 8) as i32;
+                increase.push_back((x, y, z));
+            }
+        }
+    }
+
+    fn seed_sky(&mut self, increase: &mut VecDeque<(i32, i32, i32)>) {
+        let base_x = self.center_x * 16;
+        let base_z = self.center_z * 16;
+        let column = self.column_mut(4);
+        let (min_y, max_y) = (column.min_y(), column.max_y());
+        // Everything above the highest non-empty section is fully lit, and so are the columns
+        // next to it, so those blocks can't spread light anywhere new inside this chunk.
+        let top_filled = column
+            .chunk
+            .sections
+            .iter()
+            .filter(|s| s.block_states.non_air_blocks > 0)
+            .map(|s| (s.y as i32 + 1) * 16)
+            .max()
+            .unwrap_or(min_y);
+        for lz in 0..16 {
+            for lx in 0..16 {
+                let edge = lx == 0 || lx == 15 || lz == 0 || lz == 15;
+                let mut level = MAX_LIGHT;
+                for y in (min_y..max_y).rev() {
+                    let Some(index) = column.index(lx, y, lz) else {
+                        level = 0;
+                        break;
+                    };
+                    level = propagated(LightType::Sky, level, -1, column.properties[index] >> 4);
+                    if level == 0 {
+                        break;
+                    }
+                    column.set_light(LightType::Sky, index, level);
+                    if level > 1 && (edge || y <= top_filled) {
+                        increase.push_back((base_x + lx, y, base_z + lz));
+                    }
+                }
+            }
+        }
+    }
+
+    fn propagate_removal(
+        &mut self,
+        kind: LightType,
+        removal: &mut VecDeque<(i32, i32, i32, u8)>,
+        increase: &mut VecDeque<(i32, i32, i32)>,
+        reseed: &mut Vec<(i32, i32, i32)>,
+    ) {
+        while let Some((x, y, z, level)) = removal.pop_front() {
+            for (dx, dy, dz) in DIRECTIONS {
+                let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+                let Some((slot, index)) = self.locate(nx, ny, nz) else {
+                    continue;
+                };
+                let current = self.column(slot).light(kind, index);
+                if current == 0 {
+                    continue;
+                }
+                let straight_down = kind == LightType::Sky
+                    && dy == -1
+                    && level == MAX_LIGHT
+                    && current == MAX_LIGHT;
+                if current < level || straight_down {
+                    // This block could have been lit by the removed light, so clear it too.
+                    self.column_mut(slot).set_light(kind, index, 0);
+                    removal.push_back((nx, ny, nz, current));
+                    reseed.push((nx, ny, nz));
+                } else {
+                    // Lit from somewhere else, so it can help re-light the cleared area.
+                    increase.push_back((nx, ny, nz));
+                }
+            }
+        }
+    }
+
+    fn propagate_increase(&mut self, kind: LightType, increase: &mut VecDeque<(i32, i32, i32)>) {
+        while let Some((x, y, z)) = increase.pop_front() {
+            let Some((slot, index)) = self.locate(x, y, z) else {
+                continue;
+            };
+            let level = self.column(slot).light(kind, index);
+            if level <= 1 {
+                continue;
+            }
+            for (dx, dy, dz) in DIRECTIONS {
+                let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+                let Some((n_slot, n_index)) = self.locate(nx, ny, nz) else {
+                    continue;
+                };
+                let neighbour = self.column(n_slot);
+                if kind == LightType::Sky && !neighbour.sky {
+                    continue;
+                }
+                let new = propagated(kind, level, dy, neighbour.properties[n_index] >> 4);
+                if new > neighbour.light(kind, n_index) {
+                    self.column_mut(n_slot).set_light(kind, n_index, new);
+                    increase.push_back((nx, ny, nz));
+                }
+            }
+        }
+    }
+}
+
+/// Light level after moving one block in the `dy` direction into a block with `opacity`.
+fn propagated(kind: LightType, level: u8, dy: i32, opacity: u8) -> u8 {
+    if kind == LightType::Sky && dy == -1 && level == MAX_LIGHT && opacity == 0 {
+        MAX_LIGHT
+    } else {
+        level.saturating_sub(opacity.max(1))
+    }
+}
+
+/// Whether light from the blocks at `positions` can reach the chunk `(dx, dz)` away from the one
+/// they're in. Light loses at least one level per block sideways, even sky light, so nothing
+/// changes further than `MAX_LIGHT - 1` blocks from a changed block, measured along x plus z.
+fn in_reach(positions: &[(i32, i32, i32)], dx: i32, dz: i32) -> bool {
+    let distance = |local: i32, offset: i32| match offset {
+        -1 => local + 1,
+        1 => 16 - local,
+        _ => 0,
+    };
+    positions
+        .iter()
+        .any(|&(x, _, z)| distance(x & 0xF, dx) + distance(z & 0xF, dz) < MAX_LIGHT as i32)
+}
+
+/// Relights the chunks in `chunks` that need it, letting light cross into the other chunks in the
+/// slice. Chunks that aren't in the slice are left as they are.
+pub(crate) fn relight_dirty_chunks(chunks: &mut [Chunk], has_skylight: impl Fn(&str) -> bool) {
+    for i in 0..chunks.len() {
+        if !chunks[i].light_dirty {
+            continue;
+        }
+        let (before, rest) = chunks.split_at_mut(i);
+        let (center, after) = rest.split_first_mut().expect("index is in bounds");
+        let sky = has_skylight(&center.dimension);
+        LightRegion::new(center, before.iter_mut().chain(after.iter_mut()), sky).relight_center();
+    }
+}
+
+impl Chunk {
+    /// Computes the sky and block light of the whole chunk from scratch.
+    ///
+    /// This only looks at this chunk, so light coming from neighbouring chunks is lost. Use
+    /// [`World::relight_chunk`] if the chunk is part of a world and its neighbours should be
+    /// taken into account. `has_skylight` comes from the dimension's type, see
+    /// [`World::has_skylight`].
+    pub fn relight(&mut self, has_skylight: bool) {
+        LightRegion::new(self, [], has_skylight).relight_center();
+    }
+
+    /// Gets the light level of the given type at the specified coordinates.
+    ///
+    /// Coordinates are modulo'd by 16 like in [`Chunk::get_block`]. Returns 0 for blocks outside
+    /// of the chunk's sections.
+    pub fn get_light(&self, kind: LightType, x: i32, y: i32, z: i32) -> u8 {
+        let Some(section) = self.sections.iter().find(|s| s.y as i32 == y >> 4) else {
+            return 0;
+        };
+        let array = match kind {
+            LightType::Sky => &section.sky_light,
+            LightType::Block => &section.block_light,
+        };
+        let index = (((y & 0xF) << 8) | ((z & 0xF) << 4) | (x & 0xF)) as usize;
+        if array.len() != 2048 {
+            return 0;
+        }
+        get_nibble(array, index)
+    }
+}
+
+impl World {
+    /// Whether the dimension's type has a sky, and its chunks should therefore have sky light.
+    /// Dimensions that aren't registered are treated like the overworld.
+    pub fn has_skylight(&self, dimension: &str) -> bool {
+        self.dimensions()
+            .get(dimension)
+            .is_none_or(|dimension| dimension.dimension_type.has_skylight)
+    }
+
+    /// Loads the chunks around the given chunk, skipping any that don't exist yet.
+    fn load_light_neighbours(&self, chunk_x: i32, chunk_z: i32, dimension: &str) -> Vec<Chunk> {
+        let mut neighbours = Vec::with_capacity(8);
+        for dz in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dz == 0 {
+                    continue;
+                }
+                let (x, z) = (chunk_x + dx, chunk_z + dz);
+                if !self.chunk_exists(x, z, dimension).unwrap_or(false) {
+                    continue;
+                }
+                match self.load_chunk_owned(x, z, dimension) {
+                    Ok(chunk) => neighbours.push(chunk),
+                    Err(e) => warn!("Could not load chunk {}, {} for lighting: {}", x, z, e),
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// Copies of the loaded chunks around the given chunk that light from the blocks at
+    /// `positions` can reach, or all loaded ones if `positions` is `None`.
+    ///
+    /// Nothing is loaded from storage. Chunks nobody has loaded aren't being looked at, and their
+    /// borders are put right when they're relit with [`World::relight_chunk`].
+    fn loaded_light_neighbours(
+        &self,
+        chunk_x: i32,
+        chunk_z: i32,
+        dimension: &str,
+        positions: Option<&[(i32, i32, i32)]>,
+    ) -> Vec<Chunk> {
+        let mut neighbours = Vec::new();
+        for dz in -1..=1 {
+            for dx in -1..=1 {
+                if (dx == 0 && dz == 0) || positions.is_some_and(|p| !in_reach(p, dx, dz)) {
+                    continue;
+                }
+                let key = (chunk_x + dx, chunk_z + dz, dimension.to_string());
+                if let Some(chunk) = self.cache.get(&key) {
+                    neighbours.push(Arc::unwrap_or_clone(chunk));
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// Saves the center chunk and any neighbours whose light was changed.
+    fn save_lit_chunks(
+        &self,
+        center: Chunk,
+        neighbours: Vec<Chunk>,
+        changed: &[(i32, i32)],
+    ) -> Result<(), WorldError> {
+        trace!(
+            "Saving {} chunks after light update around {}, {}",
+            changed.len(),
+            center.x,
+            center.z
+        );
+        for chunk in neighbours {
+            if changed.contains(&(chunk.x, chunk.z)) {
+                self.save_chunk(Arc::new(chunk))?;
+            }
+        }
+        self.save_chunk(Arc::new(center))
+    }
+
+    /// Relights a chunk whose light is dirty along with the loaded chunks around it, then saves
+    /// it and any of them whose light changed. Used by [`World::save_chunk`].
+    pub(crate) fn save_relit_chunk(&self, mut chunk: Chunk) -> Result<(), WorldError> {
+        let sky = self.has_skylight(&chunk.dimension);
+        let mut neighbours = self.loaded_light_neighbours(chunk.x, chunk.z, &chunk.dimension, None);
+        let changed = {
+            let mut region = LightRegion::new(&mut chunk, neighbours.iter_mut(), sky);
+            region.relight_center();
+            region.changed_chunks()
+        };
+        self.save_lit_chunks(chunk, neighbours, &changed)
+    }
+
+    /// Relights the chunks whose light is dirty along with the loaded chunks around them, like
+    /// [`World::save_chunk`] does one chunk at a time. Returns the chunks followed by the
+    /// neighbours whose light changed, so they can all be saved together.
+    pub(crate) fn relight_with_neighbours(&self, mut chunks: Vec<Chunk>) -> Vec<Chunk> {
+        if !chunks.iter().any(|chunk| chunk.light_dirty) {
+            return chunks;
+        }
+        let edited = chunks.len();
+        let mut present = chunks
+            .iter()
+            .map(|chunk| (chunk.x, chunk.z, chunk.dimension.clone()))
+            .collect::<HashSet<_>>();
+        for i in 0..edited {
+            let (x, z, dimension) = (chunks[i].x, chunks[i].z, chunks[i].dimension.clone());
+            for neighbour in self.loaded_light_neighbours(x, z, &dimension, None) {
+                if present.insert((neighbour.x, neighbour.z, neighbour.dimension.clone())) {
+                    chunks.push(neighbour);
+                }
+            }
+        }
+
+        let mut changed = HashSet::new();
+        for i in 0..edited {
+            if !chunks[i].light_dirty {
+                continue;
+            }
+            let (before, rest) = chunks.split_at_mut(i);
+            let (center, after) = rest.split_first_mut().expect("index is in bounds");
+            let sky = self.has_skylight(&center.dimension);
+            let mut region =
+                LightRegion::new(center, before.iter_mut().chain(after.iter_mut()), sky);
+            region.relight_center();
+            changed.extend(region.changed_chunks());
+        }
+        let mut index = 0;
+        chunks.retain(|chunk| {
+            index += 1;
+            index <= edited || changed.contains(&(chunk.x, chunk.z))
+        });
+        chunks
+    }
+
+    /// Lights a chunk that isn't part of the world yet, e.g. one that was just generated to be
+    /// sent to a player, including the light coming in from the loaded chunks around it. The
+    /// chunks around it are left as they are.
+    pub fn light_new_chunk(&self, chunk: &mut Chunk) {
+        let sky = self.has_skylight(&chunk.dimension);
+        let mut neighbours = self.loaded_light_neighbours(chunk.x, chunk.z, &chunk.dimension, None);
+        LightRegion::new(chunk, neighbours.iter_mut(), sky).relight_center();
+    }
+
+    /// Sets a block and updates the light around it, including in the loaded neighbouring chunks
+    /// the change can reach. The center chunk is always saved, neighbours are only saved if their
+    /// light changed.
+    pub(crate) fn set_block_and_relight(
+        &self,
+        mut chunk: Chunk,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: BlockId,
+    ) -> Result<(), WorldError> {
+        chunk.set_block_raw(x, y, z, block)?;
+        let sky = self.has_skylight(&chunk.dimension);
+        let mut neighbours =
+            self.loaded_light_neighbours(chunk.x, chunk.z, &chunk.dimension, Some(&[(x, y, z)]));
+        let changed = {
+            let mut region = LightRegion::new(&mut chunk, neighbours.iter_mut(), sky);
+            region.update_blocks(&[(x, y, z)]);
+            region.changed_chunks()
+        };
+        self.save_lit_chunks(chunk, neighbours, &changed)
+    }
+
+    /// Like [`World::set_block_and_relight`], for any number of blocks in the same chunk.
+    pub(crate) fn set_blocks_and_relight(
+        &self,
+        mut chunk: Chunk,
+        blocks: &[((i32, i32, i32), BlockId)],
+    ) -> Result<(), WorldError> {
+        if blocks.is_empty() {
+            return Ok(());
+        }
+        let mut batch = EditBatch::new(&mut chunk);
+        for &((x, y, z), block) in blocks {
+            batch.set_block(x & 0xf, y, z & 0xf, block);
+        }
+        batch.apply_unlit()?;
+        let positions = blocks.iter().map(|(pos, _)| *pos).collect::<Vec<_>>();
+        let sky = self.has_skylight(&chunk.dimension);
+        let mut neighbours =
+            self.loaded_light_neighbours(chunk.x, chunk.z, &chunk.dimension, Some(&positions));
+        let changed = {
+            let mut region = LightRegion::new(&mut chunk, neighbours.iter_mut(), sky);
+            region.update_blocks(&positions);
+            region.changed_chunks()
+        };
+        self.save_lit_chunks(chunk, neighbours, &changed)
+    }
+
+    /// Recomputes the light of a stored chunk from scratch, taking neighbouring chunks into
+    /// account. Any neighbours whose light changed as a result are saved too.
+    ///
+    /// This should be called after a chunk was generated or imported next to existing chunks, or
+    /// after it was edited with methods that don't update light themselves, such as
+    /// [`Chunk::set_section`]. Unlike lighting done while editing, this loads the neighbours
+    /// from storage if they aren't loaded.
+    pub fn relight_chunk(&self, x: i32, z: i32, dimension: &str) -> Result<(), WorldError> {
+        let mut chunk = self.load_chunk_owned(x, z, dimension)?;
+        let sky = self.has_skylight(dimension);
+        let mut neighbours = self.load_light_neighbours(x, z, dimension);
+        let changed = {
+            let mut region = LightRegion::new(&mut chunk, neighbours.iter_mut(), sky);
+            region.relight_center();
+            region.changed_chunks()
+        };
+        self.save_lit_chunks(chunk, neighbours, &changed)
+    }
+
+    /// Gets the light level at the given position, which is the brighter of the sky light and
+    /// the block light.
+    pub fn get_light(&self, x: i32, y: i32, z: i32, dimension: &str) -> Result<u8, WorldError> {
+        let chunk = self.load_chunk(x >> 4, z >> 4, dimension)?;
+        Ok(chunk
+            .get_light(LightType::Sky, x, y, z)
+            .max(chunk.get_light(LightType::Block, x, y, z)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimensions::DimensionType;
+    use crate::testing::memory_world;
+
+    fn block(name: &str) -> BlockId {
+        BlockData {
+            name: name.to_string(),
+            properties: None,
+        }
+        .to_block_id()
+    }
+
+    fn lit_chunk(x: i32, z: i32) -> Chunk {
+        let mut chunk = Chunk::new(x, z, "overworld".to_string());
+        chunk.relight(true);
+        chunk
+    }
+
+    #[test]
+    fn test_empty_chunk_is_fully_sky_lit() {
+        let chunk = lit_chunk(0, 0);
+        assert_eq!(chunk.get_light(LightType::Sky, 3, -64, 7), 15);
+        assert_eq!(chunk.get_light(LightType::Sky, 3, 200, 7), 15);
+        assert_eq!(chunk.get_light(LightType::Block, 3, 64, 7), 0);
+    }
+
+    #[test]
+    fn test_roof_blocks_sky_light() {
+        let mut chunk = lit_chunk(0, 0);
+        let stone = block("minecraft:stone");
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, 100, z, stone).unwrap();
+            }
+        }
+        chunk.relight(true);
+        assert_eq!(chunk.get_light(LightType::Sky, 8, 101, 8), 15);
+        assert_eq!(chunk.get_light(LightType::Sky, 8, 99, 8), 0);
+        assert_eq!(chunk.get_light(LightType::Sky, 8, 0, 8), 0);
+    }
+
+    #[test]
+    fn test_torch_placement_and_removal() {
+        let mut chunk = lit_chunk(0, 0);
+        chunk.set_block(8, 64, 8, block("minecraft:torch")).unwrap();
+        assert!(chunk.light_dirty);
+        assert_eq!(chunk.get_light(LightType::Block, 8, 64, 8), 0);
+        chunk.relight(true);
+        assert!(!chunk.light_dirty);
+        assert_eq!(chunk.get_light(LightType::Block, 8, 64, 8), 14);
+        assert_eq!(chunk.get_light(LightType::Block, 11, 64, 8), 11);
+        assert_eq!(chunk.get_light(LightType::Block, 8, 66, 9), 11);
+
+        chunk.set_block(8, 64, 8, BlockId::default()).unwrap();
+        chunk.relight(true);
+        assert_eq!(chunk.get_light(LightType::Block, 8, 64, 8), 0);
+        assert_eq!(chunk.get_light(LightType::Block, 11, 64, 8), 0);
+    }
+
+    #[test]
+    fn test_light_crosses_chunk_borders() {
+        let mut center = lit_chunk(0, 0);
+        let mut east = lit_chunk(1, 0);
+        center
+            .set_block_raw(15, 64, 8, block("minecraft:glowstone"))
+            .unwrap();
+        {
+            let mut region = LightRegion::new(&mut center, [&mut east], true);
+            region.update_blocks(&[(15, 64, 8)]);
+            assert!(region.changed_chunks().contains(&(1, 0)));
+        }
+        assert_eq!(east.get_light(LightType::Block, 16, 64, 8), 14);
+        assert_eq!(east.get_light(LightType::Block, 20, 64, 8), 10);
+
+        center.set_block_raw(15, 64, 8, BlockId::default()).unwrap();
+        let mut region = LightRegion::new(&mut center, [&mut east], true);
+        region.update_blocks(&[(15, 64, 8)]);
+        drop(region);
+        assert_eq!(east.get_light(LightType::Block, 16, 64, 8), 0);
+    }
+
+    #[test]
+    fn test_saving_lights_loaded_neighbours() {
+        let (world, _) = memory_world([(0, 0), (1, 0)]);
+        let mut chunk = world.load_chunk_owned(0, 0, "overworld").unwrap();
+        chunk
+            .set_block(15, 64, 8, block("minecraft:glowstone"))
+            .unwrap();
+        world.save_chunk(Arc::new(chunk)).unwrap();
+        let east = world.load_chunk(1, 0, "overworld").unwrap();
+        assert_eq!(east.get_light(LightType::Block, 16, 64, 8), 14);
+        assert!(!world.load_chunk(0, 0, "overworld").unwrap().light_dirty);
+    }
+
+    #[test]
+    fn test_neighbours_in_reach() {
+        assert!(in_reach(&[(8, 64, 8)], -1, 0));
+        assert!(in_reach(&[(8, 64, 8)], 1, 0));
+        assert!(!in_reach(&[(8, 64, 8)], -1, -1));
+        assert!(in_reach(&[(1, 64, 2)], -1, -1));
+        assert!(!in_reach(&[(15, 64, 8)], -1, 0));
+    }
+
+    #[test]
+    fn test_skylight_comes_from_dimension_type() {
+        let (world, _) = memory_world([]);
+        assert!(world.has_skylight("overworld"));
+        assert!(!world.has_skylight("minecraft:the_nether"));
+        let mut dark = DimensionType::overworld();
+        dark.has_skylight = false;
+        world.register_dimension("caves", dark).unwrap();
+        assert!(!world.has_skylight("caves"));
+    }
+
+    #[test]
+    fn test_no_sky_light_in_nether() {
+        let mut chunk = Chunk::new(0, 0, "the_nether".to_string());
+        chunk.relight(false);
+        assert_eq!(chunk.get_light(LightType::Sky, 0, 64, 0), 0);
+        assert!(chunk
+            .sections
+            .iter()
+            .all(|s| s.sky_light.iter().all(|b| *b == 0)));
+    }
+}
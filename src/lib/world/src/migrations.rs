@@ -0,0 +1,476 @@
+//! Upgrades for chunks saved with an older layout of [`Chunk`](crate::chunk_format::Chunk).
+//!
+//! Chunks are stored as raw bitcode, which doesn't know anything about field names, so any change
+//! to the struct, or to any struct inside it, makes previously saved chunks fail to decode. Every
+//! layout is kept here as a frozen copy, so the migrations keep working however the real structs
+//! change later. To change the layout:
+//!
+//! 1. Add a `vN` module in this file with copies of the structs that change, with just the
+//!    bitcode derives. Structs that stay the same are used from the module before.
+//! 2. Make the change to the real structs.
+//! 3. Push a function onto [`MIGRATIONS`] that decodes the old layout and encodes the new one.
+//!
+//! A test checks that the newest frozen layout still matches [`Chunk`], so forgetting to do this
+//! fails there rather than on someone's world.
+//!
+//! [`CHUNK_FORMAT_VERSION`] follows the length of [`MIGRATIONS`], so it never needs bumping by
+//! hand. Old chunks are upgraded in memory when they're loaded and saved in the new layout the
+//! next time they're written, or all at once with the `migrate` command.
+
+use crate::chunk_format::now;
+use crate::errors::WorldError;
+
+/// Upgrades the bitcode of a chunk from one version to the next.
+pub(crate) type Migration = fn(&[u8]) -> Result<Vec<u8>, WorldError>;
+
+/// Every migration in order, the one at index `n` upgrades version `n` to version `n + 1`.
+///
+/// Version 0 is the layout chunks had before records carried a version.
+pub(crate) const MIGRATIONS: &[Migration] = &[v0_to_v1, v1_to_v2, v2_to_v3, v3_to_v4];
+
+/// The version of the chunk layout this build reads and writes.
+pub(crate) const CHUNK_FORMAT_VERSION: u16 = MIGRATIONS.len() as u16;
+
+/// Upgrade the bitcode of a chunk saved at `version` to [`CHUNK_FORMAT_VERSION`].
+pub(crate) fn migrate(data: Vec<u8>, version: u16) -> Result<Vec<u8>, WorldError> {
+    run_migrations(data, version, MIGRATIONS)
+}
+
+fn run_migrations(
+    mut data: Vec<u8>,
+    version: u16,
+    migrations: &[Migration],
+) -> Result<Vec<u8>, WorldError> {
+    if version as usize > migrations.len() {
+        return Err(WorldError::UnsupportedChunkVersion(
+            version,
+            migrations.len() as u16,
+        ));
+    }
+    for (from, migration) in migrations.iter().enumerate().skip(version as usize) {
+        data = migration(&data)
+            .map_err(|e| WorldError::ChunkMigrationError(from as u16, e.to_string()))?;
+    }
+    Ok(data)
+}
+
+/// The layout before chunks had `last_modified`, along with everything inside a chunk as it was
+/// then.
+pub(crate) mod v0 {
+    use bitcode_derive::{Decode, Encode};
+    use ferrumc_net_codec::net_types::var_int::VarInt;
+    use std::collections::HashMap;
+
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Hash)]
+    pub(crate) struct BlockId(pub u32);
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct Section {
+        pub y: i8,
+        pub block_states: BlockStates,
+        pub biome_states: BiomeStates,
+        pub block_light: Vec<u8>,
+        pub sky_light: Vec<u8>,
+    }
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct BlockStates {
+        pub non_air_blocks: u16,
+        pub block_data: PaletteType,
+        pub block_counts: HashMap<BlockId, i32>,
+    }
+
+    #[derive(Encode, Decode)]
+    pub(crate) enum PaletteType {
+        Single(VarInt),
+        Indirect {
+            bits_per_block: u8,
+            data: Vec<i64>,
+            palette: Vec<VarInt>,
+        },
+        Direct {
+            bits_per_block: u8,
+            data: Vec<i64>,
+        },
+    }
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct BiomeStates {
+        pub bits_per_biome: u8,
+        pub data: Vec<i64>,
+        pub palette: Vec<VarInt>,
+    }
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct BlockEntity {
+        pub xz: u8,
+        pub y: u16,
+        pub entity_type: VarInt,
+        pub nbt: Vec<u8>,
+    }
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct Heightmaps {
+        pub motion_blocking: Vec<i64>,
+        pub world_surface: Vec<i64>,
+    }
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct Chunk {
+        pub x: i32,
+        pub z: i32,
+        pub dimension: String,
+        pub sections: Vec<Section>,
+        pub heightmaps: Heightmaps,
+        pub block_entities: Vec<BlockEntity>,
+    }
+}
+
+/// The layout before chunks had the `OCEAN_FLOOR` and `MOTION_BLOCKING_NO_LEAVES` heightmaps.
+pub(crate) mod v1 {
+    use super::v0::{BlockEntity, Heightmaps, Section};
+    use bitcode_derive::{Decode, Encode};
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct Chunk {
+        pub x: i32,
+        pub z: i32,
+        pub dimension: String,
+        pub sections: Vec<Section>,
+        pub heightmaps: Heightmaps,
+        pub block_entities: Vec<BlockEntity>,
+        pub last_modified: u64,
+    }
+}
+
+/// The layout before chunks had `pending_ticks`.
+pub(crate) mod v2 {
+    use super::v0::{BlockEntity, Section};
+    use bitcode_derive::{Decode, Encode};
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct Heightmaps {
+        pub motion_blocking: Vec<i64>,
+        pub world_surface: Vec<i64>,
+        pub ocean_floor: Vec<i64>,
+        pub motion_blocking_no_leaves: Vec<i64>,
+    }
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct Chunk {
+        pub x: i32,
+        pub z: i32,
+        pub dimension: String,
+        pub sections: Vec<Section>,
+        pub heightmaps: Heightmaps,
+        pub block_entities: Vec<BlockEntity>,
+        pub last_modified: u64,
+    }
+}
+
+/// The layout before chunks had `light_dirty`.
+pub(crate) mod v3 {
+    use super::v0::{BlockEntity, BlockId, Section};
+    use super::v2::Heightmaps;
+    use bitcode_derive::{Decode, Encode};
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct PendingTick {
+        pub xz: u8,
+        pub y: i16,
+        pub block: BlockId,
+        pub delay: u32,
+        pub priority: i8,
+        pub redstone: bool,
+    }
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct Chunk {
+        pub x: i32,
+        pub z: i32,
+        pub dimension: String,
+        pub sections: Vec<Section>,
+        pub heightmaps: Heightmaps,
+        pub block_entities: Vec<BlockEntity>,
+        pub last_modified: u64,
+        pub pending_ticks: Vec<PendingTick>,
+    }
+}
+
+/// The current layout.
+pub(crate) mod v4 {
+    use super::v0::{BlockEntity, Section};
+    use super::v2::Heightmaps;
+    use super::v3::PendingTick;
+    use bitcode_derive::{Decode, Encode};
+
+    #[derive(Encode, Decode)]
+    pub(crate) struct Chunk {
+        pub x: i32,
+        pub z: i32,
+        pub dimension: String,
+        pub sections: Vec<Section>,
+        pub heightmaps: Heightmaps,
+        pub block_entities: Vec<BlockEntity>,
+        pub last_modified: u64,
+        pub pending_ticks: Vec<PendingTick>,
+        pub light_dirty: bool,
+    }
+}
+
+/// Adds `last_modified`.
+///
+/// There's no telling whether an old chunk was ever changed, so they're all treated as modified
+/// now. That way trimming unmodified chunks can never throw away someone's build.
+fn v0_to_v1(data: &[u8]) -> Result<Vec<u8>, WorldError> {
+    let old: v0::Chunk =
+        bitcode::decode(data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
+    Ok(bitcode::encode(&v1::Chunk {
+        x: old.x,
+        z: old.z,
+        dimension: old.dimension,
+        sections: old.sections,
+        heightmaps: old.heightmaps,
+        block_entities: old.block_entities,
+        last_modified: now(),
+    }))
+}
+
+/// Adds the `OCEAN_FLOOR` and `MOTION_BLOCKING_NO_LEAVES` heightmaps.
+///
+/// The old heightmaps weren't kept up to date when blocks changed, so they're all left empty.
+/// Chunks with empty heightmaps get them calculated when they're loaded, with whatever the
+/// heightmap code is by then.
+fn v1_to_v2(data: &[u8]) -> Result<Vec<u8>, WorldError> {
+    let old: v1::Chunk =
+        bitcode::decode(data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
+    Ok(bitcode::encode(&v2::Chunk {
+        x: old.x,
+        z: old.z,
+        dimension: old.dimension,
+        sections: old.sections,
+        heightmaps: v2::Heightmaps {
+            motion_blocking: Vec::new(),
+            world_surface: Vec::new(),
+            ocean_floor: Vec::new(),
+            motion_blocking_no_leaves: Vec::new(),
+        },
+        block_entities: old.block_entities,
+        last_modified: old.last_modified,
+    }))
+}
+
+/// Adds `pending_ticks`. Ticks were never saved before, so there are none.
+fn v2_to_v3(data: &[u8]) -> Result<Vec<u8>, WorldError> {
+    let old: v2::Chunk =
+        bitcode::decode(data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
+    Ok(bitcode::encode(&v3::Chunk {
+        x: old.x,
+        z: old.z,
+        dimension: old.dimension,
+        sections: old.sections,
+        heightmaps: old.heightmaps,
+        block_entities: old.block_entities,
+        last_modified: old.last_modified,
+        pending_ticks: Vec::new(),
+    }))
+}
+
+/// Adds `light_dirty`. Chunks were always relit as they were edited, so none are dirty.
+fn v3_to_v4(data: &[u8]) -> Result<Vec<u8>, WorldError> {
+    let old: v3::Chunk =
+        bitcode::decode(data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
+    Ok(bitcode::encode(&v4::Chunk {
+        x: old.x,
+        z: old.z,
+        dimension: old.dimension,
+        sections: old.sections,
+        heightmaps: old.heightmaps,
+        block_entities: old.block_entities,
+        last_modified: old.last_modified,
+        pending_ticks: old.pending_ticks,
+        light_dirty: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_id::BlockId;
+    use crate::chunk_format::{Chunk, Heightmaps, PendingTick};
+    use bitcode_derive::{Decode, Encode};
+    use ferrumc_net_codec::net_types::var_int::VarInt;
+
+    #[derive(Encode, Decode)]
+    struct V0 {
+        x: i32,
+    }
+
+    #[derive(Encode, Decode)]
+    struct V1 {
+        x: i32,
+        z: i32,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct V2 {
+        x: i64,
+        z: i64,
+    }
+
+    fn v0_to_v1(data: &[u8]) -> Result<Vec<u8>, WorldError> {
+        let old: V0 =
+            bitcode::decode(data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
+        Ok(bitcode::encode(&V1 { x: old.x, z: 0 }))
+    }
+
+    fn v1_to_v2(data: &[u8]) -> Result<Vec<u8>, WorldError> {
+        let old: V1 =
+            bitcode::decode(data).map_err(|e| WorldError::BitcodeDecodeError(e.to_string()))?;
+        Ok(bitcode::encode(&V2 {
+            x: old.x as i64,
+            z: old.z as i64,
+        }))
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[v0_to_v1, v1_to_v2];
+
+    #[test]
+    fn test_runs_every_migration_from_version() {
+        let data = run_migrations(bitcode::encode(&V0 { x: 5 }), 0, TEST_MIGRATIONS).unwrap();
+        assert_eq!(bitcode::decode::<V2>(&data).unwrap(), V2 { x: 5, z: 0 });
+
+        let data = run_migrations(bitcode::encode(&V1 { x: 5, z: 7 }), 1, TEST_MIGRATIONS).unwrap();
+        assert_eq!(bitcode::decode::<V2>(&data).unwrap(), V2 { x: 5, z: 7 });
+    }
+
+    #[test]
+    fn test_current_version_is_untouched() {
+        let data = bitcode::encode(&V2 { x: 1, z: 2 });
+        assert_eq!(
+            run_migrations(data.clone(), 2, TEST_MIGRATIONS).unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_rejects_newer_versions() {
+        assert!(matches!(
+            run_migrations(vec![], 3, TEST_MIGRATIONS),
+            Err(WorldError::UnsupportedChunkVersion(3, 2))
+        ));
+    }
+
+    /// Convert live structs into their frozen copy, which has the same encoding.
+    fn frozen<T: bitcode::DecodeOwned>(live: &impl bitcode::Encode) -> T {
+        bitcode::decode(&bitcode::encode(live)).unwrap()
+    }
+
+    #[test]
+    fn test_latest_layout_matches_chunk() {
+        let mut chunk = Chunk::new(3, 4, "overworld".to_string());
+        chunk.set_block_entity(1, 64, 2, VarInt::from(1), vec![10, 0, 0, 0]);
+        chunk
+            .pending_ticks
+            .push(PendingTick::new((1, 64, 2), BlockId::default(), 3, 0, true));
+        chunk.last_modified = 5;
+        chunk.light_dirty = true;
+        let latest: v4::Chunk = frozen(&chunk);
+        let decoded: Chunk = bitcode::decode(&bitcode::encode(&latest)).unwrap();
+        assert_eq!(decoded, chunk);
+    }
+
+    #[test]
+    fn test_old_chunks_count_as_modified() {
+        let chunk = Chunk::new(3, 4, "overworld".to_string());
+        let old = v0::Chunk {
+            x: chunk.x,
+            z: chunk.z,
+            dimension: chunk.dimension.clone(),
+            sections: frozen(&chunk.sections),
+            heightmaps: v0::Heightmaps {
+                motion_blocking: vec![],
+                world_surface: vec![],
+            },
+            block_entities: frozen(&chunk.block_entities),
+        };
+        let data = migrate(bitcode::encode(&old), 0).unwrap();
+        let migrated: Chunk = bitcode::decode(&data).unwrap();
+        assert_eq!(migrated.sections, chunk.sections);
+        assert_ne!(migrated.last_modified, 0);
+    }
+
+    #[test]
+    fn test_heightmaps_are_dropped() {
+        let chunk = Chunk::new(3, 4, "overworld".to_string());
+        let old = v1::Chunk {
+            x: chunk.x,
+            z: chunk.z,
+            dimension: chunk.dimension.clone(),
+            sections: frozen(&chunk.sections),
+            // Stale, they're calculated again when the chunk is loaded
+            heightmaps: v0::Heightmaps {
+                motion_blocking: vec![0; 37],
+                world_surface: vec![0; 37],
+            },
+            block_entities: frozen(&chunk.block_entities),
+            last_modified: 5,
+        };
+        let data = migrate(bitcode::encode(&old), 1).unwrap();
+        let migrated: Chunk = bitcode::decode(&data).unwrap();
+        assert_eq!(migrated.heightmaps, Heightmaps::new());
+        assert_eq!(migrated.sections, chunk.sections);
+        assert_eq!(migrated.last_modified, 5);
+    }
+
+    #[test]
+    fn test_old_chunks_have_no_pending_ticks() {
+        let chunk = Chunk::new(3, 4, "overworld".to_string());
+        let old = v2::Chunk {
+            x: chunk.x,
+            z: chunk.z,
+            dimension: chunk.dimension.clone(),
+            sections: frozen(&chunk.sections),
+            heightmaps: frozen(&chunk.heightmaps),
+            block_entities: frozen(&chunk.block_entities),
+            last_modified: chunk.last_modified,
+        };
+        let data = migrate(bitcode::encode(&old), 2).unwrap();
+        let migrated: Chunk = bitcode::decode(&data).unwrap();
+        assert_eq!(migrated, chunk);
+    }
+
+    #[test]
+    fn test_old_chunks_keep_their_light() {
+        let mut chunk = Chunk::new(3, 4, "overworld".to_string());
+        chunk.pending_ticks.push(PendingTick::new(
+            (1, 64, 2),
+            BlockId::default(),
+            3,
+            0,
+            false,
+        ));
+        let old = v3::Chunk {
+            x: chunk.x,
+            z: chunk.z,
+            dimension: chunk.dimension.clone(),
+            sections: frozen(&chunk.sections),
+            heightmaps: frozen(&chunk.heightmaps),
+            block_entities: frozen(&chunk.block_entities),
+            last_modified: chunk.last_modified,
+            pending_ticks: frozen(&chunk.pending_ticks),
+        };
+        let data = migrate(bitcode::encode(&old), 3).unwrap();
+        let migrated: Chunk = bitcode::decode(&data).unwrap();
+        assert!(!migrated.light_dirty);
+        assert_eq!(migrated, chunk);
+    }
+
+    #[test]
+    fn test_reports_failed_migration() {
+        assert!(matches!(
+            run_migrations(vec![], 1, TEST_MIGRATIONS),
+            Err(WorldError::ChunkMigrationError(1, _))
+        ));
+    }
+}
@@ -0,0 +1,972 @@
+//! Pistons.
+//!
+//! Redstone tells the world when a piston's power changes, and the piston works out what it
+//! moves with vanilla's rules: at most [`PUSH_LIMIT`] blocks, slime and honey dragging whatever
+//! sticks to them along, some blocks stopping the piston and others breaking instead. Unlike
+//! vanilla, blocks with block entities move too, NBT and all, apart from the few that are
+//! [immovable](PushReaction::Block).
+//!
+//! Moving blocks turn into `moving_piston` for [`MOVE_TICKS`] game ticks while the players'
+//! clients animate them from the block event, and then land where they were going. A piston
+//! retracting before its blocks landed puts them down straight away, and a sticky one lets go of
+//! its block instead of pulling it back, like in vanilla.
+
+use crate::block_id::{BlockId, Direction, Extended};
+use crate::chunk_format::BlockEntity;
+use crate::errors::WorldError;
+use crate::redstone::Pos;
+use crate::region_edit::{AppliedEdit, BlockChange};
+use crate::World;
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+
+/// The most blocks a piston moves.
+pub const PUSH_LIMIT: usize = 12;
+
+/// How many game ticks blocks take to move.
+pub const MOVE_TICKS: u64 = 2;
+
+lazy_static! {
+    static ref AIR: BlockId = BlockId::from_name("minecraft:air").unwrap_or_default();
+    static ref PISTON_HEAD: BlockId =
+        BlockId::from_name("minecraft:piston_head").unwrap_or_default();
+    static ref MOVING_PISTON: BlockId =
+        BlockId::from_name("minecraft:moving_piston").unwrap_or_default();
+}
+
+/// Blocks pistons can't move.
+const IMMOVABLE: &[&str] = &[
+    "obsidian",
+    "crying_obsidian",
+    "respawn_anchor",
+    "reinforced_deepslate",
+    "bedrock",
+    "barrier",
+    "light",
+    "end_portal",
+    "end_portal_frame",
+    "end_gateway",
+    "nether_portal",
+    "command_block",
+    "chain_command_block",
+    "repeating_command_block",
+    "structure_block",
+    "jigsaw",
+    "moving_piston",
+    "piston_head",
+    "ender_chest",
+    "enchanting_table",
+    "beacon",
+    "spawner",
+    "trial_spawner",
+    "vault",
+    "grindstone",
+    "anvil",
+    "chipped_anvil",
+    "damaged_anvil",
+];
+
+/// Blocks that break when a piston pushes them.
+const BREAKABLE: &[&str] = &[
+    "water",
+    "lava",
+    "fire",
+    "soul_fire",
+    "structure_void",
+    "redstone_wire",
+    "repeater",
+    "comparator",
+    "torch",
+    "wall_torch",
+    "soul_torch",
+    "soul_wall_torch",
+    "redstone_torch",
+    "redstone_wall_torch",
+    "lever",
+    "tripwire",
+    "tripwire_hook",
+    "cobweb",
+    "short_grass",
+    "grass",
+    "fern",
+    "tall_grass",
+    "large_fern",
+    "dead_bush",
+    "seagrass",
+    "tall_seagrass",
+    "kelp",
+    "kelp_plant",
+    "lily_pad",
+    "sugar_cane",
+    "cactus",
+    "bamboo",
+    "bamboo_sapling",
+    "vine",
+    "ladder",
+    "snow",
+    "scaffolding",
+    "cake",
+    "dragon_egg",
+    "pumpkin",
+    "carved_pumpkin",
+    "jack_o_lantern",
+    "melon",
+    "pumpkin_stem",
+    "melon_stem",
+    "attached_pumpkin_stem",
+    "attached_melon_stem",
+    "flower_pot",
+    "decorated_pot",
+    "wheat",
+    "carrots",
+    "potatoes",
+    "beetroots",
+    "nether_wart",
+    "sweet_berry_bush",
+    "cocoa",
+    "chorus_plant",
+    "chorus_flower",
+    "turtle_egg",
+    "sea_pickle",
+    "frogspawn",
+    "lantern",
+    "soul_lantern",
+    "bell",
+    "candle",
+    "amethyst_cluster",
+    "dandelion",
+    "poppy",
+    "blue_orchid",
+    "allium",
+    "azure_bluet",
+    "oxeye_daisy",
+    "cornflower",
+    "lily_of_the_valley",
+    "wither_rose",
+    "torchflower",
+    "sunflower",
+    "lilac",
+    "rose_bush",
+    "peony",
+    "pitcher_plant",
+    "pink_petals",
+    "spore_blossom",
+    "glow_lichen",
+    "sculk_vein",
+    "hanging_roots",
+    "crimson_roots",
+    "warped_roots",
+    "nether_sprouts",
+    "small_dripleaf",
+    "big_dripleaf",
+    "big_dripleaf_stem",
+    "pointed_dripstone",
+    "cave_vines",
+    "cave_vines_plant",
+    "twisting_vines",
+    "twisting_vines_plant",
+    "weeping_vines",
+    "weeping_vines_plant",
+    "mangrove_propagule",
+];
+
+/// Whole families of blocks that break when pushed.
+const BREAKABLE_SUFFIXES: &[&str] = &[
+    "_button",
+    "_pressure_plate",
+    "_carpet",
+    "_door",
+    "_bed",
+    "_sapling",
+    "_tulip",
+    "_mushroom",
+    "_fungus",
+    "_candle",
+    "_amethyst_bud",
+    "_coral",
+    "_coral_fan",
+    "_coral_wall_fan",
+    "_head",
+    "_wall_head",
+    "_skull",
+    "_wall_skull",
+];
+
+/// What a block does when a piston tries to move it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PushReaction {
+    /// Air, there's nothing to move.
+    Empty,
+    Normal,
+    /// Breaks when pushed, and can't be pulled.
+    Destroy,
+    /// Stops the piston.
+    Block,
+    /// Can be pushed but not pulled, like glazed terracotta.
+    PushOnly,
+}
+
+/// What the block does when a piston tries to move it.
+pub fn push_reaction(block: BlockId) -> PushReaction {
+    let Some(name) = block.name() else {
+        return PushReaction::Block;
+    };
+    let name = name.trim_start_matches("minecraft:");
+    match name {
+        "air" | "cave_air" | "void_air" => PushReaction::Empty,
+        // Pistons can only be moved while they're retracted
+        "piston" | "sticky_piston" if block.get::<Extended>().is_some_and(|e| e.0) => {
+            PushReaction::Block
+        }
+        "piston" | "sticky_piston" => PushReaction::Normal,
+        _ if IMMOVABLE.contains(&name) => PushReaction::Block,
+        _ if name.ends_with("_glazed_terracotta") => PushReaction::PushOnly,
+        _ if BREAKABLE.contains(&name)
+            || BREAKABLE_SUFFIXES
+                .iter()
+                .any(|suffix| name.ends_with(suffix)) =>
+        {
+            PushReaction::Destroy
+        }
+        _ => PushReaction::Normal,
+    }
+}
+
+/// Slime and honey take the blocks around them along.
+fn is_sticky(block: Option<BlockId>) -> bool {
+    matches!(
+        block.and_then(|block| block.name()),
+        Some("minecraft:slime_block" | "minecraft:honey_block")
+    )
+}
+
+/// Whether one of two blocks drags the other along. Slime and honey don't stick to each other.
+fn sticks_to(a: Option<BlockId>, b: Option<BlockId>) -> bool {
+    let names = (
+        a.and_then(|block| block.name()),
+        b.and_then(|block| block.name()),
+    );
+    if matches!(
+        names,
+        (Some("minecraft:slime_block"), Some("minecraft:honey_block"))
+            | (Some("minecraft:honey_block"), Some("minecraft:slime_block"))
+    ) {
+        return false;
+    }
+    is_sticky(a) || is_sticky(b)
+}
+
+fn is_empty(block: Option<BlockId>) -> bool {
+    block.is_some_and(|block| push_reaction(block) == PushReaction::Empty)
+}
+
+/// Whether a block can be moved `movement` by a piston facing `piston_facing`. Blocks that
+/// aren't loaded or are outside the world never move.
+fn is_movable(
+    block: Option<BlockId>,
+    movement: Direction,
+    allow_destroy: bool,
+    piston_facing: Direction,
+) -> bool {
+    let Some(block) = block else {
+        return false;
+    };
+    match push_reaction(block) {
+        PushReaction::Empty | PushReaction::Normal => true,
+        PushReaction::Destroy => allow_destroy,
+        PushReaction::Block => false,
+        PushReaction::PushOnly => movement == piston_facing,
+    }
+}
+
+fn offset(pos: Pos, direction: Direction, distance: i32) -> Pos {
+    let (dx, dy, dz) = direction.offset();
+    (
+        pos.0 + dx * distance,
+        pos.1 + dy * distance,
+        pos.2 + dz * distance,
+    )
+}
+
+/// The blocks a piston moves.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PushStructure {
+    /// Where the blocks moving are, in the order vanilla moves them.
+    pub moved: Vec<Pos>,
+    /// Blocks in the way that break.
+    pub broken: Vec<Pos>,
+}
+
+/// Work out what the piston at `piston` facing `facing` moves, `None` if something stops it.
+///
+/// When retracting, this finds what a sticky piston pulls, starting two blocks in front of it,
+/// so the head should already be out of the way. `block` looks up a block, `None` if it isn't
+/// loaded or is outside the world.
+pub fn resolve(
+    piston: Pos,
+    facing: Direction,
+    extending: bool,
+    block: &mut impl FnMut(Pos) -> Option<BlockId>,
+) -> Option<PushStructure> {
+    let (push, start) = if extending {
+        (facing, facing.step(piston))
+    } else {
+        (facing.opposite(), offset(piston, facing, 2))
+    };
+    let mut resolver = Resolver {
+        block,
+        piston,
+        push,
+        structure: PushStructure::default(),
+    };
+    let first = resolver.get(start);
+    if !is_movable(first, push, false, facing) {
+        return if extending && first.map(push_reaction) == Some(PushReaction::Destroy) {
+            resolver.structure.broken.push(start);
+            Some(resolver.structure)
+        } else {
+            None
+        };
+    }
+    if !resolver.add_line(start, push) {
+        return None;
+    }
+    // Sticky blocks found while adding branches are added to the end, and branch out too
+    let mut i = 0;
+    while i < resolver.structure.moved.len() {
+        let pos = resolver.structure.moved[i];
+        if is_sticky(resolver.get(pos)) && !resolver.add_branches(pos) {
+            return None;
+        }
+        i += 1;
+    }
+    Some(resolver.structure)
+}
+
+struct Resolver<'a, F> {
+    block: &'a mut F,
+    piston: Pos,
+    push: Direction,
+    structure: PushStructure,
+}
+
+impl<F: FnMut(Pos) -> Option<BlockId>> Resolver<'_, F> {
+    fn get(&mut self, pos: Pos) -> Option<BlockId> {
+        (self.block)(pos)
+    }
+
+    /// Add the line of blocks starting at `origin`: whatever sticks to it from behind, then
+    /// everything in front of it up to a gap. `facing` is the way the sticky block pulling this
+    /// line in faces, or the piston's for the first line.
+    fn add_line(&mut self, origin: Pos, facing: Direction) -> bool {
+        let mut block = self.get(origin);
+        if is_empty(block)
+            || !is_movable(block, self.push, false, facing)
+            || origin == self.piston
+            || self.structure.moved.contains(&origin)
+        {
+            return true;
+        }
+        let back = self.push.opposite();
+        let mut length = 1;
+        if length + self.structure.moved.len() > PUSH_LIMIT {
+            return false;
+        }
+        while is_sticky(block) {
+            let pos = offset(origin, back, length as i32);
+            let previous = block;
+            block = self.get(pos);
+            if is_empty(block)
+                || !sticks_to(previous, block)
+                || !is_movable(block, self.push, false, back)
+                || pos == self.piston
+            {
+                break;
+            }
+            length += 1;
+            if length + self.structure.moved.len() > PUSH_LIMIT {
+                return false;
+            }
+        }
+
+        let mut added = 0;
+        for distance in (0..length).rev() {
+            self.structure
+                .moved
+                .push(offset(origin, back, distance as i32));
+            added += 1;
+        }
+        let mut distance = 1;
+        loop {
+            let pos = offset(origin, self.push, distance);
+            if let Some(collision) = self.structure.moved.iter().position(|&p| p == pos) {
+                // Ran into blocks already moving, which have to move after these
+                self.reorder(added, collision);
+                let mut i = 0;
+                while i <= collision + added {
+                    let pos = self.structure.moved[i];
+                    if is_sticky(self.get(pos)) && !self.add_branches(pos) {
+                        return false;
+                    }
+                    i += 1;
+                }
+                return true;
+            }
+            let block = self.get(pos);
+            if is_empty(block) {
+                return true;
+            }
+            if !is_movable(block, self.push, true, self.push) || pos == self.piston {
+                return false;
+            }
+            if block.map(push_reaction) == Some(PushReaction::Destroy) {
+                self.structure.broken.push(pos);
+                return true;
+            }
+            if self.structure.moved.len() >= PUSH_LIMIT {
+                return false;
+            }
+            self.structure.moved.push(pos);
+            added += 1;
+            distance += 1;
+        }
+    }
+
+    /// Move the last `added` blocks in front of the ones from `collision` on.
+    fn reorder(&mut self, added: usize, collision: usize) {
+        let moved = &self.structure.moved;
+        let tail = moved.len() - added;
+        let mut reordered = moved[..collision].to_vec();
+        reordered.extend_from_slice(&moved[tail..]);
+        reordered.extend_from_slice(&moved[collision..tail]);
+        self.structure.moved = reordered;
+    }
+
+    /// Add the lines of blocks stuck to the sides of the sticky block at `pos`.
+    fn add_branches(&mut self, pos: Pos) -> bool {
+        let block = self.get(pos);
+        for dir in Direction::ALL {
+            if dir == self.push || dir == self.push.opposite() {
+                continue;
+            }
+            let side = dir.step(pos);
+            let side_block = self.get(side);
+            if sticks_to(side_block, block) && !self.add_line(side, dir) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A block on its way somewhere, shown as `moving_piston` until it lands.
+struct MovingBlock {
+    dimension: String,
+    /// The piston moving it.
+    piston: Pos,
+    pos: Pos,
+    block: BlockId,
+    block_entity: Option<BlockEntity>,
+    lands: u64,
+}
+
+/// Something that happened to pistons, for sending to the players.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PistonEvent {
+    /// A piston started extending or retracting. The players have to be sent a block event for
+    /// it before the changed blocks, so their clients animate the move.
+    Moved {
+        pos: Pos,
+        facing: Direction,
+        extending: bool,
+        sticky: bool,
+        /// Which way the blocks move: the way the piston faces when extending, and back towards
+        /// it when retracting.
+        direction: Direction,
+        /// Where the blocks are moving to, the head included. Entities there get pushed along.
+        moving_to: Vec<Pos>,
+        applied: AppliedEdit,
+    },
+    /// Moving blocks landed.
+    Landed(AppliedEdit),
+}
+
+/// Blocks that are moving, and what happened since the players were last told.
+#[derive(Default)]
+pub struct Pistons {
+    /// Game ticks since the world was loaded.
+    time: u64,
+    moving: Vec<MovingBlock>,
+    events: Vec<PistonEvent>,
+}
+
+impl Pistons {
+    /// Forget the blocks moving in a dimension.
+    pub fn unload_dimension(&mut self, dimension: &str) {
+        self.moving.retain(|moving| moving.dimension != dimension);
+    }
+}
+
+/// Blocks to set, with the block entities that go with them.
+type Placement = (BlockId, Option<BlockEntity>);
+
+impl World {
+    fn piston_block_at(&self, dimension: &str, (x, y, z): Pos) -> Option<BlockId> {
+        self.get_block_and_fetch(x, y, z, dimension).ok()
+    }
+
+    /// Extend or retract the piston at `pos`, if it isn't already. Returns whether it did
+    /// anything; pistons that can't push what's in front of them stay where they are.
+    pub fn move_piston(
+        &self,
+        dimension: &str,
+        pos: Pos,
+        extending: bool,
+    ) -> Result<bool, WorldError> {
+        let Some(piston) = self.piston_block_at(dimension, pos) else {
+            return Ok(false);
+        };
+        let sticky = match piston.name() {
+            Some("minecraft:piston") => false,
+            Some("minecraft:sticky_piston") => true,
+            _ => return Ok(false),
+        };
+        let facing = piston.get::<Direction>().unwrap_or(Direction::North);
+        if piston.get::<Extended>().is_some_and(|e| e.0) == extending {
+            return Ok(false);
+        }
+        let head = facing.step(pos);
+        let moving_piston = MOVING_PISTON
+            .with(facing)
+            .and_then(|block| block.with_property("type", piston_type(sticky)).ok())
+            .unwrap_or(*MOVING_PISTON);
+
+        let mut blocks: BTreeMap<Pos, Placement> = BTreeMap::new();
+        let mut moving = Vec::new();
+        let direction;
+        if extending {
+            let mut lookup = |pos: Pos| self.piston_block_at(dimension, pos);
+            let Some(structure) = resolve(pos, facing, true, &mut lookup) else {
+                return Ok(false);
+            };
+            direction = facing;
+            self.collect_moves(
+                dimension,
+                &structure,
+                direction,
+                moving_piston,
+                &mut blocks,
+                &mut moving,
+            )?;
+            let piston_head = PISTON_HEAD
+                .with(facing)
+                .and_then(|block| block.with_property("type", piston_type(sticky)).ok())
+                .and_then(|block| block.with_property("short", "false").ok())
+                .unwrap_or(*PISTON_HEAD);
+            blocks.insert(head, (moving_piston, None));
+            moving.push((head, piston_head, None));
+        } else {
+            // Blocks still on their way out are dropped where they were going
+            let dropped = self.land_now(dimension, pos)?;
+            direction = facing.opposite();
+            let head_block = self.piston_block_at(dimension, head);
+            if head_block.and_then(|block| block.name()) == Some("minecraft:piston_head") {
+                blocks.insert(head, (*AIR, None));
+            }
+            let pulled = self.piston_block_at(dimension, offset(pos, facing, 2));
+            let pulls = pulled.is_some_and(|block| push_reaction(block) == PushReaction::Normal);
+            if sticky && !dropped && pulls {
+                let mut lookup = |at: Pos| {
+                    if at == head {
+                        Some(*AIR)
+                    } else {
+                        self.piston_block_at(dimension, at)
+                    }
+                };
+                if let Some(structure) = resolve(pos, facing, false, &mut lookup) {
+                    self.collect_moves(
+                        dimension,
+                        &structure,
+                        direction,
+                        moving_piston,
+                        &mut blocks,
+                        &mut moving,
+                    )?;
+                }
+            }
+        }
+        let base = piston.with(Extended(extending)).unwrap_or(piston);
+        blocks.insert(pos, (base, None));
+        let moving_to = moving.iter().map(|(to, ..)| *to).collect();
+
+        let applied = self.place_piston_blocks(dimension, &blocks)?;
+        let mut pistons = self.pistons.lock().unwrap();
+        let lands = pistons.time + MOVE_TICKS;
+        for (to, block, block_entity) in moving {
+            pistons.moving.push(MovingBlock {
+                dimension: dimension.to_string(),
+                piston: pos,
+                pos: to,
+                block,
+                block_entity,
+                lands,
+            });
+        }
+        pistons.events.push(PistonEvent::Moved {
+            pos,
+            facing,
+            extending,
+            sticky,
+            direction,
+            moving_to,
+            applied,
+        });
+        Ok(true)
+    }
+
+    /// Clear out where the blocks in `structure` are and break what's in their way, putting
+    /// `moving_piston` where they're going.
+    fn collect_moves(
+        &self,
+        dimension: &str,
+        structure: &PushStructure,
+        direction: Direction,
+        moving_piston: BlockId,
+        blocks: &mut BTreeMap<Pos, Placement>,
+        moving: &mut Vec<(Pos, BlockId, Option<BlockEntity>)>,
+    ) -> Result<(), WorldError> {
+        for &pos in structure.broken.iter().chain(&structure.moved) {
+            blocks.insert(pos, (*AIR, None));
+        }
+        for &from in &structure.moved {
+            let Some(block) = self.piston_block_at(dimension, from) else {
+                continue;
+            };
+            let (x, y, z) = from;
+            let chunk = self.load_chunk(x >> 4, z >> 4, dimension)?;
+            let block_entity = chunk
+                .get_block_entity((x & 0xf) as u8, y as u16, (z & 0xf) as u8)
+                .cloned();
+            let to = direction.step(from);
+            moving.push((to, block, block_entity));
+        }
+        for (to, ..) in moving.iter() {
+            blocks.insert(*to, (moving_piston, None));
+        }
+        Ok(())
+    }
+
+    /// Set blocks a chunk at a time, replacing the block entities there with the ones given.
+    fn place_piston_blocks(
+        &self,
+        dimension: &str,
+        blocks: &BTreeMap<Pos, Placement>,
+    ) -> Result<AppliedEdit, WorldError> {
+        let mut chunks: BTreeMap<(i32, i32), Vec<(Pos, &Placement)>> = BTreeMap::new();
+        for (&pos, placement) in blocks {
+            chunks
+                .entry((pos.0 >> 4, pos.2 >> 4))
+                .or_default()
+                .push((pos, placement));
+        }
+        let mut changes = Vec::new();
+        for ((chunk_x, chunk_z), placements) in chunks {
+            let mut chunk = self.load_chunk_owned(chunk_x, chunk_z, dimension)?;
+            chunk.mark_modified();
+            let mut set = Vec::with_capacity(placements.len());
+            for ((x, y, z), (block, block_entity)) in placements {
+                let old = chunk.get_block(x, y, z)?;
+                let (local_x, local_z) = ((x & 0xf) as u8, (z & 0xf) as u8);
+                chunk.remove_block_entity(local_x, y as u16, local_z);
+                if let Some(block_entity) = block_entity {
+                    chunk.set_block_entity(
+                        local_x,
+                        y as u16,
+                        local_z,
+                        block_entity.entity_type,
+                        block_entity.nbt.clone(),
+                    );
+                    self.block_entity_changed(
+                        dimension,
+                        (x, y, z),
+                        block_entity.entity_type,
+                        block_entity.nbt.clone(),
+                    );
+                }
+                set.push(((x, y, z), *block));
+                changes.push(BlockChange {
+                    position: (x, y, z),
+                    old,
+                    new: *block,
+                });
+            }
+            self.set_blocks_and_relight(chunk, &set)?;
+        }
+        for change in &changes {
+            let (x, y, z) = change.position;
+            self.block_updated(x, y, z, dimension, change.new);
+        }
+        changes.retain(|change| change.old != change.new);
+        Ok(AppliedEdit {
+            dimension: dimension.to_string(),
+            changes,
+        })
+    }
+
+    /// Put moving blocks down where they were going, unless something replaced them on the way.
+    fn land(&self, landing: Vec<MovingBlock>) -> Result<(), WorldError> {
+        let mut dimensions: BTreeMap<String, BTreeMap<Pos, Placement>> = BTreeMap::new();
+        for moving in landing {
+            let still_moving = self
+                .piston_block_at(&moving.dimension, moving.pos)
+                .and_then(|block| block.name())
+                == Some("minecraft:moving_piston");
+            if still_moving {
+                dimensions
+                    .entry(moving.dimension)
+                    .or_default()
+                    .insert(moving.pos, (moving.block, moving.block_entity));
+            }
+        }
+        for (dimension, blocks) in dimensions {
+            let applied = self.place_piston_blocks(&dimension, &blocks)?;
+            if !applied.changes.is_empty() {
+                self.pistons
+                    .lock()
+                    .unwrap()
+                    .events
+                    .push(PistonEvent::Landed(applied));
+            }
+        }
+        Ok(())
+    }
+
+    /// Land whatever the piston at `pos` is still moving. Returns whether there was anything.
+    fn land_now(&self, dimension: &str, pos: Pos) -> Result<bool, WorldError> {
+        let landing = {
+            let mut pistons = self.pistons.lock().unwrap();
+            let (landing, moving) = std::mem::take(&mut pistons.moving)
+                .into_iter()
+                .partition::<Vec<_>, _>(|moving| {
+                    moving.dimension == dimension && moving.piston == pos
+                });
+            pistons.moving = moving;
+            landing
+        };
+        let any = !landing.is_empty();
+        self.land(landing)?;
+        Ok(any)
+    }
+
+    /// Land the blocks that finished moving, then move the pistons redstone powered or
+    /// unpowered this tick.
+    pub(crate) fn tick_pistons(&self) -> Result<(), WorldError> {
+        let landing = {
+            let mut pistons = self.pistons.lock().unwrap();
+            pistons.time += 1;
+            let time = pistons.time;
+            let (landing, moving) = std::mem::take(&mut pistons.moving)
+                .into_iter()
+                .partition::<Vec<_>, _>(|moving| moving.lands <= time);
+            pistons.moving = moving;
+            landing
+        };
+        self.land(landing)?;
+        let triggered = self.redstone.lock().unwrap().take_pistons();
+        for (dimension, pos, extending) in triggered {
+            self.move_piston(&dimension, pos, extending)?;
+        }
+        Ok(())
+    }
+
+    /// Take what happened to pistons since this was last called, for sending to players.
+    pub fn take_piston_events(&self) -> Vec<PistonEvent> {
+        std::mem::take(&mut self.pistons.lock().unwrap().events)
+    }
+}
+
+fn piston_type(sticky: bool) -> &'static str {
+    if sticky {
+        "sticky"
+    } else {
+        "normal"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_entities::chest::{ChestBlockEntity, ChestItem};
+    use crate::testing::{memory_world, tick};
+    use ferrumc_net_codec::net_types::var_int::VarInt;
+    use std::collections::HashMap;
+
+    fn block(name: &str) -> BlockId {
+        BlockId::from_name(&format!("minecraft:{name}")).unwrap()
+    }
+
+    /// Resolve a piston at the origin facing east, in a world that's only `blocks` on air.
+    fn push(blocks: &[(Pos, &str)], extending: bool) -> Option<PushStructure> {
+        let blocks = blocks
+            .iter()
+            .map(|&(pos, name)| (pos, block(name)))
+            .collect::<HashMap<_, _>>();
+        let mut lookup = |pos: Pos| Some(blocks.get(&pos).copied().unwrap_or(*AIR));
+        resolve((0, 0, 0), Direction::East, extending, &mut lookup)
+    }
+
+    #[test]
+    fn test_push_limit() {
+        let line = (1..=12).map(|x| ((x, 0, 0), "stone")).collect::<Vec<_>>();
+        let structure = push(&line, true).unwrap();
+        assert_eq!(structure.moved.len(), PUSH_LIMIT);
+        assert_eq!(structure.moved[0], (1, 0, 0));
+
+        let line = (1..=13).map(|x| ((x, 0, 0), "stone")).collect::<Vec<_>>();
+        assert_eq!(push(&line, true), None);
+    }
+
+    #[test]
+    fn test_immovable_and_breakable_blocks() {
+        assert_eq!(push(&[((1, 0, 0), "obsidian")], true), None);
+        assert_eq!(
+            push(&[((1, 0, 0), "stone"), ((2, 0, 0), "bedrock")], true),
+            None
+        );
+
+        let structure = push(&[((1, 0, 0), "stone"), ((2, 0, 0), "oak_button")], true).unwrap();
+        assert_eq!(structure.moved, vec![(1, 0, 0)]);
+        assert_eq!(structure.broken, vec![(2, 0, 0)]);
+
+        // Glazed terracotta can be pushed but not pulled
+        let terracotta = [((2, 0, 0), "white_glazed_terracotta")];
+        assert_eq!(push(&terracotta, false), None);
+        assert_eq!(
+            push(&[((1, 0, 0), terracotta[0].1)], true)
+                .unwrap()
+                .moved
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_sticky_pull() {
+        let structure = push(&[((2, 0, 0), "stone"), ((3, 0, 0), "stone")], false).unwrap();
+        assert_eq!(structure.moved, vec![(2, 0, 0)]);
+
+        // Slime pulls what's stuck to it, but not honey
+        let structure = push(
+            &[
+                ((2, 0, 0), "slime_block"),
+                ((3, 0, 0), "stone"),
+                ((2, 1, 0), "stone"),
+                ((2, 0, 1), "honey_block"),
+            ],
+            false,
+        )
+        .unwrap();
+        assert_eq!(structure.moved.len(), 3);
+        assert!(structure.moved.contains(&(3, 0, 0)));
+        assert!(structure.moved.contains(&(2, 1, 0)));
+        assert!(!structure.moved.contains(&(2, 0, 1)));
+    }
+
+    #[test]
+    fn test_slime_counts_towards_the_limit() {
+        let mut blocks = (0..=12)
+            .map(|y| ((1, y, 0), "slime_block"))
+            .collect::<Vec<_>>();
+        assert_eq!(push(&blocks, true), None);
+        blocks.pop();
+        assert_eq!(push(&blocks, true).unwrap().moved.len(), PUSH_LIMIT);
+    }
+
+    #[test]
+    fn test_blocks_and_block_entities_move_across_chunks() {
+        let (world, _) = memory_world([(-1, 0), (0, 0)]);
+        let name_at = |x: i32| {
+            world
+                .get_block_and_fetch(x, 64, 0, "overworld")
+                .unwrap()
+                .name()
+                .unwrap()
+        };
+        let chest = ChestBlockEntity {
+            items: vec![ChestItem {
+                slot: 3,
+                id: "minecraft:diamond".to_string(),
+                count: 5,
+            }],
+        };
+
+        let piston = block("sticky_piston")
+            .with(Direction::East)
+            .and_then(|piston| piston.with(Extended(false)))
+            .unwrap();
+        world
+            .set_block_and_fetch(-2, 64, 0, "overworld", piston)
+            .unwrap();
+        world
+            .set_block_and_fetch(-1, 64, 0, "overworld", block("chest"))
+            .unwrap();
+        world
+            .save_block_entity(-1, 64, 0, "overworld", VarInt(1), &chest)
+            .unwrap();
+        world
+            .set_block_and_fetch(0, 64, 0, "overworld", block("stone"))
+            .unwrap();
+        world
+            .set_block_and_fetch(-3, 64, 0, "overworld", block("redstone_block"))
+            .unwrap();
+
+        // The piston extends straight away, its head and the blocks it pushes move for a bit
+        tick(&world, 1);
+        assert_eq!(name_at(-1), "minecraft:moving_piston");
+        assert_eq!(name_at(0), "minecraft:moving_piston");
+        assert_eq!(name_at(1), "minecraft:moving_piston");
+        let events = world.take_piston_events();
+        let [PistonEvent::Moved {
+            extending: true,
+            moving_to,
+            ..
+        }] = events.as_slice()
+        else {
+            panic!("Expected the piston to extend, got {events:?}");
+        };
+        assert_eq!(moving_to.len(), 3);
+
+        tick(&world, MOVE_TICKS as usize);
+        assert_eq!(name_at(-1), "minecraft:piston_head");
+        assert_eq!(name_at(0), "minecraft:chest");
+        assert_eq!(name_at(1), "minecraft:stone");
+        assert_eq!(
+            world
+                .load_block_entity::<ChestBlockEntity>(0, 64, 0, "overworld")
+                .unwrap(),
+            chest
+        );
+        assert!(world
+            .load_block_entity::<ChestBlockEntity>(-1, 64, 0, "overworld")
+            .is_err());
+        assert!(matches!(
+            world.take_piston_events().as_slice(),
+            [PistonEvent::Landed(_)]
+        ));
+
+        // Unpowered, it pulls the chest back but not the stone
+        world
+            .set_block_and_fetch(-3, 64, 0, "overworld", BlockId::default())
+            .unwrap();
+        tick(&world, 1 + MOVE_TICKS as usize);
+        assert_eq!(name_at(-1), "minecraft:chest");
+        assert_eq!(name_at(0), "minecraft:air");
+        assert_eq!(name_at(1), "minecraft:stone");
+        assert_eq!(
+            world
+                .load_block_entity::<ChestBlockEntity>(-1, 64, 0, "overworld")
+                .unwrap(),
+            chest
+        );
+    }
+}
@@ -1,23 +1,25 @@
-use crate::block_id::BlockId;
+use crate::item_id::ItemId;
 use lazy_static::lazy_static;
 use serde::Deserialize;
+use tracing::warn;
 
 #[derive(Debug, Clone, Deserialize)]
 struct RecipeJson {
-    pattern: Vec<u32>,
+    /// Item names, null for slots that have to be empty.
+    pattern: Vec<Option<String>>,
     output: OutputJson,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 struct OutputJson {
-    item: u32,
+    item: String,
     count: u8,
 }
 
 #[derive(Debug, Clone)]
 pub struct Recipe {
-    pub pattern: Vec<Option<BlockId>>,
-    pub output: (BlockId, u8),
+    pub pattern: Vec<Option<ItemId>>,
+    pub output: (ItemId, u8),
 }
 
 lazy_static! {
@@ -27,17 +29,29 @@ lazy_static! {
 fn load_recipes() -> Vec<Recipe> {
     let data = include_str!("../recipes/recipes.json");
     let parsed: Vec<RecipeJson> = serde_json::from_str(data).unwrap_or_default();
-    parsed
-        .into_iter()
-        .map(|r| Recipe {
-            pattern: r
-                .pattern
-                .into_iter()
-                .map(|id| if id == 0 { None } else { Some(BlockId(id)) })
-                .collect(),
-            output: (BlockId(r.output.item), r.output.count),
-        })
-        .collect()
+    parsed.into_iter().filter_map(recipe).collect()
+}
+
+/// Look up the items of a recipe, or None if it uses one that doesn't exist.
+fn recipe(json: RecipeJson) -> Option<Recipe> {
+    let item = |name: &str| {
+        let id = ItemId::from_name(name);
+        if id.is_none() {
+            warn!("Skipping recipe with unknown item {}", name);
+        }
+        id
+    };
+    let mut pattern = Vec::with_capacity(json.pattern.len());
+    for slot in &json.pattern {
+        pattern.push(match slot {
+            Some(name) => Some(item(name)?),
+            None => None,
+        });
+    }
+    Some(Recipe {
+        pattern,
+        output: (item(&json.output.item)?, json.output.count),
+    })
 }
 
 pub fn init() {
@@ -0,0 +1,579 @@
+//! Turning blocks into [`Network`]s.
+//!
+//! Compiling finds every component connected to a starting block, through wire, touching, or a
+//! solid block in between. Wire is then traced out from every component that powers it, so that
+//! whatever reads from wire gets an edge straight to the components powering it, with the length
+//! of the wire as the distance.
+
+use super::network::{Edge, Network, Node, NodeId, NodeKind};
+use super::{identify_component, Direction, Pos, RedstoneComponent};
+use crate::block_entities::is_container;
+use crate::block_id::{BlockId, Enabled, Extended, Lit, Power, Powered, Triggered};
+use crate::light::{light_opacity, MAX_LIGHT};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Networks stop growing here, so a runaway one can't stall the tick.
+const MAX_NODES: usize = 1 << 16;
+
+/// Wire can't carry a signal further than this.
+const MAX_WIRE_LENGTH: u8 = 15;
+
+/// Compile the network `seed` is part of, `None` if it isn't a component. `block` looks up a
+/// block, `None` if it isn't loaded.
+pub(crate) fn compile(
+    seed: Pos,
+    block: &mut impl FnMut(Pos) -> Option<BlockId>,
+) -> Option<Network> {
+    let mut compiler = Compiler {
+        block,
+        blocks: HashMap::new(),
+        nodes: Vec::new(),
+        ids: HashMap::new(),
+    };
+    compiler.component(seed)?;
+    compiler.find_components(seed);
+    if compiler.nodes.is_empty() {
+        return None;
+    }
+    Some(compiler.build())
+}
+
+struct Compiler<'a, F> {
+    block: &'a mut F,
+    blocks: HashMap<Pos, Option<BlockId>>,
+    nodes: Vec<(Node, RedstoneComponent)>,
+    ids: HashMap<Pos, NodeId>,
+}
+
+impl<F: FnMut(Pos) -> Option<BlockId>> Compiler<'_, F> {
+    fn block(&mut self, pos: Pos) -> Option<BlockId> {
+        if let Some(block) = self.blocks.get(&pos) {
+            return *block;
+        }
+        let block = (self.block)(pos);
+        self.blocks.insert(pos, block);
+        block
+    }
+
+    fn component(&mut self, pos: Pos) -> Option<RedstoneComponent> {
+        self.block(pos).and_then(|block| identify_component(&block))
+    }
+
+    /// Solid blocks carry power from the components on one side to those on the other.
+    fn is_conductor(&mut self, pos: Pos) -> bool {
+        self.block(pos).is_some_and(|block| {
+            light_opacity(block) == MAX_LIGHT && identify_component(&block).is_none()
+        })
+    }
+
+    fn is_wire(&mut self, pos: Pos) -> bool {
+        self.component(pos) == Some(RedstoneComponent::Wire)
+    }
+
+    fn node(&self, pos: Pos) -> Option<(NodeId, RedstoneComponent)> {
+        let id = *self.ids.get(&pos)?;
+        Some((id, self.nodes[id].1))
+    }
+
+    /// Flood out from `seed`, adding a node for every component found.
+    fn find_components(&mut self, seed: Pos) {
+        let mut visited = HashSet::from([seed]);
+        let mut queue = VecDeque::from([seed]);
+        while let Some(pos) = queue.pop_front() {
+            let Some(component) = self.component(pos) else {
+                continue;
+            };
+            self.add_node(pos, component);
+            if self.nodes.len() >= MAX_NODES {
+                break;
+            }
+
+            let mut reach = Direction::ALL.map(|dir| dir.step(pos)).to_vec();
+            match component {
+                // Wire goes up and down blocks
+                RedstoneComponent::Wire => {
+                    for dir in Direction::HORIZONTAL {
+                        let (x, y, z) = dir.step(pos);
+                        reach.extend([(x, y + 1, z), (x, y - 1, z)]);
+                    }
+                }
+                RedstoneComponent::Piston { .. } | RedstoneComponent::Dispenser => {
+                    reach.extend(quasi_inputs(pos));
+                }
+                _ => {}
+            }
+            // Pistons below and beside take power from around the block above them
+            for below in quasi_pistons(pos) {
+                let piston = matches!(
+                    self.component(below),
+                    Some(RedstoneComponent::Piston { .. } | RedstoneComponent::Dispenser)
+                );
+                if piston && visited.insert(below) {
+                    queue.push_back(below);
+                }
+            }
+            for next in reach {
+                if !visited.insert(next) {
+                    continue;
+                }
+                if self.component(next).is_some() {
+                    queue.push_back(next);
+                } else if self.is_conductor(next) {
+                    for dir in Direction::ALL {
+                        let beyond = dir.step(next);
+                        if self.component(beyond).is_some() && visited.insert(beyond) {
+                            queue.push_back(beyond);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_node(&mut self, pos: Pos, component: RedstoneComponent) {
+        let Some(block) = self.block(pos) else {
+            return;
+        };
+        let lit = block.get::<Lit>().is_some_and(|lit| lit.0);
+        let powered = block.get::<Powered>().is_some_and(|powered| powered.0);
+        let (kind, on, output) = match component {
+            RedstoneComponent::Wire => {
+                let power = block.get::<Power>().map_or(0, |power| power.0);
+                (NodeKind::Wire, power > 0, power)
+            }
+            RedstoneComponent::Torch { .. } => (NodeKind::Torch, lit, if lit { 15 } else { 0 }),
+            RedstoneComponent::Repeater { delay, .. } => (
+                NodeKind::Repeater {
+                    delay,
+                    facing_diode: false,
+                },
+                powered,
+                if powered { 15 } else { 0 },
+            ),
+            // A comparator's strength isn't part of its state, it's worked out once the
+            // network is built
+            RedstoneComponent::Comparator { mode, .. } => (
+                NodeKind::Comparator {
+                    mode,
+                    facing_diode: false,
+                },
+                powered,
+                0,
+            ),
+            RedstoneComponent::Observer { .. } => {
+                (NodeKind::Observer, powered, if powered { 15 } else { 0 })
+            }
+            RedstoneComponent::Lamp => (NodeKind::Lamp, lit, 0),
+            RedstoneComponent::Source { power, .. } => (NodeKind::Source, power > 0, power),
+            RedstoneComponent::Piston { .. } => {
+                let extended = block.get::<Extended>().is_some_and(|extended| extended.0);
+                (NodeKind::Piston, extended, 0)
+            }
+            RedstoneComponent::Hopper => {
+                let enabled = block.get::<Enabled>().is_none_or(|enabled| enabled.0);
+                (NodeKind::Hopper, !enabled, 0)
+            }
+            RedstoneComponent::Dispenser => {
+                let triggered = block
+                    .get::<Triggered>()
+                    .is_some_and(|triggered| triggered.0);
+                (NodeKind::Dispenser, triggered, 0)
+            }
+        };
+        self.ids.insert(pos, self.nodes.len());
+        self.nodes
+            .push((Node::new(pos, kind, block, on, output), component));
+    }
+
+    fn build(mut self) -> Network {
+        let wire_inputs = self.trace_wire();
+        let mut watched: HashMap<Pos, Vec<NodeId>> = HashMap::new();
+        let mut reading: HashMap<NodeId, Pos> = HashMap::new();
+        for id in 0..self.nodes.len() {
+            let (pos, component) = (self.nodes[id].0.pos, self.nodes[id].1);
+            let (inputs, side_inputs) = match component {
+                RedstoneComponent::Wire => (wire_inputs[id].clone(), Vec::new()),
+                RedstoneComponent::Torch { attached } => (
+                    self.inputs_at(attached.step(pos), pos, true, &wire_inputs),
+                    Vec::new(),
+                ),
+                RedstoneComponent::Repeater { facing, .. } => {
+                    let inputs = self.inputs_at(facing.step(pos), pos, true, &wire_inputs);
+                    let sides = self.diode_sides(pos, facing, false, &wire_inputs);
+                    self.mark_facing_diode(id, facing);
+                    (inputs, sides)
+                }
+                RedstoneComponent::Comparator { facing, .. } => {
+                    let behind = facing.step(pos);
+                    if self.block(behind).is_some_and(is_container) {
+                        reading.insert(id, behind);
+                    }
+                    let inputs = self.inputs_at(behind, pos, true, &wire_inputs);
+                    let sides = self.diode_sides(pos, facing, true, &wire_inputs);
+                    self.mark_facing_diode(id, facing);
+                    (inputs, sides)
+                }
+                RedstoneComponent::Observer { facing } => {
+                    watched.entry(facing.step(pos)).or_default().push(id);
+                    (Vec::new(), Vec::new())
+                }
+                RedstoneComponent::Lamp | RedstoneComponent::Hopper => {
+                    let mut inputs = Vec::new();
+                    for dir in Direction::ALL {
+                        inputs.extend(self.inputs_at(dir.step(pos), pos, false, &wire_inputs));
+                    }
+                    (inputs, Vec::new())
+                }
+                RedstoneComponent::Piston { .. } | RedstoneComponent::Dispenser => {
+                    // Pistons can't be powered through their front, where the head goes
+                    let facing = match component {
+                        RedstoneComponent::Piston { facing } => Some(facing),
+                        _ => None,
+                    };
+                    let mut inputs = Vec::new();
+                    for dir in Direction::ALL {
+                        if Some(dir) != facing {
+                            inputs.extend(self.inputs_at(dir.step(pos), pos, false, &wire_inputs));
+                        }
+                    }
+                    let above = Direction::Up.step(pos);
+                    for from in quasi_inputs(pos) {
+                        inputs.extend(self.inputs_at(from, above, false, &wire_inputs));
+                    }
+                    (inputs, Vec::new())
+                }
+                RedstoneComponent::Source { .. } => (Vec::new(), Vec::new()),
+            };
+            self.nodes[id].0.inputs = dedup_edges(inputs);
+            self.nodes[id].0.side_inputs = dedup_edges(side_inputs);
+        }
+
+        let mut nodes = self
+            .nodes
+            .into_iter()
+            .map(|(node, _)| node)
+            .collect::<Vec<_>>();
+        // Sorted so updates always go out in the same order
+        let links = nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(id, node)| {
+                node.inputs
+                    .iter()
+                    .chain(&node.side_inputs)
+                    .map(move |edge| (edge.from, id))
+            })
+            .collect::<BTreeSet<_>>();
+        for (from, to) in links {
+            nodes[from].outputs.push(to);
+        }
+        for (pos, observers) in &watched {
+            if let Some(&id) = self.ids.get(pos) {
+                nodes[id].watchers.extend(observers);
+            }
+        }
+
+        let mut network = Network::new(nodes, watched);
+        network.read_containers(reading);
+        network.init_comparators();
+        network
+    }
+
+    /// Repeaters and comparators pointing into another one tick first.
+    fn mark_facing_diode(&mut self, id: NodeId, facing: Direction) {
+        let front = facing.opposite().step(self.nodes[id].0.pos);
+        let diode_in_front = matches!(
+            self.node(front),
+            Some((
+                _,
+                RedstoneComponent::Repeater { .. } | RedstoneComponent::Comparator { .. }
+            ))
+        );
+        if let NodeKind::Repeater { facing_diode, .. } | NodeKind::Comparator { facing_diode, .. } =
+            &mut self.nodes[id].0.kind
+        {
+            *facing_diode = diode_in_front;
+        }
+    }
+
+    /// The wire connected to the wire at `pos`, including up and down a block.
+    fn wire_links(&mut self, pos: Pos) -> Vec<Pos> {
+        let (x, y, z) = pos;
+        let covered = self.is_conductor((x, y + 1, z));
+        let mut links = Vec::new();
+        for dir in Direction::HORIZONTAL {
+            let side = dir.step(pos);
+            let (sx, sy, sz) = side;
+            if self.is_wire(side) {
+                links.push(side);
+            } else if self.is_conductor(side) {
+                if !covered && self.is_wire((sx, sy + 1, sz)) {
+                    links.push((sx, sy + 1, sz));
+                }
+            } else if self.is_wire((sx, sy - 1, sz)) {
+                links.push((sx, sy - 1, sz));
+            }
+        }
+        links
+    }
+
+    /// Whether the wire at `wire` powers the block at `target` next to or under it.
+    fn wire_points_at(&mut self, wire: Pos, target: Pos) -> bool {
+        let Some(block) = self.block(wire) else {
+            return false;
+        };
+        let side = Direction::HORIZONTAL
+            .into_iter()
+            .find(|dir| dir.step(wire) == target);
+        let Some(side) = side else {
+            // Wire always powers the block it's on
+            return Direction::Down.step(wire) == target;
+        };
+        let connected = |dir: Direction| {
+            block
+                .get_property(dir.as_str())
+                .is_some_and(|v| v != "none")
+        };
+        // A dot with no connections powers all around it
+        connected(side) || !Direction::HORIZONTAL.into_iter().any(connected)
+    }
+
+    /// Components powering the wire at `pos` directly, rather than through more wire.
+    fn wire_sources(&mut self, pos: Pos) -> Vec<NodeId> {
+        let mut sources = Vec::new();
+        for dir in Direction::ALL {
+            let next = dir.step(pos);
+            if let Some((id, _)) = self.node(next) {
+                if self.powers(next, pos) {
+                    sources.push(id);
+                }
+            } else if self.is_conductor(next) {
+                sources.extend(self.strong_sources(next));
+            }
+        }
+        sources
+    }
+
+    /// Whether the component at `from` gives its signal to the block next to it at `to`.
+    fn powers(&self, from: Pos, to: Pos) -> bool {
+        let Some((_, component)) = self.node(from) else {
+            return false;
+        };
+        match component {
+            RedstoneComponent::Torch { attached } => attached.step(from) != to,
+            RedstoneComponent::Repeater { facing, .. }
+            | RedstoneComponent::Comparator { facing, .. }
+            | RedstoneComponent::Observer { facing } => facing.opposite().step(from) == to,
+            RedstoneComponent::Source { .. } => true,
+            RedstoneComponent::Wire
+            | RedstoneComponent::Lamp
+            | RedstoneComponent::Piston { .. }
+            | RedstoneComponent::Hopper
+            | RedstoneComponent::Dispenser => false,
+        }
+    }
+
+    /// Components strongly powering the solid block at `pos`, which then powers wire too.
+    fn strong_sources(&self, pos: Pos) -> Vec<NodeId> {
+        let mut sources = Vec::new();
+        for dir in Direction::ALL {
+            let next = dir.step(pos);
+            let Some((id, component)) = self.node(next) else {
+                continue;
+            };
+            let strong = match component {
+                // Torches power the block above them
+                RedstoneComponent::Torch { .. } => dir == Direction::Down,
+                RedstoneComponent::Repeater { .. }
+                | RedstoneComponent::Comparator { .. }
+                | RedstoneComponent::Observer { .. } => self.powers(next, pos),
+                RedstoneComponent::Source { attached, .. } => {
+                    attached.is_some_and(|attached| attached.step(next) == pos)
+                }
+                _ => false,
+            };
+            if strong {
+                sources.push(id);
+            }
+        }
+        sources
+    }
+
+    /// Follow wire out from every component powering it, collecting which components reach each
+    /// wire and through how much wire.
+    fn trace_wire(&mut self) -> Vec<Vec<Edge>> {
+        let mut reached: Vec<HashMap<NodeId, u8>> = vec![HashMap::new(); self.nodes.len()];
+        let wires = (0..self.nodes.len())
+            .filter(|&id| self.nodes[id].1 == RedstoneComponent::Wire)
+            .collect::<Vec<_>>();
+        for start in wires {
+            let pos = self.nodes[start].0.pos;
+            let sources = self.wire_sources(pos);
+            if sources.is_empty() {
+                continue;
+            }
+            let mut distances = HashMap::from([(start, 0u8)]);
+            let mut queue = VecDeque::from([start]);
+            while let Some(wire) = queue.pop_front() {
+                let distance = distances[&wire];
+                if distance + 1 >= MAX_WIRE_LENGTH {
+                    continue;
+                }
+                let pos = self.nodes[wire].0.pos;
+                for link in self.wire_links(pos) {
+                    let Some((id, _)) = self.node(link) else {
+                        continue;
+                    };
+                    if let Entry::Vacant(entry) = distances.entry(id) {
+                        entry.insert(distance + 1);
+                        queue.push_back(id);
+                    }
+                }
+            }
+            for (wire, distance) in distances {
+                for &source in &sources {
+                    let best = reached[wire].entry(source).or_insert(distance);
+                    *best = (*best).min(distance);
+                }
+            }
+        }
+        reached
+            .into_iter()
+            .map(|sources| {
+                sources
+                    .into_iter()
+                    .map(|(from, distance)| Edge { from, distance })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The signals the component at `to` gets from the block at `from`. Torches, repeaters and
+    /// comparators also take `weak` power from solid blocks that wire runs into.
+    fn inputs_at(
+        &mut self,
+        from: Pos,
+        to: Pos,
+        weak: bool,
+        wire_inputs: &[Vec<Edge>],
+    ) -> Vec<Edge> {
+        if let Some((id, component)) = self.node(from) {
+            if component == RedstoneComponent::Wire {
+                // Wire always connects to the back of a repeater or comparator
+                return if weak || self.wire_points_at(from, to) {
+                    wire_inputs[id].clone()
+                } else {
+                    Vec::new()
+                };
+            }
+            return if self.powers(from, to) {
+                vec![Edge {
+                    from: id,
+                    distance: 0,
+                }]
+            } else {
+                Vec::new()
+            };
+        }
+        if !self.is_conductor(from) {
+            return Vec::new();
+        }
+        let mut inputs = self
+            .strong_sources(from)
+            .into_iter()
+            .map(|id| Edge {
+                from: id,
+                distance: 0,
+            })
+            .collect::<Vec<_>>();
+        if weak {
+            let mut wires = vec![Direction::Up.step(from)];
+            wires.extend(Direction::HORIZONTAL.map(|dir| dir.step(from)));
+            for wire in wires {
+                if let Some((id, RedstoneComponent::Wire)) = self.node(wire) {
+                    if self.wire_points_at(wire, from) {
+                        inputs.extend_from_slice(&wire_inputs[id]);
+                    }
+                }
+            }
+        }
+        inputs
+    }
+
+    /// Signals from beside a repeater or comparator. Only repeaters and comparators lock
+    /// repeaters; comparators also take wire and redstone blocks.
+    fn diode_sides(
+        &mut self,
+        pos: Pos,
+        facing: Direction,
+        comparator: bool,
+        wire_inputs: &[Vec<Edge>],
+    ) -> Vec<Edge> {
+        let mut inputs = Vec::new();
+        for dir in Direction::HORIZONTAL {
+            if dir == facing || dir == facing.opposite() {
+                continue;
+            }
+            let side = dir.step(pos);
+            let Some((id, component)) = self.node(side) else {
+                continue;
+            };
+            match component {
+                RedstoneComponent::Repeater { .. } | RedstoneComponent::Comparator { .. } => {
+                    if self.powers(side, pos) {
+                        inputs.push(Edge {
+                            from: id,
+                            distance: 0,
+                        });
+                    }
+                }
+                RedstoneComponent::Wire if comparator => {
+                    inputs.extend_from_slice(&wire_inputs[id]);
+                }
+                RedstoneComponent::Source { attached: None, .. } if comparator => {
+                    inputs.push(Edge {
+                        from: id,
+                        distance: 0,
+                    });
+                }
+                _ => {}
+            }
+        }
+        inputs
+    }
+}
+
+/// Pistons are also powered by whatever would power the block above them, and these are the
+/// blocks that could.
+fn quasi_inputs(piston: Pos) -> impl Iterator<Item = Pos> {
+    let above = Direction::Up.step(piston);
+    Direction::ALL
+        .into_iter()
+        .map(move |dir| dir.step(above))
+        .filter(move |&pos| pos != piston)
+}
+
+/// Where the pistons the block at `pos` is one of the [`quasi_inputs`] of would be.
+pub(super) fn quasi_pistons(pos: Pos) -> impl Iterator<Item = Pos> {
+    Direction::ALL
+        .into_iter()
+        .map(move |dir| Direction::Down.step(dir.step(pos)))
+        .filter(move |&piston| piston != pos)
+}
+
+/// Keep the shortest distance from each input.
+fn dedup_edges(edges: Vec<Edge>) -> Vec<Edge> {
+    let mut shortest: HashMap<NodeId, u8> = HashMap::new();
+    for edge in edges {
+        let distance = shortest.entry(edge.from).or_insert(edge.distance);
+        *distance = (*distance).min(edge.distance);
+    }
+    let mut edges = shortest
+        .into_iter()
+        .filter(|&(_, distance)| distance < MAX_WIRE_LENGTH)
+        .map(|(from, distance)| Edge { from, distance })
+        .collect::<Vec<_>>();
+    edges.sort_by_key(|edge| edge.from);
+    edges
+}
@@ -0,0 +1,865 @@
+//! Redstone.
+//!
+//! Rather than walking the blocks around every change, connected wire and components are compiled
+//! into a [`Network`]: a graph where every torch, repeater, comparator, observer, lamp, piston and
+//! other block redstone drives is a node, and wire is boiled down to edges that lose one signal
+//! strength per block. The graph is evaluated incrementally with vanilla's delays and update
+//! order, and only the nodes whose inputs changed do any work.
+//!
+//! Networks are compiled lazily on the tick after a block next to them was edited, and thrown away
+//! when one is. The block states they change are applied a chunk at a time, and kept for sending
+//! to the players as batched section updates. Pistons that get powered or unpowered are handed to
+//! [`crate::pistons`] instead, since moving blocks is more than a state change, and dispensers and
+//! droppers that get powered to [`crate::hoppers`]. Comparators reading a container get its signal
+//! from the world, which works it out from the container's items.
+//!
+//! Unloading a chunk throws away the networks in it, saving the node ticks they had scheduled
+//! there with the chunk. The rest of those networks are compiled again on the next tick, and the
+//! saved ticks are carried over into whatever gets compiled once the chunk is loaded again.
+
+mod compiler;
+mod network;
+
+use crate::block_id::{BlockId, ComparatorMode, Delay, Power, Powered};
+use crate::errors::WorldError;
+use crate::hoppers::DISPENSE_DELAY;
+use crate::region_edit::{AppliedEdit, BlockChange};
+use crate::World;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+pub use crate::block_id::Direction;
+pub use network::Network;
+
+/// A block position within a dimension.
+pub type Pos = (i32, i32, i32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RedstoneComponent {
+    Wire,
+    /// `attached` points at the block the torch is on.
+    Torch {
+        attached: Direction,
+    },
+    /// Repeaters and comparators face the block they take their signal from, and power the one
+    /// on the opposite side.
+    Repeater {
+        delay: u8,
+        facing: Direction,
+    },
+    Comparator {
+        mode: ComparatorMode,
+        facing: Direction,
+    },
+    /// Observers watch the block they face and pulse out of their back.
+    Observer {
+        facing: Direction,
+    },
+    Piston {
+        facing: Direction,
+    },
+    Lamp,
+    Hopper,
+    /// Dispensers and droppers.
+    Dispenser,
+    /// Levers, buttons, pressure plates, daylight detectors and redstone blocks. Their power only
+    /// changes when the block itself is replaced, so they're constant within a network.
+    /// `attached` is the block they strongly power, if any.
+    Source {
+        power: u8,
+        attached: Option<Direction>,
+    },
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::Up,
+        Direction::Down,
+    ];
+
+    pub const HORIZONTAL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+
+    pub fn offset(self) -> (i32, i32, i32) {
+        match self {
+            Direction::North => (0, 0, -1),
+            Direction::South => (0, 0, 1),
+            Direction::East => (1, 0, 0),
+            Direction::West => (-1, 0, 0),
+            Direction::Up => (0, 1, 0),
+            Direction::Down => (0, -1, 0),
+        }
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+
+    /// The position next to `pos` in this direction.
+    pub fn step(self, (x, y, z): Pos) -> Pos {
+        let (dx, dy, dz) = self.offset();
+        (x + dx, y + dy, z + dz)
+    }
+}
+
+pub fn identify_component(block: &BlockId) -> Option<RedstoneComponent> {
+    let facing = || block.get::<Direction>().unwrap_or(Direction::North);
+    let powered = || block.get::<Powered>().is_some_and(|powered| powered.0);
+    let name = block.name()?;
+    let component = match name {
+        "minecraft:redstone_wire" => RedstoneComponent::Wire,
+        "minecraft:redstone_torch" => RedstoneComponent::Torch {
+            attached: Direction::Down,
+        },
+        // Wall torches face away from the wall they're on
+        "minecraft:redstone_wall_torch" => RedstoneComponent::Torch {
+            attached: facing().opposite(),
+        },
+        "minecraft:repeater" => RedstoneComponent::Repeater {
+            delay: block.get::<Delay>().map_or(1, |delay| delay.0),
+            facing: facing(),
+        },
+        "minecraft:comparator" => RedstoneComponent::Comparator {
+            mode: block
+                .get::<ComparatorMode>()
+                .unwrap_or(ComparatorMode::Compare),
+            facing: facing(),
+        },
+        "minecraft:observer" => RedstoneComponent::Observer { facing: facing() },
+        "minecraft:piston" | "minecraft:sticky_piston" => {
+            RedstoneComponent::Piston { facing: facing() }
+        }
+        "minecraft:redstone_lamp" => RedstoneComponent::Lamp,
+        "minecraft:hopper" => RedstoneComponent::Hopper,
+        "minecraft:dispenser" | "minecraft:dropper" => RedstoneComponent::Dispenser,
+        "minecraft:redstone_block" => RedstoneComponent::Source {
+            power: 15,
+            attached: None,
+        },
+        "minecraft:lever" => RedstoneComponent::Source {
+            power: if powered() { 15 } else { 0 },
+            attached: Some(attached_face(block, facing())),
+        },
+        "minecraft:daylight_detector" => RedstoneComponent::Source {
+            power: block.get::<Power>().map_or(0, |power| power.0),
+            attached: None,
+        },
+        name if name.ends_with("_button") => RedstoneComponent::Source {
+            power: if powered() { 15 } else { 0 },
+            attached: Some(attached_face(block, facing())),
+        },
+        name if name.ends_with("_pressure_plate") => {
+            // Weighted plates have a strength, the others are just on or off
+            let power = match block.get::<Power>() {
+                Some(power) => power.0,
+                None if powered() => 15,
+                None => 0,
+            };
+            RedstoneComponent::Source {
+                power,
+                attached: Some(Direction::Down),
+            }
+        }
+        _ => return None,
+    };
+    Some(component)
+}
+
+/// Which block a lever or button is on, from its `face` and `facing`.
+fn attached_face(block: &BlockId, facing: Direction) -> Direction {
+    match block.get_property("face") {
+        Some("floor") => Direction::Down,
+        Some("ceiling") => Direction::Up,
+        _ => facing.opposite(),
+    }
+}
+
+/// Every compiled network in a world.
+#[derive(Default)]
+pub struct RedstoneNetworks {
+    dimensions: HashMap<String, DimensionNetworks>,
+    /// Game ticks since the world was loaded. Scheduled node ticks are kept against this, so
+    /// they survive their network being recompiled.
+    time: u64,
+    /// Changes applied to the world that haven't been sent to players yet.
+    updates: Vec<AppliedEdit>,
+    /// Pistons whose power changed, by dimension, and whether they should extend.
+    pistons: Vec<(String, Pos, bool)>,
+    /// Dispensers and droppers that got powered, by dimension.
+    dispensers: Vec<(String, Pos)>,
+}
+
+#[derive(Default)]
+struct DimensionNetworks {
+    networks: HashMap<u32, Network>,
+    /// Which network every node is part of.
+    owners: HashMap<Pos, u32>,
+    next_id: u32,
+    /// Blocks edited since the networks were last brought up to date.
+    edited: BTreeSet<Pos>,
+    /// Blocks whose networks need compiling again even though nothing changed, like ones that
+    /// were cut off by a chunk unloading.
+    stale: BTreeSet<Pos>,
+    /// Node ticks waiting for their networks to be compiled again, with when they're due and
+    /// their priority.
+    carried: Vec<(Pos, u64, i8)>,
+    /// The signal of every container comparators read, as far as it's known.
+    containers: HashMap<Pos, u8>,
+    /// Containers whose signal changed since the last tick.
+    changed_containers: BTreeSet<Pos>,
+    /// Containers comparators read whose signal isn't known yet.
+    unread: BTreeSet<Pos>,
+}
+
+impl RedstoneNetworks {
+    /// Note that a block was edited, so the networks around it get recompiled on the next tick.
+    pub fn block_changed(&mut self, dimension: &str, pos: Pos) {
+        let networks = self.dimensions.entry(dimension.to_string()).or_default();
+        networks.edited.insert(pos);
+        // Whatever is there now has to be read again
+        networks.containers.remove(&pos);
+    }
+
+    /// Set the signal comparators get from the container at `pos`, from how full it is. Networks
+    /// see it on the next tick.
+    pub fn container_changed(&mut self, dimension: &str, pos: Pos, signal: u8) {
+        let networks = self.dimensions.entry(dimension.to_string()).or_default();
+        if networks.containers.insert(pos, signal) != Some(signal) {
+            networks.changed_containers.insert(pos);
+        }
+    }
+
+    /// Take the containers that comparators started reading without their signal being known,
+    /// by dimension. Give it with [`RedstoneNetworks::container_changed`].
+    pub fn take_unread_containers(&mut self) -> Vec<(String, Pos)> {
+        let mut unread = Vec::new();
+        for (dimension, networks) in &mut self.dimensions {
+            for pos in std::mem::take(&mut networks.unread) {
+                unread.push((dimension.clone(), pos));
+            }
+        }
+        unread.sort();
+        unread
+    }
+
+    /// The power of the wire at `pos`, or what the component there gives out.
+    pub fn power_at(&self, dimension: &str, pos: Pos) -> u8 {
+        self.dimensions
+            .get(dimension)
+            .and_then(|networks| {
+                let id = networks.owners.get(&pos)?;
+                networks.networks.get(id)?.power_at(pos)
+            })
+            .unwrap_or(0)
+    }
+
+    /// How many networks are compiled in a dimension.
+    pub fn network_count(&self, dimension: &str) -> usize {
+        self.dimensions
+            .get(dimension)
+            .map_or(0, |networks| networks.networks.len())
+    }
+
+    /// Forget every network in a dimension.
+    pub fn unload_dimension(&mut self, dimension: &str) {
+        self.dimensions.remove(dimension);
+        self.pistons
+            .retain(|(piston_dimension, ..)| piston_dimension != dimension);
+        self.dispensers
+            .retain(|(dispenser_dimension, _)| dispenser_dimension != dimension);
+    }
+
+    /// Throw away the networks with nodes in a chunk that's being unloaded, returning the node
+    /// ticks they had scheduled in it with how long until they're due. The parts of those networks
+    /// in other chunks get compiled again on the next tick, keeping their ticks.
+    pub fn unload_chunk(
+        &mut self,
+        dimension: &str,
+        chunk_x: i32,
+        chunk_z: i32,
+    ) -> Vec<(Pos, u32, i8)> {
+        let Some(networks) = self.dimensions.get_mut(dimension) else {
+            return Vec::new();
+        };
+        let in_chunk = |(x, _, z): Pos| x >> 4 == chunk_x && z >> 4 == chunk_z;
+        let ids: BTreeSet<u32> = networks
+            .owners
+            .iter()
+            .filter(|(pos, _)| in_chunk(**pos))
+            .map(|(_, id)| *id)
+            .collect();
+        let mut scheduled = std::mem::take(&mut networks.carried);
+        for id in ids {
+            let Some(network) = networks.networks.remove(&id) else {
+                continue;
+            };
+            for pos in network.positions() {
+                networks.owners.remove(&pos);
+                if !in_chunk(pos) {
+                    networks.stale.insert(pos);
+                }
+            }
+            scheduled.extend(network.scheduled());
+        }
+        let mut saved = Vec::new();
+        for (pos, due, priority) in scheduled {
+            if in_chunk(pos) {
+                saved.push((pos, due.saturating_sub(self.time) as u32, priority));
+            } else {
+                networks.carried.push((pos, due, priority));
+            }
+        }
+        networks.stale.retain(|pos| !in_chunk(*pos));
+        networks.containers.retain(|pos, _| !in_chunk(*pos));
+        networks.changed_containers.retain(|pos| !in_chunk(*pos));
+        networks.unread.retain(|pos| !in_chunk(*pos));
+        saved
+    }
+
+    /// Schedule node ticks saved with a chunk again, given how long until they're due. They're
+    /// carried over into the networks compiled around them on the next tick.
+    pub fn restore(&mut self, dimension: &str, ticks: impl IntoIterator<Item = (Pos, u32, i8)>) {
+        let networks = self.dimensions.entry(dimension.to_string()).or_default();
+        for (pos, delay, priority) in ticks {
+            networks.stale.insert(pos);
+            networks
+                .carried
+                .push((pos, self.time + delay as u64, priority));
+        }
+    }
+
+    /// The chunks with node ticks scheduled in them, by dimension.
+    pub fn chunks_with_ticks(&self) -> BTreeSet<(i32, i32, String)> {
+        let mut chunks = BTreeSet::new();
+        for (dimension, networks) in &self.dimensions {
+            let scheduled = networks
+                .networks
+                .values()
+                .flat_map(|network| network.scheduled())
+                .chain(networks.carried.iter().copied());
+            for ((x, _, z), ..) in scheduled {
+                chunks.insert((x >> 4, z >> 4, dimension.clone()));
+            }
+        }
+        chunks
+    }
+
+    /// Take the pistons that were powered or unpowered since this was last called, sorted by
+    /// dimension and position.
+    pub fn take_pistons(&mut self) -> Vec<(String, Pos, bool)> {
+        std::mem::take(&mut self.pistons)
+    }
+
+    /// Take the dispensers and droppers that got powered since this was last called, by
+    /// dimension and position.
+    pub fn take_dispensers(&mut self) -> Vec<(String, Pos)> {
+        std::mem::take(&mut self.dispensers)
+    }
+
+    /// Recompile the networks next to edited blocks and advance every network by a game tick.
+    /// `block` looks up a block, `None` if it isn't loaded. Returns the blocks that changed, sorted
+    /// by position; they still have to be applied to the world.
+    pub fn tick(
+        &mut self,
+        mut block: impl FnMut(&str, Pos) -> Option<BlockId>,
+    ) -> Vec<AppliedEdit> {
+        self.time += 1;
+        let mut applied = Vec::new();
+        for (dimension, networks) in &mut self.dimensions {
+            let mut changes = networks.rebuild(self.time, &mut |pos: Pos| block(dimension, pos));
+            networks.read_changed_containers(self.time, &mut changes);
+            for network in networks.networks.values_mut() {
+                network.tick(self.time, &mut changes);
+                for (pos, extend) in network.take_pistons() {
+                    self.pistons.push((dimension.clone(), pos, extend));
+                }
+                for pos in network.take_dispensers() {
+                    self.dispensers.push((dimension.clone(), pos));
+                }
+            }
+            let changes = merge_changes(changes);
+            if !changes.is_empty() {
+                applied.push(AppliedEdit {
+                    dimension: dimension.clone(),
+                    changes,
+                });
+            }
+        }
+        applied.sort_by(|a, b| a.dimension.cmp(&b.dimension));
+        self.pistons.sort();
+        self.dispensers.sort();
+        applied
+    }
+}
+
+impl DimensionNetworks {
+    /// Throw away the networks next to edited blocks and compile them again, carrying over the
+    /// ticks they had scheduled. Returns the changes settling the new networks made.
+    fn rebuild(
+        &mut self,
+        time: u64,
+        block: &mut impl FnMut(Pos) -> Option<BlockId>,
+    ) -> Vec<BlockChange> {
+        let mut changes = Vec::new();
+        if self.edited.is_empty() && self.stale.is_empty() {
+            return changes;
+        }
+        let edited = std::mem::take(&mut self.edited);
+        let mut seeds: Vec<Pos> = std::mem::take(&mut self.stale).into_iter().collect();
+        let mut carried = std::mem::take(&mut self.carried);
+        for &pos in &edited {
+            seeds.push(pos);
+            seeds.extend(Direction::ALL.map(|dir| dir.step(pos)));
+            seeds.extend(compiler::quasi_pistons(pos));
+        }
+        for pos in seeds.clone() {
+            let Some(id) = self.owners.get(&pos).copied() else {
+                continue;
+            };
+            if let Some(network) = self.networks.remove(&id) {
+                for node_pos in network.positions() {
+                    self.owners.remove(&node_pos);
+                    seeds.push(node_pos);
+                }
+                carried.extend(network.scheduled());
+            }
+        }
+
+        let mut compiled = Vec::new();
+        for seed in seeds {
+            if self.owners.contains_key(&seed) {
+                continue;
+            }
+            let Some(network) = compiler::compile(seed, &mut *block) else {
+                continue;
+            };
+            let id = self.next_id;
+            self.next_id = self.next_id.wrapping_add(1);
+            for pos in network.positions() {
+                self.owners.insert(pos, id);
+            }
+            self.networks.insert(id, network);
+            compiled.push(id);
+        }
+
+        // Ticks carried over go in before the new networks settle, so nodes that were already
+        // waiting to change keep their place rather than starting their delay over
+        for (pos, due, priority) in carried {
+            if let Some(network) = self
+                .owners
+                .get(&pos)
+                .and_then(|id| self.networks.get_mut(id))
+            {
+                network.reschedule(pos, due, priority);
+            }
+        }
+        for id in compiled {
+            if let Some(network) = self.networks.get_mut(&id) {
+                for pos in network.containers() {
+                    match self.containers.get(&pos) {
+                        Some(&signal) => {
+                            network.set_container_signal(pos, signal, time, &mut changes)
+                        }
+                        None => {
+                            self.unread.insert(pos);
+                        }
+                    }
+                }
+                network.start(time, &mut changes);
+            }
+        }
+        // Observers next to an edit see it, whatever the block was
+        for &pos in &edited {
+            for dir in Direction::ALL {
+                if let Some(network) = self
+                    .owners
+                    .get(&dir.step(pos))
+                    .and_then(|id| self.networks.get_mut(id))
+                {
+                    network.observe(pos, time);
+                }
+            }
+        }
+        changes
+    }
+
+    /// Give the comparators reading containers whose signal changed the new signal.
+    fn read_changed_containers(&mut self, time: u64, changes: &mut Vec<BlockChange>) {
+        for pos in std::mem::take(&mut self.changed_containers) {
+            let Some(&signal) = self.containers.get(&pos) else {
+                continue;
+            };
+            for network in self.networks.values_mut() {
+                network.set_container_signal(pos, signal, time, changes);
+            }
+        }
+    }
+}
+
+/// Combine the changes to each block into one, leaving out blocks that ended up unchanged, and
+/// sort them by position.
+fn merge_changes(changes: Vec<BlockChange>) -> Vec<BlockChange> {
+    let mut merged: BTreeMap<Pos, BlockChange> = BTreeMap::new();
+    for change in changes {
+        merged
+            .entry(change.position)
+            .and_modify(|existing| existing.new = change.new)
+            .or_insert(change);
+    }
+    merged
+        .into_values()
+        .filter(|change| change.old != change.new)
+        .collect()
+}
+
+impl World {
+    /// Advance redstone by a game tick and apply the blocks it changed.
+    pub(crate) fn tick_redstone(&self) -> Result<(), WorldError> {
+        let applied = self
+            .redstone
+            .lock()
+            .unwrap()
+            .tick(|dimension, (x, y, z)| self.get_block_and_fetch(x, y, z, dimension).ok());
+        for edit in &applied {
+            let mut chunks: BTreeMap<(i32, i32), Vec<(Pos, BlockId)>> = BTreeMap::new();
+            for change in &edit.changes {
+                let (x, _, z) = change.position;
+                chunks
+                    .entry((x >> 4, z >> 4))
+                    .or_default()
+                    .push((change.position, change.new));
+            }
+            for ((chunk_x, chunk_z), blocks) in chunks {
+                let mut chunk = self.load_chunk_owned(chunk_x, chunk_z, &edit.dimension)?;
+                chunk.mark_modified();
+                self.set_blocks_and_relight(chunk, &blocks)?;
+            }
+        }
+        let (dispensers, unread) = {
+            let mut redstone = self.redstone.lock().unwrap();
+            redstone.updates.extend(applied);
+            (
+                redstone.take_dispensers(),
+                redstone.take_unread_containers(),
+            )
+        };
+        for (dimension, (x, y, z)) in dispensers {
+            self.schedule_tick(x, y, z, &dimension, DISPENSE_DELAY);
+        }
+        for (dimension, pos) in unread {
+            let signal = self
+                .load_container_at(&dimension, pos)?
+                .map_or(0, |(_, container)| container.signal());
+            self.redstone
+                .lock()
+                .unwrap()
+                .container_changed(&dimension, pos, signal);
+        }
+        Ok(())
+    }
+
+    /// Take the blocks redstone changed since this was last called, for sending to players.
+    pub fn take_redstone_updates(&self) -> Vec<AppliedEdit> {
+        std::mem::take(&mut self.redstone.lock().unwrap().updates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_id::{Extended, Lit};
+    use crate::testing::{memory_world, tick};
+
+    fn block(name: &str) -> BlockId {
+        BlockId::from_name(&format!("minecraft:{name}")).unwrap()
+    }
+
+    fn repeater(facing: Direction, delay: u8) -> BlockId {
+        block("repeater")
+            .with(facing)
+            .and_then(|b| b.with(Delay(delay)))
+            .and_then(|b| b.with(Powered(false)))
+            .unwrap()
+    }
+
+    /// A world that's only a few blocks, applying what the networks change.
+    struct Blocks {
+        blocks: HashMap<Pos, BlockId>,
+        networks: RedstoneNetworks,
+    }
+
+    impl Blocks {
+        fn new(blocks: &[(Pos, BlockId)]) -> Self {
+            let mut networks = RedstoneNetworks::default();
+            for (pos, _) in blocks {
+                networks.block_changed("overworld", *pos);
+            }
+            Blocks {
+                blocks: blocks.iter().copied().collect(),
+                networks,
+            }
+        }
+
+        fn set(&mut self, pos: Pos, block: BlockId) {
+            self.blocks.insert(pos, block);
+            self.networks.block_changed("overworld", pos);
+        }
+
+        fn tick(&mut self, times: usize) {
+            for _ in 0..times {
+                let blocks = &self.blocks;
+                for edit in self.networks.tick(|_, pos| blocks.get(&pos).copied()) {
+                    for change in edit.changes {
+                        self.blocks.insert(change.position, change.new);
+                    }
+                }
+            }
+        }
+
+        fn get<P: crate::block_id::StateProperty>(&self, pos: Pos) -> P {
+            self.blocks[&pos].get::<P>().unwrap()
+        }
+
+        fn power(&self, pos: Pos) -> u8 {
+            self.networks.power_at("overworld", pos)
+        }
+    }
+
+    #[test]
+    fn test_torch_inverts_the_block_it_is_on() {
+        let lever = block("lever")
+            .with_property("face", "wall")
+            .ok()
+            .and_then(|b| b.with(Direction::West))
+            .and_then(|b| b.with(Powered(true)))
+            .unwrap();
+        let torch = block("redstone_wall_torch").with(Direction::East).unwrap();
+        let mut world = Blocks::new(&[
+            ((-1, 1, 0), lever),
+            ((0, 1, 0), block("stone")),
+            ((1, 1, 0), torch.with(Lit(true)).unwrap()),
+            ((2, 1, 0), block("redstone_wire")),
+            ((3, 1, 0), block("redstone_wire")),
+            ((4, 1, 0), block("redstone_wire")),
+            ((5, 1, 0), block("redstone_lamp").with(Lit(false)).unwrap()),
+        ]);
+        assert_eq!(world.networks.network_count("overworld"), 0);
+
+        // The torch is still lit for now, so the wire and lamp come on straight away
+        world.tick(1);
+        assert_eq!(world.networks.network_count("overworld"), 1);
+        assert_eq!(world.get::<Power>((2, 1, 0)), Power(15));
+        assert_eq!(world.power((4, 1, 0)), 13);
+        assert_eq!(world.get::<Lit>((5, 1, 0)), Lit(true));
+
+        // Torches take a redstone tick to turn off
+        world.tick(1);
+        assert_eq!(world.get::<Lit>((1, 1, 0)), Lit(true));
+        world.tick(1);
+        assert_eq!(world.get::<Lit>((1, 1, 0)), Lit(false));
+        assert_eq!(world.get::<Power>((4, 1, 0)), Power(0));
+
+        // Lamps take two to go out
+        world.tick(3);
+        assert_eq!(world.get::<Lit>((5, 1, 0)), Lit(true));
+        world.tick(1);
+        assert_eq!(world.get::<Lit>((5, 1, 0)), Lit(false));
+
+        // Flipping the lever recompiles the network, and the torch comes back on
+        world.set((-1, 1, 0), lever.with(Powered(false)).unwrap());
+        world.tick(3);
+        assert_eq!(world.get::<Lit>((1, 1, 0)), Lit(true));
+        assert_eq!(world.get::<Lit>((5, 1, 0)), Lit(true));
+    }
+
+    #[test]
+    fn test_repeaters_delay_and_lock() {
+        let mut world = Blocks::new(&[
+            ((0, 1, 0), block("redstone_block")),
+            ((1, 1, 0), repeater(Direction::West, 2)),
+            ((2, 1, 0), block("redstone_wire")),
+        ]);
+        world.tick(4);
+        assert_eq!(world.get::<Powered>((1, 1, 0)), Powered(false));
+        assert_eq!(world.power((2, 1, 0)), 0);
+        world.tick(1);
+        assert_eq!(world.get::<Powered>((1, 1, 0)), Powered(true));
+        assert_eq!(world.get::<Power>((2, 1, 0)), Power(15));
+
+        // A faster repeater pointing into its side locks it before it turns on
+        let mut world = Blocks::new(&[
+            ((0, 1, 0), block("redstone_block")),
+            ((1, 1, 0), repeater(Direction::West, 2)),
+            ((2, 1, 0), block("redstone_wire")),
+            ((1, 1, 1), repeater(Direction::South, 1)),
+            ((1, 1, 2), block("redstone_block")),
+        ]);
+        world.tick(10);
+        assert_eq!(world.get::<Powered>((1, 1, 1)), Powered(true));
+        assert_eq!(world.get::<Powered>((1, 1, 0)), Powered(false));
+        assert_eq!(world.power((2, 1, 0)), 0);
+    }
+
+    #[test]
+    fn test_ticks_carry_over_an_unloaded_chunk() {
+        let mut world = Blocks::new(&[
+            ((0, 1, 0), block("redstone_block")),
+            ((1, 1, 0), repeater(Direction::West, 4)),
+        ]);
+        world.tick(3);
+        let saved = world.networks.unload_chunk("overworld", 0, 0);
+        assert_eq!(saved, [((1, 1, 0), 6, crate::tick::priority::HIGH)]);
+        assert_eq!(world.networks.network_count("overworld"), 0);
+
+        // A restart starts counting from scratch, but the tick stays as far away as it was
+        world.networks = RedstoneNetworks::default();
+        world.networks.restore("overworld", saved);
+        world.tick(5);
+        assert_eq!(world.get::<Powered>((1, 1, 0)), Powered(false));
+        world.tick(1);
+        assert_eq!(world.get::<Powered>((1, 1, 0)), Powered(true));
+    }
+
+    #[test]
+    fn test_comparators_subtract_the_side() {
+        let comparator = block("comparator")
+            .with(Direction::West)
+            .and_then(|b| b.with(ComparatorMode::Subtract))
+            .and_then(|b| b.with(Powered(false)))
+            .unwrap();
+        let mut world = Blocks::new(&[
+            ((0, 1, 0), block("redstone_block")),
+            ((1, 1, 0), comparator),
+            ((1, 1, 1), block("redstone_wire")),
+            ((2, 1, 1), block("redstone_wire")),
+            ((3, 1, 1), block("redstone_wire")),
+            ((4, 1, 1), block("redstone_block")),
+        ]);
+        world.tick(3);
+        assert_eq!(world.power((1, 1, 1)), 13);
+        assert_eq!(world.power((1, 1, 0)), 2);
+        assert_eq!(world.get::<Powered>((1, 1, 0)), Powered(true));
+    }
+
+    #[test]
+    fn test_pistons_are_told_when_their_power_changes() {
+        let piston = block("piston")
+            .with(Direction::East)
+            .and_then(|b| b.with(Extended(false)))
+            .unwrap();
+        let lever = block("lever")
+            .with_property("face", "floor")
+            .ok()
+            .and_then(|b| b.with(Powered(true)))
+            .unwrap();
+        let mut world = Blocks::new(&[((0, 1, 0), piston), ((0, 1, 1), lever)]);
+        world.tick(1);
+        assert_eq!(
+            world.networks.take_pistons(),
+            vec![("overworld".to_string(), (0, 1, 0), true)]
+        );
+        world.tick(1);
+        assert!(world.networks.take_pistons().is_empty());
+
+        // Powering the block above the piston powers it too
+        let mut world = Blocks::new(&[((0, 1, 0), piston), ((0, 2, 1), lever)]);
+        world.tick(1);
+        assert_eq!(world.networks.take_pistons().len(), 1);
+        // The world extends it, which doesn't set it off again
+        world.set((0, 1, 0), piston.with(Extended(true)).unwrap());
+        world.tick(1);
+        assert!(world.networks.take_pistons().is_empty());
+        world.set((0, 2, 1), lever.with(Powered(false)).unwrap());
+        world.tick(1);
+        assert_eq!(
+            world.networks.take_pistons(),
+            vec![("overworld".to_string(), (0, 1, 0), false)]
+        );
+    }
+
+    #[test]
+    fn test_observers_pulse_when_the_block_they_watch_changes() {
+        let observer = block("observer")
+            .with(Direction::North)
+            .and_then(|b| b.with(Powered(false)))
+            .unwrap();
+        let mut world = Blocks::new(&[((0, 1, 0), observer), ((0, 1, 1), block("redstone_wire"))]);
+        world.tick(5);
+        assert_eq!(world.power((0, 1, 1)), 0);
+
+        world.set((0, 1, -1), block("stone"));
+        world.tick(2);
+        assert_eq!(world.power((0, 1, 1)), 0);
+        world.tick(1);
+        assert_eq!(world.get::<Powered>((0, 1, 0)), Powered(true));
+        assert_eq!(world.get::<Power>((0, 1, 1)), Power(15));
+        world.tick(2);
+        assert_eq!(world.get::<Powered>((0, 1, 0)), Powered(false));
+        assert_eq!(world.power((0, 1, 1)), 0);
+    }
+
+    #[test]
+    fn test_networks_power_across_chunks() {
+        let (world, _) = memory_world([(-1, 0), (0, 0)]);
+        let lamp_lit = || {
+            world
+                .get_block_and_fetch(3, 64, 0, "overworld")
+                .unwrap()
+                .get::<Lit>()
+                .unwrap()
+                .0
+        };
+
+        world
+            .set_block_and_fetch(-3, 64, 0, "overworld", block("redstone_block"))
+            .unwrap();
+        for x in -2..3 {
+            world
+                .set_block_and_fetch(x, 64, 0, "overworld", block("redstone_wire"))
+                .unwrap();
+        }
+        let lamp = block("redstone_lamp").with(Lit(false)).unwrap();
+        world
+            .set_block_and_fetch(3, 64, 0, "overworld", lamp)
+            .unwrap();
+        tick(&world, 1);
+        // Over the chunk border and weakening with every block
+        assert_eq!(world.get_power_level(-2, 64, 0, "overworld"), 15);
+        assert_eq!(
+            world
+                .get_block_and_fetch(2, 64, 0, "overworld")
+                .unwrap()
+                .get::<Power>(),
+            Some(Power(11))
+        );
+        assert!(lamp_lit());
+        let updates = world.take_redstone_updates();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].changes.len(), 6);
+        assert!(world.take_redstone_updates().is_empty());
+
+        world
+            .set_block_and_fetch(-3, 64, 0, "overworld", BlockId::default())
+            .unwrap();
+        tick(&world, 1);
+        assert_eq!(world.get_power_level(2, 64, 0, "overworld"), 0);
+        tick(&world, 3);
+        assert!(lamp_lit());
+        tick(&world, 1);
+        assert!(!lamp_lit());
+    }
+}
@@ -0,0 +1,491 @@
+//! Evaluating compiled networks.
+
+use super::Pos;
+use crate::block_id::{BlockId, ComparatorMode, Enabled, Lit, Power, Powered, Triggered};
+use crate::region_edit::BlockChange;
+use crate::tick::priority;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+
+pub(crate) type NodeId = usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum NodeKind {
+    /// Wire only shows the power reaching it, it's skipped over when signals travel.
+    Wire,
+    Torch,
+    /// `facing_diode` is whether it powers another repeater or comparator, which ticks it first.
+    Repeater {
+        delay: u8,
+        facing_diode: bool,
+    },
+    Comparator {
+        mode: ComparatorMode,
+        facing_diode: bool,
+    },
+    Observer,
+    Lamp,
+    Source,
+    /// Pistons don't change their own state, they're handed to the world to move. See
+    /// [`Network::take_pistons`].
+    Piston,
+    /// Hoppers are disabled while powered.
+    Hopper,
+    /// Dispensers and droppers fire when they get powered. See [`Network::take_dispensers`].
+    Dispenser,
+}
+
+/// A signal reaching a node, weakened by the wire it travelled through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Edge {
+    pub(crate) from: NodeId,
+    pub(crate) distance: u8,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Node {
+    pub(crate) pos: Pos,
+    pub(crate) kind: NodeKind,
+    pub(crate) block: BlockId,
+    /// Where the signal comes from: behind repeaters and comparators, anywhere for the rest.
+    pub(crate) inputs: Vec<Edge>,
+    /// Repeaters and comparators on the sides, which lock repeaters and weaken comparators.
+    pub(crate) side_inputs: Vec<Edge>,
+    /// Nodes this one is an input of.
+    pub(crate) outputs: Vec<NodeId>,
+    /// Observers watching this node.
+    pub(crate) watchers: Vec<NodeId>,
+    /// The strength of the signal this node gives out, or the power of a wire.
+    pub(crate) output: u8,
+    /// Whether a torch or lamp is lit, a repeater, comparator or observer is powered, a piston
+    /// has been told to extend, or a hopper, dispenser or dropper is powered.
+    pub(crate) on: bool,
+    pub(crate) scheduled: bool,
+}
+
+impl Node {
+    pub(crate) fn new(pos: Pos, kind: NodeKind, block: BlockId, on: bool, output: u8) -> Self {
+        Node {
+            pos,
+            kind,
+            block,
+            inputs: Vec::new(),
+            side_inputs: Vec::new(),
+            outputs: Vec::new(),
+            watchers: Vec::new(),
+            output,
+            on,
+            scheduled: false,
+        }
+    }
+
+    /// The block state showing the node's current state.
+    fn state(&self) -> BlockId {
+        let state = match self.kind {
+            NodeKind::Wire => self.block.with(Power(self.output)),
+            NodeKind::Torch | NodeKind::Lamp => self.block.with(Lit(self.on)),
+            NodeKind::Repeater { .. } | NodeKind::Comparator { .. } | NodeKind::Observer => {
+                self.block.with(Powered(self.on))
+            }
+            NodeKind::Hopper => self.block.with(Enabled(!self.on)),
+            NodeKind::Dispenser => self.block.with(Triggered(self.on)),
+            NodeKind::Source | NodeKind::Piston => None,
+        };
+        state.unwrap_or(self.block)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct ScheduledNode {
+    due: u64,
+    priority: i8,
+    /// Ticks with the same time and priority run in the order they were scheduled.
+    order: u64,
+    node: NodeId,
+}
+
+/// Connected redstone compiled into a graph. See the [module docs](super).
+#[derive(Clone, Debug, Default)]
+pub struct Network {
+    nodes: Vec<Node>,
+    by_pos: HashMap<Pos, NodeId>,
+    /// Observers by the position they watch, including blocks that aren't part of the network.
+    watched: HashMap<Pos, Vec<NodeId>>,
+    queue: BinaryHeap<Reverse<ScheduledNode>>,
+    order: u64,
+    /// Pistons whose power changed, and whether they should extend.
+    pistons: Vec<(Pos, bool)>,
+    /// Dispensers and droppers that got powered.
+    dispensers: Vec<Pos>,
+    /// The container behind each comparator reading one.
+    reading: HashMap<NodeId, Pos>,
+    /// The signal the containers comparators read give out, by position.
+    containers: HashMap<Pos, u8>,
+}
+
+impl Network {
+    pub(crate) fn new(nodes: Vec<Node>, watched: HashMap<Pos, Vec<NodeId>>) -> Self {
+        let by_pos = nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| (node.pos, id))
+            .collect();
+        Network {
+            nodes,
+            by_pos,
+            watched,
+            ..Default::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The positions of every node.
+    pub fn positions(&self) -> impl Iterator<Item = Pos> + '_ {
+        self.nodes.iter().map(|node| node.pos)
+    }
+
+    /// The power of the wire at `pos`, or the signal the component there gives out.
+    pub fn power_at(&self, pos: Pos) -> Option<u8> {
+        self.by_pos.get(&pos).map(|&id| self.nodes[id].output)
+    }
+
+    /// Work out what comparators give out, since their state only says whether it's anything.
+    /// Ones that don't match their state get fixed by [`Network::start`].
+    pub(crate) fn init_comparators(&mut self) {
+        for id in 0..self.nodes.len() {
+            let NodeKind::Comparator { mode, .. } = self.nodes[id].kind else {
+                continue;
+            };
+            let output = self.comparator_output(id, mode);
+            let node = &mut self.nodes[id];
+            node.output = if (output > 0) == node.on {
+                output
+            } else if node.on {
+                15
+            } else {
+                0
+            };
+        }
+    }
+
+    /// Bring every node in line with its inputs, after compiling.
+    pub fn start(&mut self, time: u64, changes: &mut Vec<BlockChange>) {
+        for id in 0..self.nodes.len() {
+            self.update(id, time, changes);
+        }
+    }
+
+    /// Run the node ticks due by `time`, adding the blocks that changed to `changes`.
+    pub fn tick(&mut self, time: u64, changes: &mut Vec<BlockChange>) {
+        while let Some(Reverse(next)) = self.queue.peek().copied() {
+            if next.due > time {
+                break;
+            }
+            self.queue.pop();
+            self.nodes[next.node].scheduled = false;
+            self.tick_node(next.node, time, changes);
+        }
+    }
+
+    /// The ticks still to come, by position, for carrying over into a recompiled network.
+    pub(crate) fn scheduled(&self) -> impl Iterator<Item = (Pos, u64, i8)> + '_ {
+        self.queue
+            .iter()
+            .map(|Reverse(tick)| (self.nodes[tick.node].pos, tick.due, tick.priority))
+    }
+
+    /// Schedule a tick carried over from an older network.
+    pub(crate) fn reschedule(&mut self, pos: Pos, due: u64, priority: i8) {
+        if let Some(&id) = self.by_pos.get(&pos) {
+            if !self.nodes[id].scheduled {
+                self.push(id, due, priority);
+            }
+        }
+    }
+
+    /// Take the pistons that were powered or unpowered since this was last called.
+    pub(crate) fn take_pistons(&mut self) -> Vec<(Pos, bool)> {
+        std::mem::take(&mut self.pistons)
+    }
+
+    /// Take the dispensers and droppers that got powered since this was last called.
+    pub(crate) fn take_dispensers(&mut self) -> Vec<Pos> {
+        std::mem::take(&mut self.dispensers)
+    }
+
+    /// Note which container every comparator reading one has behind it. Their signal counts as
+    /// nothing until it's given with [`Network::set_container_signal`].
+    pub(crate) fn read_containers(&mut self, reading: HashMap<NodeId, Pos>) {
+        self.reading = reading;
+    }
+
+    /// The containers comparators in this network read.
+    pub(crate) fn containers(&self) -> BTreeSet<Pos> {
+        self.reading.values().copied().collect()
+    }
+
+    /// Change the signal a container gives the comparators reading it.
+    pub(crate) fn set_container_signal(
+        &mut self,
+        pos: Pos,
+        signal: u8,
+        time: u64,
+        changes: &mut Vec<BlockChange>,
+    ) {
+        let readers = self
+            .reading
+            .iter()
+            .filter(|(_, container)| **container == pos)
+            .map(|(id, _)| *id)
+            .collect::<BTreeSet<_>>();
+        if readers.is_empty() || self.containers.insert(pos, signal) == Some(signal) {
+            return;
+        }
+        for id in readers {
+            self.update(id, time, changes);
+        }
+    }
+
+    /// The block at `pos` changed, so any observer watching it fires.
+    pub(crate) fn observe(&mut self, pos: Pos, time: u64) {
+        let observers = self.watched.get(&pos).cloned().unwrap_or_default();
+        for observer in observers {
+            self.trigger_observer(observer, time);
+        }
+    }
+
+    fn push(&mut self, id: NodeId, due: u64, priority: i8) {
+        self.nodes[id].scheduled = true;
+        self.order += 1;
+        self.queue.push(Reverse(ScheduledNode {
+            due,
+            priority,
+            order: self.order,
+            node: id,
+        }));
+    }
+
+    fn schedule(&mut self, id: NodeId, time: u64, delay: u64, priority: i8) {
+        self.push(id, time + delay, priority);
+    }
+
+    fn strongest(&self, edges: &[Edge]) -> u8 {
+        edges
+            .iter()
+            .map(|edge| self.nodes[edge.from].output.saturating_sub(edge.distance))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn input(&self, id: NodeId) -> u8 {
+        self.strongest(&self.nodes[id].inputs)
+    }
+
+    fn side_input(&self, id: NodeId) -> u8 {
+        self.strongest(&self.nodes[id].side_inputs)
+    }
+
+    fn comparator_output(&self, id: NodeId, mode: ComparatorMode) -> u8 {
+        let container = self
+            .reading
+            .get(&id)
+            .and_then(|pos| self.containers.get(pos))
+            .copied()
+            .unwrap_or(0);
+        let back = self.input(id).max(container);
+        let side = self.side_input(id);
+        match mode {
+            ComparatorMode::Compare if back >= side => back,
+            ComparatorMode::Compare => 0,
+            ComparatorMode::Subtract => back.saturating_sub(side),
+        }
+    }
+
+    /// React to an input changing, the way the block would to a neighbour update.
+    fn update(&mut self, id: NodeId, time: u64, changes: &mut Vec<BlockChange>) {
+        let node = &self.nodes[id];
+        match node.kind {
+            NodeKind::Wire => {
+                let power = self.input(id);
+                if power != node.output {
+                    self.nodes[id].output = power;
+                    self.changed(id, time, changes);
+                }
+            }
+            NodeKind::Torch => {
+                let lit = self.input(id) == 0;
+                if lit != node.on && !node.scheduled {
+                    self.schedule(id, time, 2, priority::NORMAL);
+                }
+            }
+            NodeKind::Repeater {
+                delay,
+                facing_diode,
+            } => {
+                if node.scheduled || self.side_input(id) > 0 {
+                    return;
+                }
+                let powered = self.input(id) > 0;
+                if powered != node.on {
+                    let priority = if facing_diode {
+                        priority::EXTREMELY_HIGH
+                    } else if node.on {
+                        priority::VERY_HIGH
+                    } else {
+                        priority::HIGH
+                    };
+                    self.schedule(id, time, delay as u64 * 2, priority);
+                }
+            }
+            NodeKind::Comparator { mode, facing_diode } => {
+                if !node.scheduled && self.comparator_output(id, mode) != node.output {
+                    let priority = if facing_diode {
+                        priority::HIGH
+                    } else {
+                        priority::NORMAL
+                    };
+                    self.schedule(id, time, 2, priority);
+                }
+            }
+            NodeKind::Lamp => {
+                let powered = self.input(id) > 0;
+                if powered && !node.on {
+                    self.nodes[id].on = true;
+                    self.changed(id, time, changes);
+                } else if !powered && node.on && !node.scheduled {
+                    self.schedule(id, time, 4, priority::NORMAL);
+                }
+            }
+            NodeKind::Piston => {
+                let powered = self.input(id) > 0;
+                if powered != node.on {
+                    let pos = node.pos;
+                    self.nodes[id].on = powered;
+                    self.pistons.push((pos, powered));
+                }
+            }
+            NodeKind::Hopper => {
+                let powered = self.input(id) > 0;
+                if powered != node.on {
+                    self.nodes[id].on = powered;
+                    self.changed(id, time, changes);
+                }
+            }
+            NodeKind::Dispenser => {
+                let powered = self.input(id) > 0;
+                if powered != node.on {
+                    let pos = node.pos;
+                    self.nodes[id].on = powered;
+                    self.changed(id, time, changes);
+                    if powered {
+                        self.dispensers.push(pos);
+                    }
+                }
+            }
+            NodeKind::Observer | NodeKind::Source => {}
+        }
+    }
+
+    fn tick_node(&mut self, id: NodeId, time: u64, changes: &mut Vec<BlockChange>) {
+        let node = &self.nodes[id];
+        match node.kind {
+            NodeKind::Torch => {
+                let lit = self.input(id) == 0;
+                if lit != node.on {
+                    self.set_output(id, lit, if lit { 15 } else { 0 }, time, changes);
+                }
+            }
+            NodeKind::Repeater { delay, .. } => {
+                if self.side_input(id) > 0 {
+                    return;
+                }
+                let powered = self.input(id) > 0;
+                if node.on && !powered {
+                    self.set_output(id, false, 0, time, changes);
+                } else if !node.on {
+                    self.set_output(id, true, 15, time, changes);
+                    // Even the shortest pulse comes out as long as the delay
+                    if !powered {
+                        self.schedule(id, time, delay as u64 * 2, priority::VERY_HIGH);
+                    }
+                }
+            }
+            NodeKind::Comparator { mode, .. } => {
+                let output = self.comparator_output(id, mode);
+                if output != node.output {
+                    self.set_output(id, output > 0, output, time, changes);
+                }
+            }
+            NodeKind::Observer => {
+                if node.on {
+                    self.set_output(id, false, 0, time, changes);
+                } else {
+                    self.set_output(id, true, 15, time, changes);
+                    self.schedule(id, time, 2, priority::NORMAL);
+                }
+            }
+            NodeKind::Lamp => {
+                if node.on && self.input(id) == 0 {
+                    self.nodes[id].on = false;
+                    self.changed(id, time, changes);
+                }
+            }
+            NodeKind::Wire
+            | NodeKind::Source
+            | NodeKind::Piston
+            | NodeKind::Hopper
+            | NodeKind::Dispenser => {}
+        }
+    }
+
+    /// Change what a node gives out and update everything it powers.
+    fn set_output(
+        &mut self,
+        id: NodeId,
+        on: bool,
+        output: u8,
+        time: u64,
+        changes: &mut Vec<BlockChange>,
+    ) {
+        let node = &mut self.nodes[id];
+        let was_on = node.on;
+        node.on = on;
+        node.output = output;
+        if was_on != on {
+            self.changed(id, time, changes);
+        }
+        for i in 0..self.nodes[id].outputs.len() {
+            let output = self.nodes[id].outputs[i];
+            self.update(output, time, changes);
+        }
+    }
+
+    /// Record a node's new block state, which its observers see.
+    fn changed(&mut self, id: NodeId, time: u64, changes: &mut Vec<BlockChange>) {
+        let node = &mut self.nodes[id];
+        let new = node.state();
+        changes.push(BlockChange {
+            position: node.pos,
+            old: node.block,
+            new,
+        });
+        node.block = new;
+        for i in 0..self.nodes[id].watchers.len() {
+            let observer = self.nodes[id].watchers[i];
+            self.trigger_observer(observer, time);
+        }
+    }
+
+    fn trigger_observer(&mut self, id: NodeId, time: u64) {
+        if !self.nodes[id].scheduled {
+            self.schedule(id, time, 2, priority::NORMAL);
+        }
+    }
+}
@@ -0,0 +1,689 @@
+//! Editing regions that span any number of chunks, e.g. for `/fill` and `/clone`.
+//!
+//! A [`RegionEdit`] is split up by chunk and each chunk gets its own [`EditBatch`] on the thread
+//! pool. Nothing is saved until every chunk is done, and then they're all written in a single
+//! transaction, so a failed edit leaves the world as it was. A chunk that was saved by someone else
+//! while it was being edited has the edit redone on top of the newer version rather than being
+//! written over.
+//! Every edit made for a player is journaled with the blocks it replaced, so it can be undone and
+//! redone later.
+
+use crate::block_id::BlockId;
+use crate::chunk_format::{BlockEntity, Chunk};
+use crate::edit_batch::EditBatch;
+use crate::errors::WorldError;
+use crate::World;
+use ferrumc_threadpool::ThreadPool;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// How many edits each player can undo.
+const HISTORY_LENGTH: usize = 32;
+
+/// How many times an edit is redone on a chunk that keeps being saved by someone else before the
+/// edit gives up.
+pub(crate) const MAX_REDOS: usize = 8;
+
+/// The blocks an edit covers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Region {
+    /// Every block between two corners, both included.
+    Cuboid {
+        min: (i32, i32, i32),
+        max: (i32, i32, i32),
+    },
+    /// Any other shape, one position per block.
+    Blocks(Vec<(i32, i32, i32)>),
+}
+
+impl Region {
+    /// The cuboid between two corners, in any order.
+    pub fn cuboid(from: (i32, i32, i32), to: (i32, i32, i32)) -> Self {
+        Region::Cuboid {
+            min: (from.0.min(to.0), from.1.min(to.1), from.2.min(to.2)),
+            max: (from.0.max(to.0), from.1.max(to.1), from.2.max(to.2)),
+        }
+    }
+
+    /// How many blocks the region covers, or None if that's more than fits in a u64.
+    pub fn volume(&self) -> Option<u64> {
+        match self {
+            Region::Cuboid { min, max } => {
+                let size = |min: i32, max: i32| (max as i64 - min as i64 + 1) as u64;
+                size(min.0, max.0)
+                    .checked_mul(size(min.1, max.1))?
+                    .checked_mul(size(min.2, max.2))
+            }
+            Region::Blocks(positions) => Some(positions.len() as u64),
+        }
+    }
+
+    pub fn positions(&self) -> Box<dyn Iterator<Item = (i32, i32, i32)> + '_> {
+        match *self {
+            Region::Cuboid { min, max } => Box::new((min.1..=max.1).flat_map(move |y| {
+                (min.2..=max.2).flat_map(move |z| (min.0..=max.0).map(move |x| (x, y, z)))
+            })),
+            Region::Blocks(ref positions) => Box::new(positions.iter().copied()),
+        }
+    }
+}
+
+/// Block changes to make all at once, grouped by chunk.
+///
+/// Setting the same block twice keeps the last one.
+#[derive(Clone, Debug, Default)]
+pub struct RegionEdit {
+    chunks: BTreeMap<(i32, i32), Vec<(i32, i32, i32, BlockId)>>,
+    len: usize,
+}
+
+impl RegionEdit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set every block in a region to the same block.
+    pub fn fill(region: &Region, block: impl Into<BlockId>) -> Self {
+        let block = block.into();
+        let mut edit = Self::new();
+        for (x, y, z) in region.positions() {
+            edit.set_block(x, y, z, block);
+        }
+        edit
+    }
+
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: impl Into<BlockId>) {
+        self.chunks
+            .entry((x >> 4, z >> 4))
+            .or_default()
+            .push((x, y, z, block.into()));
+        self.len += 1;
+    }
+
+    /// How many blocks have been set, counting any that were set more than once.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The chunks the edit touches.
+    pub fn chunks(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.chunks.keys().copied()
+    }
+}
+
+/// A block that an edit actually changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockChange {
+    pub position: (i32, i32, i32),
+    pub old: BlockId,
+    pub new: BlockId,
+}
+
+/// What a region edit, undo or redo did, for sending the changes to players.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AppliedEdit {
+    pub dimension: String,
+    /// Sorted by chunk, and by y, z and x within each chunk. Blocks that already were what they
+    /// were set to are left out.
+    pub changes: Vec<BlockChange>,
+}
+
+/// An edit as it's kept for undoing, with the block entities of the blocks it replaced.
+struct JournalEntry {
+    dimension: String,
+    changes: Vec<BlockChange>,
+    block_entities: Vec<((i32, i32), BlockEntity)>,
+}
+
+#[derive(Default)]
+struct ActorHistory {
+    undo: VecDeque<JournalEntry>,
+    redo: Vec<JournalEntry>,
+}
+
+/// The undo and redo journals of everyone who has made region edits.
+#[derive(Default)]
+pub(crate) struct EditHistory {
+    actors: HashMap<Uuid, ActorHistory>,
+}
+
+impl EditHistory {
+    fn record(&mut self, actor: Uuid, entry: JournalEntry) {
+        let history = self.actors.entry(actor).or_default();
+        history.redo.clear();
+        history.undo.push_back(entry);
+        if history.undo.len() > HISTORY_LENGTH {
+            history.undo.pop_front();
+        }
+    }
+}
+
+/// An edit of one chunk, along with what it was made on so it can be made again.
+struct ChunkEdit {
+    position: (i32, i32),
+    /// The version of the chunk the edit was made on.
+    base: Arc<Chunk>,
+    blocks: Vec<(i32, i32, i32, BlockId)>,
+    restore: Vec<BlockEntity>,
+    result: ChunkResult,
+}
+
+/// What applying an edit did to one chunk.
+struct ChunkResult {
+    /// `None` when nothing in it changed.
+    chunk: Option<Chunk>,
+    changes: Vec<BlockChange>,
+    removed: Vec<BlockEntity>,
+}
+
+impl World {
+    /// Apply a region edit, journaling it for `actor` if there is one.
+    ///
+    /// Every chunk gets an [`EditBatch`] of its own on the thread pool. All the chunks have to
+    /// exist already and nothing is changed if one doesn't. Blocks above or below the dimension
+    /// are left out. Block entities of replaced blocks are removed and redstone next to changed
+    /// blocks is recompiled, scheduled ticks aren't updated.
+    ///
+    /// Region edits run one at a time, so overlapping ones can't lose each other's changes, and
+    /// blocks changed some other way while an edit runs are kept, the edit is redone on top of
+    /// them. This blocks until the whole edit is saved, so call it from a background thread when the tick
+    /// shouldn't wait for it, and never from the thread pool itself.
+    pub fn edit_region(
+        &self,
+        edit: RegionEdit,
+        dimension: &str,
+        actor: Option<Uuid>,
+        thread_pool: &ThreadPool,
+    ) -> Result<AppliedEdit, WorldError> {
+        let mut history = self.region_edits.lock().unwrap();
+        let (changes, block_entities) =
+            self.apply_region_edit(edit, HashMap::new(), dimension, thread_pool)?;
+        if let Some(actor) = actor {
+            if !changes.is_empty() {
+                history.record(
+                    actor,
+                    JournalEntry {
+                        dimension: dimension.to_string(),
+                        changes: changes.clone(),
+                        block_entities,
+                    },
+                );
+            }
+        }
+        Ok(AppliedEdit {
+            dimension: dimension.to_string(),
+            changes,
+        })
+    }
+
+    /// Put back the blocks and block entities the last edit `actor` made replaced. Returns `None`
+    /// if there's nothing to undo.
+    pub fn undo_region_edit(
+        &self,
+        actor: Uuid,
+        thread_pool: &ThreadPool,
+    ) -> Result<Option<AppliedEdit>, WorldError> {
+        let mut history = self.region_edits.lock().unwrap();
+        let Some(history) = history.actors.get_mut(&actor) else {
+            return Ok(None);
+        };
+        let Some(entry) = history.undo.pop_back() else {
+            return Ok(None);
+        };
+        match self.revert(&entry, thread_pool) {
+            Ok((applied, redo)) => {
+                history.redo.push(redo);
+                Ok(Some(applied))
+            }
+            Err(e) => {
+                // Nothing was saved, so it can still be undone later
+                history.undo.push_back(entry);
+                Err(e)
+            }
+        }
+    }
+
+    /// Make the last edit `actor` undid again, block entities included. Returns `None` if there's
+    /// nothing to redo.
+    pub fn redo_region_edit(
+        &self,
+        actor: Uuid,
+        thread_pool: &ThreadPool,
+    ) -> Result<Option<AppliedEdit>, WorldError> {
+        let mut history = self.region_edits.lock().unwrap();
+        let Some(history) = history.actors.get_mut(&actor) else {
+            return Ok(None);
+        };
+        let Some(entry) = history.redo.pop() else {
+            return Ok(None);
+        };
+        match self.revert(&entry, thread_pool) {
+            Ok((applied, undo)) => {
+                history.undo.push_back(undo);
+                Ok(Some(applied))
+            }
+            Err(e) => {
+                history.redo.push(entry);
+                Err(e)
+            }
+        }
+    }
+
+    /// Build an edit that copies the blocks of a region, moved by `offset`. The blocks are read
+    /// straight away, so the source and destination can overlap. Block entities aren't copied.
+    pub fn copy_region(
+        &self,
+        source: &Region,
+        offset: (i32, i32, i32),
+        dimension: &str,
+    ) -> Result<RegionEdit, WorldError> {
+        let mut chunks: BTreeMap<(i32, i32), Vec<(i32, i32, i32)>> = BTreeMap::new();
+        for (x, y, z) in source.positions() {
+            chunks.entry((x >> 4, z >> 4)).or_default().push((x, y, z));
+        }
+        let (dx, dy, dz) = offset;
+        let mut edit = RegionEdit::new();
+        for ((chunk_x, chunk_z), positions) in chunks {
+            let chunk = self.load_chunk(chunk_x, chunk_z, dimension)?;
+            for (x, y, z) in positions {
+                // Anything outside the chunk's sections is air
+                let block = chunk.get_block(x, y, z).unwrap_or_default();
+                edit.set_block(x + dx, y + dy, z + dz, block);
+            }
+        }
+        Ok(edit)
+    }
+
+    /// Put back the blocks and block entities a journaled edit replaced, returning what changed
+    /// and the entry that reverses this in turn. Undoing and redoing are both done this way.
+    fn revert(
+        &self,
+        entry: &JournalEntry,
+        thread_pool: &ThreadPool,
+    ) -> Result<(AppliedEdit, JournalEntry), WorldError> {
+        let mut edit = RegionEdit::new();
+        for change in &entry.changes {
+            let (x, y, z) = change.position;
+            edit.set_block(x, y, z, change.old);
+        }
+        let mut restore: HashMap<(i32, i32), Vec<BlockEntity>> = HashMap::new();
+        for (chunk, block_entity) in &entry.block_entities {
+            restore
+                .entry(*chunk)
+                .or_default()
+                .push(block_entity.clone());
+        }
+        let (changes, block_entities) =
+            self.apply_region_edit(edit, restore, &entry.dimension, thread_pool)?;
+        let reverse = JournalEntry {
+            dimension: entry.dimension.clone(),
+            changes: changes.clone(),
+            block_entities,
+        };
+        let applied = AppliedEdit {
+            dimension: entry.dimension.clone(),
+            changes,
+        };
+        Ok((applied, reverse))
+    }
+
+    /// Apply an edit and put back `restore`d block entities, returning the blocks that changed
+    /// and the block entities that were removed along with them.
+    fn apply_region_edit(
+        &self,
+        edit: RegionEdit,
+        mut restore: HashMap<(i32, i32), Vec<BlockEntity>>,
+        dimension: &str,
+        thread_pool: &ThreadPool,
+    ) -> Result<(Vec<BlockChange>, Vec<((i32, i32), BlockEntity)>), WorldError> {
+        let dimension_type = self
+            .dimensions
+            .get(dimension)
+            .ok_or_else(|| WorldError::UnknownDimension(dimension.to_string()))?
+            .dimension_type;
+        let heights = dimension_type.min_y..dimension_type.min_y + dimension_type.height as i32;
+
+        let mut chunks = edit.chunks;
+        for chunk in restore.keys() {
+            chunks.entry(*chunk).or_default();
+        }
+        for &(chunk_x, chunk_z) in chunks.keys() {
+            if !self.chunk_exists(chunk_x, chunk_z, dimension)? {
+                return Err(WorldError::ChunkNotFound);
+            }
+        }
+
+        let mut batch = thread_pool.batch();
+        for ((chunk_x, chunk_z), mut blocks) in chunks {
+            blocks.retain(|(_, y, _, _)| heights.contains(y));
+            let restore = restore.remove(&(chunk_x, chunk_z)).unwrap_or_default();
+            let world = self.clone();
+            let dimension = dimension.to_string();
+            batch.execute(move || -> Result<ChunkEdit, WorldError> {
+                let base = world.load_chunk(chunk_x, chunk_z, &dimension)?;
+                let result = edit_chunk(base.as_ref().clone(), blocks.clone(), restore.clone())?;
+                Ok(ChunkEdit {
+                    position: (chunk_x, chunk_z),
+                    base,
+                    blocks,
+                    restore,
+                    result,
+                })
+            });
+        }
+
+        let mut edits = batch.wait().into_iter().collect::<Result<Vec<_>, _>>()?;
+        edits.sort_by_key(|edit| edit.position);
+        let mut changes = Vec::new();
+        let mut removed = Vec::new();
+        let mut edited = Vec::new();
+        // Checking for newer versions and writing the chunks both happen under the flush lock, so
+        // no flush or direct save can write a chunk in between them
+        let flushing = self.dirty.flush_lock.lock().unwrap();
+        for mut edit in edits {
+            let (chunk_x, chunk_z) = edit.position;
+            // Players and ticks change chunks without waiting for region edits, so if the chunk
+            // was saved since it was loaded the edit is made again on the newer version. Without
+            // a cache every load is a new copy, so copies are compared by what's in them.
+            let mut redos = 0;
+            loop {
+                let current = self.load_chunk(chunk_x, chunk_z, dimension)?;
+                if Arc::ptr_eq(&current, &edit.base) || current == edit.base {
+                    break;
+                }
+                if redos == MAX_REDOS {
+                    return Err(WorldError::ChunkContended(chunk_x, chunk_z));
+                }
+                redos += 1;
+                edit.result = edit_chunk(
+                    current.as_ref().clone(),
+                    edit.blocks.clone(),
+                    edit.restore.clone(),
+                )?;
+                edit.base = current;
+            }
+            edited.extend(edit.result.chunk);
+            changes.extend(edit.result.changes);
+            removed.extend(edit.result.removed.into_iter().map(|b| (edit.position, b)));
+        }
+        if !edited.is_empty() {
+            let lit = self.relight_with_neighbours(edited);
+            self.write_chunks_locked(&flushing, lit)?;
+        }
+        drop(flushing);
+        {
+            let mut redstone = self.redstone.lock().unwrap();
+            for change in &changes {
+                redstone.block_changed(dimension, change.position);
+            }
+        }
+        Ok((changes, removed))
+    }
+}
+
+fn edit_chunk(
+    mut chunk: Chunk,
+    blocks: Vec<(i32, i32, i32, BlockId)>,
+    restore: Vec<BlockEntity>,
+) -> Result<ChunkResult, WorldError> {
+    let mut latest = HashMap::with_capacity(blocks.len());
+    for (x, y, z, block) in blocks {
+        latest.insert((x, y, z), block);
+    }
+    let mut changes = Vec::new();
+    for ((x, y, z), new) in latest {
+        let old = chunk.get_block(x, y, z).unwrap_or_default();
+        if old != new {
+            changes.push(BlockChange {
+                position: (x, y, z),
+                old,
+                new,
+            });
+        }
+    }
+    changes.sort_by_key(|change| {
+        let (x, y, z) = change.position;
+        (y, z, x)
+    });
+
+    let replaced = changes
+        .iter()
+        .map(|change| {
+            let (x, y, z) = change.position;
+            (x & 0xf, y, z & 0xf)
+        })
+        .collect::<HashSet<_>>();
+    let (removed, kept) = std::mem::take(&mut chunk.block_entities)
+        .into_iter()
+        .partition(|block_entity: &BlockEntity| {
+            replaced.contains(&(
+                block_entity.x() as i32,
+                block_entity.y as i16 as i32,
+                block_entity.z() as i32,
+            ))
+        });
+    chunk.block_entities = kept;
+
+    if !changes.is_empty() {
+        let mut batch = EditBatch::new(&mut chunk);
+        for change in &changes {
+            let (x, y, z) = change.position;
+            batch.set_block(x & 0xf, y, z & 0xf, change.new);
+        }
+        batch.apply()?;
+    }
+    let modified = !changes.is_empty() || !restore.is_empty();
+    for block_entity in restore {
+        chunk
+            .block_entities
+            .retain(|existing| (existing.xz, existing.y) != (block_entity.xz, block_entity.y));
+        chunk.block_entities.push(block_entity);
+    }
+    let chunk = modified.then(|| {
+        chunk.mark_modified();
+        chunk
+    });
+    Ok(ChunkResult {
+        chunk,
+        changes,
+        removed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{memory_world, memory_world_with};
+    use crate::WorldOptions;
+    use ferrumc_net_codec::net_types::var_int::VarInt;
+
+    #[test]
+    fn test_cuboids() {
+        let region = Region::cuboid((2, 5, -1), (0, 4, 1));
+        assert_eq!(
+            region,
+            Region::Cuboid {
+                min: (0, 4, -1),
+                max: (2, 5, 1)
+            }
+        );
+        assert_eq!(region.volume(), Some(18));
+        let everything = Region::cuboid((i32::MIN, i32::MIN, i32::MIN), (i32::MAX, i32::MAX, 0));
+        assert_eq!(everything.volume(), None);
+        let positions = region.positions().collect::<Vec<_>>();
+        assert_eq!(positions.len(), 18);
+        assert_eq!(positions[0], (0, 4, -1));
+        assert_eq!(positions[1], (1, 4, -1));
+        assert_eq!(positions[17], (2, 5, 1));
+    }
+
+    #[test]
+    fn test_edits_are_split_by_chunk() {
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        let edit = RegionEdit::fill(&Region::cuboid((-1, 0, 15), (16, 0, 16)), stone);
+        assert_eq!(edit.len(), 18 * 2);
+        assert_eq!(
+            edit.chunks().collect::<Vec<_>>(),
+            [(-1, 0), (-1, 1), (0, 0), (0, 1), (1, 0), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_only_real_changes_are_kept() {
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        let dirt = BlockId::from_name("minecraft:dirt").unwrap();
+        let mut chunk = Chunk::new(0, 0, "overworld".to_string());
+        chunk.set_block(1, 64, 1, stone).unwrap();
+        chunk.set_block_entity(1, 64, 1, Default::default(), vec![]);
+
+        let blocks = vec![(1, 64, 1, stone), (2, 64, 1, stone), (2, 64, 1, dirt)];
+        let result = edit_chunk(chunk, blocks, vec![]).unwrap();
+        // The stone is already there, so its block entity stays too
+        assert_eq!(
+            result.changes,
+            [BlockChange {
+                position: (2, 64, 1),
+                old: BlockId::default(),
+                new: dirt,
+            }]
+        );
+        assert!(result.removed.is_empty());
+        let chunk = result.chunk.unwrap();
+        assert_eq!(chunk.get_block(2, 64, 1).unwrap(), dirt);
+        assert_eq!(chunk.block_entities.len(), 1);
+    }
+
+    #[test]
+    fn test_edits_span_chunks_and_undo() {
+        let (world, _) = memory_world((-1..2).flat_map(|x| (-1..1).map(move |z| (x, z))));
+        let pool = ThreadPool::new();
+        let actor = Uuid::new_v4();
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        let dirt = BlockId::from_name("minecraft:dirt").unwrap();
+        let chest = BlockId::from_name("minecraft:chest").unwrap();
+        world
+            .set_block_and_fetch(0, 64, 0, "overworld", chest)
+            .unwrap();
+        let mut chunk = world.load_chunk_owned(0, 0, "overworld").unwrap();
+        chunk.set_block_entity(0, 64, 0, VarInt(1), Vec::new());
+        world.save_chunk(Arc::new(chunk)).unwrap();
+
+        let region = Region::cuboid((-4, 64, -4), (20, 65, 3));
+        let applied = world
+            .edit_region(
+                RegionEdit::fill(&region, stone),
+                "overworld",
+                Some(actor),
+                &pool,
+            )
+            .unwrap();
+        assert_eq!(Some(applied.changes.len() as u64), region.volume());
+        for (x, z) in [(-4, -4), (0, 0), (20, 3)] {
+            assert_eq!(
+                world.get_block_and_fetch(x, 65, z, "overworld").unwrap(),
+                stone
+            );
+        }
+        let chunk = world.load_chunk(0, 0, "overworld").unwrap();
+        assert!(chunk.get_block_entity(0, 64, 0).is_none());
+
+        // Clone the stone onto dirt, then put it all back
+        let mut dirt_edit = RegionEdit::new();
+        dirt_edit.set_block(1, 70, 1, dirt);
+        world
+            .edit_region(dirt_edit, "overworld", Some(actor), &pool)
+            .unwrap();
+        let copy = world
+            .copy_region(
+                &Region::cuboid((1, 70, 1), (1, 70, 1)),
+                (-2, 0, 0),
+                "overworld",
+            )
+            .unwrap();
+        world
+            .edit_region(copy, "overworld", Some(actor), &pool)
+            .unwrap();
+        assert_eq!(
+            world.get_block_and_fetch(-1, 70, 1, "overworld").unwrap(),
+            dirt
+        );
+
+        for _ in 0..3 {
+            assert!(world.undo_region_edit(actor, &pool).unwrap().is_some());
+        }
+        assert!(world.undo_region_edit(actor, &pool).unwrap().is_none());
+        assert_eq!(
+            world.get_block_and_fetch(0, 64, 0, "overworld").unwrap(),
+            chest
+        );
+        assert_eq!(
+            world.get_block_and_fetch(5, 64, 0, "overworld").unwrap(),
+            BlockId::default()
+        );
+        let chunk = world.load_chunk(0, 0, "overworld").unwrap();
+        assert!(chunk.get_block_entity(0, 64, 0).is_some());
+
+        let redone = world.redo_region_edit(actor, &pool).unwrap().unwrap();
+        assert_eq!(Some(redone.changes.len() as u64), region.volume());
+        assert_eq!(
+            world.get_block_and_fetch(0, 64, 0, "overworld").unwrap(),
+            stone
+        );
+        // Someone else's history is their own
+        assert!(world
+            .undo_region_edit(Uuid::new_v4(), &pool)
+            .unwrap()
+            .is_none());
+
+        // Nothing changes if a chunk is missing
+        let missing = RegionEdit::fill(&Region::cuboid((0, 64, 0), (40, 64, 0)), dirt);
+        assert!(matches!(
+            world.edit_region(missing, "overworld", Some(actor), &pool),
+            Err(WorldError::ChunkNotFound)
+        ));
+        assert_eq!(
+            world.get_block_and_fetch(0, 64, 0, "overworld").unwrap(),
+            stone
+        );
+    }
+
+    #[test]
+    fn test_edits_work_without_a_cache() {
+        // Every load makes a new copy of the chunk, which mustn't look like a newer version
+        let options = WorldOptions {
+            cache_ttl: 0,
+            cache_capacity: 0,
+            flush_interval: 0,
+            ..Default::default()
+        };
+        let (world, _) = memory_world_with(options, [(0, 0), (1, 0)]);
+        let pool = ThreadPool::new();
+        let actor = Uuid::new_v4();
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+
+        let region = Region::cuboid((0, 64, 0), (31, 64, 0));
+        let applied = world
+            .edit_region(
+                RegionEdit::fill(&region, stone),
+                "overworld",
+                Some(actor),
+                &pool,
+            )
+            .unwrap();
+        assert_eq!(applied.changes.len(), 32);
+        assert_eq!(
+            world.get_block_and_fetch(20, 64, 0, "overworld").unwrap(),
+            stone
+        );
+        assert!(world.undo_region_edit(actor, &pool).unwrap().is_some());
+        assert_eq!(
+            world.get_block_and_fetch(20, 64, 0, "overworld").unwrap(),
+            BlockId::default()
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! The format of the Litematica mod.
+//!
+//! A file can hold several regions, each with its own palette and position. They're merged into
+//! one schematic covering all of them when read, and a single region is written. Block states are
+//! packed tightly, so unlike in chunks an index can start in one long and end in the next.
+
+use super::{
+    get, get_list, read_entity, resolve_palette, start_compound, start_compound_list, vec3,
+    write_entity_data, Schematic, SchematicBlockEntity,
+};
+use crate::block_entities::BLOCK_ENTITY_TYPES;
+use crate::block_id::BlockId;
+use crate::errors::WorldError;
+use crate::exporting::DATA_VERSION;
+use crate::vanilla_chunk_format::{compound_without, BlockData};
+use ferrumc_nbt::{NBTSerializable, NBTSerializeOptions, NbtTape, NbtTapeElement};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version of the format that's written. Older ones are read the same way.
+const VERSION: i32 = 6;
+
+struct Region<'a, 'b> {
+    element: &'b NbtTapeElement<'a>,
+    /// The lowest corner, since regions can extend either way from their position.
+    min: (i32, i32, i32),
+    size: (i32, i32, i32),
+}
+
+pub(super) fn read(tape: &NbtTape, root: &NbtTapeElement) -> Result<Schematic, WorldError> {
+    let data_version: i32 = get(tape, root, "MinecraftDataVersion").unwrap_or(0);
+    let regions = root
+        .get("Regions")
+        .and_then(|regions| regions.as_compound())
+        .into_iter()
+        .flatten()
+        .map(|(name, element)| {
+            let invalid = || WorldError::SchematicError(format!("Invalid region {name}"));
+            let position = read_xyz(tape, element.get("Position")).ok_or_else(invalid)?;
+            let size = read_xyz(tape, element.get("Size")).ok_or_else(invalid)?;
+            let axis = |position: i32, size: i32| {
+                if size < 0 {
+                    Some((
+                        position.checked_add(size)?.checked_add(1)?,
+                        size.checked_neg()?,
+                    ))
+                } else {
+                    Some((position, size))
+                }
+            };
+            let (min_x, size_x) = axis(position.0, size.0).ok_or_else(invalid)?;
+            let (min_y, size_y) = axis(position.1, size.1).ok_or_else(invalid)?;
+            let (min_z, size_z) = axis(position.2, size.2).ok_or_else(invalid)?;
+            Ok(Region {
+                element,
+                min: (min_x, min_y, min_z),
+                size: (size_x, size_y, size_z),
+            })
+        })
+        .collect::<Result<Vec<_>, WorldError>>()?;
+    if regions.is_empty() {
+        return Err(WorldError::SchematicError("No regions".to_string()));
+    }
+
+    let min =
+        |axis: fn((i32, i32, i32)) -> i32| regions.iter().map(|r| axis(r.min)).min().unwrap_or(0);
+    let max = |axis: fn((i32, i32, i32)) -> i32| {
+        regions
+            .iter()
+            .map(|r| axis(r.min) as i64 + axis(r.size) as i64)
+            .max()
+            .unwrap_or(0)
+    };
+    let size = |axis: fn((i32, i32, i32)) -> i32| {
+        u16::try_from(max(axis) - min(axis) as i64).map_err(|_| {
+            WorldError::SchematicError(format!(
+                "Schematics can't be more than {} blocks across",
+                u16::MAX
+            ))
+        })
+    };
+    let origin = (min(|p| p.0), min(|p| p.1), min(|p| p.2));
+    let mut schematic = Schematic::new(size(|p| p.0)?, size(|p| p.1)?, size(|p| p.2)?)?;
+
+    let metadata = root.get("Metadata");
+    let metadata_tag = |tag: &str| metadata.and_then(|metadata| get::<String>(tape, metadata, tag));
+    schematic.name = metadata_tag("Name");
+    // Litematica always writes an author, even if it's empty
+    schematic.author = metadata_tag("Author").filter(|author| !author.is_empty());
+
+    for region in &regions {
+        let element = region.element;
+        let (size_x, size_y, size_z) = region.size;
+        let corner = (
+            region.min.0 - origin.0,
+            region.min.1 - origin.1,
+            region.min.2 - origin.2,
+        );
+
+        let palette: Vec<BlockData> = get(tape, element, "BlockStatePalette").unwrap_or_default();
+        let palette = resolve_palette(palette, data_version);
+        let states: Vec<i64> = get(tape, element, "BlockStates").unwrap_or_default();
+        let volume = size_x as usize * size_y as usize * size_z as usize;
+        let indices = unpack(bits_per_entry(palette.len()), &states, volume);
+        for (index, palette_index) in indices.into_iter().enumerate() {
+            let block = palette
+                .get(palette_index as usize)
+                .copied()
+                .unwrap_or_default();
+            // Regions can overlap, so only blocks that are there count
+            if block == BlockId::default() {
+                continue;
+            }
+            let index = index as i32;
+            let x = index % size_x;
+            let z = index / size_x % size_z;
+            let y = index / (size_x * size_z);
+            schematic.set_block(corner.0 + x, corner.1 + y, corner.2 + z, block)?;
+        }
+
+        for element in &get_list(tape, element, "TileEntities") {
+            let (Some(x), Some(y), Some(z)) = (
+                get::<i32>(tape, element, "x"),
+                get::<i32>(tape, element, "y"),
+                get::<i32>(tape, element, "z"),
+            ) else {
+                continue;
+            };
+            let position = (corner.0 + x, corner.1 + y, corner.2 + z);
+            // Older versions left the id out, but it's almost always the same as the block's name
+            let id = get::<String>(tape, element, "id").or_else(|| {
+                let block = schematic.get_block(position.0, position.1, position.2)?;
+                let name = block.to_block_data()?.name;
+                BLOCK_ENTITY_TYPES.contains(&name.as_str()).then_some(name)
+            });
+            let Some(id) = id else {
+                continue;
+            };
+            schematic.block_entities.push(SchematicBlockEntity {
+                id,
+                position,
+                data: compound_without(tape, element, &["id", "x", "y", "z"]),
+            });
+        }
+
+        for element in &get_list(tape, element, "Entities") {
+            let Some(mut entity) = read_entity(tape, Some(element), None, None) else {
+                continue;
+            };
+            let (x, y, z) = entity.position;
+            entity.position = (
+                x + corner.0 as f64,
+                y + corner.1 as f64,
+                z + corner.2 as f64,
+            );
+            schematic.entities.push(entity);
+        }
+    }
+
+    Ok(schematic)
+}
+
+pub(super) fn write(schematic: &Schematic) -> Vec<u8> {
+    let options = |name| NBTSerializeOptions::WithHeader(name);
+    let (palette, indices) = schematic.palette();
+    let states = pack(bits_per_entry(palette.len()), &indices);
+    let palette = palette
+        .iter()
+        .map(|block| block.to_block_data().unwrap_or_default())
+        .collect::<Vec<_>>();
+    let size = (
+        schematic.width as i32,
+        schematic.height as i32,
+        schematic.length as i32,
+    );
+    let name = schematic
+        .name
+        .clone()
+        .unwrap_or_else(|| "Unnamed".to_string());
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as i64)
+        .unwrap_or_default();
+
+    let mut buf = Vec::new();
+    start_compound(&mut buf, "");
+    VERSION.serialize(&mut buf, &options("Version"));
+    DATA_VERSION.serialize(&mut buf, &options("MinecraftDataVersion"));
+
+    start_compound(&mut buf, "Metadata");
+    name.serialize(&mut buf, &options("Name"));
+    schematic
+        .author
+        .clone()
+        .unwrap_or_default()
+        .serialize(&mut buf, &options("Author"));
+    "".serialize(&mut buf, &options("Description"));
+    1i32.serialize(&mut buf, &options("RegionCount"));
+    (schematic.blocks.len() as i32).serialize(&mut buf, &options("TotalVolume"));
+    let total_blocks = schematic
+        .blocks
+        .iter()
+        .filter(|block| **block != BlockId::default())
+        .count();
+    (total_blocks as i32).serialize(&mut buf, &options("TotalBlocks"));
+    write_xyz(&mut buf, "EnclosingSize", size);
+    time.serialize(&mut buf, &options("TimeCreated"));
+    time.serialize(&mut buf, &options("TimeModified"));
+    buf.push(0);
+
+    start_compound(&mut buf, "Regions");
+    start_compound(&mut buf, &name);
+    write_xyz(&mut buf, "Position", (0, 0, 0));
+    write_xyz(&mut buf, "Size", size);
+    palette.serialize(&mut buf, &options("BlockStatePalette"));
+    states.serialize(&mut buf, &options("BlockStates"));
+
+    start_compound_list(&mut buf, "TileEntities", schematic.block_entities.len());
+    for block_entity in &schematic.block_entities {
+        let (x, y, z) = block_entity.position;
+        x.serialize(&mut buf, &options("x"));
+        y.serialize(&mut buf, &options("y"));
+        z.serialize(&mut buf, &options("z"));
+        block_entity.id.serialize(&mut buf, &options("id"));
+        buf.extend_from_slice(&block_entity.data);
+        buf.push(0);
+    }
+
+    start_compound_list(&mut buf, "Entities", schematic.entities.len());
+    for entity in &schematic.entities {
+        vec3(entity.position).serialize(&mut buf, &options("Pos"));
+        write_entity_data(&mut buf, entity);
+        buf.push(0);
+    }
+    start_compound_list(&mut buf, "PendingBlockTicks", 0);
+    start_compound_list(&mut buf, "PendingFluidTicks", 0);
+
+    // The ends of the region, `Regions` and the root
+    buf.extend_from_slice(&[0, 0, 0]);
+    buf
+}
+
+fn read_xyz(tape: &NbtTape, element: Option<&NbtTapeElement>) -> Option<(i32, i32, i32)> {
+    let element = element?;
+    Some((
+        get(tape, element, "x")?,
+        get(tape, element, "y")?,
+        get(tape, element, "z")?,
+    ))
+}
+
+fn write_xyz(buf: &mut Vec<u8>, name: &str, (x, y, z): (i32, i32, i32)) {
+    start_compound(buf, name);
+    x.serialize(buf, &NBTSerializeOptions::WithHeader("x"));
+    y.serialize(buf, &NBTSerializeOptions::WithHeader("y"));
+    z.serialize(buf, &NBTSerializeOptions::WithHeader("z"));
+    buf.push(0);
+}
+
+fn bits_per_entry(palette_len: usize) -> usize {
+    (usize::BITS - palette_len.saturating_sub(1).leading_zeros()).max(2) as usize
+}
+
+fn unpack(bits: usize, data: &[i64], count: usize) -> Vec<u64> {
+    let mask = (1u64 << bits) - 1;
+    (0..count)
+        .map(|index| {
+            let start = index * bits;
+            let (long, offset) = (start / 64, start % 64);
+            let Some(&low) = data.get(long) else {
+                return 0;
+            };
+            let mut value = (low as u64) >> offset;
+            if offset + bits > 64 {
+                let high = data.get(long + 1).copied().unwrap_or_default() as u64;
+                value |= high << (64 - offset);
+            }
+            value & mask
+        })
+        .collect()
+}
+
+fn pack(bits: usize, values: &[u64]) -> Vec<i64> {
+    let mut data = vec![0u64; (values.len() * bits).div_ceil(64)];
+    for (index, value) in values.iter().enumerate() {
+        let start = index * bits;
+        let (long, offset) = (start / 64, start % 64);
+        data[long] |= value << offset;
+        if offset + bits > 64 {
+            data[long + 1] |= value >> (64 - offset);
+        }
+    }
+    data.into_iter().map(|long| long as i64).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packing_spans_longs() {
+        assert_eq!(bits_per_entry(1), 2);
+        assert_eq!(bits_per_entry(4), 2);
+        assert_eq!(bits_per_entry(5), 3);
+        assert_eq!(bits_per_entry(33), 6);
+
+        let values = (0..100).map(|value| value % 31).collect::<Vec<u64>>();
+        let packed = pack(5, &values);
+        // 500 bits, with no padding at the end of each long
+        assert_eq!(packed.len(), 8);
+        assert_eq!(unpack(5, &packed, values.len()), values);
+        // The 13th value starts at bit 60 and ends in the next long
+        assert_eq!(packed[0] as u64 >> 60, values[12] & 0xf);
+    }
+
+    #[test]
+    fn test_negative_sizes() {
+        let mut buf = Vec::new();
+        start_compound(&mut buf, "");
+        start_compound(&mut buf, "Regions");
+        start_compound(&mut buf, "Flipped");
+        write_xyz(&mut buf, "Position", (10, 64, 10));
+        write_xyz(&mut buf, "Size", (-2, 1, 3));
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        vec![BlockData::default(), stone.to_block_data().unwrap()].serialize(
+            &mut buf,
+            &NBTSerializeOptions::WithHeader("BlockStatePalette"),
+        );
+        // Only the first block, at the lowest x, y and z, is stone
+        pack(2, &[1, 0, 0, 0, 0, 0])
+            .serialize(&mut buf, &NBTSerializeOptions::WithHeader("BlockStates"));
+        buf.extend_from_slice(&[0, 0, 0]);
+
+        let mut tape = NbtTape::new(&buf);
+        tape.parse();
+        let (_, root) = tape.root.as_ref().unwrap();
+        let schematic = read(&tape, root).unwrap();
+        assert_eq!(
+            (schematic.width, schematic.height, schematic.length),
+            (2, 1, 3)
+        );
+        assert_eq!(schematic.get_block(0, 0, 0), Some(stone));
+        assert_eq!(schematic.get_block(1, 0, 0), Some(BlockId::default()));
+    }
+}
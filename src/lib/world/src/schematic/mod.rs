@@ -0,0 +1,856 @@
+//! Structures saved as schematic files, and pasting them into or copying them out of the world.
+//!
+//! Sponge schematics (`.schem`, versions 1 to 3, as used by WorldEdit) and Litematica files
+//! (`.litematic`) can be read and written, along with the block entities and entities in them.
+//! Block states from files saved by older versions are upgraded the same way imported chunks are,
+//! see [`data_fixer`](crate::data_fixer), and anything that still can't be mapped becomes air.
+
+mod litematica;
+mod sponge;
+mod transform;
+
+pub use transform::{Mirror, Rotation};
+
+use crate::block_entities::{block_entity_type_name, BLOCK_ENTITY_TYPES};
+use crate::block_id::{BlockId, BLOCK2ID};
+use crate::chunk_format::Chunk;
+use crate::data_fixer::{resolve_block, upgrade_block};
+use crate::edit_batch::EditBatch;
+use crate::entities::StoredEntity;
+use crate::errors::WorldError;
+use crate::exporting::compound_body;
+use crate::importing::stored_entity;
+use crate::region_edit::MAX_REDOS;
+use crate::vanilla_chunk_format::{compound_without, root_compound, BlockData};
+use crate::vanilla_save_format::VanillaEntity;
+use crate::World;
+use ferrumc_nbt::{FromNbt, NBTSerializable, NBTSerializeOptions, NbtTape, NbtTapeElement};
+use ferrumc_net_codec::net_types::var_int::VarInt;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::warn;
+use transform::Transform;
+use uuid::Uuid;
+
+/// The most blocks a schematic can hold. Files are checked against it before anything is
+/// allocated for them, so one claiming to be huge can't run the server out of memory.
+pub const MAX_VOLUME: usize = 1 << 24;
+/// The most entries a palette read from a file can have, well above the number of block states.
+const MAX_PALETTE_LEN: usize = 1 << 16;
+
+/// The file formats a schematic can be saved as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchematicFormat {
+    /// What WorldEdit wrote up to 7.2.
+    SpongeV2,
+    /// What WorldEdit writes from 7.3 on.
+    SpongeV3,
+    Litematica,
+}
+
+impl SchematicFormat {
+    /// Pick the format from a file's extension. `.schem` files are written as Sponge v3.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "schem" => Some(SchematicFormat::SpongeV3),
+            "litematic" => Some(SchematicFormat::Litematica),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchematicBlockEntity {
+    /// The namespaced block entity type, e.g. `minecraft:chest`.
+    pub id: String,
+    /// The position inside the schematic.
+    pub position: (i32, i32, i32),
+    /// The rest of the block entity's NBT, as the serialized tags inside its compound.
+    pub data: Vec<u8>,
+}
+
+/// A box of blocks, with the block entities and entities inside it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schematic {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    /// The size along the x axis.
+    pub width: u16,
+    /// The size along the y axis.
+    pub height: u16,
+    /// The size along the z axis.
+    pub length: u16,
+    /// Where the schematic's corner ends up relative to the position it's pasted at, like the
+    /// offset WorldEdit saves from where the player stood when copying it.
+    pub offset: (i32, i32, i32),
+    /// Indexed by `(y * length + z) * width + x`, the same order both formats use.
+    blocks: Vec<BlockId>,
+    pub block_entities: Vec<SchematicBlockEntity>,
+    /// Entities, positioned relative to the schematic's corner.
+    pub entities: Vec<StoredEntity>,
+}
+
+impl Schematic {
+    /// An empty schematic, filled with air. Fails if it would hold more than [`MAX_VOLUME`]
+    /// blocks.
+    pub fn new(width: u16, height: u16, length: u16) -> Result<Self, WorldError> {
+        let volume = width as usize * height as usize * length as usize;
+        if volume > MAX_VOLUME {
+            return Err(WorldError::SchematicError(format!(
+                "A {width}x{height}x{length} schematic is more than the {MAX_VOLUME} blocks allowed"
+            )));
+        }
+        Ok(Schematic {
+            name: None,
+            author: None,
+            width,
+            height,
+            length,
+            offset: (0, 0, 0),
+            blocks: vec![BlockId::default(); volume],
+            block_entities: Vec::new(),
+            entities: Vec::new(),
+        })
+    }
+
+    fn index(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        let inside = |value: i32, size: u16| (0..size as i32).contains(&value);
+        if !inside(x, self.width) || !inside(y, self.height) || !inside(z, self.length) {
+            return None;
+        }
+        Some(((y as usize * self.length as usize) + z as usize) * self.width as usize + x as usize)
+    }
+
+    fn position(&self, index: usize) -> (i32, i32, i32) {
+        let width = self.width as usize;
+        let layer = width * self.length as usize;
+        (
+            (index % width) as i32,
+            (index / layer) as i32,
+            (index % layer / width) as i32,
+        )
+    }
+
+    /// Get the block at a position inside the schematic, or None if it's outside of it.
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<BlockId> {
+        self.index(x, y, z).map(|index| self.blocks[index])
+    }
+
+    pub fn set_block(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: impl Into<BlockId>,
+    ) -> Result<(), WorldError> {
+        let index = self.index(x, y, z).ok_or_else(|| {
+            WorldError::SchematicError(format!("{x}, {y}, {z} is outside of the schematic"))
+        })?;
+        self.blocks[index] = block.into();
+        Ok(())
+    }
+
+    /// Every block in the schematic with its position, air included.
+    pub fn blocks(&self) -> impl Iterator<Item = ((i32, i32, i32), BlockId)> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| (self.position(index), *block))
+    }
+
+    /// The distinct blocks in the schematic with air first, and the index of each block into
+    /// them.
+    fn palette(&self) -> (Vec<BlockId>, Vec<u64>) {
+        let mut palette = vec![BlockId::default()];
+        let mut lookup = HashMap::from([(BlockId::default(), 0u64)]);
+        let indices = self
+            .blocks
+            .iter()
+            .map(|block| {
+                *lookup.entry(*block).or_insert_with(|| {
+                    palette.push(*block);
+                    palette.len() as u64 - 1
+                })
+            })
+            .collect();
+        (palette, indices)
+    }
+
+    /// Read a schematic file, working out the format from what's in it.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, WorldError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Read a schematic from the contents of a file, gzipped or not.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, WorldError> {
+        let mut decompressed = Vec::new();
+        let data = if data.starts_with(&[0x1f, 0x8b]) {
+            GzDecoder::new(data).read_to_end(&mut decompressed)?;
+            decompressed.as_slice()
+        } else {
+            data
+        };
+        if data.first() != Some(&10) {
+            return Err(WorldError::SchematicError(
+                "Not an NBT compound".to_string(),
+            ));
+        }
+
+        let mut tape = NbtTape::new(data);
+        tape.parse();
+        let (_, root) = tape
+            .root
+            .as_ref()
+            .ok_or_else(|| WorldError::SchematicError("Missing root compound".to_string()))?;
+        if root.get("Regions").is_some() {
+            litematica::read(&tape, root)
+        } else {
+            sponge::read(&tape, root)
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>, format: SchematicFormat) -> Result<(), WorldError> {
+        std::fs::write(path, self.to_bytes(format)?)?;
+        Ok(())
+    }
+
+    /// Serialize the schematic as a gzipped file in the given format.
+    pub fn to_bytes(&self, format: SchematicFormat) -> Result<Vec<u8>, WorldError> {
+        let nbt = match format {
+            SchematicFormat::SpongeV2 => sponge::write(self, 2),
+            SchematicFormat::SpongeV3 => sponge::write(self, 3),
+            SchematicFormat::Litematica => litematica::write(self),
+        };
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&nbt)?;
+        Ok(encoder.finish()?)
+    }
+}
+
+/// How [`World::paste_schematic`] places a schematic.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PasteOptions {
+    pub rotation: Rotation,
+    pub mirror: Mirror,
+    /// Leave the blocks that are already there where the schematic has air.
+    pub skip_air: bool,
+    /// Leave the schematic's entities out.
+    pub skip_entities: bool,
+}
+
+/// Everything a paste changes in one chunk.
+#[derive(Default)]
+struct ChunkPaste {
+    blocks: Vec<(i32, i32, i32, BlockId)>,
+    block_entities: Vec<(i32, i32, i32, VarInt, Vec<u8>)>,
+    entities: Vec<StoredEntity>,
+}
+
+impl World {
+    /// Paste a schematic with its corner, moved by its offset, at `x`, `y`, `z`. It's turned
+    /// around that position if the options say so.
+    ///
+    /// The blocks go through an [`EditBatch`] per chunk, so a schematic can span any number of
+    /// chunks, but they all have to exist already. Nothing is changed if one doesn't. Blocks that
+    /// would end up above or below the dimension are left out. The block entities it places are
+    /// queued for sending to players, and redstone around the pasted blocks is recompiled.
+    ///
+    /// Blocks changed some other way while the paste runs are kept, it's redone on top of them.
+    pub fn paste_schematic(
+        &self,
+        schematic: &Schematic,
+        x: i32,
+        y: i32,
+        z: i32,
+        dimension: &str,
+        options: &PasteOptions,
+    ) -> Result<(), WorldError> {
+        let dimension_type = self
+            .dimensions
+            .get(dimension)
+            .ok_or_else(|| WorldError::UnknownDimension(dimension.to_string()))?
+            .dimension_type;
+        let heights = dimension_type.min_y..dimension_type.min_y + dimension_type.height as i32;
+        let transform = Transform {
+            mirror: options.mirror,
+            rotation: options.rotation,
+        };
+        let (offset_x, offset_y, offset_z) = schematic.offset;
+        let place = |(block_x, block_y, block_z): (i32, i32, i32)| {
+            let (dx, dy, dz) =
+                transform.block_pos((block_x + offset_x, block_y + offset_y, block_z + offset_z));
+            (x + dx, y + dy, z + dz)
+        };
+
+        let mut chunks: BTreeMap<(i32, i32), ChunkPaste> = BTreeMap::new();
+        let mut states = HashMap::new();
+        for (position, block) in schematic.blocks() {
+            if options.skip_air && block == BlockId::default() {
+                continue;
+            }
+            let (block_x, block_y, block_z) = place(position);
+            if !heights.contains(&block_y) {
+                continue;
+            }
+            let block = *states
+                .entry(block)
+                .or_insert_with(|| transform.block(block));
+            chunks
+                .entry((block_x >> 4, block_z >> 4))
+                .or_default()
+                .blocks
+                .push((block_x, block_y, block_z, block));
+        }
+
+        for block_entity in &schematic.block_entities {
+            let Some(entity_type) = BLOCK_ENTITY_TYPES
+                .iter()
+                .position(|id| *id == block_entity.id)
+            else {
+                warn!(
+                    "Skipping block entity with unknown type {} in schematic",
+                    block_entity.id
+                );
+                continue;
+            };
+            let (block_x, block_y, block_z) = place(block_entity.position);
+            if !heights.contains(&block_y) {
+                continue;
+            }
+            chunks
+                .entry((block_x >> 4, block_z >> 4))
+                .or_default()
+                .block_entities
+                .push((
+                    block_x,
+                    block_y,
+                    block_z,
+                    VarInt(entity_type as i32),
+                    root_compound(&block_entity.data),
+                ));
+        }
+
+        if !options.skip_entities {
+            for entity in &schematic.entities {
+                let (entity_x, entity_y, entity_z) = entity.position;
+                let (dx, dy, dz) = transform.entity_pos((
+                    entity_x + offset_x as f64,
+                    entity_y + offset_y as f64,
+                    entity_z + offset_z as f64,
+                ));
+                let position = (x as f64 + dx, y as f64 + dy, z as f64 + dz);
+                let entity = StoredEntity {
+                    // Every copy needs a UUID of its own
+                    uuid: Uuid::new_v4().as_u128(),
+                    position,
+                    velocity: transform.vector(entity.velocity),
+                    yaw: transform.yaw(entity.yaw),
+                    ..entity.clone()
+                };
+                chunks
+                    .entry((
+                        (position.0.floor() as i32) >> 4,
+                        (position.2.floor() as i32) >> 4,
+                    ))
+                    .or_default()
+                    .entities
+                    .push(entity);
+            }
+        }
+
+        for &(chunk_x, chunk_z) in chunks.keys() {
+            if !self.chunk_exists(chunk_x, chunk_z, dimension)? {
+                return Err(WorldError::ChunkNotFound);
+            }
+        }
+
+        let mut pasted = Vec::with_capacity(chunks.len());
+        let mut blocks = Vec::new();
+        let mut block_entities = Vec::new();
+        let mut entities = Vec::new();
+        for ((chunk_x, chunk_z), mut paste) in chunks {
+            let base = self.load_chunk(chunk_x, chunk_z, dimension)?;
+            let chunk = paste_chunk(base.as_ref().clone(), &paste)?;
+            blocks.extend(paste.blocks.iter().map(|&(x, y, z, _)| (x, y, z)));
+            block_entities.extend(
+                paste
+                    .block_entities
+                    .iter()
+                    .map(|(x, y, z, entity_type, nbt)| ((*x, *y, *z), *entity_type, nbt.clone())),
+            );
+            let pasted_entities = std::mem::take(&mut paste.entities);
+            if !pasted_entities.is_empty() {
+                entities.push(((chunk_x, chunk_z), pasted_entities));
+            }
+            pasted.push(((chunk_x, chunk_z), base, paste, chunk));
+        }
+
+        // Like region edits, the chunks are checked for newer versions and written under the
+        // flush lock, so blocks changed since they were loaded are pasted over, not lost
+        let flushing = self.dirty.flush_lock.lock().unwrap();
+        let mut changed = Vec::with_capacity(pasted.len());
+        for ((chunk_x, chunk_z), mut base, paste, mut chunk) in pasted {
+            let mut redos = 0;
+            loop {
+                let current = self.load_chunk(chunk_x, chunk_z, dimension)?;
+                if Arc::ptr_eq(&current, &base) || current == base {
+                    break;
+                }
+                if redos == MAX_REDOS {
+                    return Err(WorldError::ChunkContended(chunk_x, chunk_z));
+                }
+                redos += 1;
+                chunk = paste_chunk(current.as_ref().clone(), &paste)?;
+                base = current;
+            }
+            changed.push(chunk);
+        }
+        let lit = self.relight_with_neighbours(changed);
+        self.write_chunks_locked(&flushing, lit)?;
+        drop(flushing);
+        {
+            let mut redstone = self.redstone.lock().unwrap();
+            for position in blocks {
+                redstone.block_changed(dimension, position);
+            }
+        }
+        for (position, entity_type, nbt) in block_entities {
+            self.block_entity_changed(dimension, position, entity_type, nbt);
+        }
+
+        // Only once the blocks are in, so a paste that fails doesn't leave its entities behind
+        for ((chunk_x, chunk_z), pasted) in entities {
+            let mut stored = self.load_entities(chunk_x, chunk_z, dimension)?;
+            stored.extend(pasted);
+            self.save_entities(chunk_x, chunk_z, dimension, &stored)?;
+        }
+        Ok(())
+    }
+
+    /// Copy the blocks, block entities and entities between two corners, both included, into a
+    /// schematic. All the chunks in between have to exist, and the box can't hold more than
+    /// [`MAX_VOLUME`] blocks.
+    pub fn capture_schematic(
+        &self,
+        from: (i32, i32, i32),
+        to: (i32, i32, i32),
+        dimension: &str,
+    ) -> Result<Schematic, WorldError> {
+        let min = (from.0.min(to.0), from.1.min(to.1), from.2.min(to.2));
+        let max = (from.0.max(to.0), from.1.max(to.1), from.2.max(to.2));
+        let size = |min: i32, max: i32| {
+            u16::try_from(max as i64 - min as i64 + 1).map_err(|_| {
+                WorldError::SchematicError(format!(
+                    "Schematics can't be more than {} blocks across",
+                    u16::MAX
+                ))
+            })
+        };
+        let mut schematic = Schematic::new(
+            size(min.0, max.0)?,
+            size(min.1, max.1)?,
+            size(min.2, max.2)?,
+        )?;
+        let inside = |x: i32, y: i32, z: i32| {
+            (min.0..=max.0).contains(&x)
+                && (min.1..=max.1).contains(&y)
+                && (min.2..=max.2).contains(&z)
+        };
+
+        for chunk_x in (min.0 >> 4)..=(max.0 >> 4) {
+            for chunk_z in (min.2 >> 4)..=(max.2 >> 4) {
+                let chunk = self.load_chunk(chunk_x, chunk_z, dimension)?;
+                let xs = (chunk_x * 16).max(min.0)..=(chunk_x * 16 + 15).min(max.0);
+                let zs = (chunk_z * 16).max(min.2)..=(chunk_z * 16 + 15).min(max.2);
+                for y in min.1..=max.1 {
+                    for z in zs.clone() {
+                        for x in xs.clone() {
+                            // Anything outside the chunk's sections is air
+                            let block = chunk.get_block(x, y, z).unwrap_or_default();
+                            schematic.set_block(x - min.0, y - min.1, z - min.2, block)?;
+                        }
+                    }
+                }
+
+                for block_entity in &chunk.block_entities {
+                    let x = chunk_x * 16 + block_entity.x() as i32;
+                    let y = block_entity.y as i16 as i32;
+                    let z = chunk_z * 16 + block_entity.z() as i32;
+                    if !inside(x, y, z) {
+                        continue;
+                    }
+                    let Some(id) = block_entity_type_name(block_entity.entity_type.0) else {
+                        warn!(
+                            "Skipping block entity with unknown type {} at {x}, {y}, {z}",
+                            block_entity.entity_type.0
+                        );
+                        continue;
+                    };
+                    schematic.block_entities.push(SchematicBlockEntity {
+                        id: id.to_string(),
+                        position: (x - min.0, y - min.1, z - min.2),
+                        data: compound_body(&block_entity.nbt)
+                            .unwrap_or_default()
+                            .to_vec(),
+                    });
+                }
+
+                for mut entity in self.load_entities(chunk_x, chunk_z, dimension)? {
+                    let (x, y, z) = entity.position;
+                    if !inside(x.floor() as i32, y.floor() as i32, z.floor() as i32) {
+                        continue;
+                    }
+                    entity.position = (x - min.0 as f64, y - min.1 as f64, z - min.2 as f64);
+                    schematic.entities.push(entity);
+                }
+            }
+        }
+        Ok(schematic)
+    }
+}
+
+/// Turn a schematic's palette into block ids, upgrading the states first if it was saved by an
+/// older version.
+/// Paste one chunk's share of a schematic into it.
+fn paste_chunk(mut chunk: Chunk, paste: &ChunkPaste) -> Result<Chunk, WorldError> {
+    if !paste.blocks.is_empty() {
+        // Block entities of the blocks being replaced go with them
+        let replaced = paste
+            .blocks
+            .iter()
+            .map(|(x, y, z, _)| (x & 0xf, *y, z & 0xf))
+            .collect::<HashSet<_>>();
+        chunk.block_entities.retain(|block_entity| {
+            !replaced.contains(&(
+                block_entity.x() as i32,
+                block_entity.y as i16 as i32,
+                block_entity.z() as i32,
+            ))
+        });
+
+        let mut batch = EditBatch::new(&mut chunk);
+        for &(x, y, z, block) in &paste.blocks {
+            batch.set_block(x & 0xf, y, z & 0xf, block);
+        }
+        batch.apply()?;
+    }
+    for (x, y, z, entity_type, nbt) in &paste.block_entities {
+        // Stored as a u16, but it's really the (possibly negative) world y
+        chunk.set_block_entity(
+            (x & 0xf) as u8,
+            *y as i16 as u16,
+            (z & 0xf) as u8,
+            *entity_type,
+            nbt.clone(),
+        );
+    }
+    chunk.mark_modified();
+    Ok(chunk)
+}
+
+fn resolve_palette(palette: Vec<BlockData>, data_version: i32) -> Vec<BlockId> {
+    let mut unmapped = 0;
+    let palette = palette
+        .into_iter()
+        .map(|mut block| {
+            upgrade_block(&mut block, data_version);
+            match resolve_block(&block) {
+                Some(resolved) => BlockId(BLOCK2ID[&resolved] as u32),
+                None => {
+                    unmapped += 1;
+                    BlockId::default()
+                }
+            }
+        })
+        .collect();
+    if unmapped > 0 {
+        warn!("{unmapped} unknown blocks in the schematic were replaced with air");
+    }
+    palette
+}
+
+/// Parse a block state written like `minecraft:oak_stairs[facing=east,half=top]`.
+fn parse_block_state(state: &str) -> BlockData {
+    let (name, properties) = match state.split_once('[') {
+        Some((name, properties)) => (name, Some(properties.trim_end_matches(']'))),
+        None => (state, None),
+    };
+    let name = if name.contains(':') {
+        name.to_string()
+    } else {
+        format!("minecraft:{name}")
+    };
+    let properties = properties
+        .map(|properties| {
+            properties
+                .split(',')
+                .filter_map(|property| property.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect::<BTreeMap<_, _>>()
+        })
+        .filter(|properties| !properties.is_empty());
+    BlockData { name, properties }
+}
+
+fn block_state_string(block: BlockId) -> String {
+    let data = block.to_block_data().unwrap_or_default();
+    match data.properties {
+        Some(properties) if !properties.is_empty() => {
+            let properties = properties
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>();
+            format!("{}[{}]", data.name, properties.join(","))
+        }
+        _ => data.name,
+    }
+}
+
+fn get<'a, T: FromNbt<'a>>(
+    tape: &NbtTape<'a>,
+    element: &NbtTapeElement<'a>,
+    key: &str,
+) -> Option<T> {
+    element.get(key).and_then(|e| T::from_nbt(tape, e).ok())
+}
+
+fn get_list<'a>(
+    tape: &NbtTape<'a>,
+    element: &NbtTapeElement<'a>,
+    key: &str,
+) -> Vec<NbtTapeElement<'a>> {
+    get(tape, element, key).unwrap_or_default()
+}
+
+fn start_compound(buf: &mut Vec<u8>, name: &str) {
+    buf.push(10);
+    name.serialize(buf, &NBTSerializeOptions::None);
+}
+
+/// Start a list of compounds. Each one has to end with a 0 after its tags.
+fn start_compound_list(buf: &mut Vec<u8>, name: &str, len: usize) {
+    buf.push(9);
+    name.serialize(buf, &NBTSerializeOptions::None);
+    buf.push(10);
+    (len as i32).serialize(buf, &NBTSerializeOptions::None);
+}
+
+/// Read an entity, with its id and position overridden if the format keeps them elsewhere.
+fn read_entity(
+    tape: &NbtTape,
+    element: Option<&NbtTapeElement>,
+    id: Option<String>,
+    position: Option<Vec<f64>>,
+) -> Option<StoredEntity> {
+    let mut entity = element
+        .and_then(|element| VanillaEntity::from_nbt(tape, element).ok())
+        .unwrap_or_default();
+    entity.id = id.or(entity.id);
+    entity.pos = position.or(entity.pos);
+    stored_entity(&entity)
+}
+
+/// Write the tags of an entity other than its position.
+fn write_entity_data(buf: &mut Vec<u8>, entity: &StoredEntity) {
+    let options = |name| NBTSerializeOptions::WithHeader(name);
+    entity.entity_type.serialize(buf, &options("id"));
+    let (x, y, z) = entity.velocity;
+    vec![x, y, z].serialize(buf, &options("Motion"));
+    vec![entity.yaw, entity.pitch].serialize(buf, &options("Rotation"));
+    let uuid = [96, 64, 32, 0].map(|shift| (entity.uuid >> shift) as u32 as i32);
+    uuid.to_vec().serialize(buf, &options("UUID"));
+    if let Some(item) = &entity.item {
+        start_compound(buf, "Item");
+        item.id.serialize(buf, &options("id"));
+        (item.count as i8).serialize(buf, &options("Count"));
+        buf.push(0);
+    }
+}
+
+fn vec3<T: Copy>((x, y, z): (T, T, T)) -> Vec<T> {
+    vec![x, y, z]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_entities::BlockEntityUpdate;
+    use crate::entities::StoredItem;
+    use crate::testing::memory_world;
+
+    fn test_schematic() -> Schematic {
+        let mut schematic = Schematic::new(3, 2, 4).unwrap();
+        schematic.name = Some("Test".to_string());
+        schematic.author = Some("Builder".to_string());
+        schematic.offset = (-1, 0, 2);
+        let stairs =
+            parse_block_state("oak_stairs[facing=east,half=top,shape=straight,waterlogged=false]");
+        schematic
+            .set_block(0, 0, 0, BlockId::from_name("minecraft:stone").unwrap())
+            .unwrap();
+        schematic.set_block(2, 1, 3, stairs).unwrap();
+        schematic
+            .set_block(1, 0, 2, BlockId::from_name("minecraft:chest").unwrap())
+            .unwrap();
+
+        let mut data = Vec::new();
+        "Hello".serialize(&mut data, &NBTSerializeOptions::WithHeader("CustomName"));
+        schematic.block_entities.push(SchematicBlockEntity {
+            id: "minecraft:chest".to_string(),
+            position: (1, 0, 2),
+            data,
+        });
+        schematic.entities.push(StoredEntity {
+            entity_type: "minecraft:item".to_string(),
+            uuid: 0x0123_4567_89ab_cdef_0123_4567_89ab_cdef,
+            position: (1.5, 0.0, 0.25),
+            velocity: (0.0, -0.5, 0.0),
+            yaw: 45.0,
+            pitch: 0.0,
+            item: Some(StoredItem {
+                id: "minecraft:diamond".to_string(),
+                count: 3,
+            }),
+        });
+        schematic
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let schematic = test_schematic();
+        for format in [
+            SchematicFormat::SpongeV2,
+            SchematicFormat::SpongeV3,
+            SchematicFormat::Litematica,
+        ] {
+            let bytes = schematic.to_bytes(format).unwrap();
+            let mut read = Schematic::from_bytes(&bytes).unwrap();
+            if format == SchematicFormat::Litematica {
+                // Litematica doesn't have an offset
+                read.offset = schematic.offset;
+            }
+            assert_eq!(read, schematic, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_block_states() {
+        let block = parse_block_state("minecraft:oak_log[axis=x]");
+        assert_eq!(block.name, "minecraft:oak_log");
+        assert_eq!(block.properties.unwrap()["axis"], "x");
+        assert_eq!(parse_block_state("stone").name, "minecraft:stone");
+
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        assert_eq!(block_state_string(stone), "minecraft:stone");
+        let log = parse_block_state("minecraft:oak_log[axis=z]").to_block_id();
+        assert_eq!(block_state_string(log), "minecraft:oak_log[axis=z]");
+    }
+
+    #[test]
+    fn test_old_palettes_are_upgraded() {
+        let palette = vec![
+            parse_block_state("minecraft:grass_path"),
+            parse_block_state("minecraft:not_a_block"),
+        ];
+        let resolved = resolve_palette(palette, 2566);
+        assert_eq!(
+            resolved,
+            vec![
+                BlockId::from_name("minecraft:dirt_path").unwrap(),
+                BlockId::default()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pastes_span_chunks_and_copy_back() {
+        let (world, _) = memory_world((0..2).flat_map(|x| (0..2).map(move |z| (x, z))));
+
+        let stone = BlockId::from_name("minecraft:stone").unwrap();
+        let chest = BlockId::from_name("minecraft:chest").unwrap();
+        let mut schematic = Schematic::new(4, 2, 4).unwrap();
+        for x in 0..4 {
+            for z in 0..4 {
+                schematic.set_block(x, 0, z, stone).unwrap();
+            }
+        }
+        schematic.set_block(3, 1, 0, chest).unwrap();
+        schematic.block_entities.push(SchematicBlockEntity {
+            id: "minecraft:chest".to_string(),
+            position: (3, 1, 0),
+            data: Vec::new(),
+        });
+
+        // Straddles the corner of all four chunks
+        world
+            .paste_schematic(
+                &schematic,
+                14,
+                64,
+                14,
+                "overworld",
+                &PasteOptions::default(),
+            )
+            .unwrap();
+        for (x, z) in [(14, 14), (17, 14), (14, 17), (17, 17)] {
+            assert_eq!(
+                world.get_block_and_fetch(x, 64, z, "overworld").unwrap(),
+                stone
+            );
+        }
+        assert_eq!(
+            world.get_block_and_fetch(17, 65, 14, "overworld").unwrap(),
+            chest
+        );
+        let chunk = world.load_chunk(1, 0, "overworld").unwrap();
+        assert!(chunk.get_block_entity(1, 65, 14).is_some());
+        // Players are sent the chest's contents
+        assert!(matches!(
+            world.take_block_entity_updates().as_slice(),
+            [BlockEntityUpdate {
+                position: (17, 65, 14),
+                ..
+            }]
+        ));
+
+        let copy = world
+            .capture_schematic((14, 64, 14), (17, 65, 17), "overworld")
+            .unwrap();
+        assert_eq!(copy.get_block(0, 0, 0), Some(stone));
+        assert_eq!(copy.get_block(3, 1, 0), Some(chest));
+        assert_eq!(copy.block_entities.len(), 1);
+        assert_eq!(copy.block_entities[0].position, (3, 1, 0));
+
+        // A quarter turn clockwise moves the chest from +x to +z of the paste position
+        let options = PasteOptions {
+            rotation: Rotation::Clockwise90,
+            skip_air: true,
+            ..Default::default()
+        };
+        world
+            .paste_schematic(&schematic, 4, 80, 4, "overworld", &options)
+            .unwrap();
+        assert_eq!(
+            world
+                .get_block_and_fetch(4, 81, 7, "overworld")
+                .unwrap()
+                .to_block_data()
+                .unwrap()
+                .name,
+            "minecraft:chest"
+        );
+
+        // Nothing is pasted if part of it would land in a chunk that doesn't exist
+        let result = world.paste_schematic(&schematic, 33, 100, 0, "overworld", &options);
+        assert!(matches!(result, Err(WorldError::ChunkNotFound)));
+        assert_eq!(
+            world.get_block_and_fetch(30, 100, 0, "overworld").unwrap(),
+            BlockId::default()
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! The Sponge schematic format WorldEdit uses, see
+//! <https://github.com/SpongePowered/Schematic-Specification>.
+//!
+//! Versions 1 and 2 keep everything at the top level, version 3 moves it into a `Schematic`
+//! compound, puts the blocks into a `Blocks` compound and the rest of each block entity and entity
+//! into a `Data` compound next to its id and position.
+
+use super::{
+    get, get_list, parse_block_state, read_entity, resolve_palette, start_compound,
+    start_compound_list, vec3, write_entity_data, Schematic, SchematicBlockEntity, MAX_PALETTE_LEN,
+};
+use crate::errors::WorldError;
+use crate::exporting::DATA_VERSION;
+use crate::vanilla_chunk_format::{compound_without, BlockData};
+use ferrumc_nbt::{FromNbt, NBTSerializable, NBTSerializeOptions, NbtTape, NbtTapeElement};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tags that are kept apart from the rest of a block entity's data.
+const BLOCK_ENTITY_TAGS: [&str; 6] = ["Pos", "Id", "id", "x", "y", "z"];
+
+pub(super) fn read(tape: &NbtTape, root: &NbtTapeElement) -> Result<Schematic, WorldError> {
+    let root = root.get("Schematic").unwrap_or(root);
+    let missing = |tag: &str| WorldError::SchematicError(format!("Missing {tag}"));
+    let version: i32 = get(tape, root, "Version").ok_or_else(|| missing("Version"))?;
+    // Version 1 predates the data version, so it counts as older than anything
+    let data_version: i32 = get(tape, root, "DataVersion").unwrap_or(0);
+    let size = |tag: &'static str| {
+        get::<i16>(tape, root, tag)
+            .map(|size| size as u16)
+            .ok_or_else(|| missing(tag))
+    };
+    let mut schematic = Schematic::new(size("Width")?, size("Height")?, size("Length")?)?;
+
+    let metadata = root.get("Metadata");
+    let metadata_tag = |tag: &str| metadata.and_then(|metadata| get::<String>(tape, metadata, tag));
+    schematic.name = metadata_tag("Name");
+    schematic.author = metadata_tag("Author");
+
+    let offset = get::<Vec<i32>>(tape, root, "Offset");
+    // WorldEdit puts where the schematic was copied from in the offset before version 3, and the
+    // actual offset in its own metadata
+    let worldedit_offset = ["WEOffsetX", "WEOffsetY", "WEOffsetZ"]
+        .map(|tag| metadata.and_then(|metadata| get::<i32>(tape, metadata, tag)));
+    schematic.offset = match (version, worldedit_offset, offset.as_deref()) {
+        (..=2, [Some(x), Some(y), Some(z)], _) => (x, y, z),
+        (_, _, Some(&[x, y, z])) => (x, y, z),
+        _ => (0, 0, 0),
+    };
+
+    let (blocks, palette_tag, data_tag) = if version >= 3 {
+        (root.get("Blocks"), "Palette", "Data")
+    } else {
+        (Some(root), "Palette", "BlockData")
+    };
+    if let Some(blocks) = blocks {
+        let palette = blocks
+            .get(palette_tag)
+            .and_then(|palette| palette.as_compound())
+            .ok_or_else(|| missing(palette_tag))?;
+        let mut states = BTreeMap::new();
+        for (state, index) in palette {
+            let index = i32::from_nbt(tape, index)
+                .ok()
+                .and_then(|index| usize::try_from(index).ok())
+                .filter(|index| *index < MAX_PALETTE_LEN)
+                .ok_or_else(|| {
+                    WorldError::SchematicError(format!("Invalid palette index for {state}"))
+                })?;
+            states.insert(index, parse_block_state(state));
+        }
+        // Indices nothing uses are left as air
+        let len = states.keys().next_back().map_or(0, |index| index + 1);
+        let mut palette = vec![BlockData::default(); len];
+        for (index, state) in states {
+            palette[index] = state;
+        }
+        let palette = resolve_palette(palette, data_version);
+
+        let data: Vec<i8> = get(tape, blocks, data_tag).ok_or_else(|| missing(data_tag))?;
+        let mut bytes = data.iter().map(|byte| *byte as u8);
+        for block in schematic.blocks.iter_mut() {
+            let index = read_varint(&mut bytes).ok_or_else(|| {
+                WorldError::SchematicError("Block data is shorter than the schematic".to_string())
+            })?;
+            *block = palette.get(index).copied().unwrap_or_default();
+        }
+
+        // Version 1 called them tile entities
+        let mut block_entities = get_list(tape, blocks, "BlockEntities");
+        block_entities.extend(get_list(tape, blocks, "TileEntities"));
+        for element in &block_entities {
+            let Some(&[x, y, z]) = get::<Vec<i32>>(tape, element, "Pos").as_deref() else {
+                continue;
+            };
+            let Some(id) = get::<String>(tape, element, "Id").or(get(tape, element, "id")) else {
+                continue;
+            };
+            let data = if version >= 3 {
+                element
+                    .get("Data")
+                    .map(|data| compound_without(tape, data, &BLOCK_ENTITY_TAGS))
+                    .unwrap_or_default()
+            } else {
+                compound_without(tape, element, &BLOCK_ENTITY_TAGS)
+            };
+            schematic.block_entities.push(SchematicBlockEntity {
+                id,
+                position: (x, y, z),
+                data,
+            });
+        }
+    }
+
+    for element in &get_list(tape, root, "Entities") {
+        let data = if version >= 3 {
+            element.get("Data")
+        } else {
+            Some(element)
+        };
+        let entity = read_entity(
+            tape,
+            data,
+            get(tape, element, "Id"),
+            get(tape, element, "Pos"),
+        );
+        schematic.entities.extend(entity);
+    }
+
+    Ok(schematic)
+}
+
+pub(super) fn write(schematic: &Schematic, version: i32) -> Vec<u8> {
+    let options = |name| NBTSerializeOptions::WithHeader(name);
+    let (palette, indices) = schematic.palette();
+    let palette = palette
+        .iter()
+        .enumerate()
+        .map(|(index, block)| (super::block_state_string(*block), index as i32))
+        .collect::<BTreeMap<_, _>>();
+    let mut block_data = Vec::with_capacity(indices.len());
+    for index in indices {
+        write_varint(&mut block_data, index as u32);
+    }
+
+    let mut buf = Vec::new();
+    if version >= 3 {
+        start_compound(&mut buf, "");
+    }
+    start_compound(&mut buf, "Schematic");
+    version.serialize(&mut buf, &options("Version"));
+    DATA_VERSION.serialize(&mut buf, &options("DataVersion"));
+    (schematic.width as i16).serialize(&mut buf, &options("Width"));
+    (schematic.height as i16).serialize(&mut buf, &options("Height"));
+    (schematic.length as i16).serialize(&mut buf, &options("Length"));
+    vec3(schematic.offset).serialize(&mut buf, &options("Offset"));
+
+    start_compound(&mut buf, "Metadata");
+    schematic.name.serialize(&mut buf, &options("Name"));
+    schematic.author.serialize(&mut buf, &options("Author"));
+    let date = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as i64)
+        .unwrap_or_default();
+    date.serialize(&mut buf, &options("Date"));
+    if version <= 2 {
+        let (x, y, z) = schematic.offset;
+        x.serialize(&mut buf, &options("WEOffsetX"));
+        y.serialize(&mut buf, &options("WEOffsetY"));
+        z.serialize(&mut buf, &options("WEOffsetZ"));
+    }
+    buf.push(0);
+
+    if version >= 3 {
+        start_compound(&mut buf, "Blocks");
+        palette.serialize(&mut buf, &options("Palette"));
+        block_data.serialize(&mut buf, &options("Data"));
+    } else {
+        (palette.len() as i32).serialize(&mut buf, &options("PaletteMax"));
+        palette.serialize(&mut buf, &options("Palette"));
+        block_data.serialize(&mut buf, &options("BlockData"));
+    }
+    start_compound_list(&mut buf, "BlockEntities", schematic.block_entities.len());
+    for block_entity in &schematic.block_entities {
+        vec3(block_entity.position).serialize(&mut buf, &options("Pos"));
+        block_entity.id.serialize(&mut buf, &options("Id"));
+        if version >= 3 {
+            start_compound(&mut buf, "Data");
+            buf.extend_from_slice(&block_entity.data);
+            buf.push(0);
+        } else {
+            buf.extend_from_slice(&block_entity.data);
+        }
+        buf.push(0);
+    }
+    if version >= 3 {
+        // The end of `Blocks`
+        buf.push(0);
+    }
+
+    start_compound_list(&mut buf, "Entities", schematic.entities.len());
+    for entity in &schematic.entities {
+        vec3(entity.position).serialize(&mut buf, &options("Pos"));
+        entity.entity_type.serialize(&mut buf, &options("Id"));
+        if version >= 3 {
+            start_compound(&mut buf, "Data");
+            write_entity_data(&mut buf, entity);
+            buf.push(0);
+        } else {
+            write_entity_data(&mut buf, entity);
+        }
+        buf.push(0);
+    }
+
+    buf.push(0);
+    if version >= 3 {
+        buf.push(0);
+    }
+    buf
+}
+
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Option<usize> {
+    let mut value = 0usize;
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next()?;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u32) {
+    loop {
+        if value < 0x80 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_id::BlockId;
+
+    #[test]
+    fn test_varints() {
+        let mut buf = Vec::new();
+        for value in [0, 1, 127, 128, 300, 24275] {
+            write_varint(&mut buf, value);
+        }
+        let mut bytes = buf.into_iter();
+        for value in [0, 1, 127, 128, 300, 24275] {
+            assert_eq!(read_varint(&mut bytes), Some(value));
+        }
+        assert_eq!(read_varint(&mut bytes), None);
+    }
+
+    #[test]
+    fn test_oversized_files_are_rejected() {
+        let options = |name| NBTSerializeOptions::WithHeader(name);
+        let file = |size: i16, palette_index: i32| {
+            let mut buf = Vec::new();
+            start_compound(&mut buf, "Schematic");
+            2i32.serialize(&mut buf, &options("Version"));
+            for tag in ["Width", "Height", "Length"] {
+                size.serialize(&mut buf, &options(tag));
+            }
+            start_compound(&mut buf, "Palette");
+            palette_index.serialize(&mut buf, &options("minecraft:stone"));
+            buf.push(0);
+            buf.push(0);
+            buf
+        };
+        let read_file = |nbt: Vec<u8>| {
+            let mut tape = NbtTape::new(&nbt);
+            tape.parse();
+            let (_, root) = tape.root.as_ref().unwrap();
+            read(&tape, root).map(|_| ())
+        };
+
+        assert!(matches!(
+            read_file(file(i16::MAX, 0)),
+            Err(WorldError::SchematicError(_))
+        ));
+        assert!(matches!(
+            read_file(file(1, i32::MAX)),
+            Err(WorldError::SchematicError(_))
+        ));
+    }
+
+    #[test]
+    fn test_worldedit_offset() {
+        let schematic = Schematic {
+            offset: (5, -2, 7),
+            ..Schematic::new(1, 1, 1).unwrap()
+        };
+        let nbt = write(&schematic, 2);
+        let mut tape = NbtTape::new(&nbt);
+        tape.parse();
+        let (_, root) = tape.root.as_ref().unwrap();
+        assert_eq!(read(&tape, root).unwrap().offset, (5, -2, 7));
+        assert_eq!(
+            read(&tape, root).unwrap().get_block(0, 0, 0),
+            Some(BlockId::default())
+        );
+    }
+}
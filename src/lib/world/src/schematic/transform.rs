@@ -0,0 +1,307 @@
+//! Rotating and mirroring schematics as they're pasted, both the positions of everything in them
+//! and the block states that face a direction.
+
+use crate::block_id::{BlockId, BLOCK2ID};
+use std::ops::Neg;
+
+/// Clockwise when looking down, so north becomes east after a quarter turn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    CounterClockwise90,
+}
+
+impl Rotation {
+    fn quarter_turns(self) -> usize {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 1,
+            Rotation::Clockwise180 => 2,
+            Rotation::CounterClockwise90 => 3,
+        }
+    }
+}
+
+/// Named the same way as vanilla's structure blocks. Mirroring happens before rotating.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mirror {
+    #[default]
+    None,
+    /// Flips the z axis, swapping north and south.
+    LeftRight,
+    /// Flips the x axis, swapping east and west.
+    FrontBack,
+}
+
+/// The horizontal directions in clockwise order.
+const DIRECTIONS: [&str; 4] = ["north", "east", "south", "west"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Transform {
+    pub mirror: Mirror,
+    pub rotation: Rotation,
+}
+
+impl Transform {
+    fn horizontal<T: Neg<Output = T>>(self, x: T, z: T) -> (T, T) {
+        let (x, z) = match self.mirror {
+            Mirror::None => (x, z),
+            Mirror::LeftRight => (x, -z),
+            Mirror::FrontBack => (-x, z),
+        };
+        match self.rotation {
+            Rotation::None => (x, z),
+            Rotation::Clockwise90 => (-z, x),
+            Rotation::Clockwise180 => (-x, -z),
+            Rotation::CounterClockwise90 => (z, -x),
+        }
+    }
+
+    pub(crate) fn block_pos(self, (x, y, z): (i32, i32, i32)) -> (i32, i32, i32) {
+        let (x, z) = self.horizontal(x, z);
+        (x, y, z)
+    }
+
+    /// Like [`Transform::block_pos`], but for a point anywhere inside a block. Blocks are turned
+    /// around their centres, so an entity stays in the same spot of the block it was in.
+    pub(crate) fn entity_pos(self, (x, y, z): (f64, f64, f64)) -> (f64, f64, f64) {
+        let (x, z) = self.horizontal(x - 0.5, z - 0.5);
+        (x + 0.5, y, z + 0.5)
+    }
+
+    pub(crate) fn vector(self, (x, y, z): (f64, f64, f64)) -> (f64, f64, f64) {
+        let (x, z) = self.horizontal(x, z);
+        (x, y, z)
+    }
+
+    /// Turn an entity's yaw, where 0 is facing south and 90 is facing west.
+    pub(crate) fn yaw(self, yaw: f32) -> f32 {
+        let yaw = match self.mirror {
+            Mirror::None => yaw,
+            Mirror::LeftRight => 180.0 - yaw,
+            Mirror::FrontBack => -yaw,
+        };
+        (yaw + 90.0 * self.rotation.quarter_turns() as f32).rem_euclid(360.0)
+    }
+
+    fn direction(self, direction: &str) -> Option<&'static str> {
+        let mut index = DIRECTIONS.iter().position(|d| *d == direction)?;
+        let flip = match self.mirror {
+            Mirror::None => false,
+            Mirror::LeftRight => index % 2 == 0,
+            Mirror::FrontBack => index % 2 == 1,
+        };
+        if flip {
+            index += 2;
+        }
+        Some(DIRECTIONS[(index + self.rotation.quarter_turns()) % 4])
+    }
+
+    /// Turn a block state to match, e.g. a stair facing north becomes one facing east after a
+    /// clockwise quarter turn. Blocks that don't face anywhere are returned as they are.
+    pub(crate) fn block(self, block: BlockId) -> BlockId {
+        if self == Transform::default() {
+            return block;
+        }
+        let Some(mut data) = block.to_block_data() else {
+            return block;
+        };
+        let Some(properties) = data.properties.as_mut() else {
+            return block;
+        };
+        let mirrored = self.mirror != Mirror::None;
+
+        if let Some(facing) = properties.get_mut("facing") {
+            if let Some(direction) = self.direction(facing) {
+                *facing = direction.to_string();
+            }
+        }
+        if let Some(axis) = properties.get_mut("axis") {
+            if self.rotation.quarter_turns() % 2 == 1 {
+                match axis.as_str() {
+                    "x" => *axis = "z".to_string(),
+                    "z" => *axis = "x".to_string(),
+                    _ => {}
+                }
+            }
+        }
+        // Signs, banners and heads, in sixteenths of a turn starting from south
+        if let Some(rotation) = properties.get_mut("rotation") {
+            if let Ok(value) = rotation.parse::<i32>() {
+                let value = match self.mirror {
+                    Mirror::None => value,
+                    Mirror::LeftRight => 8 - value,
+                    Mirror::FrontBack => -value,
+                };
+                let value = value + 4 * self.rotation.quarter_turns() as i32;
+                *rotation = value.rem_euclid(16).to_string();
+            }
+        }
+        // Fences, walls, panes and redstone wire say which sides they connect to
+        if DIRECTIONS.iter().all(|d| properties.contains_key(*d)) {
+            let sides = DIRECTIONS.map(|d| properties[d].clone());
+            for (direction, value) in DIRECTIONS.iter().zip(sides) {
+                if let Some(turned) = self.direction(direction) {
+                    properties.insert(turned.to_string(), value);
+                }
+            }
+        }
+        if let Some(shape) = properties.get_mut("shape") {
+            if let Some(rail) = self.rail_shape(shape) {
+                *shape = rail;
+            } else if mirrored {
+                // Stairs curving one way curve the other way in a mirror
+                *shape = swap_left_right(shape);
+            }
+        }
+        if mirrored {
+            // Door hinges and double chests
+            for key in ["hinge", "type"] {
+                if let Some(value) = properties.get_mut(key) {
+                    *value = swap_left_right(value);
+                }
+            }
+        }
+
+        BLOCK2ID
+            .get(&data)
+            .map(|id| BlockId(*id as u32))
+            .unwrap_or(block)
+    }
+
+    fn rail_shape(self, shape: &str) -> Option<String> {
+        if let Some(direction) = shape.strip_prefix("ascending_") {
+            return Some(format!("ascending_{}", self.direction(direction)?));
+        }
+        let (a, b) = shape.split_once('_')?;
+        let (a, b) = (self.direction(a)?, self.direction(b)?);
+        let is_x = |d: &str| d == "east" || d == "west";
+        // Vanilla always names the north or south end first
+        Some(match (is_x(a), is_x(b)) {
+            (true, true) => "east_west".to_string(),
+            (false, false) => "north_south".to_string(),
+            (true, false) => format!("{b}_{a}"),
+            (false, true) => format!("{a}_{b}"),
+        })
+    }
+}
+
+fn swap_left_right(value: &str) -> String {
+    if value.contains("left") {
+        value.replace("left", "right")
+    } else {
+        value.replace("right", "left")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vanilla_chunk_format::BlockData;
+    use std::collections::BTreeMap;
+
+    fn block(name: &str, properties: &[(&str, &str)]) -> BlockId {
+        let name = format!("minecraft:{name}");
+        let wanted: BTreeMap<String, String> = properties
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        // Only the properties that matter are given, so take the first state that has them
+        crate::block_id::ID2BLOCK
+            .iter()
+            .position(|state: &BlockData| {
+                state.name == name
+                    && wanted.iter().all(|(key, value)| {
+                        state.properties.as_ref().and_then(|p| p.get(key)) == Some(value)
+                    })
+            })
+            .map(|id| BlockId(id as u32))
+            .unwrap_or_else(|| panic!("no state of {name} with {properties:?}"))
+    }
+
+    fn property(block: BlockId, key: &str) -> String {
+        block.to_block_data().unwrap().properties.unwrap()[key].clone()
+    }
+
+    const CLOCKWISE: Transform = Transform {
+        mirror: Mirror::None,
+        rotation: Rotation::Clockwise90,
+    };
+
+    #[test]
+    fn test_positions() {
+        assert_eq!(CLOCKWISE.block_pos((1, 2, -3)), (3, 2, 1));
+        let mirror = Transform {
+            mirror: Mirror::LeftRight,
+            rotation: Rotation::None,
+        };
+        assert_eq!(mirror.block_pos((1, 2, 3)), (1, 2, -3));
+
+        // An entity in the middle of the block at 0 stays in the middle of the block it ends up in
+        assert_eq!(CLOCKWISE.entity_pos((0.5, 64.0, 2.5)), (-1.5, 64.0, 0.5));
+        assert_eq!(CLOCKWISE.block_pos((0, 64, 2)), (-2, 64, 0));
+        // South to west
+        assert_eq!(CLOCKWISE.yaw(0.0), 90.0);
+    }
+
+    #[test]
+    fn test_facing_and_axis() {
+        let stairs = block("oak_stairs", &[("facing", "north"), ("half", "top")]);
+        let turned = CLOCKWISE.block(stairs);
+        assert_eq!(property(turned, "facing"), "east");
+        assert_eq!(property(turned, "half"), "top");
+
+        let log = block("oak_log", &[("axis", "x")]);
+        assert_eq!(property(CLOCKWISE.block(log), "axis"), "z");
+
+        let sign = block("oak_sign", &[("rotation", "0")]);
+        assert_eq!(property(CLOCKWISE.block(sign), "rotation"), "4");
+    }
+
+    #[test]
+    fn test_connections_and_rails() {
+        let fence = block(
+            "oak_fence",
+            &[
+                ("north", "true"),
+                ("east", "false"),
+                ("south", "false"),
+                ("west", "false"),
+            ],
+        );
+        let turned = CLOCKWISE.block(fence);
+        assert_eq!(property(turned, "north"), "false");
+        assert_eq!(property(turned, "east"), "true");
+
+        let rail = block("rail", &[("shape", "north_south")]);
+        assert_eq!(property(CLOCKWISE.block(rail), "shape"), "east_west");
+        let corner = block("rail", &[("shape", "north_east")]);
+        assert_eq!(property(CLOCKWISE.block(corner), "shape"), "south_east");
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let mirror = Transform {
+            mirror: Mirror::FrontBack,
+            rotation: Rotation::None,
+        };
+        let door = block(
+            "oak_door",
+            &[("facing", "east"), ("hinge", "left"), ("half", "lower")],
+        );
+        let mirrored = mirror.block(door);
+        assert_eq!(property(mirrored, "facing"), "west");
+        assert_eq!(property(mirrored, "hinge"), "right");
+
+        let stairs = block(
+            "oak_stairs",
+            &[("facing", "north"), ("shape", "inner_left")],
+        );
+        let mirrored = mirror.block(stairs);
+        assert_eq!(property(mirrored, "facing"), "north");
+        assert_eq!(property(mirrored, "shape"), "inner_right");
+    }
+}
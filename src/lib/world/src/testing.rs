@@ -0,0 +1,36 @@
+//! Helpers for tests that need a whole [`World`] rather than a single chunk.
+
+use crate::chunk_format::Chunk;
+use crate::{World, WorldOptions};
+use ferrumc_storage::memory::MemoryBackend;
+use std::sync::Arc;
+
+/// A world kept in memory with empty overworld chunks at the given chunk coordinates, and the
+/// backend it's kept in, for tests that reopen it.
+pub(crate) fn memory_world(
+    chunks: impl IntoIterator<Item = (i32, i32)>,
+) -> (World, Arc<MemoryBackend>) {
+    memory_world_with(WorldOptions::default(), chunks)
+}
+
+/// Like [`memory_world`], opened with other options.
+pub(crate) fn memory_world_with(
+    options: WorldOptions,
+    chunks: impl IntoIterator<Item = (i32, i32)>,
+) -> (World, Arc<MemoryBackend>) {
+    let backend = Arc::new(MemoryBackend::new());
+    let world = World::with_options(backend.clone(), options).unwrap();
+    for (x, z) in chunks {
+        world
+            .save_chunk(Arc::new(Chunk::new(x, z, "overworld".to_string())))
+            .unwrap();
+    }
+    (world, backend)
+}
+
+/// Run the given number of game ticks.
+pub(crate) fn tick(world: &World, times: usize) {
+    for _ in 0..times {
+        world.tick().unwrap();
+    }
+}
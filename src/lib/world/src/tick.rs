@@ -1,13 +1,60 @@
-use crate::block_id::BlockId;
+//! Scheduled and random block ticks.
+//!
+//! Scheduled ticks are due at an absolute game tick rather than counting down, and wait in a
+//! priority queue per chunk, with an index of when each chunk's next tick is due. Ticks that are
+//! waiting cost nothing until they're due. Ticks due on the same game tick run in vanilla's order,
+//! by [`priority`] and then in the order they were scheduled.
+//!
+//! When a chunk is unloaded the ticks waiting in it, along with the ones redstone scheduled there,
+//! are saved with it as [`PendingTick`]s, at the start of the next world tick so the cache never
+//! has to wait on the scheduler. They're scheduled again as soon as it's loaded, the redstone ones
+//! on the next tick.
+//!
+//! Random ticks work like vanilla's: every game tick, the `randomTickSpeed` game rule's worth of
+//! blocks are picked at random in every loaded section that has blocks that react to them.
+//! Positions can also be registered with [`TickManager::schedule_random`] to be ticked with a
+//! chance of their own.
+
+use crate::block_id::{Age, BlockId, Distance, Moisture, Persistent, Stage};
+use crate::chunk_format::{Chunk, PendingTick};
+use crate::db_functions::save_chunk_internal;
 use crate::errors::WorldError;
-use crate::redstone::{self, Direction};
+use crate::fluids::{self, FluidEdits};
+use crate::hoppers;
 use crate::vanilla_chunk_format::BlockData;
 use crate::World;
 use rand::Rng;
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::sync::Arc;
+
+type ChunkKey = (i32, i32, String);
+
+/// The distance from a log at which leaves decay, and the furthest they keep track of.
+const LEAVES_DECAY_DISTANCE: u8 = 7;
+
+const NEIGHBOURS: [(i32, i32, i32); 6] = [
+    (0, -1, 0),
+    (0, 1, 0),
+    (0, 0, -1),
+    (0, 0, 1),
+    (-1, 0, 0),
+    (1, 0, 0),
+];
+
+/// Vanilla's tick priorities, lower goes first.
+pub mod priority {
+    pub const EXTREMELY_HIGH: i8 = -3;
+    pub const VERY_HIGH: i8 = -2;
+    pub const HIGH: i8 = -1;
+    pub const NORMAL: i8 = 0;
+    pub const LOW: i8 = 1;
+    pub const VERY_LOW: i8 = 2;
+    pub const EXTREMELY_LOW: i8 = 3;
+}
 
 /// Position of a block in the world.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct BlockPos {
     pub x: i32,
     pub y: i32,
@@ -20,7 +67,10 @@ pub struct BlockPos {
 pub struct ScheduledTick {
     pub pos: BlockPos,
     pub block: BlockId,
+    /// Game ticks from now until it's due.
     pub delay: u32,
+    /// See [`priority`].
+    pub priority: i8,
 }
 
 #[derive(Clone, Debug)]
@@ -29,16 +79,181 @@ pub struct RandomTick {
     pub chance: f32,
 }
 
+/// A scheduled tick waiting in its chunk's queue.
+#[derive(Clone, Debug)]
+struct QueuedTick {
+    due: u64,
+    priority: i8,
+    /// Ticks with the same time and priority run in the order they were scheduled.
+    order: u64,
+    pos: (i32, i32, i32),
+    block: BlockId,
+}
+
+impl QueuedTick {
+    fn key(&self) -> (u64, i8, u64) {
+        (self.due, self.priority, self.order)
+    }
+}
+
+impl PartialEq for QueuedTick {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for QueuedTick {}
+
+impl PartialOrd for QueuedTick {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedTick {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+#[derive(Default)]
+struct ChunkTicks {
+    queue: BinaryHeap<Reverse<QueuedTick>>,
+    /// How many ticks are waiting at each position.
+    positions: HashMap<(i32, i32, i32), u32>,
+}
+
+impl ChunkTicks {
+    fn next_due(&self) -> Option<u64> {
+        self.queue.peek().map(|Reverse(tick)| tick.due)
+    }
+
+    fn pop(&mut self) -> Option<QueuedTick> {
+        let Reverse(tick) = self.queue.pop()?;
+        if let Some(count) = self.positions.get_mut(&tick.pos) {
+            *count -= 1;
+            if *count == 0 {
+                self.positions.remove(&tick.pos);
+            }
+        }
+        Some(tick)
+    }
+}
+
 #[derive(Default)]
 pub struct TickManager {
-    pub scheduled: HashMap<(i32, i32, String), VecDeque<ScheduledTick>>,
-    pub random: HashMap<(i32, i32, String), Vec<RandomTick>>,
+    /// The current game tick.
+    time: u64,
+    order: u64,
+    scheduled: HashMap<ChunkKey, ChunkTicks>,
+    /// When the next tick in each chunk with any is due.
+    due: BTreeSet<(u64, ChunkKey)>,
+    pub random: HashMap<ChunkKey, Vec<RandomTick>>,
+    /// Chunks whose copy in storage still has the ticks that were restored from it, so it's saved
+    /// again when it's unloaded even if none are left.
+    stored: HashSet<ChunkKey>,
 }
 
 impl TickManager {
+    /// The current game tick.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
     pub fn schedule(&mut self, tick: ScheduledTick) {
-        let key = (tick.pos.x >> 4, tick.pos.z >> 4, tick.pos.dimension.clone());
-        self.scheduled.entry(key).or_default().push_back(tick);
+        let pos = (tick.pos.x, tick.pos.y, tick.pos.z);
+        let due = self.time + tick.delay as u64;
+        self.push(&tick.pos.dimension, pos, tick.block, due, tick.priority);
+    }
+
+    fn push(
+        &mut self,
+        dimension: &str,
+        pos: (i32, i32, i32),
+        block: BlockId,
+        due: u64,
+        priority: i8,
+    ) {
+        self.order += 1;
+        let key = (pos.0 >> 4, pos.2 >> 4, dimension.to_string());
+        let chunk = self.scheduled.entry(key.clone()).or_default();
+        let next = chunk.next_due();
+        chunk.queue.push(Reverse(QueuedTick {
+            due,
+            priority,
+            order: self.order,
+            pos,
+            block,
+        }));
+        *chunk.positions.entry(pos).or_default() += 1;
+        match next {
+            Some(next) if next <= due => {}
+            Some(next) => {
+                self.due.remove(&(next, key.clone()));
+                self.due.insert((due, key));
+            }
+            None => {
+                self.due.insert((due, key));
+            }
+        }
+    }
+
+    /// Whether a tick is already waiting for the given position.
+    pub fn is_scheduled(&self, pos: &BlockPos) -> bool {
+        let key = (pos.x >> 4, pos.z >> 4, pos.dimension.clone());
+        self.scheduled
+            .get(&key)
+            .is_some_and(|chunk| chunk.positions.contains_key(&(pos.x, pos.y, pos.z)))
+    }
+
+    /// Whether any ticks are waiting in a chunk.
+    pub fn has_scheduled(&self, chunk_x: i32, chunk_z: i32, dimension: &str) -> bool {
+        self.scheduled
+            .contains_key(&(chunk_x, chunk_z, dimension.to_string()))
+    }
+
+    /// The chunks with ticks waiting in them.
+    pub fn chunks_with_ticks(&self) -> impl Iterator<Item = &(i32, i32, String)> {
+        self.scheduled.keys()
+    }
+
+    /// Take every tick out, leaving the manager empty but on the same game tick, so more can be
+    /// scheduled while the taken ones run.
+    pub fn take(&mut self) -> TickManager {
+        let empty = TickManager {
+            time: self.time,
+            order: self.order,
+            stored: std::mem::take(&mut self.stored),
+            ..Default::default()
+        };
+        std::mem::replace(self, empty)
+    }
+
+    /// Take over the ticks of another manager, which run before this one's own when they're due
+    /// at the same time. Ticks scheduled here while the other one moved on keep their delay.
+    pub fn merge(&mut self, other: TickManager) {
+        let newer = std::mem::replace(self, other);
+        self.stored.extend(newer.stored);
+        let behind = self.time.saturating_sub(newer.time);
+        self.time = self.time.max(newer.time);
+        let mut ticks: Vec<(String, QueuedTick)> = newer
+            .scheduled
+            .into_iter()
+            .flat_map(|((_, _, dimension), chunk)| {
+                chunk
+                    .queue
+                    .into_iter()
+                    .map(move |Reverse(tick)| (dimension.clone(), tick))
+            })
+            .collect();
+        ticks.sort_by_key(|(_, tick)| tick.order);
+        for (dimension, tick) in ticks {
+            let due = tick.due + behind;
+            self.push(&dimension, tick.pos, tick.block, due, tick.priority);
+        }
+        for (key, ticks) in newer.random {
+            self.random.entry(key).or_default().extend(ticks);
+        }
     }
 
     pub fn schedule_random(&mut self, pos: BlockPos, chance: f32) {
@@ -49,180 +264,420 @@ impl TickManager {
             .push(RandomTick { pos, chance });
     }
 
+    /// Advance a game tick and run the ticks that are due.
     pub fn tick_world(&mut self, world: &World) -> Result<(), WorldError> {
-        // Handle scheduled ticks
-        let mut to_process = Vec::new();
-        for queue in self.scheduled.values_mut() {
-            let mut i = 0;
-            while i < queue.len() {
-                if queue[i].delay == 0 {
-                    let tick = queue.remove(i).unwrap();
-                    to_process.push(tick);
-                } else {
-                    queue[i].delay -= 1;
-                    i += 1;
+        self.time += 1;
+        let mut fluid_edits = FluidEdits::default();
+        for (dimension, tick) in self.take_due() {
+            let (x, y, z) = tick.pos;
+            let pos = BlockPos { x, y, z, dimension };
+            tick_block(world, self, &mut fluid_edits, &pos, tick.block)?;
+        }
+        fluid_edits.apply(world)?;
+
+        // Blocks can register more random ticks while being ticked, so they're added back after
+        let random = std::mem::take(&mut self.random);
+        let result = self.run_random_ticks(world, &random);
+        for (key, ticks) in std::mem::replace(&mut self.random, random) {
+            self.random.entry(key).or_default().extend(ticks);
+        }
+        result?;
+        tick_random_sections(world)
+    }
+
+    /// Take every tick that's due, in the order they should run.
+    fn take_due(&mut self) -> Vec<(String, QueuedTick)> {
+        let mut due = Vec::new();
+        while let Some((next, key)) = self.due.first().cloned() {
+            if next > self.time {
+                break;
+            }
+            self.due.pop_first();
+            let Some(chunk) = self.scheduled.get_mut(&key) else {
+                continue;
+            };
+            while chunk.next_due().is_some_and(|next| next <= self.time) {
+                if let Some(tick) = chunk.pop() {
+                    due.push((key.2.clone(), tick));
+                }
+            }
+            match chunk.next_due() {
+                Some(next) => {
+                    self.due.insert((next, key));
+                }
+                None => {
+                    self.scheduled.remove(&key);
                 }
             }
         }
-        self.scheduled.retain(|_, v| !v.is_empty());
-        for tick in to_process {
-            tick_block(world, self, &tick.pos, tick.block)?;
-        }
+        due.sort_by(|(_, a), (_, b)| a.cmp(b));
+        due
+    }
 
-        // Handle random ticks – trigger positions based on probability
+    /// Trigger positions based on probability.
+    fn run_random_ticks(
+        &mut self,
+        world: &World,
+        random: &HashMap<ChunkKey, Vec<RandomTick>>,
+    ) -> Result<(), WorldError> {
         let mut rng = rand::rng();
-        let positions: Vec<RandomTick> = self.random.values().flat_map(|v| v.clone()).collect();
-        for rt in positions {
+        let mut fluid_edits = FluidEdits::default();
+        for rt in random.values().flatten() {
             if rng.random::<f32>() < rt.chance {
                 let block_id =
                     world.get_block_and_fetch(rt.pos.x, rt.pos.y, rt.pos.z, &rt.pos.dimension)?;
-                tick_block(world, self, &rt.pos, block_id)?;
+                tick_block(world, self, &mut fluid_edits, &rt.pos, block_id)?;
             }
         }
-        Ok(())
+        fluid_edits.apply(world)
     }
 
     /// Remove all scheduled and random ticks for the given chunk.
     pub fn cleanup_chunk(&mut self, chunk_x: i32, chunk_z: i32, dimension: &str) {
+        self.take_chunk(chunk_x, chunk_z, dimension);
+        self.forget_stored(chunk_x, chunk_z, dimension);
+    }
+
+    /// Remove all scheduled and random ticks for the given chunk, returning the scheduled ones in
+    /// the order they'd run, for saving with it.
+    pub fn take_chunk(&mut self, chunk_x: i32, chunk_z: i32, dimension: &str) -> Vec<PendingTick> {
         let key = (chunk_x, chunk_z, dimension.to_string());
-        self.scheduled.remove(&key);
         self.random.remove(&key);
+        let Some(mut chunk) = self.scheduled.remove(&key) else {
+            return Vec::new();
+        };
+        if let Some(next) = chunk.next_due() {
+            self.due.remove(&(next, key));
+        }
+        let mut saved = Vec::new();
+        while let Some(tick) = chunk.pop() {
+            let delay = tick.due.saturating_sub(self.time) as u32;
+            saved.push(PendingTick::new(
+                tick.pos,
+                tick.block,
+                delay,
+                tick.priority,
+                false,
+            ));
+        }
+        saved
+    }
+
+    /// Schedule the ticks saved with a chunk again, leaving out positions that already have one
+    /// waiting. Redstone ticks are skipped, they belong to the redstone networks.
+    pub fn restore(&mut self, chunk_x: i32, chunk_z: i32, dimension: &str, ticks: &[PendingTick]) {
+        let waiting: HashSet<(i32, i32, i32)> = self
+            .scheduled
+            .get(&(chunk_x, chunk_z, dimension.to_string()))
+            .map(|chunk| chunk.positions.keys().copied().collect())
+            .unwrap_or_default();
+        for tick in ticks.iter().filter(|tick| !tick.redstone) {
+            let pos = tick.pos(chunk_x, chunk_z);
+            if waiting.contains(&pos) {
+                continue;
+            }
+            let due = self.time + tick.delay as u64;
+            self.push(dimension, pos, tick.block, due, tick.priority);
+        }
     }
 
     /// Remove all ticks associated with a dimension.
     pub fn cleanup_dimension(&mut self, dimension: &str) {
         self.scheduled.retain(|(_, _, dim), _| dim != dimension);
+        self.due.retain(|(_, (_, _, dim))| dim != dimension);
         self.random.retain(|(_, _, dim), _| dim != dimension);
+        self.stored.retain(|(_, _, dim)| dim != dimension);
+    }
+
+    /// Schedule the ticks saved with a chunk that was just loaded, unless they were already
+    /// restored from the same copy in storage. Returns whether they were.
+    pub(crate) fn restore_stored(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        dimension: &str,
+        ticks: &[PendingTick],
+    ) -> bool {
+        if !self
+            .stored
+            .insert((chunk_x, chunk_z, dimension.to_string()))
+        {
+            return false;
+        }
+        self.restore(chunk_x, chunk_z, dimension, ticks);
+        true
+    }
+
+    /// Forget that a chunk's copy in storage still has ticks in it, returning whether it had.
+    pub(crate) fn forget_stored(&mut self, chunk_x: i32, chunk_z: i32, dimension: &str) -> bool {
+        self.stored
+            .remove(&(chunk_x, chunk_z, dimension.to_string()))
+    }
+
+    /// The chunks whose copy in storage still has ticks that were restored from it.
+    pub(crate) fn chunks_stored_with_ticks(&self) -> impl Iterator<Item = &ChunkKey> {
+        self.stored.iter()
     }
 }
 
+/// Ticks moving between the scheduler and chunk storage, waiting for the start of the next world
+/// tick.
+pub(crate) enum TickTransfer {
+    /// A chunk was loaded with these redstone ticks saved in it. The networks can be locked while
+    /// chunks load, so they only get them on the next tick.
+    Loaded(ChunkKey, Vec<PendingTick>),
+    /// A chunk was evicted from the cache, so the ticks waiting in it should be saved with it.
+    Unloaded(Arc<Chunk>),
+}
+
+impl World {
+    /// Hand the redstone ticks of chunks that were loaded to the networks and save the ticks of
+    /// chunks that were unloaded since the last tick.
+    pub(crate) fn transfer_ticks(&self) -> Result<(), WorldError> {
+        let transfers = std::mem::take(&mut *self.tick_transfers.lock().unwrap());
+        for transfer in transfers {
+            match transfer {
+                TickTransfer::Loaded((x, z, dimension), ticks) => {
+                    let redstone = ticks
+                        .iter()
+                        .map(|tick| (tick.pos(x, z), tick.delay, tick.priority));
+                    self.redstone.lock().unwrap().restore(&dimension, redstone);
+                }
+                TickTransfer::Unloaded(chunk) => {
+                    // Loaded again since, so its ticks are still wanted
+                    if self
+                        .cache
+                        .contains_key(&(chunk.x, chunk.z, chunk.dimension.clone()))
+                    {
+                        continue;
+                    }
+                    let Some(ticks) = self.take_chunk_ticks(&chunk) else {
+                        continue;
+                    };
+                    // An unsaved version can only be the same or newer
+                    let mut chunk = self
+                        .dirty
+                        .get(chunk.x, chunk.z, &chunk.dimension)
+                        .unwrap_or(chunk)
+                        .as_ref()
+                        .clone();
+                    chunk.pending_ticks = ticks;
+                    if self.write_behind.is_some() {
+                        self.dirty.mark(Arc::new(chunk));
+                    } else {
+                        save_chunk_internal(self, &chunk)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Hand the ticks saved in a chunk that was just loaded to the scheduler. The copy in storage
+    /// keeps them until the chunk is saved again, at the latest when it's unloaded.
+    pub(crate) fn restore_chunk_ticks(&self, chunk: &Chunk, ticks: Vec<PendingTick>) {
+        let (redstone, block): (Vec<_>, Vec<_>) = ticks.into_iter().partition(|tick| tick.redstone);
+        if !self.tick_manager.lock().unwrap().restore_stored(
+            chunk.x,
+            chunk.z,
+            &chunk.dimension,
+            &block,
+        ) {
+            return;
+        }
+        if !redstone.is_empty() {
+            self.tick_transfers
+                .lock()
+                .unwrap()
+                .push(TickTransfer::Loaded(
+                    (chunk.x, chunk.z, chunk.dimension.clone()),
+                    redstone,
+                ));
+        }
+    }
+
+    /// Take the block and redstone ticks waiting in a chunk out of the world, for saving with it.
+    /// `None` if it doesn't need saving: no ticks are waiting and none are left in storage.
+    fn take_chunk_ticks(&self, chunk: &Chunk) -> Option<Vec<PendingTick>> {
+        let (mut ticks, stored) = {
+            let mut tick_manager = self.tick_manager.lock().unwrap();
+            (
+                tick_manager.take_chunk(chunk.x, chunk.z, &chunk.dimension),
+                tick_manager.forget_stored(chunk.x, chunk.z, &chunk.dimension),
+            )
+        };
+        let redstone =
+            self.redstone
+                .lock()
+                .unwrap()
+                .unload_chunk(&chunk.dimension, chunk.x, chunk.z);
+        for (pos, delay, priority) in redstone {
+            let block = chunk.get_block(pos.0, pos.1, pos.2).unwrap_or_default();
+            ticks.push(PendingTick::new(pos, block, delay, priority, true));
+        }
+        (stored || !ticks.is_empty()).then_some(ticks)
+    }
+
+    /// Save every tick that's waiting with its chunk, for shutting down. The ticks are taken out of
+    /// the world, so don't tick it after this. They're written out by the next [`World::sync`].
+    pub fn save_pending_ticks(&self) -> Result<(), WorldError> {
+        self.transfer_ticks()?;
+        let mut chunks: BTreeSet<ChunkKey> = {
+            let tick_manager = self.tick_manager.lock().unwrap();
+            tick_manager
+                .chunks_with_ticks()
+                .chain(tick_manager.chunks_stored_with_ticks())
+                .cloned()
+                .collect()
+        };
+        chunks.extend(self.redstone.lock().unwrap().chunks_with_ticks());
+        for (x, z, dimension) in chunks {
+            let mut chunk = match self.load_chunk_owned(x, z, &dimension) {
+                Ok(chunk) => chunk,
+                Err(WorldError::ChunkNotFound) => continue,
+                Err(e) => return Err(e),
+            };
+            chunk.pending_ticks = self.take_chunk_ticks(&chunk).unwrap_or_default();
+            self.save_chunk(Arc::new(chunk))?;
+        }
+        Ok(())
+    }
+}
+
+/// Run a tick for a block. Fluids put the blocks they change in `fluid_edits`, which are written
+/// before any other block is ticked, since the others change the world directly.
 fn tick_block(
     world: &World,
     tm: &mut TickManager,
+    fluid_edits: &mut FluidEdits,
     pos: &BlockPos,
     block: BlockId,
 ) -> Result<(), WorldError> {
-    if let Some(data) = block.to_block_data() {
-        match data.name.as_str() {
-            "minecraft:water" => water_tick(world, tm, pos)?,
-            "minecraft:wheat" => crop_tick(world, pos, &data)?,
-            name if name.ends_with("_sapling") => sapling_tick(world, pos, &data)?,
-            name if name.ends_with("_leaves") => leaves_tick(world, pos)?,
-            "minecraft:farmland" => farmland_tick(world, pos, &data)?,
-            "minecraft:redstone_torch" | "minecraft:redstone_wall_torch" => {
-                redstone::tick_torch(world, tm, pos, block)?
-            }
-            "minecraft:repeater" => {
-                let delay = data
-                    .properties
-                    .as_ref()
-                    .and_then(|p| p.get("delay"))
-                    .and_then(|v| v.parse::<u8>().ok())
-                    .unwrap_or(1);
-                let facing = data
-                    .properties
-                    .as_ref()
-                    .and_then(|p| p.get("facing"))
-                    .and_then(|v| Direction::from_str(v))
-                    .unwrap_or(Direction::North);
-                redstone::tick_repeater(world, tm, pos, delay, facing, block)?
-            }
-            "minecraft:comparator" => {
-                let facing = data
-                    .properties
-                    .as_ref()
-                    .and_then(|p| p.get("facing"))
-                    .and_then(|v| Direction::from_str(v))
-                    .unwrap_or(Direction::North);
-                redstone::tick_comparator(world, tm, pos, facing, block)?
-            }
-            "minecraft:observer" => {
-                let facing = data
-                    .properties
-                    .as_ref()
-                    .and_then(|p| p.get("facing"))
-                    .and_then(|v| Direction::from_str(v))
-                    .unwrap_or(Direction::North);
-                redstone::tick_observer(world, tm, pos, facing, block)?
-            }
-            "minecraft:piston" | "minecraft:sticky_piston" => {
-                let facing = data
-                    .properties
-                    .as_ref()
-                    .and_then(|p| p.get("facing"))
-                    .and_then(|v| Direction::from_str(v))
-                    .unwrap_or(Direction::North);
-                redstone::tick_piston(world, tm, pos, facing, block)?
-            }
-            _ => {}
+    if matches!(block.name(), Some("minecraft:water" | "minecraft:lava")) {
+        return fluids::tick_fluid(world, tm, fluid_edits, pos);
+    }
+    fluid_edits.apply(world)?;
+    match block.name() {
+        Some("minecraft:dispenser" | "minecraft:dropper") => hoppers::dispense(world, pos, block),
+        Some(name) if name.ends_with("_leaves") => leaves_update(world, pos, block),
+        // Blocks that change on random ticks do the same when a tick was scheduled for them
+        _ => match random_tick_handler(block) {
+            Some(handler) => handler(world, pos, block),
+            None => Ok(()),
+        },
+    }
+}
+
+type RandomTickHandler = fn(&World, &BlockPos, BlockId) -> Result<(), WorldError>;
+
+/// What a block does on a random tick, `None` if it doesn't react to them.
+fn random_tick_handler(block: BlockId) -> Option<RandomTickHandler> {
+    match block.name()? {
+        "minecraft:wheat" => Some(crop_tick),
+        name if name.ends_with("_sapling") => Some(sapling_tick),
+        name if name.ends_with("_leaves") && leaves_decay(block) => Some(leaves_tick),
+        "minecraft:farmland" => Some(farmland_tick),
+        _ => None,
+    }
+}
+
+/// The sections of a chunk with blocks in them that react to random ticks.
+fn random_tick_sections(chunk: &Chunk) -> Vec<i8> {
+    chunk
+        .sections
+        .iter()
+        .filter(|section| {
+            section
+                .block_states
+                .block_counts
+                .iter()
+                .any(|(block, count)| *count > 0 && random_tick_handler(*block).is_some())
+        })
+        .map(|section| section.y)
+        .collect()
+}
+
+impl World {
+    /// Keep track of the sections of a chunk that was just put in the cache that get random
+    /// ticks. Call after it's in the cache, so the next tick doesn't forget it as unloaded.
+    pub(crate) fn track_random_sections(&self, chunk: &Chunk) {
+        let sections = random_tick_sections(chunk);
+        let key = (chunk.x, chunk.z, chunk.dimension.clone());
+        let mut random_sections = self.random_sections.lock().unwrap();
+        if sections.is_empty() {
+            random_sections.remove(&key);
+        } else {
+            random_sections.insert(key, sections);
         }
     }
-    Ok(())
 }
 
-fn water_tick(world: &World, tm: &mut TickManager, pos: &BlockPos) -> Result<(), WorldError> {
-    let dirs = [(0, -1, 0), (1, 0, 0), (-1, 0, 0), (0, 0, 1), (0, 0, -1)];
-    for (dx, dy, dz) in dirs.iter() {
-        let nx = pos.x + dx;
-        let ny = pos.y + dy;
-        let nz = pos.z + dz;
-        if world.get_block_and_fetch(nx, ny, nz, &pos.dimension)? == BlockId::default() {
-            let mut props = BTreeMap::new();
-            props.insert("level".to_string(), "0".to_string());
-            let water = crate::vanilla_chunk_format::BlockData {
-                name: "minecraft:water".to_string(),
-                properties: Some(props),
-            };
-            world.set_block_and_fetch(nx, ny, nz, &pos.dimension, water.clone())?;
-            tm.schedule(ScheduledTick {
-                pos: BlockPos {
-                    x: nx,
-                    y: ny,
-                    z: nz,
-                    dimension: pos.dimension.clone(),
-                },
-                block: water.to_block_id(),
-                delay: 1,
-            });
+/// Give `randomTickSpeed` blocks picked at random in every loaded section with blocks that react
+/// to them a random tick, like vanilla.
+fn tick_random_sections(world: &World) -> Result<(), WorldError> {
+    let speed = world.game_rules().random_tick_speed();
+    if speed == 0 {
+        return Ok(());
+    }
+    let mut rng = rand::rng();
+    let tracked: Vec<(ChunkKey, Vec<i8>)> = {
+        let mut random_sections = world.random_sections.lock().unwrap();
+        // Chunks are only tracked when they're cached, so these were unloaded since
+        random_sections.retain(|key, _| world.cache.contains_key(key));
+        random_sections
+            .iter()
+            .map(|(key, sections)| (key.clone(), sections.clone()))
+            .collect()
+    };
+    for (key, sections) in tracked {
+        let Some(chunk) = world.cache.get(&key) else {
+            continue;
+        };
+        for section in &chunk.sections {
+            if !sections.contains(&section.y) {
+                continue;
+            }
+            for _ in 0..speed {
+                let index: i32 = rng.random_range(0..4096);
+                let x = (chunk.x << 4) | (index & 15);
+                let y = ((section.y as i32) << 4) | (index >> 8);
+                let z = (chunk.z << 4) | ((index >> 4) & 15);
+                // Earlier ticks might have changed the chunk since it was taken from the cache
+                let picked = chunk.get_block(x, y, z).ok().and_then(random_tick_handler);
+                if picked.is_none() {
+                    continue;
+                }
+                let block = world.get_block_and_fetch(x, y, z, &chunk.dimension)?;
+                if let Some(handler) = random_tick_handler(block) {
+                    let pos = BlockPos {
+                        x,
+                        y,
+                        z,
+                        dimension: chunk.dimension.clone(),
+                    };
+                    handler(world, &pos, block)?;
+                }
+            }
         }
     }
     Ok(())
 }
 
-fn crop_tick(
-    world: &World,
-    pos: &BlockPos,
-    data: &crate::vanilla_chunk_format::BlockData,
-) -> Result<(), WorldError> {
-    let mut props = data.properties.clone().unwrap_or_default();
-    let age = props
-        .get("age")
-        .and_then(|v| v.parse::<u32>().ok())
-        .unwrap_or(0);
+fn crop_tick(world: &World, pos: &BlockPos, block: BlockId) -> Result<(), WorldError> {
+    let age = block.get::<Age>().map_or(0, |age| age.0);
     if age < 7 {
-        props.insert("age".to_string(), (age + 1).to_string());
-        let new_data = crate::vanilla_chunk_format::BlockData {
-            name: data.name.clone(),
-            properties: Some(props),
-        };
-        world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, new_data)?;
+        if let Some(grown) = block.with(Age(age + 1)) {
+            world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, grown)?;
+        }
     }
     Ok(())
 }
 
-fn sapling_tick(world: &World, pos: &BlockPos, data: &BlockData) -> Result<(), WorldError> {
-    let mut props = data.properties.clone().unwrap_or_default();
-    let stage = props
-        .get("stage")
-        .and_then(|v| v.parse::<u32>().ok())
-        .unwrap_or(0);
-    if stage == 0 {
-        props.insert("stage".to_string(), "1".to_string());
-        let new_data = BlockData {
-            name: data.name.clone(),
-            properties: Some(props),
-        };
-        world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, new_data)?;
+fn sapling_tick(world: &World, pos: &BlockPos, block: BlockId) -> Result<(), WorldError> {
+    if block.get::<Stage>() == Some(Stage(0)) {
+        if let Some(grown) = block.with(Stage(1)) {
+            world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, grown)?;
+        }
     } else {
         let log = BlockData {
             name: "minecraft:oak_log".to_string(),
@@ -233,77 +688,89 @@ fn sapling_tick(world: &World, pos: &BlockPos, data: &BlockData) -> Result<(), W
     Ok(())
 }
 
-fn leaves_tick(world: &World, pos: &BlockPos) -> Result<(), WorldError> {
-    let mut has_log = false;
-    'outer: for dx in -1..=1 {
-        for dy in -1..=1 {
-            for dz in -1..=1 {
-                let block_id = world.get_block_and_fetch(
-                    pos.x + dx,
-                    pos.y + dy,
-                    pos.z + dz,
-                    &pos.dimension,
-                )?;
-                if let Some(data) = block_id.to_block_data() {
-                    if data.name.ends_with("_log") {
-                        has_log = true;
-                        break 'outer;
-                    }
-                }
-            }
-        }
+/// Whether leaves are far enough from a log to decay. Ones placed by players never do.
+fn leaves_decay(block: BlockId) -> bool {
+    block.get::<Persistent>() != Some(Persistent(true))
+        && block.get::<Distance>() == Some(Distance(LEAVES_DECAY_DISTANCE))
+}
+
+/// Leaves that are too far from a log decay on a random tick, like vanilla.
+fn leaves_tick(world: &World, pos: &BlockPos, block: BlockId) -> Result<(), WorldError> {
+    if !leaves_decay(block) {
+        return Ok(());
     }
-    if !has_log {
-        let air = BlockData {
-            name: "minecraft:air".to_string(),
-            properties: None,
+    let air = BlockData {
+        name: "minecraft:air".to_string(),
+        properties: None,
+    };
+    world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, air)?;
+    Ok(())
+}
+
+/// How long after a block next to them changes leaves work out their distance again.
+pub(crate) fn leaves_update_delay(block: BlockId) -> Option<u32> {
+    block.name()?.ends_with("_leaves").then_some(1)
+}
+
+/// Set the `distance` of leaves to one more than their closest neighbour's, counting logs as 0.
+/// Changing it updates the leaves around them in turn.
+fn leaves_update(world: &World, pos: &BlockPos, block: BlockId) -> Result<(), WorldError> {
+    let mut distance = LEAVES_DECAY_DISTANCE;
+    for (dx, dy, dz) in NEIGHBOURS {
+        // Outside the world or in a chunk that doesn't exist, so not a log
+        let Ok(neighbour) =
+            world.get_block_and_fetch(pos.x + dx, pos.y + dy, pos.z + dz, &pos.dimension)
+        else {
+            continue;
         };
-        world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, air)?;
+        distance = distance.min(log_distance(neighbour) + 1);
+    }
+    if block.get::<Distance>() == Some(Distance(distance)) {
+        return Ok(());
+    }
+    if let Some(updated) = block.with(Distance(distance)) {
+        world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, updated)?;
     }
     Ok(())
 }
 
-fn farmland_tick(world: &World, pos: &BlockPos, data: &BlockData) -> Result<(), WorldError> {
+/// How far a block counts as from a log for the leaves next to it.
+fn log_distance(block: BlockId) -> u8 {
+    match block.name() {
+        Some(name) if name.ends_with("_log") || name.ends_with("_wood") => 0,
+        Some(name) if name.ends_with("_leaves") => block
+            .get::<Distance>()
+            .map_or(LEAVES_DECAY_DISTANCE, |distance| distance.0),
+        _ => LEAVES_DECAY_DISTANCE,
+    }
+}
+
+fn farmland_tick(world: &World, pos: &BlockPos, block: BlockId) -> Result<(), WorldError> {
     let mut hydrated = false;
     'outer: for dx in -4..=4 {
         for dz in -4..=4 {
             let block_id =
                 world.get_block_and_fetch(pos.x + dx, pos.y, pos.z + dz, &pos.dimension)?;
-            if let Some(b) = block_id.to_block_data() {
-                if b.name == "minecraft:water" {
-                    hydrated = true;
-                    break 'outer;
-                }
+            if block_id.name() == Some("minecraft:water") {
+                hydrated = true;
+                break 'outer;
             }
         }
     }
-    let mut props = data.properties.clone().unwrap_or_default();
-    let moisture = props
-        .get("moisture")
-        .and_then(|v| v.parse::<u32>().ok())
-        .unwrap_or(0);
+    let moisture = block.get::<Moisture>().map_or(0, |moisture| moisture.0);
     if hydrated {
         if moisture < 7 {
-            props.insert("moisture".to_string(), "7".to_string());
-            let new_data = BlockData {
-                name: data.name.clone(),
-                properties: Some(props),
-            };
-            world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, new_data)?;
+            if let Some(wet) = block.with(Moisture(7)) {
+                world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, wet)?;
+            }
         }
     } else if moisture > 0 {
-        props.insert("moisture".to_string(), (moisture - 1).to_string());
-        let new_data = BlockData {
-            name: data.name.clone(),
-            properties: Some(props),
-        };
-        world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, new_data)?;
+        if let Some(drier) = block.with(Moisture(moisture - 1)) {
+            world.set_block_and_fetch(pos.x, pos.y, pos.z, &pos.dimension, drier)?;
+        }
     } else {
         let above = world.get_block_and_fetch(pos.x, pos.y + 1, pos.z, &pos.dimension)?;
-        if above
-            .to_block_data()
-            .map_or(true, |b| b.name == "minecraft:air")
-        {
+        if above.name().map_or(true, |name| name == "minecraft:air") {
             let dirt = BlockData {
                 name: "minecraft:dirt".to_string(),
                 properties: None,
@@ -326,7 +793,12 @@ pub fn schedule_block_tick(world: &World, x: i32, y: i32, z: i32, dimension: &st
         dimension: dimension.to_string(),
     };
     let mut guard = world.tick_manager.lock().unwrap();
-    guard.schedule(ScheduledTick { pos, block, delay });
+    guard.schedule(ScheduledTick {
+        pos,
+        block,
+        delay,
+        priority: priority::NORMAL,
+    });
 }
 
 /// Helper to register a position for random ticks.
@@ -340,3 +812,109 @@ pub fn schedule_random_tick(world: &World, x: i32, y: i32, z: i32, dimension: &s
     let mut guard = world.tick_manager.lock().unwrap();
     guard.schedule_random(pos, chance);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_id::{Delay, Direction, Powered};
+    use crate::game_rules::RANDOM_TICK_SPEED;
+    use crate::testing::{memory_world, tick};
+
+    fn block(name: &str) -> BlockId {
+        BlockId::from_name(&format!("minecraft:{name}")).unwrap()
+    }
+
+    #[test]
+    fn test_pending_ticks_survive_a_restart() {
+        let (world, backend) = memory_world([(0, 0)]);
+        let wheat = block("wheat").with(Age(0)).unwrap();
+        let repeater = block("repeater")
+            .with(Direction::West)
+            .and_then(|b| b.with(Delay(4)))
+            .and_then(|b| b.with(Powered(false)))
+            .unwrap();
+        world
+            .set_block_and_fetch(0, 64, 0, "overworld", block("redstone_block"))
+            .unwrap();
+        world
+            .set_block_and_fetch(1, 64, 0, "overworld", repeater)
+            .unwrap();
+        world
+            .set_block_and_fetch(5, 64, 5, "overworld", wheat)
+            .unwrap();
+        world.schedule_tick(5, 64, 5, "overworld", 5);
+        // Compiles the repeater, which starts counting down its 8 ticks
+        tick(&world, 1);
+        world.save_pending_ticks().unwrap();
+        world.sync().unwrap();
+
+        let reopened = World::with_backend(backend.clone()).unwrap();
+        let get = |x: i32, z: i32| reopened.get_block_and_fetch(x, 64, z, "overworld").unwrap();
+        assert_eq!(get(5, 5), wheat);
+        // Loading the chunk hands its ticks back, due as long after the restart as they had left
+        tick(&reopened, 3);
+        assert_eq!(get(5, 5).get::<Age>(), Some(Age(0)));
+        tick(&reopened, 1);
+        assert_eq!(get(5, 5).get::<Age>(), Some(Age(1)));
+        tick(&reopened, 3);
+        assert_eq!(get(1, 0).get::<Powered>(), Some(Powered(false)));
+        tick(&reopened, 1);
+        assert_eq!(get(1, 0).get::<Powered>(), Some(Powered(true)));
+    }
+
+    #[test]
+    fn test_random_ticks_follow_random_tick_speed() {
+        let (world, backend) = memory_world([(0, 0)]);
+        let wheat = block("wheat").with(Age(0)).unwrap();
+        let leaves = block("oak_leaves")
+            .with(Persistent(false))
+            .and_then(|b| b.with(Distance(7)))
+            .unwrap();
+        let placed_leaves = leaves.with(Persistent(true)).unwrap();
+        let get = |x: i32, y: i32, z: i32| world.get_block_and_fetch(x, y, z, "overworld").unwrap();
+        world
+            .set_block_and_fetch(1, 64, 1, "overworld", wheat)
+            .unwrap();
+        world
+            .set_block_and_fetch(8, 64, 8, "overworld", leaves)
+            .unwrap();
+        world
+            .set_block_and_fetch(8, 64, 11, "overworld", placed_leaves)
+            .unwrap();
+        world
+            .set_block_and_fetch(12, 64, 12, "overworld", block("oak_log"))
+            .unwrap();
+        for y in 65..68 {
+            world
+                .set_block_and_fetch(12, y, 12, "overworld", leaves)
+                .unwrap();
+        }
+
+        world.set_game_rule(RANDOM_TICK_SPEED, 0).unwrap();
+        tick(&world, 20);
+        assert_eq!(get(1, 64, 1), wheat);
+        assert_eq!(get(8, 64, 8), leaves);
+
+        // Every block in the section gets picked most ticks at this speed
+        world.set_game_rule(RANDOM_TICK_SPEED, 4096).unwrap();
+        tick(&world, 20);
+        assert!(get(1, 64, 1).get::<Age>().unwrap().0 > 0);
+        assert_eq!(get(8, 64, 8), BlockId::default());
+        assert_eq!(get(8, 64, 11), placed_leaves);
+        // Still connected to the log through the leaves below
+        assert_eq!(get(12, 67, 12).get::<Distance>(), Some(Distance(3)));
+
+        // Without the log the distance counts up to where the leaves decay
+        world
+            .set_block_and_fetch(12, 64, 12, "overworld", BlockId::default())
+            .unwrap();
+        tick(&world, 20);
+        for y in 65..68 {
+            assert_eq!(get(12, y, 12), BlockId::default());
+        }
+
+        // Rules are saved with the world
+        let reopened = World::with_backend(backend.clone()).unwrap();
+        assert_eq!(reopened.game_rules().random_tick_speed(), 4096);
+    }
+}
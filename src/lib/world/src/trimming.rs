@@ -0,0 +1,311 @@
+//! Shrinking a world by deleting the chunks it doesn't need.
+//!
+//! [`World::trim`] deletes every chunk outside an area to keep, every chunk nobody has changed
+//! since it was generated, or both at once, in which case only untouched chunks outside the area
+//! go. Whether a chunk was changed comes from [`Chunk::last_modified`](crate::chunk_format::Chunk),
+//! which is stamped whenever a player edits it. Deleting chunks only frees pages inside the LMDB
+//! file, so [`World::compact`] rewrites the file afterwards to actually give the space back.
+
+use crate::backups::LOCK_FILE;
+use crate::codec::decode_chunk;
+use crate::db_functions::parse_key;
+use crate::errors::WorldError;
+use crate::World;
+use ferrumc_storage::errors::StorageError;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use tracing::{error, info};
+
+/// The part of a dimension to keep, in chunk coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrimArea {
+    /// Every chunk within `radius` chunks of a center chunk.
+    Radius {
+        center_x: i32,
+        center_z: i32,
+        radius: u32,
+    },
+    /// Every chunk whose center is inside a polygon, given by its corners in order.
+    Polygon(Vec<(i32, i32)>),
+}
+
+impl TrimArea {
+    /// Check if a chunk is inside the area.
+    pub fn contains(&self, x: i32, z: i32) -> bool {
+        match self {
+            TrimArea::Radius {
+                center_x,
+                center_z,
+                radius,
+            } => {
+                let dx = x as i64 - *center_x as i64;
+                let dz = z as i64 - *center_z as i64;
+                dx * dx + dz * dz <= *radius as i64 * *radius as i64
+            }
+            TrimArea::Polygon(corners) => {
+                // Cast a ray from the center of the chunk and count how many edges it crosses
+                let (px, pz) = (x as f64 + 0.5, z as f64 + 0.5);
+                let mut inside = false;
+                let mut previous = corners.len() - 1;
+                for current in 0..corners.len() {
+                    let (ax, az) = (corners[current].0 as f64, corners[current].1 as f64);
+                    let (bx, bz) = (corners[previous].0 as f64, corners[previous].1 as f64);
+                    if (az > pz) != (bz > pz) && px < (bx - ax) * (pz - az) / (bz - az) + ax {
+                        inside = !inside;
+                    }
+                    previous = current;
+                }
+                inside
+            }
+        }
+    }
+}
+
+/// What to trim.
+#[derive(Debug, Clone, Default)]
+pub struct TrimOptions {
+    /// Only trim this dimension. Every dimension is trimmed if this isn't set.
+    pub dimension: Option<String>,
+    /// Delete chunks outside this area.
+    pub keep: Option<TrimArea>,
+    /// Only delete chunks nobody has changed since they were generated.
+    pub unmodified_only: bool,
+    /// Work out what would be deleted, without deleting anything.
+    pub dry_run: bool,
+}
+
+/// What [`World::trim`] deleted, or would have deleted on a dry run.
+#[derive(Debug, Clone, Default)]
+pub struct TrimReport {
+    /// How many chunks were looked at.
+    pub checked: usize,
+    /// How many chunks were deleted in each dimension.
+    pub removed: BTreeMap<String, usize>,
+    /// How much space the deleted chunks took up in the database, compressed.
+    pub removed_bytes: u64,
+}
+
+impl TrimReport {
+    pub fn total_removed(&self) -> usize {
+        self.removed.values().sum()
+    }
+}
+
+impl World {
+    /// Delete the chunks picked by `options`, `batch_size` at a time.
+    ///
+    /// This is meant to be run while the server is offline. Chunks that can't be decoded are
+    /// never deleted for being unmodified, since there's no way to tell if they were. Run
+    /// [`World::compact`] afterwards to shrink the database file.
+    pub fn trim(&self, options: &TrimOptions, batch_size: usize) -> Result<TrimReport, WorldError> {
+        if options.keep.is_none() && !options.unmodified_only {
+            return Err(WorldError::TrimError(
+                "Give an area to keep or only trim unmodified chunks, otherwise everything would \
+                be deleted"
+                    .to_string(),
+            ));
+        }
+        if let Some(TrimArea::Polygon(corners)) = &options.keep {
+            if corners.len() < 3 {
+                return Err(WorldError::TrimError(format!(
+                    "A polygon needs at least 3 corners, got {}",
+                    corners.len()
+                )));
+            }
+        }
+        let dimension = match &options.dimension {
+            Some(name) => Some(self.dimensions.id(name)?),
+            None => None,
+        };
+
+        // Unsaved changes could mark a chunk as modified, so get them into the table first
+        self.sync()?;
+        let mut report = TrimReport::default();
+        if !self.storage_backend.table_exists("chunks".to_string())? {
+            info!("No chunks to trim.");
+            return Ok(report);
+        }
+
+        let keys: Vec<u128> = self
+            .storage_backend
+            .get_keys("chunks".to_string())?
+            .into_iter()
+            .filter(|key| {
+                let (id, x, z) = parse_key(*key);
+                // Chunks in dimensions that aren't registered any more are left alone
+                self.dimensions.by_id(id).is_some()
+                    && dimension.is_none_or(|dimension| dimension == id)
+                    && options
+                        .keep
+                        .as_ref()
+                        .is_none_or(|keep| !keep.contains(x, z))
+            })
+            .collect();
+        report.checked = keys.len();
+
+        let progress_style = ProgressStyle::default_bar()
+            .template("[{elapsed_precise}/{eta_precise} eta] {bar:40.cyan/blue} {percent}%, {pos:>7}/{len:7}, {per_sec}, {msg}")
+            .unwrap();
+        let progress = ProgressBar::new(keys.len() as u64);
+        progress.set_style(progress_style);
+
+        for batch in keys.chunks(batch_size.max(1)) {
+            let records = self
+                .storage_backend
+                .batch_get("chunks".to_string(), batch.to_vec())?;
+            let doomed: Vec<(u128, u64)> = batch
+                .par_iter()
+                .zip(records.par_iter())
+                .filter_map(|(key, record)| {
+                    let record = record.as_ref()?;
+                    if options.unmodified_only {
+                        match decode_chunk(record, self.verify_chunk_data) {
+                            Ok(chunk) if chunk.last_modified != 0 => return None,
+                            Ok(_) => {}
+                            Err(e) => {
+                                error!("Failed to decode chunk with key {:X}: {}", key, e);
+                                return None;
+                            }
+                        }
+                    }
+                    Some((*key, record.len() as u64))
+                })
+                .collect();
+
+            for (key, size) in doomed {
+                let (id, x, z) = parse_key(key);
+                let Some(dimension) = self.dimensions.by_id(id) else {
+                    continue;
+                };
+                if !options.dry_run {
+                    self.delete_chunk(x, z, &dimension.name)?;
+                }
+                *report.removed.entry(dimension.name).or_default() += 1;
+                report.removed_bytes += size;
+            }
+            progress.inc(batch.len() as u64);
+        }
+
+        if !options.dry_run {
+            self.sync()?;
+        }
+        progress.finish();
+
+        Ok(report)
+    }
+
+    /// Rewrite the database file without the space freed by deleted chunks, and close the world.
+    ///
+    /// A compacted copy is written next to the database and then swapped in, so the world is
+    /// consumed to make sure nothing writes to the old file afterwards. Only worlds opened with
+    /// [`World::new`] on a backend that can be backed up support this. Returns the size of the
+    /// file before and after, in bytes.
+    pub fn compact(self) -> Result<(u64, u64), WorldError> {
+        let (Some(data_file), Some(db_path)) =
+            (self.storage_backend.data_file(), self.db_path.clone())
+        else {
+            return Err(StorageError::Unsupported(format!(
+                "Compacting is not supported by {}",
+                self.storage_backend.details()
+            ))
+            .into());
+        };
+        self.sync()?;
+        let data_path = db_path.join(data_file);
+        let before = std::fs::metadata(&data_path)?.len();
+
+        let staging_dir = db_path.join(".compact");
+        if staging_dir.exists() {
+            std::fs::remove_dir_all(&staging_dir)?;
+        }
+        std::fs::create_dir_all(&staging_dir)?;
+        let staged_data = staging_dir.join(data_file);
+        if let Err(e) = self.storage_backend.backup(&staged_data, true) {
+            let _ = std::fs::remove_dir_all(&staging_dir);
+            return Err(e.into());
+        }
+        drop(self);
+
+        let after = std::fs::metadata(&staged_data)?.len();
+        std::fs::rename(&staged_data, &data_path)?;
+        // The lock file describes the old data file's readers, LMDB recreates it on open
+        let lock_path = db_path.join(LOCK_FILE);
+        if lock_path.exists() {
+            std::fs::remove_file(lock_path)?;
+        }
+        std::fs::remove_dir_all(&staging_dir)?;
+
+        Ok((before, after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_format::Chunk;
+    use crate::testing::memory_world;
+    use ferrumc_storage::backend::StorageBackend;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_radius_is_round() {
+        let area = TrimArea::Radius {
+            center_x: 10,
+            center_z: -10,
+            radius: 5,
+        };
+        assert!(area.contains(10, -10));
+        assert!(area.contains(15, -10));
+        assert!(area.contains(13, -6));
+        assert!(!area.contains(14, -6));
+        assert!(!area.contains(16, -10));
+    }
+
+    #[test]
+    fn test_polygon() {
+        // An L shape
+        let area = TrimArea::Polygon(vec![(0, 0), (10, 0), (10, 4), (4, 4), (4, 10), (0, 10)]);
+        assert!(area.contains(0, 0));
+        assert!(area.contains(9, 3));
+        assert!(area.contains(2, 9));
+        assert!(!area.contains(6, 6));
+        assert!(!area.contains(10, 0));
+        assert!(!area.contains(-1, 5));
+    }
+
+    #[test]
+    fn test_trimming_keeps_modified_chunks_and_the_area() {
+        let (world, backend) = memory_world([]);
+        for x in 0..4 {
+            let mut chunk = Chunk::new(x, 0, "overworld".to_string());
+            if x == 3 {
+                chunk.mark_modified();
+            }
+            world.save_chunk(Arc::new(chunk)).unwrap();
+        }
+
+        let mut options = TrimOptions {
+            keep: Some(TrimArea::Radius {
+                center_x: 0,
+                center_z: 0,
+                radius: 1,
+            }),
+            unmodified_only: true,
+            dry_run: true,
+            ..Default::default()
+        };
+        let report = world.trim(&options, 2).unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.removed.get("overworld"), Some(&1));
+        assert!(world.chunk_exists(2, 0, "overworld").unwrap());
+
+        options.dry_run = false;
+        assert_eq!(world.trim(&options, 2).unwrap().total_removed(), 1);
+        assert!(!world.chunk_exists(2, 0, "overworld").unwrap());
+        for x in [0, 1, 3] {
+            assert!(world.chunk_exists(x, 0, "overworld").unwrap());
+        }
+        assert_eq!(backend.get_keys("chunks".to_string()).unwrap().len(), 3);
+    }
+}
@@ -1,6 +1,7 @@
 use bitcode::{Decode, Encode};
 use ferrumc_macros::NBTDeserialize;
 use ferrumc_macros::NBTSerialize;
+use ferrumc_nbt::{FromNbt, NBTSerializable, NBTSerializeOptions, NbtTape, NbtTapeElement};
 use macro_rules_attribute::{apply, attribute_alias};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -45,18 +46,19 @@ pub(crate) struct VanillaChunk {
     #[nbt(rename = "LastUpdate")]
     pub last_update: Option<i64>,
     pub sections: Option<Vec<Section>>,
+    pub block_entities: Option<Vec<VanillaBlockEntity>>,
 }
 
 #[apply(ChunkDerives)]
 #[derive(deepsize::DeepSizeOf)]
 #[nbt(net_encode)]
 pub(crate) struct VanillaHeightmaps {
-    // #[nbt(rename = "MOTION_BLOCKING_NO_LEAVES")]
-    // pub motion_blocking_no_leaves: Option<Vec<i64>>,
+    #[nbt(rename = "MOTION_BLOCKING_NO_LEAVES")]
+    pub motion_blocking_no_leaves: Option<Vec<i64>>,
     #[nbt(rename = "MOTION_BLOCKING")]
     pub motion_blocking: Option<Vec<i64>>,
-    // #[nbt(rename = "OCEAN_FLOOR")]
-    // pub ocean_floor: Option<Vec<i64>>,
+    #[nbt(rename = "OCEAN_FLOOR")]
+    pub ocean_floor: Option<Vec<i64>>,
     #[nbt(rename = "WORLD_SURFACE")]
     pub world_surface: Option<Vec<i64>>,
 }
@@ -128,3 +130,93 @@ pub(crate) struct Biomes {
     pub data: Option<Vec<i64>>,
     pub palette: Vec<String>,
 }
+
+/// A block entity as stored in a vanilla chunk.
+///
+/// The NBT derives can't handle arbitrary compounds, so this is (de)serialized by hand. `data` is
+/// the body of the compound without the position and id, and without the trailing end tag.
+#[derive(
+    Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize, deepsize::DeepSizeOf,
+)]
+pub(crate) struct VanillaBlockEntity {
+    pub id: String,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub data: Vec<u8>,
+}
+
+impl NBTSerializable for VanillaBlockEntity {
+    fn serialize(&self, buf: &mut Vec<u8>, options: &NBTSerializeOptions<'_>) {
+        match options {
+            NBTSerializeOptions::WithHeader(name) => {
+                Self::id().serialize(buf, &NBTSerializeOptions::None);
+                name.serialize(buf, &NBTSerializeOptions::None);
+            }
+            NBTSerializeOptions::Network => {
+                Self::id().serialize(buf, &NBTSerializeOptions::None);
+            }
+            NBTSerializeOptions::None | NBTSerializeOptions::Flatten => {}
+        }
+
+        self.id
+            .serialize(buf, &NBTSerializeOptions::WithHeader("id"));
+        self.x.serialize(buf, &NBTSerializeOptions::WithHeader("x"));
+        self.y.serialize(buf, &NBTSerializeOptions::WithHeader("y"));
+        self.z.serialize(buf, &NBTSerializeOptions::WithHeader("z"));
+        buf.extend_from_slice(&self.data);
+
+        if options != &NBTSerializeOptions::Flatten {
+            0u8.serialize(buf, &NBTSerializeOptions::None);
+        }
+    }
+
+    fn id() -> u8 {
+        10
+    }
+}
+
+impl<'a> FromNbt<'a> for VanillaBlockEntity {
+    fn from_nbt(tapes: &NbtTape<'a>, element: &NbtTapeElement<'a>) -> ferrumc_nbt::Result<Self> {
+        // One broken block entity would make the whole list it's in fail to read, so anything
+        // missing is just defaulted instead.
+        let get_int = |name: &str| {
+            element
+                .get(name)
+                .and_then(|e| i32::from_nbt(tapes, e).ok())
+                .unwrap_or_default()
+        };
+        Ok(VanillaBlockEntity {
+            id: element
+                .get("id")
+                .and_then(|e| String::from_nbt(tapes, e).ok())
+                .unwrap_or_default(),
+            x: get_int("x"),
+            y: get_int("y"),
+            z: get_int("z"),
+            // `keepPacked` only marks block entities in chunks that weren't done generating
+            data: compound_without(tapes, element, &["id", "x", "y", "z", "keepPacked"]),
+        })
+    }
+}
+
+/// Serialize the tags of a compound, leaving out the ones given.
+pub(crate) fn compound_without(tape: &NbtTape, element: &NbtTapeElement, skip: &[&str]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (name, value) in element.as_compound().into_iter().flatten() {
+        if !skip.contains(name) {
+            data.extend(tape.serialize_element(value, &NBTSerializeOptions::WithHeader(name)));
+        }
+    }
+    data
+}
+
+/// Wrap the tags of a compound back up, the way block entities are kept in chunks.
+pub(crate) fn root_compound(data: &[u8]) -> Vec<u8> {
+    let mut nbt = Vec::with_capacity(data.len() + 4);
+    nbt.push(10);
+    "".serialize(&mut nbt, &NBTSerializeOptions::None);
+    nbt.extend_from_slice(data);
+    nbt.push(0);
+    nbt
+}
@@ -0,0 +1,125 @@
+//! The parts of a vanilla save other than the terrain: entities, `level.dat` and player data.
+//!
+//! Everything here is optional, since these files gained and lost fields a lot between versions
+//! and a missing field is better off defaulted than failing the whole import. Only the fields the
+//! importer actually uses are read.
+
+use ferrumc_macros::NBTDeserialize;
+
+/// A chunk from a region file in the `entities` folder.
+#[derive(NBTDeserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct VanillaEntityChunk {
+    /// The chunk coordinates, as `[x, z]`.
+    #[nbt(rename = "Position")]
+    pub position: Option<Vec<i32>>,
+    #[nbt(rename = "Entities")]
+    pub entities: Option<Vec<VanillaEntity>>,
+}
+
+#[derive(NBTDeserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct VanillaEntity {
+    pub id: Option<String>,
+    /// The four parts of the UUID, most significant first.
+    #[nbt(rename = "UUID")]
+    pub uuid: Option<Vec<i32>>,
+    #[nbt(rename = "Pos")]
+    pub pos: Option<Vec<f64>>,
+    #[nbt(rename = "Motion")]
+    pub motion: Option<Vec<f64>>,
+    /// Yaw and pitch.
+    #[nbt(rename = "Rotation")]
+    pub rotation: Option<Vec<f32>>,
+    /// The item an item entity is carrying.
+    #[nbt(rename = "Item")]
+    pub item: Option<VanillaItem>,
+}
+
+/// An item stack, either in an inventory or carried by an item entity.
+#[derive(NBTDeserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct VanillaItem {
+    pub id: Option<String>,
+    /// Only set for items in an inventory.
+    #[nbt(rename = "Slot")]
+    pub slot: Option<i8>,
+    /// How many items there are, up to 1.20.4.
+    #[nbt(rename = "Count")]
+    pub legacy_count: Option<i8>,
+    /// How many items there are, from 1.20.5 on.
+    pub count: Option<i32>,
+}
+
+impl VanillaItem {
+    pub fn count(&self) -> u8 {
+        match (self.count, self.legacy_count) {
+            (Some(count), _) => count.clamp(0, u8::MAX as i32) as u8,
+            (None, Some(count)) => count.max(0) as u8,
+            // Newer versions leave the count out when it's 1
+            (None, None) => 1,
+        }
+    }
+}
+
+/// The root of `level.dat`.
+#[derive(NBTDeserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct VanillaLevel {
+    #[nbt(rename = "Data")]
+    pub data: Option<VanillaLevelData>,
+}
+
+#[derive(NBTDeserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct VanillaLevelData {
+    #[nbt(rename = "SpawnX")]
+    pub spawn_x: Option<i32>,
+    #[nbt(rename = "SpawnY")]
+    pub spawn_y: Option<i32>,
+    #[nbt(rename = "SpawnZ")]
+    pub spawn_z: Option<i32>,
+    /// Ticks since the world was created.
+    #[nbt(rename = "Time")]
+    pub time: Option<i64>,
+    /// The time of day, in ticks. Doesn't wrap around at the end of a day.
+    #[nbt(rename = "DayTime")]
+    pub day_time: Option<i64>,
+    /// Where the seed is kept from 1.16 on.
+    #[nbt(rename = "WorldGenSettings")]
+    pub world_gen_settings: Option<VanillaWorldGenSettings>,
+    /// Where the seed was kept before 1.16.
+    #[nbt(rename = "RandomSeed")]
+    pub random_seed: Option<i64>,
+    #[nbt(rename = "GameRules")]
+    pub game_rules: Option<VanillaGameRules>,
+}
+
+/// The game rules the server knows about. Their values are all strings, whatever their type.
+#[derive(NBTDeserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct VanillaGameRules {
+    #[nbt(rename = "randomTickSpeed")]
+    pub random_tick_speed: Option<String>,
+}
+
+#[derive(NBTDeserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct VanillaWorldGenSettings {
+    pub seed: Option<i64>,
+}
+
+/// A player's file from the `playerdata` folder.
+#[derive(NBTDeserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct VanillaPlayer {
+    #[nbt(rename = "Pos")]
+    pub pos: Option<Vec<f64>>,
+    #[nbt(rename = "Dimension")]
+    pub dimension: Option<String>,
+    #[nbt(rename = "Inventory")]
+    pub inventory: Option<Vec<VanillaItem>>,
+    /// Armor and the offhand, which moved out of the inventory list in 1.21.5.
+    pub equipment: Option<VanillaEquipment>,
+}
+
+#[derive(NBTDeserialize, Debug, Clone, PartialEq, Default)]
+pub(crate) struct VanillaEquipment {
+    pub head: Option<VanillaItem>,
+    pub chest: Option<VanillaItem>,
+    pub legs: Option<VanillaItem>,
+    pub feet: Option<VanillaItem>,
+    pub offhand: Option<VanillaItem>,
+}
@@ -0,0 +1,418 @@
+//! Write-behind persistence for chunks.
+//!
+//! Saving a chunk only marks it dirty. A background worker then writes the dirty chunks out in
+//! batches, at most `flush_interval` milliseconds after they were changed, or sooner once
+//! `flush_batch_size` of them have piled up. Repeated edits to the same chunk in between are
+//! coalesced into a single write.
+//!
+//! Dirty chunks are kept here until they're written, even if the cache evicts them in the
+//! meantime, so loads check here before going to the storage backend and never see stale data.
+//! Dropping the last handle to a world stops the worker and writes whatever is left.
+//!
+//! When writing a batch fails, its chunks are written one at a time instead, so one chunk that
+//! can't be written doesn't hold back the rest. The ones that still fail are set aside in a
+//! quarantine and logged. They can still be loaded from there, and are retried with a growing
+//! backoff while the other chunks keep being written as usual. Once a flush has left chunks in
+//! quarantine [`MAX_FLUSH_RETRIES`] times in a row, the error is handed back from
+//! [`World::save_chunk`](crate::World::save_chunk) until they're written.
+
+use crate::chunk_format::Chunk;
+use crate::db_functions::save_chunk_internal_batch;
+use crate::dimensions::DimensionRegistry;
+use crate::errors::WorldError;
+use ferrumc_storage::backend::StorageBackend;
+use ferrumc_storage::compressors::Compressor;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::{error, info, trace, warn};
+
+type ChunkKey = (i32, i32, String);
+
+/// How long to wait after the first failed flush, doubled after every failure after that.
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+/// Failed flushes in a row before the error is reported to whoever saves chunks.
+pub(crate) const MAX_FLUSH_RETRIES: u32 = 5;
+
+/// Chunks that have changed since they were last written to the storage backend.
+pub(crate) struct DirtyChunks {
+    chunks: Mutex<HashMap<ChunkKey, Arc<Chunk>>>,
+    /// Chunks that failed to be written on their own, kept apart so they aren't in every batch.
+    quarantine: Mutex<HashMap<ChunkKey, Arc<Chunk>>>,
+    wake: Condvar,
+    closed: AtomicBool,
+    batch_size: usize,
+    /// The last error, once flushes have left chunks in quarantine [`MAX_FLUSH_RETRIES`] times
+    /// in a row.
+    failed: Mutex<Option<String>>,
+    /// Held while writing, so two flushes can't write the same chunk out of order, and so a
+    /// chunk being deleted can't get written back by a flush that started before the delete.
+    pub(crate) flush_lock: Mutex<()>,
+}
+
+impl DirtyChunks {
+    pub(crate) fn new(batch_size: usize) -> Self {
+        DirtyChunks {
+            chunks: Mutex::new(HashMap::new()),
+            quarantine: Mutex::new(HashMap::new()),
+            wake: Condvar::new(),
+            closed: AtomicBool::new(false),
+            batch_size: batch_size.max(1),
+            failed: Mutex::new(None),
+            flush_lock: Mutex::new(()),
+        }
+    }
+
+    /// Mark a chunk as changed, replacing any older unsaved version of it.
+    pub(crate) fn mark(&self, chunk: Arc<Chunk>) {
+        let key = (chunk.x, chunk.z, chunk.dimension.clone());
+        let mut chunks = self.chunks.lock().unwrap();
+        // The new version gets a fresh try, whatever was wrong with the old one
+        self.quarantine.lock().unwrap().remove(&key);
+        chunks.insert(key, chunk);
+        if chunks.len() >= self.batch_size {
+            self.wake.notify_one();
+        }
+    }
+
+    /// Get the unsaved version of a chunk, if there is one.
+    pub(crate) fn get(&self, x: i32, z: i32, dimension: &str) -> Option<Arc<Chunk>> {
+        let key = (x, z, dimension.to_string());
+        let chunks = self.chunks.lock().unwrap();
+        chunks
+            .get(&key)
+            .or_else(|| self.quarantine.lock().unwrap().get(&key))
+            .cloned()
+    }
+
+    pub(crate) fn contains(&self, key: &ChunkKey) -> bool {
+        let chunks = self.chunks.lock().unwrap();
+        chunks.contains_key(key) || self.quarantine.lock().unwrap().contains_key(key)
+    }
+
+    /// Forget about the unsaved version of a chunk. Hold [`Self::flush_lock`] while doing this
+    /// and deleting the chunk.
+    pub(crate) fn remove(&self, x: i32, z: i32, dimension: &str) {
+        let key = (x, z, dimension.to_string());
+        let mut chunks = self.chunks.lock().unwrap();
+        chunks.remove(&key);
+        self.quarantine.lock().unwrap().remove(&key);
+    }
+
+    /// Fails with [`WorldError::FlushError`] if the worker gave up retrying its last flush.
+    pub(crate) fn check_failed(&self) -> Result<(), WorldError> {
+        match self.failed.lock().unwrap().as_ref() {
+            Some(e) => Err(WorldError::FlushError(e.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the worker to flush as soon as possible.
+    pub(crate) fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Write every dirty chunk to the storage backend in one batch, and retry the quarantined
+    /// ones.
+    ///
+    /// If the batch fails its chunks are written one at a time, and the ones that still fail are
+    /// quarantined. Returns how many chunks were written, or an error if any are left in
+    /// quarantine.
+    pub(crate) fn flush(
+        &self,
+        storage_backend: &dyn StorageBackend,
+        dimensions: &DimensionRegistry,
+        compressor: &Compressor,
+    ) -> Result<usize, WorldError> {
+        let _flushing = self.flush_lock.lock().unwrap();
+        let save = |chunks: &[Arc<Chunk>]| {
+            save_chunk_internal_batch(storage_backend, dimensions, compressor, chunks)
+        };
+        let mut written = 0;
+
+        let quarantined = snapshot(&self.quarantine);
+        for (key, chunk) in &quarantined {
+            if save(std::slice::from_ref(chunk)).is_ok() {
+                info!("Saved chunk {}, {} in {} after all", key.0, key.1, key.2);
+                forget(&self.quarantine, key, chunk);
+                written += 1;
+            }
+        }
+
+        // Chunks stay in the map while they're written, so they can still be loaded from here
+        let pending = snapshot(&self.chunks);
+        let chunks: Vec<Arc<Chunk>> = pending.iter().map(|(_, chunk)| chunk.clone()).collect();
+        if !chunks.is_empty() && save(&chunks).is_ok() {
+            for (key, chunk) in &pending {
+                forget(&self.chunks, key, chunk);
+            }
+            written += pending.len();
+        } else {
+            // Find out which chunks are to blame, and write all the others
+            for (key, chunk) in &pending {
+                match save(std::slice::from_ref(chunk)) {
+                    Ok(()) => {
+                        forget(&self.chunks, key, chunk);
+                        written += 1;
+                    }
+                    Err(e) => {
+                        error!(
+                            "Failed to save chunk {}, {} in {}, setting it aside: {}",
+                            key.0, key.1, key.2, e
+                        );
+                        let mut dirty = self.chunks.lock().unwrap();
+                        // A newer version that came in meanwhile gets its own try
+                        if dirty
+                            .get(key)
+                            .is_some_and(|current| Arc::ptr_eq(current, chunk))
+                        {
+                            dirty.remove(key);
+                            self.quarantine
+                                .lock()
+                                .unwrap()
+                                .insert(key.clone(), chunk.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        let quarantined = self.quarantine.lock().unwrap().len();
+        if quarantined > 0 {
+            return Err(WorldError::FlushError(format!(
+                "{quarantined} chunks can't be saved, see the errors above for why"
+            )));
+        }
+        Ok(written)
+    }
+
+    /// Wait until it's time to flush again. Returns true once the world has been dropped.
+    ///
+    /// After `failures` failed flushes in a row this backs off instead, ignoring full batches
+    /// that would only fail again straight away.
+    fn wait(&self, interval: Duration, failures: u32) -> bool {
+        if failures > 0 {
+            let backoff = RETRY_BACKOFF.saturating_mul(1 << (failures - 1).min(16));
+            std::thread::sleep(backoff.min(interval));
+            return self.closed.load(Ordering::Acquire);
+        }
+        let chunks = self.chunks.lock().unwrap();
+        if self.closed.load(Ordering::Acquire) || chunks.len() >= self.batch_size {
+            return self.closed.load(Ordering::Acquire);
+        }
+        let _ = self.wake.wait_timeout(chunks, interval).unwrap();
+        self.closed.load(Ordering::Acquire)
+    }
+}
+
+/// Copy what's in a map of chunks, so it can be written without holding the lock.
+fn snapshot(chunks: &Mutex<HashMap<ChunkKey, Arc<Chunk>>>) -> Vec<(ChunkKey, Arc<Chunk>)> {
+    chunks
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(key, chunk)| (key.clone(), chunk.clone()))
+        .collect()
+}
+
+/// Take a chunk that was written out of a map, unless it changed again while being written.
+fn forget(chunks: &Mutex<HashMap<ChunkKey, Arc<Chunk>>>, key: &ChunkKey, chunk: &Arc<Chunk>) {
+    let mut chunks = chunks.lock().unwrap();
+    if chunks
+        .get(key)
+        .is_some_and(|current| Arc::ptr_eq(current, chunk))
+    {
+        chunks.remove(key);
+    }
+}
+
+/// Owns the background worker, and stops it when the last handle to the world is dropped.
+pub(crate) struct WriteBehindWorker {
+    dirty: Arc<DirtyChunks>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl WriteBehindWorker {
+    pub(crate) fn spawn(
+        dirty: Arc<DirtyChunks>,
+        storage_backend: Arc<dyn StorageBackend>,
+        dimensions: Arc<DimensionRegistry>,
+        compressor: Compressor,
+        interval: Duration,
+    ) -> Self {
+        let handle = std::thread::Builder::new()
+            .name("chunk-writer".to_string())
+            .spawn({
+                let dirty = dirty.clone();
+                move || {
+                    let mut failures = 0;
+                    loop {
+                        let closed = dirty.wait(interval, failures.min(MAX_FLUSH_RETRIES));
+                        match dirty.flush(storage_backend.as_ref(), &dimensions, &compressor) {
+                            Ok(count) => {
+                                if count > 0 {
+                                    trace!("Saved {} dirty chunks", count);
+                                }
+                                failures = 0;
+                                *dirty.failed.lock().unwrap() = None;
+                            }
+                            Err(e) => {
+                                failures += 1;
+                                if failures < MAX_FLUSH_RETRIES && !closed {
+                                    warn!("Failed to save dirty chunks, retrying: {}", e);
+                                    continue;
+                                }
+                                error!(
+                                    "Failed to save dirty chunks {} times in a row: {}",
+                                    failures, e
+                                );
+                                *dirty.failed.lock().unwrap() = Some(e.to_string());
+                            }
+                        }
+                        if closed {
+                            for (x, z, dimension) in dirty.quarantine.lock().unwrap().keys() {
+                                error!("Chunk {}, {} in {} was never saved", x, z, dimension);
+                            }
+                            break;
+                        }
+                    }
+                }
+            })
+            .expect("Failed to spawn chunk writer thread");
+        WriteBehindWorker {
+            dirty,
+            handle: Mutex::new(Some(handle)),
+        }
+    }
+}
+
+impl Drop for WriteBehindWorker {
+    fn drop(&mut self) {
+        self.dirty.closed.store(true, Ordering::Release);
+        // Take the lock so the worker can't miss the wakeup between checking and waiting
+        drop(self.dirty.chunks.lock().unwrap());
+        self.dirty.wake();
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            if handle.join().is_err() {
+                error!("Chunk writer thread panicked, some chunks may not have been saved");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrumc_storage::compressors::CompressorType;
+    use ferrumc_storage::memory::MemoryBackend;
+
+    fn chunk(x: i32) -> Arc<Chunk> {
+        Arc::new(Chunk::new(x, 0, "overworld".to_string()))
+    }
+
+    #[test]
+    fn test_coalesces_edits() {
+        let backend = MemoryBackend::new();
+        let dimensions = DimensionRegistry::load(&backend).unwrap();
+        let compressor = Compressor::create(CompressorType::Zlib, 1);
+        let dirty = DirtyChunks::new(100);
+        dirty.mark(chunk(0));
+        dirty.mark(chunk(0));
+        dirty.mark(chunk(1));
+        assert_eq!(dirty.chunks.lock().unwrap().len(), 2);
+
+        assert_eq!(dirty.flush(&backend, &dimensions, &compressor).unwrap(), 2);
+        assert_eq!(dirty.chunks.lock().unwrap().len(), 0);
+        assert_eq!(backend.get_keys("chunks".to_string()).unwrap().len(), 2);
+        assert_eq!(dirty.flush(&backend, &dimensions, &compressor).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_worker_flushes_on_drop() {
+        let backend = Arc::new(MemoryBackend::new());
+        let dirty = Arc::new(DirtyChunks::new(100));
+        let worker = WriteBehindWorker::spawn(
+            dirty.clone(),
+            backend.clone(),
+            Arc::new(DimensionRegistry::load(backend.as_ref()).unwrap()),
+            Compressor::create(CompressorType::Zlib, 1),
+            Duration::from_secs(3600),
+        );
+        dirty.mark(chunk(0));
+        drop(worker);
+        assert_eq!(dirty.chunks.lock().unwrap().len(), 0);
+        assert!(backend.table_exists("chunks".to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_worker_flushes_full_batches_early() {
+        let backend = Arc::new(MemoryBackend::new());
+        let dirty = Arc::new(DirtyChunks::new(2));
+        let _worker = WriteBehindWorker::spawn(
+            dirty.clone(),
+            backend.clone(),
+            Arc::new(DimensionRegistry::load(backend.as_ref()).unwrap()),
+            Compressor::create(CompressorType::Zlib, 1),
+            Duration::from_secs(3600),
+        );
+        dirty.mark(chunk(0));
+        dirty.mark(chunk(1));
+        let start = std::time::Instant::now();
+        while dirty.chunks.lock().unwrap().len() != 0 {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_failed_chunks_dont_hold_back_the_rest() {
+        let backend = MemoryBackend::new();
+        let dimensions = DimensionRegistry::load(&backend).unwrap();
+        let compressor = Compressor::create(CompressorType::Zlib, 1);
+        let dirty = DirtyChunks::new(100);
+        dirty.mark(chunk(0));
+        dirty.mark(chunk(1));
+        // Chunks in dimensions that don't exist can never be written
+        dirty.mark(Arc::new(Chunk::new(0, 0, "nowhere".to_string())));
+
+        assert!(dirty.flush(&backend, &dimensions, &compressor).is_err());
+        assert_eq!(backend.get_keys("chunks".to_string()).unwrap().len(), 2);
+        assert_eq!(dirty.chunks.lock().unwrap().len(), 0);
+        assert_eq!(dirty.quarantine.lock().unwrap().len(), 1);
+        assert!(dirty.get(0, 0, "nowhere").is_some());
+
+        // Later chunks are written in one batch again, while the quarantined one keeps failing
+        dirty.mark(chunk(2));
+        assert!(dirty.flush(&backend, &dimensions, &compressor).is_err());
+        assert_eq!(backend.get_keys("chunks".to_string()).unwrap().len(), 3);
+
+        dirty.remove(0, 0, "nowhere");
+        assert!(!dirty.contains(&(0, 0, "nowhere".to_string())));
+        assert_eq!(dirty.flush(&backend, &dimensions, &compressor).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_worker_reports_repeated_failures() {
+        let backend = Arc::new(MemoryBackend::new());
+        let dirty = Arc::new(DirtyChunks::new(1));
+        let worker = WriteBehindWorker::spawn(
+            dirty.clone(),
+            backend.clone(),
+            Arc::new(DimensionRegistry::load(backend.as_ref()).unwrap()),
+            Compressor::create(CompressorType::Zlib, 1),
+            Duration::from_millis(10),
+        );
+        // Chunks in dimensions that don't exist can never be written
+        dirty.mark(Arc::new(Chunk::new(0, 0, "nowhere".to_string())));
+        let start = std::time::Instant::now();
+        while dirty.check_failed().is_ok() {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        dirty.remove(0, 0, "nowhere");
+        drop(worker);
+        assert!(dirty.check_failed().is_ok());
+    }
+}
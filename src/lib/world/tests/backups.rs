@@ -0,0 +1,37 @@
+mod common;
+use common::setup_world;
+
+use ferrumc_world::backups::{list_backups, restore};
+use ferrumc_world::chunk_format::Chunk;
+use ferrumc_world::World;
+use std::sync::Arc;
+
+#[test]
+fn backup_and_restore_round_trip() {
+    let world = setup_world();
+    let mut dir = std::env::temp_dir();
+    dir.push(format!(
+        "ferrumc_backup_round_trip_{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    let backup_dir = dir.join("backups");
+
+    let backup = world.backup(&backup_dir, true).unwrap();
+    assert_eq!(list_backups(&backup_dir).unwrap()[0].path, backup);
+
+    // Changes after the backup shouldn't survive the restore
+    world
+        .save_chunk(Arc::new(Chunk::new(5, 5, "overworld".to_string())))
+        .unwrap();
+
+    let restored_path = dir.join("restored");
+    restore(&backup, &restored_path).unwrap();
+    let restored = World::new(&restored_path).unwrap();
+    assert!(restored.chunk_exists(0, 0, "overworld").unwrap());
+    assert!(!restored.chunk_exists(5, 5, "overworld").unwrap());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
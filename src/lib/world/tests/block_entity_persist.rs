@@ -23,6 +23,7 @@ fn init_config() {
                 cache_ttl: 0,
                 cache_capacity: 0,
                 verify_chunk_data: false,
+                ..Default::default()
             },
             ..Default::default()
         };
@@ -41,7 +42,7 @@ fn chest_persist_reload() {
     path.push(format!("ferrumc_world_chest_{}", nanos));
     std::fs::create_dir_all(&path).unwrap();
 
-    let world = World::new(&path);
+    let world = World::new(&path).unwrap();
     let mut chunk = Chunk::new(0, 0, "overworld".to_string());
 
     let chest = ChestBlockEntity {
@@ -55,7 +56,7 @@ fn chest_persist_reload() {
     world.save_chunk(Arc::new(chunk)).unwrap();
     drop(world);
 
-    let world = World::new(&path);
+    let world = World::new(&path).unwrap();
     let chunk = world.load_chunk_owned(0, 0, "overworld").unwrap();
     let loaded: ChestBlockEntity = chunk.get_block_entity_data(0, 64, 0).expect("block entity");
     assert_eq!(loaded.items.len(), 1);
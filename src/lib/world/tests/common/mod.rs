@@ -18,6 +18,7 @@ pub fn setup_world() -> World {
                 cache_ttl: 0,
                 cache_capacity: 0,
                 verify_chunk_data: false,
+                ..Default::default()
             },
             ..Default::default()
         };
@@ -30,7 +31,7 @@ pub fn setup_world() -> World {
             .unwrap()
             .as_nanos()));
     std::fs::create_dir_all(&path).unwrap();
-    let world = World::new(&path);
+    let world = World::new(&path).unwrap();
     let chunk = Chunk::new(0, 0, "overworld".to_string());
     world.save_chunk(Arc::new(chunk)).unwrap();
     world
@@ -0,0 +1,35 @@
+use ferrumc_storage::backend::StorageBackend;
+use ferrumc_storage::memory::MemoryBackend;
+use ferrumc_world::chunk_format::Chunk;
+use ferrumc_world::errors::WorldError;
+use ferrumc_world::{World, WorldOptions};
+use std::sync::Arc;
+
+#[test]
+fn chunks_round_trip_without_touching_disk() {
+    let backend = Arc::new(MemoryBackend::new());
+    let world = World::with_backend(backend.clone()).unwrap();
+
+    let chunk = Chunk::new(3, -7, "overworld".to_string());
+    world.save_chunk(Arc::new(chunk.clone())).unwrap();
+    world.sync().unwrap();
+
+    // A fresh world on the same backend has nothing cached, so this has to go through storage
+    let reopened = World::with_backend(backend.clone()).unwrap();
+    assert!(reopened.chunk_exists(3, -7, "overworld").unwrap());
+    assert_eq!(*reopened.load_chunk(3, -7, "overworld").unwrap(), chunk);
+    assert!(!reopened.chunk_exists(0, 0, "overworld").unwrap());
+    assert_eq!(backend.get_keys("chunks".to_string()).unwrap().len(), 1);
+}
+
+#[test]
+fn mismatched_cache_options_are_rejected() {
+    let options = WorldOptions {
+        cache_ttl: 0,
+        ..Default::default()
+    };
+    assert!(matches!(
+        World::with_options(Arc::new(MemoryBackend::new()), options),
+        Err(WorldError::InvalidCacheSize(_))
+    ));
+}
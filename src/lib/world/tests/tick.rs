@@ -4,7 +4,7 @@ mod common;
 use common::setup_world;
 
 use ferrumc_world::block_id::BlockId;
-use ferrumc_world::tick::{BlockPos, ScheduledTick, TickManager};
+use ferrumc_world::tick::{priority, BlockPos, ScheduledTick, TickManager};
 use ferrumc_world::vanilla_chunk_format::BlockData;
 
 #[test]
@@ -68,13 +68,14 @@ fn cleanup_chunk_removes_entries() {
         pos: pos.clone(),
         block: BlockId::default(),
         delay: 0,
+        priority: priority::NORMAL,
     });
     tm.schedule_random(pos.clone(), 1.0);
     let key = (0, 0, "overworld".to_string());
-    assert!(tm.scheduled.contains_key(&key));
+    assert!(tm.has_scheduled(0, 0, "overworld"));
     assert!(tm.random.contains_key(&key));
     tm.cleanup_chunk(0, 0, "overworld");
-    assert!(!tm.scheduled.contains_key(&key));
+    assert!(!tm.has_scheduled(0, 0, "overworld"));
     assert!(!tm.random.contains_key(&key));
 }
 
@@ -97,19 +98,58 @@ fn cleanup_dimension_removes_all_matches() {
         pos: over.clone(),
         block: BlockId::default(),
         delay: 0,
+        priority: priority::NORMAL,
     });
     tm.schedule_random(over.clone(), 1.0);
     tm.schedule(ScheduledTick {
         pos: nether.clone(),
         block: BlockId::default(),
         delay: 0,
+        priority: priority::NORMAL,
     });
     tm.schedule_random(nether.clone(), 1.0);
     tm.cleanup_dimension("overworld");
     let key_over = (0, 0, "overworld".to_string());
     let key_nether = (0, 0, "nether".to_string());
-    assert!(!tm.scheduled.contains_key(&key_over));
+    assert!(!tm.has_scheduled(0, 0, "overworld"));
     assert!(!tm.random.contains_key(&key_over));
-    assert!(tm.scheduled.contains_key(&key_nether));
+    assert!(tm.has_scheduled(0, 0, "nether"));
     assert!(tm.random.contains_key(&key_nether));
 }
+
+#[test]
+fn ticks_leave_in_time_then_priority_order() {
+    let mut tm = TickManager::default();
+    let pos = |x: i32| BlockPos {
+        x,
+        y: 0,
+        z: 0,
+        dimension: "overworld".to_string(),
+    };
+    for (x, delay, priority) in [
+        (0, 5, priority::NORMAL),
+        (1, 2, priority::LOW),
+        (2, 2, priority::HIGH),
+        (3, 2, priority::HIGH),
+    ] {
+        tm.schedule(ScheduledTick {
+            pos: pos(x),
+            block: BlockId::default(),
+            delay,
+            priority,
+        });
+    }
+    let saved = tm.take_chunk(0, 0, "overworld");
+    let order: Vec<_> = saved
+        .iter()
+        .map(|tick| (tick.pos(0, 0).0, tick.delay))
+        .collect();
+    assert_eq!(order, [(2, 2), (3, 2), (1, 2), (0, 5)]);
+    assert!(!tm.has_scheduled(0, 0, "overworld"));
+
+    // Restoring them twice doesn't run anything twice
+    tm.restore(0, 0, "overworld", &saved);
+    tm.restore(0, 0, "overworld", &saved);
+    assert!(tm.is_scheduled(&pos(1)));
+    assert_eq!(tm.take_chunk(0, 0, "overworld"), saved);
+}
@@ -0,0 +1,46 @@
+use crate::WorldGenerator;
+use crate::end::EndGenerator;
+use crate::errors::WorldGenError;
+use crate::nether::NetherGenerator;
+use ferrumc_world::chunk_format::Chunk;
+use ferrumc_world::dimensions::Dimension;
+
+/// One generator of each kind, so chunks in any dimension are generated the same way no matter
+/// who asks for them.
+pub struct Generators {
+    overworld: WorldGenerator,
+    nether: NetherGenerator,
+    end: EndGenerator,
+}
+
+impl Generators {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            overworld: WorldGenerator::new(seed),
+            nether: NetherGenerator::new(seed),
+            end: EndGenerator::new(seed),
+        }
+    }
+
+    /// The overworld biome at a chunk.
+    pub fn biome_at(&self, x: i32, z: i32) -> u8 {
+        self.overworld.biome_at(x, z)
+    }
+
+    /// Generate a chunk of a dimension with the generator its type names. Generators that don't
+    /// exist fall back to the overworld one.
+    pub fn generate_chunk(
+        &self,
+        x: i32,
+        z: i32,
+        dimension: &Dimension,
+    ) -> Result<Chunk, WorldGenError> {
+        let mut chunk = match dimension.dimension_type.generator.as_str() {
+            "nether" => self.nether.generate_chunk(x, z),
+            "end" => self.end.generate_chunk(x, z),
+            _ => self.overworld.generate_chunk(x, z),
+        }?;
+        chunk.dimension = dimension.name.clone();
+        Ok(chunk)
+    }
+}
@@ -1,6 +1,7 @@
 mod biomes;
 pub mod end;
 pub mod errors;
+mod generators;
 pub mod nether;
 mod noise_settings;
 mod structures;
@@ -14,6 +15,8 @@ use noise_settings::{NoiseSettings, OVERWORLD_NOISE_SETTINGS};
 use std::collections::BTreeMap;
 use structures::{temple::Temple, village::Village, StructurePlacer};
 
+pub use generators::Generators;
+
 /// Trait for generating a biome
 ///
 /// Should be implemented for each biome's generator
@@ -233,6 +236,9 @@ impl WorldGenerator {
         self.apply_carvers(&mut chunk);
         self.apply_features(&mut chunk);
         self.apply_structures(&mut chunk);
+        // The light is left for the world to work out, since it depends on the dimension's type
+        // and on the chunks around this one, see World::light_new_chunk
+        chunk.light_dirty = true;
         Ok(chunk)
     }
 }
@@ -1,4 +1,6 @@
-use ferrumc_world_gen::WorldGenerator;
+use ferrumc_world::block_id::BlockId;
+use ferrumc_world::dimensions::{Dimension, DimensionType};
+use ferrumc_world_gen::{Generators, WorldGenerator};
 
 #[test]
 #[ignore]
@@ -7,3 +9,20 @@ fn snapshot_chunk() {
     let chunk = generator.generate_chunk(0, 0).unwrap();
     insta::assert_snapshot!(format!("{:?}", chunk));
 }
+
+#[test]
+fn generators_follow_the_dimension_type() {
+    let generators = Generators::new(0);
+    let caves = Dimension {
+        id: 2,
+        name: "caves".to_string(),
+        dimension_type: DimensionType::nether(),
+    };
+    let chunk = generators.generate_chunk(0, 0, &caves).unwrap();
+    assert_eq!(chunk.dimension, "caves");
+    let netherrack = BlockId::from_name("minecraft:netherrack").unwrap();
+    let column: Vec<_> = (0..128)
+        .filter_map(|y| chunk.get_block(0, y, 0).ok())
+        .collect();
+    assert!(column.contains(&netherrack));
+}
@@ -75,6 +75,9 @@ fn direct_palette_round_trip() {
         sections: vec![section],
         heightmaps: Heightmaps::default(),
         block_entities: vec![],
+        last_modified: 0,
+        pending_ticks: vec![],
+        light_dirty: false,
     };
 
     // Set a couple of blocks to non-zero ids
@@ -1,16 +1,75 @@
 use ferrumc_net::packets::outgoing::login_play::{GlobalPos, LoginPlayPacket};
+use ferrumc_net::packets::outgoing::registry_data::RegistryDataPacket;
 use ferrumc_net_codec::net_types::network_position::NetworkPosition;
+use ferrumc_net_codec::net_types::prefixed_optional::PrefixedOptional;
+use ferrumc_world::dimensions::{Dimension, DimensionType};
+
+const VANILLA: [&str; 3] = [
+    "minecraft:overworld",
+    "minecraft:the_end",
+    "minecraft:the_nether",
+];
 
 #[test]
 fn login_packet_has_no_death_location() {
-    let packet = LoginPlayPacket::new(1, 0, None);
+    let packet = LoginPlayPacket::new(1, &VANILLA, "minecraft:overworld", None).unwrap();
     assert!(packet.death_location.is_none());
     assert_eq!(packet.dimension_name, "minecraft:overworld");
+    assert_eq!(packet.dimension_type.0, 0);
 }
 
 #[test]
 fn respawn_packet_contains_death_location() {
     let death = GlobalPos::new("minecraft:overworld", NetworkPosition { x: 1, y: 2, z: 3 });
-    let packet = LoginPlayPacket::new(1, 0, Some(death));
+    let packet = LoginPlayPacket::new(1, &VANILLA, "minecraft:overworld", Some(death)).unwrap();
     assert!(packet.death_location.is_some());
 }
+
+#[test]
+fn login_packet_lists_custom_dimensions() {
+    let names = [
+        "minecraft:overworld",
+        "minecraft:the_end",
+        "minecraft:the_nether",
+        "ferrumc:lobby",
+    ];
+    let packet = LoginPlayPacket::in_dimension(1, &names, 3, None).unwrap();
+    assert_eq!(packet.dimension_length.0, 4);
+    assert_eq!(packet.dimension_type.0, 3);
+    assert_eq!(packet.dimension_name, "ferrumc:lobby");
+}
+
+#[test]
+fn login_packet_rejects_dimensions_that_werent_sent() {
+    assert!(LoginPlayPacket::in_dimension(1, &VANILLA, 3, None).is_err());
+    assert!(LoginPlayPacket::new(1, &VANILLA, "ferrumc:lobby", None).is_err());
+}
+
+#[test]
+fn dimension_types_are_sent_in_the_order_given() {
+    let lobby = Dimension {
+        id: 2,
+        name: "lobby".to_string(),
+        dimension_type: DimensionType {
+            height: 128,
+            min_y: 0,
+            has_skylight: true,
+            generator: "overworld".to_string(),
+        },
+    };
+    let overworld = Dimension {
+        id: 0,
+        name: "overworld".to_string(),
+        dimension_type: DimensionType::overworld(),
+    };
+    let packet = RegistryDataPacket::dimension_types(&[overworld, lobby]);
+    assert_eq!(packet.registry_id, "minecraft:dimension_type");
+    assert_eq!(packet.entries.length.0, 2);
+    assert_eq!(packet.entries.data[0].id, "minecraft:overworld");
+    assert_eq!(packet.entries.data[1].id, "ferrumc:lobby");
+    assert!(packet
+        .entries
+        .data
+        .iter()
+        .all(|entry| matches!(&entry.data, PrefixedOptional::Some(nbt) if nbt[0] == 10)));
+}
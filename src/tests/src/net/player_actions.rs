@@ -5,7 +5,7 @@ use ferrumc_core::chunks::block_break_progress::BlockBreakProgress;
 use ferrumc_core::inventory::{Inventory, ItemStack};
 use ferrumc_net::packets::packet_events::{PlayerDiggingEvent, UseItemEvent};
 use ferrumc_net::server::{handle_player_digging, handle_use_item};
-use ferrumc_world::block_id::BlockId;
+use ferrumc_world::item_id::ItemId;
 
 #[test]
 fn player_digging_progress_tracking() {
@@ -48,7 +48,7 @@ fn use_item_consumes_stack() {
 
     {
         let mut inv = world.get_mut::<Inventory>(entity).unwrap();
-        inv.hotbar[0] = Some(ItemStack::new(BlockId(1), 1, 64, None));
+        inv.hotbar[0] = Some(ItemStack::new(ItemId(1), 1, 64, None));
     }
 
     {